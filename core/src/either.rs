@@ -18,7 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::muxing::StreamMuxerEvent;
+use crate::muxing::{StreamMuxerEvent, StreamPriority};
 use crate::{
     muxing::StreamMuxer,
     transport::{ListenerId, Transport, TransportError, TransportEvent},
@@ -69,6 +69,23 @@ where
         }
     }
 
+    fn poll_outbound_with_priority(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        priority: StreamPriority,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        match self.as_pin_mut() {
+            future::Either::Left(inner) => inner
+                .poll_outbound_with_priority(cx, priority)
+                .map_ok(future::Either::Left)
+                .map_err(Either::Left),
+            future::Either::Right(inner) => inner
+                .poll_outbound_with_priority(cx, priority)
+                .map_ok(future::Either::Right)
+                .map_err(Either::Right),
+        }
+    }
+
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         match self.as_pin_mut() {
             future::Either::Left(inner) => inner.poll_close(cx).map_err(Either::Left),
@@ -88,6 +88,19 @@ pub trait StreamMuxer {
         cx: &mut Context<'_>,
     ) -> Poll<Result<Self::Substream, Self::Error>>;
 
+    /// Poll for a new, outbound substream with the given [`StreamPriority`].
+    ///
+    /// Muxers that do not support prioritizing substreams ignore the priority, which is what the
+    /// default implementation does.
+    fn poll_outbound_with_priority(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        priority: StreamPriority,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let _ = priority;
+        self.poll_outbound(cx)
+    }
+
     /// Poll to close this [`StreamMuxer`].
     ///
     /// After this has returned `Poll::Ready(Ok(()))`, the muxer has become useless and may be safely
@@ -111,6 +124,32 @@ pub trait StreamMuxer {
     ) -> Poll<Result<StreamMuxerEvent, Self::Error>>;
 }
 
+/// The priority of a substream relative to the other substreams of the same connection.
+///
+/// When substreams compete for sending data, those with a higher priority are served first.
+/// This allows e.g. latency-sensitive protocols to not be starved by bulk transfers.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamPriority(i32);
+
+impl StreamPriority {
+    /// The priority of substreams that give way to all others.
+    pub const LOW: Self = Self(-1);
+    /// The default priority of substreams.
+    pub const DEFAULT: Self = Self(0);
+    /// The priority of latency-sensitive substreams.
+    pub const HIGH: Self = Self(1);
+
+    /// Creates a priority from its numeric value, where higher values take precedence.
+    pub const fn new(value: i32) -> Self {
+        Self(value)
+    }
+
+    /// The numeric value of the priority.
+    pub const fn value(&self) -> i32 {
+        self.0
+    }
+}
+
 /// An event produced by a [`StreamMuxer`].
 #[derive(Debug)]
 pub enum StreamMuxerEvent {
@@ -142,6 +181,18 @@ pub trait StreamMuxerExt: StreamMuxer + Sized {
         Pin::new(self).poll_outbound(cx)
     }
 
+    /// Convenience function for calling [`StreamMuxer::poll_outbound_with_priority`] for [`StreamMuxer`]s that are `Unpin`.
+    fn poll_outbound_with_priority_unpin(
+        &mut self,
+        cx: &mut Context<'_>,
+        priority: StreamPriority,
+    ) -> Poll<Result<Self::Substream, Self::Error>>
+    where
+        Self: Unpin,
+    {
+        Pin::new(self).poll_outbound_with_priority(cx, priority)
+    }
+
     /// Convenience function for calling [`StreamMuxer::poll`] for [`StreamMuxer`]s that are `Unpin`.
     fn poll_unpin(&mut self, cx: &mut Context<'_>) -> Poll<Result<StreamMuxerEvent, Self::Error>>
     where
@@ -1,4 +1,4 @@
-use crate::muxing::{StreamMuxer, StreamMuxerEvent};
+use crate::muxing::{StreamMuxer, StreamMuxerEvent, StreamPriority};
 use futures::{AsyncRead, AsyncWrite};
 use pin_project::pin_project;
 use std::error::Error;
@@ -65,6 +65,18 @@ where
             .map_err(into_io_error)
     }
 
+    fn poll_outbound_with_priority(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        priority: StreamPriority,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        self.project()
+            .inner
+            .poll_outbound_with_priority(cx, priority)
+            .map_ok(SubstreamBox::new)
+            .map_err(into_io_error)
+    }
+
     #[inline]
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.project().inner.poll_close(cx).map_err(into_io_error)
@@ -125,6 +137,14 @@ impl StreamMuxer for StreamMuxerBox {
         self.project().poll_outbound(cx)
     }
 
+    fn poll_outbound_with_priority(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        priority: StreamPriority,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        self.project().poll_outbound_with_priority(cx, priority)
+    }
+
     #[inline]
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.project().poll_close(cx)
@@ -211,3 +231,68 @@ impl AsyncWrite for SubstreamBox {
         self.0.as_mut().poll_close(cx)
     }
 }
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncRead for SubstreamBox {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let n = futures::ready!(AsyncRead::poll_read(self, cx, buf.initialize_unfilled()))?;
+        buf.advance(n);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncWrite for SubstreamBox {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        AsyncWrite::poll_write_vectored(self, cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        AsyncWrite::poll_flush(self, cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        AsyncWrite::poll_close(self, cx)
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn substream_box_implements_tokio_io() {
+        futures::executor::block_on(async {
+            let mut stream = SubstreamBox::new(futures::io::Cursor::new(Vec::new()));
+            stream.write_all(b"hello").await.unwrap();
+            stream.flush().await.unwrap();
+
+            let mut stream = SubstreamBox::new(futures::io::Cursor::new(b"hello".to_vec()));
+            let mut buf = Vec::new();
+            stream.read_to_end(&mut buf).await.unwrap();
+            assert_eq!(buf, b"hello");
+        });
+    }
+}
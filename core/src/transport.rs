@@ -42,6 +42,7 @@ pub mod global_only;
 pub mod map;
 pub mod map_err;
 pub mod memory;
+pub mod throttle;
 pub mod timeout;
 pub mod upgrade;
 
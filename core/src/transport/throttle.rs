@@ -0,0 +1,564 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Transports limiting the bandwidth of the connections they establish.
+//!
+//! The [`Throttle`] transport wraps around a transport producing multiplexed connections and
+//! limits the number of bytes per second that are read from and written to the streams of each
+//! connection, as well as the number of bytes per second across all connections. The limits can
+//! be adjusted at runtime through a [`Handle`].
+//!
+//! Limits are enforced with token buckets that allow for bursts of up to one second worth of
+//! traffic. Only the payload of the streams counts towards the limits, the overhead of the
+//! underlying transport, security and multiplexing protocols does not.
+
+use crate::{
+    muxing::{StreamMuxer, StreamMuxerEvent, StreamPriority},
+    transport::{ListenerId, TransportError, TransportEvent},
+    Multiaddr,
+};
+use futures::{
+    future::{MapOk, TryFutureExt},
+    prelude::*,
+    ready,
+};
+use futures_timer::Delay;
+use instant::Instant;
+use libp2p_identity::PeerId;
+use parking_lot::Mutex;
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// Bandwidth limits applied by a [`Throttle`] transport.
+///
+/// All rates are in bytes per second. A rate of `0` disables the respective limit, which is the
+/// default for all of them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    connection_upload: u64,
+    connection_download: u64,
+    total_upload: u64,
+    total_download: u64,
+}
+
+impl Limits {
+    /// Creates a new set of limits, without limiting any bandwidth.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the bytes per second written to each individual connection.
+    pub fn connection_upload(mut self, bytes_per_second: u64) -> Self {
+        self.connection_upload = bytes_per_second;
+        self
+    }
+
+    /// Limits the bytes per second read from each individual connection.
+    pub fn connection_download(mut self, bytes_per_second: u64) -> Self {
+        self.connection_download = bytes_per_second;
+        self
+    }
+
+    /// Limits the bytes per second written to all connections combined.
+    pub fn total_upload(mut self, bytes_per_second: u64) -> Self {
+        self.total_upload = bytes_per_second;
+        self
+    }
+
+    /// Limits the bytes per second read from all connections combined.
+    pub fn total_download(mut self, bytes_per_second: u64) -> Self {
+        self.total_download = bytes_per_second;
+        self
+    }
+}
+
+/// Handle to adjust the limits of a [`Throttle`] transport at runtime.
+///
+/// Changes apply to all connections, including the ones already established.
+#[derive(Debug, Clone)]
+pub struct Handle {
+    shared: Arc<Shared>,
+}
+
+impl Handle {
+    /// Returns the limits currently in effect.
+    pub fn limits(&self) -> Limits {
+        self.shared.limits()
+    }
+
+    /// Replaces the limits currently in effect.
+    ///
+    /// Streams that are already waiting for bandwidth to become available only pick up the new
+    /// limits once their current wait has elapsed.
+    pub fn set_limits(&self, limits: Limits) {
+        self.shared.set_limits(limits)
+    }
+}
+
+/// A [`Transport`](crate::Transport) that wraps around another transport and limits the bandwidth
+/// of the connections established through it.
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug)]
+#[pin_project::pin_project]
+pub struct Throttle<T> {
+    #[pin]
+    transport: T,
+    shared: Arc<Shared>,
+}
+
+impl<T> Throttle<T> {
+    /// Wraps around a transport to limit the bandwidth of its connections.
+    pub fn new(transport: T, limits: Limits) -> Self {
+        let shared = Arc::new(Shared::default());
+        shared.set_limits(limits);
+        Throttle { transport, shared }
+    }
+
+    /// Returns a [`Handle`] to adjust the limits at runtime.
+    pub fn handle(&self) -> Handle {
+        Handle {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T, M> crate::Transport for Throttle<T>
+where
+    T: crate::Transport<Output = (PeerId, M)>,
+    M: StreamMuxer,
+{
+    type Output = (PeerId, ThrottledMuxer<M>);
+    type Error = T::Error;
+    type ListenerUpgrade =
+        MapOk<T::ListenerUpgrade, Box<dyn FnOnce((PeerId, M)) -> Self::Output + Send>>;
+    type Dial = MapOk<T::Dial, Box<dyn FnOnce((PeerId, M)) -> Self::Output + Send>>;
+
+    fn listen_on(
+        &mut self,
+        id: ListenerId,
+        addr: Multiaddr,
+    ) -> Result<(), TransportError<Self::Error>> {
+        self.transport.listen_on(id, addr)
+    }
+
+    fn remove_listener(&mut self, id: ListenerId) -> bool {
+        self.transport.remove_listener(id)
+    }
+
+    fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let shared = self.shared.clone();
+        Ok(self
+            .transport
+            .dial(addr)?
+            .map_ok(Box::new(|(peer_id, muxer)| {
+                (peer_id, ThrottledMuxer::new(muxer, shared))
+            })))
+    }
+
+    fn dial_as_listener(
+        &mut self,
+        addr: Multiaddr,
+    ) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let shared = self.shared.clone();
+        Ok(self
+            .transport
+            .dial_as_listener(addr)?
+            .map_ok(Box::new(|(peer_id, muxer)| {
+                (peer_id, ThrottledMuxer::new(muxer, shared))
+            })))
+    }
+
+    fn address_translation(&self, server: &Multiaddr, observed: &Multiaddr) -> Option<Multiaddr> {
+        self.transport.address_translation(server, observed)
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+        let this = self.project();
+        let shared = this.shared;
+        this.transport.poll(cx).map(|event| {
+            event.map_upgrade(|upgrade| {
+                let shared = shared.clone();
+                upgrade.map_ok(Box::new(|(peer_id, muxer)| {
+                    (peer_id, ThrottledMuxer::new(muxer, shared))
+                }) as Box<_>)
+            })
+        })
+    }
+}
+
+/// Wraps around a [`StreamMuxer`] and limits the bandwidth of all the streams opened through it.
+#[pin_project::pin_project]
+pub struct ThrottledMuxer<M> {
+    #[pin]
+    inner: M,
+    shared: Arc<Shared>,
+    connection: Arc<Buckets>,
+}
+
+impl<M> ThrottledMuxer<M> {
+    fn new(inner: M, shared: Arc<Shared>) -> Self {
+        Self {
+            inner,
+            shared,
+            connection: Arc::default(),
+        }
+    }
+
+    fn throttle<S>(&self, inner: S) -> ThrottledStream<S> {
+        ThrottledStream {
+            inner,
+            shared: self.shared.clone(),
+            connection: self.connection.clone(),
+            read_delay: None,
+            write_delay: None,
+        }
+    }
+}
+
+impl<M> StreamMuxer for ThrottledMuxer<M>
+where
+    M: StreamMuxer,
+{
+    type Substream = ThrottledStream<M::Substream>;
+    type Error = M::Error;
+
+    fn poll_inbound(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let inner = ready!(self.as_mut().project().inner.poll_inbound(cx)?);
+        Poll::Ready(Ok(self.throttle(inner)))
+    }
+
+    fn poll_outbound(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let inner = ready!(self.as_mut().project().inner.poll_outbound(cx)?);
+        Poll::Ready(Ok(self.throttle(inner)))
+    }
+
+    fn poll_outbound_with_priority(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        priority: StreamPriority,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let inner = ready!(self
+            .as_mut()
+            .project()
+            .inner
+            .poll_outbound_with_priority(cx, priority)?);
+        Poll::Ready(Ok(self.throttle(inner)))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().inner.poll_close(cx)
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+        self.project().inner.poll(cx)
+    }
+}
+
+/// Wraps around an [`AsyncRead`] + [`AsyncWrite`] and limits the bandwidth that goes through it.
+#[pin_project::pin_project]
+pub struct ThrottledStream<S> {
+    #[pin]
+    inner: S,
+    shared: Arc<Shared>,
+    connection: Arc<Buckets>,
+    read_delay: Option<Delay>,
+    write_delay: Option<Delay>,
+}
+
+impl<S: AsyncRead> AsyncRead for ThrottledStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        if buf.is_empty() {
+            return this.inner.poll_read(cx, buf);
+        }
+        let allowed = ready!(poll_acquire(
+            this.shared,
+            this.connection,
+            Direction::Download,
+            this.read_delay,
+            cx
+        ));
+        let len = buf.len().min(allowed);
+        let num_bytes = ready!(this.inner.poll_read(cx, &mut buf[..len]))?;
+        consume(this.shared, this.connection, Direction::Download, num_bytes);
+        Poll::Ready(Ok(num_bytes))
+    }
+}
+
+impl<S: AsyncWrite> AsyncWrite for ThrottledStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        if buf.is_empty() {
+            return this.inner.poll_write(cx, buf);
+        }
+        let allowed = ready!(poll_acquire(
+            this.shared,
+            this.connection,
+            Direction::Upload,
+            this.write_delay,
+            cx
+        ));
+        let len = buf.len().min(allowed);
+        let num_bytes = ready!(this.inner.poll_write(cx, &buf[..len]))?;
+        consume(this.shared, this.connection, Direction::Upload, num_bytes);
+        Poll::Ready(Ok(num_bytes))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_close(cx)
+    }
+}
+
+/// Waits until both the connection and the global bucket of the given direction have bandwidth
+/// available and returns the number of bytes that may be transferred.
+fn poll_acquire(
+    shared: &Shared,
+    connection: &Buckets,
+    direction: Direction,
+    delay: &mut Option<Delay>,
+    cx: &mut Context<'_>,
+) -> Poll<usize> {
+    loop {
+        if let Some(d) = delay.as_mut() {
+            ready!(d.poll_unpin(cx));
+            *delay = None;
+        }
+
+        let (connection_rate, total_rate) = shared.rates(direction);
+        let connection = connection.get(direction).lock().available(connection_rate);
+        let total = shared.total.get(direction).lock().available(total_rate);
+
+        match (connection, total) {
+            (Ok(a), Ok(b)) => return Poll::Ready(a.min(b)),
+            (Err(wait), Ok(_)) | (Ok(_), Err(wait)) => *delay = Some(Delay::new(wait)),
+            (Err(a), Err(b)) => *delay = Some(Delay::new(a.max(b))),
+        }
+    }
+}
+
+fn consume(shared: &Shared, connection: &Buckets, direction: Direction, num_bytes: usize) {
+    connection.get(direction).lock().consume(num_bytes);
+    shared.total.get(direction).lock().consume(num_bytes);
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Upload,
+    Download,
+}
+
+/// State shared between a [`Throttle`] transport, its [`Handle`]s and all streams.
+#[derive(Debug, Default)]
+struct Shared {
+    connection_upload: AtomicU64,
+    connection_download: AtomicU64,
+    total_upload: AtomicU64,
+    total_download: AtomicU64,
+    total: Buckets,
+}
+
+impl Shared {
+    fn limits(&self) -> Limits {
+        Limits {
+            connection_upload: self.connection_upload.load(Ordering::Relaxed),
+            connection_download: self.connection_download.load(Ordering::Relaxed),
+            total_upload: self.total_upload.load(Ordering::Relaxed),
+            total_download: self.total_download.load(Ordering::Relaxed),
+        }
+    }
+
+    fn set_limits(&self, limits: Limits) {
+        self.connection_upload
+            .store(limits.connection_upload, Ordering::Relaxed);
+        self.connection_download
+            .store(limits.connection_download, Ordering::Relaxed);
+        self.total_upload
+            .store(limits.total_upload, Ordering::Relaxed);
+        self.total_download
+            .store(limits.total_download, Ordering::Relaxed);
+    }
+
+    /// Returns the per-connection and the total rate of the given direction.
+    fn rates(&self, direction: Direction) -> (u64, u64) {
+        match direction {
+            Direction::Upload => (
+                self.connection_upload.load(Ordering::Relaxed),
+                self.total_upload.load(Ordering::Relaxed),
+            ),
+            Direction::Download => (
+                self.connection_download.load(Ordering::Relaxed),
+                self.total_download.load(Ordering::Relaxed),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    upload: Mutex<Bucket>,
+    download: Mutex<Bucket>,
+}
+
+impl Buckets {
+    fn get(&self, direction: Direction) -> &Mutex<Bucket> {
+        match direction {
+            Direction::Upload => &self.upload,
+            Direction::Download => &self.download,
+        }
+    }
+}
+
+/// A token bucket holding up to one second worth of bytes.
+///
+/// The rate is passed in on every access so that changes through a [`Handle`] take effect
+/// immediately.
+#[derive(Debug)]
+struct Bucket {
+    /// Bytes that may be transferred right away. Negative if more bytes have been transferred
+    /// than were available, e.g. by concurrent streams sharing the bucket.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Default for Bucket {
+    fn default() -> Self {
+        Self {
+            tokens: f64::INFINITY,
+            last_refill: Instant::now(),
+        }
+    }
+}
+
+impl Bucket {
+    /// Refills the bucket and returns the number of bytes available, or the time to wait until
+    /// bytes become available.
+    fn available(&mut self, rate: u64) -> Result<usize, Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+
+        if rate == 0 {
+            // Start with a full bucket should a limit be set later.
+            self.tokens = f64::INFINITY;
+            return Ok(usize::MAX);
+        }
+
+        let rate = rate as f64;
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+
+        if self.tokens >= 1.0 {
+            Ok(self.tokens as usize)
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+
+    fn consume(&mut self, num_bytes: usize) {
+        self.tokens -= num_bytes as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(limits: Limits) -> (ThrottledStream<Vec<u8>>, Handle) {
+        let muxer = ThrottledMuxer::new((), Arc::new(Shared::default()));
+        muxer.shared.set_limits(limits);
+        let handle = Handle {
+            shared: muxer.shared.clone(),
+        };
+        (muxer.throttle(Vec::new()), handle)
+    }
+
+    #[async_std::test]
+    async fn upload_is_limited_per_connection() {
+        let (mut stream, _) = stream(Limits::new().connection_upload(10_000));
+
+        let start = Instant::now();
+        stream.write_all(&[0; 20_000]).await.unwrap();
+
+        // The first 10_000 bytes are sent in a burst, the remaining ones take a second.
+        assert!(start.elapsed() >= Duration::from_millis(900));
+        assert_eq!(stream.inner.len(), 20_000);
+    }
+
+    #[async_std::test]
+    async fn download_is_limited_in_total() {
+        let muxer = ThrottledMuxer::new((), Arc::new(Shared::default()));
+        muxer
+            .shared
+            .set_limits(Limits::new().total_download(10_000));
+        let mut a = muxer.throttle(futures::io::Cursor::new(vec![0; 10_000]));
+        let mut b = muxer.throttle(futures::io::Cursor::new(vec![0; 10_000]));
+
+        let start = Instant::now();
+        a.read_to_end(&mut Vec::new()).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500));
+        b.read_to_end(&mut Vec::new()).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(900));
+    }
+
+    #[async_std::test]
+    async fn limits_can_be_lifted() {
+        let (mut stream, handle) = stream(Limits::new().connection_upload(1_000));
+        stream.write_all(&[0; 1_000]).await.unwrap();
+
+        handle.set_limits(Limits::new());
+        assert_eq!(handle.limits(), Limits::new());
+
+        let start = Instant::now();
+        stream.write_all(&[0; 100_000]).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+}
@@ -31,16 +31,18 @@ use crate::{
     },
     upgrade::{
         self, apply_inbound, apply_outbound, InboundConnectionUpgrade, InboundUpgradeApply,
-        OutboundConnectionUpgrade, OutboundUpgradeApply, UpgradeError,
+        NegotiationError, OutboundConnectionUpgrade, OutboundUpgradeApply, ProtocolError,
+        UpgradeError,
     },
     Negotiated,
 };
 use futures::{prelude::*, ready};
+use futures_timer::Delay;
 use libp2p_identity::PeerId;
 use multiaddr::Multiaddr;
 use std::{
     error::Error,
-    fmt,
+    fmt, io,
     pin::Pin,
     task::{Context, Poll},
     time::Duration,
@@ -65,10 +67,15 @@ use std::{
 ///   4. The [`Transport::Output`] conforms to the requirements of a `Swarm`,
 ///      namely a tuple of a [`PeerId`] (from the authentication upgrade) and a
 ///      [`StreamMuxer`] (from the multiplexing upgrade).
+///
+/// The [security](Builder::security_timeout) and [multiplexing](Builder::multiplex_timeout)
+/// stages can each be subject to a timeout.
 #[derive(Clone)]
 pub struct Builder<T> {
     inner: T,
     version: upgrade::Version,
+    security_timeout: Option<Duration>,
+    multiplex_timeout: Option<Duration>,
 }
 
 impl<T> Builder<T>
@@ -78,7 +85,32 @@ where
 {
     /// Creates a `Builder` over the given (base) `Transport`.
     pub fn new(inner: T, version: upgrade::Version) -> Builder<T> {
-        Builder { inner, version }
+        Builder {
+            inner,
+            version,
+            security_timeout: None,
+            multiplex_timeout: None,
+        }
+    }
+
+    /// Sets a timeout for the [authentication](Builder::authenticate) of a connection,
+    /// i.e. the negotiation of the security protocol and its handshake.
+    ///
+    /// The timeout starts once the connection is established. If it is reached, the
+    /// upgrade fails with an [`UpgradeTimeout`] of [`UpgradeStage::Security`].
+    pub fn security_timeout(mut self, timeout: Duration) -> Self {
+        self.security_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets a timeout for the [negotiation of a multiplexer](Authenticated::multiplex) on a
+    /// connection.
+    ///
+    /// The timeout starts once all preceding upgrades completed. If it is reached, the
+    /// upgrade fails with an [`UpgradeTimeout`] of [`UpgradeStage::Multiplex`].
+    pub fn multiplex_timeout(mut self, timeout: Duration) -> Self {
+        self.multiplex_timeout = Some(timeout);
+        self
     }
 
     /// Upgrades the transport to perform authentication of the remote.
@@ -106,12 +138,52 @@ where
         E: Error + 'static,
     {
         let version = self.version;
-        Authenticated(Builder::new(
-            self.inner.and_then(move |conn, endpoint| Authenticate {
+        let timeout = self.security_timeout;
+        Authenticated(Builder {
+            inner: self.inner.and_then(move |conn, endpoint| Authenticate {
                 inner: upgrade::apply(conn, upgrade, endpoint, version),
+                timeout: timeout.map(StageTimer::new),
             }),
             version,
-        ))
+            security_timeout: self.security_timeout,
+            multiplex_timeout: self.multiplex_timeout,
+        })
+    }
+
+    /// Like [`Builder::authenticate`] but accepts a function which returns the upgrade.
+    ///
+    /// The supplied function is applied to the [`ConnectedPoint`] of every connection,
+    /// allowing to select or order the security protocols per remote, e.g. by the
+    /// address that is dialed.
+    ///
+    /// ## Transitions
+    ///
+    ///   * I/O upgrade: `C -> (PeerId, D)`.
+    ///   * Transport output: `C -> (PeerId, D)`
+    pub fn authenticate_ext<C, D, U, E, F>(
+        self,
+        up: F,
+    ) -> Authenticated<AndThen<T, impl FnOnce(C, ConnectedPoint) -> Authenticate<C, U> + Clone>>
+    where
+        T: Transport<Output = C>,
+        C: AsyncRead + AsyncWrite + Unpin,
+        D: AsyncRead + AsyncWrite + Unpin,
+        U: InboundConnectionUpgrade<Negotiated<C>, Output = (PeerId, D), Error = E>,
+        U: OutboundConnectionUpgrade<Negotiated<C>, Output = (PeerId, D), Error = E> + Clone,
+        E: Error + 'static,
+        F: for<'a> FnOnce(&'a ConnectedPoint) -> U + Clone,
+    {
+        let version = self.version;
+        let timeout = self.security_timeout;
+        Authenticated(Builder {
+            inner: self.inner.and_then(move |conn, endpoint| Authenticate {
+                inner: upgrade::apply(conn, up(&endpoint), endpoint, version),
+                timeout: timeout.map(StageTimer::new),
+            }),
+            version,
+            security_timeout: self.security_timeout,
+            multiplex_timeout: self.multiplex_timeout,
+        })
     }
 }
 
@@ -127,6 +199,7 @@ where
 {
     #[pin]
     inner: EitherUpgrade<C, U>,
+    timeout: Option<StageTimer>,
 }
 
 impl<C, U> Future for Authenticate<C, U>
@@ -143,7 +216,13 @@ where
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
-        Future::poll(this.inner, cx)
+        if let Poll::Ready(output) = Future::poll(this.inner, cx) {
+            return Poll::Ready(output);
+        }
+        match this.timeout {
+            Some(timeout) => timeout.poll(cx, UpgradeStage::Security).map(Err),
+            None => Poll::Pending,
+        }
     }
 }
 
@@ -160,6 +239,7 @@ where
     peer_id: Option<PeerId>,
     #[pin]
     upgrade: EitherUpgrade<C, U>,
+    timeout: Option<StageTimer>,
 }
 
 impl<C, U, M, E> Future for Multiplex<C, U>
@@ -172,9 +252,15 @@ where
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
-        let m = match ready!(Future::poll(this.upgrade, cx)) {
-            Ok(m) => m,
-            Err(err) => return Poll::Ready(Err(err)),
+        let m = match Future::poll(this.upgrade, cx) {
+            Poll::Ready(Ok(m)) => m,
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Pending => {
+                return match this.timeout {
+                    Some(timeout) => timeout.poll(cx, UpgradeStage::Multiplex).map(Err),
+                    None => Poll::Pending,
+                }
+            }
         };
         let i = this
             .peer_id
@@ -212,10 +298,12 @@ where
         U: OutboundConnectionUpgrade<Negotiated<C>, Output = D, Error = E> + Clone,
         E: Error + 'static,
     {
-        Authenticated(Builder::new(
-            Upgrade::new(self.0.inner, upgrade),
-            self.0.version,
-        ))
+        Authenticated(Builder {
+            inner: Upgrade::new(self.0.inner, upgrade),
+            version: self.0.version,
+            security_timeout: self.0.security_timeout,
+            multiplex_timeout: self.0.multiplex_timeout,
+        })
     }
 
     /// Upgrades the transport with a (sub)stream multiplexer.
@@ -241,11 +329,13 @@ where
         E: Error + 'static,
     {
         let version = self.0.version;
+        let timeout = self.0.multiplex_timeout;
         Multiplexed(self.0.inner.and_then(move |(i, c), endpoint| {
             let upgrade = upgrade::apply(c, upgrade, endpoint, version);
             Multiplex {
                 peer_id: Some(i),
                 upgrade,
+                timeout: timeout.map(StageTimer::new),
             }
         }))
     }
@@ -275,11 +365,13 @@ where
         F: for<'a> FnOnce(&'a PeerId, &'a ConnectedPoint) -> U + Clone,
     {
         let version = self.0.version;
+        let timeout = self.0.multiplex_timeout;
         Multiplexed(self.0.inner.and_then(move |(peer_id, c), endpoint| {
             let upgrade = upgrade::apply(c, up(&peer_id, &endpoint), endpoint, version);
             Multiplex {
                 peer_id: Some(peer_id),
                 upgrade,
+                timeout: timeout.map(StageTimer::new),
             }
         }))
     }
@@ -499,6 +591,105 @@ where
     }
 }
 
+/// A stage of the upgrade process configured through a [`Builder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UpgradeStage {
+    /// The [authentication](Builder::authenticate) of the remote.
+    Security,
+    /// The [negotiation of a multiplexer](Authenticated::multiplex).
+    Multiplex,
+}
+
+impl fmt::Display for UpgradeStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpgradeStage::Security => write!(f, "security"),
+            UpgradeStage::Multiplex => write!(f, "multiplex"),
+        }
+    }
+}
+
+/// A stage of the upgrade process did not complete within its configured timeout.
+///
+/// See [`Builder::security_timeout`] and [`Builder::multiplex_timeout`].
+///
+/// The timeout is reported as an [`io::Error`] of kind [`io::ErrorKind::TimedOut`] wrapping this
+/// error, in place of an error of the protocol negotiation. Use [`UpgradeTimeout::find`] to
+/// retrieve it from an error returned by the transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpgradeTimeout {
+    stage: UpgradeStage,
+    timeout: Duration,
+}
+
+impl UpgradeTimeout {
+    /// The stage that timed out.
+    pub fn stage(&self) -> UpgradeStage {
+        self.stage
+    }
+
+    /// The configured timeout of the stage.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Searches the given error and its sources for an [`UpgradeTimeout`].
+    pub fn find<'a>(mut error: &'a (dyn Error + 'static)) -> Option<&'a UpgradeTimeout> {
+        loop {
+            if let Some(timeout) = error.downcast_ref::<UpgradeTimeout>() {
+                return Some(timeout);
+            }
+            // The source of an `io::Error` is the source of its inner error, thus check the
+            // inner error itself as well.
+            if let Some(inner) = error.downcast_ref::<io::Error>().and_then(|e| e.get_ref()) {
+                if let Some(timeout) = UpgradeTimeout::find(inner) {
+                    return Some(timeout);
+                }
+            }
+            error = error.source()?;
+        }
+    }
+}
+
+impl fmt::Display for UpgradeTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The {} upgrade did not complete within {:?}",
+            self.stage, self.timeout
+        )
+    }
+}
+
+impl Error for UpgradeTimeout {}
+
+/// The timer of a stage of the upgrade process.
+struct StageTimer {
+    delay: Delay,
+    timeout: Duration,
+}
+
+impl StageTimer {
+    fn new(timeout: Duration) -> Self {
+        Self {
+            delay: Delay::new(timeout),
+            timeout,
+        }
+    }
+
+    fn poll<E>(&mut self, cx: &mut Context<'_>, stage: UpgradeStage) -> Poll<UpgradeError<E>> {
+        ready!(self.delay.poll_unpin(cx));
+
+        let timeout = UpgradeTimeout {
+            stage,
+            timeout: self.timeout,
+        };
+        Poll::Ready(UpgradeError::Select(NegotiationError::ProtocolError(
+            ProtocolError::IoError(io::Error::new(io::ErrorKind::TimedOut, timeout)),
+        )))
+    }
+}
+
 /// The [`Transport::Dial`] future of an [`Upgrade`]d transport.
 pub struct DialUpgradeFuture<F, U, C>
 where
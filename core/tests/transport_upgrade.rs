@@ -19,6 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use futures::prelude::*;
+use libp2p_core::transport::upgrade::{UpgradeStage, UpgradeTimeout};
 use libp2p_core::transport::{ListenerId, MemoryTransport, Transport};
 use libp2p_core::upgrade::{
     self, InboundConnectionUpgrade, OutboundConnectionUpgrade, UpgradeInfo,
@@ -28,7 +29,7 @@ use libp2p_mplex::MplexConfig;
 use libp2p_noise as noise;
 use multiaddr::{Multiaddr, Protocol};
 use rand::random;
-use std::{io, pin::Pin};
+use std::{io, pin::Pin, time::Duration};
 
 #[derive(Clone)]
 struct HelloUpgrade {}
@@ -128,3 +129,104 @@ fn upgrade_pipeline() {
     async_std::task::spawn(server);
     async_std::task::block_on(client);
 }
+
+#[test]
+fn security_timeout() {
+    let listener_keys = identity::Keypair::generate_ed25519();
+    let mut listener_transport = MemoryTransport::default()
+        .upgrade(upgrade::Version::V1)
+        .authenticate(noise::Config::new(&listener_keys).unwrap())
+        .multiplex(MplexConfig::default())
+        .boxed();
+
+    let dialer_keys = identity::Keypair::generate_ed25519();
+    let mut dialer_transport = MemoryTransport::default()
+        .upgrade(upgrade::Version::V1)
+        .security_timeout(Duration::from_millis(100))
+        .authenticate(noise::Config::new(&dialer_keys).unwrap())
+        .multiplex(MplexConfig::default())
+        .boxed();
+
+    let listen_addr = Multiaddr::from(Protocol::Memory(random::<u64>()));
+    listener_transport
+        .listen_on(ListenerId::next(), listen_addr.clone())
+        .unwrap();
+
+    // Accept the connection, but never upgrade it.
+    let server = async move {
+        let mut upgrades = Vec::new();
+        loop {
+            if let Some((upgrade, _)) = listener_transport.select_next_some().await.into_incoming()
+            {
+                upgrades.push(upgrade);
+            }
+        }
+    };
+
+    let client = async move {
+        let error = dialer_transport
+            .dial(listen_addr)
+            .unwrap()
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        let timeout = UpgradeTimeout::find(&error).unwrap();
+        assert_eq!(timeout.stage(), UpgradeStage::Security);
+        assert_eq!(timeout.timeout(), Duration::from_millis(100));
+    };
+
+    async_std::task::spawn(server);
+    async_std::task::block_on(client);
+}
+
+#[test]
+fn multiplex_timeout() {
+    let listener_keys = identity::Keypair::generate_ed25519();
+    let mut listener_transport = MemoryTransport::default().boxed();
+
+    let dialer_keys = identity::Keypair::generate_ed25519();
+    let mut dialer_transport = MemoryTransport::default()
+        .upgrade(upgrade::Version::V1)
+        .multiplex_timeout(Duration::from_millis(100))
+        .authenticate(noise::Config::new(&dialer_keys).unwrap())
+        .multiplex(MplexConfig::default())
+        .boxed();
+
+    let listen_addr = Multiaddr::from(Protocol::Memory(random::<u64>()));
+    listener_transport
+        .listen_on(ListenerId::next(), listen_addr.clone())
+        .unwrap();
+
+    // Authenticate the connection, but never negotiate a multiplexer.
+    let server = async move {
+        let mut connections = Vec::new();
+        loop {
+            let Some((upgrade, _)) = listener_transport.select_next_some().await.into_incoming()
+            else {
+                continue;
+            };
+            let connection = upgrade.await.unwrap();
+            let noise = noise::Config::new(&listener_keys).unwrap();
+            let (protocol, connection) =
+                multistream_select::listener_select_proto(connection, noise.protocol_info())
+                    .await
+                    .unwrap();
+            connections.push(noise.upgrade_inbound(connection, protocol).await.unwrap());
+        }
+    };
+
+    let client = async move {
+        let error = dialer_transport
+            .dial(listen_addr)
+            .unwrap()
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        let timeout = UpgradeTimeout::find(&error).unwrap();
+        assert_eq!(timeout.stage(), UpgradeStage::Multiplex);
+        assert_eq!(timeout.timeout(), Duration::from_millis(100));
+    };
+
+    async_std::task::spawn(server);
+    async_std::task::block_on(client);
+}
@@ -86,7 +86,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 println!("Listening in {address:?}");
             },
             SwarmEvent::Behaviour(BehaviourEvent::Mdns(mdns::Event::Discovered(list))) => {
-                for (peer_id, multiaddr) in list {
+                for (peer_id, multiaddr, _metadata) in list {
                     swarm.behaviour_mut().kademlia.add_address(&peer_id, multiaddr);
                 }
             }
@@ -198,6 +198,7 @@ fn handle_input_line(kademlia: &mut kad::Behaviour<MemoryStore>, line: String) {
                 value,
                 publisher: None,
                 expires: None,
+                ttl: None,
             };
             kademlia
                 .put_record(record, kad::Quorum::One)
@@ -59,16 +59,20 @@ async fn main() -> Result<(), Box<dyn Error>> {
             SwarmEvent::Behaviour(upnp::Event::NewExternalAddr(addr)) => {
                 println!("New external address: {addr}");
             }
-            SwarmEvent::Behaviour(upnp::Event::GatewayNotFound) => {
-                println!("Gateway does not support UPnP");
-                break;
+            SwarmEvent::Behaviour(upnp::Event::GatewayFound {
+                interface,
+                gateway,
+                protocol,
+            }) => {
+                println!("Found gateway {gateway} speaking {protocol:?} for interface {interface}");
             }
-            SwarmEvent::Behaviour(upnp::Event::NonRoutableGateway) => {
-                println!("Gateway is not exposed directly to the public Internet, i.e. it itself has a private IP address.");
-                break;
+            SwarmEvent::Behaviour(upnp::Event::GatewayNotFound { interface }) => {
+                println!("Gateway of interface {interface} does not support UPnP, PCP or NAT-PMP");
+            }
+            SwarmEvent::Behaviour(upnp::Event::NonRoutableGateway { interface }) => {
+                println!("Gateway of interface {interface} is not exposed directly to the public Internet, i.e. it itself has a private IP address.");
             }
             _ => {}
         }
     }
-    Ok(())
 }
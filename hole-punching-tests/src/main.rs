@@ -114,6 +114,7 @@ async fn main() -> Result<()> {
                 SwarmEvent::Behaviour(BehaviourEvent::Dcutr(dcutr::Event {
                     remote_peer_id,
                     result: Ok(connection_id),
+                    ..
                 })),
                 _,
                 _,
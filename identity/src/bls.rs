@@ -0,0 +1,267 @@
+// Copyright 2023 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! BLS12-381 keys.
+//!
+//! Public keys are points on G1 (48 bytes compressed) and signatures are points on G2
+//! (96 bytes compressed), using the proof-of-possession ciphersuite of the
+//! [BLS signature draft](https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-bls-signature-05),
+//! i.e. the same scheme as Ethereum consensus clients.
+
+use super::error::DecodingError;
+use blst::{min_pk, BLST_ERROR};
+use core::cmp;
+use core::fmt;
+use core::hash;
+use zeroize::Zeroize;
+
+/// The domain separation tag of the proof-of-possession ciphersuite.
+const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// A BLS12-381 keypair.
+#[derive(Clone)]
+pub struct Keypair {
+    secret: SecretKey,
+    public: PublicKey,
+}
+
+impl Keypair {
+    /// Generate a new random BLS12-381 keypair.
+    #[cfg(feature = "rand")]
+    pub fn generate() -> Keypair {
+        Keypair::from(SecretKey::generate())
+    }
+
+    /// Sign a message using the private key of this keypair.
+    pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
+        self.secret.0.sign(msg, DST, &[]).compress().to_vec()
+    }
+
+    /// Get the public key of this keypair.
+    pub fn public(&self) -> &PublicKey {
+        &self.public
+    }
+
+    /// Get the secret key of this keypair.
+    pub fn secret(&self) -> &SecretKey {
+        &self.secret
+    }
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keypair")
+            .field("public", &self.public)
+            .finish()
+    }
+}
+
+/// Promote a BLS12-381 secret key into a keypair.
+impl From<SecretKey> for Keypair {
+    fn from(secret: SecretKey) -> Keypair {
+        let public = PublicKey(secret.0.sk_to_pk());
+        Keypair { secret, public }
+    }
+}
+
+/// Demote a BLS12-381 keypair into a secret key.
+impl From<Keypair> for SecretKey {
+    fn from(kp: Keypair) -> SecretKey {
+        kp.secret
+    }
+}
+
+/// A BLS12-381 secret key.
+///
+/// The key material is zeroed when the secret key is dropped.
+#[derive(Clone)]
+pub struct SecretKey(min_pk::SecretKey);
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretKey")
+    }
+}
+
+impl SecretKey {
+    /// Generate a new random BLS12-381 secret key.
+    #[cfg(feature = "rand")]
+    pub fn generate() -> SecretKey {
+        use rand::RngCore as _;
+
+        let mut ikm = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut ikm);
+        let secret =
+            min_pk::SecretKey::key_gen(&ikm, &[]).expect("input key material is 32 bytes long");
+        ikm.zeroize();
+
+        SecretKey(secret)
+    }
+
+    /// Try to parse a BLS12-381 secret key from a byte slice containing the
+    /// big-endian encoding of the scalar, zeroing the input on success.
+    pub fn try_from_bytes(mut sk: impl AsMut<[u8]>) -> Result<SecretKey, DecodingError> {
+        let sk_bytes = sk.as_mut();
+        let secret = min_pk::SecretKey::from_bytes(sk_bytes)
+            .map_err(|e| DecodingError::failed_to_parse("BLS12-381 secret key", Error(e)))?;
+        sk_bytes.zeroize();
+        Ok(SecretKey(secret))
+    }
+
+    /// Returns the big-endian encoding of the scalar of this secret key.
+    ///
+    /// Note that the returned bytes are not zeroed on drop.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+}
+
+/// A BLS12-381 public key.
+#[derive(Clone)]
+pub struct PublicKey(min_pk::PublicKey);
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PublicKey(compressed): ")?;
+        for byte in self.to_bytes() {
+            write!(f, "{byte:x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl cmp::PartialEq for PublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_bytes().eq(&other.to_bytes())
+    }
+}
+
+impl cmp::Eq for PublicKey {}
+
+impl hash::Hash for PublicKey {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state);
+    }
+}
+
+impl cmp::PartialOrd for PublicKey {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl cmp::Ord for PublicKey {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.to_bytes().cmp(&other.to_bytes())
+    }
+}
+
+impl PublicKey {
+    /// Verify the BLS12-381 signature on a message using the public key.
+    pub fn verify(&self, msg: &[u8], sig: &[u8]) -> bool {
+        let Ok(sig) = min_pk::Signature::sig_validate(sig, true) else {
+            return false;
+        };
+
+        sig.verify(false, msg, DST, &[], &self.0, false) == BLST_ERROR::BLST_SUCCESS
+    }
+
+    /// Convert the public key to a byte array in compressed form.
+    pub fn to_bytes(&self) -> [u8; 48] {
+        self.0.compress()
+    }
+
+    /// Try to parse a public key from a byte array in compressed or uncompressed form.
+    ///
+    /// The point is checked to be in the G1 subgroup and not to be the point at infinity.
+    pub fn try_from_bytes(k: &[u8]) -> Result<PublicKey, DecodingError> {
+        min_pk::PublicKey::key_validate(k)
+            .map_err(|e| DecodingError::failed_to_parse("BLS12-381 public key", Error(e)))
+            .map(PublicKey)
+    }
+}
+
+/// An error reported by `blst`.
+#[derive(Debug)]
+struct Error(BLST_ERROR);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::*;
+
+    fn eq_keypairs(kp1: &Keypair, kp2: &Keypair) -> bool {
+        kp1.public() == kp2.public() && kp1.secret().to_bytes() == kp2.secret().to_bytes()
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn bls_keypair_from_secret() {
+        fn prop() -> bool {
+            let kp1 = Keypair::generate();
+            let mut sk = kp1.secret().to_bytes();
+            let kp2 = Keypair::from(SecretKey::try_from_bytes(&mut sk).unwrap());
+            eq_keypairs(&kp1, &kp2) && sk == [0u8; 32]
+        }
+        QuickCheck::new().tests(10).quickcheck(prop as fn() -> _);
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn bls_public_key_encode_decode() {
+        let kp = Keypair::generate();
+        let pk = PublicKey::try_from_bytes(&kp.public().to_bytes()).unwrap();
+        assert_eq!(&pk, kp.public());
+
+        let mut infinity = [0u8; 48];
+        infinity[0] = 0xc0;
+        assert!(PublicKey::try_from_bytes(&infinity).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn bls_signature() {
+        let kp = Keypair::generate();
+        let pk = kp.public();
+
+        let msg = "hello world".as_bytes();
+        let sig = kp.sign(msg);
+        assert_eq!(sig.len(), 96);
+        assert!(pk.verify(msg, &sig));
+
+        let mut invalid_sig = sig.clone();
+        invalid_sig[3..6].copy_from_slice(&[10, 23, 42]);
+        assert!(!pk.verify(msg, &invalid_sig));
+
+        let invalid_msg = "h3ll0 w0rld".as_bytes();
+        assert!(!pk.verify(invalid_msg, &sig));
+
+        let other = Keypair::generate();
+        assert!(!other.public().verify(msg, &sig));
+    }
+}
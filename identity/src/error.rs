@@ -45,7 +45,8 @@ impl DecodingError {
         feature = "ecdsa",
         feature = "secp256k1",
         feature = "ed25519",
-        feature = "rsa"
+        feature = "rsa",
+        feature = "bls"
     ))]
     pub(crate) fn failed_to_parse<E, S>(what: &'static str, source: S) -> Self
     where
@@ -107,14 +108,22 @@ pub struct SigningError {
 
 /// An error during encoding of key material.
 impl SigningError {
-    #[cfg(all(feature = "rsa", not(target_arch = "wasm32")))]
-    pub(crate) fn new<S: ToString>(msg: S) -> Self {
+    /// Creates a new error, e.g. for a failure of a [`Signer`](crate::Signer).
+    pub fn new<S: ToString>(msg: S) -> Self {
         Self {
             msg: msg.to_string(),
             source: None,
         }
     }
 
+    /// Sets the underlying cause of the error.
+    pub fn with_source(self, source: impl Error + Send + Sync + 'static) -> Self {
+        Self {
+            source: Some(Box::new(source)),
+            ..self
+        }
+    }
+
     #[cfg(all(feature = "rsa", not(target_arch = "wasm32")))]
     pub(crate) fn source(self, source: impl Error + Send + Sync + 'static) -> Self {
         Self {
@@ -46,6 +46,9 @@ mod proto {
     pub(crate) use self::keys_proto::*;
 }
 
+#[cfg(feature = "bls")]
+pub mod bls;
+
 #[cfg(feature = "ecdsa")]
 pub mod ecdsa;
 
@@ -62,6 +65,7 @@ mod error;
 mod keypair;
 #[cfg(feature = "peerid")]
 mod peer_id;
+mod signer;
 
 #[cfg(any(
     feature = "ecdsa",
@@ -112,6 +116,7 @@ pub use error::{DecodingError, OtherVariantError, SigningError};
 pub use keypair::{Keypair, PublicKey};
 #[cfg(feature = "peerid")]
 pub use peer_id::{ParseError, PeerId};
+pub use signer::{SignFuture, Signer};
 
 /// The type of key a `KeyPair` is holding.
 #[derive(Debug, PartialEq, Eq)]
@@ -0,0 +1,53 @@
+// Copyright 2023 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Signing with private keys that are not held in memory.
+
+use crate::{Keypair, PublicKey, SigningError};
+use std::{future::Future, pin::Pin};
+
+/// The future returned by [`Signer::sign`].
+pub type SignFuture = Pin<Box<dyn Future<Output = Result<Vec<u8>, SigningError>> + Send>>;
+
+/// A sign-only interface to an identity keypair.
+///
+/// This allows the private key to live outside of the process memory, e.g. in a hardware
+/// security module, a TPM or a remote key management service. Signing may thus be asynchronous.
+///
+/// The signatures must be verifiable with [`Signer::public`] via [`PublicKey::verify`], i.e.
+/// follow the libp2p signature format of the key type. [`Keypair`] implements this trait for
+/// keys held in memory.
+pub trait Signer: Send + Sync + 'static {
+    /// The public key of the keypair.
+    fn public(&self) -> PublicKey;
+
+    /// Signs a message with the private key of the keypair.
+    fn sign(&self, msg: &[u8]) -> SignFuture;
+}
+
+impl Signer for Keypair {
+    fn public(&self) -> PublicKey {
+        Keypair::public(self)
+    }
+
+    fn sign(&self, msg: &[u8]) -> SignFuture {
+        Box::pin(std::future::ready(Keypair::sign(self, msg)))
+    }
+}
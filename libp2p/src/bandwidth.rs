@@ -20,7 +20,7 @@
 
 #![allow(deprecated)]
 
-use crate::core::muxing::{StreamMuxer, StreamMuxerEvent};
+use crate::core::muxing::{StreamMuxer, StreamMuxerEvent, StreamPriority};
 
 use futures::{
     io::{IoSlice, IoSliceMut},
@@ -96,6 +96,20 @@ where
         Poll::Ready(Ok(logged))
     }
 
+    fn poll_outbound_with_priority(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        priority: StreamPriority,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.project();
+        let inner = ready!(this.inner.poll_outbound_with_priority(cx, priority)?);
+        let logged = InstrumentedStream {
+            inner,
+            sinks: this.sinks.clone(),
+        };
+        Poll::Ready(Ok(logged))
+    }
+
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         let this = self.project();
         this.inner.poll_close(cx)
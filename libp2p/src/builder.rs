@@ -1,4 +1,7 @@
+use libp2p_core::Multiaddr;
 use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
 
 mod phase;
 mod select_muxer;
@@ -68,10 +71,122 @@ pub struct SwarmBuilder<Provider, Phase> {
     phase: Phase,
 }
 
+/// Two security upgrades, passed to the [`SwarmBuilder`] in place of a tuple, whose priority
+/// depends on the dialed address.
+///
+/// Like with a tuple, the protocols of the first upgrade have a higher priority by default.
+/// For every dialed address, `prefer_second` decides whether the protocols of the second
+/// upgrade are proposed first instead. The address includes the `/p2p` protocol of the
+/// dialed peer, if known. The priority of inbound connections is chosen by the remote.
+///
+/// ``` rust
+/// # use libp2p::{SecurityPreference, SwarmBuilder};
+/// # use std::collections::HashSet;
+/// # use std::error::Error;
+/// # #[cfg(all(not(target_arch = "wasm32"), feature = "tokio", feature = "tcp", feature = "tls", feature = "noise", feature = "yamux"))]
+/// # async fn build_swarm() -> Result<(), Box<dyn Error>> {
+/// let tls_peers = HashSet::<libp2p::PeerId>::new();
+///
+/// let swarm = SwarmBuilder::with_new_identity()
+///     .with_tokio()
+///     .with_tcp(
+///         Default::default(),
+///         SecurityPreference::new(
+///             libp2p_noise::Config::new,
+///             libp2p_tls::Config::new,
+///             move |address| {
+///                 address.iter().any(|p| match p {
+///                     libp2p::multiaddr::Protocol::P2p(peer) => tls_peers.contains(&peer),
+///                     _ => false,
+///                 })
+///             },
+///         ),
+///         libp2p_yamux::Config::default,
+///     )?
+/// # ;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SecurityPreference<F1, F2> {
+    first: F1,
+    second: F2,
+    prefer_second: select_security::PreferSecond,
+}
+
+impl<F1, F2> SecurityPreference<F1, F2> {
+    /// Combines two security upgrades, proposing the protocols of `second` first when
+    /// `prefer_second` returns `true` for the dialed address.
+    pub fn new(
+        first: F1,
+        second: F2,
+        prefer_second: impl Fn(&Multiaddr) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            first,
+            second,
+            prefer_second: Arc::new(prefer_second),
+        }
+    }
+}
+
+/// Timeouts of the stages of establishing a connection via a transport, see
+/// [`SwarmBuilder::with_tcp_timeouts`].
+///
+/// A stage is only subject to a timeout if one is configured. Independently, the establishment
+/// of a connection as a whole is subject to the timeout configured via
+/// [`SwarmBuilder::with_connection_timeout`], and dials to the timeout of their
+/// [`DialOpts`](libp2p_swarm::dial_opts::DialOpts), if any.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransportTimeouts {
+    connect: Option<Duration>,
+    security: Option<Duration>,
+    multiplex: Option<Duration>,
+}
+
+impl TransportTimeouts {
+    /// Sets the timeout for establishing an outgoing connection, e.g. the TCP handshake.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout for the negotiation and handshake of the security protocol.
+    pub fn with_security_timeout(mut self, timeout: Duration) -> Self {
+        self.security = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout for the negotiation of the multiplexer.
+    pub fn with_multiplex_timeout(mut self, timeout: Duration) -> Self {
+        self.multiplex = Some(timeout);
+        self
+    }
+
+    fn apply<T>(
+        &self,
+        mut builder: libp2p_core::transport::upgrade::Builder<T>,
+    ) -> libp2p_core::transport::upgrade::Builder<T>
+    where
+        T: libp2p_core::Transport,
+        T::Error: 'static,
+    {
+        if let Some(timeout) = self.security {
+            builder = builder.security_timeout(timeout);
+        }
+        if let Some(timeout) = self.multiplex {
+            builder = builder.multiplex_timeout(timeout);
+        }
+        builder
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::SwarmBuilder;
-    use libp2p_core::{muxing::StreamMuxerBox, transport::dummy::DummyTransport};
+    use libp2p_core::{
+        muxing::StreamMuxerBox,
+        transport::{dummy::DummyTransport, throttle::Limits},
+    };
     use libp2p_identity::PeerId;
     use libp2p_swarm::NetworkBehaviour;
 
@@ -97,6 +212,36 @@ mod tests {
             .build();
     }
 
+    #[test]
+    #[cfg(all(
+        feature = "tokio",
+        feature = "tcp",
+        feature = "tls",
+        feature = "noise",
+        feature = "yamux",
+    ))]
+    fn tcp_with_timeouts() {
+        let _ = SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp_timeouts(
+                crate::TransportTimeouts::default()
+                    .with_connect_timeout(std::time::Duration::from_secs(5))
+                    .with_security_timeout(std::time::Duration::from_secs(3))
+                    .with_multiplex_timeout(std::time::Duration::from_secs(2)),
+            )
+            .with_tcp(
+                Default::default(),
+                libp2p_tls::Config::new,
+                libp2p_yamux::Config::default,
+            )
+            .unwrap()
+            .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)
+            .unwrap()
+            .with_swarm_config(|cfg| cfg)
+            .with_connection_timeout(std::time::Duration::from_secs(30))
+            .build();
+    }
+
     #[test]
     #[cfg(all(
         feature = "async-std",
@@ -207,6 +352,32 @@ mod tests {
             .build();
     }
 
+    #[test]
+    #[cfg(all(
+        feature = "tokio",
+        feature = "tcp",
+        feature = "tls",
+        feature = "noise",
+        feature = "yamux"
+    ))]
+    fn tcp_security_preference() {
+        let _ = SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(
+                Default::default(),
+                super::SecurityPreference::new(
+                    libp2p_noise::Config::new,
+                    libp2p_tls::Config::new,
+                    |address| address.to_string().contains("/ip6/"),
+                ),
+                libp2p_yamux::Config::default,
+            )
+            .unwrap()
+            .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)
+            .unwrap()
+            .build();
+    }
+
     #[test]
     #[cfg(all(
         feature = "tokio",
@@ -367,6 +538,7 @@ mod tests {
     #[cfg(all(
         feature = "tokio",
         feature = "tcp",
+        feature = "tls",
         feature = "noise",
         feature = "yamux",
         feature = "dns"
@@ -408,6 +580,7 @@ mod tests {
     #[cfg(all(
         feature = "tokio",
         feature = "tcp",
+        feature = "tls",
         feature = "noise",
         feature = "yamux",
         feature = "quic",
@@ -436,6 +609,7 @@ mod tests {
     #[cfg(all(
         feature = "async-std",
         feature = "tcp",
+        feature = "tls",
         feature = "noise",
         feature = "yamux",
         feature = "quic",
@@ -587,6 +761,67 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[cfg(all(feature = "tokio", feature = "tcp", feature = "tls", feature = "yamux"))]
+    fn tcp_bandwidth_limits() -> Result<(), Box<dyn std::error::Error>> {
+        let (builder, handle) = SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(
+                Default::default(),
+                libp2p_tls::Config::new,
+                libp2p_yamux::Config::default,
+            )?
+            .with_bandwidth_limits(Limits::new().connection_upload(1024));
+        let _ = builder
+            .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)
+            .unwrap()
+            .build();
+
+        handle.set_limits(Limits::new().total_download(1024));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "tokio", feature = "webrtc"))]
+    fn webrtc() {
+        let _ = SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_webrtc(
+                libp2p_webrtc::tokio::Certificate::generate(&mut rand::thread_rng()).unwrap(),
+            )
+            .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)
+            .unwrap()
+            .build();
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "tokio",
+        feature = "tcp",
+        feature = "tls",
+        feature = "quic",
+        feature = "webrtc",
+        feature = "yamux"
+    ))]
+    fn tcp_quic_webrtc() {
+        let _ = SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(
+                Default::default(),
+                libp2p_tls::Config::new,
+                libp2p_yamux::Config::default,
+            )
+            .unwrap()
+            .with_quic()
+            .with_webrtc(
+                libp2p_webrtc::tokio::Certificate::generate(&mut rand::thread_rng()).unwrap(),
+            )
+            .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)
+            .unwrap()
+            .build();
+    }
+
     #[test]
     #[cfg(feature = "tokio")]
     fn other_transport_bandwidth_metrics() -> Result<(), Box<dyn std::error::Error>> {
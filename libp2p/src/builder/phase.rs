@@ -29,22 +29,28 @@ use websocket::*;
 
 use super::select_muxer::SelectMuxerUpgrade;
 use super::select_security::SelectSecurityUpgrade;
-use super::SwarmBuilder;
+use super::{SecurityPreference, SwarmBuilder, TransportTimeouts};
 
-use libp2p_core::{muxing::StreamMuxerBox, Transport};
+use libp2p_core::{muxing::StreamMuxerBox, ConnectedPoint, Transport};
 use libp2p_identity::Keypair;
 
 #[allow(unreachable_pub)]
 pub trait IntoSecurityUpgrade<C> {
-    type Upgrade;
+    type Upgrade: Clone;
     type Error;
 
     fn into_security_upgrade(self, keypair: &Keypair) -> Result<Self::Upgrade, Self::Error>;
+
+    /// Returns the upgrade to apply to a connection with the given endpoint.
+    fn upgrade_for(upgrade: &Self::Upgrade, _endpoint: &ConnectedPoint) -> Self::Upgrade {
+        upgrade.clone()
+    }
 }
 
 impl<C, T, F, E> IntoSecurityUpgrade<C> for F
 where
     F: for<'a> FnOnce(&'a Keypair) -> Result<T, E>,
+    T: Clone,
 {
     type Upgrade = T;
     type Error = E;
@@ -74,6 +80,33 @@ where
 
         Ok(SelectSecurityUpgrade::new(u1, u2))
     }
+
+    fn upgrade_for(upgrade: &Self::Upgrade, endpoint: &ConnectedPoint) -> Self::Upgrade {
+        upgrade.for_endpoint(
+            endpoint,
+            F1::upgrade_for(upgrade.first(), endpoint),
+            F2::upgrade_for(upgrade.second(), endpoint),
+        )
+    }
+}
+
+impl<F1, F2, C> IntoSecurityUpgrade<C> for SecurityPreference<F1, F2>
+where
+    F1: IntoSecurityUpgrade<C>,
+    F2: IntoSecurityUpgrade<C>,
+{
+    type Upgrade = SelectSecurityUpgrade<F1::Upgrade, F2::Upgrade>;
+    type Error = either::Either<F1::Error, F2::Error>;
+
+    fn into_security_upgrade(self, keypair: &Keypair) -> Result<Self::Upgrade, Self::Error> {
+        let upgrade = (self.first, self.second).into_security_upgrade(keypair)?;
+
+        Ok(upgrade.with_preference(self.prefer_second))
+    }
+
+    fn upgrade_for(upgrade: &Self::Upgrade, endpoint: &ConnectedPoint) -> Self::Upgrade {
+        <(F1, F2) as IntoSecurityUpgrade<C>>::upgrade_for(upgrade, endpoint)
+    }
 }
 
 #[allow(unreachable_pub)]
@@ -49,6 +49,20 @@ impl<T: AuthenticatedMultiplexedTransport, Provider, R>
 }
 
 // Shortcuts
+impl<Provider, T: AuthenticatedMultiplexedTransport, R>
+    SwarmBuilder<Provider, BandwidthLoggingPhase<T, R>>
+{
+    pub fn with_bandwidth_limits(
+        self,
+        limits: libp2p_core::transport::throttle::Limits,
+    ) -> (
+        SwarmBuilder<Provider, BandwidthMetricsPhase<impl AuthenticatedMultiplexedTransport, R>>,
+        libp2p_core::transport::throttle::Handle,
+    ) {
+        self.without_bandwidth_logging()
+            .with_bandwidth_limits(limits)
+    }
+}
 #[cfg(feature = "metrics")]
 impl<Provider, T: AuthenticatedMultiplexedTransport, R>
     SwarmBuilder<Provider, BandwidthLoggingPhase<T, R>>
@@ -3,6 +3,7 @@ use super::*;
 use crate::bandwidth::BandwidthSinks;
 use crate::transport_ext::TransportExt;
 use crate::SwarmBuilder;
+use libp2p_core::transport::throttle;
 use std::marker::PhantomData;
 use std::sync::Arc;
 
@@ -31,6 +32,36 @@ impl<T: AuthenticatedMultiplexedTransport, Provider, R>
     }
 }
 
+impl<T: AuthenticatedMultiplexedTransport, Provider, R>
+    SwarmBuilder<Provider, BandwidthMetricsPhase<T, R>>
+{
+    /// Limits the bandwidth of the connections per connection and in total.
+    ///
+    /// The returned [`throttle::Handle`] allows adjusting the limits at runtime.
+    pub fn with_bandwidth_limits(
+        self,
+        limits: throttle::Limits,
+    ) -> (
+        SwarmBuilder<Provider, BandwidthMetricsPhase<impl AuthenticatedMultiplexedTransport, R>>,
+        throttle::Handle,
+    ) {
+        let transport = throttle::Throttle::new(self.phase.transport, limits);
+        let handle = transport.handle();
+        (
+            SwarmBuilder {
+                phase: BandwidthMetricsPhase {
+                    relay_behaviour: self.phase.relay_behaviour,
+                    transport: transport
+                        .map(|(peer_id, conn), _| (peer_id, StreamMuxerBox::new(conn))),
+                },
+                keypair: self.keypair,
+                phantom: PhantomData,
+            },
+            handle,
+        )
+    }
+}
+
 impl<T, Provider, R> SwarmBuilder<Provider, BandwidthMetricsPhase<T, R>> {
     pub fn without_bandwidth_metrics(self) -> SwarmBuilder<Provider, BehaviourPhase<T, R>> {
         SwarmBuilder {
@@ -9,9 +9,21 @@ pub struct BuildPhase<T, B> {
     pub(crate) behaviour: B,
     pub(crate) transport: T,
     pub(crate) swarm_config: libp2p_swarm::Config,
+    pub(crate) connection_timeout: std::time::Duration,
 }
 
-const CONNECTION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+pub(crate) const CONNECTION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+impl<Provider, T, B> SwarmBuilder<Provider, BuildPhase<T, B>> {
+    /// Sets the timeout for establishing a connection including all upgrades, across all
+    /// transports. Defaults to 10 seconds.
+    ///
+    /// See [`TransportTimeouts`] for timeouts of the individual stages.
+    pub fn with_connection_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.phase.connection_timeout = timeout;
+        self
+    }
+}
 
 impl<Provider, T: AuthenticatedMultiplexedTransport, B: libp2p_swarm::NetworkBehaviour>
     SwarmBuilder<Provider, BuildPhase<T, B>>
@@ -20,7 +32,7 @@ impl<Provider, T: AuthenticatedMultiplexedTransport, B: libp2p_swarm::NetworkBeh
         Swarm::new(
             libp2p_core::transport::timeout::TransportTimeout::new(
                 self.phase.transport,
-                CONNECTION_TIMEOUT,
+                self.phase.connection_timeout,
             )
             .boxed(),
             self.phase.behaviour,
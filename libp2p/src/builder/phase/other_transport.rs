@@ -69,6 +69,39 @@ impl<Provider, T: AuthenticatedMultiplexedTransport>
 }
 
 // Shortcuts
+#[cfg(all(not(target_arch = "wasm32"), feature = "tokio", feature = "webrtc"))]
+impl<T: AuthenticatedMultiplexedTransport>
+    SwarmBuilder<super::provider::Tokio, OtherTransportPhase<T>>
+{
+    /// Adds the native WebRTC transport, listening on and dialing `/webrtc-direct` addresses.
+    ///
+    /// The certificate's fingerprint is part of the listen addresses (`/certhash`) that remotes
+    /// dial. Persist it, e.g. via [`Certificate::serialize_pem`](libp2p_webrtc::tokio::Certificate::serialize_pem),
+    /// to keep those addresses valid across restarts.
+    pub fn with_webrtc(
+        self,
+        certificate: libp2p_webrtc::tokio::Certificate,
+    ) -> SwarmBuilder<
+        super::provider::Tokio,
+        OtherTransportPhase<impl AuthenticatedMultiplexedTransport>,
+    > {
+        SwarmBuilder {
+            phase: OtherTransportPhase {
+                transport: self
+                    .phase
+                    .transport
+                    .or_transport(
+                        libp2p_webrtc::tokio::Transport::new(self.keypair.clone(), certificate)
+                            .map(|(peer_id, conn), _| (peer_id, StreamMuxerBox::new(conn))),
+                    )
+                    .map(|either, _| either.into_inner()),
+            },
+            keypair: self.keypair,
+            phantom: PhantomData,
+        }
+    }
+}
+
 #[cfg(all(not(target_arch = "wasm32"), feature = "async-std", feature = "dns"))]
 impl<T: AuthenticatedMultiplexedTransport>
     SwarmBuilder<super::provider::AsyncStd, OtherTransportPhase<T>>
@@ -196,6 +229,27 @@ impl<Provider, T: AuthenticatedMultiplexedTransport>
             .with_bandwidth_logging()
     }
 }
+impl<Provider, T: AuthenticatedMultiplexedTransport>
+    SwarmBuilder<Provider, OtherTransportPhase<T>>
+{
+    pub fn with_bandwidth_limits(
+        self,
+        limits: libp2p_core::transport::throttle::Limits,
+    ) -> (
+        SwarmBuilder<
+            Provider,
+            BandwidthMetricsPhase<impl AuthenticatedMultiplexedTransport, NoRelayBehaviour>,
+        >,
+        libp2p_core::transport::throttle::Handle,
+    ) {
+        self.without_any_other_transports()
+            .without_dns()
+            .without_websocket()
+            .without_relay()
+            .without_bandwidth_logging()
+            .with_bandwidth_limits(limits)
+    }
+}
 #[cfg(feature = "metrics")]
 impl<Provider, T: AuthenticatedMultiplexedTransport>
     SwarmBuilder<Provider, OtherTransportPhase<T>>
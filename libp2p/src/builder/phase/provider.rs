@@ -32,7 +32,9 @@ impl SwarmBuilder<NoProviderSpecified, ProviderPhase> {
         SwarmBuilder {
             keypair: self.keypair,
             phantom: PhantomData,
-            phase: TcpPhase {},
+            phase: TcpPhase {
+                timeouts: Default::default(),
+            },
         }
     }
 
@@ -43,7 +45,9 @@ impl SwarmBuilder<NoProviderSpecified, ProviderPhase> {
         SwarmBuilder {
             keypair: self.keypair,
             phantom: PhantomData,
-            phase: TcpPhase {},
+            phase: TcpPhase {
+                timeouts: Default::default(),
+            },
         }
     }
 
@@ -54,7 +58,9 @@ impl SwarmBuilder<NoProviderSpecified, ProviderPhase> {
         SwarmBuilder {
             keypair: self.keypair,
             phantom: PhantomData,
-            phase: TcpPhase {},
+            phase: TcpPhase {
+                timeouts: Default::default(),
+            },
         }
     }
 }
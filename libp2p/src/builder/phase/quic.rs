@@ -148,6 +148,18 @@ impl<Provider, T: AuthenticatedMultiplexedTransport> SwarmBuilder<Provider, Quic
             .with_behaviour(constructor)
     }
 }
+#[cfg(all(not(target_arch = "wasm32"), feature = "tokio", feature = "webrtc"))]
+impl<T: AuthenticatedMultiplexedTransport> SwarmBuilder<super::provider::Tokio, QuicPhase<T>> {
+    pub fn with_webrtc(
+        self,
+        certificate: libp2p_webrtc::tokio::Certificate,
+    ) -> SwarmBuilder<
+        super::provider::Tokio,
+        OtherTransportPhase<impl AuthenticatedMultiplexedTransport>,
+    > {
+        self.without_quic().with_webrtc(certificate)
+    }
+}
 #[cfg(all(not(target_arch = "wasm32"), feature = "async-std", feature = "dns"))]
 impl<T: AuthenticatedMultiplexedTransport> SwarmBuilder<super::provider::AsyncStd, QuicPhase<T>> {
     pub async fn with_dns(
@@ -296,6 +308,26 @@ impl<Provider, T: AuthenticatedMultiplexedTransport> SwarmBuilder<Provider, Quic
             .with_bandwidth_logging()
     }
 }
+impl<Provider, T: AuthenticatedMultiplexedTransport> SwarmBuilder<Provider, QuicPhase<T>> {
+    pub fn with_bandwidth_limits(
+        self,
+        limits: libp2p_core::transport::throttle::Limits,
+    ) -> (
+        SwarmBuilder<
+            Provider,
+            BandwidthMetricsPhase<impl AuthenticatedMultiplexedTransport, NoRelayBehaviour>,
+        >,
+        libp2p_core::transport::throttle::Handle,
+    ) {
+        self.without_quic()
+            .without_any_other_transports()
+            .without_dns()
+            .without_websocket()
+            .without_relay()
+            .without_bandwidth_logging()
+            .with_bandwidth_limits(limits)
+    }
+}
 #[cfg(feature = "metrics")]
 impl<Provider, T: AuthenticatedMultiplexedTransport> SwarmBuilder<Provider, QuicPhase<T>> {
     pub fn with_bandwidth_metrics(
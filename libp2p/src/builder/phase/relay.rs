@@ -79,9 +79,10 @@ impl<Provider, T: AuthenticatedMultiplexedTransport> SwarmBuilder<Provider, Rela
     {
         let (relay_transport, relay_behaviour) =
             libp2p_relay::client::new(self.keypair.public().to_peer_id());
+        let security_upgrade = security_upgrade.into_security_upgrade(&self.keypair)?;
         let relay_transport = relay_transport
             .upgrade(libp2p_core::upgrade::Version::V1Lazy)
-            .authenticate(security_upgrade.into_security_upgrade(&self.keypair)?)
+            .authenticate_ext(move |endpoint| SecUpgrade::upgrade_for(&security_upgrade, endpoint))
             .multiplex(multiplexer_upgrade.into_multiplexer_upgrade())
             .map(|(p, c), _| (p, StreamMuxerBox::new(c)));
 
@@ -116,6 +117,22 @@ impl<Provider, T> SwarmBuilder<Provider, RelayPhase<T>> {
 }
 
 // Shortcuts
+impl<Provider, T: AuthenticatedMultiplexedTransport> SwarmBuilder<Provider, RelayPhase<T>> {
+    pub fn with_bandwidth_limits(
+        self,
+        limits: libp2p_core::transport::throttle::Limits,
+    ) -> (
+        SwarmBuilder<
+            Provider,
+            BandwidthMetricsPhase<impl AuthenticatedMultiplexedTransport, NoRelayBehaviour>,
+        >,
+        libp2p_core::transport::throttle::Handle,
+    ) {
+        self.without_relay()
+            .without_bandwidth_logging()
+            .with_bandwidth_limits(limits)
+    }
+}
 #[cfg(feature = "metrics")]
 impl<Provider, T: AuthenticatedMultiplexedTransport> SwarmBuilder<Provider, RelayPhase<T>> {
     pub fn with_bandwidth_metrics(
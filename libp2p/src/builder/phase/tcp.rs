@@ -16,7 +16,9 @@ use libp2p_core::{
 };
 use std::marker::PhantomData;
 
-pub struct TcpPhase {}
+pub struct TcpPhase {
+    pub(crate) timeouts: TransportTimeouts,
+}
 
 macro_rules! impl_tcp_builder {
     ($providerKebabCase:literal, $providerPascalCase:ty, $path:ident) => {
@@ -77,13 +79,23 @@ macro_rules! impl_tcp_builder {
                 <<<MuxUpgrade as IntoMultiplexerUpgrade<SecStream>>::Upgrade as UpgradeInfo>::InfoIter as IntoIterator>::IntoIter: Send,
                 <<MuxUpgrade as IntoMultiplexerUpgrade<SecStream>>::Upgrade as UpgradeInfo>::Info: Send,
             {
+                let timeouts = self.phase.timeouts;
+                let tcp_config = match timeouts.connect {
+                    Some(timeout) => tcp_config.connect_timeout(timeout),
+                    None => tcp_config,
+                };
+
                 Ok(SwarmBuilder {
                     phase: QuicPhase {
-                        transport: libp2p_tcp::$path::Transport::new(tcp_config)
-                            .upgrade(libp2p_core::upgrade::Version::V1Lazy)
-                            .authenticate(
-                                security_upgrade.into_security_upgrade(&self.keypair)?,
+                        transport: timeouts
+                            .apply(
+                                libp2p_tcp::$path::Transport::new(tcp_config)
+                                    .upgrade(libp2p_core::upgrade::Version::V1Lazy),
                             )
+                            .authenticate_ext({
+                                let upgrade = security_upgrade.into_security_upgrade(&self.keypair)?;
+                                move |endpoint| SecUpgrade::upgrade_for(&upgrade, endpoint)
+                            })
                             .multiplex(multiplexer_upgrade.into_multiplexer_upgrade())
                             .map(|(p, c), _| (p, StreamMuxerBox::new(c))),
                     },
@@ -99,6 +111,39 @@ impl_tcp_builder!("async-std", super::provider::AsyncStd, async_io);
 impl_tcp_builder!("tokio", super::provider::Tokio, tokio);
 
 impl<Provider> SwarmBuilder<Provider, TcpPhase> {
+    /// Configures the timeouts of the stages of establishing connections via the TCP
+    /// transport added with [`SwarmBuilder::with_tcp`].
+    ///
+    /// A connect timeout overrides the one of the [`libp2p_tcp::Config`], if any.
+    ///
+    /// ``` rust
+    /// # use libp2p::{SwarmBuilder, TransportTimeouts};
+    /// # use std::error::Error;
+    /// # use std::time::Duration;
+    /// # #[cfg(all(not(target_arch = "wasm32"), feature = "tokio", feature = "tcp", feature = "noise", feature = "yamux"))]
+    /// # async fn build_swarm() -> Result<(), Box<dyn Error>> {
+    /// let swarm = SwarmBuilder::with_new_identity()
+    ///     .with_tokio()
+    ///     .with_tcp_timeouts(
+    ///         TransportTimeouts::default()
+    ///             .with_connect_timeout(Duration::from_secs(5))
+    ///             .with_security_timeout(Duration::from_secs(3))
+    ///             .with_multiplex_timeout(Duration::from_secs(2)),
+    ///     )
+    ///     .with_tcp(
+    ///         Default::default(),
+    ///         libp2p_noise::Config::new,
+    ///         libp2p_yamux::Config::default,
+    ///     )?
+    /// # ;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_tcp_timeouts(mut self, timeouts: TransportTimeouts) -> Self {
+        self.phase.timeouts = timeouts;
+        self
+    }
+
     pub(crate) fn without_tcp(
         self,
     ) -> SwarmBuilder<Provider, QuicPhase<impl AuthenticatedMultiplexedTransport>> {
@@ -159,6 +204,18 @@ impl SwarmBuilder<super::provider::Tokio, TcpPhase> {
         self.without_tcp().with_quic_config(constructor)
     }
 }
+#[cfg(all(not(target_arch = "wasm32"), feature = "tokio", feature = "webrtc"))]
+impl SwarmBuilder<super::provider::Tokio, TcpPhase> {
+    pub fn with_webrtc(
+        self,
+        certificate: libp2p_webrtc::tokio::Certificate,
+    ) -> SwarmBuilder<
+        super::provider::Tokio,
+        OtherTransportPhase<impl AuthenticatedMultiplexedTransport>,
+    > {
+        self.without_tcp().without_quic().with_webrtc(certificate)
+    }
+}
 impl<Provider> SwarmBuilder<Provider, TcpPhase> {
     pub fn with_other_transport<
         Muxer: libp2p_core::muxing::StreamMuxer + Send + 'static,
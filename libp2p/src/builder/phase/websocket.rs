@@ -95,7 +95,7 @@ macro_rules! impl_websocket_builder {
                     $dnsTcp.await.map_err(WebsocketErrorInner::Dns)?,
                 )
                     .upgrade(libp2p_core::upgrade::Version::V1Lazy)
-                    .authenticate(security_upgrade)
+                    .authenticate_ext(move |endpoint| SecUpgrade::upgrade_for(&security_upgrade, endpoint))
                     .multiplex(multiplexer_upgrade.into_multiplexer_upgrade())
                     .map(|(p, c), _| (p, StreamMuxerBox::new(c)));
 
@@ -186,6 +186,23 @@ impl<T: AuthenticatedMultiplexedTransport, Provider> SwarmBuilder<Provider, Webs
             .with_relay_client(security_upgrade, multiplexer_upgrade)
     }
 }
+impl<Provider, T: AuthenticatedMultiplexedTransport> SwarmBuilder<Provider, WebsocketPhase<T>> {
+    pub fn with_bandwidth_limits(
+        self,
+        limits: libp2p_core::transport::throttle::Limits,
+    ) -> (
+        SwarmBuilder<
+            Provider,
+            BandwidthMetricsPhase<impl AuthenticatedMultiplexedTransport, NoRelayBehaviour>,
+        >,
+        libp2p_core::transport::throttle::Handle,
+    ) {
+        self.without_websocket()
+            .without_relay()
+            .without_bandwidth_logging()
+            .with_bandwidth_limits(limits)
+    }
+}
 #[cfg(feature = "metrics")]
 impl<Provider, T: AuthenticatedMultiplexedTransport> SwarmBuilder<Provider, WebsocketPhase<T>> {
     pub fn with_bandwidth_metrics(
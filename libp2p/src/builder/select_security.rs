@@ -26,22 +26,87 @@ use futures::future::MapOk;
 use futures::{future, TryFutureExt};
 use libp2p_core::either::EitherFuture;
 use libp2p_core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade, UpgradeInfo};
+use libp2p_core::{ConnectedPoint, Multiaddr};
 use libp2p_identity::PeerId;
+use std::fmt;
 use std::iter::{Chain, Map};
+use std::sync::Arc;
 
 /// Upgrade that combines two upgrades into one. Supports all the protocols supported by either
 /// sub-upgrade.
 ///
-/// The protocols supported by the first element have a higher priority.
-#[derive(Debug, Clone)]
-pub struct SelectSecurityUpgrade<A, B>(A, B);
+/// The protocols supported by the first element have a higher priority, unless a
+/// [`SecurityPreference`](crate::SecurityPreference) prefers the second one for the dialed address.
+#[derive(Clone)]
+pub struct SelectSecurityUpgrade<A, B> {
+    a: A,
+    b: B,
+    prefer_second: bool,
+    preference: Option<PreferSecond>,
+}
+
+/// Returns whether the second upgrade is preferred when dialing the given address.
+pub(crate) type PreferSecond = Arc<dyn Fn(&Multiaddr) -> bool + Send + Sync>;
 
 impl<A, B> SelectSecurityUpgrade<A, B> {
     /// Combines two upgrades into an `SelectUpgrade`.
     ///
     /// The protocols supported by the first element have a higher priority.
     pub fn new(a: A, b: B) -> Self {
-        SelectSecurityUpgrade(a, b)
+        SelectSecurityUpgrade {
+            a,
+            b,
+            prefer_second: false,
+            preference: None,
+        }
+    }
+
+    pub(crate) fn with_preference(mut self, preference: PreferSecond) -> Self {
+        self.preference = Some(preference);
+        self
+    }
+
+    /// Returns the upgrade to apply to a connection with the given endpoint, with the
+    /// sub-upgrades replaced by `a` and `b`.
+    pub(crate) fn for_endpoint<A2, B2>(
+        &self,
+        endpoint: &ConnectedPoint,
+        a: A2,
+        b: B2,
+    ) -> SelectSecurityUpgrade<A2, B2> {
+        let prefer_second = match (endpoint, &self.preference) {
+            (ConnectedPoint::Dialer { address, .. }, Some(preference)) => preference(address),
+            _ => false,
+        };
+
+        SelectSecurityUpgrade {
+            a,
+            b,
+            prefer_second,
+            preference: self.preference.clone(),
+        }
+    }
+
+    pub(crate) fn first(&self) -> &A {
+        &self.a
+    }
+
+    pub(crate) fn second(&self) -> &B {
+        &self.b
+    }
+}
+
+impl<A, B> fmt::Debug for SelectSecurityUpgrade<A, B>
+where
+    A: fmt::Debug,
+    B: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SelectSecurityUpgrade")
+            .field("a", &self.a)
+            .field("b", &self.b)
+            .field("prefer_second", &self.prefer_second)
+            .finish()
     }
 }
 
@@ -51,27 +116,36 @@ where
     B: UpgradeInfo,
 {
     type Info = Either<A::Info, B::Info>;
-    type InfoIter = Chain<
-        Map<<A::InfoIter as IntoIterator>::IntoIter, fn(A::Info) -> Self::Info>,
-        Map<<B::InfoIter as IntoIterator>::IntoIter, fn(B::Info) -> Self::Info>,
+    type InfoIter = Either<
+        Chain<InfoIter<A, Self::Info>, InfoIter<B, Self::Info>>,
+        Chain<InfoIter<B, Self::Info>, InfoIter<A, Self::Info>>,
     >;
 
     fn protocol_info(&self) -> Self::InfoIter {
         let a = self
-            .0
+            .a
             .protocol_info()
             .into_iter()
             .map(Either::Left as fn(A::Info) -> _);
         let b = self
-            .1
+            .b
             .protocol_info()
             .into_iter()
             .map(Either::Right as fn(B::Info) -> _);
 
-        a.chain(b)
+        if self.prefer_second {
+            Either::Right(b.chain(a))
+        } else {
+            Either::Left(a.chain(b))
+        }
     }
 }
 
+type InfoIter<U, I> = Map<
+    <<U as UpgradeInfo>::InfoIter as IntoIterator>::IntoIter,
+    fn(<U as UpgradeInfo>::Info) -> I,
+>;
+
 impl<C, A, B, TA, TB, EA, EB> InboundConnectionUpgrade<C> for SelectSecurityUpgrade<A, B>
 where
     A: InboundConnectionUpgrade<C, Output = (PeerId, TA), Error = EA>,
@@ -86,8 +160,8 @@ where
 
     fn upgrade_inbound(self, sock: C, info: Self::Info) -> Self::Future {
         match info {
-            Either::Left(info) => EitherFuture::First(self.0.upgrade_inbound(sock, info)),
-            Either::Right(info) => EitherFuture::Second(self.1.upgrade_inbound(sock, info)),
+            Either::Left(info) => EitherFuture::First(self.a.upgrade_inbound(sock, info)),
+            Either::Right(info) => EitherFuture::Second(self.b.upgrade_inbound(sock, info)),
         }
         .map_ok(future::Either::factor_first)
     }
@@ -107,9 +181,44 @@ where
 
     fn upgrade_outbound(self, sock: C, info: Self::Info) -> Self::Future {
         match info {
-            Either::Left(info) => EitherFuture::First(self.0.upgrade_outbound(sock, info)),
-            Either::Right(info) => EitherFuture::Second(self.1.upgrade_outbound(sock, info)),
+            Either::Left(info) => EitherFuture::First(self.a.upgrade_outbound(sock, info)),
+            Either::Right(info) => EitherFuture::Second(self.b.upgrade_outbound(sock, info)),
         }
         .map_ok(future::Either::factor_first)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_core::{upgrade::ReadyUpgrade, Endpoint};
+    use libp2p_swarm::StreamProtocol;
+
+    #[test]
+    fn preference_orders_protocols_of_dialed_connections() {
+        let upgrade = SelectSecurityUpgrade::new(
+            ReadyUpgrade::new(StreamProtocol::new("/first")),
+            ReadyUpgrade::new(StreamProtocol::new("/second")),
+        )
+        .with_preference(Arc::new(|address: &Multiaddr| {
+            address.to_string().starts_with("/memory/2")
+        }));
+
+        let protocols = |address: &str| {
+            let endpoint = ConnectedPoint::Dialer {
+                address: address.parse().unwrap(),
+                role_override: Endpoint::Dialer,
+            };
+            let upgrade =
+                upgrade.for_endpoint(&endpoint, upgrade.first().clone(), upgrade.second().clone());
+            Iterator::map(upgrade.protocol_info(), |p| {
+                p.map_either(|p| p.to_string(), |p| p.to_string())
+                    .into_inner()
+            })
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(protocols("/memory/1"), ["/first", "/second"]);
+        assert_eq!(protocols("/memory/2"), ["/second", "/first"]);
+    }
+}
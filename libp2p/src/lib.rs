@@ -84,6 +84,9 @@ pub use libp2p_metrics as metrics;
 #[cfg(feature = "noise")]
 #[doc(inline)]
 pub use libp2p_noise as noise;
+#[cfg(feature = "peer-store")]
+#[doc(inline)]
+pub use libp2p_peer_store as peer_store;
 #[cfg(feature = "ping")]
 #[doc(inline)]
 pub use libp2p_ping as ping;
@@ -117,6 +120,11 @@ pub use libp2p_tcp as tcp;
 #[cfg(not(target_arch = "wasm32"))]
 #[doc(inline)]
 pub use libp2p_tls as tls;
+#[cfg(feature = "tor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tor")))]
+#[cfg(not(target_arch = "wasm32"))]
+#[doc(inline)]
+pub use libp2p_tor as tor;
 #[cfg(feature = "uds")]
 #[cfg_attr(docsrs, doc(cfg(feature = "uds")))]
 #[cfg(not(target_arch = "wasm32"))]
@@ -126,6 +134,11 @@ pub use libp2p_uds as uds;
 #[cfg(not(target_arch = "wasm32"))]
 #[doc(inline)]
 pub use libp2p_upnp as upnp;
+#[cfg(feature = "webrtc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "webrtc")))]
+#[cfg(not(target_arch = "wasm32"))]
+#[doc(inline)]
+pub use libp2p_webrtc as webrtc;
 #[cfg(feature = "websocket")]
 #[cfg(not(target_arch = "wasm32"))]
 #[doc(inline)]
@@ -149,7 +162,7 @@ pub mod bandwidth;
 #[cfg(doc)]
 pub mod tutorials;
 
-pub use self::builder::SwarmBuilder;
+pub use self::builder::{SecurityPreference, SwarmBuilder, TransportTimeouts};
 pub use self::core::{
     transport::TransportError,
     upgrade::{InboundUpgrade, OutboundUpgrade},
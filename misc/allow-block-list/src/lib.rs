@@ -61,22 +61,30 @@
 //! # }
 //! ```
 
-use libp2p_core::{Endpoint, Multiaddr};
+use futures_timer::Delay;
+use libp2p_core::{multiaddr::Protocol, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
+    behaviour::{ConnectionClosed, ConnectionEstablished},
     dummy, CloseConnection, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler,
     THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
+use std::future::Future;
+use std::net::IpAddr;
+use std::pin::Pin;
 use std::task::{Context, Poll, Waker};
+use std::time::Duration;
 use void::Void;
 
 /// A [`NetworkBehaviour`] that can act as an allow or block list.
 #[derive(Default, Debug)]
 pub struct Behaviour<S> {
     state: S,
-    close_connections: VecDeque<PeerId>,
+    close_connections: VecDeque<(PeerId, CloseConnection)>,
+    /// Remote peers and addresses of established connections.
+    connections: HashMap<ConnectionId, (PeerId, Multiaddr)>,
     waker: Option<Waker>,
 }
 
@@ -86,19 +94,98 @@ pub struct AllowedPeers {
     peers: HashSet<PeerId>,
 }
 
-/// The list of explicitly blocked peers.
+/// The list of explicitly blocked peers and addresses.
 #[derive(Default)]
 pub struct BlockedPeers {
     peers: HashSet<PeerId>,
+    /// Peers blocked until the timer fires.
+    temporary_peers: HashMap<PeerId, Delay>,
+    addresses: Vec<AddressPattern>,
+    /// Addresses blocked until the timer fires.
+    temporary_addresses: Vec<(AddressPattern, Delay)>,
+}
+
+/// A pattern of remote addresses to block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressPattern {
+    /// Matches all addresses starting with the given address, e.g. `/ip4/192.0.2.1` matches all
+    /// connections from that IP address.
+    Prefix(Multiaddr),
+    /// Matches all addresses whose leading IP address lies in the given range, e.g.
+    /// `192.0.2.0/24`.
+    Cidr { ip: IpAddr, prefix_len: u8 },
+}
+
+impl AddressPattern {
+    /// Returns whether the pattern matches the address.
+    pub fn matches(&self, addr: &Multiaddr) -> bool {
+        match self {
+            AddressPattern::Prefix(prefix) => {
+                let mut addr = addr.iter();
+                prefix.iter().all(|p| addr.next() == Some(p))
+            }
+            AddressPattern::Cidr { ip, prefix_len } => {
+                let other = match addr.iter().next() {
+                    Some(Protocol::Ip4(ip)) => IpAddr::V4(ip),
+                    Some(Protocol::Ip6(ip)) => IpAddr::V6(ip),
+                    _ => return false,
+                };
+                in_range(*ip, *prefix_len, other)
+            }
+        }
+    }
+}
+
+impl fmt::Display for AddressPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressPattern::Prefix(prefix) => write!(f, "{prefix}"),
+            AddressPattern::Cidr { ip, prefix_len } => write!(f, "{ip}/{prefix_len}"),
+        }
+    }
+}
+
+/// Returns whether `other` shares the first `prefix_len` bits with `ip`.
+fn in_range(ip: IpAddr, prefix_len: u8, other: IpAddr) -> bool {
+    match (ip, other) {
+        (IpAddr::V4(ip), IpAddr::V4(other)) => {
+            let mask = u32::MAX
+                .checked_shl(32u32.saturating_sub(prefix_len.into()))
+                .unwrap_or(0);
+            u32::from(ip) & mask == u32::from(other) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(other)) => {
+            let mask = u128::MAX
+                .checked_shl(128u32.saturating_sub(prefix_len.into()))
+                .unwrap_or(0);
+            u128::from(ip) & mask == u128::from(other) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Event emitted by a [`Behaviour<BlockedPeers>`] when a time-limited block expired.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The block of the peer set via [`Behaviour::block_peer_for`] expired.
+    PeerBlockExpired { peer: PeerId },
+    /// The block of the addresses set via [`Behaviour::block_address_for`] expired.
+    AddressBlockExpired { pattern: AddressPattern },
+}
+
+impl<S> Behaviour<S> {
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake()
+        }
+    }
 }
 
 impl Behaviour<AllowedPeers> {
     /// Allow connections to the given peer.
     pub fn allow_peer(&mut self, peer: PeerId) {
         self.state.peers.insert(peer);
-        if let Some(waker) = self.waker.take() {
-            waker.wake()
-        }
+        self.wake();
     }
 
     /// Disallow connections to the given peer.
@@ -106,30 +193,96 @@ impl Behaviour<AllowedPeers> {
     /// All active connections to this peer will be closed immediately.
     pub fn disallow_peer(&mut self, peer: PeerId) {
         self.state.peers.remove(&peer);
-        self.close_connections.push_back(peer);
-        if let Some(waker) = self.waker.take() {
-            waker.wake()
-        }
+        self.close_connections
+            .push_back((peer, CloseConnection::All));
+        self.wake();
     }
 }
 
 impl Behaviour<BlockedPeers> {
     /// Block connections to a given peer.
     ///
-    /// All active connections to this peer will be closed immediately.
+    /// All active connections to this peer will be closed immediately. Replaces a time-limited
+    /// block of the peer.
     pub fn block_peer(&mut self, peer: PeerId) {
+        self.state.temporary_peers.remove(&peer);
         self.state.peers.insert(peer);
-        self.close_connections.push_back(peer);
-        if let Some(waker) = self.waker.take() {
-            waker.wake()
-        }
+        self.close_connections
+            .push_back((peer, CloseConnection::All));
+        self.wake();
+    }
+
+    /// Block connections to a given peer for the given duration, after which
+    /// [`Event::PeerBlockExpired`] is emitted.
+    ///
+    /// All active connections to this peer will be closed immediately. Replaces a previous block
+    /// of the peer.
+    pub fn block_peer_for(&mut self, peer: PeerId, duration: Duration) {
+        self.state.peers.remove(&peer);
+        self.state
+            .temporary_peers
+            .insert(peer, Delay::new(duration));
+        self.close_connections
+            .push_back((peer, CloseConnection::All));
+        self.wake();
     }
 
     /// Unblock connections to a given peer.
     pub fn unblock_peer(&mut self, peer: PeerId) {
         self.state.peers.remove(&peer);
-        if let Some(waker) = self.waker.take() {
-            waker.wake()
+        self.state.temporary_peers.remove(&peer);
+        self.wake();
+    }
+
+    /// Block connections from and to addresses matching the pattern.
+    ///
+    /// All active connections with a matching remote address will be closed immediately. Replaces
+    /// a time-limited block of the same pattern.
+    pub fn block_address(&mut self, pattern: AddressPattern) {
+        self.state
+            .temporary_addresses
+            .retain(|(other, _)| *other != pattern);
+        self.close_connections_matching(&pattern);
+        if !self.state.addresses.contains(&pattern) {
+            self.state.addresses.push(pattern);
+        }
+        self.wake();
+    }
+
+    /// Block connections from and to addresses matching the pattern for the given duration, after
+    /// which [`Event::AddressBlockExpired`] is emitted.
+    ///
+    /// All active connections with a matching remote address will be closed immediately. Replaces
+    /// a previous block of the same pattern.
+    pub fn block_address_for(&mut self, pattern: AddressPattern, duration: Duration) {
+        self.state.addresses.retain(|other| *other != pattern);
+        self.state
+            .temporary_addresses
+            .retain(|(other, _)| *other != pattern);
+        self.close_connections_matching(&pattern);
+        self.state
+            .temporary_addresses
+            .push((pattern, Delay::new(duration)));
+        self.wake();
+    }
+
+    /// Unblock connections from and to addresses matching the pattern.
+    ///
+    /// Addresses matching other blocked patterns remain blocked.
+    pub fn unblock_address(&mut self, pattern: &AddressPattern) {
+        self.state.addresses.retain(|other| other != pattern);
+        self.state
+            .temporary_addresses
+            .retain(|(other, _)| other != pattern);
+        self.wake();
+    }
+
+    fn close_connections_matching(&mut self, pattern: &AddressPattern) {
+        for (connection_id, (peer, addr)) in &self.connections {
+            if pattern.matches(addr) {
+                self.close_connections
+                    .push_back((*peer, CloseConnection::One(*connection_id)));
+            }
         }
     }
 }
@@ -162,11 +315,46 @@ impl fmt::Display for Blocked {
 
 impl std::error::Error for Blocked {}
 
-trait Enforce: 'static {
-    fn enforce(&self, peer: &PeerId) -> Result<(), ConnectionDenied>;
+/// A connection with this address was explicitly blocked and was thus
+/// [`denied`](ConnectionDenied).
+#[derive(Debug)]
+pub struct BlockedAddress {
+    address: Multiaddr,
+}
+
+impl fmt::Display for BlockedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "address {} is in the block list", self.address)
+    }
+}
+
+impl std::error::Error for BlockedAddress {}
+
+use private::Enforce;
+
+mod private {
+    use super::*;
+
+    /// The state of a [`Behaviour`](super::Behaviour), only implemented by [`AllowedPeers`] and
+    /// [`BlockedPeers`].
+    pub trait Enforce: 'static {
+        type Event: Send + 'static;
+
+        fn enforce(&self, peer: &PeerId) -> Result<(), ConnectionDenied>;
+
+        fn enforce_address(&self, _addr: &Multiaddr) -> Result<(), ConnectionDenied> {
+            Ok(())
+        }
+
+        fn poll(&mut self, _cx: &mut Context<'_>) -> Poll<Self::Event> {
+            Poll::Pending
+        }
+    }
 }
 
 impl Enforce for AllowedPeers {
+    type Event = Void;
+
     fn enforce(&self, peer: &PeerId) -> Result<(), ConnectionDenied> {
         if !self.peers.contains(peer) {
             return Err(ConnectionDenied::new(NotAllowed { peer: *peer }));
@@ -177,13 +365,52 @@ impl Enforce for AllowedPeers {
 }
 
 impl Enforce for BlockedPeers {
+    type Event = Event;
+
     fn enforce(&self, peer: &PeerId) -> Result<(), ConnectionDenied> {
-        if self.peers.contains(peer) {
+        if self.peers.contains(peer) || self.temporary_peers.contains_key(peer) {
             return Err(ConnectionDenied::new(Blocked { peer: *peer }));
         }
 
         Ok(())
     }
+
+    fn enforce_address(&self, addr: &Multiaddr) -> Result<(), ConnectionDenied> {
+        let blocked = self
+            .addresses
+            .iter()
+            .chain(self.temporary_addresses.iter().map(|(pattern, _)| pattern))
+            .any(|pattern| pattern.matches(addr));
+        if blocked {
+            return Err(ConnectionDenied::new(BlockedAddress {
+                address: addr.clone(),
+            }));
+        }
+
+        Ok(())
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Event> {
+        let expired = self
+            .temporary_peers
+            .iter_mut()
+            .find_map(|(peer, timer)| Pin::new(timer).poll(cx).is_ready().then_some(*peer));
+        if let Some(peer) = expired {
+            self.temporary_peers.remove(&peer);
+            return Poll::Ready(Event::PeerBlockExpired { peer });
+        }
+
+        if let Some(i) = self
+            .temporary_addresses
+            .iter_mut()
+            .position(|(_, timer)| Pin::new(timer).poll(cx).is_ready())
+        {
+            let (pattern, _) = self.temporary_addresses.swap_remove(i);
+            return Poll::Ready(Event::AddressBlockExpired { pattern });
+        }
+
+        Poll::Pending
+    }
 }
 
 impl<S> NetworkBehaviour for Behaviour<S>
@@ -191,16 +418,26 @@ where
     S: Enforce,
 {
     type ConnectionHandler = dummy::ConnectionHandler;
-    type ToSwarm = Void;
+    type ToSwarm = S::Event;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.state.enforce_address(remote_addr)
+    }
 
     fn handle_established_inbound_connection(
         &mut self,
         _: ConnectionId,
         peer: PeerId,
         _: &Multiaddr,
-        _: &Multiaddr,
+        remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
         self.state.enforce(&peer)?;
+        self.state.enforce_address(remote_addr)?;
 
         Ok(dummy::ConnectionHandler)
     }
@@ -223,15 +460,34 @@ where
         &mut self,
         _: ConnectionId,
         peer: PeerId,
-        _: &Multiaddr,
+        addr: &Multiaddr,
         _: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
         self.state.enforce(&peer)?;
+        self.state.enforce_address(addr)?;
 
         Ok(dummy::ConnectionHandler)
     }
 
-    fn on_swarm_event(&mut self, _event: FromSwarm) {}
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id,
+                connection_id,
+                endpoint,
+                ..
+            }) => {
+                self.connections.insert(
+                    connection_id,
+                    (peer_id, endpoint.get_remote_address().clone()),
+                );
+            }
+            FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. }) => {
+                self.connections.remove(&connection_id);
+            }
+            _ => {}
+        }
+    }
 
     fn on_connection_handler_event(
         &mut self,
@@ -246,13 +502,17 @@ where
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
-        if let Some(peer) = self.close_connections.pop_front() {
+        if let Some((peer, connection)) = self.close_connections.pop_front() {
             return Poll::Ready(ToSwarm::CloseConnection {
                 peer_id: peer,
-                connection: CloseConnection::All,
+                connection,
             });
         }
 
+        if let Poll::Ready(event) = self.state.poll(cx) {
+            return Poll::Ready(ToSwarm::GenerateEvent(event));
+        }
+
         self.waker = Some(cx.waker().clone());
         Poll::Pending
     }
@@ -340,6 +600,103 @@ mod tests {
         assert_eq!(closed_listener_peer, *dialer.local_peer_id());
     }
 
+    #[async_std::test]
+    async fn temporary_block_expires() {
+        let mut dialer = Swarm::new_ephemeral(|_| Behaviour::<BlockedPeers>::default());
+        let mut listener = Swarm::new_ephemeral(|_| Behaviour::<BlockedPeers>::default());
+        listener.listen().with_memory_addr_external().await;
+
+        dialer
+            .behaviour_mut()
+            .block_peer_for(*listener.local_peer_id(), Duration::from_millis(100));
+
+        let DialError::Denied { cause } = dial(&mut dialer, &listener).unwrap_err() else {
+            panic!("unexpected dial error")
+        };
+        assert!(cause.downcast::<Blocked>().is_ok());
+
+        let peer = dialer
+            .wait(|e| match e {
+                SwarmEvent::Behaviour(Event::PeerBlockExpired { peer }) => Some(peer),
+                _ => None,
+            })
+            .await;
+        assert_eq!(peer, *listener.local_peer_id());
+        dial(&mut dialer, &listener).unwrap();
+    }
+
+    #[async_std::test]
+    async fn cannot_dial_blocked_address() {
+        let mut dialer = Swarm::new_ephemeral(|_| Behaviour::<BlockedPeers>::default());
+        let mut listener = Swarm::new_ephemeral(|_| Behaviour::<BlockedPeers>::default());
+        let (listen_addr, _) = listener.listen().with_memory_addr_external().await;
+
+        dialer
+            .behaviour_mut()
+            .block_address(AddressPattern::Prefix(listen_addr));
+        dial(&mut dialer, &listener).unwrap();
+        async_std::task::spawn(listener.loop_on_next());
+
+        let cause = dialer
+            .wait(|e| match e {
+                SwarmEvent::OutgoingConnectionError {
+                    error: DialError::Denied { cause },
+                    ..
+                } => Some(cause),
+                _ => None,
+            })
+            .await;
+        assert!(cause.downcast::<BlockedAddress>().is_ok());
+    }
+
+    #[async_std::test]
+    async fn connections_get_closed_upon_blocked_address() {
+        let mut dialer = Swarm::new_ephemeral(|_| Behaviour::<BlockedPeers>::default());
+        let mut listener = Swarm::new_ephemeral(|_| Behaviour::<BlockedPeers>::default());
+        let (listen_addr, _) = listener.listen().with_memory_addr_external().await;
+        dialer.connect(&mut listener).await;
+
+        dialer
+            .behaviour_mut()
+            .block_address(AddressPattern::Prefix(listen_addr));
+
+        let (
+            [SwarmEvent::ConnectionClosed {
+                peer_id: closed_dialer_peer,
+                ..
+            }],
+            [SwarmEvent::ConnectionClosed {
+                peer_id: closed_listener_peer,
+                ..
+            }],
+        ) = libp2p_swarm_test::drive(&mut dialer, &mut listener).await
+        else {
+            panic!("unexpected events")
+        };
+        assert_eq!(closed_dialer_peer, *listener.local_peer_id());
+        assert_eq!(closed_listener_peer, *dialer.local_peer_id());
+    }
+
+    #[test]
+    fn address_pattern_matches() {
+        let addr = |s: &str| s.parse::<Multiaddr>().unwrap();
+        let cidr = |ip: &str, prefix_len| AddressPattern::Cidr {
+            ip: ip.parse().unwrap(),
+            prefix_len,
+        };
+
+        let prefix = AddressPattern::Prefix(addr("/ip4/192.0.2.1"));
+        assert!(prefix.matches(&addr("/ip4/192.0.2.1/tcp/4001")));
+        assert!(!prefix.matches(&addr("/ip4/192.0.2.2/tcp/4001")));
+        assert!(!AddressPattern::Prefix(addr("/ip4/192.0.2.1/tcp/4001"))
+            .matches(&addr("/ip4/192.0.2.1")));
+
+        assert!(cidr("192.0.2.0", 24).matches(&addr("/ip4/192.0.2.77/udp/4001/quic-v1")));
+        assert!(!cidr("192.0.2.0", 24).matches(&addr("/ip4/192.0.3.1/tcp/4001")));
+        assert!(cidr("2001:db8::", 32).matches(&addr("/ip6/2001:db8:1::1/tcp/4001")));
+        assert!(!cidr("192.0.2.0", 24).matches(&addr("/dns4/example.com/tcp/4001")));
+    }
+
     #[async_std::test]
     async fn cannot_dial_peer_unless_allowed() {
         let mut dialer = Swarm::new_ephemeral(|_| Behaviour::<AllowedPeers>::default());
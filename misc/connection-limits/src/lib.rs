@@ -18,15 +18,17 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr};
+use libp2p_core::{multiaddr::Protocol, ConnectedPoint, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
     behaviour::{ConnectionEstablished, DialFailure, ListenFailure},
-    dummy, ConnectionClosed, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler,
-    THandlerInEvent, THandlerOutEvent, ToSwarm,
+    dial_opts::Priority,
+    dummy, CloseConnection, ConnectionClosed, ConnectionDenied, ConnectionId, DialPriority,
+    FromSwarm, NetworkBehaviour, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
+use std::net::IpAddr;
 use std::task::{Context, Poll};
 use void::Void;
 
@@ -42,6 +44,24 @@ use void::Void;
 ///
 /// If you employ multiple [`NetworkBehaviour`]s that manage connections, it may also be a different error.
 ///
+/// # Preemption
+///
+/// Dials with a [`Priority`] set via
+/// [`DialOpts`](libp2p_swarm::dial_opts::DialOpts) may preempt established connections of lower
+/// priority instead of being denied once a limit for established connections is reached.
+/// Inbound connections have the [`Priority::Normal`] priority. The connection to close is selected
+/// by the [`EvictionPolicy`], which by default picks the connection of the lowest priority that
+/// was established last.
+///
+/// # Subnet and ASN limits
+///
+/// Connections can be limited per IP subnet of the remote address, see
+/// [`ConnectionLimits::with_max_established_per_ipv4_subnet`] and
+/// [`ConnectionLimits::with_max_established_per_ipv6_subnet`], and per autonomous system of the
+/// remote address, see [`ConnectionLimits::with_max_established_per_asn`]. The latter requires
+/// an [`AsnResolver`] set via [`Behaviour::with_asn_resolver`]. Relayed connections are exempt
+/// from these limits, as their remote address is the one of the relay.
+///
 /// # Example
 ///
 /// ```rust
@@ -66,6 +86,22 @@ pub struct Behaviour {
     established_inbound_connections: HashSet<ConnectionId>,
     established_outbound_connections: HashSet<ConnectionId>,
     established_per_peer: HashMap<PeerId, HashSet<ConnectionId>>,
+
+    /// Priorities of outbound connections, if other than [`Priority::Normal`].
+    priorities: HashMap<ConnectionId, Priority>,
+    /// Remote peers of established connections.
+    established_peers: HashMap<ConnectionId, PeerId>,
+    eviction_policy: Box<dyn EvictionPolicy>,
+    /// Connections to evict once the connection of higher priority they make room for is
+    /// established, i.e. accepted by all behaviours.
+    pending_evictions: HashMap<ConnectionId, Vec<Candidate>>,
+    /// Connections evicted in favor of connections of higher priority, to be closed.
+    evicted: VecDeque<(PeerId, ConnectionId)>,
+    /// Remote IP addresses of established, non-relayed connections.
+    established_ips: HashMap<ConnectionId, IpAddr>,
+    asn_resolver: Option<Box<dyn AsnResolver>>,
+    /// Autonomous systems of established connections, if resolved.
+    established_asns: HashMap<ConnectionId, u32>,
 }
 
 impl Behaviour {
@@ -77,9 +113,31 @@ impl Behaviour {
             established_inbound_connections: Default::default(),
             established_outbound_connections: Default::default(),
             established_per_peer: Default::default(),
+            priorities: Default::default(),
+            established_peers: Default::default(),
+            eviction_policy: Box::new(LowestPriority),
+            pending_evictions: Default::default(),
+            evicted: Default::default(),
+            established_ips: Default::default(),
+            asn_resolver: None,
+            established_asns: Default::default(),
         }
     }
 
+    /// Replaces the [`EvictionPolicy`] selecting connections to close in favor of connections of
+    /// higher priority.
+    pub fn with_eviction_policy(mut self, policy: impl EvictionPolicy) -> Self {
+        self.eviction_policy = Box::new(policy);
+        self
+    }
+
+    /// Sets the [`AsnResolver`] mapping remote IP addresses to autonomous systems, required to
+    /// enforce [`ConnectionLimits::with_max_established_per_asn`].
+    pub fn with_asn_resolver(mut self, resolver: impl AsnResolver) -> Self {
+        self.asn_resolver = Some(Box::new(resolver));
+        self
+    }
+
     /// Returns a mutable reference to [`ConnectionLimits`].
     /// > **Note**: A new limit will not be enforced against existing connections.
     pub fn limits_mut(&mut self) -> &mut ConnectionLimits {
@@ -87,6 +145,202 @@ impl Behaviour {
     }
 }
 
+impl Behaviour {
+    /// Checks a limit for established connections, which a new outbound connection of the given
+    /// priority may bypass by evicting an established connection of lower priority that matches
+    /// the filter.
+    ///
+    /// Selected connections are added to `victims`, which also make room for subsequent checks.
+    fn check_limit_or_evict(
+        &mut self,
+        limit: Option<u32>,
+        current: usize,
+        kind: Kind,
+        priority: Priority,
+        filter: impl Fn(&Candidate) -> bool,
+        victims: &mut Vec<Candidate>,
+    ) -> Result<(), ConnectionDenied> {
+        let current = current - victims.iter().filter(|victim| filter(victim)).count();
+        let Err(denied) = check_limit(limit, current, kind) else {
+            return Ok(());
+        };
+
+        let candidates = self
+            .established_inbound_connections
+            .iter()
+            .map(|id| (id, Endpoint::Listener))
+            .chain(
+                self.established_outbound_connections
+                    .iter()
+                    .map(|id| (id, Endpoint::Dialer)),
+            )
+            .filter_map(|(connection_id, endpoint)| {
+                Some(Candidate {
+                    connection_id: *connection_id,
+                    peer_id: *self.established_peers.get(connection_id)?,
+                    endpoint,
+                    priority: self
+                        .priorities
+                        .get(connection_id)
+                        .copied()
+                        .unwrap_or_default(),
+                })
+            })
+            .filter(|candidate| {
+                candidate.priority < priority
+                    && filter(candidate)
+                    && !victims
+                        .iter()
+                        .any(|victim| victim.connection_id == candidate.connection_id)
+            })
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return Err(denied);
+        }
+
+        let Some(victim) = self
+            .eviction_policy
+            .select(&candidates)
+            .and_then(|id| candidates.iter().find(|c| c.connection_id == id))
+        else {
+            return Err(denied);
+        };
+
+        victims.push(victim.clone());
+
+        Ok(())
+    }
+
+    /// Returns the established connections whose remote address is in the same subnet as `ip`,
+    /// if a limit applies to the subnet.
+    fn established_in_subnet(&self, ip: IpAddr) -> Option<(u32, HashSet<ConnectionId>)> {
+        let (prefix_len, limit) = match ip {
+            IpAddr::V4(_) => self.limits.max_established_per_ipv4_subnet?,
+            IpAddr::V6(_) => self.limits.max_established_per_ipv6_subnet?,
+        };
+        let connections = self
+            .established_ips
+            .iter()
+            .filter(|(_, other)| same_subnet(ip, **other, prefix_len))
+            .map(|(id, _)| *id)
+            .collect();
+
+        Some((limit, connections))
+    }
+
+    /// Returns the established connections in the same autonomous system as `ip`, if a limit
+    /// applies and the autonomous system could be resolved.
+    fn established_in_asn(&mut self, ip: IpAddr) -> Option<(u32, HashSet<ConnectionId>)> {
+        let limit = self.limits.max_established_per_asn?;
+        let asn = self.asn_resolver.as_mut()?.resolve(ip)?;
+        let connections = self
+            .established_asns
+            .iter()
+            .filter(|(_, other)| **other == asn)
+            .map(|(id, _)| *id)
+            .collect();
+
+        Some((limit, connections))
+    }
+
+    fn remove_established(&mut self, peer_id: PeerId, connection_id: ConnectionId) {
+        self.established_inbound_connections.remove(&connection_id);
+        self.established_outbound_connections.remove(&connection_id);
+        self.established_per_peer
+            .entry(peer_id)
+            .or_default()
+            .remove(&connection_id);
+        self.established_peers.remove(&connection_id);
+        self.established_ips.remove(&connection_id);
+        self.established_asns.remove(&connection_id);
+    }
+}
+
+/// An established connection that may be evicted in favor of a connection of higher priority.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub connection_id: ConnectionId,
+    pub peer_id: PeerId,
+    pub endpoint: Endpoint,
+    pub priority: Priority,
+}
+
+/// Selects the connection to close in favor of a new connection of higher priority.
+pub trait EvictionPolicy: Send + 'static {
+    /// Selects one of the candidates, all of which have a lower priority than the new
+    /// connection, or `None` to deny the new connection instead.
+    fn select(&mut self, candidates: &[Candidate]) -> Option<ConnectionId>;
+}
+
+impl<F> EvictionPolicy for F
+where
+    F: FnMut(&[Candidate]) -> Option<ConnectionId> + Send + 'static,
+{
+    fn select(&mut self, candidates: &[Candidate]) -> Option<ConnectionId> {
+        self(candidates)
+    }
+}
+
+/// The default [`EvictionPolicy`], selecting the connection of the lowest priority that was
+/// established last.
+struct LowestPriority;
+
+impl EvictionPolicy for LowestPriority {
+    fn select(&mut self, candidates: &[Candidate]) -> Option<ConnectionId> {
+        candidates
+            .iter()
+            .max_by_key(|c| (std::cmp::Reverse(c.priority), c.connection_id))
+            .map(|c| c.connection_id)
+    }
+}
+
+/// Resolves the autonomous system an IP address belongs to, e.g. via a local copy of an
+/// IP-to-ASN database.
+pub trait AsnResolver: Send + 'static {
+    /// Returns the number of the autonomous system announcing `ip`, if known.
+    fn resolve(&mut self, ip: IpAddr) -> Option<u32>;
+}
+
+impl<F> AsnResolver for F
+where
+    F: FnMut(IpAddr) -> Option<u32> + Send + 'static,
+{
+    fn resolve(&mut self, ip: IpAddr) -> Option<u32> {
+        self(ip)
+    }
+}
+
+/// Returns the IP address of a remote address, unless the connection is relayed.
+fn remote_ip(addr: &Multiaddr) -> Option<IpAddr> {
+    if addr.iter().any(|p| p == Protocol::P2pCircuit) {
+        return None;
+    }
+    match addr.iter().next()? {
+        Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+        Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+        _ => None,
+    }
+}
+
+/// Returns whether both addresses share the first `prefix_len` bits.
+fn same_subnet(a: IpAddr, b: IpAddr, prefix_len: u8) -> bool {
+    match (a, b) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => {
+            let mask = u32::MAX
+                .checked_shl(32u32.saturating_sub(prefix_len.into()))
+                .unwrap_or(0);
+            u32::from(a) & mask == u32::from(b) & mask
+        }
+        (IpAddr::V6(a), IpAddr::V6(b)) => {
+            let mask = u128::MAX
+                .checked_shl(128u32.saturating_sub(prefix_len.into()))
+                .unwrap_or(0);
+            u128::from(a) & mask == u128::from(b) & mask
+        }
+        _ => false,
+    }
+}
+
 fn check_limit(limit: Option<u32>, current: usize, kind: Kind) -> Result<(), ConnectionDenied> {
     let limit = limit.unwrap_or(u32::MAX);
     let current = current as u32;
@@ -129,6 +383,8 @@ enum Kind {
     EstablishedOutgoing,
     EstablishedPerPeer,
     EstablishedTotal,
+    EstablishedPerSubnet,
+    EstablishedPerAsn,
 }
 
 impl fmt::Display for Kind {
@@ -140,6 +396,10 @@ impl fmt::Display for Kind {
             Kind::EstablishedOutgoing => write!(f, "established outgoing connections"),
             Kind::EstablishedPerPeer => write!(f, "established connections per peer"),
             Kind::EstablishedTotal => write!(f, "established connections"),
+            Kind::EstablishedPerSubnet => write!(f, "established connections per subnet"),
+            Kind::EstablishedPerAsn => {
+                write!(f, "established connections per autonomous system")
+            }
         }
     }
 }
@@ -155,6 +415,10 @@ pub struct ConnectionLimits {
     max_established_outgoing: Option<u32>,
     max_established_per_peer: Option<u32>,
     max_established_total: Option<u32>,
+    /// Prefix length of the subnets and the limit per subnet.
+    max_established_per_ipv4_subnet: Option<(u8, u32)>,
+    max_established_per_ipv6_subnet: Option<(u8, u32)>,
+    max_established_per_asn: Option<u32>,
 }
 
 impl ConnectionLimits {
@@ -199,6 +463,37 @@ impl ConnectionLimits {
         self.max_established_per_peer = limit;
         self
     }
+
+    /// Configures the maximum number of concurrent established connections per IPv4 subnet with
+    /// the given prefix length, e.g. `24` for connections from a `/24`.
+    pub fn with_max_established_per_ipv4_subnet(
+        mut self,
+        prefix_len: u8,
+        limit: Option<u32>,
+    ) -> Self {
+        self.max_established_per_ipv4_subnet = limit.map(|limit| (prefix_len.min(32), limit));
+        self
+    }
+
+    /// Configures the maximum number of concurrent established connections per IPv6 subnet with
+    /// the given prefix length, e.g. `64` for connections from a `/64`.
+    pub fn with_max_established_per_ipv6_subnet(
+        mut self,
+        prefix_len: u8,
+        limit: Option<u32>,
+    ) -> Self {
+        self.max_established_per_ipv6_subnet = limit.map(|limit| (prefix_len.min(128), limit));
+        self
+    }
+
+    /// Configures the maximum number of concurrent established connections per autonomous
+    /// system, as resolved by the [`AsnResolver`] set via [`Behaviour::with_asn_resolver`].
+    ///
+    /// Connections whose autonomous system can't be resolved are not limited.
+    pub fn with_max_established_per_asn(mut self, limit: Option<u32>) -> Self {
+        self.max_established_per_asn = limit;
+        self
+    }
 }
 
 impl NetworkBehaviour for Behaviour {
@@ -227,7 +522,7 @@ impl NetworkBehaviour for Behaviour {
         connection_id: ConnectionId,
         peer: PeerId,
         _: &Multiaddr,
-        _: &Multiaddr,
+        remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
         self.pending_inbound_connections.remove(&connection_id);
 
@@ -244,6 +539,14 @@ impl NetworkBehaviour for Behaviour {
                 .unwrap_or(0),
             Kind::EstablishedPerPeer,
         )?;
+        if let Some(ip) = remote_ip(remote_addr) {
+            if let Some((limit, connections)) = self.established_in_subnet(ip) {
+                check_limit(Some(limit), connections.len(), Kind::EstablishedPerSubnet)?;
+            }
+            if let Some((limit, connections)) = self.established_in_asn(ip) {
+                check_limit(Some(limit), connections.len(), Kind::EstablishedPerAsn)?;
+            }
+        }
         check_limit(
             self.limits.max_established_total,
             self.established_inbound_connections.len()
@@ -276,31 +579,73 @@ impl NetworkBehaviour for Behaviour {
         &mut self,
         connection_id: ConnectionId,
         peer: PeerId,
-        _: &Multiaddr,
+        addr: &Multiaddr,
         _: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
         self.pending_outbound_connections.remove(&connection_id);
 
-        check_limit(
+        let priority = self
+            .priorities
+            .get(&connection_id)
+            .copied()
+            .unwrap_or_default();
+        let mut victims = Vec::new();
+
+        self.check_limit_or_evict(
             self.limits.max_established_outgoing,
             self.established_outbound_connections.len(),
             Kind::EstablishedOutgoing,
+            priority,
+            |candidate| candidate.endpoint == Endpoint::Dialer,
+            &mut victims,
         )?;
-        check_limit(
+        self.check_limit_or_evict(
             self.limits.max_established_per_peer,
             self.established_per_peer
                 .get(&peer)
                 .map(|connections| connections.len())
                 .unwrap_or(0),
             Kind::EstablishedPerPeer,
+            priority,
+            |candidate| candidate.peer_id == peer,
+            &mut victims,
         )?;
-        check_limit(
+        if let Some(ip) = remote_ip(addr) {
+            if let Some((limit, connections)) = self.established_in_subnet(ip) {
+                self.check_limit_or_evict(
+                    Some(limit),
+                    connections.len(),
+                    Kind::EstablishedPerSubnet,
+                    priority,
+                    |candidate| connections.contains(&candidate.connection_id),
+                    &mut victims,
+                )?;
+            }
+            if let Some((limit, connections)) = self.established_in_asn(ip) {
+                self.check_limit_or_evict(
+                    Some(limit),
+                    connections.len(),
+                    Kind::EstablishedPerAsn,
+                    priority,
+                    |candidate| connections.contains(&candidate.connection_id),
+                    &mut victims,
+                )?;
+            }
+        }
+        self.check_limit_or_evict(
             self.limits.max_established_total,
             self.established_inbound_connections.len()
                 + self.established_outbound_connections.len(),
             Kind::EstablishedTotal,
+            priority,
+            |_| true,
+            &mut victims,
         )?;
 
+        if !victims.is_empty() {
+            self.pending_evictions.insert(connection_id, victims);
+        }
+
         Ok(dummy::ConnectionHandler)
     }
 
@@ -311,12 +656,8 @@ impl NetworkBehaviour for Behaviour {
                 connection_id,
                 ..
             }) => {
-                self.established_inbound_connections.remove(&connection_id);
-                self.established_outbound_connections.remove(&connection_id);
-                self.established_per_peer
-                    .entry(peer_id)
-                    .or_default()
-                    .remove(&connection_id);
+                self.remove_established(peer_id, connection_id);
+                self.priorities.remove(&connection_id);
             }
             FromSwarm::ConnectionEstablished(ConnectionEstablished {
                 peer_id,
@@ -324,6 +665,21 @@ impl NetworkBehaviour for Behaviour {
                 connection_id,
                 ..
             }) => {
+                for victim in self
+                    .pending_evictions
+                    .remove(&connection_id)
+                    .unwrap_or_default()
+                {
+                    tracing::debug!(
+                        connection=%victim.connection_id,
+                        peer=%victim.peer_id,
+                        "Evicting connection in favor of connection of higher priority"
+                    );
+                    self.remove_established(victim.peer_id, victim.connection_id);
+                    self.evicted
+                        .push_back((victim.peer_id, victim.connection_id));
+                }
+
                 match endpoint {
                     ConnectedPoint::Listener { .. } => {
                         self.established_inbound_connections.insert(connection_id);
@@ -337,9 +693,30 @@ impl NetworkBehaviour for Behaviour {
                     .entry(peer_id)
                     .or_default()
                     .insert(connection_id);
+                self.established_peers.insert(connection_id, peer_id);
+
+                if let Some(ip) = remote_ip(endpoint.get_remote_address()) {
+                    self.established_ips.insert(connection_id, ip);
+                    if let Some(asn) = self
+                        .asn_resolver
+                        .as_mut()
+                        .and_then(|resolver| resolver.resolve(ip))
+                    {
+                        self.established_asns.insert(connection_id, asn);
+                    }
+                }
+            }
+            FromSwarm::DialPriority(DialPriority {
+                connection_id,
+                priority,
+                ..
+            }) => {
+                self.priorities.insert(connection_id, priority);
             }
             FromSwarm::DialFailure(DialFailure { connection_id, .. }) => {
                 self.pending_outbound_connections.remove(&connection_id);
+                self.priorities.remove(&connection_id);
+                self.pending_evictions.remove(&connection_id);
             }
             FromSwarm::ListenFailure(ListenFailure { connection_id, .. }) => {
                 self.pending_inbound_connections.remove(&connection_id);
@@ -358,6 +735,13 @@ impl NetworkBehaviour for Behaviour {
     }
 
     fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Some((peer_id, connection_id)) = self.evicted.pop_front() {
+            return Poll::Ready(ToSwarm::CloseConnection {
+                peer_id,
+                connection: CloseConnection::One(connection_id),
+            });
+        }
+
         Poll::Pending
     }
 }
@@ -477,6 +861,240 @@ mod tests {
         quickcheck(prop as fn(_));
     }
 
+    #[test]
+    fn high_priority_dial_evicts_connection_of_lower_priority() {
+        let mut swarm1 = Swarm::new_ephemeral(|_| {
+            Behaviour::new(ConnectionLimits::default().with_max_established(Some(1)))
+        });
+        let mut swarm2 = Swarm::new_ephemeral(|_| Behaviour::new(ConnectionLimits::default()));
+        let mut swarm3 = Swarm::new_ephemeral(|_| Behaviour::new(ConnectionLimits::default()));
+
+        async_std::task::block_on(async {
+            swarm2.listen().with_memory_addr_external().await;
+            let (swarm3_addr, _) = swarm3.listen().with_memory_addr_external().await;
+            let swarm2_peer_id = *swarm2.local_peer_id();
+            let swarm3_peer_id = *swarm3.local_peer_id();
+
+            swarm1.connect(&mut swarm2).await;
+
+            swarm1
+                .dial(
+                    DialOpts::peer_id(swarm3_peer_id)
+                        .addresses(vec![swarm3_addr])
+                        .priority(Priority::High)
+                        .build(),
+                )
+                .unwrap();
+            async_std::task::spawn(swarm2.loop_on_next());
+            async_std::task::spawn(swarm3.loop_on_next());
+
+            let mut evicted = false;
+            let mut established = false;
+            while !(evicted && established) {
+                match swarm1.next_swarm_event().await {
+                    SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                        assert_eq!(peer_id, swarm3_peer_id);
+                        established = true;
+                    }
+                    SwarmEvent::ConnectionClosed { peer_id, .. } => {
+                        assert_eq!(peer_id, swarm2_peer_id);
+                        evicted = true;
+                    }
+                    SwarmEvent::OutgoingConnectionError { error, .. } => {
+                        panic!("Unexpected dial error: {error}")
+                    }
+                    _ => {}
+                }
+            }
+
+            assert!(swarm1.is_connected(&swarm3_peer_id));
+            assert!(!swarm1.is_connected(&swarm2_peer_id));
+        });
+    }
+
+    #[test]
+    fn eviction_policy_may_deny_connection() {
+        let mut swarm1 = Swarm::new_ephemeral(|_| Behaviour {
+            limits: super::Behaviour::new(
+                ConnectionLimits::default().with_max_established(Some(1)),
+            )
+            .with_eviction_policy(|_: &[Candidate]| None),
+            connection_denier: None.into(),
+        });
+        let mut swarm2 = Swarm::new_ephemeral(|_| Behaviour::new(ConnectionLimits::default()));
+        let mut swarm3 = Swarm::new_ephemeral(|_| Behaviour::new(ConnectionLimits::default()));
+
+        async_std::task::block_on(async {
+            swarm2.listen().with_memory_addr_external().await;
+            let (swarm3_addr, _) = swarm3.listen().with_memory_addr_external().await;
+
+            swarm1.connect(&mut swarm2).await;
+
+            swarm1
+                .dial(
+                    DialOpts::peer_id(*swarm3.local_peer_id())
+                        .addresses(vec![swarm3_addr])
+                        .priority(Priority::High)
+                        .build(),
+                )
+                .unwrap();
+            async_std::task::spawn(swarm2.loop_on_next());
+            async_std::task::spawn(swarm3.loop_on_next());
+
+            let cause = swarm1
+                .wait(|event| match event {
+                    SwarmEvent::OutgoingConnectionError {
+                        error: DialError::Denied { cause },
+                        ..
+                    } => Some(cause),
+                    _ => None,
+                })
+                .await;
+
+            assert_eq!(cause.downcast::<Exceeded>().unwrap().limit, 1);
+        });
+    }
+
+    #[test]
+    fn no_eviction_if_other_behaviour_denies_connection() {
+        let mut swarm1 = Swarm::new_ephemeral(|_| {
+            Behaviour::new(ConnectionLimits::default().with_max_established(Some(1)))
+        });
+        let mut swarm2 = Swarm::new_ephemeral(|_| Behaviour::new(ConnectionLimits::default()));
+        let mut swarm3 = Swarm::new_ephemeral(|_| Behaviour::new(ConnectionLimits::default()));
+
+        async_std::task::block_on(async {
+            swarm2.listen().with_memory_addr_external().await;
+            let (swarm3_addr, _) = swarm3.listen().with_memory_addr_external().await;
+            let swarm2_peer_id = *swarm2.local_peer_id();
+
+            swarm1.connect(&mut swarm2).await;
+            swarm1
+                .behaviour_mut()
+                .connection_denier
+                .enable(ConnectionDenier {});
+
+            swarm1
+                .dial(
+                    DialOpts::peer_id(*swarm3.local_peer_id())
+                        .addresses(vec![swarm3_addr])
+                        .priority(Priority::High)
+                        .build(),
+                )
+                .unwrap();
+            async_std::task::spawn(swarm2.loop_on_next());
+            async_std::task::spawn(swarm3.loop_on_next());
+
+            let cause = swarm1
+                .wait(|event| match event {
+                    SwarmEvent::OutgoingConnectionError {
+                        error: DialError::Denied { cause },
+                        ..
+                    } => Some(cause),
+                    SwarmEvent::ConnectionClosed { peer_id, .. } => {
+                        panic!("Unexpected eviction of connection to {peer_id}")
+                    }
+                    _ => None,
+                })
+                .await;
+
+            cause.downcast::<std::io::Error>().unwrap();
+            assert!(swarm1.is_connected(&swarm2_peer_id));
+            assert!(swarm1.behaviour_mut().limits.evicted.is_empty());
+            assert!(swarm1.behaviour_mut().limits.pending_evictions.is_empty());
+        });
+    }
+
+    /// Establishes an inbound connection from `remote_addr`, as the swarm would.
+    fn establish_inbound(
+        behaviour: &mut super::Behaviour,
+        remote_addr: &str,
+    ) -> Result<(), ConnectionDenied> {
+        let connection_id = ConnectionId::new_unchecked(rand::random());
+        let peer_id = PeerId::random();
+        let local_addr: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+        let send_back_addr: Multiaddr = remote_addr.parse().unwrap();
+
+        behaviour.handle_established_inbound_connection(
+            connection_id,
+            peer_id,
+            &local_addr,
+            &send_back_addr,
+        )?;
+        behaviour.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+            peer_id,
+            connection_id,
+            endpoint: &ConnectedPoint::Listener {
+                local_addr,
+                send_back_addr,
+            },
+            failed_addresses: &[],
+            other_established: 0,
+        }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn max_established_per_subnet() {
+        let mut behaviour = super::Behaviour::new(
+            ConnectionLimits::default()
+                .with_max_established_per_ipv4_subnet(24, Some(2))
+                .with_max_established_per_ipv6_subnet(64, Some(1)),
+        );
+
+        establish_inbound(&mut behaviour, "/ip4/203.0.113.1/tcp/1").unwrap();
+        establish_inbound(&mut behaviour, "/ip4/203.0.113.2/tcp/1").unwrap();
+        let cause = establish_inbound(&mut behaviour, "/ip4/203.0.113.3/tcp/1").unwrap_err();
+        assert_eq!(cause.downcast::<Exceeded>().unwrap().limit, 2);
+        establish_inbound(&mut behaviour, "/ip4/203.0.114.1/tcp/1").unwrap();
+
+        establish_inbound(&mut behaviour, "/ip6/2001:db8::1/tcp/1").unwrap();
+        establish_inbound(&mut behaviour, "/ip6/2001:db8::2/tcp/1").unwrap_err();
+        establish_inbound(&mut behaviour, "/ip6/2001:db8:0:1::1/tcp/1").unwrap();
+
+        // Relayed connections are exempt.
+        establish_inbound(
+            &mut behaviour,
+            &format!(
+                "/ip4/203.0.113.4/tcp/1/p2p/{}/p2p-circuit",
+                PeerId::random()
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn max_established_per_asn() {
+        let mut behaviour = super::Behaviour::new(
+            ConnectionLimits::default().with_max_established_per_asn(Some(1)),
+        )
+        .with_asn_resolver(|ip: IpAddr| match ip {
+            IpAddr::V4(ip) if ip.octets()[0] == 203 => Some(64496),
+            _ => None,
+        });
+
+        establish_inbound(&mut behaviour, "/ip4/203.0.113.1/tcp/1").unwrap();
+        let cause = establish_inbound(&mut behaviour, "/ip4/203.0.114.1/tcp/1").unwrap_err();
+        assert_eq!(cause.downcast::<Exceeded>().unwrap().limit, 1);
+
+        // Connections of unknown autonomous systems are not limited.
+        establish_inbound(&mut behaviour, "/ip4/198.51.100.1/tcp/1").unwrap();
+        establish_inbound(&mut behaviour, "/ip4/198.51.100.2/tcp/1").unwrap();
+    }
+
+    #[test]
+    fn subnet_membership() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        assert!(same_subnet(ip("10.1.2.3"), ip("10.1.2.200"), 24));
+        assert!(!same_subnet(ip("10.1.2.3"), ip("10.1.3.3"), 24));
+        assert!(same_subnet(ip("10.1.2.3"), ip("192.168.0.1"), 0));
+        assert!(!same_subnet(ip("10.1.2.3"), ip("10.1.2.4"), 32));
+        assert!(same_subnet(ip("2001:db8::1"), ip("2001:db8::ffff"), 64));
+        assert!(!same_subnet(ip("10.1.2.3"), ip("::ffff:10.1.2.3"), 0));
+    }
+
     /// Another sibling [`NetworkBehaviour`] implementation might deny established connections in
     /// [`handle_established_outbound_connection`] or [`handle_established_inbound_connection`].
     /// [`Behaviour`] must not increase the established counters in
@@ -0,0 +1,215 @@
+// Copyright 2023 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_swarm::ConnectionId;
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Accounting of the memory held by [`NetworkBehaviour`](libp2p_swarm::NetworkBehaviour)s and
+/// [`ConnectionHandler`](libp2p_swarm::ConnectionHandler)s, e.g. for buffers.
+///
+/// Memory is accounted to a label, e.g. the name of a protocol, and optionally to a connection.
+/// Cloning returns a handle to the same accounting, which can be passed to the components whose
+/// memory should be attributed.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryAccounting {
+    accounts: Arc<Mutex<HashMap<AccountKey, Arc<AtomicUsize>>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct AccountKey {
+    label: Cow<'static, str>,
+    connection: Option<ConnectionId>,
+}
+
+impl MemoryAccounting {
+    /// Returns the account of the given label.
+    pub fn account(&self, label: impl Into<Cow<'static, str>>) -> Account {
+        self.get_or_insert(AccountKey {
+            label: label.into(),
+            connection: None,
+        })
+    }
+
+    /// Returns the account of the given label on a connection.
+    ///
+    /// The account is removed once the connection is closed and no [`Reservation`] is left.
+    pub fn connection_account(
+        &self,
+        label: impl Into<Cow<'static, str>>,
+        connection: ConnectionId,
+    ) -> Account {
+        self.get_or_insert(AccountKey {
+            label: label.into(),
+            connection: Some(connection),
+        })
+    }
+
+    /// Returns the memory accounted to each account, largest first.
+    pub fn usage(&self) -> Vec<Usage> {
+        let mut usage = self
+            .accounts
+            .lock()
+            .expect("lock not poisoned")
+            .iter()
+            .map(|(key, bytes)| Usage {
+                label: key.label.clone(),
+                connection: key.connection,
+                bytes: bytes.load(Ordering::Relaxed),
+            })
+            .collect::<Vec<_>>();
+        usage.sort_by_key(|usage| std::cmp::Reverse(usage.bytes));
+        usage
+    }
+
+    /// Removes the empty accounts of a closed connection.
+    pub(crate) fn remove_connection(&self, connection: ConnectionId) {
+        self.accounts
+            .lock()
+            .expect("lock not poisoned")
+            .retain(|key, bytes| {
+                key.connection != Some(connection) || bytes.load(Ordering::Relaxed) > 0
+            });
+    }
+
+    fn get_or_insert(&self, key: AccountKey) -> Account {
+        let bytes = self
+            .accounts
+            .lock()
+            .expect("lock not poisoned")
+            .entry(key)
+            .or_default()
+            .clone();
+
+        Account { bytes }
+    }
+}
+
+/// The memory accounted to an account of a [`MemoryAccounting`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Usage {
+    pub label: Cow<'static, str>,
+    /// The connection the memory is accounted to, if any.
+    pub connection: Option<ConnectionId>,
+    pub bytes: usize,
+}
+
+/// An account of a [`MemoryAccounting`], to which memory is accounted via [`Reservation`]s.
+#[derive(Debug, Clone)]
+pub struct Account {
+    bytes: Arc<AtomicUsize>,
+}
+
+impl Account {
+    /// Accounts `bytes` to this account until the returned [`Reservation`] is dropped.
+    pub fn reserve(&self, bytes: usize) -> Reservation {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+
+        Reservation {
+            account: self.bytes.clone(),
+            bytes,
+        }
+    }
+
+    /// Returns the memory currently accounted to this account.
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+}
+
+/// Memory accounted to an [`Account`], released on drop.
+#[derive(Debug)]
+pub struct Reservation {
+    account: Arc<AtomicUsize>,
+    bytes: usize,
+}
+
+impl Reservation {
+    /// Updates the accounted memory, e.g. after a buffer grew or shrank.
+    pub fn resize(&mut self, bytes: usize) {
+        if bytes > self.bytes {
+            self.account
+                .fetch_add(bytes - self.bytes, Ordering::Relaxed);
+        } else {
+            self.account
+                .fetch_sub(self.bytes - bytes, Ordering::Relaxed);
+        }
+        self.bytes = bytes;
+    }
+
+    /// Returns the memory accounted by this reservation.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.account.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accounts_reservations() {
+        let accounting = MemoryAccounting::default();
+        let connection = ConnectionId::new_unchecked(1);
+
+        let kad = accounting.account("kad");
+        let mut kad_buffer = kad.reserve(100);
+        let gossipsub = accounting.connection_account("gossipsub", connection);
+        let gossipsub_buffer = gossipsub.reserve(300);
+        kad_buffer.resize(50);
+        let _other_kad_buffer = accounting.account("kad").reserve(25);
+
+        assert_eq!(
+            accounting.usage(),
+            vec![
+                Usage {
+                    label: "gossipsub".into(),
+                    connection: Some(connection),
+                    bytes: 300,
+                },
+                Usage {
+                    label: "kad".into(),
+                    connection: None,
+                    bytes: 75,
+                },
+            ]
+        );
+
+        accounting.remove_connection(connection);
+        assert_eq!(accounting.usage().len(), 2);
+        drop(gossipsub_buffer);
+        accounting.remove_connection(connection);
+        assert_eq!(accounting.usage().len(), 1);
+        assert_eq!(kad.bytes(), 75);
+    }
+}
@@ -18,16 +18,23 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+mod accounting;
+
+pub use accounting::{Account, MemoryAccounting, Reservation, Usage};
+
+use futures_timer::Delay;
 use libp2p_core::{Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
-    dummy, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler, THandlerInEvent,
-    THandlerOutEvent, ToSwarm,
+    dummy, ConnectionClosed, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 use void::Void;
 
 use std::{
     fmt,
+    future::Future,
+    pin::Pin,
     task::{Context, Poll},
     time::{Duration, Instant},
 };
@@ -47,6 +54,19 @@ use std::{
 /// [Behaviour::with_max_bytes] and [Behaviour::with_max_percentage] are mutually exclusive.
 /// If you need to employ both of them, compose two instances of [Behaviour] into your custom behaviour.
 ///
+/// # Soft limit
+///
+/// With a soft limit configured via [`Behaviour::with_soft_limit`], upgrading new inbound
+/// connections is paused while the memory usage exceeds the soft limit, see
+/// [`ToSwarm::PauseInboundUpgrades`]. Connections are only denied once the memory usage exceeds
+/// the threshold.
+///
+/// # Memory attribution
+///
+/// The [`MemoryAccounting`] returned by [`Behaviour::accounting`] can be passed to other
+/// [`NetworkBehaviour`]s and their connection handlers to account the memory they hold, e.g. for
+/// buffers. Denied connections are logged along with the accounts holding the most memory.
+///
 /// # Example
 ///
 /// ```rust
@@ -65,6 +85,12 @@ pub struct Behaviour {
     max_allowed_bytes: usize,
     process_physical_memory_bytes: usize,
     last_refreshed: Instant,
+    /// The memory usage above which inbound upgrades are paused, if any.
+    soft_max_bytes: Option<usize>,
+    inbound_upgrades_paused: bool,
+    /// Timer to refresh the memory stats while a soft limit is set.
+    refresh_timer: Delay,
+    accounting: MemoryAccounting,
 }
 
 /// The maximum duration for which the retrieved memory-stats of the process are allowed to be stale.
@@ -83,6 +109,10 @@ impl Behaviour {
                 .map(|s| s.physical_mem)
                 .unwrap_or_default(),
             last_refreshed: Instant::now(),
+            soft_max_bytes: None,
+            inbound_upgrades_paused: false,
+            refresh_timer: Delay::new(MAX_STALE_DURATION),
+            accounting: MemoryAccounting::default(),
         }
     }
 
@@ -98,15 +128,41 @@ impl Behaviour {
         Self::with_max_bytes((system_memory_bytes as f64 * percentage).round() as usize)
     }
 
+    /// Sets a soft limit as a fraction of the memory usage threshold, e.g. `0.8` for 80% of it.
+    ///
+    /// Upgrading new inbound connections is paused while the soft limit is exceeded.
+    pub fn with_soft_limit(mut self, fraction: f64) -> Self {
+        self.soft_max_bytes = Some((self.max_allowed_bytes as f64 * fraction).round() as usize);
+        self
+    }
+
     /// Gets the process memory usage threshold in bytes.
     pub fn max_allowed_bytes(&self) -> usize {
         self.max_allowed_bytes
     }
 
+    /// Gets the soft limit of the process memory usage in bytes, if any.
+    pub fn soft_max_bytes(&self) -> Option<usize> {
+        self.soft_max_bytes
+    }
+
+    /// Returns the [`MemoryAccounting`] to attribute memory usage to its accounts.
+    pub fn accounting(&self) -> MemoryAccounting {
+        self.accounting.clone()
+    }
+
     fn check_limit(&mut self) -> Result<(), ConnectionDenied> {
         self.refresh_memory_stats_if_needed();
 
         if self.process_physical_memory_bytes > self.max_allowed_bytes {
+            if tracing::enabled!(tracing::Level::DEBUG) {
+                let usage = self.accounting.usage();
+                tracing::debug!(
+                    process_memory=%self.process_physical_memory_bytes,
+                    largest_accounts=?&usage[..usage.len().min(3)],
+                    "Memory usage limit exceeded"
+                );
+            }
             return Err(ConnectionDenied::new(MemoryUsageLimitExceeded {
                 process_physical_memory_bytes: self.process_physical_memory_bytes,
                 max_allowed_bytes: self.max_allowed_bytes,
@@ -178,7 +234,11 @@ impl NetworkBehaviour for Behaviour {
         Ok(dummy::ConnectionHandler)
     }
 
-    fn on_swarm_event(&mut self, _: FromSwarm) {}
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. }) = event {
+            self.accounting.remove_connection(connection_id);
+        }
+    }
 
     fn on_connection_handler_event(
         &mut self,
@@ -189,7 +249,34 @@ impl NetworkBehaviour for Behaviour {
         void::unreachable(event)
     }
 
-    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        let Some(soft_max_bytes) = self.soft_max_bytes else {
+            return Poll::Pending;
+        };
+
+        while Pin::new(&mut self.refresh_timer).poll(cx).is_ready() {
+            self.refresh_timer.reset(MAX_STALE_DURATION);
+            self.refresh_memory_stats_if_needed();
+        }
+
+        let exceeded = self.process_physical_memory_bytes > soft_max_bytes;
+        if exceeded != self.inbound_upgrades_paused {
+            self.inbound_upgrades_paused = exceeded;
+            if exceeded {
+                tracing::debug!(
+                    process_memory=%self.process_physical_memory_bytes,
+                    soft_limit=%soft_max_bytes,
+                    "Pausing inbound upgrades"
+                );
+                return Poll::Ready(ToSwarm::PauseInboundUpgrades);
+            }
+            tracing::debug!("Resuming inbound upgrades");
+            return Poll::Ready(ToSwarm::ResumeInboundUpgrades);
+        }
+
         Poll::Pending
     }
 }
@@ -0,0 +1,47 @@
+// Copyright 2023 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_memory_connection_limits::*;
+use std::time::Duration;
+
+use libp2p_swarm::{Swarm, SwarmEvent};
+use libp2p_swarm_test::SwarmExt;
+
+#[async_std::test]
+async fn soft_limit_pauses_inbound_upgrades() {
+    let mut dialer = Swarm::new_ephemeral(|_| Behaviour::with_max_bytes(usize::MAX));
+    // Any memory usage exceeds a soft limit of zero bytes.
+    let mut listener =
+        Swarm::new_ephemeral(|_| Behaviour::with_max_bytes(usize::MAX).with_soft_limit(0.0));
+    let (listen_addr, _) = listener.listen().await;
+
+    dialer.dial(listen_addr).unwrap();
+    async_std::task::spawn(dialer.loop_on_next());
+
+    let incoming = async_std::future::timeout(
+        Duration::from_millis(500),
+        listener.wait(|e| match e {
+            SwarmEvent::IncomingConnection { .. } => Some(()),
+            _ => None,
+        }),
+    )
+    .await;
+    assert!(incoming.is_err(), "inbound upgrade was not paused");
+}
@@ -6,7 +6,7 @@ use futures::{
     ready,
 };
 use libp2p_core::{
-    muxing::{StreamMuxer, StreamMuxerEvent},
+    muxing::{StreamMuxer, StreamMuxerEvent, StreamPriority},
     transport::{ListenerId, TransportError, TransportEvent},
     Multiaddr,
 };
@@ -231,6 +231,20 @@ where
         Poll::Ready(Ok(logged))
     }
 
+    fn poll_outbound_with_priority(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        priority: StreamPriority,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.project();
+        let inner = ready!(this.inner.poll_outbound_with_priority(cx, priority)?);
+        let logged = InstrumentedStream {
+            inner,
+            metrics: this.metrics.clone(),
+        };
+        Poll::Ready(Ok(logged))
+    }
+
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         let this = self.project();
         this.inner.poll_close(cx)
@@ -56,14 +56,10 @@ enum EventType {
 impl From<&libp2p_dcutr::Event> for EventType {
     fn from(event: &libp2p_dcutr::Event) -> Self {
         match event {
-            libp2p_dcutr::Event {
-                remote_peer_id: _,
-                result: Ok(_),
-            } => EventType::DirectConnectionUpgradeSucceeded,
-            libp2p_dcutr::Event {
-                remote_peer_id: _,
-                result: Err(_),
-            } => EventType::DirectConnectionUpgradeFailed,
+            libp2p_dcutr::Event { result: Ok(_), .. } => {
+                EventType::DirectConnectionUpgradeSucceeded
+            }
+            libp2p_dcutr::Event { result: Err(_), .. } => EventType::DirectConnectionUpgradeFailed,
         }
     }
 }
@@ -53,6 +53,7 @@ pub(crate) struct Metrics {
     pushed: Counter,
     received: Counter,
     sent: Counter,
+    version_policy_violations: Counter,
 }
 
 impl Metrics {
@@ -93,12 +94,21 @@ impl Metrics {
             sent.clone(),
         );
 
+        let version_policy_violations = Counter::default();
+        sub_registry.register(
+            "version_policy_violations",
+            "Number of times identification information received from a peer \
+             violated the version policy",
+            version_policy_violations.clone(),
+        );
+
         Self {
             peers,
             error,
             pushed,
             received,
             sent,
+            version_policy_violations,
         }
     }
 }
@@ -119,6 +129,9 @@ impl super::Recorder<libp2p_identify::Event> for Metrics {
             libp2p_identify::Event::Sent { .. } => {
                 self.sent.inc();
             }
+            libp2p_identify::Event::VersionPolicyViolated { .. } => {
+                self.version_policy_violations.inc();
+            }
         }
     }
 }
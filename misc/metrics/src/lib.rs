@@ -38,6 +38,7 @@ mod identify;
 mod kad;
 #[cfg(feature = "ping")]
 mod ping;
+mod protocol_bandwidth;
 mod protocol_stack;
 #[cfg(feature = "relay")]
 mod relay;
@@ -45,6 +46,7 @@ mod swarm;
 
 pub use bandwidth::Transport as BandwidthTransport;
 pub use prometheus_client::registry::Registry;
+pub use protocol_bandwidth::{PeerAgents, Transport as ProtocolBandwidthTransport};
 
 /// Set of Swarm and protocol metrics derived from emitted events.
 pub struct Metrics {
@@ -140,6 +142,13 @@ impl Recorder<libp2p_relay::Event> for Metrics {
     }
 }
 
+#[cfg(feature = "relay")]
+impl Recorder<libp2p_relay::client::Event> for Metrics {
+    fn record(&self, event: &libp2p_relay::client::Event) {
+        self.relay.record(event)
+    }
+}
+
 impl<TBvEv> Recorder<libp2p_swarm::SwarmEvent<TBvEv>> for Metrics {
     fn record(&self, event: &libp2p_swarm::SwarmEvent<TBvEv>) {
         self.swarm.record(event);
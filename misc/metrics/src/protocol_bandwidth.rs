@@ -0,0 +1,772 @@
+use futures::{
+    future::{MapOk, TryFutureExt},
+    io::{IoSlice, IoSliceMut},
+    prelude::*,
+    ready,
+};
+use libp2p_core::{
+    muxing::{StreamMuxer, StreamMuxerEvent, StreamPriority},
+    transport::{ListenerId, TransportError, TransportEvent},
+    Multiaddr,
+};
+use libp2p_identity::PeerId;
+use prometheus_client::{
+    encoding::{EncodeLabelSet, EncodeLabelValue},
+    metrics::{counter::Counter, family::Family, gauge::Gauge},
+    registry::{Registry, Unit},
+};
+use std::{
+    collections::HashMap,
+    convert::TryFrom as _,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+/// The multistream-select header line, without the trailing newline.
+const MULTISTREAM_HEADER: &[u8] = b"/multistream/1.0.0";
+/// The multistream-select message rejecting a protocol, without the trailing newline.
+const MULTISTREAM_NA: &[u8] = b"na";
+/// The maximum number of bytes buffered while looking for the negotiated protocol of a stream.
+const MAX_NEGOTIATION_LEN: usize = 1024;
+/// The protocol label of streams whose negotiated protocol could not be determined.
+const UNKNOWN_PROTOCOL: &str = "unknown";
+
+/// Wraps around a [`libp2p_core::Transport`] and counts the bytes sent and received on each
+/// stream, labeled by the protocol negotiated on the stream and the agent of the remote peer.
+///
+/// The negotiated protocol is determined by observing the multistream-select negotiation at the
+/// start of each stream. Bytes of the negotiation itself are attributed to the negotiated
+/// protocol as well.
+///
+/// The agent of a remote is unknown until recorded via [`PeerAgents`], see
+/// [`Transport::peer_agents`].
+///
+/// The number of open streams is recorded per negotiated protocol and the direction the stream
+/// was opened in, e.g. to detect streams that are never closed.
+#[derive(Debug, Clone)]
+#[pin_project::pin_project]
+pub struct Transport<T> {
+    #[pin]
+    transport: T,
+    metrics: Metrics,
+    peer_agents: PeerAgents,
+}
+
+impl<T> Transport<T> {
+    pub fn new(transport: T, registry: &mut Registry) -> Self {
+        let sub_registry = registry.sub_registry_with_prefix("libp2p");
+
+        let bandwidth = Family::<Labels, Counter>::default();
+        sub_registry.register_with_unit(
+            "protocol_bandwidth",
+            "Bandwidth usage by negotiated stream protocol, direction and agent of the remote",
+            Unit::Bytes,
+            bandwidth.clone(),
+        );
+
+        let open_streams = Family::<StreamLabels, Gauge>::default();
+        sub_registry.register(
+            "protocol_streams_open",
+            "Number of open streams by negotiated stream protocol and direction they were opened in",
+            open_streams.clone(),
+        );
+
+        Transport {
+            transport,
+            metrics: Metrics {
+                bandwidth,
+                open_streams,
+            },
+            peer_agents: PeerAgents::default(),
+        }
+    }
+
+    /// Returns the handle to record the agents of remote peers with.
+    ///
+    /// Until the agent of a peer is recorded, its streams are labeled with the agent `unknown`.
+    pub fn peer_agents(&self) -> PeerAgents {
+        self.peer_agents.clone()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Metrics {
+    bandwidth: Family<Labels, Counter>,
+    open_streams: Family<StreamLabels, Gauge>,
+}
+
+fn wrap<M>(
+    metrics: &Metrics,
+    peer_agents: &PeerAgents,
+) -> Box<dyn FnOnce((PeerId, M)) -> (PeerId, Muxer<M>) + Send> {
+    let metrics = metrics.clone();
+    let peer_agents = peer_agents.clone();
+    Box::new(move |(peer_id, stream_muxer)| {
+        let connection = Connection::new(peer_id, metrics, peer_agents);
+        (peer_id, Muxer::new(stream_muxer, connection))
+    })
+}
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct Labels {
+    protocol: String,
+    direction: Direction,
+    agent: String,
+}
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct StreamLabels {
+    protocol: String,
+    direction: Direction,
+}
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelValue, Debug)]
+enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl<T, M> libp2p_core::Transport for Transport<T>
+where
+    T: libp2p_core::Transport<Output = (PeerId, M)>,
+    M: StreamMuxer + Send + 'static,
+    M::Substream: Send + 'static,
+    M::Error: Send + Sync + 'static,
+{
+    type Output = (PeerId, Muxer<M>);
+    type Error = T::Error;
+    type ListenerUpgrade =
+        MapOk<T::ListenerUpgrade, Box<dyn FnOnce((PeerId, M)) -> (PeerId, Muxer<M>) + Send>>;
+    type Dial = MapOk<T::Dial, Box<dyn FnOnce((PeerId, M)) -> (PeerId, Muxer<M>) + Send>>;
+
+    fn listen_on(
+        &mut self,
+        id: ListenerId,
+        addr: Multiaddr,
+    ) -> Result<(), TransportError<Self::Error>> {
+        self.transport.listen_on(id, addr)
+    }
+
+    fn remove_listener(&mut self, id: ListenerId) -> bool {
+        self.transport.remove_listener(id)
+    }
+
+    fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        Ok(self
+            .transport
+            .dial(addr)?
+            .map_ok(wrap(&self.metrics, &self.peer_agents)))
+    }
+
+    fn dial_as_listener(
+        &mut self,
+        addr: Multiaddr,
+    ) -> Result<Self::Dial, TransportError<Self::Error>> {
+        Ok(self
+            .transport
+            .dial_as_listener(addr)?
+            .map_ok(wrap(&self.metrics, &self.peer_agents)))
+    }
+
+    fn address_translation(&self, server: &Multiaddr, observed: &Multiaddr) -> Option<Multiaddr> {
+        self.transport.address_translation(server, observed)
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+        let this = self.project();
+        match this.transport.poll(cx) {
+            Poll::Ready(TransportEvent::Incoming {
+                listener_id,
+                upgrade,
+                local_addr,
+                send_back_addr,
+            }) => Poll::Ready(TransportEvent::Incoming {
+                listener_id,
+                upgrade: upgrade.map_ok(wrap(this.metrics, this.peer_agents)),
+                local_addr,
+                send_back_addr,
+            }),
+            Poll::Ready(other) => {
+                let mapped = other.map_upgrade(|_upgrade| unreachable!("case already matched"));
+                Poll::Ready(mapped)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Handle to record the agents of remote peers, as e.g. reported by the identify protocol.
+///
+/// Agents are grouped into a small set of buckets, e.g. `rust-libp2p` or `go-libp2p`, to bound
+/// the number of label values. Agents are forgotten once the last connection to the peer is
+/// closed.
+#[derive(Debug, Clone, Default)]
+pub struct PeerAgents(Arc<Mutex<HashMap<PeerId, PeerAgent>>>);
+
+#[derive(Debug, Default)]
+struct PeerAgent {
+    connections: usize,
+    bucket: Option<&'static str>,
+}
+
+impl PeerAgents {
+    /// Records the agent version of a connected peer.
+    ///
+    /// Only affects streams whose protocol is negotiated after the agent was recorded.
+    /// Has no effect if the peer is not connected.
+    pub fn record(&self, peer_id: PeerId, agent_version: &str) {
+        if let Some(agent) = self.0.lock().unwrap().get_mut(&peer_id) {
+            agent.bucket = Some(agent_bucket(agent_version));
+        }
+    }
+
+    fn bucket(&self, peer_id: &PeerId) -> &'static str {
+        self.0
+            .lock()
+            .unwrap()
+            .get(peer_id)
+            .and_then(|agent| agent.bucket)
+            .unwrap_or("unknown")
+    }
+
+    fn connection_established(&self, peer_id: PeerId) {
+        self.0
+            .lock()
+            .unwrap()
+            .entry(peer_id)
+            .or_default()
+            .connections += 1;
+    }
+
+    fn connection_closed(&self, peer_id: &PeerId) {
+        let mut agents = self.0.lock().unwrap();
+        if let Some(agent) = agents.get_mut(peer_id) {
+            agent.connections -= 1;
+            if agent.connections == 0 {
+                agents.remove(peer_id);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "identify")]
+impl super::Recorder<libp2p_identify::Event> for PeerAgents {
+    fn record(&self, event: &libp2p_identify::Event) {
+        if let libp2p_identify::Event::Received { peer_id, info, .. } = event {
+            PeerAgents::record(self, *peer_id, &info.agent_version);
+        }
+    }
+}
+
+/// Maps an agent version to the libp2p implementation it is based on.
+fn agent_bucket(agent_version: &str) -> &'static str {
+    let agent_version = agent_version.to_lowercase();
+    if agent_version.contains("rust-libp2p") {
+        "rust-libp2p"
+    } else if agent_version.contains("go-libp2p")
+        || agent_version.starts_with("kubo")
+        || agent_version.starts_with("go-ipfs")
+    {
+        "go-libp2p"
+    } else if agent_version.contains("js-libp2p") || agent_version.starts_with("helia") {
+        "js-libp2p"
+    } else if agent_version.contains("nim-libp2p") {
+        "nim-libp2p"
+    } else {
+        "other"
+    }
+}
+
+/// The state shared by all streams of a connection.
+#[derive(Debug)]
+struct Connection {
+    peer_id: PeerId,
+    metrics: Metrics,
+    peer_agents: PeerAgents,
+}
+
+impl Connection {
+    fn new(peer_id: PeerId, metrics: Metrics, peer_agents: PeerAgents) -> Self {
+        peer_agents.connection_established(peer_id);
+        Self {
+            peer_id,
+            metrics,
+            peer_agents,
+        }
+    }
+
+    /// Returns the metrics of a stream opened in `direction` with the negotiated `protocol`,
+    /// counting the stream as open.
+    fn stream_metrics(&self, protocol: String, direction: &Direction) -> StreamMetrics {
+        let agent = self.peer_agents.bucket(&self.peer_id).to_owned();
+
+        // Additional scope to make sure to drop the lock guard from `get_or_create`.
+        let open = {
+            let m = self.metrics.open_streams.get_or_create(&StreamLabels {
+                protocol: protocol.clone(),
+                direction: direction.clone(),
+            });
+            m.inc();
+            m.clone()
+        };
+        // Additional scope to make sure to drop the lock guard from `get_or_create`.
+        let outbound = {
+            let m = self.metrics.bandwidth.get_or_create(&Labels {
+                protocol: protocol.clone(),
+                direction: Direction::Outbound,
+                agent: agent.clone(),
+            });
+            m.clone()
+        };
+        // Additional scope to make sure to drop the lock guard from `get_or_create`.
+        let inbound = {
+            let m = self.metrics.bandwidth.get_or_create(&Labels {
+                protocol,
+                direction: Direction::Inbound,
+                agent,
+            });
+            m.clone()
+        };
+        StreamMetrics {
+            outbound,
+            inbound,
+            open,
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.peer_agents.connection_closed(&self.peer_id);
+    }
+}
+
+/// Wraps around a [`StreamMuxer`] and counts the number of bytes that go through all the opened
+/// streams, per negotiated protocol.
+#[pin_project::pin_project]
+pub struct Muxer<SMInner> {
+    #[pin]
+    inner: SMInner,
+    connection: Arc<Connection>,
+}
+
+impl<SMInner> Muxer<SMInner> {
+    fn new(inner: SMInner, connection: Connection) -> Self {
+        Self {
+            inner,
+            connection: Arc::new(connection),
+        }
+    }
+}
+
+impl<SMInner> StreamMuxer for Muxer<SMInner>
+where
+    SMInner: StreamMuxer,
+{
+    type Substream = InstrumentedStream<SMInner::Substream>;
+    type Error = SMInner::Error;
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+        let this = self.project();
+        this.inner.poll(cx)
+    }
+
+    fn poll_inbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.project();
+        let inner = ready!(this.inner.poll_inbound(cx)?);
+        Poll::Ready(Ok(InstrumentedStream::new(
+            inner,
+            Direction::Inbound,
+            this.connection.clone(),
+        )))
+    }
+
+    fn poll_outbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.project();
+        let inner = ready!(this.inner.poll_outbound(cx)?);
+        Poll::Ready(Ok(InstrumentedStream::new(
+            inner,
+            Direction::Outbound,
+            this.connection.clone(),
+        )))
+    }
+
+    fn poll_outbound_with_priority(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        priority: StreamPriority,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.project();
+        let inner = ready!(this.inner.poll_outbound_with_priority(cx, priority)?);
+        Poll::Ready(Ok(InstrumentedStream::new(
+            inner,
+            Direction::Outbound,
+            this.connection.clone(),
+        )))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.project();
+        this.inner.poll_close(cx)
+    }
+}
+
+#[derive(Debug)]
+struct StreamMetrics {
+    outbound: Counter,
+    inbound: Counter,
+    /// The number of open streams of the protocol, decremented once the stream is dropped.
+    open: Gauge,
+}
+
+#[derive(Debug)]
+enum StreamState {
+    /// The protocol of the stream is still being negotiated.
+    Negotiating {
+        negotiation: Negotiation,
+        inbound: u64,
+        outbound: u64,
+    },
+    /// The protocol of the stream is known, or could not be determined.
+    Negotiated(StreamMetrics),
+}
+
+/// Wraps around an [`AsyncRead`] + [`AsyncWrite`] and logs the bandwidth that goes through it,
+/// once the negotiated protocol of the stream is known.
+#[pin_project::pin_project(PinnedDrop)]
+pub struct InstrumentedStream<SMInner> {
+    #[pin]
+    inner: SMInner,
+    /// Whether the stream was opened by the remote or by us.
+    ///
+    /// The listening side of the negotiation confirms the protocol, thus the negotiated protocol
+    /// is found in the bytes we write on inbound streams and in the bytes we read on outbound
+    /// streams.
+    direction: Direction,
+    connection: Arc<Connection>,
+    state: StreamState,
+}
+
+impl<SMInner> InstrumentedStream<SMInner> {
+    fn new(inner: SMInner, direction: Direction, connection: Arc<Connection>) -> Self {
+        Self {
+            inner,
+            direction,
+            connection,
+            state: StreamState::Negotiating {
+                negotiation: Negotiation::default(),
+                inbound: 0,
+                outbound: 0,
+            },
+        }
+    }
+}
+
+#[pin_project::pinned_drop]
+impl<SMInner> PinnedDrop for InstrumentedStream<SMInner> {
+    fn drop(self: Pin<&mut Self>) {
+        let this = self.project();
+        // Attribute the bytes of an unfinished negotiation to the unknown protocol.
+        if let StreamState::Negotiating { .. } = this.state {
+            record_bytes(
+                this.state,
+                this.connection,
+                this.direction,
+                Direction::Inbound,
+                Some(Err(())),
+                0,
+            );
+        }
+        if let StreamState::Negotiated(metrics) = this.state {
+            metrics.open.dec();
+        }
+    }
+}
+
+/// Records `num_bytes` in the given direction, resolving the protocol of the stream opened in
+/// `stream_direction` first if `negotiated` holds the outcome of the negotiation.
+fn record_bytes(
+    state: &mut StreamState,
+    connection: &Connection,
+    stream_direction: &Direction,
+    direction: Direction,
+    negotiated: Option<Result<String, ()>>,
+    num_bytes: usize,
+) {
+    let num_bytes = u64::try_from(num_bytes).unwrap_or(u64::MAX);
+
+    if let StreamState::Negotiating {
+        inbound, outbound, ..
+    } = state
+    {
+        match direction {
+            Direction::Inbound => *inbound = inbound.saturating_add(num_bytes),
+            Direction::Outbound => *outbound = outbound.saturating_add(num_bytes),
+        }
+        let Some(negotiated) = negotiated else {
+            return;
+        };
+        let metrics = connection.stream_metrics(
+            negotiated.unwrap_or_else(|()| UNKNOWN_PROTOCOL.to_owned()),
+            stream_direction,
+        );
+        metrics.inbound.inc_by(*inbound);
+        metrics.outbound.inc_by(*outbound);
+        *state = StreamState::Negotiated(metrics);
+        return;
+    }
+
+    if let StreamState::Negotiated(metrics) = state {
+        match direction {
+            Direction::Inbound => metrics.inbound.inc_by(num_bytes),
+            Direction::Outbound => metrics.outbound.inc_by(num_bytes),
+        };
+    }
+}
+
+impl<SMInner> InstrumentedStream<SMInner> {
+    /// Records bytes read from or written to the stream.
+    fn record<'a>(
+        self: Pin<&mut Self>,
+        direction: Direction,
+        bufs: impl Iterator<Item = &'a [u8]>,
+        num_bytes: usize,
+    ) {
+        let this = self.project();
+        let negotiated = match this.state {
+            StreamState::Negotiating { negotiation, .. } if direction != *this.direction => {
+                let mut remaining = num_bytes;
+                let mut negotiated = None;
+                for buf in bufs {
+                    if remaining == 0 || negotiated.is_some() {
+                        break;
+                    }
+                    let len = buf.len().min(remaining);
+                    negotiated = negotiation.feed(&buf[..len]);
+                    remaining -= len;
+                }
+                negotiated
+            }
+            _ => None,
+        };
+        record_bytes(
+            this.state,
+            this.connection,
+            this.direction,
+            direction,
+            negotiated,
+            num_bytes,
+        );
+    }
+}
+
+impl<SMInner: AsyncRead> AsyncRead for InstrumentedStream<SMInner> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let num_bytes = ready!(self.as_mut().project().inner.poll_read(cx, buf))?;
+        self.record(Direction::Inbound, std::iter::once(&buf[..]), num_bytes);
+        Poll::Ready(Ok(num_bytes))
+    }
+
+    fn poll_read_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let num_bytes = ready!(self.as_mut().project().inner.poll_read_vectored(cx, bufs))?;
+        self.record(Direction::Inbound, bufs.iter().map(|b| &b[..]), num_bytes);
+        Poll::Ready(Ok(num_bytes))
+    }
+}
+
+impl<SMInner: AsyncWrite> AsyncWrite for InstrumentedStream<SMInner> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let num_bytes = ready!(self.as_mut().project().inner.poll_write(cx, buf))?;
+        self.record(Direction::Outbound, std::iter::once(buf), num_bytes);
+        Poll::Ready(Ok(num_bytes))
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let num_bytes = ready!(self.as_mut().project().inner.poll_write_vectored(cx, bufs))?;
+        self.record(Direction::Outbound, bufs.iter().map(|b| &b[..]), num_bytes);
+        Poll::Ready(Ok(num_bytes))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.project();
+        this.inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.project();
+        this.inner.poll_close(cx)
+    }
+}
+
+/// Parses the multistream-select messages sent by the listening side of a stream.
+///
+/// The first message that is neither the multistream-select header nor a rejection confirms the
+/// negotiated protocol.
+#[derive(Debug, Default)]
+struct Negotiation {
+    buffer: Vec<u8>,
+}
+
+impl Negotiation {
+    /// Feeds the next bytes of the stream, returning the outcome of the negotiation once known.
+    fn feed(&mut self, bytes: &[u8]) -> Option<Result<String, ()>> {
+        self.buffer.extend_from_slice(bytes);
+
+        loop {
+            let Some((len, prefix)) = decode_uvarint(&self.buffer) else {
+                // Lengths of multistream-select messages take at most two bytes.
+                return (self.buffer.len() >= 3).then_some(Err(()));
+            };
+            let Some(message) = self.buffer.get(prefix..prefix + len) else {
+                return self.give_up_if_too_long();
+            };
+            let Some(message) = message.strip_suffix(b"\n") else {
+                return Some(Err(()));
+            };
+
+            if message == MULTISTREAM_HEADER || message == MULTISTREAM_NA {
+                self.buffer.drain(..prefix + len);
+                continue;
+            }
+
+            if !message.starts_with(b"/") {
+                return Some(Err(()));
+            }
+
+            return Some(String::from_utf8(message.to_vec()).map_err(|_| ()));
+        }
+    }
+
+    fn give_up_if_too_long(&self) -> Option<Result<String, ()>> {
+        (self.buffer.len() > MAX_NEGOTIATION_LEN).then_some(Err(()))
+    }
+}
+
+/// Decodes an unsigned varint of at most three bytes, returning its value and encoded length.
+fn decode_uvarint(bytes: &[u8]) -> Option<(usize, usize)> {
+    let mut value = 0usize;
+    for (i, byte) in bytes.iter().take(3).enumerate() {
+        value |= usize::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(msg: &str) -> Vec<u8> {
+        let mut bytes = vec![u8::try_from(msg.len() + 1).unwrap()];
+        bytes.extend_from_slice(msg.as_bytes());
+        bytes.push(b'\n');
+        bytes
+    }
+
+    #[test]
+    fn negotiation_skips_header_and_rejections() {
+        let mut bytes = message("/multistream/1.0.0");
+        bytes.extend(message("na"));
+        bytes.extend(message("/ipfs/ping/1.0.0"));
+        bytes.extend_from_slice(&[1, 2, 3]);
+
+        let mut negotiation = Negotiation::default();
+        let (first, second) = bytes.split_at(10);
+
+        assert_eq!(negotiation.feed(first), None);
+        assert_eq!(
+            negotiation.feed(second),
+            Some(Ok("/ipfs/ping/1.0.0".to_owned()))
+        );
+    }
+
+    #[test]
+    fn negotiation_gives_up_on_unexpected_messages() {
+        let mut bytes = message("/multistream/1.0.0");
+        bytes.extend(message("ls"));
+
+        assert_eq!(Negotiation::default().feed(&bytes), Some(Err(())));
+        assert_eq!(Negotiation::default().feed(&[0xff; 4]), Some(Err(())));
+    }
+
+    #[test]
+    fn open_streams_are_counted_per_protocol() {
+        let metrics = Metrics {
+            bandwidth: Family::default(),
+            open_streams: Family::default(),
+        };
+        let connection = Arc::new(Connection::new(
+            PeerId::random(),
+            metrics.clone(),
+            PeerAgents::default(),
+        ));
+        let open = |direction| {
+            metrics
+                .open_streams
+                .get_or_create(&StreamLabels {
+                    protocol: "/ipfs/ping/1.0.0".to_owned(),
+                    direction,
+                })
+                .get()
+        };
+
+        let mut bytes = message("/multistream/1.0.0");
+        bytes.extend(message("/ipfs/ping/1.0.0"));
+        let mut stream = InstrumentedStream::new(
+            futures::io::Cursor::new(bytes),
+            Direction::Outbound,
+            connection,
+        );
+        futures::executor::block_on(stream.read_to_end(&mut Vec::new())).unwrap();
+
+        assert_eq!(open(Direction::Outbound), 1);
+        assert_eq!(open(Direction::Inbound), 0);
+
+        drop(stream);
+        assert_eq!(open(Direction::Outbound), 0);
+    }
+
+    #[test]
+    fn agent_versions_are_bucketed() {
+        assert_eq!(agent_bucket("rust-libp2p/0.53.0"), "rust-libp2p");
+        assert_eq!(agent_bucket("github.com/libp2p/go-libp2p"), "go-libp2p");
+        assert_eq!(agent_bucket("kubo/0.26.0/"), "go-libp2p");
+        assert_eq!(
+            agent_bucket("js-libp2p/1.2.0 UserAgent=v20.11.0"),
+            "js-libp2p"
+        );
+        assert_eq!(agent_bucket("lighthouse/v4.6.0"), "other");
+    }
+}
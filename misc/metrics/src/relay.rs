@@ -25,6 +25,7 @@ use prometheus_client::registry::Registry;
 
 pub(crate) struct Metrics {
     events: Family<EventLabels, Counter>,
+    client_events: Family<ClientEventLabels, Counter>,
 }
 
 impl Metrics {
@@ -38,7 +39,17 @@ impl Metrics {
             events.clone(),
         );
 
-        Self { events }
+        let client_events = Family::default();
+        sub_registry.register(
+            "client_events",
+            "Events emitted by the relay client NetworkBehaviour",
+            client_events.clone(),
+        );
+
+        Self {
+            events,
+            client_events,
+        }
     }
 }
 
@@ -59,6 +70,7 @@ enum EventType {
     CircuitReqOutboundConnectFailed,
     CircuitReqAccepted,
     CircuitReqAcceptFailed,
+    CircuitBytesRelayed,
     CircuitClosed,
 }
 
@@ -86,6 +98,7 @@ impl From<&libp2p_relay::Event> for EventType {
             libp2p_relay::Event::CircuitReqAccepted { .. } => EventType::CircuitReqAccepted,
             #[allow(deprecated)]
             libp2p_relay::Event::CircuitReqAcceptFailed { .. } => EventType::CircuitReqAcceptFailed,
+            libp2p_relay::Event::CircuitBytesRelayed { .. } => EventType::CircuitBytesRelayed,
             libp2p_relay::Event::CircuitClosed { .. } => EventType::CircuitClosed,
         }
     }
@@ -100,3 +113,45 @@ impl super::Recorder<libp2p_relay::Event> for Metrics {
             .inc();
     }
 }
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ClientEventLabels {
+    event: ClientEventType,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum ClientEventType {
+    ReservationReqAccepted,
+    OutboundCircuitEstablished,
+    InboundCircuitEstablished,
+    InboundCircuitDenied,
+}
+
+impl From<&libp2p_relay::client::Event> for ClientEventType {
+    fn from(event: &libp2p_relay::client::Event) -> Self {
+        match event {
+            libp2p_relay::client::Event::ReservationReqAccepted { .. } => {
+                ClientEventType::ReservationReqAccepted
+            }
+            libp2p_relay::client::Event::OutboundCircuitEstablished { .. } => {
+                ClientEventType::OutboundCircuitEstablished
+            }
+            libp2p_relay::client::Event::InboundCircuitEstablished { .. } => {
+                ClientEventType::InboundCircuitEstablished
+            }
+            libp2p_relay::client::Event::InboundCircuitDenied { .. } => {
+                ClientEventType::InboundCircuitDenied
+            }
+        }
+    }
+}
+
+impl super::Recorder<libp2p_relay::client::Event> for Metrics {
+    fn record(&self, event: &libp2p_relay::client::Event) {
+        self.client_events
+            .get_or_create(&ClientEventLabels {
+                event: event.into(),
+            })
+            .inc();
+    }
+}
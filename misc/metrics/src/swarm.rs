@@ -23,6 +23,8 @@ use std::sync::{Arc, Mutex};
 
 use crate::protocol_stack;
 use instant::Instant;
+use libp2p_core::Endpoint;
+use libp2p_identity::PeerId;
 use libp2p_swarm::{ConnectionId, DialError, SwarmEvent};
 use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue};
 use prometheus_client::metrics::counter::Counter;
@@ -37,6 +39,7 @@ pub(crate) struct Metrics {
     connections_established: Family<ConnectionLabels, Counter>,
     connections_establishment_duration: Family<ConnectionLabels, Histogram>,
     connections_duration: Family<ConnectionClosedLabels, Histogram>,
+    peers_connected_duration: Family<RoleLabels, Histogram>,
 
     new_listen_addr: Family<AddressLabels, Counter>,
     expired_listen_addr: Family<AddressLabels, Counter>,
@@ -51,7 +54,13 @@ pub(crate) struct Metrics {
     dial_attempt: Counter,
     outgoing_connection_error: Family<OutgoingConnectionErrorLabels, Counter>,
 
+    stream_negotiation_duration: Family<StreamNegotiationLabels, Histogram>,
+    stream_negotiation_rejected_protocols: Family<RoleLabels, Counter>,
+    stream_negotiation_ls_requests: Counter,
+
     connections: Arc<Mutex<HashMap<ConnectionId, Instant>>>,
+    /// The time the first of the current connections to a peer was established, and its role.
+    peers: Arc<Mutex<HashMap<PeerId, (Instant, Role)>>>,
 }
 
 impl Metrics {
@@ -165,6 +174,44 @@ impl Metrics {
             connections_duration.clone(),
         );
 
+        let peers_connected_duration = {
+            let constructor: fn() -> Histogram =
+                || Histogram::new(exponential_buckets(0.01, 3.0, 20));
+            Family::new_with_constructor(constructor)
+        };
+        sub_registry.register_with_unit(
+            "peers_connected_duration",
+            "Time a peer was connected via at least one connection, by role of the first connection",
+            Unit::Seconds,
+            peers_connected_duration.clone(),
+        );
+
+        let stream_negotiation_duration = {
+            let constructor: fn() -> Histogram =
+                || Histogram::new(exponential_buckets(0.001, 2.0, 15));
+            Family::new_with_constructor(constructor)
+        };
+        sub_registry.register_with_unit(
+            "stream_negotiation_duration",
+            "Time it took to negotiate the protocol of a stream",
+            Unit::Seconds,
+            stream_negotiation_duration.clone(),
+        );
+
+        let stream_negotiation_rejected_protocols = Family::default();
+        sub_registry.register(
+            "stream_negotiation_rejected_protocols",
+            "Number of protocols rejected during stream negotiations, i.e. of fallbacks to the next protocol",
+            stream_negotiation_rejected_protocols.clone(),
+        );
+
+        let stream_negotiation_ls_requests = Counter::default();
+        sub_registry.register(
+            "stream_negotiation_ls_requests",
+            "Number of `ls` requests for the supported protocols received during stream negotiations",
+            stream_negotiation_ls_requests.clone(),
+        );
+
         Self {
             connections_incoming,
             connections_incoming_error,
@@ -180,7 +227,12 @@ impl Metrics {
             outgoing_connection_error,
             connections_establishment_duration,
             connections_duration,
+            peers_connected_duration,
+            stream_negotiation_duration,
+            stream_negotiation_rejected_protocols,
+            stream_negotiation_ls_requests,
             connections: Default::default(),
+            peers: Default::default(),
         }
     }
 }
@@ -190,9 +242,11 @@ impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
         match event {
             SwarmEvent::Behaviour(_) => {}
             SwarmEvent::ConnectionEstablished {
+                peer_id,
                 endpoint,
                 established_in: time_taken,
                 connection_id,
+                num_established,
                 ..
             } => {
                 let labels = ConnectionLabels {
@@ -207,11 +261,19 @@ impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
                     .lock()
                     .expect("lock not to be poisoned")
                     .insert(*connection_id, Instant::now());
+                if num_established.get() == 1 {
+                    self.peers
+                        .lock()
+                        .expect("lock not to be poisoned")
+                        .insert(*peer_id, (Instant::now(), endpoint.into()));
+                }
             }
             SwarmEvent::ConnectionClosed {
+                peer_id,
                 endpoint,
                 connection_id,
                 cause,
+                num_established,
                 ..
             } => {
                 let labels = ConnectionClosedLabels {
@@ -230,6 +292,18 @@ impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
                         .elapsed()
                         .as_secs_f64(),
                 );
+                if *num_established == 0 {
+                    if let Some((connected, role)) = self
+                        .peers
+                        .lock()
+                        .expect("lock not to be poisoned")
+                        .remove(peer_id)
+                    {
+                        self.peers_connected_duration
+                            .get_or_create(&RoleLabels { role })
+                            .observe(connected.elapsed().as_secs_f64());
+                    }
+                }
             }
             SwarmEvent::IncomingConnection { send_back_addr, .. } => {
                 self.connections_incoming
@@ -287,6 +361,35 @@ impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
                     DialError::Denied { .. } => record(OutgoingConnectionError::Denied),
                 };
             }
+            SwarmEvent::StreamNegotiated { negotiation, .. } => {
+                let role = match negotiation.endpoint {
+                    Endpoint::Dialer => Role::Dialer,
+                    Endpoint::Listener => Role::Listener,
+                };
+                let (outcome, protocol) = match &negotiation.outcome {
+                    libp2p_swarm::NegotiationOutcome::Negotiated(protocol) => {
+                        (NegotiationOutcome::Negotiated, Some(protocol.clone()))
+                    }
+                    libp2p_swarm::NegotiationOutcome::Failed => (NegotiationOutcome::Failed, None),
+                    libp2p_swarm::NegotiationOutcome::Timeout => {
+                        (NegotiationOutcome::Timeout, None)
+                    }
+                    libp2p_swarm::NegotiationOutcome::Error => (NegotiationOutcome::Error, None),
+                };
+
+                self.stream_negotiation_duration
+                    .get_or_create(&StreamNegotiationLabels {
+                        role: role.clone(),
+                        outcome,
+                        protocol,
+                    })
+                    .observe(negotiation.duration.as_secs_f64());
+                self.stream_negotiation_rejected_protocols
+                    .get_or_create(&RoleLabels { role })
+                    .inc_by(negotiation.rejected_protocols as u64);
+                self.stream_negotiation_ls_requests
+                    .inc_by(negotiation.ls_requests as u64);
+            }
             SwarmEvent::NewListenAddr { address, .. } => {
                 self.new_listen_addr
                     .get_or_create(&AddressLabels {
@@ -370,6 +473,27 @@ impl From<&libp2p_swarm::ConnectionError> for ConnectionError {
     }
 }
 
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct StreamNegotiationLabels {
+    role: Role,
+    outcome: NegotiationOutcome,
+    /// The negotiated protocol, if any.
+    protocol: Option<String>,
+}
+
+#[derive(EncodeLabelValue, Hash, Clone, Eq, PartialEq, Debug)]
+enum NegotiationOutcome {
+    Negotiated,
+    Failed,
+    Timeout,
+    Error,
+}
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct RoleLabels {
+    role: Role,
+}
+
 #[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
 struct AddressLabels {
     protocols: String,
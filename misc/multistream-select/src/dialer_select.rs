@@ -21,7 +21,7 @@
 //! Protocol negotiation strategies for the peer acting as the dialer.
 
 use crate::protocol::{HeaderLine, Message, MessageIO, Protocol, ProtocolError};
-use crate::{Negotiated, NegotiationError, Version};
+use crate::{Negotiated, NegotiationError, NegotiationStats, Version};
 
 use futures::prelude::*;
 use std::{
@@ -61,6 +61,7 @@ where
         state: State::SendHeader {
             io: MessageIO::new(inner),
         },
+        stats: NegotiationStats::default(),
     }
 }
 
@@ -72,6 +73,14 @@ pub struct DialerSelectFuture<R, I: Iterator> {
     protocols: iter::Peekable<I>,
     state: State<R, I::Item>,
     version: Version,
+    stats: NegotiationStats,
+}
+
+impl<R, I: Iterator> DialerSelectFuture<R, I> {
+    /// Returns the statistics of the negotiation so far.
+    pub fn stats(&self) -> NegotiationStats {
+        self.stats
+    }
 }
 
 enum State<R, N> {
@@ -189,6 +198,7 @@ where
                                 protocol=%protocol.as_ref(),
                                 "Dialer: Received rejection of protocol"
                             );
+                            this.stats.rejected_protocols += 1;
                             let protocol = this.protocols.next().ok_or(NegotiationError::Failed)?;
                             *this.state = State::SendProtocol { io, protocol }
                         }
@@ -363,6 +373,36 @@ mod tests {
             .unwrap();
     }
 
+    #[async_std::test]
+    async fn stats_count_rejected_protocols() {
+        let (client_connection, server_connection) = futures_ringbuf::Endpoint::pair(100, 100);
+
+        let server = async_std::task::spawn(async move {
+            let negotiation = listener_select_proto(server_connection, vec!["/proto1", "/proto2"]);
+            futures::pin_mut!(negotiation);
+            let (proto, _io) = negotiation.as_mut().await.unwrap();
+            assert_eq!(proto, "/proto2");
+
+            negotiation.stats()
+        });
+
+        let negotiation = dialer_select_proto(
+            client_connection,
+            vec!["/proto3", "/proto4", "/proto2"],
+            Version::V1,
+        );
+        futures::pin_mut!(negotiation);
+        let (proto, _io) = negotiation.as_mut().await.unwrap();
+        assert_eq!(proto, "/proto2");
+
+        let expected = NegotiationStats {
+            rejected_protocols: 2,
+            ls_requests: 0,
+        };
+        assert_eq!(negotiation.stats(), expected);
+        assert_eq!(server.await, expected);
+    }
+
     #[derive(Clone, Debug)]
     struct DialerProtos(Vec<&'static str>);
 
@@ -98,6 +98,18 @@ pub use self::listener_select::{listener_select_proto, ListenerSelectFuture};
 pub use self::negotiated::{Negotiated, NegotiatedComplete, NegotiationError};
 pub use self::protocol::ProtocolError;
 
+/// Statistics of a protocol negotiation, e.g. for metrics.
+///
+/// See [`DialerSelectFuture::stats`] and [`ListenerSelectFuture::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NegotiationStats {
+    /// The number of protocols proposed by the dialer and rejected by the listener,
+    /// i.e. how often the dialer had to fall back to its next protocol.
+    pub rejected_protocols: usize,
+    /// The number of `ls` requests for the protocols supported by the listener.
+    pub ls_requests: usize,
+}
+
 /// Supported multistream-select versions.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum Version {
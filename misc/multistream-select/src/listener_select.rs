@@ -22,7 +22,7 @@
 //! in a multistream-select protocol negotiation.
 
 use crate::protocol::{HeaderLine, Message, MessageIO, Protocol, ProtocolError};
-use crate::{Negotiated, NegotiationError};
+use crate::{Negotiated, NegotiationError, NegotiationStats};
 
 use futures::prelude::*;
 use smallvec::SmallVec;
@@ -65,6 +65,7 @@ where
             io: MessageIO::new(inner),
         },
         last_sent_na: false,
+        stats: NegotiationStats::default(),
     }
 }
 
@@ -83,6 +84,14 @@ pub struct ListenerSelectFuture<R, N> {
     /// considered failed, but not with a protocol violation or I/O
     /// error.
     last_sent_na: bool,
+    stats: NegotiationStats,
+}
+
+impl<R, N> ListenerSelectFuture<R, N> {
+    /// Returns the statistics of the negotiation so far.
+    pub fn stats(&self) -> NegotiationStats {
+        self.stats
+    }
 }
 
 enum State<R, N> {
@@ -208,6 +217,7 @@ where
 
                     match msg {
                         Message::ListProtocols => {
+                            this.stats.ls_requests += 1;
                             let supported =
                                 this.protocols.iter().map(|(_, p)| p).cloned().collect();
                             let message = Message::Protocols(supported);
@@ -231,6 +241,7 @@ where
                                 Message::Protocol(p.clone())
                             } else {
                                 tracing::debug!(protocol=%p.as_ref(), "Listener: rejecting protocol");
+                                this.stats.rejected_protocols += 1;
                                 Message::NotAvailable
                             };
 
@@ -0,0 +1,743 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+//! An address book of remote peers.
+//!
+//! The [`Behaviour`] collects the addresses of remote peers reported by other behaviours via
+//! [`ToSwarm::NewExternalAddrOfPeer`], e.g. by identify, the addresses of outbound connections
+//! and addresses inserted via [`Behaviour::add_address`]. With the `kad` and `mdns` features,
+//! the addresses discovered by Kademlia and mDNS are added via `Behaviour::on_kademlia_event`
+//! and `Behaviour::on_mdns_event`.
+//!
+//! Every address expires after the time-to-live configured for its [`Source`] and is removed
+//! after repeated dial failures. The addresses of a peer are returned from
+//! [`NetworkBehaviour::handle_pending_outbound_connection`], thus dialing a peer by its
+//! [`PeerId`] alone uses the address book. Addresses of recent connections are dialed first.
+//!
+//! The address book can be kept across restarts via [`Persistence`].
+
+use instant::Instant;
+use libp2p_core::{multiaddr::Protocol, ConnectedPoint, Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::{
+    behaviour::{ConnectionEstablished, DialFailure, NewExternalAddrOfPeer},
+    dummy, ConnectionDenied, ConnectionId, DialError, FromSwarm, NetworkBehaviour, THandler,
+    THandlerInEvent, THandlerOutEvent, Timers, ToSwarm,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    fmt,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+/// Where an address of a peer was learned from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// Inserted via [`Behaviour::add_address`].
+    Manual,
+    /// Reported by a behaviour via [`ToSwarm::NewExternalAddrOfPeer`], e.g. by identify.
+    Swarm,
+    /// Discovered by Kademlia.
+    Kademlia,
+    /// Discovered by mDNS.
+    Mdns,
+    /// The address of an outbound connection to the peer.
+    Connection,
+    /// Loaded from the [`Persistence`] of the address book.
+    Persisted,
+}
+
+/// The configuration of the [`Behaviour`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    ttls: HashMap<Source, Option<Duration>>,
+    max_dial_failures: u32,
+    max_addresses_per_peer: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ttls: HashMap::from([
+                (Source::Manual, None),
+                (Source::Swarm, Some(Duration::from_secs(60 * 60))),
+                (Source::Kademlia, Some(Duration::from_secs(60 * 60))),
+                (Source::Mdns, Some(Duration::from_secs(5 * 60))),
+                (Source::Connection, Some(Duration::from_secs(24 * 60 * 60))),
+                (Source::Persisted, Some(Duration::from_secs(60 * 60))),
+            ]),
+            max_dial_failures: 3,
+            max_addresses_per_peer: 16,
+        }
+    }
+}
+
+impl Config {
+    /// Sets the time after which addresses from `source` expire, unless reported again.
+    ///
+    /// [`None`] keeps the addresses until they are removed otherwise. By default, only manually
+    /// inserted addresses don't expire.
+    pub fn with_ttl(mut self, source: Source, ttl: Option<Duration>) -> Self {
+        self.ttls.insert(source, ttl);
+        self
+    }
+
+    /// Sets the number of consecutive failed dials after which an address is removed.
+    ///
+    /// Defaults to 3.
+    pub fn with_max_dial_failures(mut self, max: u32) -> Self {
+        self.max_dial_failures = max;
+        self
+    }
+
+    /// Sets the number of addresses kept per peer.
+    ///
+    /// Inserting an address beyond the limit evicts the lowest-ranked address of the peer.
+    /// Defaults to 16.
+    pub fn with_max_addresses_per_peer(mut self, max: usize) -> Self {
+        self.max_addresses_per_peer = max;
+        self
+    }
+
+    fn ttl(&self, source: Source) -> Option<Duration> {
+        self.ttls.get(&source).copied().flatten()
+    }
+}
+
+/// What the [`Behaviour`] knows about an address of a peer.
+#[derive(Debug, Clone)]
+pub struct AddressRecord {
+    source: Source,
+    expires: Option<Instant>,
+    dial_failures: u32,
+    last_connected: Option<Instant>,
+}
+
+impl AddressRecord {
+    /// Where the address was last learned from.
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    /// When the address expires, [`None`] if it doesn't.
+    pub fn expires(&self) -> Option<Instant> {
+        self.expires
+    }
+
+    /// The number of failed dials since the address was last connected to.
+    pub fn dial_failures(&self) -> u32 {
+        self.dial_failures
+    }
+
+    /// When an outbound connection to the address was last established.
+    pub fn last_connected(&self) -> Option<Instant> {
+        self.last_connected
+    }
+
+    /// Orders the records from the most to the least promising address to dial.
+    fn rank(&self, other: &Self) -> std::cmp::Ordering {
+        other
+            .last_connected
+            .cmp(&self.last_connected)
+            .then(self.dial_failures.cmp(&other.dial_failures))
+    }
+}
+
+/// Storage of the address book, keeping it across restarts.
+///
+/// Only addresses that are added to or removed from the address book are passed on, refreshing
+/// a known address is not.
+pub trait Persistence: Send + 'static {
+    /// Returns the stored addresses, called once when the [`Behaviour`] is created.
+    fn load(&mut self) -> Vec<(PeerId, Multiaddr)>;
+
+    /// Stores an address added to the address book.
+    fn insert(&mut self, peer: &PeerId, address: &Multiaddr);
+
+    /// Removes an address removed from the address book.
+    fn remove(&mut self, peer: &PeerId, address: &Multiaddr);
+}
+
+/// Events emitted by the [`Behaviour`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A new address of a peer was added.
+    AddressAdded {
+        peer: PeerId,
+        address: Multiaddr,
+        source: Source,
+    },
+    /// An address of a peer was removed.
+    ///
+    /// Not emitted for addresses removed via [`Behaviour::remove_address`] or
+    /// [`Behaviour::remove_peer`].
+    AddressRemoved {
+        peer: PeerId,
+        address: Multiaddr,
+        reason: RemovalReason,
+    },
+}
+
+/// Why an address was removed from the address book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalReason {
+    /// The address was not reported again within the time-to-live of its [`Source`].
+    Expired,
+    /// Dialing the address failed [`Config::with_max_dial_failures`] times in a row.
+    DialFailures,
+    /// The address was evicted by a new address, exceeding
+    /// [`Config::with_max_addresses_per_peer`].
+    Evicted,
+}
+
+/// A [`NetworkBehaviour`] keeping an address book of remote peers.
+///
+/// See the crate-level documentation for details.
+pub struct Behaviour {
+    config: Config,
+    peers: HashMap<PeerId, HashMap<Multiaddr, AddressRecord>>,
+    expirations: Timers<(PeerId, Multiaddr)>,
+    persistence: Option<Box<dyn Persistence>>,
+    events: VecDeque<Event>,
+    waker: Option<Waker>,
+}
+
+impl Behaviour {
+    /// Creates an empty address book.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            peers: HashMap::new(),
+            expirations: Timers::new(),
+            persistence: None,
+            events: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    /// Creates an address book, stored in and loaded from the given [`Persistence`].
+    ///
+    /// The loaded addresses have the [`Source::Persisted`].
+    pub fn with_persistence(config: Config, mut persistence: impl Persistence) -> Self {
+        let mut behaviour = Self::new(config);
+        for (peer, address) in persistence.load() {
+            behaviour.add_address(peer, address, Source::Persisted);
+        }
+        behaviour.events.clear();
+        behaviour.persistence = Some(Box::new(persistence));
+
+        behaviour
+    }
+
+    /// Adds an address of a peer, or refreshes the expiry of a known address.
+    ///
+    /// Addresses ending with `/p2p` of another peer are rejected. Returns whether the address
+    /// is new.
+    pub fn add_address(&mut self, peer: PeerId, address: Multiaddr, source: Source) -> bool {
+        let Some(address) = normalize(&peer, address) else {
+            tracing::debug!(%peer, "Rejecting address of another peer");
+            return false;
+        };
+        let expires = self.config.ttl(source).map(|ttl| Instant::now() + ttl);
+
+        let addresses = self.peers.entry(peer).or_default();
+        match addresses.entry(address.clone()) {
+            Entry::Occupied(mut entry) => {
+                let record = entry.get_mut();
+                let extends = match (record.expires, expires) {
+                    (None, _) => false,
+                    (Some(_), None) => true,
+                    (Some(current), Some(new)) => new > current,
+                };
+                if extends {
+                    record.source = source;
+                    record.expires = expires;
+                    self.set_expiry(peer, address, expires);
+                }
+                false
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(AddressRecord {
+                    source,
+                    expires,
+                    dial_failures: 0,
+                    last_connected: None,
+                });
+                self.set_expiry(peer, address.clone(), expires);
+                if let Some(persistence) = self.persistence.as_mut() {
+                    persistence.insert(&peer, &address);
+                }
+                self.push_event(Event::AddressAdded {
+                    peer,
+                    address: address.clone(),
+                    source,
+                });
+                self.evict_beyond_limit(peer, &address);
+                true
+            }
+        }
+    }
+
+    /// Removes an address of a peer, returning whether it was known.
+    pub fn remove_address(&mut self, peer: &PeerId, address: &Multiaddr) -> bool {
+        let Some(address) = normalize(peer, address.clone()) else {
+            return false;
+        };
+
+        self.remove(*peer, &address).is_some()
+    }
+
+    /// Removes all addresses of a peer, returning whether any were known.
+    pub fn remove_peer(&mut self, peer: &PeerId) -> bool {
+        let Some(addresses) = self.peers.remove(peer) else {
+            return false;
+        };
+        for address in addresses.into_keys() {
+            self.expirations.remove(&(*peer, address.clone()));
+            if let Some(persistence) = self.persistence.as_mut() {
+                persistence.remove(peer, &address);
+            }
+        }
+
+        true
+    }
+
+    /// The addresses of a peer, from the most to the least promising one to dial.
+    pub fn addresses(&self, peer: &PeerId) -> Vec<Multiaddr> {
+        let Some(addresses) = self.peers.get(peer) else {
+            return Vec::new();
+        };
+        let mut records = addresses.iter().collect::<Vec<_>>();
+        records.sort_by(|(_, a), (_, b)| a.rank(b));
+
+        records
+            .into_iter()
+            .map(|(address, _)| address.clone())
+            .collect()
+    }
+
+    /// What is known about an address of a peer.
+    pub fn address_record(&self, peer: &PeerId, address: &Multiaddr) -> Option<&AddressRecord> {
+        self.peers.get(peer)?.get(address)
+    }
+
+    /// The peers with at least one address.
+    pub fn peers(&self) -> impl Iterator<Item = &PeerId> {
+        self.peers.keys()
+    }
+
+    /// Adds the addresses of a peer added to or updated in the Kademlia routing table.
+    #[cfg(feature = "kad")]
+    pub fn on_kademlia_event(&mut self, event: &libp2p_kad::Event) {
+        if let libp2p_kad::Event::RoutingUpdated {
+            peer, addresses, ..
+        } = event
+        {
+            for address in addresses.iter() {
+                self.add_address(*peer, address.clone(), Source::Kademlia);
+            }
+        }
+    }
+
+    /// Adds the addresses discovered by mDNS and removes the expired ones.
+    ///
+    /// Only addresses last learned from mDNS are removed on expiry.
+    #[cfg(feature = "mdns")]
+    pub fn on_mdns_event(&mut self, event: &libp2p_mdns::Event) {
+        match event {
+            libp2p_mdns::Event::Discovered(discovered) => {
+                for (peer, address, _) in discovered {
+                    self.add_address(*peer, address.clone(), Source::Mdns);
+                }
+            }
+            libp2p_mdns::Event::Expired(expired) => {
+                for (peer, address) in expired {
+                    let Some(address) = normalize(peer, address.clone()) else {
+                        continue;
+                    };
+                    if self
+                        .address_record(peer, &address)
+                        .is_some_and(|record| record.source == Source::Mdns)
+                    {
+                        self.remove_with_reason(*peer, address, RemovalReason::Expired);
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_expiry(&mut self, peer: PeerId, address: Multiaddr, expires: Option<Instant>) {
+        match expires {
+            Some(expires) => {
+                self.expirations.insert_at((peer, address), expires);
+            }
+            None => {
+                self.expirations.remove(&(peer, address));
+            }
+        }
+    }
+
+    fn evict_beyond_limit(&mut self, peer: PeerId, inserted: &Multiaddr) {
+        let Some(addresses) = self.peers.get(&peer) else {
+            return;
+        };
+        if addresses.len() <= self.config.max_addresses_per_peer {
+            return;
+        }
+
+        let evicted = addresses
+            .iter()
+            .filter(|(address, _)| *address != inserted)
+            .max_by(|(_, a), (_, b)| a.rank(b))
+            .map(|(address, _)| address.clone());
+        if let Some(address) = evicted {
+            self.remove_with_reason(peer, address, RemovalReason::Evicted);
+        }
+    }
+
+    fn remove(&mut self, peer: PeerId, address: &Multiaddr) -> Option<AddressRecord> {
+        let addresses = self.peers.get_mut(&peer)?;
+        let record = addresses.remove(address)?;
+        if addresses.is_empty() {
+            self.peers.remove(&peer);
+        }
+        self.expirations.remove(&(peer, address.clone()));
+        if let Some(persistence) = self.persistence.as_mut() {
+            persistence.remove(&peer, address);
+        }
+
+        Some(record)
+    }
+
+    fn remove_with_reason(&mut self, peer: PeerId, address: Multiaddr, reason: RemovalReason) {
+        if self.remove(peer, &address).is_some() {
+            tracing::debug!(%peer, %address, ?reason, "Removed address");
+            self.push_event(Event::AddressRemoved {
+                peer,
+                address,
+                reason,
+            });
+        }
+    }
+
+    fn on_connection_established(&mut self, peer: PeerId, address: &Multiaddr) {
+        self.add_address(peer, address.clone(), Source::Connection);
+
+        let Some(address) = normalize(&peer, address.clone()) else {
+            return;
+        };
+        if let Some(record) = self
+            .peers
+            .get_mut(&peer)
+            .and_then(|addresses| addresses.get_mut(&address))
+        {
+            record.dial_failures = 0;
+            record.last_connected = Some(Instant::now());
+        }
+    }
+
+    fn on_dial_failure(&mut self, peer: PeerId, address: &Multiaddr) {
+        let Some(address) = normalize(&peer, address.clone()) else {
+            return;
+        };
+        let Some(record) = self
+            .peers
+            .get_mut(&peer)
+            .and_then(|addresses| addresses.get_mut(&address))
+        else {
+            return;
+        };
+
+        record.dial_failures += 1;
+        if record.dial_failures >= self.config.max_dial_failures {
+            self.remove_with_reason(peer, address, RemovalReason::DialFailures);
+        }
+    }
+
+    fn push_event(&mut self, event: Event) {
+        self.events.push_back(event);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl fmt::Debug for Behaviour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Behaviour")
+            .field("config", &self.config)
+            .field("peers", &self.peers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Strips the `/p2p` suffix of the peer from the address.
+///
+/// Returns [`None`] if the address ends with `/p2p` of another peer.
+fn normalize(peer: &PeerId, mut address: Multiaddr) -> Option<Multiaddr> {
+    match address.iter().last() {
+        Some(Protocol::P2p(id)) if id == *peer => {
+            address.pop();
+            Some(address)
+        }
+        Some(Protocol::P2p(_)) => None,
+        _ => Some(address),
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = Event;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _: &[Multiaddr],
+        _: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        let Some(peer) = maybe_peer else {
+            return Ok(vec![]);
+        };
+
+        Ok(self.addresses(&peer))
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::NewExternalAddrOfPeer(NewExternalAddrOfPeer { peer_id, addr }) => {
+                self.add_address(peer_id, addr.clone(), Source::Swarm);
+            }
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id,
+                endpoint: ConnectedPoint::Dialer { address, .. },
+                failed_addresses,
+                ..
+            }) => {
+                for address in failed_addresses {
+                    self.on_dial_failure(peer_id, address);
+                }
+                self.on_connection_established(peer_id, address);
+            }
+            FromSwarm::DialFailure(DialFailure {
+                peer_id: Some(peer_id),
+                error: DialError::Transport(errors),
+                ..
+            }) => {
+                for (address, _) in errors {
+                    self.on_dial_failure(peer_id, address);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(event)
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ToSwarm<Event, THandlerInEvent<Self>>> {
+        while let Poll::Ready((peer, address)) = self.expirations.poll_expired(cx) {
+            self.remove_with_reason(peer, address, RemovalReason::Expired);
+        }
+
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(ToSwarm::GenerateEvent(event));
+        }
+        self.waker = Some(cx.waker().clone());
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn address(port: u16) -> Multiaddr {
+        format!("/ip4/127.0.0.1/tcp/{port}").parse().unwrap()
+    }
+
+    #[test]
+    fn addresses_are_ranked_by_connection_and_failures() {
+        let peer = PeerId::random();
+        let mut behaviour = Behaviour::new(Config::default());
+        for port in 1..=3 {
+            behaviour.add_address(peer, address(port), Source::Manual);
+        }
+
+        behaviour.on_dial_failure(peer, &address(1));
+        behaviour.on_connection_established(peer, &address(3).with_p2p(peer).unwrap());
+
+        assert_eq!(
+            behaviour.addresses(&peer),
+            vec![address(3), address(2), address(1)]
+        );
+        let record = behaviour.address_record(&peer, &address(3)).unwrap();
+        assert_eq!(record.source(), Source::Manual);
+        assert!(record.last_connected().is_some());
+    }
+
+    #[test]
+    fn addresses_of_other_peers_are_rejected() {
+        let peer = PeerId::random();
+        let mut behaviour = Behaviour::new(Config::default());
+
+        assert!(!behaviour.add_address(
+            peer,
+            address(1).with_p2p(PeerId::random()).unwrap(),
+            Source::Manual
+        ));
+        assert!(behaviour.add_address(peer, address(1).with_p2p(peer).unwrap(), Source::Manual));
+        assert_eq!(behaviour.addresses(&peer), vec![address(1)]);
+    }
+
+    #[test]
+    fn repeated_dial_failures_remove_address() {
+        let peer = PeerId::random();
+        let mut behaviour = Behaviour::new(Config::default().with_max_dial_failures(2));
+        behaviour.add_address(peer, address(1), Source::Manual);
+
+        behaviour.on_dial_failure(peer, &address(1));
+        assert_eq!(behaviour.addresses(&peer), vec![address(1)]);
+        behaviour.on_dial_failure(peer, &address(1));
+        assert!(behaviour.addresses(&peer).is_empty());
+        assert_eq!(
+            behaviour.events.pop_back(),
+            Some(Event::AddressRemoved {
+                peer,
+                address: address(1),
+                reason: RemovalReason::DialFailures
+            })
+        );
+    }
+
+    #[test]
+    fn lowest_ranked_address_is_evicted() {
+        let peer = PeerId::random();
+        let mut behaviour = Behaviour::new(Config::default().with_max_addresses_per_peer(2));
+        behaviour.add_address(peer, address(1), Source::Manual);
+        behaviour.add_address(peer, address(2), Source::Manual);
+        behaviour.on_dial_failure(peer, &address(1));
+
+        behaviour.add_address(peer, address(3), Source::Manual);
+
+        let mut addresses = behaviour.addresses(&peer);
+        addresses.sort();
+        assert_eq!(addresses, vec![address(2), address(3)]);
+    }
+
+    #[async_std::test]
+    async fn addresses_expire_after_ttl() {
+        let peer = PeerId::random();
+        let mut behaviour = Behaviour::new(
+            Config::default().with_ttl(Source::Mdns, Some(Duration::from_millis(10))),
+        );
+        behaviour.add_address(peer, address(1), Source::Mdns);
+        behaviour.add_address(peer, address(2), Source::Manual);
+
+        let removed = std::future::poll_fn(|cx| loop {
+            match behaviour.poll(cx) {
+                Poll::Ready(ToSwarm::GenerateEvent(Event::AddressRemoved {
+                    address,
+                    reason,
+                    ..
+                })) => return Poll::Ready((address, reason)),
+                Poll::Ready(_) => continue,
+                Poll::Pending => return Poll::Pending,
+            }
+        })
+        .await;
+
+        assert_eq!(removed, (address(1), RemovalReason::Expired));
+        assert_eq!(behaviour.addresses(&peer), vec![address(2)]);
+    }
+
+    #[derive(Clone, Default)]
+    struct MemoryPersistence(Arc<Mutex<Vec<(PeerId, Multiaddr)>>>);
+
+    impl Persistence for MemoryPersistence {
+        fn load(&mut self) -> Vec<(PeerId, Multiaddr)> {
+            self.0.lock().unwrap().clone()
+        }
+
+        fn insert(&mut self, peer: &PeerId, address: &Multiaddr) {
+            self.0.lock().unwrap().push((*peer, address.clone()));
+        }
+
+        fn remove(&mut self, peer: &PeerId, address: &Multiaddr) {
+            self.0
+                .lock()
+                .unwrap()
+                .retain(|(p, a)| p != peer || a != address);
+        }
+    }
+
+    #[test]
+    fn persisted_addresses_are_loaded() {
+        let peer = PeerId::random();
+        let persistence = MemoryPersistence::default();
+        let mut behaviour = Behaviour::with_persistence(Config::default(), persistence.clone());
+        behaviour.add_address(peer, address(1), Source::Manual);
+        behaviour.add_address(peer, address(2), Source::Manual);
+        behaviour.remove_address(&peer, &address(2));
+
+        let behaviour = Behaviour::with_persistence(Config::default(), persistence);
+
+        assert_eq!(behaviour.addresses(&peer), vec![address(1)]);
+        assert_eq!(
+            behaviour
+                .address_record(&peer, &address(1))
+                .unwrap()
+                .source(),
+            Source::Persisted
+        );
+        assert!(behaviour.events.is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+use libp2p_identity::PeerId;
+use libp2p_peer_store::{Behaviour, Config, Event, RemovalReason, Source};
+use libp2p_swarm::{dial_opts::DialOpts, Swarm, SwarmEvent};
+use libp2p_swarm_test::SwarmExt as _;
+
+#[async_std::test]
+async fn dials_peer_by_id_with_stored_addresses() {
+    let mut listener = Swarm::new_ephemeral(|_| Behaviour::new(Config::default()));
+    let mut dialer = Swarm::new_ephemeral(|_| Behaviour::new(Config::default()));
+    let (address, _) = listener.listen().await;
+    let listener_id = *listener.local_peer_id();
+
+    assert!(dialer
+        .behaviour_mut()
+        .add_address(listener_id, address.clone(), Source::Manual));
+    dialer.dial(DialOpts::peer_id(listener_id).build()).unwrap();
+    async_std::task::spawn(listener.loop_on_next());
+
+    dialer
+        .wait(|event| match event {
+            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                (peer_id == listener_id).then_some(())
+            }
+            _ => None,
+        })
+        .await;
+
+    let record = dialer
+        .behaviour()
+        .address_record(&listener_id, &address)
+        .unwrap();
+    assert!(record.last_connected().is_some());
+}
+
+#[async_std::test]
+async fn removes_address_after_dial_failures() {
+    let mut dialer =
+        Swarm::new_ephemeral(|_| Behaviour::new(Config::default().with_max_dial_failures(1)));
+    let peer = PeerId::random();
+    let address = "/memory/1234".parse::<libp2p_core::Multiaddr>().unwrap();
+    dialer
+        .behaviour_mut()
+        .add_address(peer, address.clone(), Source::Manual);
+
+    dialer.dial(DialOpts::peer_id(peer).build()).unwrap();
+
+    let removed = dialer
+        .wait(|event| match event {
+            SwarmEvent::Behaviour(Event::AddressRemoved {
+                peer,
+                address,
+                reason,
+            }) => Some((peer, address, reason)),
+            _ => None,
+        })
+        .await;
+    assert_eq!(removed, (peer, address, RemovalReason::DialFailures));
+    assert!(dialer.behaviour().addresses(&peer).is_empty());
+}
@@ -24,60 +24,184 @@ use sha2::Digest as _;
 use std::fmt;
 
 pub const SHA256: &str = "sha-256";
+pub const SHA384: &str = "sha-384";
+pub const SHA512: &str = "sha-512";
+
 const MULTIHASH_SHA256_CODE: u64 = 0x12;
+const MULTIHASH_SHA512_CODE: u64 = 0x13;
+const MULTIHASH_SHA384_CODE: u64 = 0x20;
 
 type Multihash = multihash::Multihash<64>;
 
-/// A certificate fingerprint that is assumed to be created using the SHA256 hash algorithm.
+/// The hash algorithm used to create a [`Fingerprint`].
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc8122#section-5>.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    /// Parses the name of a hash function as used in the SDP `a=fingerprint` attribute.
+    ///
+    /// The comparison is case-insensitive, as mandated by RFC 8122.
+    pub fn from_sdp_name(name: &str) -> Option<Self> {
+        [Self::Sha256, Self::Sha384, Self::Sha512]
+            .into_iter()
+            .find(|a| a.sdp_name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the name of the hash function as used in SDP (e.g. "sha-256").
+    pub fn sdp_name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => SHA256,
+            Self::Sha384 => SHA384,
+            Self::Sha512 => SHA512,
+        }
+    }
+
+    /// Returns the length of a digest in bytes.
+    pub fn digest_len(&self) -> usize {
+        match self {
+            Self::Sha256 => 32,
+            Self::Sha384 => 48,
+            Self::Sha512 => 64,
+        }
+    }
+
+    fn from_multihash_code(code: u64) -> Option<Self> {
+        match code {
+            MULTIHASH_SHA256_CODE => Some(Self::Sha256),
+            MULTIHASH_SHA384_CODE => Some(Self::Sha384),
+            MULTIHASH_SHA512_CODE => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    fn multihash_code(&self) -> u64 {
+        match self {
+            Self::Sha256 => MULTIHASH_SHA256_CODE,
+            Self::Sha384 => MULTIHASH_SHA384_CODE,
+            Self::Sha512 => MULTIHASH_SHA512_CODE,
+        }
+    }
+}
+
+/// A certificate fingerprint, created using one of the supported [`HashAlgorithm`]s.
 #[derive(Eq, PartialEq, Copy, Clone)]
-pub struct Fingerprint([u8; 32]);
+pub struct Fingerprint {
+    algorithm: HashAlgorithm,
+    /// The digest, padded with zeroes to the length of the largest supported digest.
+    digest: [u8; 64],
+}
 
 impl Fingerprint {
-    pub const FF: Fingerprint = Fingerprint([0xFF; 32]);
+    pub const FF: Fingerprint = Fingerprint::raw([0xFF; 32]);
 
+    /// Creates a new SHA256 [Fingerprint] from the given digest.
     pub const fn raw(digest: [u8; 32]) -> Self {
-        Fingerprint(digest)
+        let mut padded = [0; 64];
+        let mut i = 0;
+        while i < digest.len() {
+            padded[i] = digest[i];
+            i += 1;
+        }
+
+        Fingerprint {
+            algorithm: HashAlgorithm::Sha256,
+            digest: padded,
+        }
+    }
+
+    /// Creates a new [Fingerprint] from the given digest.
+    ///
+    /// Returns `None` if the length of the digest doesn't match the algorithm.
+    pub fn try_from_digest(algorithm: HashAlgorithm, digest: &[u8]) -> Option<Self> {
+        if digest.len() != algorithm.digest_len() {
+            return None;
+        }
+
+        let mut padded = [0; 64];
+        padded[..digest.len()].copy_from_slice(digest);
+
+        Some(Fingerprint {
+            algorithm,
+            digest: padded,
+        })
     }
 
     /// Creates a new [Fingerprint] from a raw certificate by hashing the given bytes with SHA256.
     pub fn from_certificate(bytes: &[u8]) -> Self {
-        Fingerprint(sha2::Sha256::digest(bytes).into())
+        Fingerprint::raw(sha2::Sha256::digest(bytes).into())
+    }
+
+    /// Creates a new [Fingerprint] from a raw certificate by hashing the given bytes with the
+    /// given algorithm.
+    pub fn from_certificate_with(algorithm: HashAlgorithm, bytes: &[u8]) -> Self {
+        let digest = match algorithm {
+            HashAlgorithm::Sha256 => sha2::Sha256::digest(bytes).to_vec(),
+            HashAlgorithm::Sha384 => sha2::Sha384::digest(bytes).to_vec(),
+            HashAlgorithm::Sha512 => sha2::Sha512::digest(bytes).to_vec(),
+        };
+
+        Fingerprint::try_from_digest(algorithm, &digest).expect("digest to match algorithm")
+    }
+
+    /// Parses a fingerprint from the hash function and fingerprint values of an SDP
+    /// `a=fingerprint` attribute, e.g. `sha-256` and `A8:17:77:...`.
+    pub fn try_from_sdp(algorithm: &str, fingerprint: &str) -> Option<Self> {
+        let algorithm = HashAlgorithm::from_sdp_name(algorithm)?;
+        let digest = hex::decode(fingerprint.replace(':', "")).ok()?;
+
+        Fingerprint::try_from_digest(algorithm, &digest)
     }
 
     /// Converts [`Multihash`](multihash::Multihash) to [`Fingerprint`].
     pub fn try_from_multihash(hash: Multihash) -> Option<Self> {
-        if hash.code() != MULTIHASH_SHA256_CODE {
-            // Only support SHA256 for now.
-            return None;
-        }
-
-        let bytes = hash.digest().try_into().ok()?;
+        let algorithm = HashAlgorithm::from_multihash_code(hash.code())?;
 
-        Some(Self(bytes))
+        Fingerprint::try_from_digest(algorithm, hash.digest())
     }
 
     /// Converts this fingerprint to [`Multihash`](multihash::Multihash).
     pub fn to_multihash(self) -> Multihash {
-        Multihash::wrap(MULTIHASH_SHA256_CODE, &self.0).expect("fingerprint's len to be 32 bytes")
+        Multihash::wrap(self.algorithm.multihash_code(), self.digest())
+            .expect("fingerprint's len to be at most 64 bytes")
     }
 
     /// Formats this fingerprint as uppercase hex, separated by colons (`:`).
     ///
     /// This is the format described in <https://www.rfc-editor.org/rfc/rfc4572#section-5>.
     pub fn to_sdp_format(self) -> String {
-        self.0.map(|byte| format!("{byte:02X}")).join(":")
+        self.digest()
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect::<Vec<_>>()
+            .join(":")
     }
 
     /// Returns the algorithm used (e.g. "sha-256").
     /// See <https://datatracker.ietf.org/doc/html/rfc8122#section-5>
     pub fn algorithm(&self) -> String {
-        SHA256.to_owned()
+        self.algorithm.sdp_name().to_owned()
+    }
+
+    /// Returns the [`HashAlgorithm`] used to create this fingerprint.
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the digest of this fingerprint.
+    pub fn digest(&self) -> &[u8] {
+        &self.digest[..self.algorithm.digest_len()]
     }
 }
 
 impl fmt::Debug for Fingerprint {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(&hex::encode(self.0))
+        f.write_str(&hex::encode(self.digest()))
     }
 }
 
@@ -106,4 +230,35 @@ mod tests {
         let fp = Fingerprint::raw(bytes);
         assert_eq!(fp, Fingerprint::raw(REGULAR_FORMAT));
     }
+
+    #[test]
+    fn from_sdp_sha384() {
+        let value = "A3:8B:11:0D:2B:35:4A:5E:28:10:62:58:0D:71:E0:5B:39:6E:E6:AA:42:C3:C8:A6:13:B2:62:A5:0C:AF:56:0B:71:FF:5B:0D:F3:39:1D:33:6D:3E:8A:E9:E5:5F:DF:E7";
+
+        let fp = Fingerprint::try_from_sdp("SHA-384", value).unwrap();
+
+        assert_eq!(fp.hash_algorithm(), HashAlgorithm::Sha384);
+        assert_eq!(fp.algorithm(), "sha-384");
+        assert_eq!(fp.to_sdp_format(), value);
+    }
+
+    #[test]
+    fn rejects_digest_of_wrong_length() {
+        assert!(Fingerprint::try_from_sdp("sha-512", SDP_FORMAT).is_none());
+        assert!(Fingerprint::try_from_sdp("md5", SDP_FORMAT).is_none());
+    }
+
+    #[test]
+    fn multihash_roundtrip() {
+        for algorithm in [
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha384,
+            HashAlgorithm::Sha512,
+        ] {
+            let fp = Fingerprint::from_certificate_with(algorithm, b"certificate");
+
+            assert_eq!(fp.digest().len(), algorithm.digest_len());
+            assert_eq!(Fingerprint::try_from_multihash(fp.to_multihash()), Some(fp));
+        }
+    }
 }
@@ -10,6 +10,6 @@ pub mod sdp;
 mod stream;
 mod transport;
 
-pub use fingerprint::{Fingerprint, SHA256};
+pub use fingerprint::{Fingerprint, HashAlgorithm, SHA256, SHA384, SHA512};
 pub use stream::{DropListener, Stream, MAX_MSG_LEN};
 pub use transport::parse_webrtc_dial_addr;
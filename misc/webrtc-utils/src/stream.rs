@@ -57,10 +57,22 @@ pub use drop_listener::DropListener;
 ///
 /// To be a proper libp2p stream, we need to implement [`AsyncRead`] and [`AsyncWrite`] as well
 /// as support a half-closed state which we do by framing messages in a protobuf envelope.
+///
+/// Closing the write-half sends a `FIN` which the remote acknowledges with a `FIN_ACK` once it
+/// received all our data. The data channel is only closed by the [`DropListener`] after the
+/// stream has been dropped and the `FIN_ACK` was received, thus no data in flight is lost.
 pub struct Stream<T> {
     io: FramedDc<T>,
     state: State,
     read_buffer: Bytes,
+    /// Whether the remote closed its write-half via a `FIN`, i.e. reading reached the end.
+    fin_received: bool,
+    /// The progress of acknowledging the `FIN` of the remote.
+    fin_ack: Option<Closing>,
+    /// Whether we closed our write-half via a `FIN`.
+    fin_sent: bool,
+    /// Whether the remote acknowledged our `FIN`.
+    fin_acked: bool,
     /// Dropping this will close the oneshot and notify the receiver by emitting `Canceled`.
     drop_notifier: Option<oneshot::Sender<GracefullyClosed>>,
 }
@@ -77,6 +89,10 @@ where
             io: framed_dc::new(data_channel.clone()),
             state: State::Open,
             read_buffer: Bytes::default(),
+            fin_received: false,
+            fin_ack: None,
+            fin_sent: false,
+            fin_acked: false,
             drop_notifier: Some(sender),
         };
         let listener = DropListener::new(framed_dc::new(data_channel), receiver);
@@ -112,6 +128,59 @@ where
     }
 }
 
+impl<T> Stream<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Tracks the flags of the `FIN` / `FIN_ACK` handshake.
+    fn handle_fin_flags(&mut self, flag: Flag) {
+        match flag {
+            Flag::FIN => {
+                self.fin_received = true;
+                self.fin_ack = Some(Closing::Requested);
+            }
+            Flag::FIN_ACK => self.fin_acked = true,
+            Flag::STOP_SENDING | Flag::RESET => {}
+        }
+    }
+
+    /// Sends and flushes the `FIN_ACK` for a `FIN` received from the remote.
+    fn poll_fin_ack(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            match self.fin_ack {
+                Some(Closing::Requested) => {
+                    ready!(self.io.poll_ready_unpin(cx))?;
+
+                    self.io.start_send_unpin(Message {
+                        flag: Some(Flag::FIN_ACK),
+                        message: None,
+                    })?;
+                    self.fin_ack = Some(Closing::MessageSent);
+                }
+                Some(Closing::MessageSent) => {
+                    ready!(self.io.poll_flush_unpin(cx))?;
+
+                    self.fin_ack = None;
+                }
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+}
+
+impl<T> Drop for Stream<T> {
+    fn drop(&mut self) {
+        // Without a graceful close of the write-half, the `DropListener` resets the stream.
+        if self.fin_sent {
+            if let Some(notifier) = self.drop_notifier.take() {
+                let _ = notifier.send(GracefullyClosed {
+                    fin_acked: self.fin_acked,
+                });
+            }
+        }
+    }
+}
+
 impl<T> AsyncRead for Stream<T>
 where
     T: AsyncRead + AsyncWrite + Unpin,
@@ -122,7 +191,7 @@ where
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
         loop {
-            self.state.read_barrier()?;
+            ready!(self.poll_fin_ack(cx))?;
 
             if !self.read_buffer.is_empty() {
                 let n = std::cmp::min(self.read_buffer.len(), buf.len());
@@ -132,6 +201,12 @@ where
                 return Poll::Ready(Ok(n));
             }
 
+            if self.fin_received {
+                return Poll::Ready(Ok(0));
+            }
+
+            self.state.read_barrier()?;
+
             let Self {
                 read_buffer,
                 io,
@@ -143,15 +218,17 @@ where
                 Some((flag, message)) => {
                     if let Some(flag) = flag {
                         state.handle_inbound_flag(flag, read_buffer);
+                        self.handle_fin_flags(flag);
                     }
 
-                    debug_assert!(read_buffer.is_empty());
+                    debug_assert!(self.read_buffer.is_empty());
                     if let Some(message) = message {
-                        *read_buffer = message.into();
+                        self.read_buffer = message.into();
                     }
                 }
                 None => {
                     state.handle_inbound_flag(Flag::FIN, read_buffer);
+                    self.fin_received = true;
                     return Poll::Ready(Ok(0));
                 }
             }
@@ -184,13 +261,15 @@ where
                     // Read side is closed. Discard any incoming messages.
                     drop(message);
                     // But still handle flags, e.g. a `Flag::StopSending`.
-                    state.handle_inbound_flag(flag, read_buffer)
+                    state.handle_inbound_flag(flag, read_buffer);
+                    self.handle_fin_flags(flag);
                 }
                 Poll::Ready(Some((None, message))) => drop(message),
                 Poll::Ready(None) | Poll::Pending => break,
             }
         }
 
+        ready!(self.poll_fin_ack(cx))?;
         self.state.write_barrier()?;
 
         ready!(self.io.poll_ready_unpin(cx))?;
@@ -210,6 +289,8 @@ where
     }
 
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_fin_ack(cx))?;
+
         loop {
             match self.state.close_write_barrier()? {
                 Some(Closing::Requested) => {
@@ -227,11 +308,7 @@ where
                     ready!(self.io.poll_flush_unpin(cx))?;
 
                     self.state.write_closed();
-                    let _ = self
-                        .drop_notifier
-                        .take()
-                        .expect("to not close twice")
-                        .send(GracefullyClosed {});
+                    self.fin_sent = true;
 
                     return Poll::Ready(Ok(()));
                 }
@@ -263,6 +340,10 @@ mod tests {
     use crate::stream::framed_dc::codec;
     use asynchronous_codec::Encoder;
     use bytes::BytesMut;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    };
 
     #[test]
     fn max_data_len() {
@@ -283,4 +364,99 @@ mod tests {
         // maximum limit specified in the libp2p WebRTC specification.
         assert_eq!(dst.len(), MAX_MSG_LEN);
     }
+
+    #[test]
+    fn fin_is_acknowledged_before_data_channel_is_closed() {
+        let (a, b) = MockDataChannel::pair();
+        let (mut a_stream, a_drop_listener) = Stream::new(a.clone());
+        let (mut b_stream, _b_drop_listener) = Stream::new(b);
+
+        futures::executor::block_on(async {
+            a_stream.write_all(b"hello").await.unwrap();
+            a_stream.close().await.unwrap();
+
+            let mut buf = Vec::new();
+            b_stream.read_to_end(&mut buf).await.unwrap();
+            assert_eq!(buf, b"hello");
+
+            // The data channel stays open until the stream got dropped and `FIN_ACK` was received.
+            assert!(!a.closed.load(Ordering::SeqCst));
+            drop(a_stream);
+            a_drop_listener.await.unwrap();
+            assert!(a.closed.load(Ordering::SeqCst));
+        });
+    }
+
+    /// One end of an in-memory data channel.
+    #[derive(Clone)]
+    struct MockDataChannel {
+        inbound: Arc<Mutex<Vec<u8>>>,
+        outbound: Arc<Mutex<Vec<u8>>>,
+        closed: Arc<AtomicBool>,
+    }
+
+    impl MockDataChannel {
+        fn pair() -> (Self, Self) {
+            let a_to_b = Arc::new(Mutex::new(Vec::new()));
+            let b_to_a = Arc::new(Mutex::new(Vec::new()));
+            let closed = Arc::new(AtomicBool::new(false));
+
+            (
+                Self {
+                    inbound: b_to_a.clone(),
+                    outbound: a_to_b.clone(),
+                    closed: closed.clone(),
+                },
+                Self {
+                    inbound: a_to_b,
+                    outbound: b_to_a,
+                    closed,
+                },
+            )
+        }
+    }
+
+    impl AsyncRead for MockDataChannel {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let mut inbound = self.inbound.lock().unwrap();
+            if inbound.is_empty() {
+                if self.closed.load(Ordering::SeqCst) {
+                    return Poll::Ready(Ok(0));
+                }
+                return Poll::Pending;
+            }
+
+            let n = usize::min(buf.len(), inbound.len());
+            buf[..n].copy_from_slice(&inbound[..n]);
+            inbound.drain(..n);
+
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    impl AsyncWrite for MockDataChannel {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.outbound.lock().unwrap().extend_from_slice(buf);
+
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.closed.store(true, Ordering::SeqCst);
+
+            Poll::Ready(Ok(()))
+        }
+    }
 }
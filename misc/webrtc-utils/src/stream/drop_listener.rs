@@ -20,16 +20,25 @@
 
 use futures::channel::oneshot;
 use futures::channel::oneshot::Canceled;
-use futures::{AsyncRead, AsyncWrite, FutureExt, SinkExt};
+use futures::{AsyncRead, AsyncWrite, FutureExt, SinkExt, StreamExt};
+use futures_timer::Delay;
 
 use std::future::Future;
 use std::io;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use crate::proto::{Flag, Message};
 use crate::stream::framed_dc::FramedDc;
 
+/// How long to wait for the `FIN_ACK` of the remote before closing the data channel anyway.
+const FIN_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Completes the closing of a stream once it got dropped.
+///
+/// If the stream was dropped without gracefully closing its write-half, a `RESET` is sent.
+/// Otherwise the data channel is closed once the remote acknowledged our `FIN` via a `FIN_ACK`.
 #[must_use]
 pub struct DropListener<T> {
     state: State<T>,
@@ -56,6 +65,15 @@ enum State<T> {
     Flushing {
         stream: FramedDc<T>,
     },
+    /// The stream got dropped after sending a `FIN` and we are waiting for the `FIN_ACK`.
+    WaitingForFinAck {
+        stream: FramedDc<T>,
+        timeout: Delay,
+    },
+    /// We are closing the data channel.
+    Closing {
+        stream: FramedDc<T>,
+    },
     /// Bad state transition.
     Poisoned,
 }
@@ -75,8 +93,16 @@ where
                     stream,
                     mut receiver,
                 } => match receiver.poll_unpin(cx) {
-                    Poll::Ready(Ok(GracefullyClosed {})) => {
-                        return Poll::Ready(Ok(()));
+                    Poll::Ready(Ok(GracefullyClosed { fin_acked: true })) => {
+                        *state = State::Closing { stream };
+                        continue;
+                    }
+                    Poll::Ready(Ok(GracefullyClosed { fin_acked: false })) => {
+                        *state = State::WaitingForFinAck {
+                            stream,
+                            timeout: Delay::new(FIN_ACK_TIMEOUT),
+                        };
+                        continue;
                     }
                     Poll::Ready(Err(Canceled)) => {
                         tracing::info!("Stream dropped without graceful close, sending Reset");
@@ -109,6 +135,44 @@ where
                         return Poll::Pending;
                     }
                 },
+                State::WaitingForFinAck {
+                    mut stream,
+                    mut timeout,
+                } => match stream.poll_next_unpin(cx) {
+                    Poll::Ready(Some(Ok(Message {
+                        flag: Some(Flag::FIN_ACK) | Some(Flag::RESET),
+                        ..
+                    }))) => {
+                        *state = State::Closing { stream };
+                        continue;
+                    }
+                    Poll::Ready(Some(Ok(_))) => {
+                        // The stream got dropped, thus we discard any other messages.
+                        *state = State::WaitingForFinAck { stream, timeout };
+                        continue;
+                    }
+                    Poll::Ready(Some(Err(e))) => {
+                        return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, e)))
+                    }
+                    Poll::Ready(None) => return Poll::Ready(Ok(())),
+                    Poll::Pending => {
+                        if timeout.poll_unpin(cx).is_ready() {
+                            tracing::debug!("Timed out waiting for FIN_ACK, closing data channel");
+                            *state = State::Closing { stream };
+                            continue;
+                        }
+
+                        *state = State::WaitingForFinAck { stream, timeout };
+                        return Poll::Pending;
+                    }
+                },
+                State::Closing { mut stream } => match stream.poll_close_unpin(cx)? {
+                    Poll::Ready(()) => return Poll::Ready(Ok(())),
+                    Poll::Pending => {
+                        *state = State::Closing { stream };
+                        return Poll::Pending;
+                    }
+                },
                 State::Poisoned => {
                     unreachable!()
                 }
@@ -117,5 +181,8 @@ where
     }
 }
 
-/// Indicates that our stream got gracefully closed.
-pub struct GracefullyClosed {}
+/// Indicates that our stream got dropped after gracefully closing its write-half.
+pub struct GracefullyClosed {
+    /// Whether the remote already acknowledged our `FIN`.
+    pub(crate) fin_acked: bool,
+}
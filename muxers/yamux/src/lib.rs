@@ -24,10 +24,12 @@
 
 use either::Either;
 use futures::{prelude::*, ready};
-use libp2p_core::muxing::{StreamMuxer, StreamMuxerEvent};
+use libp2p_core::muxing::{StreamMuxer, StreamMuxerEvent, StreamPriority};
 use libp2p_core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade, UpgradeInfo};
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::io::{IoSlice, IoSliceMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::task::Waker;
 use std::{
     io, iter,
@@ -52,6 +54,14 @@ pub struct Muxer<C> {
     inbound_stream_buffer: VecDeque<Stream>,
     /// Waker to be called when new inbound streams are available.
     inbound_stream_waker: Option<Waker>,
+    /// The number of open inbound streams, including buffered ones.
+    num_inbound_streams: Arc<AtomicUsize>,
+    /// The number of open outbound streams.
+    num_outbound_streams: Arc<AtomicUsize>,
+    /// The maximum number of open inbound streams, see [`Config::set_max_inbound_streams`].
+    max_inbound_streams: usize,
+    /// The priorities of all open streams.
+    priorities: Arc<Priorities>,
 }
 
 /// How many streams to buffer before we start resetting them.
@@ -60,20 +70,49 @@ pub struct Muxer<C> {
 /// Thus, for peers running on a recent version of `rust-libp2p`, we should never need to reset streams because they'll voluntarily stop opening them once they hit the ACK backlog.
 const MAX_BUFFERED_INBOUND_STREAMS: usize = 256;
 
+/// The maximum number of bytes a stream writes at once while a stream of higher priority is open.
+///
+/// Yamux serves the frames of all streams in round-robin order, so a stream of higher priority
+/// has to wait for at most one frame of this size per other stream, instead of one frame of
+/// the full stream window.
+const MAX_LOW_PRIORITY_WRITE: usize = 4 * 1024;
+
 impl<C> Muxer<C>
 where
     C: AsyncRead + AsyncWrite + Send + Unpin + 'static,
 {
     /// Create a new Yamux connection.
-    fn new(connection: Either<yamux012::Connection<C>, yamux013::Connection<C>>) -> Self {
+    fn new(
+        connection: Either<yamux012::Connection<C>, yamux013::Connection<C>>,
+        max_inbound_streams: usize,
+    ) -> Self {
         Muxer {
             connection,
             inbound_stream_buffer: VecDeque::default(),
             inbound_stream_waker: None,
+            num_inbound_streams: Arc::default(),
+            num_outbound_streams: Arc::default(),
+            max_inbound_streams,
+            priorities: Arc::default(),
         }
     }
 }
 
+impl<C> Muxer<C> {
+    /// The number of inbound streams currently open on this connection.
+    ///
+    /// This includes streams that were accepted by the remote but not yet returned by
+    /// [`StreamMuxer::poll_inbound`].
+    pub fn num_inbound_streams(&self) -> usize {
+        self.num_inbound_streams.load(Ordering::Relaxed)
+    }
+
+    /// The number of outbound streams currently open on this connection.
+    pub fn num_outbound_streams(&self) -> usize {
+        self.num_outbound_streams.load(Ordering::Relaxed)
+    }
+}
+
 impl<C> StreamMuxer for Muxer<C>
 where
     C: AsyncRead + AsyncWrite + Unpin + 'static,
@@ -98,20 +137,38 @@ where
         Poll::Pending
     }
 
-    #[tracing::instrument(level = "trace", name = "StreamMuxer::poll_outbound", skip(self, cx))]
     fn poll_outbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        self.poll_outbound_with_priority(cx, StreamPriority::DEFAULT)
+    }
+
+    /// Opens a new outbound stream.
+    ///
+    /// Yamux itself has no notion of stream priorities. Instead, while a stream of higher
+    /// priority is open, streams of lower priority write their data in small frames to keep the
+    /// delay they add to the stream of higher priority short.
+    #[tracing::instrument(level = "trace", name = "StreamMuxer::poll_outbound", skip(self, cx))]
+    fn poll_outbound_with_priority(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
+        priority: StreamPriority,
     ) -> Poll<Result<Self::Substream, Self::Error>> {
         let stream = match self.connection.as_mut() {
             Either::Left(c) => ready!(c.poll_new_outbound(cx))
                 .map_err(|e| Error(Either::Left(e)))
-                .map(|s| Stream(Either::Left(s))),
+                .map(Either::Left),
             Either::Right(c) => ready!(c.poll_new_outbound(cx))
                 .map_err(|e| Error(Either::Right(e)))
-                .map(|s| Stream(Either::Right(s))),
+                .map(Either::Right),
         }?;
-        Poll::Ready(Ok(stream))
+        Poll::Ready(Ok(Stream::new(
+            stream,
+            &self.num_outbound_streams,
+            priority,
+            &self.priorities,
+        )))
     }
 
     #[tracing::instrument(level = "trace", name = "StreamMuxer::poll_close", skip(self, cx))]
@@ -133,7 +190,7 @@ where
 
         if this.inbound_stream_buffer.len() >= MAX_BUFFERED_INBOUND_STREAMS {
             tracing::warn!(
-                stream=%inbound_stream.0,
+                stream=%inbound_stream.inner,
                 "dropping stream because buffer is full"
             );
             drop(inbound_stream);
@@ -153,7 +210,88 @@ where
 
 /// A stream produced by the yamux multiplexer.
 #[derive(Debug)]
-pub struct Stream(Either<yamux012::Stream, yamux013::Stream>);
+pub struct Stream {
+    inner: Either<yamux012::Stream, yamux013::Stream>,
+    /// The counter of open streams in the stream's direction, decremented on drop.
+    num_streams: Arc<AtomicUsize>,
+    priority: StreamPriority,
+    /// The priorities of all open streams of the connection.
+    priorities: Arc<Priorities>,
+}
+
+impl Stream {
+    fn new(
+        inner: Either<yamux012::Stream, yamux013::Stream>,
+        num_streams: &Arc<AtomicUsize>,
+        priority: StreamPriority,
+        priorities: &Arc<Priorities>,
+    ) -> Self {
+        num_streams.fetch_add(1, Ordering::Relaxed);
+        priorities.add(priority);
+        Stream {
+            inner,
+            num_streams: num_streams.clone(),
+            priority,
+            priorities: priorities.clone(),
+        }
+    }
+
+    /// The priority the stream was opened with.
+    ///
+    /// Inbound streams always have [`StreamPriority::DEFAULT`].
+    pub fn priority(&self) -> StreamPriority {
+        self.priority
+    }
+
+    /// The maximum number of bytes to write at once.
+    fn max_write(&self) -> usize {
+        if self.priorities.highest() > Some(self.priority) {
+            MAX_LOW_PRIORITY_WRITE
+        } else {
+            usize::MAX
+        }
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        self.num_streams.fetch_sub(1, Ordering::Relaxed);
+        self.priorities.remove(self.priority);
+    }
+}
+
+/// The number of open streams per priority of a connection.
+#[derive(Debug, Default)]
+struct Priorities(Mutex<BTreeMap<StreamPriority, usize>>);
+
+impl Priorities {
+    fn add(&self, priority: StreamPriority) {
+        *self
+            .0
+            .lock()
+            .expect("lock not poisoned")
+            .entry(priority)
+            .or_default() += 1;
+    }
+
+    fn remove(&self, priority: StreamPriority) {
+        let mut priorities = self.0.lock().expect("lock not poisoned");
+        if let Some(count) = priorities.get_mut(&priority) {
+            *count -= 1;
+            if *count == 0 {
+                priorities.remove(&priority);
+            }
+        }
+    }
+
+    fn highest(&self) -> Option<StreamPriority> {
+        self.0
+            .lock()
+            .expect("lock not poisoned")
+            .last_key_value()
+            .map(|(p, _)| *p)
+    }
+}
 
 impl AsyncRead for Stream {
     fn poll_read(
@@ -161,7 +299,7 @@ impl AsyncRead for Stream {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_read(cx, buf))
+        either::for_both!(self.inner.as_mut(), s => Pin::new(s).poll_read(cx, buf))
     }
 
     fn poll_read_vectored(
@@ -169,7 +307,7 @@ impl AsyncRead for Stream {
         cx: &mut Context<'_>,
         bufs: &mut [IoSliceMut<'_>],
     ) -> Poll<io::Result<usize>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_read_vectored(cx, bufs))
+        either::for_both!(self.inner.as_mut(), s => Pin::new(s).poll_read_vectored(cx, bufs))
     }
 }
 
@@ -179,7 +317,8 @@ impl AsyncWrite for Stream {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_write(cx, buf))
+        let len = buf.len().min(self.max_write());
+        either::for_both!(self.inner.as_mut(), s => Pin::new(s).poll_write(cx, &buf[..len]))
     }
 
     fn poll_write_vectored(
@@ -187,15 +326,23 @@ impl AsyncWrite for Stream {
         cx: &mut Context<'_>,
         bufs: &[IoSlice<'_>],
     ) -> Poll<io::Result<usize>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_write_vectored(cx, bufs))
+        let max_write = self.max_write();
+        if max_write != usize::MAX {
+            let buf = bufs
+                .iter()
+                .find(|b| !b.is_empty())
+                .map_or(&[][..], |b| &b[..]);
+            return self.poll_write(cx, &buf[..buf.len().min(max_write)]);
+        }
+        either::for_both!(self.inner.as_mut(), s => Pin::new(s).poll_write_vectored(cx, bufs))
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_flush(cx))
+        either::for_both!(self.inner.as_mut(), s => Pin::new(s).poll_flush(cx))
     }
 
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_close(cx))
+        either::for_both!(self.inner.as_mut(), s => Pin::new(s).poll_close(cx))
     }
 }
 
@@ -204,28 +351,49 @@ where
     C: AsyncRead + AsyncWrite + Unpin + 'static,
 {
     fn poll_inner(&mut self, cx: &mut Context<'_>) -> Poll<Result<Stream, Error>> {
-        let stream = match self.connection.as_mut() {
-            Either::Left(c) => ready!(c.poll_next_inbound(cx))
-                .ok_or(Error(Either::Left(yamux012::ConnectionError::Closed)))?
-                .map_err(|e| Error(Either::Left(e)))
-                .map(|s| Stream(Either::Left(s)))?,
-            Either::Right(c) => ready!(c.poll_next_inbound(cx))
-                .ok_or(Error(Either::Right(yamux013::ConnectionError::Closed)))?
-                .map_err(|e| Error(Either::Right(e)))
-                .map(|s| Stream(Either::Right(s)))?,
-        };
+        loop {
+            let stream = match self.connection.as_mut() {
+                Either::Left(c) => ready!(c.poll_next_inbound(cx))
+                    .ok_or(Error(Either::Left(yamux012::ConnectionError::Closed)))?
+                    .map_err(|e| Error(Either::Left(e)))
+                    .map(Either::Left)?,
+                Either::Right(c) => ready!(c.poll_next_inbound(cx))
+                    .ok_or(Error(Either::Right(yamux013::ConnectionError::Closed)))?
+                    .map_err(|e| Error(Either::Right(e)))
+                    .map(Either::Right)?,
+            };
+
+            if self.num_inbound_streams() >= self.max_inbound_streams {
+                tracing::warn!(
+                    %stream,
+                    "dropping stream because the maximum number of inbound streams is reached"
+                );
+                continue;
+            }
 
-        Poll::Ready(Ok(stream))
+            return Poll::Ready(Ok(Stream::new(
+                stream,
+                &self.num_inbound_streams,
+                StreamPriority::DEFAULT,
+                &self.priorities,
+            )));
+        }
     }
 }
 
 /// The yamux configuration.
 #[derive(Debug, Clone)]
-pub struct Config(Either<Config012, Config013>);
+pub struct Config {
+    inner: Either<Config012, Config013>,
+    max_inbound_streams: usize,
+}
 
 impl Default for Config {
     fn default() -> Self {
-        Self(Either::Right(Config013::default()))
+        Self {
+            inner: Either::Right(Config013::default()),
+            max_inbound_streams: usize::MAX,
+        }
     }
 }
 
@@ -292,20 +460,26 @@ impl Config {
     /// it will be used for an inbound or outbound upgrade.
     #[deprecated(note = "Will be removed with the next breaking release.")]
     pub fn client() -> Self {
-        Self(Either::Left(Config012 {
-            mode: Some(yamux012::Mode::Client),
+        Self {
+            inner: Either::Left(Config012 {
+                mode: Some(yamux012::Mode::Client),
+                ..Default::default()
+            }),
             ..Default::default()
-        }))
+        }
     }
 
     /// Creates a new `YamuxConfig` in server mode, regardless of whether
     /// it will be used for an inbound or outbound upgrade.
     #[deprecated(note = "Will be removed with the next breaking release.")]
     pub fn server() -> Self {
-        Self(Either::Left(Config012 {
-            mode: Some(yamux012::Mode::Server),
+        Self {
+            inner: Either::Left(Config012 {
+                mode: Some(yamux012::Mode::Server),
+                ..Default::default()
+            }),
             ..Default::default()
-        }))
+        }
     }
 
     /// Sets the size (in bytes) of the receive window per substream.
@@ -323,8 +497,51 @@ impl Config {
     }
 
     /// Sets the maximum number of concurrent substreams.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the limit of the connection receive window does not allow each substream a
+    /// receive window of 256 KiB, see [`Config::set_max_connection_receive_window`].
     pub fn set_max_num_streams(&mut self, num_streams: usize) -> &mut Self {
-        self.set(|cfg| cfg.set_max_num_streams(num_streams))
+        match self.inner.as_mut() {
+            Either::Left(c) => {
+                c.inner.set_max_num_streams(num_streams);
+            }
+            Either::Right(c) => {
+                c.0.set_max_num_streams(num_streams);
+            }
+        }
+        self
+    }
+
+    /// Sets the maximum size (in bytes) of the receive windows of all substreams of a connection
+    /// combined, or `None` for no limit.
+    ///
+    /// The receive window of each substream starts at 256 KiB and is auto-tuned based on the
+    /// connection's round-trip time and the substream's bandwidth, within this limit. The default
+    /// is 1 GiB.
+    ///
+    /// Has no effect if any of the deprecated options was set, which falls back to fixed receive
+    /// windows per substream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the limit is smaller than 256 KiB times the maximum number of substreams (see
+    /// [`Config::set_max_num_streams`]).
+    pub fn set_max_connection_receive_window(&mut self, num_bytes: Option<usize>) -> &mut Self {
+        if let Either::Right(c) = self.inner.as_mut() {
+            c.0.set_max_connection_receive_window(num_bytes);
+        }
+        self
+    }
+
+    /// Sets the maximum number of inbound substreams open at the same time.
+    ///
+    /// Inbound substreams beyond the limit are reset. Unlike [`Config::set_max_num_streams`],
+    /// this leaves room for the local node to open outbound substreams.
+    pub fn set_max_inbound_streams(&mut self, num_streams: usize) -> &mut Self {
+        self.max_inbound_streams = num_streams;
+        self
     }
 
     /// Sets the window update mode that determines when the remote
@@ -337,11 +554,11 @@ impl Config {
     }
 
     fn set(&mut self, f: impl FnOnce(&mut yamux012::Config) -> &mut yamux012::Config) -> &mut Self {
-        let cfg012 = match self.0.as_mut() {
+        let cfg012 = match self.inner.as_mut() {
             Either::Left(c) => &mut c.inner,
             Either::Right(_) => {
-                self.0 = Either::Left(Config012::default());
-                &mut self.0.as_mut().unwrap_left().inner
+                self.inner = Either::Left(Config012::default());
+                &mut self.inner.as_mut().unwrap_left().inner
             }
         };
 
@@ -369,7 +586,7 @@ where
     type Future = future::Ready<Result<Self::Output, Self::Error>>;
 
     fn upgrade_inbound(self, io: C, _: Self::Info) -> Self::Future {
-        let connection = match self.0 {
+        let connection = match self.inner {
             Either::Left(Config012 { inner, mode }) => Either::Left(yamux012::Connection::new(
                 io,
                 inner,
@@ -380,7 +597,7 @@ where
             }
         };
 
-        future::ready(Ok(Muxer::new(connection)))
+        future::ready(Ok(Muxer::new(connection, self.max_inbound_streams)))
     }
 }
 
@@ -393,7 +610,7 @@ where
     type Future = future::Ready<Result<Self::Output, Self::Error>>;
 
     fn upgrade_outbound(self, io: C, _: Self::Info) -> Self::Future {
-        let connection = match self.0 {
+        let connection = match self.inner {
             Either::Left(Config012 { inner, mode }) => Either::Left(yamux012::Connection::new(
                 io,
                 inner,
@@ -404,7 +621,7 @@ where
             }
         };
 
-        future::ready(Ok(Muxer::new(connection)))
+        future::ready(Ok(Muxer::new(connection, self.max_inbound_streams)))
     }
 }
 
@@ -450,12 +667,26 @@ mod test {
         // that do not depend on any of the behaviors (i.e. configuration options) of v0.12.
         let mut cfg = Config::default();
         assert!(matches!(
-            cfg,
-            Config(Either::Right(Config013(yamux013::Config { .. })))
+            cfg.inner,
+            Either::Right(Config013(yamux013::Config { .. }))
         ));
 
-        // In case a user makes any configurations, use yamux v0.12 instead.
-        cfg.set_max_num_streams(42);
-        assert!(matches!(cfg, Config(Either::Left(Config012 { .. }))));
+        // In case a user sets any of the deprecated options, use yamux v0.12 instead.
+        #[allow(deprecated)]
+        cfg.set_max_buffer_size(42);
+        assert!(matches!(cfg.inner, Either::Left(Config012 { .. })));
+    }
+
+    #[test]
+    fn config_tuning_keeps_v013() {
+        let mut cfg = Config::default();
+        cfg.set_max_num_streams(42)
+            .set_max_connection_receive_window(Some(42 * 256 * 1024))
+            .set_max_inbound_streams(21);
+
+        assert!(matches!(
+            cfg.inner,
+            Either::Right(Config013(yamux013::Config { .. }))
+        ));
     }
 }
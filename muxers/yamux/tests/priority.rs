@@ -0,0 +1,51 @@
+use futures::future::{self, poll_fn};
+use futures::{AsyncWrite, FutureExt};
+use libp2p_core::muxing::{StreamMuxerExt, StreamPriority};
+use libp2p_core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade};
+use libp2p_yamux::{Config, Muxer, Stream};
+use std::pin::Pin;
+
+#[async_std::test]
+async fn lower_priority_streams_write_small_frames() {
+    let (listener_io, dialer_io) = futures_ringbuf::Endpoint::pair(1_000_000, 1_000_000);
+
+    let (mut listener, mut dialer) = future::try_join(
+        Config::default().upgrade_inbound(listener_io, ""),
+        Config::default().upgrade_outbound(dialer_io, ""),
+    )
+    .await
+    .unwrap();
+
+    let mut bulk = poll_fn(|cx| dialer.poll_outbound_unpin(cx)).await.unwrap();
+    let urgent = poll_fn(|cx| dialer.poll_outbound_with_priority_unpin(cx, StreamPriority::HIGH))
+        .await
+        .unwrap();
+    assert_eq!(bulk.priority(), StreamPriority::DEFAULT);
+    assert_eq!(urgent.priority(), StreamPriority::HIGH);
+
+    let data = vec![0; 64 * 1024];
+    let written = write(&mut dialer, &mut listener, &mut bulk, &data).await;
+    assert!(written <= 4 * 1024);
+
+    drop(urgent);
+    let written = write(&mut dialer, &mut listener, &mut bulk, &data).await;
+    assert!(written > 4 * 1024);
+}
+
+async fn write<C>(
+    dialer: &mut Muxer<C>,
+    listener: &mut Muxer<C>,
+    stream: &mut Stream,
+    data: &[u8],
+) -> usize
+where
+    C: futures::AsyncRead + futures::AsyncWrite + Unpin + 'static,
+{
+    poll_fn(|cx| {
+        let _ = dialer.poll_unpin(cx);
+        let _ = listener.poll_unpin(cx);
+        Pin::new(&mut *stream).poll_write(cx, data)
+    })
+    .map(Result::unwrap)
+    .await
+}
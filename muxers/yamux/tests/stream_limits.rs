@@ -0,0 +1,46 @@
+use futures::future::{self, poll_fn};
+use futures::{AsyncReadExt, AsyncWriteExt, FutureExt};
+use libp2p_core::muxing::StreamMuxerExt;
+use libp2p_core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade};
+use libp2p_yamux::Config;
+
+#[async_std::test]
+async fn inbound_streams_beyond_limit_are_reset() {
+    let (listener_io, dialer_io) = futures_ringbuf::Endpoint::pair(100_000, 100_000);
+
+    let mut listener_config = Config::default();
+    listener_config.set_max_inbound_streams(1);
+
+    let (mut listener, mut dialer) = future::try_join(
+        listener_config.upgrade_inbound(listener_io, ""),
+        Config::default().upgrade_outbound(dialer_io, ""),
+    )
+    .await
+    .unwrap();
+
+    let mut stream1 = poll_fn(|cx| dialer.poll_outbound_unpin(cx)).await.unwrap();
+    let mut stream2 = poll_fn(|cx| dialer.poll_outbound_unpin(cx)).await.unwrap();
+    assert_eq!(dialer.num_outbound_streams(), 2);
+
+    stream1.write_all(b"1").await.unwrap();
+    stream2.write_all(b"2").await.unwrap();
+
+    // The first stream is buffered by the listener, the second one exceeds the limit.
+    let mut buf = [0; 1];
+    let mut read = stream2.read(&mut buf);
+    let result = poll_fn(|cx| {
+        let _ = dialer.poll_unpin(cx);
+        let _ = listener.poll_unpin(cx);
+        read.poll_unpin(cx)
+    })
+    .await;
+    assert!(matches!(result, Ok(0) | Err(_)));
+    assert_eq!(listener.num_inbound_streams(), 1);
+
+    let mut inbound = poll_fn(|cx| listener.poll_inbound_unpin(cx)).await.unwrap();
+    inbound.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"1");
+
+    drop(inbound);
+    assert_eq!(listener.num_inbound_streams(), 0);
+}
@@ -25,7 +25,7 @@ use crate::protocol::{AutoNatCodec, DialRequest, DialResponse, ResponseError};
 use crate::DEFAULT_PROTOCOL_NAME;
 use as_client::AsClient;
 pub use as_client::{OutboundProbeError, OutboundProbeEvent};
-use as_server::AsServer;
+use as_server::{AsServer, DialBackPolicy};
 pub use as_server::{InboundProbeError, InboundProbeEvent};
 use futures_timer::Delay;
 use instant::Instant;
@@ -42,6 +42,7 @@ use libp2p_swarm::{
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     iter,
+    sync::Arc,
     task::{Context, Poll},
     time::Duration,
 };
@@ -212,6 +213,8 @@ pub struct Behaviour {
 
     listen_addresses: ListenAddresses,
     other_candidates: HashSet<Multiaddr>,
+
+    dial_back_policy: Option<DialBackPolicy>,
 }
 
 impl Behaviour {
@@ -240,9 +243,27 @@ impl Behaviour {
             probe_id: ProbeId(0),
             listen_addresses: Default::default(),
             other_candidates: Default::default(),
+            dial_back_policy: None,
         }
     }
 
+    /// Sets a policy deciding which of the addresses of a client to dial back as a server, e.g.
+    /// to refuse ports below 1024 and thereby prevent clients from using the server to scan the
+    /// services of third parties.
+    ///
+    /// The policy is called with the client and each address it requested a dial-back to, after
+    /// the IP address of the request was replaced with the one the client was observed at.
+    /// Requests of clients observed at a non-global IP address are refused beforehand if
+    /// [`Config::only_global_ips`] is set. If the policy refuses all addresses, the request is
+    /// answered with [`ResponseError::DialRefused`]. By default, all addresses are dialed back.
+    pub fn with_dial_back_policy<F>(mut self, policy: F) -> Self
+    where
+        F: Fn(PeerId, &Multiaddr) -> bool + Send + Sync + 'static,
+    {
+        self.dial_back_policy = Some(DialBackPolicy(Arc::new(policy)));
+        self
+    }
+
     /// Assumed public address of the local peer.
     /// Returns `None` in case of status [`NatStatus::Private`] or [`NatStatus::Unknown`].
     pub fn public_address(&self) -> Option<&Multiaddr> {
@@ -308,6 +329,7 @@ impl Behaviour {
         AsServer {
             inner: &mut self.inner,
             config: &self.config,
+            dial_back_policy: self.dial_back_policy.as_ref(),
             connected: &self.connected,
             probe_id: &mut self.probe_id,
             throttled_clients: &mut self.throttled_clients,
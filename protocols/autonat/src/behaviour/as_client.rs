@@ -137,6 +137,13 @@ impl<'a> HandleInnerEvent for AsClient<'a> {
                 actions.push_back(ToSwarm::GenerateEvent(Event::OutboundProbe(event)));
 
                 if let Some(old) = self.handle_reported_status(response.result.clone().into()) {
+                    // The previously confirmed address is no longer reachable, let other
+                    // behaviours (e.g. Kademlia) know that they can't rely on it anymore.
+                    if let NatStatus::Public(address) = &old {
+                        if !self.nat_status.is_public() {
+                            actions.push_back(ToSwarm::ExternalAddrExpired(address.clone()));
+                        }
+                    }
                     actions.push_back(ToSwarm::GenerateEvent(Event::StatusChanged {
                         old,
                         new: self.nat_status.clone(),
@@ -34,7 +34,9 @@ use libp2p_swarm::{
 };
 use std::{
     collections::{HashMap, HashSet, VecDeque},
+    fmt,
     num::NonZeroU8,
+    sync::Arc,
 };
 
 /// Inbound probe failed.
@@ -73,10 +75,27 @@ pub enum InboundProbeEvent {
     },
 }
 
+/// The policy deciding which addresses of a client to dial back.
+#[derive(Clone)]
+pub(crate) struct DialBackPolicy(pub(crate) Arc<dyn Fn(PeerId, &Multiaddr) -> bool + Send + Sync>);
+
+impl DialBackPolicy {
+    fn allows(&self, peer: PeerId, addr: &Multiaddr) -> bool {
+        (self.0)(peer, addr)
+    }
+}
+
+impl fmt::Debug for DialBackPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DialBackPolicy").finish()
+    }
+}
+
 /// View over [`super::Behaviour`] in a server role.
 pub(crate) struct AsServer<'a> {
     pub(crate) inner: &'a mut request_response::Behaviour<AutoNatCodec>,
     pub(crate) config: &'a Config,
+    pub(crate) dial_back_policy: Option<&'a DialBackPolicy>,
     pub(crate) connected: &'a HashMap<PeerId, HashMap<ConnectionId, Option<Multiaddr>>>,
     pub(crate) probe_id: &'a mut ProbeId,
     pub(crate) throttled_clients: &'a mut Vec<(PeerId, Instant)>,
@@ -310,6 +329,9 @@ impl<'a> AsServer<'a> {
             })?;
 
         let mut addrs = Self::filter_valid_addrs(sender, request.addresses, observed_addr);
+        if let Some(policy) = self.dial_back_policy {
+            addrs.retain(|addr| policy.allows(sender, addr));
+        }
         addrs.truncate(self.config.max_peer_addresses);
 
         if addrs.is_empty() {
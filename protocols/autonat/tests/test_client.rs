@@ -217,6 +217,76 @@ async fn test_confidence() {
     }
 }
 
+#[async_std::test]
+async fn test_expires_external_address_when_no_longer_public() {
+    let mut client = Swarm::new_ephemeral(|key| {
+        Behaviour::new(
+            key.public().to_peer_id(),
+            Config {
+                retry_interval: TEST_RETRY_INTERVAL,
+                refresh_interval: TEST_REFRESH_INTERVAL,
+                confidence_max: MAX_CONFIDENCE,
+                only_global_ips: false,
+                throttle_server_period: Duration::ZERO,
+                boot_delay: Duration::from_millis(100),
+                ..Default::default()
+            },
+        )
+    });
+    let (server_id, addr, _) = new_server_swarm().await;
+    client.behaviour_mut().add_server(server_id, Some(addr));
+
+    let listener_id = client
+        .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+    let public_address = loop {
+        match client.next_swarm_event().await {
+            SwarmEvent::Behaviour(Event::StatusChanged { old, new }) => {
+                assert_eq!(old, NatStatus::Unknown);
+                match new {
+                    NatStatus::Public(address) => break address,
+                    other => panic!("Unexpected NAT status: {other:?}."),
+                }
+            }
+            SwarmEvent::ExternalAddrExpired { address } => {
+                panic!("Unexpected expiry of {address}.")
+            }
+            _ => {}
+        }
+    };
+
+    // With a confidence of 0, a single failed probe flips the status to private.
+    assert_eq!(client.behaviour().confidence(), 0);
+    assert!(client.remove_listener(listener_id));
+    client
+        .wait(|e| match e {
+            SwarmEvent::ListenerClosed { .. } => Some(()),
+            _ => None,
+        })
+        .await;
+    let unreachable_addr = "/ip4/127.0.0.1/tcp/42".parse().unwrap();
+    client.behaviour_mut().probe_address(unreachable_addr);
+
+    let mut expired = None;
+    loop {
+        match client.next_swarm_event().await {
+            SwarmEvent::ExternalAddrExpired { address } => {
+                assert!(expired.is_none());
+                expired = Some(address);
+            }
+            SwarmEvent::Behaviour(Event::StatusChanged { old, new }) => {
+                assert_eq!(old, NatStatus::Public(public_address.clone()));
+                assert_eq!(new, NatStatus::Private);
+                break;
+            }
+            _ => {}
+        }
+    }
+    // The expiry is reported before the status change.
+    assert_eq!(expired, Some(public_address));
+    assert!(client.behaviour().public_address().is_none());
+}
+
 #[async_std::test]
 async fn test_throttle_server_period() {
     let mut client = Swarm::new_ephemeral(|key| {
@@ -348,6 +348,44 @@ async fn test_global_ips_config() {
     };
 }
 
+#[async_std::test]
+async fn test_dial_back_policy() {
+    let mut server = Swarm::new_ephemeral(|key| {
+        Behaviour::new(
+            key.public().to_peer_id(),
+            Config {
+                boot_delay: Duration::from_secs(60),
+                only_global_ips: false,
+                ..Default::default()
+            },
+        )
+        // Refuse to dial well-known ports.
+        .with_dial_back_policy(|_, addr| {
+            addr.iter()
+                .all(|p| !matches!(p, Protocol::Tcp(port) if port < 1024))
+        })
+    });
+    let server_id = *server.local_peer_id();
+    let (_, server_addr) = server.listen().await;
+
+    let (mut client, client_id) = new_client_swarm(server_id, server_addr).await;
+    client
+        .behaviour_mut()
+        .probe_address("/ip4/127.0.0.1/tcp/22".parse().unwrap());
+    async_std::task::spawn(client.loop_on_next());
+
+    match server.next_behaviour_event().await {
+        Event::InboundProbe(InboundProbeEvent::Error { peer, error, .. }) => {
+            assert_eq!(peer, client_id);
+            assert!(matches!(
+                error,
+                InboundProbeError::Response(ResponseError::DialRefused)
+            ));
+        }
+        other => panic!("Unexpected behaviour event: {other:?}."),
+    };
+}
+
 async fn new_server_swarm(config: Option<Config>) -> (Swarm<Behaviour>, PeerId, Multiaddr) {
     let mut config = config.unwrap_or_else(|| Config {
         only_global_ips: false,
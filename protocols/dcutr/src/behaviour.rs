@@ -20,8 +20,13 @@
 
 //! [`NetworkBehaviour`] to act as a direct connection upgrade through relay node.
 
+use crate::port_prediction::{self, MAX_FAN_OUT};
 use crate::{handler, protocol};
 use either::Either;
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use futures::{FutureExt, StreamExt};
+use futures_timer::Delay;
 use libp2p_core::connection::ConnectedPoint;
 use libp2p_core::multiaddr::Protocol;
 use libp2p_core::{Endpoint, Multiaddr};
@@ -35,18 +40,104 @@ use libp2p_swarm::{
 use libp2p_swarm::{NetworkBehaviour, NotifyHandler, THandlerInEvent, ToSwarm};
 use lru::LruCache;
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::num::NonZeroUsize;
+use std::num::{NonZeroU8, NonZeroUsize};
 use std::task::{Context, Poll};
+use std::time::Duration;
 use thiserror::Error;
 use void::Void;
 
-pub(crate) const MAX_NUMBER_OF_UPGRADE_ATTEMPTS: u8 = 3;
+const MAX_NUMBER_OF_UPGRADE_ATTEMPTS: u8 = 3;
+
+/// Configuration for the [`Behaviour`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    max_attempts: u8,
+    retry_delay: Duration,
+    port_prediction: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_attempts: MAX_NUMBER_OF_UPGRADE_ATTEMPTS,
+            retry_delay: Duration::ZERO,
+            port_prediction: 0,
+        }
+    }
+}
+
+impl Config {
+    /// Sets the maximum number of hole-punch attempts per relayed connection.
+    ///
+    /// Defaults to 3.
+    pub fn with_max_attempts(mut self, v: u8) -> Self {
+        self.max_attempts = v.max(1);
+        self
+    }
+
+    /// Sets the delay between a failed hole-punch attempt and the next one.
+    ///
+    /// Defaults to no delay.
+    pub fn with_retry_delay(mut self, v: Duration) -> Self {
+        self.retry_delay = v;
+        self
+    }
+
+    /// Enables dialing up to `fan_out` addresses with predicted ports in each hole-punch attempt
+    /// if the remote appears to be behind an endpoint-dependent ("symmetric") NAT, i.e. if the
+    /// addresses the remote reports differ only in their port.
+    ///
+    /// The ports are predicted by continuing the sequence of the reported ports. `fan_out` is
+    /// capped at 16, `0` disables port prediction.
+    ///
+    /// Disabled by default.
+    pub fn with_port_prediction(mut self, fan_out: u8) -> Self {
+        self.port_prediction = fan_out.min(MAX_FAN_OUT);
+        self
+    }
+}
 
 /// The events produced by the [`Behaviour`].
 #[derive(Debug)]
 pub struct Event {
     pub remote_peer_id: PeerId,
     pub result: Result<ConnectionId, Error>,
+    /// The hole-punch attempts made on the relayed connection, in order.
+    pub attempts: Vec<Attempt>,
+    /// The address family of the direct connection if the hole-punch succeeded.
+    pub address_family: Option<AddressFamily>,
+}
+
+/// A single hole-punch attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attempt {
+    /// The round-trip time over the relayed connection, measured during the `CONNECT` and `SYNC`
+    /// exchange.
+    ///
+    /// `None` if the exchange failed.
+    pub rtt: Option<Duration>,
+    /// The number of addresses with predicted ports dialed in addition to the addresses reported
+    /// by the remote.
+    ///
+    /// See [`Config::with_port_prediction`].
+    pub predicted_addresses: usize,
+}
+
+/// The address family of a direct connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressFamily {
+    Ip4,
+    Ip6,
+}
+
+impl AddressFamily {
+    fn of(addr: &Multiaddr) -> Option<Self> {
+        addr.iter().find_map(|p| match p {
+            Protocol::Ip4(_) => Some(AddressFamily::Ip4),
+            Protocol::Ip6(_) => Some(AddressFamily::Ip6),
+            _ => None,
+        })
+    }
 }
 
 #[derive(Debug, Error)]
@@ -66,6 +157,8 @@ enum InnerError {
 }
 
 pub struct Behaviour {
+    config: Config,
+
     /// Queue of actions to return when polled.
     queued_events: VecDeque<ToSwarm<Event, Either<handler::relayed::Command, Void>>>,
 
@@ -79,19 +172,68 @@ pub struct Behaviour {
     /// Indexed by the [`ConnectionId`] of the relayed connection and
     /// the [`PeerId`] we are trying to establish a direct connection to.
     outgoing_direct_connection_attempts: HashMap<(ConnectionId, PeerId), u8>,
+
+    /// The hole-punch attempts made so far, indexed like `outgoing_direct_connection_attempts`.
+    attempts: HashMap<(ConnectionId, PeerId), Vec<Attempt>>,
+
+    /// Hole-punch attempts to retry once the retry delay elapsed.
+    pending_retries: FuturesUnordered<BoxFuture<'static, (ConnectionId, PeerId)>>,
 }
 
 impl Behaviour {
     pub fn new(local_peer_id: PeerId) -> Self {
+        Self::with_config(local_peer_id, Config::default())
+    }
+
+    pub fn with_config(local_peer_id: PeerId, config: Config) -> Self {
         Behaviour {
+            config,
             queued_events: Default::default(),
             direct_connections: Default::default(),
             address_candidates: Candidates::new(local_peer_id),
             direct_to_relayed_connections: Default::default(),
             outgoing_direct_connection_attempts: Default::default(),
+            attempts: Default::default(),
+            pending_retries: Default::default(),
         }
     }
 
+    fn record_attempt(
+        &mut self,
+        relayed_connection_id: ConnectionId,
+        peer_id: PeerId,
+        rtt: Option<Duration>,
+        predicted_addresses: usize,
+    ) {
+        self.attempts
+            .entry((relayed_connection_id, peer_id))
+            .or_default()
+            .push(Attempt {
+                rtt,
+                predicted_addresses,
+            });
+    }
+
+    /// Extends the addresses reported by the remote with the predicted ones, returning the
+    /// number of predicted addresses.
+    fn predict_addresses(&self, remote_addrs: &mut Vec<Multiaddr>) -> usize {
+        let predicted = port_prediction::predict(remote_addrs, self.config.port_prediction);
+        let num_predicted = predicted.len();
+        remote_addrs.extend(predicted);
+
+        num_predicted
+    }
+
+    fn take_attempts(
+        &mut self,
+        relayed_connection_id: ConnectionId,
+        peer_id: PeerId,
+    ) -> Vec<Attempt> {
+        self.attempts
+            .remove(&(relayed_connection_id, peer_id))
+            .unwrap_or_default()
+    }
+
     fn observed_addresses(&self) -> Vec<Multiaddr> {
         self.address_candidates.iter().cloned().collect()
     }
@@ -108,32 +250,35 @@ impl Behaviour {
             return;
         };
 
-        let Some(relayed_connection_id) = self
+        let Some(&relayed_connection_id) = self
             .direct_to_relayed_connections
             .get(&failed_direct_connection)
         else {
             return;
         };
 
-        let Some(attempt) = self
+        let Some(&attempt) = self
             .outgoing_direct_connection_attempts
-            .get(&(*relayed_connection_id, peer_id))
+            .get(&(relayed_connection_id, peer_id))
         else {
             return;
         };
 
-        if *attempt < MAX_NUMBER_OF_UPGRADE_ATTEMPTS {
-            self.queued_events.push_back(ToSwarm::NotifyHandler {
-                handler: NotifyHandler::One(*relayed_connection_id),
-                peer_id,
-                event: Either::Left(handler::relayed::Command::Connect),
-            })
+        if attempt < self.config.max_attempts {
+            self.pending_retries.push(
+                Delay::new(self.config.retry_delay)
+                    .map(move |()| (relayed_connection_id, peer_id))
+                    .boxed(),
+            );
         } else {
+            let attempts = self.take_attempts(relayed_connection_id, peer_id);
             self.queued_events.extend([ToSwarm::GenerateEvent(Event {
                 remote_peer_id: peer_id,
                 result: Err(Error {
-                    inner: InnerError::AttemptsExceeded(MAX_NUMBER_OF_UPGRADE_ATTEMPTS),
+                    inner: InnerError::AttemptsExceeded(self.config.max_attempts),
                 }),
+                attempts,
+                address_family: None,
             })]);
         }
     }
@@ -159,6 +304,8 @@ impl Behaviour {
             if connections.is_empty() {
                 self.direct_connections.remove(&peer_id);
             }
+        } else {
+            self.attempts.remove(&(connection_id, peer_id));
         }
     }
 }
@@ -179,8 +326,11 @@ impl NetworkBehaviour for Behaviour {
                 local_addr: local_addr.clone(),
                 send_back_addr: remote_addr.clone(),
             };
-            let mut handler =
-                handler::relayed::Handler::new(connected_point, self.observed_addresses());
+            let mut handler = handler::relayed::Handler::new(
+                connected_point,
+                self.observed_addresses(),
+                self.config.max_attempts,
+            );
             handler.on_behaviour_event(handler::relayed::Command::Connect);
 
             return Ok(Either::Left(handler)); // TODO: We could make two `handler::relayed::Handler` here, one inbound one outbound.
@@ -214,6 +364,7 @@ impl NetworkBehaviour for Behaviour {
                     role_override,
                 },
                 self.observed_addresses(),
+                self.config.max_attempts,
             ))); // TODO: We could make two `handler::relayed::Handler` here, one inbound one outbound.
         }
 
@@ -234,9 +385,12 @@ impl NetworkBehaviour for Behaviour {
                 );
             }
 
+            let attempts = self.take_attempts(relayed_connection_id, peer);
             self.queued_events.extend([ToSwarm::GenerateEvent(Event {
                 remote_peer_id: peer,
                 result: Ok(connection_id),
+                attempts,
+                address_family: AddressFamily::of(addr),
             })]);
         }
         Ok(Either::Right(dummy::ConnectionHandler))
@@ -260,12 +414,20 @@ impl NetworkBehaviour for Behaviour {
         };
 
         match handler_event {
-            Either::Left(handler::relayed::Event::InboundConnectNegotiated { remote_addrs }) => {
-                tracing::debug!(target=%event_source, addresses=?remote_addrs, "Attempting to hole-punch as dialer");
+            Either::Left(handler::relayed::Event::InboundConnectNegotiated {
+                mut remote_addrs,
+                rtt,
+            }) => {
+                let predicted = self.predict_addresses(&mut remote_addrs);
+                tracing::debug!(target=%event_source, addresses=?remote_addrs, %predicted, ?rtt, "Attempting to hole-punch as dialer");
+
+                self.record_attempt(relayed_connection_id, event_source, Some(rtt), predicted);
 
+                let concurrency = dial_concurrency(&remote_addrs);
                 let opts = DialOpts::peer_id(event_source)
                     .addresses(remote_addrs)
                     .condition(dial_opts::PeerCondition::Always)
+                    .override_dial_concurrency_factor(concurrency)
                     .build();
 
                 let maybe_direct_connection_id = opts.connection_id();
@@ -275,30 +437,46 @@ impl NetworkBehaviour for Behaviour {
                 self.queued_events.push_back(ToSwarm::Dial { opts });
             }
             Either::Left(handler::relayed::Event::InboundConnectFailed { error }) => {
+                self.record_attempt(relayed_connection_id, event_source, None, 0);
+                let attempts = self.take_attempts(relayed_connection_id, event_source);
                 self.queued_events.push_back(ToSwarm::GenerateEvent(Event {
                     remote_peer_id: event_source,
                     result: Err(Error {
                         inner: InnerError::InboundError(error),
                     }),
+                    attempts,
+                    address_family: None,
                 }));
             }
             Either::Left(handler::relayed::Event::OutboundConnectFailed { error }) => {
+                self.record_attempt(relayed_connection_id, event_source, None, 0);
+                let attempts = self.take_attempts(relayed_connection_id, event_source);
                 self.queued_events.push_back(ToSwarm::GenerateEvent(Event {
                     remote_peer_id: event_source,
                     result: Err(Error {
                         inner: InnerError::OutboundError(error),
                     }),
+                    attempts,
+                    address_family: None,
                 }));
 
                 // Maybe treat these as transient and retry?
             }
-            Either::Left(handler::relayed::Event::OutboundConnectNegotiated { remote_addrs }) => {
-                tracing::debug!(target=%event_source, addresses=?remote_addrs, "Attempting to hole-punch as listener");
+            Either::Left(handler::relayed::Event::OutboundConnectNegotiated {
+                mut remote_addrs,
+                rtt,
+            }) => {
+                let predicted = self.predict_addresses(&mut remote_addrs);
+                tracing::debug!(target=%event_source, addresses=?remote_addrs, %predicted, ?rtt, "Attempting to hole-punch as listener");
 
+                self.record_attempt(relayed_connection_id, event_source, Some(rtt), predicted);
+
+                let concurrency = dial_concurrency(&remote_addrs);
                 let opts = DialOpts::peer_id(event_source)
                     .condition(dial_opts::PeerCondition::Always)
                     .addresses(remote_addrs)
                     .override_role()
+                    .override_dial_concurrency_factor(concurrency)
                     .build();
 
                 let maybe_direct_connection_id = opts.connection_id();
@@ -316,11 +494,24 @@ impl NetworkBehaviour for Behaviour {
     }
 
     #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
-    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
         if let Some(event) = self.queued_events.pop_front() {
             return Poll::Ready(event);
         }
 
+        if let Poll::Ready(Some((relayed_connection_id, peer_id))) =
+            self.pending_retries.poll_next_unpin(cx)
+        {
+            return Poll::Ready(ToSwarm::NotifyHandler {
+                handler: NotifyHandler::One(relayed_connection_id),
+                peer_id,
+                event: Either::Left(handler::relayed::Command::Connect),
+            });
+        }
+
         Poll::Pending
     }
 
@@ -377,3 +568,9 @@ impl Candidates {
 fn is_relayed(addr: &Multiaddr) -> bool {
     addr.iter().any(|p| p == Protocol::P2pCircuit)
 }
+
+/// Dials all addresses of a hole-punch attempt concurrently, as they are only reachable while the
+/// remote dials us.
+fn dial_concurrency(addrs: &[Multiaddr]) -> NonZeroU8 {
+    NonZeroU8::new(u8::try_from(addrs.len()).unwrap_or(u8::MAX)).unwrap_or(NonZeroU8::MIN)
+}
@@ -20,7 +20,6 @@
 
 //! [`ConnectionHandler`] handling relayed connection potentially upgraded to a direct connection.
 
-use crate::behaviour::MAX_NUMBER_OF_UPGRADE_ATTEMPTS;
 use crate::{protocol, PROTOCOL_NAME};
 use either::Either;
 use futures::future;
@@ -29,7 +28,7 @@ use libp2p_core::upgrade::{DeniedUpgrade, ReadyUpgrade};
 use libp2p_core::ConnectedPoint;
 use libp2p_swarm::handler::{
     ConnectionEvent, DialUpgradeError, FullyNegotiatedInbound, FullyNegotiatedOutbound,
-    ListenUpgradeError,
+    ListenUpgradeError, StreamPriority,
 };
 use libp2p_swarm::{
     ConnectionHandler, ConnectionHandlerEvent, StreamProtocol, StreamUpgradeError,
@@ -48,10 +47,20 @@ pub enum Command {
 
 #[derive(Debug)]
 pub enum Event {
-    InboundConnectNegotiated { remote_addrs: Vec<Multiaddr> },
-    OutboundConnectNegotiated { remote_addrs: Vec<Multiaddr> },
-    InboundConnectFailed { error: inbound::Error },
-    OutboundConnectFailed { error: outbound::Error },
+    InboundConnectNegotiated {
+        remote_addrs: Vec<Multiaddr>,
+        rtt: Duration,
+    },
+    OutboundConnectNegotiated {
+        remote_addrs: Vec<Multiaddr>,
+        rtt: Duration,
+    },
+    InboundConnectFailed {
+        error: inbound::Error,
+    },
+    OutboundConnectFailed {
+        error: outbound::Error,
+    },
 }
 
 pub struct Handler {
@@ -66,19 +75,25 @@ pub struct Handler {
     >,
 
     // Inbound DCUtR handshakes
-    inbound_stream: futures_bounded::FuturesSet<Result<Vec<Multiaddr>, inbound::Error>>,
+    inbound_stream: futures_bounded::FuturesSet<Result<(Vec<Multiaddr>, Duration), inbound::Error>>,
 
     // Outbound DCUtR handshake.
-    outbound_stream: futures_bounded::FuturesSet<Result<Vec<Multiaddr>, outbound::Error>>,
+    outbound_stream:
+        futures_bounded::FuturesSet<Result<(Vec<Multiaddr>, Duration), outbound::Error>>,
 
     /// The addresses we will send to the other party for hole-punching attempts.
     holepunch_candidates: Vec<Multiaddr>,
 
     attempts: u8,
+    max_attempts: u8,
 }
 
 impl Handler {
-    pub fn new(endpoint: ConnectedPoint, holepunch_candidates: Vec<Multiaddr>) -> Self {
+    pub fn new(
+        endpoint: ConnectedPoint,
+        holepunch_candidates: Vec<Multiaddr>,
+        max_attempts: u8,
+    ) -> Self {
         Self {
             endpoint,
             queued_events: Default::default(),
@@ -86,6 +101,7 @@ impl Handler {
             outbound_stream: futures_bounded::FuturesSet::new(Duration::from_secs(10), 1),
             holepunch_candidates,
             attempts: 0,
+            max_attempts,
         }
     }
 
@@ -206,7 +222,9 @@ impl ConnectionHandler for Handler {
             Command::Connect => {
                 self.queued_events
                     .push_back(ConnectionHandlerEvent::OutboundSubstreamRequest {
-                        protocol: SubstreamProtocol::new(ReadyUpgrade::new(PROTOCOL_NAME), ()),
+                        // Hole punching relies on timing the simultaneous dials precisely.
+                        protocol: SubstreamProtocol::new(ReadyUpgrade::new(PROTOCOL_NAME), ())
+                            .with_priority(StreamPriority::HIGH),
                     });
                 self.attempts += 1;
             }
@@ -214,7 +232,7 @@ impl ConnectionHandler for Handler {
     }
 
     fn connection_keep_alive(&self) -> bool {
-        if self.attempts < MAX_NUMBER_OF_UPGRADE_ATTEMPTS {
+        if self.attempts < self.max_attempts {
             return true;
         }
 
@@ -234,10 +252,11 @@ impl ConnectionHandler for Handler {
         }
 
         match self.inbound_stream.poll_unpin(cx) {
-            Poll::Ready(Ok(Ok(addresses))) => {
+            Poll::Ready(Ok(Ok((addresses, rtt)))) => {
                 return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
                     Event::InboundConnectNegotiated {
                         remote_addrs: addresses,
+                        rtt,
                     },
                 ))
             }
@@ -257,10 +276,11 @@ impl ConnectionHandler for Handler {
         }
 
         match self.outbound_stream.poll_unpin(cx) {
-            Poll::Ready(Ok(Ok(addresses))) => {
+            Poll::Ready(Ok(Ok((addresses, rtt)))) => {
                 return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
                     Event::OutboundConnectNegotiated {
                         remote_addrs: addresses,
+                        rtt,
                     },
                 ))
             }
@@ -25,6 +25,7 @@
 
 mod behaviour;
 mod handler;
+mod port_prediction;
 mod protocol;
 
 mod proto {
@@ -33,7 +34,7 @@ mod proto {
     pub(crate) use self::holepunch::pb::{mod_HolePunch::*, HolePunch};
 }
 
-pub use behaviour::{Behaviour, Error, Event};
+pub use behaviour::{AddressFamily, Attempt, Behaviour, Config, Error, Event};
 pub use protocol::PROTOCOL_NAME;
 pub mod inbound {
     pub use crate::protocol::inbound::ProtocolViolation;
@@ -0,0 +1,153 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Prediction of the ports of a remote behind an endpoint-dependent NAT.
+
+use libp2p_core::multiaddr::{Multiaddr, Protocol};
+
+/// The maximum number of addresses with predicted ports dialed per hole-punch attempt.
+pub(crate) const MAX_FAN_OUT: u8 = 16;
+
+/// The maximum distance between the ports observed for a remote that is considered a
+/// sequential allocation of ports by its NAT.
+const MAX_PORT_STEP: u16 = 64;
+
+/// Predicts further addresses of a remote behind an endpoint-dependent ("symmetric") NAT.
+///
+/// Such a NAT maps each connection of the remote to a different port, hence the addresses that
+/// different peers observed for the remote only differ in their port. As most of these NATs
+/// allocate ports sequentially, the port mapped for the hole-punch likely continues the
+/// sequence of the observed ports.
+///
+/// Returns up to `fan_out` addresses continuing the sequence of the ports of the address with
+/// the most observed ports, or none if the remote does not appear to be behind an
+/// endpoint-dependent NAT allocating ports sequentially.
+pub(crate) fn predict(addresses: &[Multiaddr], fan_out: u8) -> Vec<Multiaddr> {
+    let fan_out = fan_out.min(MAX_FAN_OUT);
+    if fan_out == 0 {
+        return Vec::new();
+    }
+
+    // The observed ports per address, with the port replaced by zero.
+    let mut observed: Vec<(Multiaddr, Vec<u16>)> = Vec::new();
+    for address in addresses {
+        let Some(port) = port_of(address) else {
+            continue;
+        };
+        let template = with_port(address, 0);
+        match observed.iter_mut().find(|(a, _)| *a == template) {
+            Some((_, ports)) => ports.push(port),
+            None => observed.push((template, vec![port])),
+        }
+    }
+
+    let Some((template, mut ports)) = observed
+        .into_iter()
+        .filter(|(_, ports)| ports.len() > 1)
+        .max_by_key(|(_, ports)| ports.len())
+    else {
+        return Vec::new();
+    };
+    ports.sort_unstable();
+    ports.dedup();
+
+    let Some(step) = ports.windows(2).map(|w| w[1] - w[0]).min() else {
+        return Vec::new();
+    };
+    if step > MAX_PORT_STEP {
+        return Vec::new();
+    }
+    let last = *ports.last().expect("at least two ports");
+
+    (1..=u16::from(fan_out))
+        .map_while(|i| last.checked_add(step * i))
+        .map(|port| with_port(&template, port))
+        .collect()
+}
+
+/// The TCP or UDP port of the address.
+fn port_of(address: &Multiaddr) -> Option<u16> {
+    address.iter().find_map(|p| match p {
+        Protocol::Tcp(port) | Protocol::Udp(port) => Some(port),
+        _ => None,
+    })
+}
+
+fn with_port(address: &Multiaddr, port: u16) -> Multiaddr {
+    address
+        .iter()
+        .map(|p| match p {
+            Protocol::Tcp(_) => Protocol::Tcp(port),
+            Protocol::Udp(_) => Protocol::Udp(port),
+            p => p,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continues_sequence_of_observed_ports() {
+        let addresses = [
+            "/ip4/1.2.3.4/udp/40002/quic-v1".parse().unwrap(),
+            "/ip4/1.2.3.4/udp/40000/quic-v1".parse().unwrap(),
+            "/ip4/1.2.3.4/tcp/4001".parse().unwrap(),
+        ];
+
+        assert_eq!(
+            predict(&addresses, 3),
+            vec![
+                "/ip4/1.2.3.4/udp/40004/quic-v1"
+                    .parse::<Multiaddr>()
+                    .unwrap(),
+                "/ip4/1.2.3.4/udp/40006/quic-v1".parse().unwrap(),
+                "/ip4/1.2.3.4/udp/40008/quic-v1".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn predicts_nothing_for_endpoint_independent_mappings() {
+        let addresses = [
+            "/ip4/1.2.3.4/tcp/4001".parse().unwrap(),
+            "/ip4/1.2.3.4/tcp/4001".parse().unwrap(),
+            "/ip4/5.6.7.8/tcp/4002".parse().unwrap(),
+        ];
+        assert!(predict(&addresses, 8).is_empty());
+
+        let random_ports = [
+            "/ip4/1.2.3.4/tcp/4001".parse().unwrap(),
+            "/ip4/1.2.3.4/tcp/61234".parse().unwrap(),
+        ];
+        assert!(predict(&random_ports, 8).is_empty());
+    }
+
+    #[test]
+    fn caps_fan_out() {
+        let addresses = [
+            "/ip4/1.2.3.4/tcp/4001".parse().unwrap(),
+            "/ip4/1.2.3.4/tcp/4002".parse().unwrap(),
+        ];
+        assert_eq!(predict(&addresses, u8::MAX).len(), usize::from(MAX_FAN_OUT));
+        assert!(predict(&addresses, 0).is_empty());
+    }
+}
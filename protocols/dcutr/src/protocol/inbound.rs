@@ -21,15 +21,17 @@
 use crate::proto;
 use asynchronous_codec::Framed;
 use futures::prelude::*;
+use instant::Instant;
 use libp2p_core::{multiaddr::Protocol, Multiaddr};
 use libp2p_swarm::Stream;
 use std::io;
+use std::time::Duration;
 use thiserror::Error;
 
 pub(crate) async fn handshake(
     stream: Stream,
     candidates: Vec<Multiaddr>,
-) -> Result<Vec<Multiaddr>, Error> {
+) -> Result<(Vec<Multiaddr>, Duration), Error> {
     let mut stream = Framed::new(
         stream,
         quick_protobuf_codec::Codec::new(super::MAX_MESSAGE_SIZE_BYTES),
@@ -74,6 +76,9 @@ pub(crate) async fn handshake(
     };
 
     stream.send(msg).await?;
+
+    let sent_time = Instant::now();
+
     let proto::HolePunch { type_pb, .. } = stream
         .next()
         .await
@@ -83,7 +88,9 @@ pub(crate) async fn handshake(
         return Err(Error::Protocol(ProtocolViolation::UnexpectedTypeConnect));
     }
 
-    Ok(obs_addrs)
+    let rtt = sent_time.elapsed();
+
+    Ok((obs_addrs, rtt))
 }
 
 #[derive(Debug, Error)]
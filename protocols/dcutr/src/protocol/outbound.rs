@@ -27,12 +27,13 @@ use instant::Instant;
 use libp2p_core::{multiaddr::Protocol, Multiaddr};
 use libp2p_swarm::Stream;
 use std::io;
+use std::time::Duration;
 use thiserror::Error;
 
 pub(crate) async fn handshake(
     stream: Stream,
     candidates: Vec<Multiaddr>,
-) -> Result<Vec<Multiaddr>, Error> {
+) -> Result<(Vec<Multiaddr>, Duration), Error> {
     let mut stream = Framed::new(
         stream,
         quick_protobuf_codec::Codec::new(super::MAX_MESSAGE_SIZE_BYTES),
@@ -91,7 +92,7 @@ pub(crate) async fn handshake(
 
     Delay::new(rtt / 2).await;
 
-    Ok(obs_addrs)
+    Ok((obs_addrs, rtt))
 }
 
 #[derive(Debug, Error)]
@@ -85,17 +85,22 @@ async fn connect() {
         })
         .await;
 
-    let reported_conn_id = src
+    let (reported_conn_id, attempts, address_family) = src
         .wait(move |e| match e {
             SwarmEvent::Behaviour(ClientEvent::Dcutr(dcutr::Event {
                 result: Ok(connection_id),
+                attempts,
+                address_family,
                 ..
-            })) => Some(connection_id),
+            })) => Some((connection_id, attempts, address_family)),
             _ => None,
         })
         .await;
 
     assert_eq!(established_conn_id, reported_conn_id);
+    assert_eq!(attempts.len(), 1);
+    assert!(attempts[0].rtt.is_some());
+    assert_eq!(address_family, Some(dcutr::AddressFamily::Ip4));
 }
 
 fn build_relay() -> Swarm<Relay> {
@@ -204,6 +209,7 @@ async fn wait_for_reservation(
                 assert_eq!(address, client_addr);
             }
             SwarmEvent::NewExternalAddrOfPeer { .. } => {}
+            SwarmEvent::RemoteProtocolsChanged { .. } => {}
             e => panic!("{e:?}"),
         }
     }
@@ -20,39 +20,49 @@
 
 use std::{
     cmp::{max, Ordering},
+    collections::hash_map::Entry,
     collections::HashSet,
     collections::VecDeque,
     collections::{BTreeSet, HashMap},
-    fmt,
+    fmt, io,
     net::IpAddr,
+    sync::{Arc, Weak},
     task::{Context, Poll},
     time::Duration,
 };
 
-use futures::StreamExt;
+use futures::{Future, StreamExt};
 use futures_ticker::Ticker;
+use hashlink::LinkedHashMap;
 use prometheus_client::registry::Registry;
 use rand::{seq::SliceRandom, thread_rng};
 
 use instant::Instant;
-use libp2p_core::{multiaddr::Protocol::Ip4, multiaddr::Protocol::Ip6, Endpoint, Multiaddr};
+use libp2p_core::{
+    multiaddr::Protocol::Ip4, multiaddr::Protocol::Ip6, Endpoint, Multiaddr, PeerRecord,
+};
 use libp2p_identity::Keypair;
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
-    behaviour::{AddressChange, ConnectionClosed, ConnectionEstablished, FromSwarm},
+    behaviour::{AddressChange, ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm},
     dial_opts::DialOpts,
-    ConnectionDenied, ConnectionId, NetworkBehaviour, NotifyHandler, THandler, THandlerInEvent,
-    THandlerOutEvent, ToSwarm,
+    ConnectionDenied, ConnectionId, DialError, NetworkBehaviour, NotifyHandler, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 
 use crate::backoff::BackoffStorage;
+use crate::choke::Choking;
 use crate::config::{Config, ValidationMode};
 use crate::gossip_promises::GossipPromises;
 use crate::handler::{Handler, HandlerEvent, HandlerIn};
 use crate::mcache::MessageCache;
 use crate::metrics::{Churn, Config as MetricsConfig, Inclusion, Metrics, Penalty};
-use crate::peer_score::{PeerScore, PeerScoreParams, PeerScoreThresholds, RejectReason};
+use crate::peer_score::{
+    Backend, PeerScore, PeerScoreParams, PeerScoreThresholds, RejectReason, ScoringBackend,
+};
 use crate::protocol::SIGNING_PREFIX;
+use crate::queue::{MessageCounts, QueueDepth};
+use crate::seen_cache::SeenCacheStore;
 use crate::subscription_filter::{AllowAllSubscriptionFilter, TopicSubscriptionFilter};
 use crate::time_cache::DuplicateCache;
 use crate::topic::{Hasher, Topic, TopicHash};
@@ -62,6 +72,7 @@ use crate::types::{
     SubscriptionAction,
 };
 use crate::types::{PeerConnections, PeerKind, RpcOut};
+use crate::validation::{Validated, ValidatorConfig, ValidatorPool};
 use crate::{rpc_proto::proto, TopicScoreParams};
 use crate::{PublishError, SubscriptionError, ValidationError};
 use instant::SystemTime;
@@ -71,6 +82,12 @@ use std::{cmp::Ordering::Equal, fmt::Debug};
 #[cfg(test)]
 mod tests;
 
+/// The maximum number of message ids received via IDONTWANT we track per peer.
+const IDONTWANT_CAP: usize = 10_000;
+
+/// How long message ids received via IDONTWANT are kept before they expire.
+const IDONTWANT_TIMEOUT: Duration = Duration::from_secs(3);
+
 /// Determines if published messages should be signed or not.
 ///
 /// Without signing, a number of privacy preserving modes can be selected.
@@ -144,6 +161,15 @@ pub enum Event {
     },
     /// A peer that does not support gossipsub has connected.
     GossipsubNotSupported { peer_id: PeerId },
+    /// Messages to a peer have been dropped because its send queues were full.
+    ///
+    /// See [`ConfigBuilder::send_queue_size`](crate::ConfigBuilder::send_queue_size).
+    SlowPeer {
+        /// The peer whose send queues were full.
+        peer_id: PeerId,
+        /// The number of messages dropped per [`MessageClass`](crate::MessageClass).
+        dropped: MessageCounts,
+    },
 }
 
 /// A data structure for storing configuration for publishing messages. See [`MessageAuthenticity`]
@@ -161,6 +187,15 @@ enum PublishConfig {
     Anonymous,
 }
 
+/// The backoff of an explicit peer after failed dials.
+#[derive(Debug)]
+struct ExplicitPeerBackoff {
+    /// The time waited after the last failed dial.
+    backoff: Duration,
+    /// When to redial the peer, [`None`] if a dial is in progress.
+    retry_at: Option<Instant>,
+}
+
 /// A strictly linearly increasing sequence number.
 ///
 /// We start from the current time as unix timestamp in milliseconds.
@@ -254,10 +289,20 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// duplicates from being propagated to the application and on the network.
     duplicate_cache: DuplicateCache<MessageId>,
 
+    /// Optionally persists the IDs of the messages in the `duplicate_cache` across restarts.
+    seen_cache_store: Option<Box<dyn SeenCacheStore>>,
+
+    /// Validates received messages before they are delivered and forwarded, if set via
+    /// [`Behaviour::with_validator`].
+    validator: Option<ValidatorPool>,
+
     /// A set of connected peers, indexed by their [`PeerId`] tracking both the [`PeerKind`] and
     /// the set of [`ConnectionId`]s.
     connected_peers: HashMap<PeerId, PeerConnections>,
 
+    /// The depths of the send queues of the handler of each connection.
+    send_queue_depths: HashMap<ConnectionId, Weak<QueueDepth>>,
+
     /// A map of all connected peers - A map of topic hash to a list of gossipsub peer Ids.
     topic_peers: HashMap<TopicHash, BTreeSet<PeerId>>,
 
@@ -268,6 +313,9 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// forward messages to, outside of the scoring system.
     explicit_peers: HashSet<PeerId>,
 
+    /// The dial backoff of explicit peers whose last dial failed.
+    explicit_peer_backoffs: HashMap<PeerId, ExplicitPeerBackoff>,
+
     /// A list of peers that have been blacklisted by the user.
     /// Messages are not sent to and are rejected from these peers.
     blacklisted_peers: HashSet<PeerId>,
@@ -300,13 +348,16 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// be removed from this list which may result in a true outbound rediscovery.
     px_peers: HashSet<PeerId>,
 
+    /// Signed peer records of connected peers, sent to pruned peers in Peer eXchange.
+    peer_records: HashMap<PeerId, PeerRecord>,
+
     /// Set of connected outbound peers (we only consider true outbound peers found through
     /// discovery and not by PX).
     outbound_peers: HashSet<PeerId>,
 
     /// Stores optional peer score data together with thresholds, decay interval and gossip
     /// promises.
-    peer_score: Option<(PeerScore, PeerScoreThresholds, Ticker, GossipPromises)>,
+    peer_score: Option<(Backend, PeerScoreThresholds, Ticker, GossipPromises)>,
 
     /// Counts the number of `IHAVE` received from each peer since the last heartbeat.
     count_received_ihave: HashMap<PeerId, usize>,
@@ -322,6 +373,10 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// our own messages back if the messages are anonymous or use a random author.
     published_message_ids: DuplicateCache<MessageId>,
 
+    /// Tracks the delivery latency of mesh peers and which of them are choked, if choking is
+    /// enabled.
+    choking: Option<Choking>,
+
     /// The filter used to handle message subscriptions.
     subscription_filter: F,
 
@@ -442,9 +497,12 @@ where
             control_pool: HashMap::new(),
             publish_config: privacy.into(),
             duplicate_cache: DuplicateCache::new(config.duplicate_cache_time()),
+            seen_cache_store: None,
+            validator: None,
             topic_peers: HashMap::new(),
             peer_topics: HashMap::new(),
             explicit_peers: HashSet::new(),
+            explicit_peer_backoffs: HashMap::new(),
             blacklisted_peers: HashSet::new(),
             mesh: HashMap::new(),
             fanout: HashMap::new(),
@@ -461,13 +519,22 @@ where
             ),
             heartbeat_ticks: 0,
             px_peers: HashSet::new(),
+            peer_records: HashMap::new(),
             outbound_peers: HashSet::new(),
             peer_score: None,
             count_received_ihave: HashMap::new(),
             count_sent_iwant: HashMap::new(),
             pending_iwant_msgs: HashSet::new(),
             connected_peers: HashMap::new(),
+            send_queue_depths: HashMap::new(),
             published_message_ids: DuplicateCache::new(config.published_message_ids_cache_time()),
+            choking: config.choking().then(|| {
+                Choking::new(
+                    config.choke_duplicates_threshold(),
+                    config.unchoke_latency_threshold(),
+                    config.duplicate_cache_time(),
+                )
+            }),
             config,
             subscription_filter,
             data_transform,
@@ -517,6 +584,33 @@ where
             .map(|(score, ..)| score.score(peer_id))
     }
 
+    /// Lists the current scores of all connected peers. The iterator is empty if peer scoring
+    /// is not activated.
+    pub fn peer_scores(&self) -> impl Iterator<Item = (&PeerId, f64)> {
+        self.peer_score.iter().flat_map(|(score, ..)| {
+            self.connected_peers
+                .keys()
+                .map(|peer_id| (peer_id, score.score(peer_id)))
+        })
+    }
+
+    /// Returns the number of messages queued for a connected peer per
+    /// [`MessageClass`](crate::MessageClass), summed over all its connections.
+    pub fn send_queue_depth(&self, peer_id: &PeerId) -> Option<MessageCounts> {
+        let connections = self.connected_peers.get(peer_id)?;
+        let mut depth = MessageCounts::default();
+        for connection_id in &connections.connections {
+            if let Some(connection_depth) = self
+                .send_queue_depths
+                .get(connection_id)
+                .and_then(Weak::upgrade)
+            {
+                depth.add(&connection_depth.get());
+            }
+        }
+        Some(depth)
+    }
+
     /// Subscribe to a topic.
     ///
     /// Returns [`Ok(true)`] if the subscription worked. Returns [`Ok(false)`] if we were already
@@ -683,6 +777,7 @@ where
                 // Floodsub peers
                 for (peer, connections) in &self.connected_peers {
                     if connections.kind == PeerKind::Floodsub
+                        && set.contains(peer)
                         && !self
                             .score_below_threshold(peer, |ts| ts.publish_threshold)
                             .0
@@ -699,7 +794,9 @@ where
 
         // If the message isn't a duplicate and we have sent it to some peers add it to the
         // duplicate cache and memcache.
-        self.duplicate_cache.insert(msg_id.clone());
+        if self.duplicate_cache.insert(msg_id.clone()) {
+            self.persist_seen(&msg_id);
+        }
         self.mcache.put(&msg_id, raw_message.clone());
 
         // If the message is anonymous or has a random author add it to the published message ids
@@ -810,6 +907,11 @@ where
     }
 
     /// Adds a new peer to the list of explicitly connected peers.
+    ///
+    /// Explicit peers are dialed if not connected and redialed with an exponential backoff, see
+    /// [`Config::explicit_peer_dial_backoff`]. They are never added to the mesh, but receive all
+    /// messages published or forwarded on topics they are subscribed to, regardless of their
+    /// score.
     pub fn add_explicit_peer(&mut self, peer_id: &PeerId) {
         tracing::debug!(peer=%peer_id, "Adding explicit peer");
 
@@ -823,6 +925,31 @@ where
     pub fn remove_explicit_peer(&mut self, peer_id: &PeerId) {
         tracing::debug!(peer=%peer_id, "Removing explicit peer");
         self.explicit_peers.remove(peer_id);
+        self.explicit_peer_backoffs.remove(peer_id);
+    }
+
+    /// Adds the signed peer record of a connected peer, e.g. as received via identify.
+    ///
+    /// The record is sent to pruned peers if the peer is selected for Peer eXchange, allowing
+    /// them to dial the peer. It replaces any record of the peer with a lower sequence number and
+    /// is forgotten once the peer disconnects.
+    pub fn add_peer_record(&mut self, record: PeerRecord) {
+        let peer_id = record.peer_id();
+        if !self.connected_peers.contains_key(&peer_id) {
+            tracing::debug!(peer=%peer_id, "Ignoring signed peer record of disconnected peer");
+            return;
+        }
+
+        match self.peer_records.entry(peer_id) {
+            Entry::Occupied(mut e) => {
+                if e.get().seq() < record.seq() {
+                    e.insert(record);
+                }
+            }
+            Entry::Vacant(e) => {
+                e.insert(record);
+            }
+        }
     }
 
     /// Blacklists a peer. All messages from this peer will be rejected and any message that was
@@ -868,10 +995,67 @@ where
 
         let interval = Ticker::new(params.decay_interval);
         let peer_score = PeerScore::new_with_message_delivery_time_callback(params, callback);
-        self.peer_score = Some((peer_score, threshold, interval, GossipPromises::default()));
+        self.peer_score = Some((
+            Backend::Builtin(Box::new(peer_score)),
+            threshold,
+            interval,
+            GossipPromises::default(),
+        ));
+        Ok(())
+    }
+
+    /// Activates peer scoring with a custom [`ScoringBackend`] instead of the built-in one.
+    /// [`ScoringBackend::refresh_scores`] is called every `refresh_interval`. Returns an error if
+    /// the thresholds are not valid or if peer scoring got already activated.
+    pub fn with_scoring_backend(
+        &mut self,
+        backend: impl ScoringBackend,
+        threshold: PeerScoreThresholds,
+        refresh_interval: Duration,
+    ) -> Result<(), String> {
+        threshold.validate()?;
+
+        if self.peer_score.is_some() {
+            return Err("Peer score set twice".into());
+        }
+
+        self.peer_score = Some((
+            Backend::Custom(Box::new(backend)),
+            threshold,
+            Ticker::new(refresh_interval),
+            GossipPromises::default(),
+        ));
+        Ok(())
+    }
+
+    /// Persists the IDs of seen messages in the given [`SeenCacheStore`], such that messages seen
+    /// before a restart are neither delivered nor forwarded again afterwards. The IDs already
+    /// persisted in the store are added to the duplicate cache right away. Replaces any store set
+    /// before. Returns an error if loading the persisted IDs failed.
+    pub fn with_seen_cache_store(&mut self, mut store: impl SeenCacheStore) -> io::Result<()> {
+        for msg_id in store.load()? {
+            self.duplicate_cache.insert(msg_id);
+        }
+        self.seen_cache_store = Some(Box::new(store));
         Ok(())
     }
 
+    /// Validates received messages with the given async `validator` before delivering them via
+    /// [`Event::Message`] and forwarding them, instead of leaving validation to the application
+    /// via [`Behaviour::report_message_validation_result`]. Replaces any validator set before.
+    ///
+    /// Validations run concurrently within the limits of the [`ValidatorConfig`], but the
+    /// messages of a topic are delivered and forwarded in the order they were received. Messages
+    /// whose validation times out or exceeds the limits are ignored, i.e. neither delivered nor
+    /// forwarded, without penalizing the peer.
+    pub fn with_validator<V, Fut>(&mut self, config: ValidatorConfig, validator: V)
+    where
+        V: FnMut(PeerId, MessageId, Message) -> Fut + Send + 'static,
+        Fut: Future<Output = MessageAcceptance> + Send + 'static,
+    {
+        self.validator = Some(ValidatorPool::new(config, validator));
+    }
+
     /// Sets scoring parameters for a topic.
     ///
     /// The [`Self::with_peer_score()`] must first be called to initialise peer scoring.
@@ -890,7 +1074,7 @@ where
 
     /// Returns a scoring parameters for a topic if existent.
     pub fn get_topic_params<H: Hasher>(&self, topic: &Topic<H>) -> Option<&TopicScoreParams> {
-        self.peer_score.as_ref()?.0.get_topic_params(&topic.hash())
+        self.peer_score.as_ref()?.0.topic_params(&topic.hash())
     }
 
     /// Sets the application specific score for a peer. Returns true if scoring is active and
@@ -1061,7 +1245,10 @@ where
                 |p| p != peer && !self.score_below_threshold(p, |_| 0.0).0,
             )
             .into_iter()
-            .map(|p| PeerInfo { peer_id: Some(p) })
+            .map(|p| PeerInfo {
+                peer_id: Some(p),
+                signed_peer_record: self.peer_records.get(&p).cloned(),
+            })
             .collect()
         } else {
             Vec::new()
@@ -1114,15 +1301,24 @@ where
         tracing::debug!(topic=%topic_hash, "Completed LEAVE for topic");
     }
 
-    /// Checks if the given peer is still connected and if not dials the peer again.
+    /// Checks if the given peer is still connected and if not dials the peer again, unless it is
+    /// backing off from failed dials.
     fn check_explicit_peer_connection(&mut self, peer_id: &PeerId) {
-        if !self.peer_topics.contains_key(peer_id) {
-            // Connect to peer
-            tracing::debug!(peer=%peer_id, "Connecting to explicit peer");
-            self.events.push_back(ToSwarm::Dial {
-                opts: DialOpts::peer_id(*peer_id).build(),
-            });
+        if self.peer_topics.contains_key(peer_id) {
+            return;
+        }
+        if let Some(backoff) = self.explicit_peer_backoffs.get_mut(peer_id) {
+            match backoff.retry_at {
+                Some(retry_at) if retry_at <= Instant::now() => backoff.retry_at = None,
+                _ => return,
+            }
         }
+
+        // Connect to peer
+        tracing::debug!(peer=%peer_id, "Connecting to explicit peer");
+        self.events.push_back(ToSwarm::Dial {
+            opts: DialOpts::peer_id(*peer_id).build(),
+        });
     }
 
     /// Determines if a peer's score is below a given `PeerScoreThreshold` chosen via the
@@ -1136,7 +1332,7 @@ where
     }
 
     fn score_below_threshold_from_scores(
-        peer_score: &Option<(PeerScore, PeerScoreThresholds, Ticker, GossipPromises)>,
+        peer_score: &Option<(Backend, PeerScoreThresholds, Ticker, GossipPromises)>,
         peer_id: &PeerId,
         threshold: impl Fn(&PeerScoreThresholds) -> f64,
     ) -> (bool, f64) {
@@ -1217,6 +1413,10 @@ where
                 continue;
             }
 
+            if let Some(choking) = &mut self.choking {
+                choking.ihave_received(&topic, peer_id, &ids);
+            }
+
             for id in ids.into_iter().filter(want_message) {
                 // have not seen this message and are not currently requesting it
                 if iwant_ids.insert(id) {
@@ -1555,12 +1755,9 @@ where
                         continue;
                     }
 
-                    // NOTE: We cannot dial any peers from PX currently as we typically will not
-                    // know their multiaddr. Until SignedRecords are spec'd this
-                    // remains a stub. By default `config.prune_peers()` is set to zero and
-                    // this is skipped. If the user modifies this, this will only be able to
-                    // dial already known peers (from an external discovery mechanism for
-                    // example).
+                    // Peers without a signed peer record can only be dialed if their addresses are
+                    // known otherwise, e.g. from an external discovery mechanism. By default
+                    // `config.prune_peers()` is set to zero and this is skipped.
                     if self.config.prune_peers() > 0 {
                         self.px_connect(px);
                     }
@@ -1572,11 +1769,12 @@ where
 
     fn px_connect(&mut self, mut px: Vec<PeerInfo>) {
         let n = self.config.prune_peers();
-        // Ignore peerInfo with no ID
-        //
-        //TODO: Once signed records are spec'd: Can we use peerInfo without any IDs if they have a
-        // signed peer record?
-        px.retain(|p| p.peer_id.is_some());
+        // Ignore peerInfo with no ID and, if required, without a signed peer record. The ID is
+        // taken from the signed peer record when decoding if absent.
+        px.retain(|p| {
+            p.peer_id.is_some()
+                && (p.signed_peer_record.is_some() || !self.config.require_signed_px_records())
+        });
         if px.len() > n {
             // only use at most prune_peers many random peers
             let mut rng = thread_rng();
@@ -1585,16 +1783,19 @@ where
         }
 
         for p in px {
-            // TODO: Once signed records are spec'd: extract signed peer record if given and handle
-            // it, see https://github.com/libp2p/specs/pull/217
             if let Some(peer_id) = p.peer_id {
                 // mark as px peer
                 self.px_peers.insert(peer_id);
 
-                // dial peer
-                self.events.push_back(ToSwarm::Dial {
-                    opts: DialOpts::peer_id(peer_id).build(),
-                });
+                // dial peer, at the addresses of its signed peer record if given. The record was
+                // validated to belong to the peer when decoding.
+                let opts = match p.signed_peer_record {
+                    Some(record) => DialOpts::peer_id(peer_id)
+                        .addresses(record.addresses().to_vec())
+                        .build(),
+                    None => DialOpts::peer_id(peer_id).build(),
+                };
+                self.events.push_back(ToSwarm::Dial { opts });
             }
         }
     }
@@ -1651,7 +1852,7 @@ where
         // If we are not validating messages, assume this message is validated
         // This will allow the message to be gossiped without explicitly calling
         // `validate_message`.
-        if !self.config.validate_messages() {
+        if !self.validates_messages() {
             raw_message.validated = true;
         }
 
@@ -1720,6 +1921,9 @@ where
             if let Some((peer_score, ..)) = &mut self.peer_score {
                 peer_score.duplicated_message(propagation_source, &msg_id, &message.topic);
             }
+            if let Some(choking) = &mut self.choking {
+                choking.message_received(&msg_id, &message.topic, propagation_source, true);
+            }
             self.mcache.observe_duplicate(&msg_id, propagation_source);
             return;
         }
@@ -1727,12 +1931,23 @@ where
             message=%msg_id,
             "Put message in duplicate_cache and resolve promises"
         );
+        self.persist_seen(&msg_id);
 
         // Record the received message with the metrics
         if let Some(metrics) = self.metrics.as_mut() {
             metrics.msg_recvd(&message.topic);
         }
 
+        if let Some(choking) = &mut self.choking {
+            choking.message_received(&msg_id, &message.topic, propagation_source, false);
+        }
+
+        // Tell our mesh peers not to send us this message again, if it is large enough to make
+        // the additional control message worthwhile.
+        if raw_message.raw_protobuf_len() > self.config.idontwant_message_size_threshold() {
+            self.send_idontwant(&raw_message, &msg_id, propagation_source);
+        }
+
         // Tells score that message arrived (but is maybe not fully validated yet).
         // Consider the message as delivered for gossip promises.
         if let Some((peer_score, .., gossip_promises)) = &mut self.peer_score {
@@ -1745,6 +1960,19 @@ where
 
         // Dispatch the message to the user if we are subscribed to any of the topics
         if self.mesh.contains_key(&message.topic) {
+            if let Some(validator) = &mut self.validator {
+                tracing::debug!("Validating received message");
+                if !validator.push(*propagation_source, msg_id.clone(), message) {
+                    tracing::debug!(message=%msg_id, "Too many messages being validated, ignoring message");
+                    let _ = self.report_message_validation_result(
+                        &msg_id,
+                        propagation_source,
+                        MessageAcceptance::Ignore,
+                    );
+                }
+                return;
+            }
+
             tracing::debug!("Sending received message to user");
             self.events
                 .push_back(ToSwarm::GenerateEvent(Event::Message {
@@ -1761,7 +1989,7 @@ where
         }
 
         // forward the message to mesh peers, if no validation is required
-        if !self.config.validate_messages() {
+        if !self.validates_messages() {
             if self
                 .forward_msg(
                     &msg_id,
@@ -1777,6 +2005,129 @@ where
         }
     }
 
+    /// Whether received messages are only forwarded once validated, either by the application or
+    /// by the validator set via [`Behaviour::with_validator`].
+    fn validates_messages(&self) -> bool {
+        self.config.validate_messages() || self.validator.is_some()
+    }
+
+    /// Delivers and forwards or drops a message validated by the validator set via
+    /// [`Behaviour::with_validator`].
+    fn on_message_validated(&mut self, validated: Validated) {
+        let Validated {
+            propagation_source,
+            message_id,
+            message,
+            acceptance,
+        } = validated;
+
+        if acceptance == MessageAcceptance::Accept {
+            tracing::debug!("Sending received message to user");
+            self.events
+                .push_back(ToSwarm::GenerateEvent(Event::Message {
+                    propagation_source,
+                    message_id: message_id.clone(),
+                    message,
+                }));
+        }
+
+        if let Err(e) =
+            self.report_message_validation_result(&message_id, &propagation_source, acceptance)
+        {
+            tracing::error!(message=%message_id, "Failed to forward validated message: {e}");
+        }
+    }
+
+    /// Sends an IDONTWANT control message for the given message to all mesh peers of its topic
+    /// that support gossipsub v1.2, except the peer it was received from and its source.
+    fn send_idontwant(
+        &mut self,
+        message: &RawMessage,
+        msg_id: &MessageId,
+        propagation_source: &PeerId,
+    ) {
+        let Some(mesh_peers) = self.mesh.get(&message.topic) else {
+            return;
+        };
+
+        let recipient_peers = mesh_peers
+            .iter()
+            .filter(|peer_id| {
+                *peer_id != propagation_source
+                    && Some(*peer_id) != message.source.as_ref()
+                    && self.connected_peers.get(peer_id).map_or(false, |peer| {
+                        matches!(peer.kind, PeerKind::Gossipsubv1_2 | PeerKind::Episub)
+                    })
+            })
+            .copied()
+            .collect::<Vec<_>>();
+
+        for peer_id in recipient_peers {
+            tracing::debug!(peer=%peer_id, message=%msg_id, "Sending IDONTWANT to peer");
+            self.send_message(
+                peer_id,
+                RpcOut::Control(ControlAction::IDontWant {
+                    message_ids: vec![msg_id.clone()],
+                }),
+            );
+        }
+    }
+
+    /// Handles an IDONTWANT control message. The given messages won't be forwarded to the peer
+    /// until the entries expire.
+    fn handle_idontwant(&mut self, peer_id: &PeerId, message_ids: Vec<MessageId>) {
+        let Some(peer) = self.connected_peers.get_mut(peer_id) else {
+            tracing::error!(peer=%peer_id, "IDONTWANT: Received message from an unknown peer");
+            return;
+        };
+
+        let now = Instant::now();
+        for message_id in message_ids {
+            // Bound the number of tracked ids, dropping the oldest ones first.
+            if peer.dont_send.len() >= IDONTWANT_CAP {
+                peer.dont_send.pop_front();
+            }
+            peer.dont_send.insert(message_id, now);
+        }
+    }
+
+    /// Handles a CHOKE or UNCHOKE control message of a mesh peer. Messages of the topic are only
+    /// announced to peers that choked us, until they unchoke us again.
+    fn handle_choke(&mut self, peer_id: &PeerId, topic_hash: TopicHash, choked: bool) {
+        let Some(choking) = &mut self.choking else {
+            tracing::debug!(peer=%peer_id, "CHOKE: Ignoring message, choking is disabled");
+            return;
+        };
+
+        if !self
+            .mesh
+            .get(&topic_hash)
+            .map_or(false, |peers| peers.contains(peer_id))
+        {
+            tracing::debug!(
+                peer=%peer_id,
+                topic=%topic_hash,
+                "CHOKE: Ignoring message of peer not in our mesh"
+            );
+            return;
+        }
+
+        if !self
+            .connected_peers
+            .get(peer_id)
+            .map_or(false, |peer| peer.kind == PeerKind::Episub)
+        {
+            tracing::debug!(
+                peer=%peer_id,
+                "CHOKE: Ignoring message of peer that didn't negotiate choking"
+            );
+            return;
+        }
+
+        tracing::debug!(peer=%peer_id, topic=%topic_hash, %choked, "Peer changed choke state");
+        choking.set_choked_by(topic_hash, *peer_id, choked);
+    }
+
     // Handles invalid messages received.
     fn handle_invalid_message(
         &mut self,
@@ -1876,7 +2227,10 @@ where
                             self.connected_peers
                                 .get(propagation_source)
                                 .map(|v| &v.kind),
-                            Some(PeerKind::Gossipsubv1_1) | Some(PeerKind::Gossipsub)
+                            Some(PeerKind::Episub)
+                                | Some(PeerKind::Gossipsubv1_2)
+                                | Some(PeerKind::Gossipsubv1_1)
+                                | Some(PeerKind::Gossipsub)
                         )
                         && !Self::score_below_threshold_from_scores(
                             &self.peer_score,
@@ -1996,12 +2350,27 @@ where
     }
 
     /// Heartbeat function which shifts the memcache and updates the mesh.
+    /// Reports a message that entered the duplicate cache to the seen cache store, if any.
+    fn persist_seen(&mut self, msg_id: &MessageId) {
+        if let Some(store) = &mut self.seen_cache_store {
+            if let Err(e) = store.insert(msg_id, self.config.duplicate_cache_time()) {
+                tracing::warn!(message=%msg_id, "Failed to persist seen message: {e}");
+            }
+        }
+    }
+
     fn heartbeat(&mut self) {
         tracing::debug!("Starting heartbeat");
         let start = Instant::now();
 
         self.heartbeat_ticks += 1;
 
+        if let Some(store) = &mut self.seen_cache_store {
+            if let Err(e) = store.flush() {
+                tracing::warn!("Failed to persist seen messages: {e}");
+            }
+        }
+
         let mut to_graft = HashMap::new();
         let mut to_prune = HashMap::new();
         let mut no_px = HashSet::new();
@@ -2009,6 +2378,16 @@ where
         // clean up expired backoffs
         self.backoffs.heartbeat();
 
+        // clean up expired IDONTWANT entries
+        for peer in self.connected_peers.values_mut() {
+            while let Some((_, received)) = peer.dont_send.front() {
+                if *received + IDONTWANT_TIMEOUT > start {
+                    break;
+                }
+                peer.dont_send.pop_front();
+            }
+        }
+
         // clean up ihave counters
         self.count_sent_iwant.clear();
         self.count_received_ihave.clear();
@@ -2021,6 +2400,18 @@ where
             for p in self.explicit_peers.clone() {
                 self.check_explicit_peer_connection(&p);
             }
+        } else {
+            // redial explicit peers whose backoff expired
+            let now = Instant::now();
+            let due = self
+                .explicit_peer_backoffs
+                .iter()
+                .filter(|(_, backoff)| backoff.retry_at.is_some_and(|t| t <= now))
+                .map(|(peer, _)| *peer)
+                .collect::<Vec<_>>();
+            for p in due {
+                self.check_explicit_peer_connection(&p);
+            }
         }
 
         // Cache the scores of all connected peers, and record metrics for current penalties.
@@ -2380,6 +2771,33 @@ where
             })
         }
 
+        // choke mesh peers delivering mostly duplicates and unchoke fast ones
+        if let Some(choking) = &mut self.choking {
+            let connected_peers = &self.connected_peers;
+            let (to_choke, to_unchoke) =
+                choking.heartbeat(&self.mesh, self.config.mesh_n_low(), |peer| {
+                    connected_peers
+                        .get(peer)
+                        .map_or(false, |p| p.kind == PeerKind::Episub)
+                });
+            for (peer, topic_hash) in to_choke {
+                tracing::debug!(%peer, topic=%topic_hash, "HEARTBEAT: Choking peer");
+                Self::control_pool_add(
+                    &mut self.control_pool,
+                    peer,
+                    ControlAction::Choke { topic_hash },
+                );
+            }
+            for (peer, topic_hash) in to_unchoke {
+                tracing::debug!(%peer, topic=%topic_hash, "HEARTBEAT: Unchoking peer");
+                Self::control_pool_add(
+                    &mut self.control_pool,
+                    peer,
+                    ControlAction::Unchoke { topic_hash },
+                );
+            }
+        }
+
         self.emit_gossip();
 
         // send graft/prunes
@@ -2394,7 +2812,19 @@ where
         self.mcache.shift();
 
         tracing::debug!("Completed Heartbeat");
+        // Connections denied after their handler was created are never reported as closed.
+        self.send_queue_depths
+            .retain(|_, depth| depth.strong_count() > 0);
+
         if let Some(metrics) = self.metrics.as_mut() {
+            let mut depth = MessageCounts::default();
+            for connection_depth in self.send_queue_depths.values() {
+                if let Some(connection_depth) = connection_depth.upgrade() {
+                    depth.add(&connection_depth.get());
+                }
+            }
+            metrics.set_send_queue_depth(&depth);
+
             let duration = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
             metrics.observe_heartbeat_duration(duration);
         }
@@ -2598,6 +3028,52 @@ where
                     }
                 }
             }
+
+            // Floodsub peers never join the mesh, thus forward to all subscribed ones.
+            if let Some(topic_peers) = self.topic_peers.get(topic) {
+                for peer_id in topic_peers {
+                    if Some(peer_id) != propagation_source
+                        && !originating_peers.contains(peer_id)
+                        && Some(peer_id) != message.source.as_ref()
+                        && self
+                            .connected_peers
+                            .get(peer_id)
+                            .map_or(false, |peer| peer.kind == PeerKind::Floodsub)
+                    {
+                        recipient_peers.insert(*peer_id);
+                    }
+                }
+            }
+        }
+
+        // Don't forward the message to peers that told us they already have it.
+        recipient_peers.retain(|peer_id| {
+            self.connected_peers
+                .get(peer_id)
+                .map_or(true, |peer| !peer.dont_send.contains_key(msg_id))
+        });
+
+        // Only announce the message to mesh peers that choked us.
+        let mut announce_peers = Vec::new();
+        if let Some(choking) = &self.choking {
+            recipient_peers.retain(|peer_id| {
+                if choking.is_choked_by(&message.topic, peer_id) {
+                    announce_peers.push(*peer_id);
+                    return false;
+                }
+                true
+            });
+        }
+        let announced = !announce_peers.is_empty();
+        for peer in announce_peers {
+            tracing::debug!(%peer, message=%msg_id, "Announcing message to choking peer");
+            self.send_message(
+                peer,
+                RpcOut::Control(ControlAction::IHave {
+                    topic_hash: message.topic.clone(),
+                    message_ids: vec![msg_id.clone()],
+                }),
+            );
         }
 
         // forward the message to peers
@@ -2611,7 +3087,7 @@ where
             tracing::debug!("Completed forwarding message");
             Ok(true)
         } else {
-            Ok(false)
+            Ok(announced)
         }
     }
 
@@ -2758,6 +3234,8 @@ where
         // Diverging from the go implementation we only want to consider a peer as outbound peer
         // if its first connection is outbound.
 
+        self.explicit_peer_backoffs.remove(&peer_id);
+
         if endpoint.is_dialer() && other_established == 0 && !self.px_peers.contains(&peer_id) {
             // The first connection is outbound and it is not a peer from peer exchange => mark
             // it as outbound peer
@@ -2787,6 +3265,7 @@ where
             .or_insert(PeerConnections {
                 kind: PeerKind::Floodsub,
                 connections: vec![],
+                dont_send: LinkedHashMap::new(),
             })
             .connections
             .push(connection_id);
@@ -2815,6 +3294,42 @@ where
         }
     }
 
+    fn new_handler(&mut self, connection_id: ConnectionId) -> Handler {
+        let depth = Arc::new(QueueDepth::default());
+        self.send_queue_depths
+            .insert(connection_id, Arc::downgrade(&depth));
+
+        Handler::new(
+            self.config.protocol_config(),
+            self.config.send_queue_config(),
+            depth,
+        )
+    }
+
+    fn on_dial_failure(&mut self, DialFailure { peer_id, error, .. }: DialFailure) {
+        let Some(peer_id) = peer_id.filter(|p| self.explicit_peers.contains(p)) else {
+            return;
+        };
+        if matches!(error, DialError::DialPeerConditionFalse(_)) {
+            return;
+        }
+
+        let backoff = match self.explicit_peer_backoffs.get(&peer_id) {
+            Some(previous) => {
+                (previous.backoff * 2).min(self.config.explicit_peer_max_dial_backoff())
+            }
+            None => self.config.explicit_peer_dial_backoff(),
+        };
+        tracing::debug!(peer=%peer_id, ?backoff, "Failed to dial explicit peer, backing off");
+        self.explicit_peer_backoffs.insert(
+            peer_id,
+            ExplicitPeerBackoff {
+                backoff,
+                retry_at: Some(Instant::now() + backoff),
+            },
+        );
+    }
+
     fn on_connection_closed(
         &mut self,
         ConnectionClosed {
@@ -2825,6 +3340,8 @@ where
             ..
         }: ConnectionClosed,
     ) {
+        self.send_queue_depths.remove(&connection_id);
+
         // Remove IP from peer scoring system
         if let Some((peer_score, ..)) = &mut self.peer_score {
             if let Some(ip) = get_ip_addr(endpoint.get_remote_address()) {
@@ -2919,8 +3436,9 @@ where
                 }
             }
 
-            // Forget px and outbound status for this peer
+            // Forget px and outbound status and the signed peer record of this peer
             self.px_peers.remove(&peer_id);
+            self.peer_records.remove(&peer_id);
             self.outbound_peers.remove(&peer_id);
 
             // Remove peer from peer_topics and connected_peers
@@ -2997,22 +3515,22 @@ where
 
     fn handle_established_inbound_connection(
         &mut self,
-        _: ConnectionId,
+        connection_id: ConnectionId,
         _: PeerId,
         _: &Multiaddr,
         _: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        Ok(Handler::new(self.config.protocol_config()))
+        Ok(self.new_handler(connection_id))
     }
 
     fn handle_established_outbound_connection(
         &mut self,
-        _: ConnectionId,
+        connection_id: ConnectionId,
         _: PeerId,
         _: &Multiaddr,
         _: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        Ok(Handler::new(self.config.protocol_config()))
+        Ok(self.new_handler(connection_id))
     }
 
     fn on_connection_handler_event(
@@ -3022,6 +3540,31 @@ where
         handler_event: THandlerOutEvent<Self>,
     ) {
         match handler_event {
+            HandlerEvent::MessagesDropped(dropped) => {
+                tracing::debug!(
+                    peer=%propagation_source,
+                    dropped=%dropped.total(),
+                    "Dropped messages to slow peer"
+                );
+
+                if let Some(metrics) = self.metrics.as_mut() {
+                    metrics.register_send_queue_dropped(&dropped);
+                }
+                if self.config.penalize_slow_peers() {
+                    if let Some((peer_score, ..)) = &mut self.peer_score {
+                        peer_score.add_penalty(&propagation_source, 1);
+                        if let Some(metrics) = self.metrics.as_mut() {
+                            metrics.register_score_penalty(Penalty::SlowPeer);
+                        }
+                    }
+                }
+
+                self.events
+                    .push_back(ToSwarm::GenerateEvent(Event::SlowPeer {
+                        peer_id: propagation_source,
+                        dropped,
+                    }));
+            }
             HandlerEvent::PeerKind(kind) => {
                 // We have identified the protocol this peer is using
 
@@ -3064,9 +3607,12 @@ where
                     self.handle_received_subscriptions(&rpc.subscriptions, &propagation_source);
                 }
 
-                // Check if peer is graylisted in which case we ignore the event
-                if let (true, _) =
-                    self.score_below_threshold(&propagation_source, |pst| pst.graylist_threshold)
+                // Check if peer is graylisted in which case we ignore the event. Explicit peers are
+                // never graylisted.
+                if !self.explicit_peers.contains(&propagation_source)
+                    && self
+                        .score_below_threshold(&propagation_source, |pst| pst.graylist_threshold)
+                        .0
                 {
                     tracing::debug!(peer=%propagation_source, "RPC Dropped from greylisted peer");
                     return;
@@ -3127,6 +3673,15 @@ where
                             peers,
                             backoff,
                         } => prune_msgs.push((topic_hash, peers, backoff)),
+                        ControlAction::IDontWant { message_ids } => {
+                            self.handle_idontwant(&propagation_source, message_ids)
+                        }
+                        ControlAction::Choke { topic_hash } => {
+                            self.handle_choke(&propagation_source, topic_hash, true)
+                        }
+                        ControlAction::Unchoke { topic_hash } => {
+                            self.handle_choke(&propagation_source, topic_hash, false)
+                        }
                     }
                 }
                 if !ihave_msgs.is_empty() {
@@ -3147,6 +3702,10 @@ where
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        while let Some(Poll::Ready(validated)) = self.validator.as_mut().map(|v| v.poll(cx)) {
+            self.on_message_validated(validated);
+        }
+
         if let Some(event) = self.events.pop_front() {
             return Poll::Ready(event);
         }
@@ -3174,6 +3733,7 @@ where
                 self.on_connection_closed(connection_closed)
             }
             FromSwarm::AddressChange(address_change) => self.on_address_change(address_change),
+            FromSwarm::DialFailure(dial_failure) => self.on_dial_failure(dial_failure),
             _ => {}
         }
     }
@@ -3279,6 +3839,8 @@ fn get_random_peers_dynamic(
                 f(p) && match connected_peers.get(p) {
                     Some(connections) if connections.kind == PeerKind::Gossipsub => true,
                     Some(connections) if connections.kind == PeerKind::Gossipsubv1_1 => true,
+                    Some(connections) if connections.kind == PeerKind::Gossipsubv1_2 => true,
+                    Some(connections) if connections.kind == PeerKind::Episub => true,
                     _ => false,
                 }
             })
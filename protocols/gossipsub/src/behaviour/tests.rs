@@ -27,6 +27,7 @@ use async_std::net::Ipv4Addr;
 use byteorder::{BigEndian, ByteOrder};
 use libp2p_core::ConnectedPoint;
 use rand::Rng;
+use std::sync::{Arc, Mutex};
 use std::thread::sleep;
 
 #[derive(Default, Debug)]
@@ -325,6 +326,34 @@ fn proto_to_message(rpc: &proto::RPC) -> Rpc {
             })
             .collect();
 
+        let choke_msgs: Vec<ControlAction> = rpc_control
+            .choke
+            .into_iter()
+            .map(|choke| ControlAction::Choke {
+                topic_hash: TopicHash::from_raw(choke.topic_id.unwrap_or_default()),
+            })
+            .collect();
+
+        let unchoke_msgs: Vec<ControlAction> = rpc_control
+            .unchoke
+            .into_iter()
+            .map(|unchoke| ControlAction::Unchoke {
+                topic_hash: TopicHash::from_raw(unchoke.topic_id.unwrap_or_default()),
+            })
+            .collect();
+
+        let idontwant_msgs: Vec<ControlAction> = rpc_control
+            .idontwant
+            .into_iter()
+            .map(|idontwant| ControlAction::IDontWant {
+                message_ids: idontwant
+                    .message_ids
+                    .into_iter()
+                    .map(MessageId::from)
+                    .collect::<Vec<_>>(),
+            })
+            .collect();
+
         let mut prune_msgs = Vec::new();
 
         for prune in rpc_control.prune {
@@ -332,15 +361,7 @@ fn proto_to_message(rpc: &proto::RPC) -> Rpc {
             let peers = prune
                 .peers
                 .into_iter()
-                .filter_map(|info| {
-                    info.peer_id
-                        .and_then(|id| PeerId::from_bytes(&id).ok())
-                        .map(|peer_id|
-                            //TODO signedPeerRecord, see https://github.com/libp2p/specs/pull/217
-                            PeerInfo {
-                                peer_id: Some(peer_id),
-                            })
-                })
+                .filter_map(PeerInfo::from_proto)
                 .collect::<Vec<PeerInfo>>();
 
             let topic_hash = TopicHash::from_raw(prune.topic_id.unwrap_or_default());
@@ -355,6 +376,9 @@ fn proto_to_message(rpc: &proto::RPC) -> Rpc {
         control_msgs.extend(iwant_msgs);
         control_msgs.extend(graft_msgs);
         control_msgs.extend(prune_msgs);
+        control_msgs.extend(idontwant_msgs);
+        control_msgs.extend(choke_msgs);
+        control_msgs.extend(unchoke_msgs);
     }
 
     Rpc {
@@ -932,6 +956,7 @@ fn test_get_random_peers() {
                 PeerConnections {
                     kind: PeerKind::Gossipsubv1_1,
                     connections: vec![ConnectionId::new_unchecked(0)],
+                    dont_send: LinkedHashMap::new(),
                 },
             )
         })
@@ -1412,6 +1437,123 @@ fn test_explicit_peer_reconnects() {
     );
 }
 
+#[test]
+fn test_explicit_peer_dial_backoff() {
+    let config = ConfigBuilder::default()
+        .check_explicit_peers_ticks(1)
+        .explicit_peer_dial_backoff(Duration::from_secs(60 * 60))
+        .explicit_peer_max_dial_backoff(Duration::from_secs(90 * 60))
+        .build()
+        .unwrap();
+    let (mut gs, others, _) = inject_nodes1()
+        .peer_no(1)
+        .topics(Vec::new())
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    let peer = *others.first().unwrap();
+    gs.add_explicit_peer(&peer);
+    disconnect_peer(&mut gs, &peer);
+    flush_events(&mut gs);
+
+    let count_dials = |gs: &Behaviour| {
+        gs.events
+            .iter()
+            .filter(|e| matches!(e, ToSwarm::Dial { opts } if opts.get_peer_id() == Some(peer)))
+            .count()
+    };
+    let fail_dial = |gs: &mut Behaviour| {
+        gs.on_swarm_event(FromSwarm::DialFailure(DialFailure {
+            peer_id: Some(peer),
+            error: &DialError::NoAddresses,
+            connection_id: ConnectionId::new_unchecked(0),
+        }));
+    };
+
+    // the backoff doubles with every failed dial, up to the maximum
+    fail_dial(&mut gs);
+    assert_eq!(
+        gs.explicit_peer_backoffs[&peer].backoff,
+        Duration::from_secs(60 * 60)
+    );
+    fail_dial(&mut gs);
+    assert_eq!(
+        gs.explicit_peer_backoffs[&peer].backoff,
+        Duration::from_secs(90 * 60)
+    );
+
+    // no redial while backing off
+    gs.heartbeat();
+    assert_eq!(count_dials(&gs), 0);
+
+    // redial once the backoff expired
+    gs.explicit_peer_backoffs.get_mut(&peer).unwrap().retry_at = Some(Instant::now());
+    gs.heartbeat();
+    gs.heartbeat();
+    assert_eq!(count_dials(&gs), 1, "Explicit peer should be dialed once");
+
+    // a connection resets the backoff
+    gs.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+        peer_id: peer,
+        connection_id: ConnectionId::new_unchecked(1),
+        endpoint: &ConnectedPoint::Dialer {
+            address: Multiaddr::empty(),
+            role_override: Endpoint::Dialer,
+        },
+        failed_addresses: &[],
+        other_established: 0,
+    }));
+    assert!(gs.explicit_peer_backoffs.is_empty());
+}
+
+#[test]
+fn test_explicit_peers_are_not_graylisted() {
+    let peer_score_params = PeerScoreParams::default();
+    let peer_score_thresholds = PeerScoreThresholds {
+        gossip_threshold: peer_score_params.behaviour_penalty_weight,
+        publish_threshold: peer_score_params.behaviour_penalty_weight,
+        graylist_threshold: peer_score_params.behaviour_penalty_weight,
+        ..PeerScoreThresholds::default()
+    };
+    let (mut gs, _, topics) = inject_nodes1()
+        .topics(vec!["test".into()])
+        .scoring(Some((peer_score_params, peer_score_thresholds)))
+        .create_network();
+
+    let peer = add_peer(&mut gs, &topics, false, true);
+
+    //reduce score of the peer below the graylist threshold
+    gs.peer_score.as_mut().unwrap().0.add_penalty(&peer, 2);
+    flush_events(&mut gs);
+
+    gs.on_connection_handler_event(
+        peer,
+        ConnectionId::new_unchecked(0),
+        HandlerEvent::Message {
+            rpc: Rpc {
+                messages: vec![RawMessage {
+                    source: Some(PeerId::random()),
+                    data: vec![1, 2, 3, 4],
+                    sequence_number: Some(1u64),
+                    topic: topics[0].clone(),
+                    signature: None,
+                    key: None,
+                    validated: true,
+                }],
+                subscriptions: Vec::new(),
+                control_msgs: Vec::new(),
+            },
+            invalid_messages: Vec::new(),
+        },
+    );
+
+    assert!(gs
+        .events
+        .iter()
+        .any(|e| matches!(e, ToSwarm::GenerateEvent(Event::Message { .. }))));
+}
+
 #[test]
 fn test_handle_graft_explicit_peer() {
     let (mut gs, peers, topic_hashes) = inject_nodes1()
@@ -1759,6 +1901,7 @@ fn test_connect_to_px_peers_on_handle_prune() {
     for _ in 0..config.prune_peers() + 5 {
         px.push(PeerInfo {
             peer_id: Some(PeerId::random()),
+            signed_peer_record: None,
         });
     }
 
@@ -1797,6 +1940,134 @@ fn test_connect_to_px_peers_on_handle_prune() {
     ));
 }
 
+fn signed_peer_record() -> PeerRecord {
+    let keypair = Keypair::generate_ed25519();
+    PeerRecord::new(&keypair, vec!["/ip4/1.2.3.4/tcp/4001".parse().unwrap()]).unwrap()
+}
+
+#[test]
+fn test_dial_only_signed_px_peers_if_required() {
+    let config = ConfigBuilder::default()
+        .prune_peers(16)
+        .require_signed_px_records()
+        .build()
+        .unwrap();
+
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config.clone())
+        .create_network();
+
+    let record = signed_peer_record();
+    let px = vec![
+        PeerInfo {
+            peer_id: Some(PeerId::random()),
+            signed_peer_record: None,
+        },
+        PeerInfo {
+            peer_id: Some(record.peer_id()),
+            signed_peer_record: Some(record.clone()),
+        },
+    ];
+
+    gs.handle_prune(
+        &peers[0],
+        vec![(
+            topics[0].clone(),
+            px,
+            Some(config.prune_backoff().as_secs()),
+        )],
+    );
+
+    let dials: Vec<_> = gs
+        .events
+        .iter()
+        .filter_map(|e| match e {
+            ToSwarm::Dial { opts } => opts.get_peer_id(),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(dials, vec![record.peer_id()]);
+}
+
+#[test]
+fn test_validate_signed_px_records() {
+    let record = signed_peer_record();
+    let encoded = record.to_signed_envelope().into_protobuf_encoding();
+
+    // The peer ID is taken from the record if absent.
+    let info = PeerInfo::from_proto(proto::PeerInfo {
+        peer_id: None,
+        signed_peer_record: Some(encoded.clone()),
+    })
+    .unwrap();
+    assert_eq!(info.peer_id, Some(record.peer_id()));
+    assert_eq!(info.signed_peer_record, Some(record));
+
+    // A record of a different peer is rejected.
+    assert!(PeerInfo::from_proto(proto::PeerInfo {
+        peer_id: Some(PeerId::random().to_bytes()),
+        signed_peer_record: Some(encoded.clone()),
+    })
+    .is_none());
+
+    // A record with an invalid signature is rejected.
+    let mut tampered = encoded;
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert!(PeerInfo::from_proto(proto::PeerInfo {
+        peer_id: None,
+        signed_peer_record: Some(tampered),
+    })
+    .is_none());
+}
+
+#[test]
+fn test_send_signed_peer_records_in_px() {
+    let config = ConfigBuilder::default()
+        .do_px()
+        .prune_peers(16)
+        .build()
+        .unwrap();
+
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(2)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    // Records of disconnected peers are ignored.
+    gs.add_peer_record(signed_peer_record());
+    assert!(gs.peer_records.is_empty());
+
+    let record = signed_peer_record();
+    gs.peer_records.insert(peers[1], record.clone());
+
+    gs.send_graft_prune(
+        HashMap::new(),
+        vec![(peers[0], vec![topics[0].clone()])]
+            .into_iter()
+            .collect(),
+        HashSet::new(),
+    );
+
+    assert_eq!(
+        count_control_msgs(&gs, |peer_id, m| peer_id == &peers[0]
+            && match m {
+                ControlAction::Prune { peers: px, .. } =>
+                    px == &vec![PeerInfo {
+                        peer_id: Some(peers[1]),
+                        signed_peer_record: Some(record.clone()),
+                    }],
+                _ => false,
+            }),
+        1
+    );
+}
+
 #[test]
 fn test_send_px_and_backoff_in_prune() {
     let config: Config = Config::default();
@@ -2416,6 +2687,7 @@ fn test_ignore_px_from_negative_scored_peer() {
     //handle prune from single peer with px peers
     let px = vec![PeerInfo {
         peer_id: Some(PeerId::random()),
+        signed_peer_record: None,
     }];
 
     gs.handle_prune(
@@ -2998,6 +3270,7 @@ fn test_ignore_px_from_peers_below_accept_px_threshold() {
     // Handle prune from peer peers[0] with px peers
     let px = vec![PeerInfo {
         peer_id: Some(PeerId::random()),
+        signed_peer_record: None,
     }];
     gs.handle_prune(
         &peers[0],
@@ -3020,6 +3293,7 @@ fn test_ignore_px_from_peers_below_accept_px_threshold() {
     //handle prune from peer peers[1] with px peers
     let px = vec![PeerInfo {
         peer_id: Some(PeerId::random()),
+        signed_peer_record: None,
     }];
     gs.handle_prune(
         &peers[1],
@@ -4771,6 +5045,45 @@ fn test_publish_to_floodsub_peers_without_flood_publish() {
     );
 }
 
+#[test]
+fn test_forward_to_subscribed_floodsub_peers() {
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(2)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .create_network();
+
+    let floodsub_peer = add_peer_with_addr_and_kind(
+        &mut gs,
+        &topics,
+        false,
+        false,
+        Multiaddr::empty(),
+        Some(PeerKind::Floodsub),
+    );
+    assert!(!gs.mesh[&topics[0]].contains(&floodsub_peer));
+
+    let message = RawMessage {
+        source: Some(peers[0]),
+        data: vec![1, 2, 3],
+        sequence_number: Some(0),
+        topic: topics[0].clone(),
+        signature: None,
+        key: None,
+        validated: true,
+    };
+    gs.handle_received_message(message, &peers[0]);
+
+    assert!(gs.events.iter().any(|e| matches!(
+        e,
+        ToSwarm::NotifyHandler {
+            peer_id,
+            event: HandlerIn::Message(RpcOut::Forward(_)),
+            ..
+        } if peer_id == &floodsub_peer
+    )));
+}
+
 #[test]
 fn test_do_not_use_floodsub_in_fanout() {
     let config = ConfigBuilder::default()
@@ -5108,3 +5421,577 @@ fn test_graft_without_subscribe() {
     // We unsubscribe from the topic.
     let _ = gs.unsubscribe(&Topic::new(topic));
 }
+
+/// Creates a network with one subscribed topic and the given kinds of mesh peers.
+fn idontwant_network(
+    kinds: &[PeerKind],
+) -> (
+    Behaviour<IdentityTransform, AllowAllSubscriptionFilter>,
+    Vec<PeerId>,
+    TopicHash,
+) {
+    let (mut gs, _, topic_hashes) = inject_nodes1()
+        .peer_no(0)
+        .topics(vec![String::from("test")])
+        .to_subscribe(true)
+        .create_network();
+
+    let peers = kinds
+        .iter()
+        .map(|kind| {
+            add_peer_with_addr_and_kind(
+                &mut gs,
+                &topic_hashes,
+                false,
+                false,
+                Multiaddr::empty(),
+                Some(kind.clone()),
+            )
+        })
+        .collect::<Vec<_>>();
+    for peer in &peers {
+        assert!(gs.mesh[&topic_hashes[0]].contains(peer));
+    }
+
+    (gs, peers, topic_hashes[0].clone())
+}
+
+fn message_of_size(topic: &TopicHash, size: usize) -> RawMessage {
+    RawMessage {
+        source: Some(PeerId::random()),
+        data: vec![0; size],
+        sequence_number: Some(0),
+        topic: topic.clone(),
+        signature: None,
+        key: None,
+        validated: true,
+    }
+}
+
+#[test]
+fn test_sends_idontwant_for_large_messages_to_v1_2_peers() {
+    let (mut gs, peers, topic) = idontwant_network(&[
+        PeerKind::Gossipsubv1_2,
+        PeerKind::Gossipsubv1_2,
+        PeerKind::Gossipsubv1_1,
+    ]);
+    flush_events(&mut gs);
+
+    let message = message_of_size(&topic, 2000);
+    gs.handle_received_message(message.clone(), &peers[0]);
+    let msg_id = gs
+        .config
+        .message_id(&gs.data_transform.inbound_transform(message).unwrap());
+
+    let idontwants = |gs: &Behaviour<_, _>, peer: &PeerId| {
+        count_control_msgs(gs, |p, action| {
+            p == peer
+                && matches!(action, ControlAction::IDontWant { message_ids } if message_ids == &vec![msg_id.clone()])
+        })
+    };
+    assert_eq!(
+        idontwants(&gs, &peers[0]),
+        0,
+        "Not sent to the propagation source"
+    );
+    assert_eq!(idontwants(&gs, &peers[1]), 1, "Sent to the v1.2 mesh peer");
+    assert_eq!(
+        idontwants(&gs, &peers[2]),
+        0,
+        "Not sent to the v1.1 mesh peer"
+    );
+}
+
+#[test]
+fn test_does_not_send_idontwant_for_small_messages() {
+    let (mut gs, peers, topic) =
+        idontwant_network(&[PeerKind::Gossipsubv1_2, PeerKind::Gossipsubv1_2]);
+    flush_events(&mut gs);
+
+    gs.handle_received_message(message_of_size(&topic, 10), &peers[0]);
+
+    assert_eq!(
+        count_control_msgs(&gs, |_, action| matches!(
+            action,
+            ControlAction::IDontWant { .. }
+        )),
+        0
+    );
+}
+
+#[test]
+fn test_does_not_forward_messages_to_peers_that_sent_idontwant() {
+    let (mut gs, peers, topic) = idontwant_network(&[
+        PeerKind::Gossipsubv1_2,
+        PeerKind::Gossipsubv1_2,
+        PeerKind::Gossipsubv1_2,
+    ]);
+    flush_events(&mut gs);
+
+    let message = message_of_size(&topic, 10);
+    let msg_id = gs.config.message_id(
+        &gs.data_transform
+            .inbound_transform(message.clone())
+            .unwrap(),
+    );
+    gs.handle_idontwant(&peers[1], vec![msg_id]);
+    gs.handle_received_message(message, &peers[0]);
+
+    let forwarded_to = gs
+        .events
+        .iter()
+        .filter_map(|e| match e {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerIn::Message(RpcOut::Forward(_)),
+                ..
+            } => Some(*peer_id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(forwarded_to, vec![peers[2]]);
+}
+
+#[test]
+fn test_idontwant_entries_expire_in_heartbeat() {
+    let (mut gs, peers, _) = idontwant_network(&[PeerKind::Gossipsubv1_2]);
+
+    let old = MessageId::from("old");
+    let new = MessageId::from("new");
+    gs.handle_idontwant(&peers[0], vec![old.clone(), new.clone()]);
+    *gs.connected_peers
+        .get_mut(&peers[0])
+        .unwrap()
+        .dont_send
+        .get_mut(&old)
+        .unwrap() = Instant::now() - IDONTWANT_TIMEOUT;
+
+    gs.heartbeat();
+
+    let dont_send = &gs.connected_peers[&peers[0]].dont_send;
+    assert!(!dont_send.contains_key(&old));
+    assert!(dont_send.contains_key(&new));
+}
+
+#[test]
+fn test_custom_scoring_backend() {
+    /// A backend only scoring peers based on their application score.
+    #[derive(Default)]
+    struct ApplicationScores(HashMap<PeerId, f64>);
+
+    impl ScoringBackend for ApplicationScores {
+        fn score(&self, peer_id: &PeerId) -> f64 {
+            self.0.get(peer_id).copied().unwrap_or_default()
+        }
+
+        fn set_application_score(&mut self, peer_id: &PeerId, new_score: f64) -> bool {
+            self.0.insert(*peer_id, new_score);
+            true
+        }
+    }
+
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(2)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .create_network();
+    gs.with_scoring_backend(
+        ApplicationScores::default(),
+        PeerScoreThresholds::default(),
+        Duration::from_secs(1),
+    )
+    .unwrap();
+
+    assert!(gs.set_application_score(&peers[0], -100.0));
+
+    let scores = gs.peer_scores().collect::<HashMap<_, _>>();
+    assert_eq!(scores.len(), 2);
+    assert_eq!(scores[&peers[0]], -100.0);
+    assert_eq!(scores[&peers[1]], 0.0);
+
+    // Peers with a negative score are removed from the mesh.
+    gs.heartbeat();
+    assert!(!gs.mesh[&topic_hashes[0]].contains(&peers[0]));
+    assert!(gs.mesh[&topic_hashes[0]].contains(&peers[1]));
+}
+
+/// Creates a network with one subscribed topic, three mesh peers of the given kind and choking
+/// enabled.
+fn choking_network(
+    kind: PeerKind,
+) -> (
+    Behaviour<IdentityTransform, AllowAllSubscriptionFilter>,
+    Vec<PeerId>,
+    TopicHash,
+) {
+    let config = ConfigBuilder::default()
+        .mesh_n_low(2)
+        .mesh_n(3)
+        .mesh_n_high(4)
+        .mesh_outbound_min(1)
+        .choking(true)
+        .build()
+        .unwrap();
+    let (mut gs, _, topic_hashes) = inject_nodes1()
+        .peer_no(0)
+        .topics(vec![String::from("test")])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    let peers = (0..3)
+        .map(|_| {
+            add_peer_with_addr_and_kind(
+                &mut gs,
+                &topic_hashes,
+                false,
+                false,
+                Multiaddr::empty(),
+                Some(kind.clone()),
+            )
+        })
+        .collect::<Vec<_>>();
+    // Only mesh_n_low peers are added on subscription.
+    gs.mesh
+        .get_mut(&topic_hashes[0])
+        .unwrap()
+        .extend(peers.iter().copied());
+
+    (gs, peers, topic_hashes[0].clone())
+}
+
+#[test]
+fn test_chokes_mesh_peers_delivering_duplicates() {
+    let (mut gs, peers, topic) = choking_network(PeerKind::Episub);
+
+    // peers[0] delivers all messages first, the others only duplicates.
+    for _ in 0..10 {
+        let message = message_of_size(&topic, 10);
+        for peer in &peers {
+            gs.handle_received_message(message.clone(), peer);
+        }
+    }
+    flush_events(&mut gs);
+    gs.heartbeat();
+
+    let chokes = |gs: &Behaviour<_, _>, peer: &PeerId| {
+        count_control_msgs(gs, |p, action| {
+            p == peer
+                && matches!(action, ControlAction::Choke { topic_hash } if topic_hash == &topic)
+        })
+    };
+    assert_eq!(chokes(&gs, &peers[0]), 0, "The fastest peer stays unchoked");
+    assert_eq!(
+        chokes(&gs, &peers[1]) + chokes(&gs, &peers[2]),
+        1,
+        "Only one peer is choked per heartbeat"
+    );
+
+    // Further duplicates don't choke more peers than the mesh_n_low limit allows.
+    for _ in 0..10 {
+        let message = message_of_size(&topic, 10);
+        for peer in &peers {
+            gs.handle_received_message(message.clone(), peer);
+        }
+    }
+    flush_events(&mut gs);
+    gs.heartbeat();
+    assert_eq!(
+        count_control_msgs(&gs, |_, action| matches!(
+            action,
+            ControlAction::Choke { .. }
+        )),
+        0
+    );
+}
+
+#[test]
+fn test_unchokes_peers_with_early_announcements() {
+    let (mut gs, peers, topic) = choking_network(PeerKind::Episub);
+
+    for _ in 0..10 {
+        let message = message_of_size(&topic, 10);
+        gs.handle_received_message(message.clone(), &peers[0]);
+        gs.handle_received_message(message, &peers[1]);
+    }
+    flush_events(&mut gs);
+    gs.heartbeat();
+    assert_eq!(
+        count_control_msgs(&gs, |p, action| p == &peers[1]
+            && matches!(action, ControlAction::Choke { .. })),
+        1
+    );
+
+    // The choked peer announces messages before anybody else delivers them.
+    let messages = (0..10)
+        .map(|_| message_of_size(&topic, 10))
+        .collect::<Vec<_>>();
+    let message_ids = messages
+        .iter()
+        .map(|m| {
+            gs.config
+                .message_id(&gs.data_transform.inbound_transform(m.clone()).unwrap())
+        })
+        .collect();
+    gs.handle_ihave(&peers[1], vec![(topic.clone(), message_ids)]);
+    for message in messages {
+        gs.handle_received_message(message, &peers[0]);
+    }
+    flush_events(&mut gs);
+    gs.heartbeat();
+    assert_eq!(
+        count_control_msgs(&gs, |p, action| p == &peers[1]
+            && matches!(action, ControlAction::Unchoke { topic_hash } if topic_hash == &topic)),
+        1
+    );
+}
+
+#[test]
+fn test_does_not_unchoke_peers_announcing_unknown_messages() {
+    let (mut gs, peers, topic) = choking_network(PeerKind::Episub);
+
+    for _ in 0..10 {
+        let message = message_of_size(&topic, 10);
+        gs.handle_received_message(message.clone(), &peers[0]);
+        gs.handle_received_message(message, &peers[1]);
+    }
+    flush_events(&mut gs);
+    gs.heartbeat();
+    assert_eq!(
+        count_control_msgs(&gs, |p, action| p == &peers[1]
+            && matches!(action, ControlAction::Choke { .. })),
+        1
+    );
+
+    // The choked peer announces messages that never arrive.
+    let message_ids = (0..10)
+        .map(|i| MessageId::from(format!("bogus {i}")))
+        .collect();
+    gs.handle_ihave(&peers[1], vec![(topic.clone(), message_ids)]);
+    flush_events(&mut gs);
+    gs.heartbeat();
+    assert_eq!(
+        count_control_msgs(&gs, |p, action| p == &peers[1]
+            && matches!(action, ControlAction::Unchoke { .. })),
+        0
+    );
+}
+
+#[test]
+fn test_only_announces_messages_to_choking_peers() {
+    let (mut gs, peers, topic) = choking_network(PeerKind::Episub);
+    flush_events(&mut gs);
+
+    gs.handle_choke(&peers[1], topic.clone(), true);
+    let message = message_of_size(&topic, 10);
+    let msg_id = gs.config.message_id(
+        &gs.data_transform
+            .inbound_transform(message.clone())
+            .unwrap(),
+    );
+    gs.handle_received_message(message, &peers[0]);
+
+    let forwarded_to = gs
+        .events
+        .iter()
+        .filter_map(|e| match e {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerIn::Message(RpcOut::Forward(_)),
+                ..
+            } => Some(*peer_id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(forwarded_to, vec![peers[2]]);
+    assert_eq!(
+        count_control_msgs(&gs, |p, action| p == &peers[1]
+            && matches!(action, ControlAction::IHave { message_ids, .. } if message_ids == &vec![msg_id.clone()])),
+        1
+    );
+
+    // After being unchoked, the peer receives full messages again.
+    gs.handle_choke(&peers[1], topic.clone(), false);
+    flush_events(&mut gs);
+    gs.handle_received_message(message_of_size(&topic, 10), &peers[0]);
+    assert_eq!(
+        gs.events
+            .iter()
+            .filter(|e| matches!(
+                e,
+                ToSwarm::NotifyHandler {
+                    event: HandlerIn::Message(RpcOut::Forward(_)),
+                    ..
+                }
+            ))
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn test_does_not_choke_peers_without_choking_support() {
+    let (mut gs, peers, topic) = choking_network(PeerKind::Gossipsubv1_2);
+
+    for _ in 0..10 {
+        let message = message_of_size(&topic, 10);
+        for peer in &peers {
+            gs.handle_received_message(message.clone(), peer);
+        }
+    }
+    flush_events(&mut gs);
+    gs.heartbeat();
+    assert_eq!(
+        count_control_msgs(&gs, |_, action| matches!(
+            action,
+            ControlAction::Choke { .. }
+        )),
+        0
+    );
+
+    // CHOKEs of peers that didn't negotiate choking are ignored.
+    gs.handle_choke(&peers[1], topic.clone(), true);
+    flush_events(&mut gs);
+    gs.handle_received_message(message_of_size(&topic, 10), &peers[0]);
+    assert_eq!(
+        gs.events
+            .iter()
+            .filter(|e| matches!(
+                e,
+                ToSwarm::NotifyHandler {
+                    event: HandlerIn::Message(RpcOut::Forward(_)),
+                    ..
+                }
+            ))
+            .count(),
+        2
+    );
+}
+
+#[derive(Clone, Default)]
+struct TestSeenCacheStore {
+    persisted: Arc<Mutex<Vec<MessageId>>>,
+    buffered: Vec<MessageId>,
+}
+
+impl SeenCacheStore for TestSeenCacheStore {
+    fn load(&mut self) -> io::Result<Vec<MessageId>> {
+        Ok(self.persisted.lock().unwrap().clone())
+    }
+
+    fn insert(&mut self, id: &MessageId, _: Duration) -> io::Result<()> {
+        self.buffered.push(id.clone());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.persisted
+            .lock()
+            .unwrap()
+            .extend(self.buffered.drain(..));
+        Ok(())
+    }
+}
+
+#[test]
+fn test_seen_cache_store_prevents_redelivery_after_restart() {
+    let store = TestSeenCacheStore::default();
+    let network = || {
+        let (mut gs, _, topic_hashes) = inject_nodes1()
+            .peer_no(2)
+            .topics(vec!["topic".into()])
+            .to_subscribe(true)
+            .create_network();
+        gs.with_seen_cache_store(store.clone()).unwrap();
+        (gs, topic_hashes[0].clone())
+    };
+    let delivered = |gs: &Behaviour| {
+        gs.events
+            .iter()
+            .filter(|e| matches!(e, ToSwarm::GenerateEvent(Event::Message { .. })))
+            .count()
+    };
+
+    let (mut gs, topic) = network();
+    let message = RawMessage {
+        source: Some(PeerId::random()),
+        data: vec![1, 2, 3],
+        sequence_number: Some(0),
+        topic,
+        signature: None,
+        key: None,
+        validated: true,
+    };
+    gs.handle_received_message(message.clone(), &PeerId::random());
+    assert_eq!(delivered(&gs), 1);
+    gs.heartbeat();
+    assert_eq!(store.persisted.lock().unwrap().len(), 1);
+
+    // After a restart, the message is still considered seen.
+    let (mut gs, _) = network();
+    gs.handle_received_message(message, &PeerId::random());
+    assert_eq!(delivered(&gs), 0);
+}
+
+#[test]
+fn test_validator_delivers_and_forwards_accepted_messages() {
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(2)
+        .topics(vec!["topic".into()])
+        .to_subscribe(true)
+        .create_network();
+    gs.with_validator(ValidatorConfig::default(), |_, _, message| async move {
+        if message.data == [1] {
+            MessageAcceptance::Accept
+        } else {
+            MessageAcceptance::Reject
+        }
+    });
+    flush_events(&mut gs);
+
+    for data in [vec![0], vec![1]] {
+        let message = RawMessage {
+            source: Some(PeerId::random()),
+            data,
+            sequence_number: Some(0),
+            topic: topic_hashes[0].clone(),
+            signature: None,
+            key: None,
+            validated: false,
+        };
+        gs.handle_received_message(message, &peers[0]);
+    }
+    assert!(
+        gs.events.is_empty(),
+        "Expected messages to await validation"
+    );
+
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    while let Some(Poll::Ready(validated)) = gs.validator.as_mut().map(|v| v.poll(&mut cx)) {
+        gs.on_message_validated(validated);
+    }
+
+    let delivered: Vec<_> = gs
+        .events
+        .iter()
+        .filter_map(|e| match e {
+            ToSwarm::GenerateEvent(Event::Message { message, .. }) => Some(message.data.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(delivered, vec![vec![1]]);
+    let forwarded: Vec<_> = gs
+        .events
+        .iter()
+        .filter_map(|e| match e {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerIn::Message(RpcOut::Forward(message)),
+                ..
+            } => Some((*peer_id, message.data.clone())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(forwarded, vec![(peers[1], vec![1])]);
+}
@@ -0,0 +1,261 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Episub-style choking of mesh peers.
+//!
+//! Mesh peers that mostly deliver messages we already received from someone else get choked:
+//! they stop forwarding full messages to us on the topic and only announce them via IHAVE.
+//! Choked peers whose announcements arrive early get unchoked again.
+use crate::time_cache::{Entry, TimeCache};
+use crate::topic::TopicHash;
+use crate::types::MessageId;
+use instant::Instant;
+use libp2p_identity::PeerId;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+
+/// The minimum number of deliveries or announcements of a peer in a topic before we decide
+/// whether to choke or unchoke it.
+const MIN_SAMPLES: u32 = 10;
+
+/// Delivery statistics of a mesh peer in a topic since the last choking decision.
+#[derive(Debug, Default)]
+struct DeliveryStats {
+    /// The number of messages the peer delivered first.
+    first: u32,
+    /// The number of messages the peer delivered after another peer.
+    duplicates: u32,
+    /// The number of IHAVE announcements of the choked peer.
+    announcements: u32,
+    /// The sum of the delays of the announcements relative to the first delivery of the message.
+    announcement_delay: Duration,
+}
+
+impl DeliveryStats {
+    fn duplicate_ratio(&self) -> Option<f64> {
+        let deliveries = self.first + self.duplicates;
+        (deliveries >= MIN_SAMPLES).then(|| self.duplicates as f64 / deliveries as f64)
+    }
+
+    fn mean_announcement_delay(&self) -> Option<Duration> {
+        (self.announcements >= MIN_SAMPLES).then(|| self.announcement_delay / self.announcements)
+    }
+}
+
+/// Tracks the delivery latency of mesh peers and which of them are choked, in both directions.
+pub(crate) struct Choking {
+    /// The time each recently received message was first received.
+    first_seen: TimeCache<MessageId, Instant>,
+    /// Choked peers that announced a message before we received it, with the topic of the
+    /// announcement.
+    ///
+    /// These announcements only count once the message arrives, so announcing made-up message
+    /// IDs doesn't get a peer unchoked.
+    early_announcements: TimeCache<MessageId, Vec<(TopicHash, PeerId)>>,
+    /// Delivery statistics of our mesh peers, per topic.
+    stats: HashMap<TopicHash, HashMap<PeerId, DeliveryStats>>,
+    /// Mesh peers we choked, i.e. which only send us IHAVEs, per topic.
+    choked: HashMap<TopicHash, HashSet<PeerId>>,
+    /// Mesh peers that choked us, i.e. to which we only send IHAVEs, per topic.
+    choked_by: HashMap<TopicHash, HashSet<PeerId>>,
+    /// The ratio of duplicate deliveries above which a peer gets choked.
+    duplicates_threshold: f64,
+    /// The mean announcement delay below which a choked peer gets unchoked.
+    latency_threshold: Duration,
+}
+
+impl Choking {
+    pub(crate) fn new(
+        duplicates_threshold: f64,
+        latency_threshold: Duration,
+        first_seen_ttl: Duration,
+    ) -> Self {
+        Self {
+            first_seen: TimeCache::new(first_seen_ttl),
+            early_announcements: TimeCache::new(first_seen_ttl),
+            stats: HashMap::new(),
+            choked: HashMap::new(),
+            choked_by: HashMap::new(),
+            duplicates_threshold,
+            latency_threshold,
+        }
+    }
+
+    /// Records a message received from a peer.
+    pub(crate) fn message_received(
+        &mut self,
+        msg_id: &MessageId,
+        topic: &TopicHash,
+        peer: &PeerId,
+        duplicate: bool,
+    ) {
+        if !duplicate {
+            if let Entry::Vacant(entry) = self.first_seen.entry(msg_id.clone()) {
+                entry.insert(Instant::now());
+                self.count_early_announcements(msg_id);
+            }
+        }
+
+        let stats = self
+            .stats
+            .entry(topic.clone())
+            .or_default()
+            .entry(*peer)
+            .or_default();
+        if duplicate {
+            stats.duplicates += 1;
+        } else {
+            stats.first += 1;
+        }
+    }
+
+    /// Records the announcements of messages by a peer we choked.
+    pub(crate) fn ihave_received(&mut self, topic: &TopicHash, peer: &PeerId, ids: &[MessageId]) {
+        if !self.choked.get(topic).map_or(false, |c| c.contains(peer)) {
+            return;
+        }
+
+        let stats = self
+            .stats
+            .entry(topic.clone())
+            .or_default()
+            .entry(*peer)
+            .or_default();
+        for id in ids {
+            let Some(first_seen) = self.first_seen.get(id) else {
+                let announcers = self.early_announcements.entry(id.clone()).or_default();
+                if !announcers.iter().any(|(t, p)| t == topic && p == peer) {
+                    announcers.push((topic.clone(), *peer));
+                }
+                continue;
+            };
+            stats.announcement_delay += first_seen.elapsed();
+            stats.announcements += 1;
+        }
+    }
+
+    /// Counts the announcements of a message made before its first delivery, which are as early
+    /// as it gets.
+    fn count_early_announcements(&mut self, msg_id: &MessageId) {
+        if !self.early_announcements.contains_key(msg_id) {
+            return;
+        }
+        let announcers =
+            std::mem::take(self.early_announcements.entry(msg_id.clone()).or_default());
+
+        for (topic, peer) in announcers {
+            if !self.choked.get(&topic).map_or(false, |c| c.contains(&peer)) {
+                continue;
+            }
+            self.stats
+                .entry(topic)
+                .or_default()
+                .entry(peer)
+                .or_default()
+                .announcements += 1;
+        }
+    }
+
+    /// Returns whether the peer choked us in the topic.
+    pub(crate) fn is_choked_by(&self, topic: &TopicHash, peer: &PeerId) -> bool {
+        self.choked_by
+            .get(topic)
+            .map_or(false, |peers| peers.contains(peer))
+    }
+
+    /// Handles a CHOKE or UNCHOKE control message of a mesh peer.
+    pub(crate) fn set_choked_by(&mut self, topic: TopicHash, peer: PeerId, choked: bool) {
+        if choked {
+            self.choked_by.entry(topic).or_default().insert(peer);
+        } else if let Some(peers) = self.choked_by.get_mut(&topic) {
+            peers.remove(&peer);
+        }
+    }
+
+    /// Decides which mesh peers to choke and unchoke, returning the peers to send CHOKE and
+    /// UNCHOKE control messages to, respectively.
+    ///
+    /// At least `mesh_n_low` peers of each mesh are kept unchoked, and at most one peer per topic
+    /// is choked per call. Only peers for which `supports_choking` returns `true` are choked.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn heartbeat(
+        &mut self,
+        mesh: &HashMap<TopicHash, BTreeSet<PeerId>>,
+        mesh_n_low: usize,
+        supports_choking: impl Fn(&PeerId) -> bool,
+    ) -> (Vec<(PeerId, TopicHash)>, Vec<(PeerId, TopicHash)>) {
+        // Forget about peers that left the mesh.
+        let in_mesh = |topic: &TopicHash, peer: &PeerId| {
+            mesh.get(topic).map_or(false, |peers| peers.contains(peer))
+        };
+        for (topic, peers) in self.choked.iter_mut().chain(self.choked_by.iter_mut()) {
+            peers.retain(|peer| in_mesh(topic, peer));
+        }
+        for (topic, stats) in self.stats.iter_mut() {
+            stats.retain(|peer, _| in_mesh(topic, peer));
+        }
+
+        let mut to_choke = Vec::new();
+        let mut to_unchoke = Vec::new();
+
+        for (topic, peers) in mesh {
+            let choked = self.choked.entry(topic.clone()).or_default();
+            let stats = self.stats.entry(topic.clone()).or_default();
+            let mut unchoked = peers.len() - choked.len();
+
+            choked.retain(|peer| {
+                let fast = match stats.get(peer).and_then(|s| s.mean_announcement_delay()) {
+                    Some(delay) => {
+                        stats.remove(peer);
+                        delay <= self.latency_threshold
+                    }
+                    None => false,
+                };
+                if fast || unchoked < mesh_n_low {
+                    to_unchoke.push((*peer, topic.clone()));
+                    unchoked += 1;
+                    return false;
+                }
+                true
+            });
+
+            if unchoked <= mesh_n_low {
+                continue;
+            }
+
+            // Choke the unchoked peer with the most duplicate deliveries, if any is above the
+            // threshold.
+            let worst = peers
+                .iter()
+                .filter(|peer| !choked.contains(peer) && supports_choking(peer))
+                .filter_map(|peer| Some((peer, stats.get(peer)?.duplicate_ratio()?)))
+                .filter(|(_, ratio)| *ratio >= self.duplicates_threshold)
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(peer, _)| *peer);
+            if let Some(peer) = worst {
+                choked.insert(peer);
+                stats.remove(&peer);
+                to_choke.push((peer, topic.clone()));
+            }
+        }
+
+        (to_choke, to_unchoke)
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Compression of RPC frames, negotiated via a suffix of the protocol id, e.g.
+//! `/meshsub/1.1.0/snappy`.
+//!
+//! On a stream with compression, every frame starts with a flag byte after the length prefix,
+//! telling whether the remainder of the frame is compressed. RPCs smaller than
+//! [`ConfigBuilder::compression_threshold`](crate::ConfigBuilder::compression_threshold) and
+//! RPCs that don't shrink are sent uncompressed.
+
+// Without any compression feature, `Compression` has no variants.
+#![cfg_attr(
+    not(any(feature = "deflate", feature = "snappy", feature = "zstd")),
+    allow(unused_variables, unreachable_code)
+)]
+
+use std::io;
+
+/// The flag of frames containing an uncompressed RPC.
+pub(crate) const FLAG_UNCOMPRESSED: u8 = 0;
+/// The flag of frames containing a compressed RPC.
+pub(crate) const FLAG_COMPRESSED: u8 = 1;
+
+/// A compression algorithm for RPC frames, see [`ConfigBuilder::compression`](crate::ConfigBuilder::compression).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// DEFLATE as of RFC 1951, negotiated via the `/deflate` suffix.
+    #[cfg(feature = "deflate")]
+    Deflate,
+    /// Snappy in the raw block format, negotiated via the `/snappy` suffix.
+    #[cfg(feature = "snappy")]
+    Snappy,
+    /// Zstandard, negotiated via the `/zstd` suffix.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// The suffix appended to the protocol ids to negotiate this compression.
+    pub(crate) fn protocol_suffix(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => "deflate",
+            #[cfg(feature = "snappy")]
+            Compression::Snappy => "snappy",
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    pub(crate) fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match *self {
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => {
+                use std::io::Write;
+
+                let mut encoder = flate2::write::DeflateEncoder::new(
+                    Vec::with_capacity(data.len() / 2),
+                    flate2::Compression::fast(),
+                );
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "snappy")]
+            Compression::Snappy => snap::raw::Encoder::new()
+                .compress_vec(data)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::bulk::compress(data, 1),
+        }
+    }
+
+    /// Decompresses `data`, failing if it exceeds `max_length` bytes once decompressed.
+    pub(crate) fn decompress(&self, data: &[u8], max_length: usize) -> io::Result<Vec<u8>> {
+        let decompressed: Vec<u8> = match *self {
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => {
+                use std::io::Read;
+
+                let mut decompressed = Vec::new();
+                flate2::read::DeflateDecoder::new(data)
+                    .take(max_length as u64 + 1)
+                    .read_to_end(&mut decompressed)?;
+                decompressed
+            }
+            #[cfg(feature = "snappy")]
+            Compression::Snappy => {
+                // The decompressed length is part of the frame, check it before allocating.
+                let length = snap::raw::decompress_len(data)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                if length > max_length {
+                    return Err(exceeds_max_length(max_length));
+                }
+                snap::raw::Decoder::new()
+                    .decompress_vec(data)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                use std::io::Read;
+
+                let mut decompressed = Vec::new();
+                zstd::stream::read::Decoder::new(data)?
+                    .take(max_length as u64 + 1)
+                    .read_to_end(&mut decompressed)?;
+                decompressed
+            }
+        };
+
+        if decompressed.len() > max_length {
+            return Err(exceeds_max_length(max_length));
+        }
+
+        Ok(decompressed)
+    }
+}
+
+fn exceeds_max_length(max_length: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("decompressed message exceeds maximum of {max_length}b"),
+    )
+}
+
+#[cfg(all(test, any(feature = "deflate", feature = "snappy", feature = "zstd")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompression_is_limited() {
+        let algorithms = [
+            #[cfg(feature = "deflate")]
+            Compression::Deflate,
+            #[cfg(feature = "snappy")]
+            Compression::Snappy,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd,
+        ];
+
+        for compression in algorithms {
+            let data = vec![42; 10_000];
+            let compressed = compression.compress(&data).unwrap();
+            assert!(compressed.len() < data.len());
+
+            assert_eq!(
+                compression.decompress(&compressed, data.len()).unwrap(),
+                data
+            );
+            assert_eq!(
+                compression
+                    .decompress(&compressed, data.len() - 1)
+                    .unwrap_err()
+                    .kind(),
+                io::ErrorKind::PermissionDenied,
+                "{compression:?}"
+            );
+        }
+    }
+}
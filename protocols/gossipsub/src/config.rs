@@ -22,8 +22,10 @@ use std::borrow::Cow;
 use std::sync::Arc;
 use std::time::Duration;
 
+use crate::compression::Compression;
 use crate::error::ConfigBuilderError;
 use crate::protocol::{ProtocolConfig, ProtocolId, FLOODSUB_PROTOCOL};
+use crate::queue::{MessageClass, QueueConfig, QueueFullPolicy};
 use crate::types::{Message, MessageId, PeerKind};
 
 use libp2p_identity::PeerId;
@@ -57,6 +59,7 @@ pub enum ValidationMode {
 pub enum Version {
     V1_0,
     V1_1,
+    V1_2,
 }
 
 /// Configuration parameters that define the performance of the gossipsub network.
@@ -75,12 +78,15 @@ pub struct Config {
     heartbeat_interval: Duration,
     fanout_ttl: Duration,
     check_explicit_peers_ticks: u64,
+    explicit_peer_dial_backoff: Duration,
+    explicit_peer_max_dial_backoff: Duration,
     duplicate_cache_time: Duration,
     validate_messages: bool,
     message_id_fn: Arc<dyn Fn(&Message) -> MessageId + Send + Sync + 'static>,
     allow_self_origin: bool,
     do_px: bool,
     prune_peers: usize,
+    require_signed_px_records: bool,
     prune_backoff: Duration,
     unsubscribe_backoff: Duration,
     backoff_slack: u32,
@@ -95,6 +101,12 @@ pub struct Config {
     max_ihave_messages: usize,
     iwant_followup_time: Duration,
     published_message_ids_cache_time: Duration,
+    idontwant_message_size_threshold: usize,
+    choking: bool,
+    choke_duplicates_threshold: f64,
+    unchoke_latency_threshold: Duration,
+    send_queue: QueueConfig,
+    penalize_slow_peers: bool,
 }
 
 impl Config {
@@ -172,6 +184,19 @@ impl Config {
         self.check_explicit_peers_ticks
     }
 
+    /// The time to wait before redialing an explicit peer after a failed dial (default is 10
+    /// seconds). The time doubles with every consecutive failed dial, up to
+    /// [`Config::explicit_peer_max_dial_backoff`].
+    pub fn explicit_peer_dial_backoff(&self) -> Duration {
+        self.explicit_peer_dial_backoff
+    }
+
+    /// The maximum time to wait before redialing an explicit peer after failed dials (default is
+    /// 5 minutes).
+    pub fn explicit_peer_max_dial_backoff(&self) -> Duration {
+        self.explicit_peer_max_dial_backoff
+    }
+
     /// The maximum byte size for each gossipsub RPC (default is 65536 bytes).
     ///
     /// This represents the maximum size of the entire protobuf payload. It must be at least
@@ -227,8 +252,8 @@ impl Config {
     /// Whether Peer eXchange is enabled; this should be enabled in bootstrappers and other well
     /// connected/trusted nodes. The default is false.
     ///
-    /// Note: Peer exchange is not implemented today, see
-    /// <https://github.com/libp2p/rust-libp2p/issues/2398>.
+    /// Pruned peers can only dial the peers we exchange if we know their signed peer records,
+    /// see [`Behaviour::add_peer_record`](crate::Behaviour::add_peer_record).
     pub fn do_px(&self) -> bool {
         self.do_px
     }
@@ -237,12 +262,18 @@ impl Config {
     /// When we prune a peer that's eligible for PX (has a good score, etc), we will try to
     /// send them signed peer records for up to `prune_peers` other peers that we
     /// know of. It is recommended that this value is larger than `mesh_n_high` so that the pruned
-    /// peer can reliably form a full mesh. The default is typically 16 however until signed
-    /// records are spec'd this is disabled and set to 0.
+    /// peer can reliably form a full mesh. The default is 0, disabling dialing peers received
+    /// via Peer eXchange.
     pub fn prune_peers(&self) -> usize {
         self.prune_peers
     }
 
+    /// Whether peers received via Peer eXchange are only dialed if they come with a signed peer
+    /// record. The default is false.
+    pub fn require_signed_px_records(&self) -> bool {
+        self.require_signed_px_records
+    }
+
     /// Controls the backoff time for pruned peers. This is how long
     /// a peer must wait before attempting to graft into our mesh again after being pruned.
     /// When pruning a peer, we send them our value of `prune_backoff` so they know
@@ -346,10 +377,82 @@ impl Config {
         self.protocol.protocol_ids.contains(&FLOODSUB_PROTOCOL)
     }
 
+    /// Whether floodsub compatibility mode is enabled, see
+    /// [`ConfigBuilder::floodsub_compatibility`]. Default false.
+    pub fn floodsub_compatibility(&self) -> bool {
+        self.protocol.floodsub_compatibility
+    }
+
     /// Published message ids time cache duration. The default is 10 seconds.
     pub fn published_message_ids_cache_time(&self) -> Duration {
         self.published_message_ids_cache_time
     }
+
+    /// The minimum size in bytes of a message for which we send IDONTWANT control messages to
+    /// our mesh peers supporting gossipsub v1.2 upon receiving it, so they don't forward it to us
+    /// again. Smaller messages aren't worth the additional control traffic.
+    /// The default is 1000 bytes.
+    pub fn idontwant_message_size_threshold(&self) -> usize {
+        self.idontwant_message_size_threshold
+    }
+
+    /// The compression offered for RPC frames, see [`ConfigBuilder::compression`].
+    /// The default is no compression.
+    pub fn compression(&self) -> Option<Compression> {
+        self.protocol.compression
+    }
+
+    /// The minimum size in bytes of an RPC to be compressed on streams with compression.
+    /// The default is 1024 bytes.
+    pub fn compression_threshold(&self) -> usize {
+        self.protocol.compression_threshold
+    }
+
+    /// Whether to choke mesh peers that mostly deliver duplicate messages, episub style. Choked
+    /// peers only announce messages of the topic to us via IHAVE instead of forwarding them, and
+    /// get unchoked once their announcements arrive early enough. Requires the mesh peers to
+    /// support the choking extension, negotiated via the `/choke` suffix of the gossipsub 1.2
+    /// protocol ids, other peers are never choked. The default is false.
+    pub fn choking(&self) -> bool {
+        self.choking
+    }
+
+    /// The ratio of duplicate deliveries of a mesh peer, between 0 and 1, from which on it gets
+    /// choked if [`Config::choking`] is enabled. The default is 0.9.
+    pub fn choke_duplicates_threshold(&self) -> f64 {
+        self.choke_duplicates_threshold
+    }
+
+    /// The mean delay of the announcements of a choked mesh peer, relative to the first delivery
+    /// of the messages, up to which it gets unchoked if [`Config::choking`] is enabled.
+    /// The default is 100 milliseconds.
+    pub fn unchoke_latency_threshold(&self) -> Duration {
+        self.unchoke_latency_threshold
+    }
+
+    /// The maximum number of messages of the given [`MessageClass`] queued for sending on a
+    /// connection. Once reached, messages of the class are dropped according to
+    /// [`Config::send_queue_full_policy`]. The defaults are 1000 control, 500 published,
+    /// 500 forwarded and 200 gossip messages.
+    pub fn send_queue_size(&self, class: MessageClass) -> usize {
+        self.send_queue.size(class)
+    }
+
+    /// Which message to drop when the send queue of a [`MessageClass`] is full.
+    /// The default is [`QueueFullPolicy::DropNewest`].
+    pub fn send_queue_full_policy(&self) -> QueueFullPolicy {
+        self.send_queue.full_policy
+    }
+
+    /// Whether to apply a behavioural penalty to peers for which messages had to be dropped
+    /// because their send queues were full. The default is false.
+    pub fn penalize_slow_peers(&self) -> bool {
+        self.penalize_slow_peers
+    }
+
+    pub(crate) fn send_queue_config(&self) -> QueueConfig {
+        self.send_queue.clone()
+    }
 }
 
 impl Default for Config {
@@ -384,6 +487,8 @@ impl Default for ConfigBuilder {
                 heartbeat_interval: Duration::from_secs(1),
                 fanout_ttl: Duration::from_secs(60),
                 check_explicit_peers_ticks: 300,
+                explicit_peer_dial_backoff: Duration::from_secs(10),
+                explicit_peer_max_dial_backoff: Duration::from_secs(5 * 60),
                 duplicate_cache_time: Duration::from_secs(60),
                 validate_messages: false,
                 message_id_fn: Arc::new(|message| {
@@ -402,7 +507,8 @@ impl Default for ConfigBuilder {
                 }),
                 allow_self_origin: false,
                 do_px: false,
-                prune_peers: 0, // NOTE: Increasing this currently has little effect until Signed records are implemented.
+                prune_peers: 0,
+                require_signed_px_records: false,
                 prune_backoff: Duration::from_secs(60),
                 unsubscribe_backoff: Duration::from_secs(10),
                 backoff_slack: 1,
@@ -417,6 +523,12 @@ impl Default for ConfigBuilder {
                 max_ihave_messages: 10,
                 iwant_followup_time: Duration::from_secs(3),
                 published_message_ids_cache_time: Duration::from_secs(10),
+                idontwant_message_size_threshold: 1000,
+                choking: false,
+                choke_duplicates_threshold: 0.9,
+                unchoke_latency_threshold: Duration::from_millis(100),
+                send_queue: QueueConfig::default(),
+                penalize_slow_peers: false,
             },
             invalid_protocol: false,
         }
@@ -433,7 +545,8 @@ impl From<Config> for ConfigBuilder {
 }
 
 impl ConfigBuilder {
-    /// The protocol id prefix to negotiate this protocol (default is `/meshsub/1.1.0` and `/meshsub/1.0.0`).
+    /// The protocol id prefix to negotiate this protocol (default is `/meshsub/1.2.0`,
+    /// `/meshsub/1.1.0` and `/meshsub/1.0.0`).
     pub fn protocol_id_prefix(
         &mut self,
         protocol_id_prefix: impl Into<Cow<'static, str>>,
@@ -441,18 +554,26 @@ impl ConfigBuilder {
         let cow = protocol_id_prefix.into();
 
         match (
+            StreamProtocol::try_from_owned(format!("{}/1.2.0", cow)),
             StreamProtocol::try_from_owned(format!("{}/1.1.0", cow)),
             StreamProtocol::try_from_owned(format!("{}/1.0.0", cow)),
         ) {
-            (Ok(p1), Ok(p2)) => {
+            (Ok(p1), Ok(p2), Ok(p3)) => {
                 self.config.protocol.protocol_ids = vec![
                     ProtocolId {
                         protocol: p1,
-                        kind: PeerKind::Gossipsubv1_1,
+                        kind: PeerKind::Gossipsubv1_2,
+                        compression: None,
                     },
                     ProtocolId {
                         protocol: p2,
+                        kind: PeerKind::Gossipsubv1_1,
+                        compression: None,
+                    },
+                    ProtocolId {
+                        protocol: p3,
                         kind: PeerKind::Gossipsub,
+                        compression: None,
                     },
                 ]
             }
@@ -464,7 +585,8 @@ impl ConfigBuilder {
         self
     }
 
-    /// The full protocol id to negotiate this protocol (does not append `/1.0.0` or `/1.1.0`).
+    /// The full protocol id to negotiate this protocol (does not append `/1.0.0`, `/1.1.0` or
+    /// `/1.2.0`).
     pub fn protocol_id(
         &mut self,
         protocol_id: impl Into<Cow<'static, str>>,
@@ -477,9 +599,11 @@ impl ConfigBuilder {
                 self.config.protocol.protocol_ids = vec![ProtocolId {
                     protocol,
                     kind: match custom_id_version {
+                        Version::V1_2 => PeerKind::Gossipsubv1_2,
                         Version::V1_1 => PeerKind::Gossipsubv1_1,
                         Version::V1_0 => PeerKind::Gossipsub,
                     },
+                    compression: None,
                 }]
             }
             _ => {
@@ -565,6 +689,27 @@ impl ConfigBuilder {
         self
     }
 
+    /// The time to wait before redialing an explicit peer after a failed dial (default is 10
+    /// seconds). The time doubles with every consecutive failed dial, up to
+    /// [`ConfigBuilder::explicit_peer_max_dial_backoff`].
+    pub fn explicit_peer_dial_backoff(
+        &mut self,
+        explicit_peer_dial_backoff: Duration,
+    ) -> &mut Self {
+        self.config.explicit_peer_dial_backoff = explicit_peer_dial_backoff;
+        self
+    }
+
+    /// The maximum time to wait before redialing an explicit peer after failed dials (default is
+    /// 5 minutes).
+    pub fn explicit_peer_max_dial_backoff(
+        &mut self,
+        explicit_peer_max_dial_backoff: Duration,
+    ) -> &mut Self {
+        self.config.explicit_peer_max_dial_backoff = explicit_peer_max_dial_backoff;
+        self
+    }
+
     /// Time to live for fanout peers (default is 60 seconds).
     pub fn fanout_ttl(&mut self, fanout_ttl: Duration) -> &mut Self {
         self.config.fanout_ttl = fanout_ttl;
@@ -621,8 +766,8 @@ impl ConfigBuilder {
     /// Enables Peer eXchange. This should be enabled in bootstrappers and other well
     /// connected/trusted nodes. The default is false.
     ///
-    /// Note: Peer exchange is not implemented today, see
-    /// <https://github.com/libp2p/rust-libp2p/issues/2398>.
+    /// Pruned peers can only dial the peers we exchange if we know their signed peer records,
+    /// see [`Behaviour::add_peer_record`](crate::Behaviour::add_peer_record).
     pub fn do_px(&mut self) -> &mut Self {
         self.config.do_px = true;
         self
@@ -639,6 +784,16 @@ impl ConfigBuilder {
         self
     }
 
+    /// Only dials peers received via Peer eXchange if they come with a signed peer record,
+    /// ignoring unsigned ones. This prevents peers from poisoning our address book with addresses
+    /// they made up. The default is false.
+    ///
+    /// Invalid signed peer records are always ignored.
+    pub fn require_signed_px_records(&mut self) -> &mut Self {
+        self.config.require_signed_px_records = true;
+        self
+    }
+
     /// Controls the backoff time for pruned peers. This is how long
     /// a peer must wait before attempting to graft into our mesh again after being pruned.
     /// When pruning a peer, we send them our value of [`Self::prune_backoff`] so they know
@@ -773,6 +928,19 @@ impl ConfigBuilder {
         self
     }
 
+    /// Enables floodsub compatibility mode, to migrate a network off `libp2p-floodsub` without
+    /// upgrading all peers at once.
+    ///
+    /// In addition to [`ConfigBuilder::support_floodsub`], messages received from floodsub peers
+    /// are accepted without a signature, as floodsub doesn't sign messages, even if the
+    /// [`ValidationMode`] is [`ValidationMode::Strict`]. Signatures that are present are still
+    /// verified. Messages published or forwarded by us are sent to all floodsub peers subscribed
+    /// to the topic.
+    pub fn floodsub_compatibility(&mut self) -> &mut Self {
+        self.config.protocol.floodsub_compatibility = true;
+        self.support_floodsub()
+    }
+
     /// Published message ids time cache duration. The default is 10 seconds.
     pub fn published_message_ids_cache_time(
         &mut self,
@@ -782,6 +950,81 @@ impl ConfigBuilder {
         self
     }
 
+    /// The minimum size in bytes of a message for which we send IDONTWANT control messages to
+    /// our mesh peers supporting gossipsub v1.2 upon receiving it, so they don't forward it to us
+    /// again. Smaller messages aren't worth the additional control traffic.
+    /// The default is 1000 bytes.
+    pub fn idontwant_message_size_threshold(&mut self, size: usize) -> &mut Self {
+        self.config.idontwant_message_size_threshold = size;
+        self
+    }
+
+    /// Offers compression of RPC frames, negotiated via the protocol ids suffixed with the
+    /// algorithm, e.g. `/meshsub/1.1.0/deflate`. These are preferred over the uncompressed
+    /// protocol ids, which remain supported for peers without compression.
+    /// The default is no compression.
+    pub fn compression(&mut self, compression: Compression) -> &mut Self {
+        self.config.protocol.compression = Some(compression);
+        self
+    }
+
+    /// The minimum size in bytes of an RPC to be compressed on streams with compression. Smaller
+    /// RPCs are sent uncompressed as they hardly shrink.
+    /// The default is 1024 bytes.
+    pub fn compression_threshold(&mut self, size: usize) -> &mut Self {
+        self.config.protocol.compression_threshold = size;
+        self
+    }
+
+    /// Whether to choke mesh peers that mostly deliver duplicate messages, episub style. Choked
+    /// peers only announce messages of the topic to us via IHAVE instead of forwarding them, and
+    /// get unchoked once their announcements arrive early enough. Requires the mesh peers to
+    /// support the choking extension, negotiated via the `/choke` suffix of the gossipsub 1.2
+    /// protocol ids, other peers are never choked. The default is false.
+    pub fn choking(&mut self, choking: bool) -> &mut Self {
+        self.config.choking = choking;
+        self.config.protocol.choking = choking;
+        self
+    }
+
+    /// The ratio of duplicate deliveries of a mesh peer, between 0 and 1, from which on it gets
+    /// choked if [`Config::choking`] is enabled. The default is 0.9.
+    pub fn choke_duplicates_threshold(&mut self, threshold: f64) -> &mut Self {
+        self.config.choke_duplicates_threshold = threshold;
+        self
+    }
+
+    /// The mean delay of the announcements of a choked mesh peer, relative to the first delivery
+    /// of the messages, up to which it gets unchoked if [`Config::choking`] is enabled.
+    /// The default is 100 milliseconds.
+    pub fn unchoke_latency_threshold(&mut self, threshold: Duration) -> &mut Self {
+        self.config.unchoke_latency_threshold = threshold;
+        self
+    }
+
+    /// The maximum number of messages of the given [`MessageClass`] queued for sending on a
+    /// connection. Once reached, messages of the class are dropped according to
+    /// [`Config::send_queue_full_policy`]. The defaults are 1000 control, 500 published,
+    /// 500 forwarded and 200 gossip messages.
+    pub fn send_queue_size(&mut self, class: MessageClass, size: usize) -> &mut Self {
+        self.config.send_queue.set_size(class, size);
+        self
+    }
+
+    /// Which message to drop when the send queue of a [`MessageClass`] is full.
+    /// The default is [`QueueFullPolicy::DropNewest`].
+    pub fn send_queue_full_policy(&mut self, policy: QueueFullPolicy) -> &mut Self {
+        self.config.send_queue.full_policy = policy;
+        self
+    }
+
+    /// Whether to apply a behavioural penalty to peers for which messages had to be dropped
+    /// because their send queues were full. The default is false.
+    pub fn penalize_slow_peers(&mut self, penalize: bool) -> &mut Self {
+        self.config.penalize_slow_peers = penalize;
+        self
+    }
+
     /// Constructs a [`Config`] from the given configuration and validates the settings.
     pub fn build(&self) -> Result<Config, ConfigBuilderError> {
         // check all constraints on config
@@ -813,6 +1056,17 @@ impl ConfigBuilder {
             return Err(ConfigBuilderError::InvalidProtocol);
         }
 
+        if !(0.0..=1.0).contains(&self.config.choke_duplicates_threshold) {
+            return Err(ConfigBuilderError::ChokeDuplicatesThresholdInvalid);
+        }
+
+        if MessageClass::ALL
+            .iter()
+            .any(|class| self.config.send_queue.size(*class) == 0)
+        {
+            return Err(ConfigBuilderError::SendQueueSizeZero);
+        }
+
         Ok(self.config.clone())
     }
 }
@@ -837,6 +1091,7 @@ impl std::fmt::Debug for Config {
         let _ = builder.field("allow_self_origin", &self.allow_self_origin);
         let _ = builder.field("do_px", &self.do_px);
         let _ = builder.field("prune_peers", &self.prune_peers);
+        let _ = builder.field("require_signed_px_records", &self.require_signed_px_records);
         let _ = builder.field("prune_backoff", &self.prune_backoff);
         let _ = builder.field("backoff_slack", &self.backoff_slack);
         let _ = builder.field("flood_publish", &self.flood_publish);
@@ -852,6 +1107,18 @@ impl std::fmt::Debug for Config {
             "published_message_ids_cache_time",
             &self.published_message_ids_cache_time,
         );
+        let _ = builder.field(
+            "idontwant_message_size_threshold",
+            &self.idontwant_message_size_threshold,
+        );
+        let _ = builder.field("choking", &self.choking);
+        let _ = builder.field(
+            "choke_duplicates_threshold",
+            &self.choke_duplicates_threshold,
+        );
+        let _ = builder.field("unchoke_latency_threshold", &self.unchoke_latency_threshold);
+        let _ = builder.field("send_queue", &self.send_queue);
+        let _ = builder.field("penalize_slow_peers", &self.penalize_slow_peers);
         builder.finish()
     }
 }
@@ -925,19 +1192,50 @@ mod test {
 
         let protocol_ids = protocol_config.protocol_info();
 
-        assert_eq!(protocol_ids.len(), 2);
+        assert_eq!(protocol_ids.len(), 3);
 
         assert_eq!(
             protocol_ids[0].protocol,
-            StreamProtocol::new("/purple/1.1.0")
+            StreamProtocol::new("/purple/1.2.0")
         );
-        assert_eq!(protocol_ids[0].kind, PeerKind::Gossipsubv1_1);
+        assert_eq!(protocol_ids[0].kind, PeerKind::Gossipsubv1_2);
 
         assert_eq!(
             protocol_ids[1].protocol,
+            StreamProtocol::new("/purple/1.1.0")
+        );
+        assert_eq!(protocol_ids[1].kind, PeerKind::Gossipsubv1_1);
+
+        assert_eq!(
+            protocol_ids[2].protocol,
             StreamProtocol::new("/purple/1.0.0")
         );
-        assert_eq!(protocol_ids[1].kind, PeerKind::Gossipsub);
+        assert_eq!(protocol_ids[2].kind, PeerKind::Gossipsub);
+    }
+
+    #[test]
+    fn create_config_with_choking() {
+        let protocol_config = ConfigBuilder::default()
+            .choking(true)
+            .build()
+            .unwrap()
+            .protocol_config();
+
+        let protocol_ids = protocol_config.protocol_info();
+
+        assert_eq!(protocol_ids.len(), 4);
+
+        assert_eq!(
+            protocol_ids[0].protocol,
+            StreamProtocol::new("/meshsub/1.2.0/choke")
+        );
+        assert_eq!(protocol_ids[0].kind, PeerKind::Episub);
+
+        assert_eq!(
+            protocol_ids[1].protocol,
+            StreamProtocol::new("/meshsub/1.2.0")
+        );
+        assert_eq!(protocol_ids[1].kind, PeerKind::Gossipsubv1_2);
     }
 
     #[test]
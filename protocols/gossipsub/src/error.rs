@@ -136,6 +136,10 @@ pub enum ConfigBuilderError {
     UnsubscribeBackoffIsZero,
     /// Invalid protocol
     InvalidProtocol,
+    /// The choke duplicates threshold is not between 0 and 1
+    ChokeDuplicatesThresholdInvalid,
+    /// The size of a send queue is zero
+    SendQueueSizeZero,
 }
 
 impl std::error::Error for ConfigBuilderError {}
@@ -151,6 +155,10 @@ impl std::fmt::Display for ConfigBuilderError {
             Self::MeshOutboundInvalid => write!(f, "The inequality doesn't hold mesh_outbound_min <= self.config.mesh_n / 2"),
             Self::UnsubscribeBackoffIsZero => write!(f, "unsubscribe_backoff is zero"),
             Self::InvalidProtocol => write!(f, "Invalid protocol"),
+            Self::ChokeDuplicatesThresholdInvalid => {
+                write!(f, "The choke duplicates threshold is not between 0 and 1")
+            }
+            Self::SendQueueSizeZero => write!(f, "The size of a send queue is zero"),
         }
     }
 }
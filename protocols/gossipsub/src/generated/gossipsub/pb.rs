@@ -154,6 +154,9 @@ pub struct ControlMessage {
     pub iwant: Vec<gossipsub::pb::ControlIWant>,
     pub graft: Vec<gossipsub::pb::ControlGraft>,
     pub prune: Vec<gossipsub::pb::ControlPrune>,
+    pub idontwant: Vec<gossipsub::pb::ControlIDontWant>,
+    pub choke: Vec<gossipsub::pb::ControlChoke>,
+    pub unchoke: Vec<gossipsub::pb::ControlUnChoke>,
 }
 
 impl<'a> MessageRead<'a> for ControlMessage {
@@ -165,6 +168,9 @@ impl<'a> MessageRead<'a> for ControlMessage {
                 Ok(18) => msg.iwant.push(r.read_message::<gossipsub::pb::ControlIWant>(bytes)?),
                 Ok(26) => msg.graft.push(r.read_message::<gossipsub::pb::ControlGraft>(bytes)?),
                 Ok(34) => msg.prune.push(r.read_message::<gossipsub::pb::ControlPrune>(bytes)?),
+                Ok(42) => msg.idontwant.push(r.read_message::<gossipsub::pb::ControlIDontWant>(bytes)?),
+                Ok(50) => msg.choke.push(r.read_message::<gossipsub::pb::ControlChoke>(bytes)?),
+                Ok(58) => msg.unchoke.push(r.read_message::<gossipsub::pb::ControlUnChoke>(bytes)?),
                 Ok(t) => { r.read_unknown(bytes, t)?; }
                 Err(e) => return Err(e),
             }
@@ -180,6 +186,9 @@ impl MessageWrite for ControlMessage {
         + self.iwant.iter().map(|s| 1 + sizeof_len((s).get_size())).sum::<usize>()
         + self.graft.iter().map(|s| 1 + sizeof_len((s).get_size())).sum::<usize>()
         + self.prune.iter().map(|s| 1 + sizeof_len((s).get_size())).sum::<usize>()
+        + self.idontwant.iter().map(|s| 1 + sizeof_len((s).get_size())).sum::<usize>()
+        + self.choke.iter().map(|s| 1 + sizeof_len((s).get_size())).sum::<usize>()
+        + self.unchoke.iter().map(|s| 1 + sizeof_len((s).get_size())).sum::<usize>()
     }
 
     fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
@@ -187,6 +196,9 @@ impl MessageWrite for ControlMessage {
         for s in &self.iwant { w.write_with_tag(18, |w| w.write_message(s))?; }
         for s in &self.graft { w.write_with_tag(26, |w| w.write_message(s))?; }
         for s in &self.prune { w.write_with_tag(34, |w| w.write_message(s))?; }
+        for s in &self.idontwant { w.write_with_tag(42, |w| w.write_message(s))?; }
+        for s in &self.choke { w.write_with_tag(50, |w| w.write_message(s))?; }
+        for s in &self.unchoke { w.write_with_tag(58, |w| w.write_message(s))?; }
         Ok(())
     }
 }
@@ -259,6 +271,102 @@ impl MessageWrite for ControlIWant {
     }
 }
 
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ControlIDontWant {
+    pub message_ids: Vec<Vec<u8>>,
+}
+
+impl<'a> MessageRead<'a> for ControlIDontWant {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(10) => msg.message_ids.push(r.read_bytes(bytes)?.to_owned()),
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for ControlIDontWant {
+    fn get_size(&self) -> usize {
+        0
+        + self.message_ids.iter().map(|s| 1 + sizeof_len((s).len())).sum::<usize>()
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        for s in &self.message_ids { w.write_with_tag(10, |w| w.write_bytes(&**s))?; }
+        Ok(())
+    }
+}
+
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ControlChoke {
+    pub topic_id: Option<String>,
+}
+
+impl<'a> MessageRead<'a> for ControlChoke {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(10) => msg.topic_id = Some(r.read_string(bytes)?.to_owned()),
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for ControlChoke {
+    fn get_size(&self) -> usize {
+        0
+        + self.topic_id.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        if let Some(ref s) = self.topic_id { w.write_with_tag(10, |w| w.write_string(&**s))?; }
+        Ok(())
+    }
+}
+
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ControlUnChoke {
+    pub topic_id: Option<String>,
+}
+
+impl<'a> MessageRead<'a> for ControlUnChoke {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(10) => msg.topic_id = Some(r.read_string(bytes)?.to_owned()),
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for ControlUnChoke {
+    fn get_size(&self) -> usize {
+        0
+        + self.topic_id.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        if let Some(ref s) = self.topic_id { w.write_with_tag(10, |w| w.write_string(&**s))?; }
+        Ok(())
+    }
+}
+
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct ControlGraft {
@@ -19,6 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::protocol::{GossipsubCodec, ProtocolConfig};
+use crate::queue::{MessageCounts, QueueConfig, QueueDepth, SendQueue};
 use crate::rpc_proto::proto;
 use crate::types::{PeerKind, RawMessage, Rpc, RpcOut};
 use crate::ValidationError;
@@ -33,9 +34,9 @@ use libp2p_swarm::handler::{
     FullyNegotiatedInbound, FullyNegotiatedOutbound, StreamUpgradeError, SubstreamProtocol,
 };
 use libp2p_swarm::Stream;
-use smallvec::SmallVec;
 use std::{
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
@@ -55,6 +56,8 @@ pub enum HandlerEvent {
     /// An inbound or outbound substream has been established with the peer and this informs over
     /// which protocol. This message only occurs once per connection.
     PeerKind(PeerKind),
+    /// Messages have been dropped because their send queues were full.
+    MessagesDropped(MessageCounts),
 }
 
 /// A message sent from the behaviour to the handler.
@@ -95,7 +98,7 @@ pub struct EnabledHandler {
     inbound_substream: Option<InboundSubstreamState>,
 
     /// Queue of values that we want to send to the remote.
-    send_queue: SmallVec<[proto::RPC; 16]>,
+    send_queue: SendQueue,
 
     /// Flag indicating that an outbound substream is being established to prevent duplicate
     /// requests.
@@ -150,7 +153,7 @@ enum OutboundSubstreamState {
     /// Waiting for the user to send a message. The idle state for an outbound substream.
     WaitingOutput(Framed<Stream, GossipsubCodec>),
     /// Waiting to send a message to the remote.
-    PendingSend(Framed<Stream, GossipsubCodec>, proto::RPC),
+    PendingSend(Framed<Stream, GossipsubCodec>, Box<proto::RPC>),
     /// Waiting to flush the substream so that the data arrives to the remote.
     PendingFlush(Framed<Stream, GossipsubCodec>),
     /// An error occurred during processing.
@@ -159,7 +162,11 @@ enum OutboundSubstreamState {
 
 impl Handler {
     /// Builds a new [`Handler`].
-    pub fn new(protocol_config: ProtocolConfig) -> Self {
+    pub(crate) fn new(
+        protocol_config: ProtocolConfig,
+        queue_config: QueueConfig,
+        queue_depth: Arc<QueueDepth>,
+    ) -> Self {
         Handler::Enabled(EnabledHandler {
             listen_protocol: protocol_config,
             inbound_substream: None,
@@ -167,7 +174,7 @@ impl Handler {
             outbound_substream_establishing: false,
             outbound_substream_attempts: 0,
             inbound_substream_attempts: 0,
-            send_queue: SmallVec::new(),
+            send_queue: SendQueue::new(queue_config, queue_depth),
             peer_kind: None,
             peer_kind_sent: false,
             last_io_activity: Instant::now(),
@@ -231,6 +238,12 @@ impl EnabledHandler {
             }
         }
 
+        if let Some(dropped) = self.send_queue.take_dropped() {
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                HandlerEvent::MessagesDropped(dropped),
+            ));
+        }
+
         // determine if we need to create the outbound stream
         if !self.send_queue.is_empty()
             && self.outbound_substream.is_none()
@@ -251,9 +264,10 @@ impl EnabledHandler {
                 // outbound idle state
                 Some(OutboundSubstreamState::WaitingOutput(substream)) => {
                     if let Some(message) = self.send_queue.pop() {
-                        self.send_queue.shrink_to_fit();
-                        self.outbound_substream =
-                            Some(OutboundSubstreamState::PendingSend(substream, message));
+                        self.outbound_substream = Some(OutboundSubstreamState::PendingSend(
+                            substream,
+                            Box::new(message),
+                        ));
                         continue;
                     }
 
@@ -264,7 +278,7 @@ impl EnabledHandler {
                 Some(OutboundSubstreamState::PendingSend(mut substream, message)) => {
                     match Sink::poll_ready(Pin::new(&mut substream), cx) {
                         Poll::Ready(Ok(())) => {
-                            match Sink::start_send(Pin::new(&mut substream), message) {
+                            match Sink::start_send(Pin::new(&mut substream), *message) {
                                 Ok(()) => {
                                     self.outbound_substream =
                                         Some(OutboundSubstreamState::PendingFlush(substream))
@@ -409,7 +423,7 @@ impl ConnectionHandler for Handler {
     fn on_behaviour_event(&mut self, message: HandlerIn) {
         match self {
             Handler::Enabled(handler) => match message {
-                HandlerIn::Message(m) => handler.send_queue.push(m.into_protobuf()),
+                HandlerIn::Message(m) => handler.send_queue.push(m),
                 HandlerIn::JoinedMesh => {
                     handler.in_mesh = true;
                 }
@@ -95,6 +95,8 @@
 
 mod backoff;
 mod behaviour;
+mod choke;
+mod compression;
 mod config;
 mod error;
 mod gossip_promises;
@@ -103,21 +105,29 @@ mod mcache;
 mod metrics;
 mod peer_score;
 mod protocol;
+mod queue;
 mod rpc_proto;
+mod seen_cache;
 mod subscription_filter;
 mod time_cache;
 mod topic;
 mod transform;
 mod types;
+mod validation;
 
 pub use self::behaviour::{Behaviour, Event, MessageAuthenticity};
+pub use self::compression::Compression;
 pub use self::config::{Config, ConfigBuilder, ValidationMode, Version};
 pub use self::error::{ConfigBuilderError, PublishError, SubscriptionError, ValidationError};
 pub use self::metrics::Config as MetricsConfig;
 pub use self::peer_score::{
     score_parameter_decay, score_parameter_decay_with_base, PeerScoreParams, PeerScoreThresholds,
-    TopicScoreParams,
+    RejectReason, ScoringBackend, TopicScoreParams,
 };
+pub use self::queue::{MessageClass, MessageCounts, QueueFullPolicy};
+#[cfg(feature = "file-store")]
+pub use self::seen_cache::FileSeenCacheStore;
+pub use self::seen_cache::SeenCacheStore;
 pub use self::subscription_filter::{
     AllowAllSubscriptionFilter, CallbackSubscriptionFilter, CombinedSubscriptionFilters,
     MaxCountSubscriptionFilter, RegexSubscriptionFilter, TopicSubscriptionFilter,
@@ -126,6 +136,7 @@ pub use self::subscription_filter::{
 pub use self::topic::{Hasher, Topic, TopicHash};
 pub use self::transform::{DataTransform, IdentityTransform};
 pub use self::types::{Message, MessageAcceptance, MessageId, RawMessage};
+pub use self::validation::ValidatorConfig;
 
 #[deprecated(note = "Will be removed from the public API.")]
 pub type Rpc = self::types::Rpc;
@@ -30,6 +30,7 @@ use prometheus_client::metrics::gauge::Gauge;
 use prometheus_client::metrics::histogram::{linear_buckets, Histogram};
 use prometheus_client::registry::Registry;
 
+use crate::queue::{MessageClass, MessageCounts};
 use crate::topic::TopicHash;
 use crate::types::{MessageAcceptance, PeerKind};
 
@@ -174,6 +175,12 @@ pub(crate) struct Metrics {
     /// The number of times we have decided that an IWANT control message is required for this
     /// topic. A very high metric might indicate an underperforming network.
     topic_iwant_msgs: Family<TopicHash, Counter>,
+    /// The number of messages queued to be sent to peers per message class, summed over all
+    /// connections.
+    send_queue_depth: Family<MessageClassLabel, Gauge>,
+    /// The number of messages dropped per message class because the send queue of a connection
+    /// was full.
+    send_queue_dropped: Family<MessageClassLabel, Counter>,
 }
 
 impl Metrics {
@@ -292,6 +299,14 @@ impl Metrics {
             "topic_iwant_msgs",
             "Number of times we have decided an IWANT is required for this topic"
         );
+        let send_queue_depth = register_family!(
+            "send_queue_depth",
+            "Number of messages queued to be sent to peers by message class"
+        );
+        let send_queue_dropped = register_family!(
+            "send_queue_dropped",
+            "Number of messages dropped because the send queue of a peer was full"
+        );
         let memcache_misses = {
             let metric = Counter::default();
             registry.register(
@@ -327,6 +342,8 @@ impl Metrics {
             heartbeat_duration,
             memcache_misses,
             topic_iwant_msgs,
+            send_queue_depth,
+            send_queue_dropped,
         }
     }
 
@@ -435,6 +452,24 @@ impl Metrics {
             .inc();
     }
 
+    /// Set the number of messages queued to be sent to peers.
+    pub(crate) fn set_send_queue_depth(&mut self, depth: &MessageCounts) {
+        for class in MessageClass::ALL {
+            self.send_queue_depth
+                .get_or_create(&MessageClassLabel { class })
+                .set(depth.get(class) as i64);
+        }
+    }
+
+    /// Register messages dropped because the send queue of a peer was full.
+    pub(crate) fn register_send_queue_dropped(&mut self, dropped: &MessageCounts) {
+        for class in MessageClass::ALL {
+            self.send_queue_dropped
+                .get_or_create(&MessageClassLabel { class })
+                .inc_by(dropped.get(class) as u64);
+        }
+    }
+
     /// Registers that a message was published on a specific topic.
     pub(crate) fn register_published_message(&mut self, topic: &TopicHash) {
         if self.register_topic(topic).is_ok() {
@@ -567,6 +602,8 @@ pub(crate) enum Penalty {
     MessageDeficit,
     /// Too many peers under one IP address.
     IPColocation,
+    /// Messages to a peer were dropped because its send queue was full.
+    SlowPeer,
 }
 
 /// Label for the mesh inclusion event metrics.
@@ -589,6 +626,12 @@ struct ProtocolLabel {
     protocol: PeerKind,
 }
 
+/// Label for the message classes of the send queues.
+#[derive(PartialEq, Eq, Hash, EncodeLabelSet, Clone, Debug)]
+struct MessageClassLabel {
+    class: MessageClass,
+}
+
 /// Label for the kinds of scoring penalties that can occur
 #[derive(PartialEq, Eq, Hash, EncodeLabelSet, Clone, Debug)]
 struct PenaltyLabel {
@@ -30,8 +30,11 @@ use std::collections::{hash_map, HashMap, HashSet};
 use std::net::IpAddr;
 use std::time::Duration;
 
+mod backend;
 mod params;
 use crate::ValidationError;
+pub(crate) use backend::Backend;
+pub use backend::ScoringBackend;
 pub use params::{
     score_parameter_decay, score_parameter_decay_with_base, PeerScoreParams, PeerScoreThresholds,
     TopicScoreParams,
@@ -895,8 +898,8 @@ impl PeerScore {
 }
 
 /// The reason a Gossipsub message has been rejected.
-#[derive(Clone, Copy)]
-pub(crate) enum RejectReason {
+#[derive(Debug, Clone, Copy)]
+pub enum RejectReason {
     /// The message failed the configured validation during decoding.
     ValidationError(ValidationError),
     /// The message source is us.
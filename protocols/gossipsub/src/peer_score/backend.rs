@@ -0,0 +1,299 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Pluggable scoring backends.
+
+use super::{PeerScore, RejectReason, TopicScoreParams};
+use crate::metrics::Metrics;
+use crate::{MessageId, TopicHash};
+use libp2p_identity::PeerId;
+use std::net::IpAddr;
+
+/// A backend computing the scores of peers.
+///
+/// The [`Behaviour`](crate::Behaviour) reports all events relevant for scoring to the backend
+/// and compares the returned scores against the configured
+/// [`PeerScoreThresholds`](crate::PeerScoreThresholds). Apart from [`ScoringBackend::score`],
+/// all methods have no-op default implementations, so a backend only needs to implement the
+/// events it is interested in.
+///
+/// The built-in implementation of the gossipsub v1.1 peer scoring is used by
+/// [`Behaviour::with_peer_score`](crate::Behaviour::with_peer_score).
+pub trait ScoringBackend: Send + 'static {
+    /// Returns the current score of the given peer.
+    fn score(&self, peer_id: &PeerId) -> f64;
+
+    /// Called periodically, with the interval given to
+    /// [`Behaviour::with_scoring_backend`](crate::Behaviour::with_scoring_backend), e.g. to
+    /// decay counters.
+    fn refresh_scores(&mut self) {}
+
+    /// A peer connected.
+    fn add_peer(&mut self, _peer_id: PeerId) {}
+
+    /// A peer disconnected.
+    fn remove_peer(&mut self, _peer_id: &PeerId) {}
+
+    /// A connection to a peer was established from or to the given IP address.
+    fn add_ip(&mut self, _peer_id: &PeerId, _ip: IpAddr) {}
+
+    /// The last connection to a peer from or to the given IP address was closed.
+    fn remove_ip(&mut self, _peer_id: &PeerId, _ip: &IpAddr) {}
+
+    /// A peer was added to the mesh of a topic.
+    fn graft(&mut self, _peer_id: &PeerId, _topic_hash: TopicHash) {}
+
+    /// A peer was removed from the mesh of a topic.
+    fn prune(&mut self, _peer_id: &PeerId, _topic_hash: TopicHash) {}
+
+    /// A new message was received from a peer and is about to be validated.
+    fn validate_message(&mut self, _from: &PeerId, _msg_id: &MessageId, _topic_hash: &TopicHash) {}
+
+    /// A message received from a peer was validated and is forwarded.
+    fn deliver_message(&mut self, _from: &PeerId, _msg_id: &MessageId, _topic_hash: &TopicHash) {}
+
+    /// A message was received from a peer that we already received before.
+    fn duplicated_message(&mut self, _from: &PeerId, _msg_id: &MessageId, _topic_hash: &TopicHash) {
+    }
+
+    /// A message received from a peer was rejected for the given reason.
+    fn reject_message(
+        &mut self,
+        _from: &PeerId,
+        _msg_id: &MessageId,
+        _topic_hash: &TopicHash,
+        _reason: RejectReason,
+    ) {
+    }
+
+    /// A message received from a peer was invalid and its id couldn't be determined.
+    fn reject_invalid_message(&mut self, _from: &PeerId, _topic_hash: &TopicHash) {}
+
+    /// A peer misbehaved `count` times, e.g. by breaking IWANT promises or grafting during a
+    /// backoff.
+    fn add_penalty(&mut self, _peer_id: &PeerId, _count: usize) {}
+
+    /// Sets the application specific score of a peer. Returns whether the score was applied.
+    fn set_application_score(&mut self, _peer_id: &PeerId, _new_score: f64) -> bool {
+        false
+    }
+
+    /// Sets the scoring parameters of a topic.
+    fn set_topic_params(&mut self, _topic_hash: TopicHash, _params: TopicScoreParams) {}
+
+    /// Returns the scoring parameters of a topic, if any.
+    fn topic_params(&self, _topic_hash: &TopicHash) -> Option<&TopicScoreParams> {
+        None
+    }
+}
+
+impl ScoringBackend for PeerScore {
+    fn score(&self, peer_id: &PeerId) -> f64 {
+        self.score(peer_id)
+    }
+
+    fn refresh_scores(&mut self) {
+        self.refresh_scores()
+    }
+
+    fn add_peer(&mut self, peer_id: PeerId) {
+        self.add_peer(peer_id)
+    }
+
+    fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.remove_peer(peer_id)
+    }
+
+    fn add_ip(&mut self, peer_id: &PeerId, ip: IpAddr) {
+        self.add_ip(peer_id, ip)
+    }
+
+    fn remove_ip(&mut self, peer_id: &PeerId, ip: &IpAddr) {
+        self.remove_ip(peer_id, ip)
+    }
+
+    fn graft(&mut self, peer_id: &PeerId, topic_hash: TopicHash) {
+        self.graft(peer_id, topic_hash)
+    }
+
+    fn prune(&mut self, peer_id: &PeerId, topic_hash: TopicHash) {
+        self.prune(peer_id, topic_hash)
+    }
+
+    fn validate_message(&mut self, from: &PeerId, msg_id: &MessageId, topic_hash: &TopicHash) {
+        self.validate_message(from, msg_id, topic_hash)
+    }
+
+    fn deliver_message(&mut self, from: &PeerId, msg_id: &MessageId, topic_hash: &TopicHash) {
+        self.deliver_message(from, msg_id, topic_hash)
+    }
+
+    fn duplicated_message(&mut self, from: &PeerId, msg_id: &MessageId, topic_hash: &TopicHash) {
+        self.duplicated_message(from, msg_id, topic_hash)
+    }
+
+    fn reject_message(
+        &mut self,
+        from: &PeerId,
+        msg_id: &MessageId,
+        topic_hash: &TopicHash,
+        reason: RejectReason,
+    ) {
+        self.reject_message(from, msg_id, topic_hash, reason)
+    }
+
+    fn reject_invalid_message(&mut self, from: &PeerId, topic_hash: &TopicHash) {
+        self.reject_invalid_message(from, topic_hash)
+    }
+
+    fn add_penalty(&mut self, peer_id: &PeerId, count: usize) {
+        self.add_penalty(peer_id, count)
+    }
+
+    fn set_application_score(&mut self, peer_id: &PeerId, new_score: f64) -> bool {
+        self.set_application_score(peer_id, new_score)
+    }
+
+    fn set_topic_params(&mut self, topic_hash: TopicHash, params: TopicScoreParams) {
+        self.set_topic_params(topic_hash, params)
+    }
+
+    fn topic_params(&self, topic_hash: &TopicHash) -> Option<&TopicScoreParams> {
+        self.get_topic_params(topic_hash)
+    }
+}
+
+/// The scoring backend of the [`Behaviour`](crate::Behaviour).
+///
+/// The built-in [`PeerScore`] is kept as such, so it can keep reporting penalties to the metrics.
+pub(crate) enum Backend {
+    Builtin(Box<PeerScore>),
+    Custom(Box<dyn ScoringBackend>),
+}
+
+impl Backend {
+    /// Returns the score of a peer, logging metrics of the built-in backend.
+    pub(crate) fn metric_score(&self, peer_id: &PeerId, metrics: Option<&mut Metrics>) -> f64 {
+        match self {
+            Backend::Builtin(peer_score) => peer_score.metric_score(peer_id, metrics),
+            Backend::Custom(backend) => backend.score(peer_id),
+        }
+    }
+
+    /// Returns the mesh message deliveries of a peer in a topic, for the built-in backend.
+    pub(crate) fn mesh_message_deliveries(&self, peer: &PeerId, topic: &TopicHash) -> Option<f64> {
+        match self {
+            Backend::Builtin(peer_score) => peer_score.mesh_message_deliveries(peer, topic),
+            Backend::Custom(_) => None,
+        }
+    }
+
+    fn as_dyn(&self) -> &dyn ScoringBackend {
+        match self {
+            Backend::Builtin(peer_score) => peer_score.as_ref(),
+            Backend::Custom(backend) => backend.as_ref(),
+        }
+    }
+
+    fn as_dyn_mut(&mut self) -> &mut dyn ScoringBackend {
+        match self {
+            Backend::Builtin(peer_score) => peer_score.as_mut(),
+            Backend::Custom(backend) => backend.as_mut(),
+        }
+    }
+}
+
+impl ScoringBackend for Backend {
+    fn score(&self, peer_id: &PeerId) -> f64 {
+        self.as_dyn().score(peer_id)
+    }
+
+    fn refresh_scores(&mut self) {
+        self.as_dyn_mut().refresh_scores()
+    }
+
+    fn add_peer(&mut self, peer_id: PeerId) {
+        self.as_dyn_mut().add_peer(peer_id)
+    }
+
+    fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.as_dyn_mut().remove_peer(peer_id)
+    }
+
+    fn add_ip(&mut self, peer_id: &PeerId, ip: IpAddr) {
+        self.as_dyn_mut().add_ip(peer_id, ip)
+    }
+
+    fn remove_ip(&mut self, peer_id: &PeerId, ip: &IpAddr) {
+        self.as_dyn_mut().remove_ip(peer_id, ip)
+    }
+
+    fn graft(&mut self, peer_id: &PeerId, topic_hash: TopicHash) {
+        self.as_dyn_mut().graft(peer_id, topic_hash)
+    }
+
+    fn prune(&mut self, peer_id: &PeerId, topic_hash: TopicHash) {
+        self.as_dyn_mut().prune(peer_id, topic_hash)
+    }
+
+    fn validate_message(&mut self, from: &PeerId, msg_id: &MessageId, topic_hash: &TopicHash) {
+        self.as_dyn_mut().validate_message(from, msg_id, topic_hash)
+    }
+
+    fn deliver_message(&mut self, from: &PeerId, msg_id: &MessageId, topic_hash: &TopicHash) {
+        self.as_dyn_mut().deliver_message(from, msg_id, topic_hash)
+    }
+
+    fn duplicated_message(&mut self, from: &PeerId, msg_id: &MessageId, topic_hash: &TopicHash) {
+        self.as_dyn_mut()
+            .duplicated_message(from, msg_id, topic_hash)
+    }
+
+    fn reject_message(
+        &mut self,
+        from: &PeerId,
+        msg_id: &MessageId,
+        topic_hash: &TopicHash,
+        reason: RejectReason,
+    ) {
+        self.as_dyn_mut()
+            .reject_message(from, msg_id, topic_hash, reason)
+    }
+
+    fn reject_invalid_message(&mut self, from: &PeerId, topic_hash: &TopicHash) {
+        self.as_dyn_mut().reject_invalid_message(from, topic_hash)
+    }
+
+    fn add_penalty(&mut self, peer_id: &PeerId, count: usize) {
+        self.as_dyn_mut().add_penalty(peer_id, count)
+    }
+
+    fn set_application_score(&mut self, peer_id: &PeerId, new_score: f64) -> bool {
+        self.as_dyn_mut().set_application_score(peer_id, new_score)
+    }
+
+    fn set_topic_params(&mut self, topic_hash: TopicHash, params: TopicScoreParams) {
+        self.as_dyn_mut().set_topic_params(topic_hash, params)
+    }
+
+    fn topic_params(&self, topic_hash: &TopicHash) -> Option<&TopicScoreParams> {
+        self.as_dyn().topic_params(topic_hash)
+    }
+}
@@ -18,6 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use crate::compression::{Compression, FLAG_COMPRESSED, FLAG_UNCOMPRESSED};
 use crate::config::ValidationMode;
 use crate::handler::HandlerEvent;
 use crate::rpc_proto::proto;
@@ -28,28 +29,39 @@ use crate::types::{
 use crate::ValidationError;
 use asynchronous_codec::{Decoder, Encoder, Framed};
 use byteorder::{BigEndian, ByteOrder};
-use bytes::BytesMut;
+use bytes::{Buf, BufMut, BytesMut};
 use futures::prelude::*;
 use libp2p_core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
 use libp2p_identity::{PeerId, PublicKey};
 use libp2p_swarm::StreamProtocol;
 use quick_protobuf::Writer;
+use std::io;
 use std::pin::Pin;
 use void::Void;
 
 pub(crate) const SIGNING_PREFIX: &[u8] = b"libp2p-pubsub:";
 
+pub(crate) const GOSSIPSUB_1_2_0_PROTOCOL: ProtocolId = ProtocolId {
+    protocol: StreamProtocol::new("/meshsub/1.2.0"),
+    kind: PeerKind::Gossipsubv1_2,
+    compression: None,
+};
 pub(crate) const GOSSIPSUB_1_1_0_PROTOCOL: ProtocolId = ProtocolId {
     protocol: StreamProtocol::new("/meshsub/1.1.0"),
     kind: PeerKind::Gossipsubv1_1,
+    compression: None,
 };
 pub(crate) const GOSSIPSUB_1_0_0_PROTOCOL: ProtocolId = ProtocolId {
     protocol: StreamProtocol::new("/meshsub/1.0.0"),
     kind: PeerKind::Gossipsub,
+    compression: None,
 };
+/// The suffix of the gossipsub 1.2 protocol ids negotiating the choking extension.
+const CHOKE_PROTOCOL_SUFFIX: &str = "choke";
 pub(crate) const FLOODSUB_PROTOCOL: ProtocolId = ProtocolId {
     protocol: StreamProtocol::new("/floodsub/1.0.0"),
     kind: PeerKind::Floodsub,
+    compression: None,
 };
 
 /// Implementation of [`InboundUpgrade`] and [`OutboundUpgrade`] for the Gossipsub protocol.
@@ -61,6 +73,42 @@ pub struct ProtocolConfig {
     pub(crate) max_transmit_size: usize,
     /// Determines the level of validation to be done on incoming messages.
     pub(crate) validation_mode: ValidationMode,
+    /// Whether to accept unsigned messages from floodsub peers, see
+    /// [`crate::ConfigBuilder::floodsub_compatibility`].
+    pub(crate) floodsub_compatibility: bool,
+    /// The compression offered in addition to the uncompressed protocols.
+    pub(crate) compression: Option<Compression>,
+    /// The minimum size of RPCs compressed on streams with compression.
+    pub(crate) compression_threshold: usize,
+    /// Whether to offer the choking extension, see [`crate::ConfigBuilder::choking`].
+    pub(crate) choking: bool,
+}
+
+impl ProtocolConfig {
+    /// The validation mode for messages received via the given protocol.
+    ///
+    /// In floodsub compatibility mode, [`ValidationMode::Strict`] is relaxed to
+    /// [`ValidationMode::Permissive`] for floodsub peers, as floodsub doesn't sign messages.
+    fn validation_mode(&self, protocol_id: &ProtocolId) -> ValidationMode {
+        match self.validation_mode {
+            ValidationMode::Strict
+                if self.floodsub_compatibility && protocol_id.kind == PeerKind::Floodsub =>
+            {
+                ValidationMode::Permissive
+            }
+            ref mode => mode.clone(),
+        }
+    }
+
+    /// The codec for a stream negotiated via the given protocol.
+    fn codec(&self, protocol_id: &ProtocolId) -> GossipsubCodec {
+        GossipsubCodec::new(self.max_transmit_size, self.validation_mode(protocol_id))
+            .with_compression(
+                protocol_id
+                    .compression
+                    .map(|compression| (compression, self.compression_threshold)),
+            )
+    }
 }
 
 impl Default for ProtocolConfig {
@@ -68,7 +116,15 @@ impl Default for ProtocolConfig {
         Self {
             max_transmit_size: 65536,
             validation_mode: ValidationMode::Strict,
-            protocol_ids: vec![GOSSIPSUB_1_1_0_PROTOCOL, GOSSIPSUB_1_0_0_PROTOCOL],
+            floodsub_compatibility: false,
+            compression: None,
+            compression_threshold: 1024,
+            choking: false,
+            protocol_ids: vec![
+                GOSSIPSUB_1_2_0_PROTOCOL,
+                GOSSIPSUB_1_1_0_PROTOCOL,
+                GOSSIPSUB_1_0_0_PROTOCOL,
+            ],
         }
     }
 }
@@ -80,6 +136,45 @@ pub struct ProtocolId {
     pub protocol: StreamProtocol,
     /// The type of protocol we support
     pub kind: PeerKind,
+    /// The compression of the RPC frames.
+    pub compression: Option<Compression>,
+}
+
+impl ProtocolId {
+    /// The protocol id with the suffix negotiating the given compression.
+    fn with_compression(&self, compression: Compression) -> Option<ProtocolId> {
+        let protocol = StreamProtocol::try_from_owned(format!(
+            "{}/{}",
+            self.protocol,
+            compression.protocol_suffix()
+        ))
+        .ok()?;
+
+        Some(ProtocolId {
+            protocol,
+            kind: self.kind.clone(),
+            compression: Some(compression),
+        })
+    }
+}
+
+impl ProtocolId {
+    /// The protocol id with the suffix negotiating the choking extension, for gossipsub 1.2
+    /// protocol ids.
+    fn with_choking(&self) -> Option<ProtocolId> {
+        if self.kind != PeerKind::Gossipsubv1_2 {
+            return None;
+        }
+        let protocol =
+            StreamProtocol::try_from_owned(format!("{}/{CHOKE_PROTOCOL_SUFFIX}", self.protocol))
+                .ok()?;
+
+        Some(ProtocolId {
+            protocol,
+            kind: PeerKind::Episub,
+            compression: self.compression,
+        })
+    }
 }
 
 impl AsRef<str> for ProtocolId {
@@ -93,7 +188,28 @@ impl UpgradeInfo for ProtocolConfig {
     type InfoIter = Vec<Self::Info>;
 
     fn protocol_info(&self) -> Self::InfoIter {
-        self.protocol_ids.clone()
+        // Protocols with the choking extension are preferred over those without.
+        let protocol_ids = if self.choking {
+            self.protocol_ids
+                .iter()
+                .filter_map(|id| id.with_choking())
+                .chain(self.protocol_ids.iter().cloned())
+                .collect()
+        } else {
+            self.protocol_ids.clone()
+        };
+
+        let Some(compression) = self.compression else {
+            return protocol_ids;
+        };
+
+        // Protocols with compression are preferred over those without.
+        protocol_ids
+            .iter()
+            .filter(|id| id.kind != PeerKind::Floodsub)
+            .filter_map(|id| id.with_compression(compression))
+            .chain(protocol_ids.iter().cloned())
+            .collect()
     }
 }
 
@@ -107,10 +223,7 @@ where
 
     fn upgrade_inbound(self, socket: TSocket, protocol_id: Self::Info) -> Self::Future {
         Box::pin(future::ok((
-            Framed::new(
-                socket,
-                GossipsubCodec::new(self.max_transmit_size, self.validation_mode),
-            ),
+            Framed::new(socket, self.codec(&protocol_id)),
             protocol_id.kind,
         )))
     }
@@ -126,10 +239,7 @@ where
 
     fn upgrade_outbound(self, socket: TSocket, protocol_id: Self::Info) -> Self::Future {
         Box::pin(future::ok((
-            Framed::new(
-                socket,
-                GossipsubCodec::new(self.max_transmit_size, self.validation_mode),
-            ),
+            Framed::new(socket, self.codec(&protocol_id)),
             protocol_id.kind,
         )))
     }
@@ -142,6 +252,11 @@ pub struct GossipsubCodec {
     validation_mode: ValidationMode,
     /// The codec to handle common encoding/decoding of protobuf messages
     codec: quick_protobuf_codec::Codec<proto::RPC>,
+    /// The maximum length of an uncompressed RPC.
+    max_length: usize,
+    /// The compression of RPC frames negotiated for the stream, with the minimum size of the
+    /// RPCs to compress.
+    compression: Option<(Compression, usize)>,
 }
 
 impl GossipsubCodec {
@@ -150,9 +265,17 @@ impl GossipsubCodec {
         GossipsubCodec {
             validation_mode,
             codec,
+            max_length,
+            compression: None,
         }
     }
 
+    /// Frames RPCs with the given compression and threshold, see [`crate::compression`].
+    pub(crate) fn with_compression(mut self, compression: Option<(Compression, usize)>) -> Self {
+        self.compression = compression;
+        self
+    }
+
     /// Verifies a gossipsub message. This returns either a success or failure. All errors
     /// are logged, which prevents error handling in the codec and handler. We simply drop invalid
     /// messages and log warnings, rather than propagating errors through the codec.
@@ -215,7 +338,94 @@ impl Encoder for GossipsubCodec {
     type Error = quick_protobuf_codec::Error;
 
     fn encode(&mut self, item: Self::Item<'_>, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        self.codec.encode(item, dst)
+        use quick_protobuf::MessageWrite;
+
+        let Some((compression, threshold)) = self.compression else {
+            return self.codec.encode(item, dst);
+        };
+
+        let mut rpc = Vec::with_capacity(item.get_size());
+        item.write_message(&mut Writer::new(&mut rpc))
+            .expect("Encoding to succeed");
+        let compressed = if rpc.len() >= threshold {
+            Some(compression.compress(&rpc)?).filter(|compressed| compressed.len() < rpc.len())
+        } else {
+            None
+        };
+        let (flag, payload) = match compressed {
+            Some(compressed) => (FLAG_COMPRESSED, compressed),
+            None => (FLAG_UNCOMPRESSED, rpc),
+        };
+
+        let mut uvi_buf = unsigned_varint::encode::usize_buffer();
+        dst.extend_from_slice(unsigned_varint::encode::usize(
+            payload.len() + 1,
+            &mut uvi_buf,
+        ));
+        dst.put_u8(flag);
+        dst.extend_from_slice(&payload);
+
+        Ok(())
+    }
+}
+
+impl GossipsubCodec {
+    /// Decodes the next RPC, decompressing it if compression was negotiated.
+    fn decode_rpc(
+        &mut self,
+        src: &mut BytesMut,
+    ) -> Result<Option<proto::RPC>, quick_protobuf_codec::Error> {
+        use quick_protobuf::{BytesReader, MessageRead};
+
+        let Some((compression, _)) = self.compression else {
+            return self.codec.decode(src);
+        };
+
+        let (frame_length, remaining) = match unsigned_varint::decode::usize(src) {
+            Ok((length, remaining)) => (length, remaining),
+            Err(unsigned_varint::decode::Error::Insufficient) => return Ok(None),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e).into()),
+        };
+        // Frames are only compressed if that shrinks them, thus never exceed the flag and the
+        // maximum length of an RPC.
+        if frame_length > self.max_length + 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "message with {frame_length}b exceeds maximum of {}b",
+                    self.max_length
+                ),
+            )
+            .into());
+        }
+        let varint_length = src.len() - remaining.len();
+        if src.len() < varint_length + frame_length {
+            return Ok(None);
+        }
+        src.advance(varint_length);
+        let frame = src.split_to(frame_length);
+
+        let decompressed;
+        let rpc = match frame.split_first() {
+            Some((&FLAG_UNCOMPRESSED, rpc)) => rpc,
+            Some((&FLAG_COMPRESSED, compressed)) => {
+                decompressed = compression.decompress(compressed, self.max_length)?;
+                &decompressed
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "frame without valid compression flag",
+                )
+                .into())
+            }
+        };
+
+        let mut reader = BytesReader::from_bytes(rpc);
+        let rpc = proto::RPC::from_reader(&mut reader, rpc)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(Some(rpc))
     }
 }
 
@@ -224,7 +434,7 @@ impl Decoder for GossipsubCodec {
     type Error = quick_protobuf_codec::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let Some(rpc) = self.codec.decode(src)? else {
+        let Some(rpc) = self.decode_rpc(src)? else {
             return Ok(None);
         };
         // Store valid messages.
@@ -442,6 +652,34 @@ impl Decoder for GossipsubCodec {
                 })
                 .collect();
 
+            let choke_msgs: Vec<ControlAction> = rpc_control
+                .choke
+                .into_iter()
+                .map(|choke| ControlAction::Choke {
+                    topic_hash: TopicHash::from_raw(choke.topic_id.unwrap_or_default()),
+                })
+                .collect();
+
+            let unchoke_msgs: Vec<ControlAction> = rpc_control
+                .unchoke
+                .into_iter()
+                .map(|unchoke| ControlAction::Unchoke {
+                    topic_hash: TopicHash::from_raw(unchoke.topic_id.unwrap_or_default()),
+                })
+                .collect();
+
+            let idontwant_msgs: Vec<ControlAction> = rpc_control
+                .idontwant
+                .into_iter()
+                .map(|idontwant| ControlAction::IDontWant {
+                    message_ids: idontwant
+                        .message_ids
+                        .into_iter()
+                        .map(MessageId::from)
+                        .collect::<Vec<_>>(),
+                })
+                .collect();
+
             let mut prune_msgs = Vec::new();
 
             for prune in rpc_control.prune {
@@ -449,16 +687,7 @@ impl Decoder for GossipsubCodec {
                 let peers = prune
                     .peers
                     .into_iter()
-                    .filter_map(|info| {
-                        info.peer_id
-                            .as_ref()
-                            .and_then(|id| PeerId::from_bytes(id).ok())
-                            .map(|peer_id|
-                                    //TODO signedPeerRecord, see https://github.com/libp2p/specs/pull/217
-                                    PeerInfo {
-                                        peer_id: Some(peer_id),
-                                    })
-                    })
+                    .filter_map(PeerInfo::from_proto)
                     .collect::<Vec<PeerInfo>>();
 
                 let topic_hash = TopicHash::from_raw(prune.topic_id.unwrap_or_default());
@@ -473,6 +702,9 @@ impl Decoder for GossipsubCodec {
             control_msgs.extend(iwant_msgs);
             control_msgs.extend(graft_msgs);
             control_msgs.extend(prune_msgs);
+            control_msgs.extend(idontwant_msgs);
+            control_msgs.extend(choke_msgs);
+            control_msgs.extend(unchoke_msgs);
         }
 
         Ok(Some(HandlerEvent::Message {
@@ -611,4 +843,93 @@ mod tests {
         assert_eq!(protocol_config.protocol_ids[0].protocol, "/foosub");
         assert_eq!(protocol_config.protocol_ids[1].protocol, "/floodsub/1.0.0");
     }
+
+    #[test]
+    fn floodsub_compatibility_relaxes_strict_validation_for_floodsub() {
+        let protocol_config = ConfigBuilder::default()
+            .floodsub_compatibility()
+            .build()
+            .unwrap()
+            .protocol_config();
+
+        assert!(protocol_config.protocol_ids.contains(&FLOODSUB_PROTOCOL));
+        assert!(matches!(
+            protocol_config.validation_mode(&FLOODSUB_PROTOCOL),
+            ValidationMode::Permissive
+        ));
+        assert!(matches!(
+            protocol_config.validation_mode(&GOSSIPSUB_1_1_0_PROTOCOL),
+            ValidationMode::Strict
+        ));
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn compressed_protocols_are_preferred() {
+        let protocol_config = ConfigBuilder::default()
+            .compression(Compression::Deflate)
+            .support_floodsub()
+            .build()
+            .unwrap()
+            .protocol_config();
+
+        let protocols = protocol_config
+            .protocol_info()
+            .into_iter()
+            .map(|id| id.protocol.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            protocols,
+            vec![
+                "/meshsub/1.2.0/deflate",
+                "/meshsub/1.1.0/deflate",
+                "/meshsub/1.0.0/deflate",
+                "/meshsub/1.2.0",
+                "/meshsub/1.1.0",
+                "/meshsub/1.0.0",
+                "/floodsub/1.0.0",
+            ]
+        );
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn encode_decode_with_compression() {
+        let message = RawMessage {
+            source: None,
+            data: vec![42; 4096],
+            sequence_number: None,
+            topic: TopicHash::from_raw("topic"),
+            signature: None,
+            key: None,
+            validated: false,
+        };
+        let rpc = || Rpc {
+            messages: vec![message.clone()],
+            subscriptions: vec![],
+            control_msgs: vec![],
+        };
+
+        let mut plain = BytesMut::new();
+        GossipsubCodec::new(8192, ValidationMode::None)
+            .encode(rpc().into_protobuf(), &mut plain)
+            .unwrap();
+
+        for threshold in [1024, 8192] {
+            let mut codec = GossipsubCodec::new(8192, ValidationMode::None)
+                .with_compression(Some((Compression::Deflate, threshold)));
+            let mut buf = BytesMut::new();
+            codec.encode(rpc().into_protobuf(), &mut buf).unwrap();
+            assert_eq!(buf.len() < plain.len(), threshold == 1024);
+
+            match codec.decode(&mut buf).unwrap().unwrap() {
+                HandlerEvent::Message { rpc, .. } => {
+                    assert_eq!(rpc.messages, vec![message.clone()])
+                }
+                _ => panic!("Must decode a message"),
+            }
+            assert!(buf.is_empty());
+        }
+    }
 }
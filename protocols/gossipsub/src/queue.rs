@@ -0,0 +1,267 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Bounded queue of the messages to send on a connection, with one queue per [`MessageClass`].
+
+use crate::rpc_proto::proto;
+use crate::types::{ControlAction, RpcOut};
+use prometheus_client::encoding::EncodeLabelValue;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// The priority class of a message sent to a peer.
+///
+/// Each class has its own bounded queue per connection, see
+/// [`ConfigBuilder::send_queue_size`](crate::ConfigBuilder::send_queue_size). Messages of a class
+/// are only sent once the queues of all higher priority classes are empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
+pub enum MessageClass {
+    /// Subscriptions and control messages other than `IHAVE`. The highest priority.
+    Control,
+    /// Messages published by the local node.
+    Publish,
+    /// Messages forwarded on behalf of other peers, including responses to `IWANT`.
+    Forward,
+    /// `IHAVE` control messages. The lowest priority.
+    Gossip,
+}
+
+impl MessageClass {
+    /// All classes, from the highest to the lowest priority.
+    pub(crate) const ALL: [MessageClass; 4] = [
+        MessageClass::Control,
+        MessageClass::Publish,
+        MessageClass::Forward,
+        MessageClass::Gossip,
+    ];
+
+    pub(crate) fn of(rpc: &RpcOut) -> Self {
+        match rpc {
+            RpcOut::Publish(_) => MessageClass::Publish,
+            RpcOut::Forward(_) => MessageClass::Forward,
+            RpcOut::Control(ControlAction::IHave { .. }) => MessageClass::Gossip,
+            RpcOut::Subscribe(_) | RpcOut::Unsubscribe(_) | RpcOut::Control(_) => {
+                MessageClass::Control
+            }
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Which message to drop when the queue of a [`MessageClass`] is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueFullPolicy {
+    /// Drop the message to be queued.
+    #[default]
+    DropNewest,
+    /// Drop the oldest queued message of the class in favour of the message to be queued.
+    DropOldest,
+}
+
+/// A number of messages per [`MessageClass`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessageCounts {
+    counts: [usize; 4],
+}
+
+impl MessageCounts {
+    /// The number of messages of the given class.
+    pub fn get(&self, class: MessageClass) -> usize {
+        self.counts[class.index()]
+    }
+
+    /// The number of messages of all classes.
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    pub(crate) fn add(&mut self, other: &MessageCounts) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+    }
+}
+
+/// The number of queued messages per [`MessageClass`] of a connection, shared between its
+/// handler and the behaviour.
+#[derive(Debug, Default)]
+pub(crate) struct QueueDepth([AtomicUsize; 4]);
+
+impl QueueDepth {
+    pub(crate) fn get(&self) -> MessageCounts {
+        MessageCounts {
+            counts: std::array::from_fn(|i| self.0[i].load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// The size of the queue of each [`MessageClass`] and what to do when one is full.
+#[derive(Debug, Clone)]
+pub(crate) struct QueueConfig {
+    pub(crate) sizes: [usize; 4],
+    pub(crate) full_policy: QueueFullPolicy,
+}
+
+impl QueueConfig {
+    pub(crate) fn size(&self, class: MessageClass) -> usize {
+        self.sizes[class.index()]
+    }
+
+    pub(crate) fn set_size(&mut self, class: MessageClass, size: usize) {
+        self.sizes[class.index()] = size;
+    }
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            sizes: [1000, 500, 500, 200],
+            full_policy: QueueFullPolicy::DropNewest,
+        }
+    }
+}
+
+/// The messages to send on a connection.
+pub(crate) struct SendQueue {
+    config: QueueConfig,
+    queues: [VecDeque<proto::RPC>; 4],
+    depth: Arc<QueueDepth>,
+    /// The messages dropped since they were last taken via [`SendQueue::take_dropped`].
+    dropped: MessageCounts,
+}
+
+impl SendQueue {
+    /// Creates an empty queue, keeping the given [`QueueDepth`] up to date.
+    pub(crate) fn new(config: QueueConfig, depth: Arc<QueueDepth>) -> Self {
+        Self {
+            config,
+            queues: Default::default(),
+            depth,
+            dropped: MessageCounts::default(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, rpc: RpcOut) {
+        let class = MessageClass::of(&rpc);
+        let i = class.index();
+
+        if self.queues[i].len() >= self.config.size(class) {
+            self.dropped.counts[i] += 1;
+            tracing::debug!(?class, "Send queue full, dropping message");
+
+            match self.config.full_policy {
+                QueueFullPolicy::DropNewest => return,
+                QueueFullPolicy::DropOldest => {
+                    if self.queues[i].pop_front().is_none() {
+                        return;
+                    }
+                    self.depth.0[i].fetch_sub(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        self.queues[i].push_back(rpc.into_protobuf());
+        self.depth.0[i].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Removes the oldest message of the highest priority class.
+    pub(crate) fn pop(&mut self) -> Option<proto::RPC> {
+        let (i, message) = self
+            .queues
+            .iter_mut()
+            .enumerate()
+            .find_map(|(i, queue)| Some((i, queue.pop_front()?)))?;
+        self.depth.0[i].fetch_sub(1, Ordering::Relaxed);
+
+        Some(message)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.queues.iter().all(VecDeque::is_empty)
+    }
+
+    /// Takes the counts of the messages dropped since the last call, if any.
+    pub(crate) fn take_dropped(&mut self) -> Option<MessageCounts> {
+        if self.dropped.total() == 0 {
+            return None;
+        }
+        Some(std::mem::take(&mut self.dropped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TopicHash;
+
+    fn subscribe() -> RpcOut {
+        RpcOut::Subscribe(TopicHash::from_raw("topic"))
+    }
+
+    fn ihave(id: &str) -> RpcOut {
+        RpcOut::Control(ControlAction::IHave {
+            topic_hash: TopicHash::from_raw("topic"),
+            message_ids: vec![id.into()],
+        })
+    }
+
+    fn ihave_id(rpc: proto::RPC) -> Vec<u8> {
+        rpc.control.unwrap().ihave[0].message_ids[0].clone()
+    }
+
+    #[test]
+    fn higher_priority_classes_are_sent_first() {
+        let depth = Arc::new(QueueDepth::default());
+        let mut queue = SendQueue::new(QueueConfig::default(), depth.clone());
+        queue.push(ihave("1"));
+        queue.push(subscribe());
+
+        assert_eq!(depth.get().get(MessageClass::Gossip), 1);
+        assert_eq!(depth.get().get(MessageClass::Control), 1);
+        assert!(!queue.pop().unwrap().subscriptions.is_empty());
+        assert!(queue.pop().unwrap().control.is_some());
+        assert!(queue.pop().is_none());
+        assert_eq!(depth.get().total(), 0);
+    }
+
+    #[test]
+    fn full_queues_drop_messages_according_to_policy() {
+        let mut config = QueueConfig::default();
+        config.set_size(MessageClass::Gossip, 1);
+
+        let mut queue = SendQueue::new(config.clone(), Default::default());
+        queue.push(ihave("1"));
+        queue.push(ihave("2"));
+        assert_eq!(ihave_id(queue.pop().unwrap()), b"1");
+        assert_eq!(queue.take_dropped().unwrap().get(MessageClass::Gossip), 1);
+        assert_eq!(queue.take_dropped(), None);
+
+        config.full_policy = QueueFullPolicy::DropOldest;
+        let mut queue = SendQueue::new(config, Default::default());
+        queue.push(ihave("1"));
+        queue.push(ihave("2"));
+        assert_eq!(ihave_id(queue.pop().unwrap()), b"2");
+        assert_eq!(queue.take_dropped().unwrap().get(MessageClass::Gossip), 1);
+    }
+}
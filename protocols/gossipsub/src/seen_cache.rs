@@ -0,0 +1,55 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Persistence of the IDs of seen messages across restarts.
+
+#[cfg(feature = "file-store")]
+mod file;
+
+#[cfg(feature = "file-store")]
+pub use file::FileSeenCacheStore;
+
+use crate::MessageId;
+use std::io;
+use std::time::Duration;
+
+/// Persists the IDs of the messages in the duplicate cache of a [`Behaviour`](crate::Behaviour),
+/// such that a restarting node doesn't re-deliver or re-forward messages it has already seen.
+///
+/// The behaviour keeps its duplicate cache in memory and reports every newly seen message to the
+/// store. On activation via
+/// [`Behaviour::with_seen_cache_store`](crate::Behaviour::with_seen_cache_store), the IDs
+/// returned by [`SeenCacheStore::load`] are added to the duplicate cache.
+pub trait SeenCacheStore: Send + 'static {
+    /// Loads the IDs of the persisted messages that are to be considered seen still.
+    fn load(&mut self) -> io::Result<Vec<MessageId>>;
+
+    /// Persists that the message with the given ID was seen, and should be considered seen for
+    /// `ttl`.
+    ///
+    /// Called for every message entering the duplicate cache, so implementations are expected to
+    /// buffer writes until the next call to [`SeenCacheStore::flush`].
+    fn insert(&mut self, id: &MessageId, ttl: Duration) -> io::Result<()>;
+
+    /// Writes buffered insertions through to the underlying storage.
+    ///
+    /// Called on every heartbeat.
+    fn flush(&mut self) -> io::Result<()>;
+}
@@ -0,0 +1,253 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use super::*;
+
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CURRENT_FILE: &str = "seen";
+const PREVIOUS_FILE: &str = "seen.old";
+
+/// A [`SeenCacheStore`] appending the IDs of seen messages to a file in a directory.
+///
+/// Every line of the file holds the time at which a message stops being considered seen, in
+/// seconds since the UNIX epoch, followed by the hex-encoded message ID. To bound the size of the
+/// files, the file is rotated once all entries of the previously rotated file expired, which is
+/// then deleted.
+#[derive(Debug)]
+pub struct FileSeenCacheStore {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// The latest expiry of the entries in the current file, if any.
+    current_expiry: Option<SystemTime>,
+    /// The latest expiry of the entries in the previous file, if any.
+    previous_expiry: Option<SystemTime>,
+}
+
+impl FileSeenCacheStore {
+    /// Creates a new `FileSeenCacheStore` storing message IDs in the given directory.
+    ///
+    /// The directory is created if it doesn't exist.
+    pub fn new(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        fs::create_dir_all(&path)?;
+        let writer = open_append(&path.join(CURRENT_FILE))?;
+
+        Ok(Self {
+            path,
+            writer,
+            current_expiry: None,
+            previous_expiry: None,
+        })
+    }
+
+    /// Moves the current file to the previous one, if all entries of the latter expired.
+    fn rotate(&mut self, now: SystemTime) -> io::Result<()> {
+        let Some(current_expiry) = self.current_expiry else {
+            return Ok(());
+        };
+        if self.previous_expiry.map_or(false, |expiry| expiry > now) {
+            return Ok(());
+        }
+
+        fs::rename(self.path.join(CURRENT_FILE), self.path.join(PREVIOUS_FILE))?;
+        self.writer = open_append(&self.path.join(CURRENT_FILE))?;
+        self.previous_expiry = Some(current_expiry);
+        self.current_expiry = None;
+
+        Ok(())
+    }
+}
+
+impl SeenCacheStore for FileSeenCacheStore {
+    fn load(&mut self) -> io::Result<Vec<MessageId>> {
+        self.writer.flush()?;
+
+        let now = SystemTime::now();
+        let mut entries = Vec::new();
+        for file in [PREVIOUS_FILE, CURRENT_FILE] {
+            match File::open(self.path.join(file)) {
+                Ok(file) => read_entries(file, now, &mut entries)?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Compact the live entries into a fresh current file.
+        let current = self.path.join(CURRENT_FILE);
+        let tmp = current.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        for (id, expiry) in &entries {
+            write_entry(&mut writer, id, *expiry)?;
+        }
+        writer.flush()?;
+        fs::rename(&tmp, &current)?;
+        match fs::remove_file(self.path.join(PREVIOUS_FILE)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+
+        self.writer = open_append(&current)?;
+        self.current_expiry = entries.iter().map(|(_, expiry)| *expiry).max();
+        self.previous_expiry = None;
+
+        Ok(entries.into_iter().map(|(id, _)| id).collect())
+    }
+
+    fn insert(&mut self, id: &MessageId, ttl: Duration) -> io::Result<()> {
+        let expiry = SystemTime::now() + ttl;
+        write_entry(&mut self.writer, id, expiry)?;
+        self.current_expiry = Some(self.current_expiry.map_or(expiry, |e| e.max(expiry)));
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.rotate(SystemTime::now())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<BufWriter<File>> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(BufWriter::new(file))
+}
+
+fn write_entry(writer: &mut impl Write, id: &MessageId, expiry: SystemTime) -> io::Result<()> {
+    let expiry = expiry
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    let id = id.0.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    });
+    writeln!(writer, "{expiry} {id}")
+}
+
+fn read_entries(
+    file: File,
+    now: SystemTime,
+    entries: &mut Vec<(MessageId, SystemTime)>,
+) -> io::Result<()> {
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        // Skip malformed lines, e.g. one truncated by a crash while writing.
+        let Some((expiry, id)) = parse_entry(&line) else {
+            tracing::debug!(%line, "Skipping malformed seen cache entry");
+            continue;
+        };
+        if expiry > now {
+            entries.push((id, expiry));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_entry(line: &str) -> Option<(SystemTime, MessageId)> {
+    let (expiry, id) = line.split_once(' ')?;
+    let expiry = UNIX_EPOCH + Duration::from_secs(expiry.parse().ok()?);
+    if id.len() % 2 != 0 {
+        return None;
+    }
+    let id = (0..id.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(id.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    Some((expiry, MessageId(id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persists_seen_messages_until_expiry() {
+        let dir =
+            std::env::temp_dir().join(format!("gossipsub-seen-cache-{}", rand::random::<u64>()));
+
+        let mut store = FileSeenCacheStore::new(&dir).unwrap();
+        assert!(store.load().unwrap().is_empty());
+        store
+            .insert(&MessageId::new(b"live"), Duration::from_secs(60))
+            .unwrap();
+        store
+            .insert(&MessageId::new(b"expired"), Duration::ZERO)
+            .unwrap();
+        store.flush().unwrap();
+        drop(store);
+
+        let mut store = FileSeenCacheStore::new(&dir).unwrap();
+        assert_eq!(store.load().unwrap(), vec![MessageId::new(b"live")]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotation_keeps_unexpired_messages() {
+        let dir =
+            std::env::temp_dir().join(format!("gossipsub-seen-cache-{}", rand::random::<u64>()));
+
+        let mut store = FileSeenCacheStore::new(&dir).unwrap();
+        store
+            .insert(&MessageId::new(b"first"), Duration::from_secs(60))
+            .unwrap();
+        // Rotates the first message into the previous file.
+        store.flush().unwrap();
+        store
+            .insert(&MessageId::new(b"second"), Duration::from_secs(60))
+            .unwrap();
+        // Doesn't rotate, as the previous file hasn't expired yet.
+        store.flush().unwrap();
+        drop(store);
+
+        let mut store = FileSeenCacheStore::new(&dir).unwrap();
+        let mut loaded = store.load().unwrap();
+        loaded.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            loaded,
+            vec![MessageId::new(b"first"), MessageId::new(b"second")]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parses_written_entries() {
+        let mut buf = Vec::new();
+        let expiry = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        write_entry(&mut buf, &MessageId::new(&[0x00, 0xab, 0xff]), expiry).unwrap();
+        let line = String::from_utf8(buf).unwrap();
+
+        assert_eq!(line, "1700000000 00abff\n");
+        assert_eq!(
+            parse_entry(line.trim_end()),
+            Some((expiry, MessageId::new(&[0x00, 0xab, 0xff])))
+        );
+        assert_eq!(parse_entry("1700000000 0ab"), None);
+        assert_eq!(parse_entry("garbage"), None);
+    }
+}
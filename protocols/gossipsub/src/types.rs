@@ -20,6 +20,9 @@
 
 //! A collection of types using the Gossipsub system.
 use crate::TopicHash;
+use hashlink::LinkedHashMap;
+use instant::Instant;
+use libp2p_core::{PeerRecord, SignedEnvelope};
 use libp2p_identity::PeerId;
 use libp2p_swarm::ConnectionId;
 use prometheus_client::encoding::EncodeLabelValue;
@@ -31,7 +34,7 @@ use crate::rpc_proto::proto;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Validation kinds from the application for received messages.
 pub enum MessageAcceptance {
     /// The message is considered valid, and it should be delivered and forwarded to the network.
@@ -77,11 +80,17 @@ pub(crate) struct PeerConnections {
     pub(crate) kind: PeerKind,
     /// Its current connections.
     pub(crate) connections: Vec<ConnectionId>,
+    /// Message ids the peer told us via IDONTWANT not to send, with the time they were received.
+    pub(crate) dont_send: LinkedHashMap<MessageId, Instant>,
 }
 
 /// Describes the types of peers that can exist in the gossipsub context.
 #[derive(Debug, Clone, PartialEq, Hash, EncodeLabelValue, Eq)]
 pub enum PeerKind {
+    /// A gossipsub 1.2 peer supporting the episub choking extension.
+    Episub,
+    /// A gossipsub 1.2 peer.
+    Gossipsubv1_2,
     /// A gossipsub 1.1 peer.
     Gossipsubv1_1,
     /// A gossipsub 1.0 peer.
@@ -194,12 +203,66 @@ pub enum SubscriptionAction {
     Unsubscribe,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PeerInfo {
     pub peer_id: Option<PeerId>,
-    //TODO add this when RFC: Signed Address Records got added to the spec (see pull request
-    // https://github.com/libp2p/specs/pull/217)
-    //pub signed_peer_record: ?,
+    /// The signed addresses of the peer, see
+    /// <https://github.com/libp2p/specs/blob/master/RFC/0003-routing-records.md>.
+    pub signed_peer_record: Option<PeerRecord>,
+}
+
+impl PeerInfo {
+    /// Decodes and validates a [`proto::PeerInfo`].
+    ///
+    /// Returns `None` if the peer ID is invalid or if the signed peer record is invalid or
+    /// belongs to a different peer. The peer ID is taken from the signed peer record if absent.
+    pub(crate) fn from_proto(info: proto::PeerInfo) -> Option<Self> {
+        let peer_id = match info.peer_id {
+            Some(id) => Some(PeerId::from_bytes(&id).ok()?),
+            None => None,
+        };
+
+        let signed_peer_record = match info.signed_peer_record {
+            Some(bytes) => {
+                let record = SignedEnvelope::from_protobuf_encoding(&bytes)
+                    .ok()
+                    .and_then(|envelope| PeerRecord::from_signed_envelope(envelope).ok());
+                match record {
+                    Some(record) if !peer_id.is_some_and(|p| p != record.peer_id()) => Some(record),
+                    _ => {
+                        tracing::debug!(peer=?peer_id, "Ignoring PX peer with invalid signed peer record");
+                        return None;
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let peer_id = peer_id.or_else(|| signed_peer_record.as_ref().map(PeerRecord::peer_id))?;
+
+        Some(PeerInfo {
+            peer_id: Some(peer_id),
+            signed_peer_record,
+        })
+    }
+}
+
+// Only hashes the peer ID, as `PeerRecord` does not implement `Hash`.
+impl std::hash::Hash for PeerInfo {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.peer_id.hash(state);
+    }
+}
+
+impl From<PeerInfo> for proto::PeerInfo {
+    fn from(info: PeerInfo) -> Self {
+        proto::PeerInfo {
+            peer_id: info.peer_id.map(|id| id.to_bytes()),
+            signed_peer_record: info
+                .signed_peer_record
+                .map(|record| record.into_signed_envelope().into_protobuf_encoding()),
+        }
+    }
 }
 
 /// A Control message received by the gossipsub system.
@@ -231,6 +294,23 @@ pub enum ControlAction {
         /// The backoff time in seconds before we allow to reconnect
         backoff: Option<u64>,
     },
+    /// The node has received a message and doesn't want it to be forwarded to it again -
+    /// IDontWant control message.
+    IDontWant {
+        /// A list of message ids of messages the node already received.
+        message_ids: Vec<MessageId>,
+    },
+    /// The node only wants to receive announcements of the messages of a mesh topic instead of
+    /// the full messages - Choke control message.
+    Choke {
+        /// The mesh topic the peer should only announce messages for.
+        topic_hash: TopicHash,
+    },
+    /// The node wants to receive the full messages of a mesh topic again - Unchoke control message.
+    Unchoke {
+        /// The mesh topic the peer should forward messages for again.
+        topic_hash: TopicHash,
+    },
 }
 
 /// A Gossipsub RPC message sent.
@@ -300,6 +380,9 @@ impl From<RpcOut> for proto::RPC {
                     iwant: vec![],
                     graft: vec![],
                     prune: vec![],
+                    idontwant: vec![],
+                    choke: vec![],
+                    unchoke: vec![],
                 }),
             },
             RpcOut::Control(ControlAction::IWant { message_ids }) => proto::RPC {
@@ -312,6 +395,9 @@ impl From<RpcOut> for proto::RPC {
                     }],
                     graft: vec![],
                     prune: vec![],
+                    idontwant: vec![],
+                    choke: vec![],
+                    unchoke: vec![],
                 }),
             },
             RpcOut::Control(ControlAction::Graft { topic_hash }) => proto::RPC {
@@ -324,35 +410,77 @@ impl From<RpcOut> for proto::RPC {
                         topic_id: Some(topic_hash.into_string()),
                     }],
                     prune: vec![],
+                    idontwant: vec![],
+                    choke: vec![],
+                    unchoke: vec![],
                 }),
             },
             RpcOut::Control(ControlAction::Prune {
                 topic_hash,
                 peers,
                 backoff,
-            }) => {
-                proto::RPC {
-                    publish: Vec::new(),
-                    subscriptions: vec![],
-                    control: Some(proto::ControlMessage {
-                        ihave: vec![],
-                        iwant: vec![],
-                        graft: vec![],
-                        prune: vec![proto::ControlPrune {
-                            topic_id: Some(topic_hash.into_string()),
-                            peers: peers
-                                .into_iter()
-                                .map(|info| proto::PeerInfo {
-                                    peer_id: info.peer_id.map(|id| id.to_bytes()),
-                                    // TODO, see https://github.com/libp2p/specs/pull/217
-                                    signed_peer_record: None,
-                                })
-                                .collect(),
-                            backoff,
-                        }],
-                    }),
-                }
-            }
+            }) => proto::RPC {
+                publish: Vec::new(),
+                subscriptions: vec![],
+                control: Some(proto::ControlMessage {
+                    ihave: vec![],
+                    iwant: vec![],
+                    graft: vec![],
+                    prune: vec![proto::ControlPrune {
+                        topic_id: Some(topic_hash.into_string()),
+                        peers: peers.into_iter().map(Into::into).collect(),
+                        backoff,
+                    }],
+                    idontwant: vec![],
+                    choke: vec![],
+                    unchoke: vec![],
+                }),
+            },
+            RpcOut::Control(ControlAction::IDontWant { message_ids }) => proto::RPC {
+                publish: Vec::new(),
+                subscriptions: Vec::new(),
+                control: Some(proto::ControlMessage {
+                    ihave: vec![],
+                    iwant: vec![],
+                    graft: vec![],
+                    prune: vec![],
+                    idontwant: vec![proto::ControlIDontWant {
+                        message_ids: message_ids.into_iter().map(|msg_id| msg_id.0).collect(),
+                    }],
+                    choke: vec![],
+                    unchoke: vec![],
+                }),
+            },
+            RpcOut::Control(ControlAction::Choke { topic_hash }) => proto::RPC {
+                publish: Vec::new(),
+                subscriptions: Vec::new(),
+                control: Some(proto::ControlMessage {
+                    ihave: vec![],
+                    iwant: vec![],
+                    graft: vec![],
+                    prune: vec![],
+                    idontwant: vec![],
+                    choke: vec![proto::ControlChoke {
+                        topic_id: Some(topic_hash.into_string()),
+                    }],
+                    unchoke: vec![],
+                }),
+            },
+            RpcOut::Control(ControlAction::Unchoke { topic_hash }) => proto::RPC {
+                publish: Vec::new(),
+                subscriptions: Vec::new(),
+                control: Some(proto::ControlMessage {
+                    ihave: vec![],
+                    iwant: vec![],
+                    graft: vec![],
+                    prune: vec![],
+                    idontwant: vec![],
+                    choke: vec![],
+                    unchoke: vec![proto::ControlUnChoke {
+                        topic_id: Some(topic_hash.into_string()),
+                    }],
+                }),
+            },
         }
     }
 }
@@ -411,6 +539,9 @@ impl From<Rpc> for proto::RPC {
             iwant: Vec::new(),
             graft: Vec::new(),
             prune: Vec::new(),
+            idontwant: Vec::new(),
+            choke: Vec::new(),
+            unchoke: Vec::new(),
         };
 
         let empty_control_msg = rpc.control_msgs.is_empty();
@@ -447,18 +578,29 @@ impl From<Rpc> for proto::RPC {
                 } => {
                     let rpc_prune = proto::ControlPrune {
                         topic_id: Some(topic_hash.into_string()),
-                        peers: peers
-                            .into_iter()
-                            .map(|info| proto::PeerInfo {
-                                peer_id: info.peer_id.map(|id| id.to_bytes()),
-                                // TODO, see https://github.com/libp2p/specs/pull/217
-                                signed_peer_record: None,
-                            })
-                            .collect(),
+                        peers: peers.into_iter().map(Into::into).collect(),
                         backoff,
                     };
                     control.prune.push(rpc_prune);
                 }
+                ControlAction::IDontWant { message_ids } => {
+                    let rpc_idontwant = proto::ControlIDontWant {
+                        message_ids: message_ids.into_iter().map(|msg_id| msg_id.0).collect(),
+                    };
+                    control.idontwant.push(rpc_idontwant);
+                }
+                ControlAction::Choke { topic_hash } => {
+                    let rpc_choke = proto::ControlChoke {
+                        topic_id: Some(topic_hash.into_string()),
+                    };
+                    control.choke.push(rpc_choke);
+                }
+                ControlAction::Unchoke { topic_hash } => {
+                    let rpc_unchoke = proto::ControlUnChoke {
+                        topic_id: Some(topic_hash.into_string()),
+                    };
+                    control.unchoke.push(rpc_unchoke);
+                }
             }
         }
 
@@ -497,6 +639,8 @@ impl PeerKind {
             Self::Floodsub => "Floodsub",
             Self::Gossipsub => "Gossipsub v1.0",
             Self::Gossipsubv1_1 => "Gossipsub v1.1",
+            Self::Gossipsubv1_2 => "Gossipsub v1.2",
+            Self::Episub => "Gossipsub v1.2 with choking",
         }
     }
 }
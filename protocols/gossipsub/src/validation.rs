@@ -0,0 +1,311 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Asynchronous validation of received messages, see
+//! [`Behaviour::with_validator`](crate::Behaviour::with_validator).
+
+use crate::topic::TopicHash;
+use crate::types::{Message, MessageAcceptance, MessageId};
+use futures::future::{BoxFuture, Either};
+use futures::stream::FuturesUnordered;
+use futures::{Future, FutureExt, StreamExt};
+use futures_timer::Delay;
+use libp2p_identity::PeerId;
+use std::collections::{HashMap, VecDeque};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// The configuration of the validator set via
+/// [`Behaviour::with_validator`](crate::Behaviour::with_validator).
+#[derive(Debug, Clone)]
+pub struct ValidatorConfig {
+    max_concurrent_validations: usize,
+    max_queued_validations: usize,
+    validation_timeout: Duration,
+}
+
+impl Default for ValidatorConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_validations: 64,
+            max_queued_validations: 1024,
+            validation_timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+impl ValidatorConfig {
+    /// Sets the maximum number of messages validated concurrently. Defaults to 64.
+    pub fn with_max_concurrent_validations(mut self, n: usize) -> Self {
+        self.max_concurrent_validations = n;
+        self
+    }
+
+    /// Sets the maximum number of messages waiting for their validation to start. Further
+    /// messages are ignored, as if validated with [`MessageAcceptance::Ignore`]. Defaults to 1024.
+    pub fn with_max_queued_validations(mut self, n: usize) -> Self {
+        self.max_queued_validations = n;
+        self
+    }
+
+    /// Sets the time after which a validation is aborted and the message ignored, as if validated
+    /// with [`MessageAcceptance::Ignore`]. Defaults to 2 seconds.
+    pub fn with_validation_timeout(mut self, timeout: Duration) -> Self {
+        self.validation_timeout = timeout;
+        self
+    }
+}
+
+/// A message whose validation completed.
+pub(crate) struct Validated {
+    pub(crate) propagation_source: PeerId,
+    pub(crate) message_id: MessageId,
+    pub(crate) message: Message,
+    pub(crate) acceptance: MessageAcceptance,
+}
+
+/// A message being validated.
+struct Validation {
+    seq: u64,
+    propagation_source: PeerId,
+    message_id: MessageId,
+    message: Message,
+    acceptance: Option<MessageAcceptance>,
+}
+
+type ValidatorFn =
+    Box<dyn FnMut(PeerId, MessageId, Message) -> BoxFuture<'static, MessageAcceptance> + Send>;
+
+/// Runs the validator on received messages with bounded concurrency.
+///
+/// Validations run concurrently, but completed validations of a topic are returned in the order
+/// the messages were received.
+pub(crate) struct ValidatorPool {
+    validator: ValidatorFn,
+    config: ValidatorConfig,
+    next_seq: u64,
+    /// The messages being validated per topic, in the order they were received.
+    topics: HashMap<TopicHash, VecDeque<Validation>>,
+    /// The messages whose validation is yet to start.
+    queued: VecDeque<(TopicHash, u64)>,
+    running: FuturesUnordered<BoxFuture<'static, (TopicHash, u64, MessageAcceptance)>>,
+}
+
+impl ValidatorPool {
+    pub(crate) fn new<F, Fut>(config: ValidatorConfig, mut validator: F) -> Self
+    where
+        F: FnMut(PeerId, MessageId, Message) -> Fut + Send + 'static,
+        Fut: Future<Output = MessageAcceptance> + Send + 'static,
+    {
+        Self {
+            validator: Box::new(move |source, id, message| validator(source, id, message).boxed()),
+            config,
+            next_seq: 0,
+            topics: HashMap::new(),
+            queued: VecDeque::new(),
+            running: FuturesUnordered::new(),
+        }
+    }
+
+    /// Queues the message for validation. Returns `false` if the queue is full.
+    pub(crate) fn push(
+        &mut self,
+        propagation_source: PeerId,
+        message_id: MessageId,
+        message: Message,
+    ) -> bool {
+        if self.queued.len() >= self.config.max_queued_validations {
+            return false;
+        }
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let topic = message.topic.clone();
+        self.queued.push_back((topic.clone(), seq));
+        self.topics.entry(topic).or_default().push_back(Validation {
+            seq,
+            propagation_source,
+            message_id,
+            message,
+            acceptance: None,
+        });
+
+        true
+    }
+
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Validated> {
+        loop {
+            if let Some(validated) = self.pop_validated() {
+                return Poll::Ready(validated);
+            }
+
+            while self.running.len() < self.config.max_concurrent_validations {
+                let Some((topic, seq)) = self.queued.pop_front() else {
+                    break;
+                };
+                self.start(topic, seq);
+            }
+
+            match self.running.poll_next_unpin(cx) {
+                Poll::Ready(Some((topic, seq, acceptance))) => {
+                    if let Some(validation) = self
+                        .topics
+                        .get_mut(&topic)
+                        .and_then(|v| v.iter_mut().find(|v| v.seq == seq))
+                    {
+                        validation.acceptance = Some(acceptance);
+                    }
+                }
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    fn start(&mut self, topic: TopicHash, seq: u64) {
+        let Some(validation) = self
+            .topics
+            .get(&topic)
+            .and_then(|v| v.iter().find(|v| v.seq == seq))
+        else {
+            return;
+        };
+
+        let validation_timeout = self.config.validation_timeout;
+        let message_id = validation.message_id.clone();
+        let validate = (self.validator)(
+            validation.propagation_source,
+            validation.message_id.clone(),
+            validation.message.clone(),
+        );
+        let timeout = Delay::new(validation_timeout);
+
+        self.running.push(
+            async move {
+                let acceptance = match futures::future::select(validate, timeout).await {
+                    Either::Left((acceptance, _)) => acceptance,
+                    Either::Right(((), _)) => {
+                        tracing::debug!(
+                            message=%message_id,
+                            "Validation timed out after {validation_timeout:?}, ignoring message"
+                        );
+                        MessageAcceptance::Ignore
+                    }
+                };
+                (topic, seq, acceptance)
+            }
+            .boxed(),
+        );
+    }
+
+    /// Removes the first message of a topic whose validation completed.
+    fn pop_validated(&mut self) -> Option<Validated> {
+        let topic = self.topics.iter().find_map(|(topic, validations)| {
+            validations
+                .front()
+                .filter(|v| v.acceptance.is_some())
+                .map(|_| topic.clone())
+        })?;
+
+        let validations = self.topics.get_mut(&topic).expect("topic to exist");
+        let validation = validations.pop_front().expect("front to exist");
+        if validations.is_empty() {
+            self.topics.remove(&topic);
+        }
+
+        Some(Validated {
+            propagation_source: validation.propagation_source,
+            message_id: validation.message_id,
+            message: validation.message,
+            acceptance: validation.acceptance.expect("validation to be completed"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::oneshot;
+    use futures::task::noop_waker;
+
+    fn message(topic: &str, data: u8) -> Message {
+        Message {
+            source: None,
+            data: vec![data],
+            sequence_number: None,
+            topic: TopicHash::from_raw(topic),
+        }
+    }
+
+    #[test]
+    fn validations_of_a_topic_complete_in_order() {
+        let (tx1, rx1) = oneshot::channel();
+        let (tx2, rx2) = oneshot::channel();
+        let mut receivers = VecDeque::from([rx1, rx2]);
+        let mut pool = ValidatorPool::new(ValidatorConfig::default(), move |_, _, _| {
+            let rx: oneshot::Receiver<MessageAcceptance> = receivers.pop_front().unwrap();
+            async move { rx.await.unwrap() }
+        });
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let source = PeerId::random();
+        assert!(pool.push(source, MessageId::new(&[1]), message("a", 1)));
+        assert!(pool.push(source, MessageId::new(&[2]), message("a", 2)));
+        assert!(pool.poll(&mut cx).is_pending());
+
+        // The second message is held back until the first one is validated.
+        tx2.send(MessageAcceptance::Reject).unwrap();
+        assert!(pool.poll(&mut cx).is_pending());
+        tx1.send(MessageAcceptance::Accept).unwrap();
+
+        let Poll::Ready(first) = pool.poll(&mut cx) else {
+            panic!("Expected the first message to be validated");
+        };
+        assert_eq!(first.message.data, vec![1]);
+        assert_eq!(first.acceptance, MessageAcceptance::Accept);
+        let Poll::Ready(second) = pool.poll(&mut cx) else {
+            panic!("Expected the second message to be validated");
+        };
+        assert_eq!(second.message.data, vec![2]);
+        assert_eq!(second.acceptance, MessageAcceptance::Reject);
+    }
+
+    #[test]
+    fn validations_exceeding_limits_are_queued_or_refused() {
+        let config = ValidatorConfig::default()
+            .with_max_concurrent_validations(1)
+            .with_max_queued_validations(1);
+        let mut pool = ValidatorPool::new(config, |_, _, _| {
+            futures::future::pending::<MessageAcceptance>()
+        });
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let source = PeerId::random();
+        assert!(pool.push(source, MessageId::new(&[1]), message("a", 1)));
+        assert!(pool.poll(&mut cx).is_pending());
+        assert_eq!(pool.running.len(), 1);
+
+        assert!(pool.push(source, MessageId::new(&[2]), message("b", 2)));
+        assert!(pool.poll(&mut cx).is_pending());
+        assert_eq!(pool.running.len(), 1);
+        assert!(!pool.push(source, MessageId::new(&[3]), message("b", 3)));
+    }
+}
@@ -0,0 +1,91 @@
+use std::io;
+
+use futures::AsyncWriteExt as _;
+use http::{header::ACCEPT, Request, Response, StatusCode};
+use libp2p_identity::PeerId;
+use libp2p_stream::{Control, OpenStreamError};
+
+use crate::{
+    codec, WellKnownProtocols, DEFAULT_MAX_BODY_SIZE, PROTOCOL_NAME, WELL_KNOWN_PROTOCOLS_PATH,
+};
+
+/// Sends HTTP requests to peers.
+///
+/// Every request is sent on a new stream. A [`Client`] can be cloned and thus allows for
+/// concurrent requests.
+#[derive(Clone)]
+pub struct Client {
+    control: Control,
+    max_body_size: usize,
+}
+
+impl Client {
+    /// Creates a new [`Client`] opening streams via the given [`Control`].
+    pub fn new(control: Control) -> Self {
+        Self {
+            control,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Sets the maximum size of the body of a response.
+    ///
+    /// Defaults to [`DEFAULT_MAX_BODY_SIZE`].
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Sends the request to the given peer and waits for its response.
+    ///
+    /// Only the path and query of the request URI are sent. The `Content-Length` header is set
+    /// to the length of the body.
+    pub async fn send(
+        &mut self,
+        peer: PeerId,
+        request: Request<Vec<u8>>,
+    ) -> Result<Response<Vec<u8>>, Error> {
+        let method = request.method().clone();
+        let mut stream = self.control.open_stream(peer, PROTOCOL_NAME).await?;
+
+        codec::write_request(&mut stream, request).await?;
+        stream.close().await?;
+        let response = codec::read_response(&mut stream, &method, self.max_body_size).await?;
+
+        Ok(response)
+    }
+
+    /// Fetches the protocols served by the given peer from its
+    /// [`WELL_KNOWN_PROTOCOLS_PATH`](crate::WELL_KNOWN_PROTOCOLS_PATH).
+    pub async fn protocols(&mut self, peer: PeerId) -> Result<WellKnownProtocols, Error> {
+        let request = Request::get(WELL_KNOWN_PROTOCOLS_PATH)
+            .header(ACCEPT, "application/json")
+            .body(Vec::new())
+            .expect("request to be valid");
+        let response = self.send(peer, request).await?;
+
+        if !response.status().is_success() {
+            return Err(Error::UnexpectedStatus(response.status()));
+        }
+
+        Ok(serde_json::from_slice(response.body())?)
+    }
+}
+
+/// Errors of a [`Client`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The stream to the peer could not be opened.
+    #[error("failed to open stream")]
+    OpenStream(#[from] OpenStreamError),
+    /// Sending the request or receiving the response failed.
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+    /// The peer responded with an unexpected status.
+    #[error("unexpected response status {0}")]
+    UnexpectedStatus(StatusCode),
+    /// The well-known protocols document of the peer is invalid.
+    #[error("invalid well-known protocols document")]
+    InvalidDocument(#[from] serde_json::Error),
+}
@@ -0,0 +1,347 @@
+//! A minimal HTTP/1.1 codec for a single request and response per stream.
+//!
+//! Bodies are delimited by the `Content-Length` header. A response without a `Content-Length`
+//! header extends until the remote closes the stream. Responses to `HEAD` requests and responses
+//! with a `1xx`, `204` or `304` status have no body. Chunked transfer encoding is not supported.
+
+use std::io;
+
+use futures::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use http::{
+    header::{CONTENT_LENGTH, TRANSFER_ENCODING},
+    HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode, Version,
+};
+
+/// The maximum size of the request line or status line and the headers of a message.
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+/// The maximum number of headers of a message.
+const MAX_HEADERS: usize = 64;
+
+pub(crate) async fn write_request<S>(stream: &mut S, request: Request<Vec<u8>>) -> io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let (parts, body) = request.into_parts();
+    let target = parts
+        .uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+
+    let mut head = format!("{} {target} HTTP/1.1\r\n", parts.method).into_bytes();
+    encode_headers(&mut head, &parts.headers, body.len());
+
+    write_message(stream, &head, &body).await
+}
+
+pub(crate) async fn write_response<S>(
+    stream: &mut S,
+    method: &Method,
+    response: Response<Vec<u8>>,
+) -> io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let (parts, body) = response.into_parts();
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        parts.status.as_u16(),
+        parts.status.canonical_reason().unwrap_or_default()
+    )
+    .into_bytes();
+    encode_headers(&mut head, &parts.headers, body.len());
+
+    if method == Method::HEAD {
+        return write_message(stream, &head, &[]).await;
+    }
+    write_message(stream, &head, &body).await
+}
+
+pub(crate) async fn read_request<S>(
+    stream: &mut S,
+    max_body_size: usize,
+) -> io::Result<Request<Vec<u8>>>
+where
+    S: AsyncRead + Unpin,
+{
+    let (mut buf, head_len) = read_head(stream).await?;
+
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut parsed = httparse::Request::new(&mut headers);
+    if !parsed.parse(&buf).map_err(invalid_data)?.is_complete() {
+        return Err(invalid_data("incomplete request head"));
+    }
+
+    let mut builder = Request::builder()
+        .method(parsed.method.unwrap_or_default())
+        .uri(parsed.path.unwrap_or_default())
+        .version(Version::HTTP_11);
+    let header_map = builder.headers_mut().expect("builder to be valid");
+    decode_headers(header_map, parsed.headers)?;
+    let content_length = content_length(header_map)?;
+
+    buf.drain(..head_len);
+    // Requests without a `Content-Length` header have no body.
+    let body = read_body(
+        stream,
+        buf,
+        Some(content_length.unwrap_or(0)),
+        max_body_size,
+    )
+    .await?;
+
+    builder.body(body).map_err(invalid_data)
+}
+
+pub(crate) async fn read_response<S>(
+    stream: &mut S,
+    method: &Method,
+    max_body_size: usize,
+) -> io::Result<Response<Vec<u8>>>
+where
+    S: AsyncRead + Unpin,
+{
+    let (mut buf, head_len) = read_head(stream).await?;
+
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut parsed = httparse::Response::new(&mut headers);
+    if !parsed.parse(&buf).map_err(invalid_data)?.is_complete() {
+        return Err(invalid_data("incomplete response head"));
+    }
+
+    let status = StatusCode::from_u16(parsed.code.unwrap_or_default()).map_err(invalid_data)?;
+    let mut builder = Response::builder().status(status).version(Version::HTTP_11);
+    let header_map = builder.headers_mut().expect("builder to be valid");
+    decode_headers(header_map, parsed.headers)?;
+    let content_length = content_length(header_map)?;
+
+    buf.drain(..head_len);
+    let has_body = method != Method::HEAD
+        && !status.is_informational()
+        && status != StatusCode::NO_CONTENT
+        && status != StatusCode::NOT_MODIFIED;
+    let content_length = if has_body { content_length } else { Some(0) };
+    let body = read_body(stream, buf, content_length, max_body_size).await?;
+
+    builder.body(body).map_err(invalid_data)
+}
+
+async fn write_message<S>(stream: &mut S, head: &[u8], body: &[u8]) -> io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    stream.write_all(head).await?;
+    stream.write_all(body).await?;
+    stream.flush().await
+}
+
+/// Encodes the headers and the end of the head, with the `Content-Length` set to the length of
+/// the body.
+fn encode_headers(buf: &mut Vec<u8>, headers: &HeaderMap, body_len: usize) {
+    for (name, value) in headers {
+        if name == CONTENT_LENGTH || name == TRANSFER_ENCODING {
+            continue;
+        }
+        buf.extend_from_slice(name.as_str().as_bytes());
+        buf.extend_from_slice(b": ");
+        buf.extend_from_slice(value.as_bytes());
+        buf.extend_from_slice(b"\r\n");
+    }
+    buf.extend_from_slice(format!("{CONTENT_LENGTH}: {body_len}\r\n\r\n").as_bytes());
+}
+
+fn decode_headers(map: &mut HeaderMap, headers: &[httparse::Header<'_>]) -> io::Result<()> {
+    for header in headers {
+        map.append(
+            HeaderName::from_bytes(header.name.as_bytes()).map_err(invalid_data)?,
+            HeaderValue::from_bytes(header.value).map_err(invalid_data)?,
+        );
+    }
+    Ok(())
+}
+
+fn content_length(headers: &HeaderMap) -> io::Result<Option<usize>> {
+    if headers.contains_key(TRANSFER_ENCODING) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "transfer encodings are not supported",
+        ));
+    }
+
+    headers
+        .get(CONTENT_LENGTH)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .ok_or_else(|| invalid_data("invalid content-length header"))
+        })
+        .transpose()
+}
+
+/// Reads until the end of the head of a message.
+///
+/// Returns all bytes read so far and the length of the head within them.
+async fn read_head<S>(stream: &mut S) -> io::Result<(Vec<u8>, usize)>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut buf = Vec::new();
+    let mut chunk = [0; 4096];
+
+    loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        // The end of the head might span the previous and the current chunk.
+        let search_start = buf.len().saturating_sub(3);
+        buf.extend_from_slice(&chunk[..n]);
+
+        if let Some(pos) = buf[search_start..]
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+        {
+            let head_len = search_start + pos + 4;
+            if head_len > MAX_HEAD_SIZE {
+                break;
+            }
+            return Ok((buf, head_len));
+        }
+
+        if buf.len() > MAX_HEAD_SIZE {
+            break;
+        }
+    }
+
+    Err(invalid_data("message head too large"))
+}
+
+/// Reads the body of a message of which `buf` has already been read.
+///
+/// Without a `content_length`, the body extends until the end of the stream.
+async fn read_body<S>(
+    stream: &mut S,
+    mut buf: Vec<u8>,
+    content_length: Option<usize>,
+    max_body_size: usize,
+) -> io::Result<Vec<u8>>
+where
+    S: AsyncRead + Unpin,
+{
+    let too_large = || invalid_data("message body too large");
+
+    match content_length {
+        Some(len) => {
+            if len > max_body_size {
+                return Err(too_large());
+            }
+            if buf.len() > len {
+                return Err(invalid_data("unexpected data after message body"));
+            }
+            let read = buf.len();
+            buf.resize(len, 0);
+            stream.read_exact(&mut buf[read..]).await?;
+        }
+        None => {
+            let limit = (max_body_size + 1).saturating_sub(buf.len());
+            stream.take(limit as u64).read_to_end(&mut buf).await?;
+            if buf.len() > max_body_size {
+                return Err(too_large());
+            }
+        }
+    }
+
+    Ok(buf)
+}
+
+fn invalid_data(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+
+    #[test]
+    fn request_roundtrip() {
+        futures::executor::block_on(async {
+            let request = Request::post("/echo?x=1")
+                .header("x-custom", "value")
+                .body(b"hello".to_vec())
+                .unwrap();
+
+            let mut buf = Vec::new();
+            write_request(&mut buf, request).await.unwrap();
+            let decoded = read_request(&mut Cursor::new(buf), 1024).await.unwrap();
+
+            assert_eq!(decoded.method(), http::Method::POST);
+            assert_eq!(decoded.uri(), "/echo?x=1");
+            assert_eq!(decoded.headers()["x-custom"], "value");
+            assert_eq!(decoded.headers()[CONTENT_LENGTH], "5");
+            assert_eq!(decoded.body(), b"hello");
+        });
+    }
+
+    #[test]
+    fn response_without_content_length_extends_to_end_of_stream() {
+        futures::executor::block_on(async {
+            let raw = b"HTTP/1.1 404 Not Found\r\nx-custom: value\r\n\r\nnot here".to_vec();
+            let decoded = read_response(&mut Cursor::new(raw), &Method::GET, 1024)
+                .await
+                .unwrap();
+
+            assert_eq!(decoded.status(), StatusCode::NOT_FOUND);
+            assert_eq!(decoded.headers()["x-custom"], "value");
+            assert_eq!(decoded.body(), b"not here");
+        });
+    }
+
+    #[test]
+    fn body_size_is_limited() {
+        futures::executor::block_on(async {
+            let response = Response::new(vec![0; 100]);
+
+            let mut buf = Vec::new();
+            write_response(&mut buf, &Method::GET, response)
+                .await
+                .unwrap();
+
+            let error = read_response(&mut Cursor::new(buf), &Method::GET, 99)
+                .await
+                .unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        });
+    }
+
+    #[test]
+    fn response_to_head_request_has_no_body() {
+        futures::executor::block_on(async {
+            let response = Response::new(b"hello".to_vec());
+
+            let mut buf = Vec::new();
+            write_response(&mut buf, &Method::HEAD, response)
+                .await
+                .unwrap();
+            let decoded = read_response(&mut Cursor::new(buf), &Method::HEAD, 1024)
+                .await
+                .unwrap();
+
+            assert_eq!(decoded.headers()[CONTENT_LENGTH], "5");
+            assert!(decoded.body().is_empty());
+        });
+    }
+
+    #[test]
+    fn chunked_encoding_is_rejected() {
+        futures::executor::block_on(async {
+            let raw = b"POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n0\r\n\r\n".to_vec();
+            let error = read_request(&mut Cursor::new(raw), 1024).await.unwrap_err();
+
+            assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+        });
+    }
+}
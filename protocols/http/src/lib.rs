@@ -0,0 +1,34 @@
+#![doc = include_str!("../README.md")]
+
+mod client;
+mod codec;
+mod server;
+
+use std::collections::BTreeMap;
+
+use libp2p_swarm::StreamProtocol;
+use serde::{Deserialize, Serialize};
+
+pub use client::{Client, Error};
+pub use server::{IncomingRequest, Responder, Server};
+
+/// The protocol of streams carrying HTTP/1.1, one request and response per stream.
+pub const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/http/1.1");
+
+/// The path of the document listing the protocols served by a peer, see [`WellKnownProtocols`].
+pub const WELL_KNOWN_PROTOCOLS_PATH: &str = "/.well-known/libp2p/protocols";
+
+/// The default maximum size of the body of a request or response.
+pub const DEFAULT_MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// The protocols served by a peer, mapping each protocol id to its metadata.
+///
+/// Served as JSON at [`WELL_KNOWN_PROTOCOLS_PATH`].
+pub type WellKnownProtocols = BTreeMap<String, ProtocolMeta>;
+
+/// Metadata of a protocol served over HTTP.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolMeta {
+    /// The path prefix under which the protocol is served.
+    pub path: String,
+}
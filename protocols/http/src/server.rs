@@ -0,0 +1,199 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{AsyncWriteExt as _, StreamExt as _};
+use futures_bounded::FuturesSet;
+use http::{
+    header::{ALLOW, CONTENT_TYPE},
+    HeaderValue, Method, Request, Response, StatusCode,
+};
+use libp2p_identity::PeerId;
+use libp2p_stream::{AlreadyRegistered, Control, IncomingStreams};
+use libp2p_swarm::Stream;
+
+use crate::{
+    codec, ProtocolMeta, WellKnownProtocols, DEFAULT_MAX_BODY_SIZE, PROTOCOL_NAME,
+    WELL_KNOWN_PROTOCOLS_PATH,
+};
+
+/// The time within which a peer has to send its request, or within which a response to
+/// [`WELL_KNOWN_PROTOCOLS_PATH`] has to be sent.
+const STREAM_TIMEOUT: Duration = Duration::from_secs(10);
+/// The maximum number of streams on which we concurrently read requests.
+const MAX_CONCURRENT_STREAMS: usize = 100;
+
+/// Receives HTTP requests from peers.
+///
+/// A [`Server`] is a [`Stream`](futures::Stream) of [`IncomingRequest`]s. Requests for
+/// [`WELL_KNOWN_PROTOCOLS_PATH`] are answered by the [`Server`] itself with the protocols
+/// registered via [`Server::with_protocol`].
+///
+/// Like [`IncomingStreams`], a [`Server`] is lazy and must be polled continuously to make
+/// progress. Dropping it stops accepting streams for [`PROTOCOL_NAME`].
+#[must_use = "Streams do nothing unless polled."]
+pub struct Server {
+    incoming: IncomingStreams,
+    protocols: WellKnownProtocols,
+    max_body_size: usize,
+
+    pending_requests: FuturesSet<(PeerId, io::Result<(Request<Vec<u8>>, Stream)>)>,
+    pending_responses: FuturesSet<io::Result<()>>,
+}
+
+impl Server {
+    /// Creates a new [`Server`] accepting streams for [`PROTOCOL_NAME`] via the given
+    /// [`Control`].
+    pub fn new(mut control: Control) -> Result<Self, AlreadyRegistered> {
+        Ok(Self {
+            incoming: control.accept(PROTOCOL_NAME)?,
+            protocols: WellKnownProtocols::new(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            pending_requests: FuturesSet::new(STREAM_TIMEOUT, MAX_CONCURRENT_STREAMS),
+            pending_responses: FuturesSet::new(STREAM_TIMEOUT, MAX_CONCURRENT_STREAMS),
+        })
+    }
+
+    /// Announces that the given protocol is served under the given path prefix.
+    pub fn with_protocol(mut self, protocol: impl Into<String>, path: impl Into<String>) -> Self {
+        self.protocols
+            .insert(protocol.into(), ProtocolMeta { path: path.into() });
+        self
+    }
+
+    /// Sets the maximum size of the body of a request.
+    ///
+    /// Defaults to [`DEFAULT_MAX_BODY_SIZE`].
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// The protocols announced at [`WELL_KNOWN_PROTOCOLS_PATH`].
+    pub fn protocols(&self) -> &WellKnownProtocols {
+        &self.protocols
+    }
+
+    fn respond_with_protocols(&mut self, peer: PeerId, request: Request<Vec<u8>>, stream: Stream) {
+        let method = request.method().clone();
+        let response = match method {
+            Method::GET | Method::HEAD => Response::builder()
+                .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+                .body(serde_json::to_vec(&self.protocols).expect("document to be serializable")),
+            _ => Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(ALLOW, HeaderValue::from_static("GET, HEAD"))
+                .body(Vec::new()),
+        }
+        .expect("response to be valid");
+
+        let responder = Responder { method, stream };
+        if self
+            .pending_responses
+            .try_push(responder.send(response))
+            .is_err()
+        {
+            tracing::warn!(%peer, "Dropping well-known protocols request because we are at capacity");
+        }
+    }
+}
+
+impl futures::Stream for Server {
+    type Item = IncomingRequest;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            while let Poll::Ready(result) = this.pending_responses.poll_unpin(cx) {
+                match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => {
+                        tracing::debug!("Failed to send well-known protocols: {e}");
+                    }
+                    Err(_) => {
+                        tracing::debug!("Timed out sending well-known protocols");
+                    }
+                }
+            }
+
+            match this.pending_requests.poll_unpin(cx) {
+                Poll::Ready(Ok((peer, Ok((request, stream))))) => {
+                    if request.uri().path() == WELL_KNOWN_PROTOCOLS_PATH {
+                        this.respond_with_protocols(peer, request, stream);
+                        continue;
+                    }
+
+                    let responder = Responder {
+                        method: request.method().clone(),
+                        stream,
+                    };
+                    return Poll::Ready(Some(IncomingRequest {
+                        peer,
+                        request,
+                        responder,
+                    }));
+                }
+                Poll::Ready(Ok((peer, Err(e)))) => {
+                    tracing::debug!(%peer, "Failed to read HTTP request: {e}");
+                    continue;
+                }
+                Poll::Ready(Err(_)) => {
+                    tracing::debug!("Timed out reading HTTP request");
+                    continue;
+                }
+                Poll::Pending => {}
+            }
+
+            match this.incoming.poll_next_unpin(cx) {
+                Poll::Ready(Some((peer, mut stream))) => {
+                    let max_body_size = this.max_body_size;
+                    let read_request = async move {
+                        match codec::read_request(&mut stream, max_body_size).await {
+                            Ok(request) => (peer, Ok((request, stream))),
+                            Err(e) => (peer, Err(e)),
+                        }
+                    };
+                    if this.pending_requests.try_push(read_request).is_err() {
+                        tracing::warn!(%peer, "Dropping inbound HTTP stream because we are at capacity");
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// An HTTP request received from a peer.
+#[derive(Debug)]
+pub struct IncomingRequest {
+    /// The peer that sent the request.
+    pub peer: PeerId,
+    /// The request.
+    pub request: Request<Vec<u8>>,
+    /// Sends the response to the request.
+    pub responder: Responder,
+}
+
+/// Sends the response to an [`IncomingRequest`].
+///
+/// Dropping a [`Responder`] closes the stream without a response.
+#[derive(Debug)]
+pub struct Responder {
+    method: Method,
+    stream: Stream,
+}
+
+impl Responder {
+    /// Sends the response and closes the stream.
+    ///
+    /// The `Content-Length` header is set to the length of the body.
+    pub async fn send(mut self, response: Response<Vec<u8>>) -> io::Result<()> {
+        codec::write_response(&mut self.stream, &self.method, response).await?;
+        self.stream.close().await
+    }
+}
@@ -0,0 +1,87 @@
+use futures::StreamExt as _;
+use http::{Method, Request, Response, StatusCode};
+use libp2p_http::{Client, ProtocolMeta, Server};
+use libp2p_identity::PeerId;
+use libp2p_stream as stream;
+use libp2p_swarm::Swarm;
+use libp2p_swarm_test::SwarmExt as _;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::EnvFilter;
+
+#[tokio::test]
+async fn request_response_roundtrip() {
+    let (mut client, server_peer_id, server) = setup().await;
+
+    tokio::spawn(async move {
+        let mut server = server;
+        while let Some(incoming) = server.next().await {
+            let response = Response::builder()
+                .header("x-path", incoming.request.uri().path())
+                .body(incoming.request.into_body())
+                .unwrap();
+            incoming.responder.send(response).await.unwrap();
+        }
+    });
+
+    let request = Request::post("/echo").body(b"hello".to_vec()).unwrap();
+    let response = client.send(server_peer_id, request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-path"], "/echo");
+    assert_eq!(response.body(), b"hello");
+}
+
+#[tokio::test]
+async fn well_known_protocols_are_served() {
+    let (mut client, server_peer_id, server) = setup().await;
+    let server = server.with_protocol("/echo/1.0.0", "/echo/");
+
+    tokio::spawn(server.for_each(|_| async {}));
+
+    let protocols = client.protocols(server_peer_id).await.unwrap();
+    assert_eq!(
+        protocols.into_iter().collect::<Vec<_>>(),
+        vec![(
+            "/echo/1.0.0".to_owned(),
+            ProtocolMeta {
+                path: "/echo/".to_owned()
+            }
+        )]
+    );
+
+    let request = Request::builder()
+        .method(Method::DELETE)
+        .uri(libp2p_http::WELL_KNOWN_PROTOCOLS_PATH)
+        .body(Vec::new())
+        .unwrap();
+    let response = client.send(server_peer_id, request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+}
+
+async fn setup() -> (Client, PeerId, Server) {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::DEBUG.into())
+                .from_env()
+                .unwrap(),
+        )
+        .with_test_writer()
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| stream::Behaviour::new());
+    let mut swarm2 = Swarm::new_ephemeral(|_| stream::Behaviour::new());
+
+    let client = Client::new(swarm1.behaviour().new_control());
+    let server = Server::new(swarm2.behaviour().new_control()).unwrap();
+
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+
+    let server_peer_id = *swarm2.local_peer_id();
+
+    tokio::spawn(swarm1.loop_on_next());
+    tokio::spawn(swarm2.loop_on_next());
+
+    (client, server_peer_id, server)
+}
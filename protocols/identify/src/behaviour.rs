@@ -19,21 +19,27 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::handler::{self, Handler, InEvent};
+use crate::policy::{PolicyAction, VersionPolicy};
 use crate::protocol::{Info, UpgradeError};
-use libp2p_core::{multiaddr, ConnectedPoint, Endpoint, Multiaddr};
+use libp2p_core::{multiaddr, ConnectedPoint, Endpoint, Multiaddr, PeerRecord, SignedEnvelope};
 use libp2p_identity::PeerId;
-use libp2p_identity::PublicKey;
+use libp2p_identity::{Keypair, PublicKey};
 use libp2p_swarm::behaviour::{ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm};
 use libp2p_swarm::{
-    ConnectionDenied, DialError, ExternalAddresses, ListenAddresses, NetworkBehaviour,
-    NotifyHandler, PeerAddresses, StreamUpgradeError, THandlerInEvent, ToSwarm,
+    CloseConnection, ConnectionDenied, DialError, ExternalAddresses, ListenAddresses,
+    NetworkBehaviour, NotifyHandler, PeerAddresses, StreamUpgradeError, THandlerInEvent, ToSwarm,
 };
 use libp2p_swarm::{ConnectionId, THandler, THandlerOutEvent};
 
+use futures::FutureExt;
+use futures_timer::Delay;
+use rand::Rng;
 use std::collections::hash_map::Entry;
 use std::num::NonZeroUsize;
+use std::sync::Arc;
 use std::{
     collections::{HashMap, HashSet, VecDeque},
+    fmt,
     task::Context,
     task::Poll,
     time::Duration,
@@ -59,6 +65,14 @@ pub struct Behaviour {
 
     listen_addresses: ListenAddresses,
     external_addresses: ExternalAddresses,
+
+    /// The signed peer record of our addresses, if enabled.
+    local_signed_peer_record: Option<SignedEnvelope>,
+
+    /// Whether our listen addresses changed since the last push.
+    push_pending: bool,
+    /// Future that fires once [`Config::push_min_interval`] passed since the last push.
+    push_cooldown: Option<Delay>,
 }
 
 /// Configuration for the [`identify::Behaviour`](Behaviour).
@@ -92,11 +106,64 @@ pub struct Config {
     /// Disabled by default.
     pub push_listen_addr_updates: bool,
 
+    /// The minimum interval between two pushes triggered by changed listen addresses.
+    ///
+    /// Changes within the interval are coalesced into a single push at its end, preventing
+    /// flapping addresses from causing a push to all peers on every change.
+    ///
+    /// Defaults to 0, i.e. pushes are sent right away.
+    pub push_min_interval: Duration,
+
+    /// The maximum random delay of the push to each individual peer, triggered by changed listen
+    /// addresses.
+    ///
+    /// Spreads out the pushes to many peers instead of sending all of them at once.
+    ///
+    /// Defaults to 0, i.e. no jitter.
+    pub push_jitter: Duration,
+
+    /// Whether pushes only contain the fields that changed since the last identify information
+    /// sent to the respective peer.
+    ///
+    /// Remotes running `libp2p-identify` older than 0.44 do not support such partial pushes.
+    ///
+    /// Disabled by default.
+    pub delta_push: bool,
+
     /// How many entries of discovered peers to keep before we discard
     /// the least-recently used one.
     ///
     /// Disabled by default.
     pub cache_size: usize,
+
+    /// Whether listen addresses of remotes are only accepted if they are part of a valid signed
+    /// peer record.
+    ///
+    /// If enabled, the listen addresses of [`Info`] in [`Event::Received`] are empty and no
+    /// [`ToSwarm::NewExternalAddrOfPeer`] is emitted for remotes that don't send a signed peer
+    /// record, preventing remotes from spoofing addresses of other peers.
+    ///
+    /// Disabled by default.
+    pub signed_addresses_only: bool,
+
+    /// The keypair of the local node, used to sign the peer record sent to remotes.
+    local_keypair: Option<Keypair>,
+
+    /// Filters or rewrites the listen addresses advertised to each remote.
+    address_filter: Option<AddressFilter>,
+
+    /// Requirements on the protocol and agent versions of remotes.
+    version_policy: Option<VersionPolicy>,
+}
+
+/// A filter of the listen addresses advertised to a remote, see [`Config::with_address_filter`].
+#[derive(Clone)]
+struct AddressFilter(Arc<dyn Fn(&Multiaddr, &Multiaddr) -> Option<Multiaddr> + Send + Sync>);
+
+impl fmt::Debug for AddressFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AddressFilter").finish_non_exhaustive()
+    }
 }
 
 impl Config {
@@ -109,7 +176,26 @@ impl Config {
             local_public_key,
             interval: Duration::from_secs(5 * 60),
             push_listen_addr_updates: false,
+            push_min_interval: Duration::ZERO,
+            push_jitter: Duration::ZERO,
+            delta_push: false,
             cache_size: 100,
+            signed_addresses_only: false,
+            local_keypair: None,
+            address_filter: None,
+            version_policy: None,
+        }
+    }
+
+    /// Creates a new configuration for the identify [`Behaviour`] that
+    /// advertises the given protocol version and the public key of the given keypair.
+    ///
+    /// In addition to the plain listen addresses, a [`PeerRecord`] of the listen addresses,
+    /// signed with the keypair, is sent to remotes.
+    pub fn new_with_signed_peer_record(protocol_version: String, local_keypair: &Keypair) -> Self {
+        Self {
+            local_keypair: Some(local_keypair.clone()),
+            ..Self::new(protocol_version, local_keypair.public())
         }
     }
 
@@ -134,11 +220,65 @@ impl Config {
         self
     }
 
+    /// Configures the minimum interval between two pushes triggered by changed
+    /// listen addresses.
+    pub fn with_push_min_interval(mut self, d: Duration) -> Self {
+        self.push_min_interval = d;
+        self
+    }
+
+    /// Configures the maximum random delay of the push to each individual peer,
+    /// triggered by changed listen addresses.
+    pub fn with_push_jitter(mut self, d: Duration) -> Self {
+        self.push_jitter = d;
+        self
+    }
+
+    /// Configures whether pushes only contain the fields that changed since the
+    /// last identify information sent to the respective peer.
+    pub fn with_delta_push(mut self, b: bool) -> Self {
+        self.delta_push = b;
+        self
+    }
+
     /// Configures the size of the LRU cache, caching addresses of discovered peers.
     pub fn with_cache_size(mut self, cache_size: usize) -> Self {
         self.cache_size = cache_size;
         self
     }
+
+    /// Configures whether listen addresses of remotes are only accepted if they
+    /// are part of a valid signed peer record.
+    pub fn with_signed_addresses_only(mut self, b: bool) -> Self {
+        self.signed_addresses_only = b;
+        self
+    }
+
+    /// Configures a filter of the listen addresses advertised to remotes.
+    ///
+    /// The filter is called with the address of the connection to the remote and each listen
+    /// address of the local node. It returns the address to advertise instead, or [`None`] to
+    /// omit the address, e.g. to not advertise private addresses to remotes connected via a
+    /// public address, or to only advertise addresses of the address family of the connection.
+    ///
+    /// If a signed peer record is sent, it only contains the advertised addresses.
+    pub fn with_address_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Multiaddr, &Multiaddr) -> Option<Multiaddr> + Send + Sync + 'static,
+    {
+        self.address_filter = Some(AddressFilter(Arc::new(filter)));
+        self
+    }
+
+    /// Configures requirements on the protocol and agent versions of remotes.
+    ///
+    /// Remotes violating the policy are reported via [`Event::VersionPolicyViolated`] and,
+    /// depending on the [`PolicyAction`], their connection is closed, e.g. to fence off
+    /// incompatible forks of a network right after the handshake.
+    pub fn with_version_policy(mut self, policy: VersionPolicy) -> Self {
+        self.version_policy = Some(policy);
+        self
+    }
 }
 
 impl Behaviour {
@@ -149,7 +289,7 @@ impl Behaviour {
             Some(size) => PeerCache::enabled(size),
         };
 
-        Self {
+        let mut behaviour = Self {
             config,
             connected: HashMap::new(),
             our_observed_addresses: Default::default(),
@@ -157,7 +297,12 @@ impl Behaviour {
             discovered_peers,
             listen_addresses: Default::default(),
             external_addresses: Default::default(),
-        }
+            local_signed_peer_record: None,
+            push_pending: false,
+            push_cooldown: None,
+        };
+        behaviour.local_signed_peer_record = behaviour.sign_peer_record(&HashSet::new());
+        behaviour
     }
 
     /// Initiates an active push of the local peer information to the given peers.
@@ -213,6 +358,101 @@ impl Behaviour {
             .cloned()
             .collect()
     }
+
+    /// The listen addresses to advertise to a remote connected via `remote_addr` and their signed
+    /// peer record, if enabled.
+    fn advertised_addresses(
+        &self,
+        remote_addr: &Multiaddr,
+    ) -> (HashSet<Multiaddr>, Option<SignedEnvelope>) {
+        let addresses = self.all_addresses();
+        let Some(AddressFilter(filter)) = &self.config.address_filter else {
+            return (addresses, self.local_signed_peer_record.clone());
+        };
+
+        let addresses = addresses
+            .iter()
+            .filter_map(|address| filter(remote_addr, address))
+            .collect();
+        let signed_peer_record = self.sign_peer_record(&addresses);
+
+        (addresses, signed_peer_record)
+    }
+
+    /// Pushes our changed listen addresses to all connected peers, unless the previous push was
+    /// less than [`Config::push_min_interval`] ago.
+    fn poll_push(&mut self, cx: &mut Context<'_>) {
+        if !self.push_pending {
+            return;
+        }
+        if let Some(Poll::Pending) = self.push_cooldown.as_mut().map(|d| d.poll_unpin(cx)) {
+            return;
+        }
+
+        self.push_pending = false;
+        self.push_cooldown = (!self.config.push_min_interval.is_zero())
+            .then(|| Delay::new(self.config.push_min_interval));
+
+        let jitter = self.config.push_jitter;
+        let push_events = self.connected.keys().map(|peer| ToSwarm::NotifyHandler {
+            peer_id: *peer,
+            handler: NotifyHandler::Any,
+            event: if jitter.is_zero() {
+                InEvent::Push
+            } else {
+                InEvent::DelayedPush(rand::thread_rng().gen_range(Duration::ZERO..=jitter))
+            },
+        });
+
+        self.events.extend(push_events);
+    }
+
+    /// Signs a [`PeerRecord`] of the given addresses, if a keypair is configured.
+    fn sign_peer_record(&self, addresses: &HashSet<Multiaddr>) -> Option<SignedEnvelope> {
+        let keypair = self.config.local_keypair.as_ref()?;
+
+        match PeerRecord::new(keypair, Vec::from_iter(addresses.iter().cloned())) {
+            Ok(record) => Some(record.into_signed_envelope()),
+            Err(e) => {
+                tracing::warn!("Failed to sign peer record: {e}");
+                None
+            }
+        }
+    }
+
+    /// Replaces the listen addresses of `info` with the ones of its signed peer record, if the
+    /// record is valid and belongs to `peer_id`.
+    ///
+    /// Otherwise the record is discarded and, if only signed addresses are accepted, so are the
+    /// listen addresses.
+    fn apply_signed_peer_record(&self, peer_id: PeerId, info: &mut Info) {
+        let record = info.signed_peer_record.take().and_then(|envelope| {
+            match PeerRecord::from_signed_envelope(envelope) {
+                Ok(record) if record.peer_id() == peer_id => Some(record),
+                Ok(record) => {
+                    tracing::debug!(
+                        peer=%peer_id,
+                        record_peer=%record.peer_id(),
+                        "Discarding signed peer record of a different peer"
+                    );
+                    None
+                }
+                Err(e) => {
+                    tracing::debug!(peer=%peer_id, "Discarding invalid signed peer record: {e}");
+                    None
+                }
+            }
+        });
+
+        match record {
+            Some(record) => {
+                info.listen_addrs = record.addresses().to_vec();
+                info.signed_peer_record = Some(record.into_signed_envelope());
+            }
+            None if self.config.signed_addresses_only => info.listen_addrs.clear(),
+            None => {}
+        }
+    }
 }
 
 impl NetworkBehaviour for Behaviour {
@@ -226,6 +466,8 @@ impl NetworkBehaviour for Behaviour {
         _: &Multiaddr,
         remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
+        let (addresses, signed_peer_record) = self.advertised_addresses(remote_addr);
+
         Ok(Handler::new(
             self.config.interval,
             peer,
@@ -233,7 +475,9 @@ impl NetworkBehaviour for Behaviour {
             self.config.protocol_version.clone(),
             self.config.agent_version.clone(),
             remote_addr.clone(),
-            self.all_addresses(),
+            addresses,
+            signed_peer_record,
+            self.config.delta_push,
         ))
     }
 
@@ -244,6 +488,8 @@ impl NetworkBehaviour for Behaviour {
         addr: &Multiaddr,
         _: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
+        let (addresses, signed_peer_record) = self.advertised_addresses(addr);
+
         Ok(Handler::new(
             self.config.interval,
             peer,
@@ -251,7 +497,9 @@ impl NetworkBehaviour for Behaviour {
             self.config.protocol_version.clone(),
             self.config.agent_version.clone(),
             addr.clone(), // TODO: This is weird? That is the public address we dialed, shouldn't need to tell the other party?
-            self.all_addresses(),
+            addresses,
+            signed_peer_record,
+            self.config.delta_push,
         ))
     }
 
@@ -263,10 +511,42 @@ impl NetworkBehaviour for Behaviour {
     ) {
         match event {
             handler::Event::Identified(mut info) => {
+                self.apply_signed_peer_record(peer_id, &mut info);
+
                 // Remove invalid multiaddrs.
                 info.listen_addrs
                     .retain(|addr| multiaddr_matches_peer_id(addr, &peer_id));
 
+                if let Some(policy) = self
+                    .config
+                    .version_policy
+                    .as_ref()
+                    .filter(|policy| !policy.complies(&info))
+                {
+                    let action = policy.action();
+                    tracing::debug!(
+                        peer=%peer_id,
+                        protocol_version=%info.protocol_version,
+                        agent_version=%info.agent_version,
+                        ?action,
+                        "Remote violates version policy"
+                    );
+                    self.events
+                        .push_back(ToSwarm::GenerateEvent(Event::VersionPolicyViolated {
+                            peer_id,
+                            connection_id: id,
+                            info: info.clone(),
+                            action,
+                        }));
+                    if action == PolicyAction::Close {
+                        self.events.push_back(ToSwarm::CloseConnection {
+                            peer_id,
+                            connection: CloseConnection::One(id),
+                        });
+                        return;
+                    }
+                }
+
                 let observed = info.observed_addr.clone();
                 self.events
                     .push_back(ToSwarm::GenerateEvent(Event::Received {
@@ -322,8 +602,13 @@ impl NetworkBehaviour for Behaviour {
         }
     }
 
-    #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
-    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+    #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self, cx))]
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        self.poll_push(cx);
+
         if let Some(event) = self.events.pop_front() {
             return Poll::Ready(event);
         }
@@ -351,15 +636,23 @@ impl NetworkBehaviour for Behaviour {
         let external_addr_changed = self.external_addresses.on_swarm_event(&event);
 
         if listen_addr_changed || external_addr_changed {
+            self.local_signed_peer_record = self.sign_peer_record(&self.all_addresses());
+
             // notify all connected handlers about our changed addresses
             let change_events = self
                 .connected
                 .iter()
-                .flat_map(|(peer, map)| map.keys().map(|id| (*peer, id)))
-                .map(|(peer_id, connection_id)| ToSwarm::NotifyHandler {
-                    peer_id,
-                    handler: NotifyHandler::One(*connection_id),
-                    event: InEvent::AddressesChanged(self.all_addresses()),
+                .flat_map(|(peer, map)| map.iter().map(|(id, addr)| (*peer, id, addr)))
+                .map(|(peer_id, connection_id, remote_addr)| {
+                    let (addresses, signed_peer_record) = self.advertised_addresses(remote_addr);
+                    ToSwarm::NotifyHandler {
+                        peer_id,
+                        handler: NotifyHandler::One(*connection_id),
+                        event: InEvent::AddressesChanged {
+                            addresses,
+                            signed_peer_record,
+                        },
+                    }
                 })
                 .collect::<Vec<_>>();
 
@@ -367,14 +660,8 @@ impl NetworkBehaviour for Behaviour {
         }
 
         if listen_addr_changed && self.config.push_listen_addr_updates {
-            // trigger an identify push for all connected peers
-            let push_events = self.connected.keys().map(|peer| ToSwarm::NotifyHandler {
-                peer_id: *peer,
-                handler: NotifyHandler::Any,
-                event: InEvent::Push,
-            });
-
-            self.events.extend(push_events);
+            // trigger an identify push for all connected peers on the next poll
+            self.push_pending = true;
         }
 
         match event {
@@ -435,6 +722,21 @@ pub enum Event {
         /// do some diff'ing to know what has changed since the last push.
         info: Info,
     },
+    /// The identification information of a peer violates the [`VersionPolicy`] configured via
+    /// [`Config::with_version_policy`].
+    ///
+    /// With [`PolicyAction::Close`], the connection is closed and the information is not
+    /// reported via [`Event::Received`].
+    VersionPolicyViolated {
+        /// The peer violating the policy.
+        peer_id: PeerId,
+        /// The connection the information was received on.
+        connection_id: ConnectionId,
+        /// The information provided by the peer.
+        info: Info,
+        /// The action taken.
+        action: PolicyAction,
+    },
     /// Error while attempting to identify the remote.
     Error {
         /// The peer with whom the error originated.
@@ -25,7 +25,7 @@ use futures::prelude::*;
 use futures_bounded::Timeout;
 use futures_timer::Delay;
 use libp2p_core::upgrade::{ReadyUpgrade, SelectUpgrade};
-use libp2p_core::Multiaddr;
+use libp2p_core::{Multiaddr, SignedEnvelope};
 use libp2p_identity::PeerId;
 use libp2p_identity::PublicKey;
 use libp2p_swarm::handler::{
@@ -91,13 +91,28 @@ pub struct Handler {
     local_supported_protocols: SupportedProtocols,
     remote_supported_protocols: HashSet<StreamProtocol>,
     external_addresses: HashSet<Multiaddr>,
+
+    /// The signed peer record of `external_addresses`, if enabled.
+    signed_peer_record: Option<SignedEnvelope>,
+
+    /// Whether pushes only contain the fields that changed since `last_sent_info`.
+    delta_push: bool,
+    /// The last identify information successfully sent to the remote.
+    last_sent_info: Option<Info>,
+    /// Future that fires when a delayed push is due.
+    pending_push: Option<Delay>,
 }
 
 /// An event from `Behaviour` with the information requested by the `Handler`.
 #[derive(Debug)]
 pub enum InEvent {
-    AddressesChanged(HashSet<Multiaddr>),
+    AddressesChanged {
+        addresses: HashSet<Multiaddr>,
+        signed_peer_record: Option<SignedEnvelope>,
+    },
     Push,
+    /// Push after the given delay, unless a delayed push is already pending.
+    DelayedPush(Duration),
 }
 
 /// Event produced by the `Handler`.
@@ -116,6 +131,7 @@ pub enum Event {
 
 impl Handler {
     /// Creates a new `Handler`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         interval: Duration,
         remote_peer_id: PeerId,
@@ -124,6 +140,8 @@ impl Handler {
         agent_version: String,
         observed_addr: Multiaddr,
         external_addresses: HashSet<Multiaddr>,
+        signed_peer_record: Option<SignedEnvelope>,
+        delta_push: bool,
     ) -> Self {
         Self {
             remote_peer_id,
@@ -143,6 +161,10 @@ impl Handler {
             remote_supported_protocols: HashSet::default(),
             remote_info: Default::default(),
             external_addresses,
+            signed_peer_record,
+            delta_push,
+            last_sent_info: None,
+            pending_push: None,
         }
     }
 
@@ -161,9 +183,7 @@ impl Handler {
 
                 if self
                     .active_streams
-                    .try_push(
-                        protocol::send_identify(stream, info).map_ok(|_| Success::SentIdentify),
-                    )
+                    .try_push(protocol::send_identify(stream, info).map_ok(Success::SentIdentify))
                     .is_err()
                 {
                     tracing::warn!("Dropping inbound stream because we are at capacity");
@@ -206,11 +226,16 @@ impl Handler {
             }
             future::Either::Right(stream) => {
                 let info = self.build_info();
+                let previous = self
+                    .delta_push
+                    .then(|| self.last_sent_info.clone())
+                    .flatten();
 
                 if self
                     .active_streams
                     .try_push(
-                        protocol::send_identify(stream, info).map_ok(Success::SentIdentifyPush),
+                        protocol::send_push(stream, info, previous)
+                            .map_ok(Success::SentIdentifyPush),
                     )
                     .is_err()
                 {
@@ -230,6 +255,7 @@ impl Handler {
             listen_addrs: Vec::from_iter(self.external_addresses.iter().cloned()),
             protocols: Vec::from_iter(self.local_supported_protocols.iter().cloned()),
             observed_addr: self.observed_addr.clone(),
+            signed_peer_record: self.signed_peer_record.clone(),
         }
     }
 
@@ -299,8 +325,12 @@ impl ConnectionHandler for Handler {
 
     fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
         match event {
-            InEvent::AddressesChanged(addresses) => {
+            InEvent::AddressesChanged {
+                addresses,
+                signed_peer_record,
+            } => {
                 self.external_addresses = addresses;
+                self.signed_peer_record = signed_peer_record;
             }
             InEvent::Push => {
                 self.events
@@ -311,6 +341,9 @@ impl ConnectionHandler for Handler {
                         ),
                     });
             }
+            InEvent::DelayedPush(delay) => {
+                self.pending_push.get_or_insert_with(|| Delay::new(delay));
+            }
         }
     }
 
@@ -335,6 +368,16 @@ impl ConnectionHandler for Handler {
             return Poll::Ready(event);
         }
 
+        if let Some(Poll::Ready(())) = self.pending_push.as_mut().map(|d| d.poll_unpin(cx)) {
+            self.pending_push = None;
+            return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
+                protocol: SubstreamProtocol::new(
+                    Either::Right(ReadyUpgrade::new(PUSH_PROTOCOL_NAME)),
+                    (),
+                ),
+            });
+        }
+
         match self.active_streams.poll_unpin(cx) {
             Poll::Ready(Ok(Ok(Success::ReceivedIdentify(remote_info)))) => {
                 self.handle_incoming_info(&remote_info);
@@ -344,11 +387,15 @@ impl ConnectionHandler for Handler {
                 )));
             }
             Poll::Ready(Ok(Ok(Success::SentIdentifyPush(info)))) => {
+                self.last_sent_info = Some(info.clone());
+
                 return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
                     Event::IdentificationPushed(info),
                 ));
             }
-            Poll::Ready(Ok(Ok(Success::SentIdentify))) => {
+            Poll::Ready(Ok(Ok(Success::SentIdentify(info)))) => {
+                self.last_sent_info = Some(info);
+
                 return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
                     Event::Identification,
                 ));
@@ -435,7 +482,7 @@ impl ConnectionHandler for Handler {
 }
 
 enum Success {
-    SentIdentify,
+    SentIdentify(Info),
     ReceivedIdentify(Info),
     SentIdentifyPush(Info),
     ReceivedIdentifyPush(PushInfo),
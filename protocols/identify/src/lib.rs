@@ -38,14 +38,30 @@
 //! The [`Behaviour`] struct implements a [`NetworkBehaviour`](libp2p_swarm::NetworkBehaviour)
 //! that negotiates and executes the protocol on every established connection, emitting
 //! [`Event`]s.
+//!
+//! # Signed peer records
+//!
+//! Plain listen addresses can be forged by the sender, e.g. to make other protocols dial a
+//! victim. A [`Config::new_with_signed_peer_record`] sends the listen addresses additionally as a
+//! [`PeerRecord`](libp2p_core::PeerRecord) signed by the local key. Received records are verified
+//! and exposed via [`Info::signed_peer_record`]. Protocols that share addresses with other peers,
+//! e.g. Kademlia, can restrict themselves to authenticated addresses via
+//! [`Config::with_signed_addresses_only`].
+//!
+//! # Version policy
+//!
+//! Networks can fence off remotes running incompatible protocol or agent versions, e.g. forks of
+//! the network, via a [`VersionPolicy`] configured with [`Config::with_version_policy`].
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 pub use self::behaviour::{Behaviour, Config, Event};
+pub use self::policy::{PolicyAction, VersionPolicy};
 pub use self::protocol::{Info, UpgradeError, PROTOCOL_NAME, PUSH_PROTOCOL_NAME};
 
 mod behaviour;
 mod handler;
+mod policy;
 mod protocol;
 
 mod proto {
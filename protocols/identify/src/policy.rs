@@ -0,0 +1,201 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::protocol::Info;
+use std::fmt;
+use std::sync::Arc;
+
+/// Requirements on the protocol and agent versions of remotes, see
+/// [`Config::with_version_policy`](crate::Config::with_version_policy).
+///
+/// Versions are matched against glob patterns, in which `*` matches any sequence of characters
+/// and `?` matches a single character. A remote complies with the policy if its protocol version
+/// matches any of the protocol version patterns, its agent version matches any of the agent
+/// version patterns and it satisfies all predicates. A policy without patterns for a version
+/// accepts any version.
+///
+/// ```
+/// # use libp2p_identify::{PolicyAction, VersionPolicy};
+/// let policy = VersionPolicy::default()
+///     .with_protocol_version("/my-network/1.*")
+///     .with_agent_version("my-node/*")
+///     .with_action(PolicyAction::Close);
+/// ```
+#[derive(Clone, Default)]
+pub struct VersionPolicy {
+    protocol_versions: Vec<String>,
+    agent_versions: Vec<String>,
+    predicates: Vec<Predicate>,
+    action: PolicyAction,
+}
+
+type Predicate = Arc<dyn Fn(&Info) -> bool + Send + Sync>;
+
+/// What to do with connections to remotes violating the [`VersionPolicy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PolicyAction {
+    /// Close the connection and otherwise ignore the received [`Info`].
+    #[default]
+    Close,
+    /// Only report the violation, e.g. to score the remote, and process the [`Info`] as usual.
+    Report,
+}
+
+impl VersionPolicy {
+    /// Accepts protocol versions matching the glob `pattern`, in addition to previously added
+    /// patterns.
+    pub fn with_protocol_version(mut self, pattern: impl Into<String>) -> Self {
+        self.protocol_versions.push(pattern.into());
+        self
+    }
+
+    /// Accepts agent versions matching the glob `pattern`, in addition to previously added
+    /// patterns.
+    pub fn with_agent_version(mut self, pattern: impl Into<String>) -> Self {
+        self.agent_versions.push(pattern.into());
+        self
+    }
+
+    /// Requires the [`Info`] of remotes to satisfy `predicate`.
+    pub fn with_predicate<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Info) -> bool + Send + Sync + 'static,
+    {
+        self.predicates.push(Arc::new(predicate));
+        self
+    }
+
+    /// Sets what to do with connections to remotes violating the policy.
+    ///
+    /// Defaults to [`PolicyAction::Close`].
+    pub fn with_action(mut self, action: PolicyAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// What to do with connections to remotes violating the policy.
+    pub fn action(&self) -> PolicyAction {
+        self.action
+    }
+
+    /// Whether the [`Info`] of a remote complies with the policy.
+    pub fn complies(&self, info: &Info) -> bool {
+        matches_any(&self.protocol_versions, &info.protocol_version)
+            && matches_any(&self.agent_versions, &info.agent_version)
+            && self.predicates.iter().all(|predicate| predicate(info))
+    }
+}
+
+impl fmt::Debug for VersionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VersionPolicy")
+            .field("protocol_versions", &self.protocol_versions)
+            .field("agent_versions", &self.agent_versions)
+            .field("predicates", &self.predicates.len())
+            .field("action", &self.action)
+            .finish()
+    }
+}
+
+fn matches_any(patterns: &[String], version: &str) -> bool {
+    patterns.is_empty() || patterns.iter().any(|pattern| glob_match(pattern, version))
+}
+
+/// Matches `text` against the glob `pattern`, supporting `*` and `?`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    // The position of the last `*` in the pattern and of the text it was tried to match at.
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                t += 1;
+            }
+            Some(c) if *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` match one more character.
+                Some((star, star_t)) => {
+                    backtrack = Some((star, star_t + 1));
+                    p = star + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_identity::Keypair;
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("/ipfs/0.1.0", "/ipfs/0.1.0"));
+        assert!(glob_match("/ipfs/*", "/ipfs/0.1.0"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(glob_match("v1.?", "v1.2"));
+        assert!(!glob_match("v1.?", "v1.23"));
+        assert!(!glob_match("/ipfs/*", "/ipns/0.1.0"));
+        assert!(!glob_match("a*b", "aXbY"));
+    }
+
+    #[test]
+    fn policy_requires_all_conditions() {
+        let info = Info {
+            public_key: Keypair::generate_ed25519().public(),
+            protocol_version: "/fork-a/1.2.0".to_owned(),
+            agent_version: "node/0.5.0".to_owned(),
+            listen_addrs: Vec::new(),
+            protocols: Vec::new(),
+            observed_addr: "/memory/1".parse().unwrap(),
+            signed_peer_record: None,
+        };
+
+        assert!(VersionPolicy::default().complies(&info));
+        assert!(VersionPolicy::default()
+            .with_protocol_version("/fork-b/*")
+            .with_protocol_version("/fork-a/1.*")
+            .with_agent_version("node/*")
+            .complies(&info));
+        assert!(!VersionPolicy::default()
+            .with_protocol_version("/fork-b/*")
+            .complies(&info));
+        assert!(!VersionPolicy::default()
+            .with_agent_version("node/*")
+            .with_predicate(|info| info.agent_version != "node/0.5.0")
+            .complies(&info));
+    }
+}
@@ -21,11 +21,11 @@
 use crate::proto;
 use asynchronous_codec::{FramedRead, FramedWrite};
 use futures::prelude::*;
-use libp2p_core::{multiaddr, Multiaddr};
+use libp2p_core::{multiaddr, Multiaddr, SignedEnvelope};
 use libp2p_identity as identity;
 use libp2p_identity::PublicKey;
 use libp2p_swarm::StreamProtocol;
-use std::io;
+use std::{collections::HashSet, io};
 use thiserror::Error;
 
 const MAX_MESSAGE_SIZE_BYTES: usize = 4096;
@@ -51,6 +51,12 @@ pub struct Info {
     pub protocols: Vec<StreamProtocol>,
     /// Address observed by or for the remote.
     pub observed_addr: Multiaddr,
+    /// A signed envelope containing a [`PeerRecord`](libp2p_core::PeerRecord) of the peer's
+    /// listen addresses.
+    ///
+    /// When receiving, this is only set if the record is valid and belongs to the remote peer, in
+    /// which case [`Info::listen_addrs`] are the addresses of the record.
+    pub signed_peer_record: Option<SignedEnvelope>,
 }
 
 impl Info {
@@ -64,8 +70,9 @@ impl Info {
         if let Some(agent_version) = info.agent_version {
             self.agent_version = agent_version;
         }
-        if !info.listen_addrs.is_empty() {
+        if !info.listen_addrs.is_empty() || info.signed_peer_record.is_some() {
             self.listen_addrs = info.listen_addrs;
+            self.signed_peer_record = info.signed_peer_record;
         }
         if !info.protocols.is_empty() {
             self.protocols = info.protocols;
@@ -86,6 +93,7 @@ pub struct PushInfo {
     pub listen_addrs: Vec<Multiaddr>,
     pub protocols: Vec<StreamProtocol>,
     pub observed_addr: Option<Multiaddr>,
+    pub signed_peer_record: Option<SignedEnvelope>,
 }
 
 pub(crate) async fn send_identify<T>(io: T, info: Info) -> Result<Info, UpgradeError>
@@ -94,19 +102,78 @@ where
 {
     tracing::trace!("Sending: {:?}", info);
 
-    let listen_addrs = info.listen_addrs.iter().map(|addr| addr.to_vec()).collect();
+    send(io, to_message(&info)).await?;
 
-    let pubkey_bytes = info.public_key.encode_protobuf();
+    Ok(info)
+}
+
+/// Pushes `info` to the remote.
+///
+/// If the `previous` info sent to the remote is given, only the fields that changed since are
+/// sent.
+pub(crate) async fn send_push<T>(
+    io: T,
+    info: Info,
+    previous: Option<Info>,
+) -> Result<Info, UpgradeError>
+where
+    T: AsyncWrite + Unpin,
+{
+    let Some(previous) = previous else {
+        return send_identify(io, info).await;
+    };
+
+    tracing::trace!("Sending changes since {:?}: {:?}", previous, info);
+
+    let mut message = to_message(&info);
+    if previous.public_key == info.public_key {
+        message.publicKey = None;
+    }
+    if previous.protocol_version == info.protocol_version {
+        message.protocolVersion = None;
+    }
+    if previous.agent_version == info.agent_version {
+        message.agentVersion = None;
+    }
+    if HashSet::<&Multiaddr>::from_iter(&previous.listen_addrs)
+        == HashSet::from_iter(&info.listen_addrs)
+    {
+        message.listenAddrs.clear();
+        message.signedPeerRecord = None;
+    }
+    if HashSet::<&StreamProtocol>::from_iter(&previous.protocols)
+        == HashSet::from_iter(&info.protocols)
+    {
+        message.protocols.clear();
+    }
+    if previous.observed_addr == info.observed_addr {
+        message.observedAddr = None;
+    }
+
+    send(io, message).await?;
+
+    Ok(info)
+}
 
-    let message = proto::Identify {
+fn to_message(info: &Info) -> proto::Identify {
+    proto::Identify {
         agentVersion: Some(info.agent_version.clone()),
         protocolVersion: Some(info.protocol_version.clone()),
-        publicKey: Some(pubkey_bytes),
-        listenAddrs: listen_addrs,
+        publicKey: Some(info.public_key.encode_protobuf()),
+        listenAddrs: info.listen_addrs.iter().map(|addr| addr.to_vec()).collect(),
         observedAddr: Some(info.observed_addr.to_vec()),
         protocols: info.protocols.iter().map(|p| p.to_string()).collect(),
-    };
+        signedPeerRecord: info
+            .signed_peer_record
+            .clone()
+            .map(|r| r.into_protobuf_encoding()),
+    }
+}
 
+async fn send<T>(io: T, message: proto::Identify) -> Result<(), UpgradeError>
+where
+    T: AsyncWrite + Unpin,
+{
     let mut framed_io = FramedWrite::new(
         io,
         quick_protobuf_codec::Codec::<proto::Identify>::new(MAX_MESSAGE_SIZE_BYTES),
@@ -115,7 +182,7 @@ where
     framed_io.send(message).await?;
     framed_io.close().await?;
 
-    Ok(info)
+    Ok(())
 }
 
 pub(crate) async fn recv_push<T>(socket: T) -> Result<PushInfo, UpgradeError>
@@ -206,6 +273,18 @@ fn parse_observed_addr(observed_addr: Option<Vec<u8>>) -> Option<Multiaddr> {
     })
 }
 
+fn parse_signed_peer_record(signed_peer_record: Option<Vec<u8>>) -> Option<SignedEnvelope> {
+    signed_peer_record.and_then(
+        |bytes| match SignedEnvelope::from_protobuf_encoding(&bytes) {
+            Ok(envelope) => Some(envelope),
+            Err(e) => {
+                tracing::debug!("Unable to decode signed peer record: {e:?}");
+                None
+            }
+        },
+    )
+}
+
 impl TryFrom<proto::Identify> for Info {
     type Error = UpgradeError;
 
@@ -225,6 +304,7 @@ impl TryFrom<proto::Identify> for Info {
             listen_addrs: parse_listen_addrs(msg.listenAddrs),
             protocols: parse_protocols(msg.protocols),
             observed_addr: parse_observed_addr(msg.observedAddr).unwrap_or(Multiaddr::empty()),
+            signed_peer_record: parse_signed_peer_record(msg.signedPeerRecord),
         };
 
         Ok(info)
@@ -242,6 +322,7 @@ impl TryFrom<proto::Identify> for PushInfo {
             listen_addrs: parse_listen_addrs(msg.listenAddrs),
             protocols: parse_protocols(msg.protocols),
             observed_addr: parse_observed_addr(msg.observedAddr),
+            signed_peer_record: parse_signed_peer_record(msg.signedPeerRecord),
         };
 
         Ok(info)
@@ -284,6 +365,7 @@ mod tests {
             observedAddr: None,
             protocolVersion: None,
             protocols: vec![],
+            signedPeerRecord: None,
             publicKey: Some(
                 identity::Keypair::generate_ed25519()
                     .public()
@@ -295,4 +377,36 @@ mod tests {
 
         assert_eq!(info.listen_addrs, vec![valid_multiaddr])
     }
+
+    #[async_std::test]
+    async fn delta_push_only_contains_changed_fields() {
+        let previous = Info {
+            public_key: identity::Keypair::generate_ed25519().public(),
+            protocol_version: "a".to_string(),
+            agent_version: "b".to_string(),
+            listen_addrs: vec!["/memory/1".parse().unwrap()],
+            protocols: vec![PROTOCOL_NAME],
+            observed_addr: "/memory/2".parse().unwrap(),
+            signed_peer_record: None,
+        };
+        let info = Info {
+            listen_addrs: vec!["/memory/3".parse().unwrap()],
+            ..previous.clone()
+        };
+
+        let mut buf = futures::io::Cursor::new(Vec::new());
+        send_push(&mut buf, info, Some(previous)).await.unwrap();
+        buf.set_position(0);
+        let message = recv(buf).await.unwrap();
+
+        assert_eq!(
+            message.listenAddrs,
+            vec!["/memory/3".parse::<Multiaddr>().unwrap().to_vec()]
+        );
+        assert_eq!(message.publicKey, None);
+        assert_eq!(message.protocolVersion, None);
+        assert_eq!(message.agentVersion, None);
+        assert_eq!(message.observedAddr, None);
+        assert!(message.protocols.is_empty());
+    }
 }
@@ -1,5 +1,5 @@
 use futures::StreamExt;
-use libp2p_core::multiaddr::Protocol;
+use libp2p_core::{multiaddr::Protocol, Multiaddr, PeerRecord};
 use libp2p_identify as identify;
 use libp2p_swarm::{Swarm, SwarmEvent};
 use libp2p_swarm_test::SwarmExt;
@@ -228,6 +228,166 @@ async fn emits_unique_listen_addresses() {
     assert!(reported_addrs.contains(&(swarm2_peer_id, swarm2_tcp_listen_addr)));
 }
 
+#[async_std::test]
+async fn signed_peer_record() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(
+            identify::Config::new("a".to_string(), identity.public())
+                .with_signed_addresses_only(true),
+        )
+    });
+    let mut swarm2 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(identify::Config::new_with_signed_peer_record(
+            "b".to_string(),
+            &identity,
+        ))
+    });
+
+    let (swarm2_mem_listen_addr, swarm2_tcp_listen_addr) =
+        swarm2.listen().with_memory_addr_external().await;
+    let swarm2_peer_id = *swarm2.local_peer_id();
+    swarm1.connect(&mut swarm2).await;
+
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    let info = swarm1
+        .wait(|e| match e {
+            SwarmEvent::Behaviour(identify::Event::Received { info, .. }) => Some(info),
+            _ => None,
+        })
+        .await;
+
+    let record = PeerRecord::from_signed_envelope(info.signed_peer_record.unwrap()).unwrap();
+    assert_eq!(record.peer_id(), swarm2_peer_id);
+    assert_eq!(info.listen_addrs, record.addresses());
+    assert!(info.listen_addrs.contains(&swarm2_mem_listen_addr));
+    assert!(info.listen_addrs.contains(&swarm2_tcp_listen_addr));
+}
+
+#[async_std::test]
+async fn ignores_unsigned_addresses_if_only_signed_are_accepted() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(
+            identify::Config::new("a".to_string(), identity.public())
+                .with_signed_addresses_only(true),
+        )
+    });
+    let mut swarm2 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(identify::Config::new("b".to_string(), identity.public()))
+    });
+
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    let info = swarm1
+        .wait(|e| match e {
+            SwarmEvent::Behaviour(identify::Event::Received { info, .. }) => Some(info),
+            _ => None,
+        })
+        .await;
+
+    assert!(info.signed_peer_record.is_none());
+    assert!(info.listen_addrs.is_empty());
+}
+
+#[async_std::test]
+async fn only_advertises_filtered_addresses() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(identify::Config::new("a".to_string(), identity.public()))
+    });
+    let mut swarm2 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(
+            identify::Config::new_with_signed_peer_record("b".to_string(), &identity)
+                .with_address_filter(|remote_addr, address| {
+                    // Only advertise addresses of the transport of the connection.
+                    let is_memory =
+                        |a: &Multiaddr| matches!(a.iter().next(), Some(Protocol::Memory(_)));
+                    (is_memory(remote_addr) == is_memory(address)).then(|| address.clone())
+                }),
+        )
+    });
+
+    let (swarm2_mem_listen_addr, _) = swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    let info = swarm1
+        .wait(|e| match e {
+            SwarmEvent::Behaviour(identify::Event::Received { info, .. }) => Some(info),
+            _ => None,
+        })
+        .await;
+
+    assert_eq!(info.listen_addrs, vec![swarm2_mem_listen_addr]);
+    let record = PeerRecord::from_signed_envelope(info.signed_peer_record.unwrap()).unwrap();
+    assert_eq!(record.addresses(), info.listen_addrs);
+}
+
+#[async_std::test]
+async fn closes_connections_violating_version_policy() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(
+            identify::Config::new("a".to_string(), identity.public()).with_version_policy(
+                identify::VersionPolicy::default().with_protocol_version("a*"),
+            ),
+        )
+    });
+    let mut swarm2 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(identify::Config::new("c".to_string(), identity.public()))
+    });
+    let swarm2_peer_id = *swarm2.local_peer_id();
+
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    let (peer_id, info, action) = swarm1
+        .wait(|e| match e {
+            SwarmEvent::Behaviour(identify::Event::VersionPolicyViolated {
+                peer_id,
+                info,
+                action,
+                ..
+            }) => Some((peer_id, info, action)),
+            SwarmEvent::Behaviour(identify::Event::Received { .. }) => {
+                panic!("Received info violating the version policy")
+            }
+            _ => None,
+        })
+        .await;
+    assert_eq!(peer_id, swarm2_peer_id);
+    assert_eq!(info.protocol_version, "c");
+    assert_eq!(action, identify::PolicyAction::Close);
+
+    swarm1
+        .wait(|e| match e {
+            SwarmEvent::ConnectionClosed { peer_id, .. } => Some(peer_id),
+            _ => None,
+        })
+        .await;
+    assert!(!swarm1.is_connected(&swarm2_peer_id));
+}
+
 #[async_std::test]
 async fn identify_push() {
     let _ = tracing_subscriber::fmt()
@@ -280,6 +440,52 @@ async fn identify_push() {
     assert!(swarm1_received_info.listen_addrs.is_empty());
 }
 
+#[async_std::test]
+async fn listen_addr_update_pushes_are_rate_limited() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(identify::Config::new("a".to_string(), identity.public()))
+    });
+    let mut swarm2 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(
+            identify::Config::new("a".to_string(), identity.public())
+                .with_push_listen_addr_updates(true)
+                .with_push_min_interval(Duration::from_secs(60))
+                .with_delta_push(true),
+        )
+    });
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+    let ([_, _], [_, _]): ([identify::Event; 2], [identify::Event; 2]) =
+        libp2p_swarm_test::drive(&mut swarm1, &mut swarm2).await;
+
+    async_std::task::spawn(swarm1.loop_on_next());
+
+    swarm2.listen_on("/memory/0".parse().unwrap()).unwrap();
+    swarm2.listen_on("/memory/0".parse().unwrap()).unwrap();
+
+    let mut pushes = 0;
+    let _ = async_std::future::timeout(Duration::from_secs(2), async {
+        loop {
+            if let SwarmEvent::Behaviour(identify::Event::Pushed { .. }) =
+                swarm2.select_next_some().await
+            {
+                pushes += 1;
+            }
+        }
+    })
+    .await;
+
+    assert_eq!(
+        pushes, 1,
+        "expect changes within the interval to be coalesced"
+    );
+}
+
 #[async_std::test]
 async fn discover_peer_after_disconnect() {
     let _ = tracing_subscriber::fmt()
@@ -26,8 +26,13 @@ use crate::addresses::Addresses;
 use crate::bootstrap;
 use crate::handler::{Handler, HandlerEvent, HandlerIn, RequestId};
 use crate::kbucket::{self, Distance, KBucketsTable, NodeStatus};
+use crate::load_shedding::{self, InboundAccounting, InboundCost, InboundLimits, ThrottleReason};
+use crate::path_cache::{CachedEntry, PathCache, PathCacheConfig};
 use crate::protocol::{ConnectionType, KadPeer, ProtocolConfig};
-use crate::query::{Query, QueryConfig, QueryId, QueryPool, QueryPoolState};
+use crate::query::{
+    AdaptiveParallelism, Query, QueryConfig, QueryId, QueryOptions, QueryPool, QueryPoolState,
+    RecordValidator,
+};
 use crate::record::{
     self,
     store::{self, RecordStore},
@@ -120,6 +125,12 @@ pub struct Behaviour<TStore> {
 
     /// Tracks the status of the current bootstrap.
     bootstrap_status: bootstrap::Status,
+
+    /// Accounts for the cost of inbound requests per peer.
+    inbound_accounting: InboundAccounting,
+
+    /// See [`Config::set_path_caching`].
+    path_cache: Option<PathCache>,
 }
 
 /// The configurable strategies for the insertion of peers
@@ -187,6 +198,9 @@ pub struct Config {
     caching: Caching,
     periodic_bootstrap_interval: Option<Duration>,
     automatic_bootstrap_throttle: Option<Duration>,
+    mode_on_reachability: bool,
+    inbound_limits: InboundLimits,
+    path_caching: Option<PathCacheConfig>,
 }
 
 impl Default for Config {
@@ -230,6 +244,9 @@ impl Config {
             caching: Caching::Enabled { max_peers: 1 },
             periodic_bootstrap_interval: Some(Duration::from_secs(5 * 60)),
             automatic_bootstrap_throttle: Some(bootstrap::DEFAULT_AUTOMATIC_THROTTLE),
+            mode_on_reachability: true,
+            inbound_limits: InboundLimits::default(),
+            path_caching: None,
         }
     }
 
@@ -297,7 +314,7 @@ impl Config {
     /// in the presence of potentially adversarial nodes.
     ///
     /// When enabled the number of disjoint paths used equals the configured
-    /// parallelism.
+    /// parallelism, unless set via [`Config::set_disjoint_query_path_count`].
     ///
     /// See the S/Kademlia paper for more information on the high level design
     /// as well as its security improvements.
@@ -306,6 +323,33 @@ impl Config {
         self
     }
 
+    /// Sets the number of disjoint paths used by iterative queries when
+    /// [`Config::disjoint_query_paths`] is enabled.
+    ///
+    /// Each path is then queried with the configured parallelism. Records
+    /// found by a record lookup are reported with the index of the path in
+    /// [`PeerRecord::path`], e.g. to accept a record only if a majority of
+    /// the paths found it. Defaults to the configured parallelism.
+    pub fn set_disjoint_query_path_count(&mut self, count: NonZeroUsize) -> &mut Self {
+        self.query_config.disjoint_path_count = Some(count);
+        self
+    }
+
+    /// Enables adaptive parallelism for iterative queries.
+    ///
+    /// Requests of an iterative query that did not receive a response within the
+    /// slow response threshold of the given [`AdaptiveParallelism`] no longer count
+    /// towards the parallelism set with [`Config::set_parallelism`], raising the
+    /// effective parallelism up to the configured maximum. `None` disables
+    /// adaptive parallelism, which is the default.
+    pub fn set_adaptive_parallelism(
+        &mut self,
+        adaptive_parallelism: Option<AdaptiveParallelism>,
+    ) -> &mut Self {
+        self.query_config.adaptive_parallelism = adaptive_parallelism;
+        self
+    }
+
     /// Sets the TTL for stored records.
     ///
     /// The TTL should be significantly longer than the (re-)publication
@@ -417,6 +461,53 @@ impl Config {
         self
     }
 
+    /// Sets whether the [`Mode`] is derived from the reachability of the local node.
+    ///
+    /// If enabled, the node operates in [`Mode::Server`] as long as it has at least one confirmed
+    /// external address and in [`Mode::Client`] otherwise. External addresses are confirmed and
+    /// expired by other behaviours, e.g. `libp2p-autonat` reporting the node as publicly reachable
+    /// or private. Each switch is reported via [`Event::ModeChanged`].
+    ///
+    /// If disabled, the node operates in [`Mode::Client`] until a mode is set via
+    /// [`Behaviour::set_mode`].
+    ///
+    /// * Default to `true`.
+    pub fn set_mode_on_reachability(&mut self, enabled: bool) -> &mut Self {
+        self.mode_on_reachability = enabled;
+        self
+    }
+
+    /// Sets the [`InboundLimits`] on the requests of a peer, above which its requests are
+    /// throttled.
+    ///
+    /// The cost of the inbound requests of a peer is accounted regardless of the limits and can
+    /// be queried via [`Behaviour::inbound_cost`].
+    ///
+    /// * Default to windows of 1 minute without any limit.
+    pub fn set_inbound_limits(&mut self, limits: InboundLimits) -> &mut Self {
+        self.inbound_limits = limits;
+        self
+    }
+
+    /// Enables caching of records and provider records at the nodes along the path of a lookup,
+    /// as described in the original Kademlia paper.
+    ///
+    /// Once a [`Behaviour::get_record`] or [`Behaviour::get_providers`] lookup finishes, the first
+    /// record found, respectively the provider records found, are written back to the closest
+    /// peers queried that did not return them. Conversely, records put to the local node while it
+    /// is not among the closest nodes to the key known to it, as well as provider records put by
+    /// peers other than the provider, are kept in a cache of bounded size instead of the
+    /// [`RecordStore`]. Lookups of remotes are answered from this cache if the [`RecordStore`] has
+    /// no entry for the key, reported via [`Event::ServedFromCache`].
+    ///
+    /// Records and provider records are only cached with [`StoreInserts::Unfiltered`].
+    ///
+    /// * Default to `None`, i.e. disabled.
+    pub fn set_path_caching(&mut self, config: Option<PathCacheConfig>) -> &mut Self {
+        self.path_caching = config;
+        self
+    }
+
     /// Sets the interval on which [`Behaviour::bootstrap`] is called periodically.
     ///
     /// * Default to `5` minutes.
@@ -464,7 +555,7 @@ where
     pub fn with_config(id: PeerId, store: TStore, config: Config) -> Self {
         let local_key = kbucket::Key::from(id);
 
-        let put_record_job = config
+        let mut put_record_job = config
             .record_replication_interval
             .or(config.record_publication_interval)
             .map(|interval| {
@@ -476,10 +567,24 @@ where
                 )
             });
 
-        let add_provider_job = config
+        let mut add_provider_job = config
             .provider_publication_interval
             .map(AddProviderJob::new);
 
+        // Records that are already in the store, e.g. because they were loaded from a
+        // `PersistentStore` after a restart, are republished right away instead of
+        // only after a full publication interval.
+        if let Some(job) = put_record_job.as_mut() {
+            if store.records().any(|r| r.publisher == Some(id)) {
+                job.start_now(true);
+            }
+        }
+        if let Some(job) = add_provider_job.as_mut() {
+            if store.provided().next().is_some() {
+                job.start_now();
+            }
+        }
+
         Behaviour {
             store,
             caching: config.caching,
@@ -499,12 +604,14 @@ where
             local_peer_id: id,
             connections: Default::default(),
             mode: Mode::Client,
-            auto_mode: true,
+            auto_mode: config.mode_on_reachability,
             no_events_waker: None,
             bootstrap_status: bootstrap::Status::new(
                 config.periodic_bootstrap_interval,
                 config.automatic_bootstrap_throttle,
             ),
+            inbound_accounting: InboundAccounting::new(config.inbound_limits),
+            path_cache: config.path_caching.map(PathCache::new),
         }
     }
 
@@ -658,7 +765,10 @@ where
         match self.kbuckets.entry(&key)? {
             kbucket::Entry::Present(mut entry, _) => {
                 if entry.value().remove(address).is_err() {
-                    Some(entry.remove()) // it is the last address, thus remove the peer.
+                    // It is the last address, thus remove the peer.
+                    let removed = entry.remove();
+                    self.peer_evicted(&removed, EvictionReason::NoAddresses);
+                    Some(removed)
                 } else {
                     None
                 }
@@ -684,12 +794,59 @@ where
     ) -> Option<kbucket::EntryView<kbucket::Key<PeerId>, Addresses>> {
         let key = kbucket::Key::from(*peer);
         match self.kbuckets.entry(&key)? {
-            kbucket::Entry::Present(entry, _) => Some(entry.remove()),
+            kbucket::Entry::Present(entry, _) => {
+                let removed = entry.remove();
+                self.peer_evicted(&removed, EvictionReason::Removed);
+                Some(removed)
+            }
             kbucket::Entry::Pending(entry, _) => Some(entry.remove()),
             kbucket::Entry::Absent(..) => None,
         }
     }
 
+    /// Exports the peers in the routing table together with their known addresses,
+    /// e.g. to persist the routing table on shutdown.
+    ///
+    /// The peers can be re-inserted on startup with [`Behaviour::import_routing_table`].
+    /// Peers pending insertion into the routing table are not included.
+    pub fn export_routing_table(&mut self) -> Vec<(PeerId, Vec<Multiaddr>)> {
+        self.kbuckets
+            .iter()
+            .flat_map(|bucket| {
+                bucket
+                    .iter()
+                    .map(|entry| {
+                        (
+                            *entry.node.key.preimage(),
+                            entry.node.value.iter().cloned().collect(),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Inserts the given peers with their addresses into the routing table,
+    /// e.g. from a routing table persisted with [`Behaviour::export_routing_table`].
+    ///
+    /// Every address is added as if by [`Behaviour::add_address`], emitting
+    /// [`Event::RoutingUpdated`] for every peer that is added to the routing table.
+    /// Returns the number of given peers that are in the routing table afterwards.
+    pub fn import_routing_table<I>(&mut self, peers: I) -> usize
+    where
+        I: IntoIterator<Item = (PeerId, Vec<Multiaddr>)>,
+    {
+        peers
+            .into_iter()
+            .filter(|(peer, addresses)| {
+                addresses.iter().fold(false, |inserted, address| {
+                    let update = self.add_address(peer, address.clone());
+                    inserted || update == RoutingUpdate::Success
+                })
+            })
+            .count()
+    }
+
     /// Returns an iterator over all non-empty buckets in the routing table.
     pub fn kbuckets(
         &mut self,
@@ -715,6 +872,17 @@ where
     /// The result of the query is delivered in a
     /// [`Event::OutboundQueryProgressed{QueryResult::GetClosestPeers}`].
     pub fn get_closest_peers<K>(&mut self, key: K) -> QueryId
+    where
+        K: Into<kbucket::Key<K>> + Into<Vec<u8>> + Clone,
+    {
+        self.get_closest_peers_with_options(key, QueryOptions::new())
+    }
+
+    /// Initiates an iterative query for the closest peers to the given key,
+    /// overriding the configured query settings with the given options.
+    ///
+    /// See [`Behaviour::get_closest_peers`].
+    pub fn get_closest_peers_with_options<K>(&mut self, key: K, options: QueryOptions) -> QueryId
     where
         K: Into<kbucket::Key<K>> + Into<Vec<u8>> + Clone,
     {
@@ -726,7 +894,8 @@ where
         };
         let peer_keys: Vec<kbucket::Key<PeerId>> = self.kbuckets.closest_keys(&target).collect();
         let inner = QueryInner::new(info);
-        self.queries.add_iter_closest(target, peer_keys, inner)
+        self.queries
+            .add_iter_closest_with_options(target, peer_keys, inner, &options)
     }
 
     /// Returns closest peers to the given key; takes peers from local routing table only.
@@ -742,14 +911,29 @@ where
     /// The result of this operation is delivered in a
     /// [`Event::OutboundQueryProgressed{QueryResult::GetRecord}`].
     pub fn get_record(&mut self, key: record::Key) -> QueryId {
+        self.get_record_with_options(key, QueryOptions::new())
+    }
+
+    /// Performs a lookup for a record in the DHT, overriding the configured
+    /// query settings with the given options.
+    ///
+    /// With a [`QueryOptions::with_quorum`], the lookup finishes as soon as the
+    /// quorum of records has been found, including a record from local storage.
+    /// With a [`QueryOptions::with_record_validator`], only valid records count
+    /// towards the quorum.
+    ///
+    /// See [`Behaviour::get_record`].
+    pub fn get_record_with_options(&mut self, key: record::Key, options: QueryOptions) -> QueryId {
         let record = if let Some(record) = self.store.get(&key) {
             if record.is_expired(Instant::now()) {
-                self.store.remove(&key);
+                let record = record.into_owned();
+                self.on_record_expired(record);
                 None
             } else {
                 Some(PeerRecord {
                     peer: None,
                     record: record.into_owned(),
+                    path: None,
                 })
             }
         } else {
@@ -757,31 +941,61 @@ where
         };
 
         let step = ProgressStep::first();
+        let quorum = options
+            .quorum()
+            .map(|q| q.eval(self.queries.config().replication_factor));
+        let validator = options.record_validator();
+        let valid = record.as_ref().map(|record| {
+            validator
+                .as_ref()
+                .map_or(true, |validator| validator(&record.record))
+        });
 
         let target = kbucket::Key::new(key.clone());
-        let info = if record.is_some() {
+        let info = if valid == Some(true) {
             QueryInfo::GetRecord {
                 key,
                 step: step.next(),
-                found_a_record: true,
+                records_found: 1,
+                quorum,
                 cache_candidates: BTreeMap::new(),
             }
         } else {
             QueryInfo::GetRecord {
                 key,
                 step: step.clone(),
-                found_a_record: false,
+                records_found: 0,
+                quorum,
                 cache_candidates: BTreeMap::new(),
             }
         };
         let peers = self.kbuckets.closest_keys(&target);
-        let inner = QueryInner::new(info);
-        let id = self.queries.add_iter_closest(target.clone(), peers, inner);
+        let mut inner = QueryInner::new(info);
+        inner.record_validator = validator;
+        let id = self
+            .queries
+            .add_iter_closest_with_options(target.clone(), peers, inner, &options);
+
+        if valid == Some(true) && quorum.map_or(false, |q| q.get() == 1) {
+            if let Some(query) = self.queries.get_mut(&id) {
+                query.finish();
+            }
+        }
 
         // No queries were actually done for the results yet.
         let stats = QueryStats::empty();
 
-        if let Some(record) = record {
+        if let (Some(record), Some(valid)) = (&record, valid) {
+            if options.record_validator().is_some() {
+                self.queued_events
+                    .push_back(ToSwarm::GenerateEvent(Event::InboundRecordFound {
+                        id,
+                        record: record.clone(),
+                        valid,
+                    }));
+            }
+        }
+        if let Some(record) = record.filter(|_| valid == Some(true)) {
             self.queued_events
                 .push_back(ToSwarm::GenerateEvent(Event::OutboundQueryProgressed {
                     id,
@@ -812,12 +1026,18 @@ where
     /// does not update the record's expiration in local storage, thus a given record
     /// with an explicit expiration will always expire at that instant and until then
     /// is subject to regular (re-)replication and (re-)publication.
+    ///
+    /// A record with a [`Record::ttl`] instead expires the TTL after its last (re-)publication,
+    /// both locally and remotely. Once expired, [`Event::RecordExpired`] is reported.
     pub fn put_record(
         &mut self,
         mut record: Record,
         quorum: Quorum,
     ) -> Result<QueryId, store::Error> {
         record.publisher = Some(*self.kbuckets.local_key().preimage());
+        if let Some(ttl) = record.ttl {
+            record.expires = Some(Instant::now() + ttl);
+        }
         self.store.put(record.clone())?;
         record.expires = record
             .expires
@@ -841,7 +1061,8 @@ where
     /// The given [`Quorum`] is understood in the context of the total
     /// number of distinct peers given.
     ///
-    /// If the record's expiration is `None`, the configured record TTL is used.
+    /// If the record's expiration is `None`, the record's TTL or otherwise the configured
+    /// record TTL is used.
     ///
     /// > **Note**: This is not a regular Kademlia DHT operation. It needs to be
     /// > used to selectively update or store a record to specific peers
@@ -865,9 +1086,12 @@ where
             // introducing a new kind of error.
             NonZeroUsize::new(1).expect("1 > 0")
         };
-        record.expires = record
-            .expires
-            .or_else(|| self.record_ttl.map(|ttl| Instant::now() + ttl));
+        record.expires = record.expires.or_else(|| {
+            record
+                .ttl
+                .or(self.record_ttl)
+                .map(|ttl| Instant::now() + ttl)
+        });
         let context = PutRecordContext::Custom;
         let info = QueryInfo::PutRecord {
             context,
@@ -1050,6 +1274,14 @@ where
         id
     }
 
+    /// Returns the cost of the inbound requests of the given peer in the current accounting
+    /// window, if it made any.
+    ///
+    /// See [`Config::set_inbound_limits`].
+    pub fn inbound_cost(&self, peer: &PeerId) -> Option<InboundCost> {
+        self.inbound_accounting.cost(peer, Instant::now())
+    }
+
     /// Set the [`Mode`] in which we should operate.
     ///
     /// By default, we are in [`Mode::Client`] and will swap into [`Mode::Server`] as soon as we have a confirmed, external address via [`FromSwarm::ExternalAddrConfirmed`].
@@ -1074,6 +1306,42 @@ where
         }
     }
 
+    /// Accounts for an inbound request of `source`.
+    ///
+    /// Returns `true` if the request is throttled, in which case its stream is reset.
+    fn throttle_inbound_request(
+        &mut self,
+        source: PeerId,
+        connection: ConnectionId,
+        request_id: Option<RequestId>,
+        is_lookup: bool,
+    ) -> bool {
+        let reason = match self
+            .inbound_accounting
+            .on_request(source, is_lookup, Instant::now())
+        {
+            Ok(()) => return false,
+            Err(reason) => reason,
+        };
+
+        tracing::debug!(peer=%source, ?reason, "Throttling inbound request");
+
+        if let Some(request_id) = request_id {
+            self.queued_events.push_back(ToSwarm::NotifyHandler {
+                peer_id: source,
+                handler: NotifyHandler::One(connection),
+                event: HandlerIn::Reset(request_id),
+            });
+        }
+        self.queued_events
+            .push_back(ToSwarm::GenerateEvent(Event::InboundRequestThrottled {
+                peer: source,
+                reason,
+            }));
+
+        true
+    }
+
     fn reconfigure_mode(&mut self) {
         if self.connections.is_empty() {
             return;
@@ -1244,6 +1512,52 @@ where
             .collect()
     }
 
+    /// Writes back the record found by a lookup to the closest peers that did not return it.
+    fn write_back_record(&mut self, write_back: WriteBack) {
+        let Some(record) = write_back.record else {
+            return;
+        };
+        if write_back.candidates.is_empty() {
+            return;
+        }
+
+        let info = QueryInfo::PutRecord {
+            quorum: NonZeroUsize::new(write_back.candidates.len()).expect("not empty"),
+            record,
+            phase: PutRecordPhase::PutRecord {
+                success: Vec::new(),
+                get_closest_peers_stats: QueryStats::empty(),
+            },
+            context: PutRecordContext::Cache,
+        };
+        self.queries
+            .add_fixed(write_back.candidates.into_values(), QueryInner::new(info));
+    }
+
+    /// Writes back the provider records found by a lookup to the closest peers that did not
+    /// return any.
+    fn write_back_providers(&mut self, key: record::Key, write_back: WriteBack) {
+        if write_back.candidates.is_empty() {
+            return;
+        }
+
+        for provider in write_back.providers {
+            let info = QueryInfo::AddProvider {
+                key: key.clone(),
+                phase: AddProviderPhase::AddProvider {
+                    provider_id: provider.node_id,
+                    external_addresses: provider.multiaddrs,
+                    get_closest_peers_stats: QueryStats::empty(),
+                },
+                context: AddProviderContext::Cache,
+            };
+            self.queries.add_fixed(
+                write_back.candidates.values().copied(),
+                QueryInner::new(info),
+            );
+        }
+    }
+
     /// Starts an iterative `ADD_PROVIDER` query for the given key.
     fn start_add_provider(&mut self, key: record::Key, context: AddProviderContext) {
         let info = QueryInfo::AddProvider {
@@ -1477,8 +1791,9 @@ where
                 })
             }
 
-            QueryInfo::GetProviders { mut step, .. } => {
+            QueryInfo::GetProviders { key, mut step, .. } => {
                 step.last = true;
+                self.write_back_providers(key, result.inner.write_back);
 
                 Some(Event::OutboundQueryProgressed {
                     id: query_id,
@@ -1533,17 +1848,23 @@ where
                     result: QueryResult::RepublishProvider(Ok(AddProviderOk { key })),
                     step: ProgressStep::first_and_last(),
                 }),
+                AddProviderContext::Cache => {
+                    tracing::debug!(record=?key, "Provider record written back");
+                    None
+                }
             },
 
             QueryInfo::GetRecord {
                 key,
                 mut step,
-                found_a_record,
+                records_found,
                 cache_candidates,
+                ..
             } => {
                 step.last = true;
+                self.write_back_record(result.inner.write_back);
 
-                let results = if found_a_record {
+                let results = if records_found > 0 {
                     Ok(GetRecordOk::FinishedWithNoAdditionalRecord { cache_candidates })
                 } else {
                     Err(GetRecordError::NotFound {
@@ -1619,6 +1940,10 @@ where
                         tracing::debug!(record=?record.key, "Record replicated");
                         None
                     }
+                    PutRecordContext::Cache => {
+                        tracing::debug!(record=?record.key, "Record written back");
+                        None
+                    }
                 }
             }
         }
@@ -1666,20 +1991,24 @@ where
                 })
             }
 
-            QueryInfo::AddProvider { context, key, .. } => Some(match context {
-                AddProviderContext::Publish => Event::OutboundQueryProgressed {
+            QueryInfo::AddProvider { context, key, .. } => match context {
+                AddProviderContext::Publish => Some(Event::OutboundQueryProgressed {
                     id: query_id,
                     stats: result.stats,
                     result: QueryResult::StartProviding(Err(AddProviderError::Timeout { key })),
                     step: ProgressStep::first_and_last(),
-                },
-                AddProviderContext::Republish => Event::OutboundQueryProgressed {
+                }),
+                AddProviderContext::Republish => Some(Event::OutboundQueryProgressed {
                     id: query_id,
                     stats: result.stats,
                     result: QueryResult::RepublishProvider(Err(AddProviderError::Timeout { key })),
                     step: ProgressStep::first_and_last(),
-                },
-            }),
+                }),
+                AddProviderContext::Cache => {
+                    tracing::debug!(record=?key, "Writing back provider record timed out");
+                    None
+                }
+            },
 
             QueryInfo::GetClosestPeers { key, mut step } => {
                 step.last = true;
@@ -1737,6 +2066,10 @@ where
                             None
                         }
                     },
+                    PutRecordContext::Cache => {
+                        tracing::debug!("Writing back record failed: {:?}", err);
+                        None
+                    }
                 }
             }
 
@@ -1830,8 +2163,22 @@ where
             // The record is cloned because of the weird libp2p protocol
             // requirement to send back the value in the response, although this
             // is a waste of resources.
-            match self.record_filtering {
-                StoreInserts::Unfiltered => match self.store.put(record.clone()) {
+            match (self.record_filtering, self.path_cache.as_mut()) {
+                // The local node is not among the k closest nodes to the key it knows of,
+                // hence the record is written back to the path of a lookup.
+                (StoreInserts::Unfiltered, Some(cache)) if num_beyond_k > 0 => {
+                    tracing::debug!(record=?record.key, "Record cached");
+                    cache.put(record.clone(), now);
+                    self.queued_events
+                        .push_back(ToSwarm::GenerateEvent(Event::InboundRequest {
+                            request: InboundRequest::PutRecord {
+                                source,
+                                connection,
+                                record: None,
+                            },
+                        }));
+                }
+                (StoreInserts::Unfiltered, _) => match self.store.put(record.clone()) {
                     Ok(()) => {
                         tracing::debug!(
                             record=?record.key,
@@ -1859,7 +2206,7 @@ where
                         return;
                     }
                 },
-                StoreInserts::FilterBoth => {
+                (StoreInserts::FilterBoth, _) => {
                     self.queued_events
                         .push_back(ToSwarm::GenerateEvent(Event::InboundRequest {
                             request: InboundRequest::PutRecord {
@@ -1923,6 +2270,105 @@ where
         }
     }
 
+    /// Caches a provider record written back by a peer other than the provider.
+    fn provider_cached(&mut self, key: record::Key, provider: KadPeer) {
+        let Some(cache) = self.path_cache.as_mut() else {
+            return;
+        };
+        if &provider.node_id == self.kbuckets.local_key().preimage() {
+            return;
+        }
+
+        tracing::debug!(record=?key, provider=%provider.node_id, "Provider record cached");
+        let record = ProviderRecord {
+            key,
+            provider: provider.node_id,
+            expires: self.provider_record_ttl.map(|ttl| Instant::now() + ttl),
+            addresses: provider.multiaddrs,
+        };
+        cache.add_provider(record, Instant::now());
+        self.queued_events
+            .push_back(ToSwarm::GenerateEvent(Event::InboundRequest {
+                request: InboundRequest::AddProvider { record: None },
+            }));
+    }
+
+    /// Adds the cached provider records of the key to the `provider_peers` from the store,
+    /// up to the replication factor, reporting them via [`Event::ServedFromCache`].
+    fn cached_provider_peers(
+        &mut self,
+        key: &record::Key,
+        source: &PeerId,
+        provider_peers: &mut Vec<KadPeer>,
+    ) {
+        let Some(cache) = self.path_cache.as_mut() else {
+            return;
+        };
+
+        let num_providers = provider_peers.len();
+        let max_providers = self.queries.config().replication_factor.get();
+        for record in cache.providers(key, Instant::now()) {
+            if provider_peers.len() >= max_providers {
+                break;
+            }
+            if &record.provider == source
+                || provider_peers.iter().any(|p| p.node_id == record.provider)
+            {
+                continue;
+            }
+
+            let connection_ty = if self.connected_peers.contains(&record.provider) {
+                ConnectionType::Connected
+            } else {
+                ConnectionType::NotConnected
+            };
+            provider_peers.push(KadPeer {
+                node_id: record.provider,
+                multiaddrs: record.addresses,
+                connection_ty,
+            });
+        }
+
+        if provider_peers.len() > num_providers {
+            self.queued_events
+                .push_back(ToSwarm::GenerateEvent(Event::ServedFromCache {
+                    peer: *source,
+                    key: key.clone(),
+                    entry: CachedEntry::Providers {
+                        num_providers: provider_peers.len() - num_providers,
+                    },
+                }));
+        }
+    }
+
+    /// Removes an expired record from the store and reports it via [`Event::RecordExpired`].
+    fn on_record_expired(&mut self, record: Record) {
+        self.store.remove(&record.key);
+        self.queued_events
+            .push_back(ToSwarm::GenerateEvent(Event::RecordExpired { record }));
+    }
+
+    /// Queues an [`Event::PeerEvicted`] for a peer removed from the routing table.
+    fn peer_evicted(
+        &mut self,
+        removed: &kbucket::EntryView<kbucket::Key<PeerId>, Addresses>,
+        reason: EvictionReason,
+    ) {
+        let key = &removed.node.key;
+        let bucket_range = self
+            .kbuckets
+            .bucket(key)
+            .map(|b| b.range())
+            .expect("Self to never be in the routing table.");
+        self.queued_events
+            .push_back(ToSwarm::GenerateEvent(Event::PeerEvicted {
+                peer: *key.preimage(),
+                addresses: removed.node.value.clone(),
+                bucket_range,
+                reason,
+            }));
+    }
+
     fn address_failed(&mut self, peer_id: PeerId, address: &Multiaddr) {
         let key = kbucket::Key::from(peer_id);
 
@@ -2248,6 +2694,10 @@ where
             }
 
             HandlerEvent::FindNodeReq { key, request_id } => {
+                if self.throttle_inbound_request(source, connection, Some(request_id), false) {
+                    return;
+                }
+
                 let closer_peers = self.find_closest(&kbucket::Key::new(key), &source);
 
                 self.queued_events
@@ -2275,8 +2725,15 @@ where
             }
 
             HandlerEvent::GetProvidersReq { key, request_id } => {
-                let provider_peers = self.provider_peers(&key, &source);
+                if self.throttle_inbound_request(source, connection, Some(request_id), true) {
+                    return;
+                }
+
+                let mut provider_peers = self.provider_peers(&key, &source);
+                self.cached_provider_peers(&key, &source, &mut provider_peers);
                 let closer_peers = self.find_closest(&kbucket::Key::new(key), &source);
+                self.inbound_accounting
+                    .on_served(source, load_shedding::providers_size(&provider_peers));
 
                 self.queued_events
                     .push_back(ToSwarm::GenerateEvent(Event::InboundRequest {
@@ -2306,6 +2763,24 @@ where
                 self.discovered(&query_id, &source, peers);
                 if let Some(query) = self.queries.get_mut(&query_id) {
                     let stats = query.stats().clone();
+                    if let (Some(cache), QueryInfo::GetProviders { key, .. }) =
+                        (&self.path_cache, &query.inner.info)
+                    {
+                        let write_back = &mut query.inner.write_back;
+                        if provider_peers.is_empty() {
+                            write_back.add_candidate(source, key, cache.max_peers());
+                        }
+                        for provider in &provider_peers {
+                            if write_back.providers.len() < K_VALUE.get()
+                                && !write_back
+                                    .providers
+                                    .iter()
+                                    .any(|p| p.node_id == provider.node_id)
+                            {
+                                write_back.providers.push(provider.clone());
+                            }
+                        }
+                    }
                     if let QueryInfo::GetProviders {
                         ref key,
                         ref mut providers_found,
@@ -2348,20 +2823,37 @@ where
             }
 
             HandlerEvent::AddProvider { key, provider } => {
-                // Only accept a provider record from a legitimate peer.
-                if provider.node_id != source {
+                // Only accept a provider record from a legitimate peer,
+                // unless it is written back to the path cache.
+                let is_write_back = provider.node_id != source;
+                if is_write_back
+                    && (self.path_cache.is_none()
+                        || self.record_filtering != StoreInserts::Unfiltered)
+                {
+                    return;
+                }
+                if self.throttle_inbound_request(source, connection, None, false) {
                     return;
                 }
 
-                self.provider_received(key, provider);
+                if is_write_back {
+                    self.provider_cached(key, provider);
+                } else {
+                    self.provider_received(key, provider);
+                }
             }
 
             HandlerEvent::GetRecord { key, request_id } => {
+                if self.throttle_inbound_request(source, connection, Some(request_id), true) {
+                    return;
+                }
+
                 // Lookup the record locally.
                 let record = match self.store.get(&key) {
                     Some(record) => {
                         if record.is_expired(Instant::now()) {
-                            self.store.remove(&key);
+                            let record = record.into_owned();
+                            self.on_record_expired(record);
                             None
                         } else {
                             Some(record.into_owned())
@@ -2369,8 +2861,22 @@ where
                     }
                     None => None,
                 };
+                let record = record.or_else(|| {
+                    let record = self.path_cache.as_mut()?.get(&key, Instant::now())?;
+                    self.queued_events
+                        .push_back(ToSwarm::GenerateEvent(Event::ServedFromCache {
+                            peer: source,
+                            key: key.clone(),
+                            entry: CachedEntry::Record,
+                        }));
+                    Some(record)
+                });
 
                 let closer_peers = self.find_closest(&kbucket::Key::new(key), &source);
+                if let Some(record) = &record {
+                    self.inbound_accounting
+                        .on_served(source, load_shedding::record_size(record));
+                }
 
                 self.queued_events
                     .push_back(ToSwarm::GenerateEvent(Event::InboundRequest {
@@ -2398,18 +2904,51 @@ where
             } => {
                 if let Some(query) = self.queries.get_mut(&query_id) {
                     let stats = query.stats().clone();
+                    let path = query.path(&source);
+                    let record = match (record, &query.inner.record_validator) {
+                        (Some(record), Some(validator)) => {
+                            let valid = validator(&record);
+                            self.queued_events.push_back(ToSwarm::GenerateEvent(
+                                Event::InboundRecordFound {
+                                    id: query_id,
+                                    record: PeerRecord {
+                                        peer: Some(source),
+                                        record: record.clone(),
+                                        path,
+                                    },
+                                    valid,
+                                },
+                            ));
+                            // Invalid records are treated as if the peer returned no record.
+                            valid.then_some(record)
+                        }
+                        (record, _) => record,
+                    };
+                    if let (Some(cache), QueryInfo::GetRecord { key, .. }) =
+                        (&self.path_cache, &query.inner.info)
+                    {
+                        let write_back = &mut query.inner.write_back;
+                        match &record {
+                            Some(record) => {
+                                write_back.record.get_or_insert_with(|| record.clone());
+                            }
+                            None => write_back.add_candidate(source, key, cache.max_peers()),
+                        }
+                    }
                     if let QueryInfo::GetRecord {
                         key,
                         ref mut step,
-                        ref mut found_a_record,
+                        ref mut records_found,
+                        quorum,
                         cache_candidates,
                     } = &mut query.inner.info
                     {
                         if let Some(record) = record {
-                            *found_a_record = true;
+                            *records_found += 1;
                             let record = PeerRecord {
                                 peer: Some(source),
                                 record,
+                                path,
                             };
 
                             self.queued_events.push_back(ToSwarm::GenerateEvent(
@@ -2424,6 +2963,10 @@ where
                             ));
 
                             *step = step.next();
+
+                            if quorum.map_or(false, |q| *records_found >= q.get()) {
+                                query.finish();
+                            }
                         } else {
                             tracing::trace!(record=?key, %source, "Record not found at source");
                             if let Caching::Enabled { max_peers } = self.caching {
@@ -2447,6 +2990,10 @@ where
             }
 
             HandlerEvent::PutRecord { record, request_id } => {
+                if self.throttle_inbound_request(source, connection, Some(request_id), false) {
+                    return;
+                }
+
                 self.record_received(source, connection, request_id, record);
             }
 
@@ -2522,6 +3069,10 @@ where
                     break;
                 }
             }
+            while let Some(record) = job.pop_expired() {
+                self.queued_events
+                    .push_back(ToSwarm::GenerateEvent(Event::RecordExpired { record }));
+            }
             self.put_record_job = Some(job);
         }
 
@@ -2541,18 +3092,32 @@ where
             // Drain applied pending entries from the routing table.
             if let Some(entry) = self.kbuckets.take_applied_pending() {
                 let kbucket::Node { key, value } = entry.inserted;
-                let event = Event::RoutingUpdated {
-                    bucket_range: self
-                        .kbuckets
-                        .bucket(&key)
-                        .map(|b| b.range())
-                        .expect("Self to never be applied from pending."),
-                    peer: key.into_preimage(),
-                    is_new_peer: true,
-                    addresses: value,
-                    old_peer: entry.evicted.map(|n| n.key.into_preimage()),
-                };
-                return Poll::Ready(ToSwarm::GenerateEvent(event));
+                let bucket_range = self
+                    .kbuckets
+                    .bucket(&key)
+                    .map(|b| b.range())
+                    .expect("Self to never be applied from pending.");
+                let peer = key.into_preimage();
+                let old_peer = entry.evicted.map(|evicted| {
+                    let old_peer = evicted.key.into_preimage();
+                    self.queued_events
+                        .push_back(ToSwarm::GenerateEvent(Event::PeerEvicted {
+                            peer: old_peer,
+                            addresses: evicted.value,
+                            bucket_range,
+                            reason: EvictionReason::Replaced { by: peer },
+                        }));
+                    old_peer
+                });
+                self.queued_events
+                    .push_back(ToSwarm::GenerateEvent(Event::RoutingUpdated {
+                        bucket_range,
+                        peer,
+                        is_new_peer: true,
+                        addresses: value,
+                        old_peer,
+                    }));
+                continue;
             }
 
             // Look for a finished query.
@@ -2664,6 +3229,11 @@ pub struct PeerRecord {
     /// retrieved from local storage.
     pub peer: Option<PeerId>,
     pub record: Record,
+    /// The index of the disjoint path of the query that found the record.
+    ///
+    /// `None` unless [`Config::disjoint_query_paths`] is enabled. Records found
+    /// on different paths were found via disjoint sets of peers.
+    pub path: Option<usize>,
 }
 
 //////////////////////////////////////////////////////////////////////////////
@@ -2712,6 +3282,28 @@ pub enum Event {
         old_peer: Option<PeerId>,
     },
 
+    /// A record was removed from the local store because it expired.
+    ///
+    /// Expired records are detected when they are looked up and when the periodic replication
+    /// job runs, see [`Config::set_replication_interval`].
+    RecordExpired {
+        /// The expired record.
+        record: Record,
+    },
+
+    /// A peer has been removed from the routing table.
+    PeerEvicted {
+        /// The ID of the removed peer.
+        peer: PeerId,
+        /// The addresses of the removed peer at the time of removal.
+        addresses: Addresses,
+        /// The minimum inclusive and maximum inclusive distance of the
+        /// bucket the peer was removed from.
+        bucket_range: (Distance, Distance),
+        /// Why the peer was removed.
+        reason: EvictionReason,
+    },
+
     /// A peer has connected for whom no listen address is known.
     ///
     /// If the peer is to be added to the routing table, a known
@@ -2749,6 +3341,41 @@ pub enum Event {
     /// This happens in response to an external
     /// address being added or removed.
     ModeChanged { new_mode: Mode },
+
+    /// An inbound request of a peer has been throttled because the peer exceeded one of the
+    /// [`InboundLimits`] set via [`Config::set_inbound_limits`].
+    ///
+    /// The stream of the request has been reset instead of answering it.
+    InboundRequestThrottled {
+        /// The peer that sent the request.
+        peer: PeerId,
+        /// The exceeded limit.
+        reason: ThrottleReason,
+    },
+
+    /// A lookup of a peer has been answered with entries of the path cache enabled via
+    /// [`Config::set_path_caching`].
+    ServedFromCache {
+        /// The peer that sent the request.
+        peer: PeerId,
+        /// The key looked up.
+        key: record::Key,
+        /// The entries served.
+        entry: CachedEntry,
+    },
+
+    /// A record has been received by a lookup with [`QueryOptions::with_record_validator`].
+    ///
+    /// Reported for every record as it arrives, before it is accounted towards the
+    /// quorum of the lookup, e.g. to penalize peers returning invalid records.
+    InboundRecordFound {
+        /// The ID of the lookup.
+        id: QueryId,
+        /// The record found.
+        record: PeerRecord,
+        /// Whether the record passed the validator.
+        valid: bool,
+    },
 }
 
 /// Information about progress events.
@@ -3105,6 +3732,10 @@ struct QueryInner {
     /// A request is pending if the targeted peer is not currently connected
     /// and these requests are sent as soon as a connection to the peer is established.
     pending_rpcs: SmallVec<[(PeerId, HandlerIn); K_VALUE.get()]>,
+    /// The results of a lookup to write back to the path, see [`Config::set_path_caching`].
+    write_back: WriteBack,
+    /// See [`QueryOptions::with_record_validator`].
+    record_validator: Option<RecordValidator>,
 }
 
 impl QueryInner {
@@ -3113,6 +3744,33 @@ impl QueryInner {
             info,
             addresses: Default::default(),
             pending_rpcs: SmallVec::default(),
+            write_back: Default::default(),
+            record_validator: None,
+        }
+    }
+}
+
+/// The results of a [`QueryInfo::GetRecord`] or [`QueryInfo::GetProviders`] lookup to write back
+/// to the closest peers that did not return any.
+#[derive(Default)]
+struct WriteBack {
+    /// The first record found.
+    record: Option<Record>,
+    /// The provider records found.
+    providers: Vec<KadPeer>,
+    /// The peers closest to the key that were queried but did not return a record,
+    /// respectively any provider records.
+    candidates: BTreeMap<kbucket::Distance, PeerId>,
+}
+
+impl WriteBack {
+    /// Tracks `peer` as candidate for the write-back, keeping the `max_peers` closest to `key`.
+    fn add_candidate(&mut self, peer: PeerId, key: &record::Key, max_peers: usize) {
+        let distance = kbucket::Key::from(peer).distance(&kbucket::Key::new(key.clone()));
+        self.candidates.insert(distance, peer);
+        if self.candidates.len() > max_peers {
+            let last = *self.candidates.keys().next_back().expect("len > 0");
+            self.candidates.remove(&last);
         }
     }
 }
@@ -3125,6 +3783,9 @@ pub enum AddProviderContext {
     /// The context is periodic republishing of provider announcements
     /// initiated earlier via [`Behaviour::start_providing`].
     Republish,
+    /// The context is writing back provider records found by [`Behaviour::get_providers`]
+    /// to the path of the lookup, see [`Config::set_path_caching`].
+    Cache,
 }
 
 /// The context of a [`QueryInfo::PutRecord`] query.
@@ -3141,6 +3802,9 @@ pub enum PutRecordContext {
     /// The context is a custom store operation targeting specific
     /// peers initiated by [`Behaviour::put_record_to`].
     Custom,
+    /// The context is writing back a record found by [`Behaviour::get_record`]
+    /// to the path of the lookup, see [`Config::set_path_caching`].
+    Cache,
 }
 
 /// Information about a running query.
@@ -3205,8 +3869,10 @@ pub enum QueryInfo {
         key: record::Key,
         /// Current index of events.
         step: ProgressStep,
-        /// Did we find at least one record?
-        found_a_record: bool,
+        /// The number of records found so far.
+        records_found: usize,
+        /// The number of records after which the query finishes, if any.
+        quorum: Option<NonZeroUsize>,
         /// The peers closest to the `key` that were queried but did not return a record,
         /// i.e. the peers that are candidates for caching the record.
         cache_candidates: BTreeMap<kbucket::Distance, PeerId>,
@@ -3366,6 +4032,19 @@ impl fmt::Display for NoKnownPeers {
 
 impl std::error::Error for NoKnownPeers {}
 
+/// The reason for an [`Event::PeerEvicted`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionReason {
+    /// The peer was the least recently connected peer of a full bucket, was
+    /// disconnected and did not respond, and was thus replaced by the given peer
+    /// that was pending insertion into the bucket.
+    Replaced { by: PeerId },
+    /// The peer was removed with [`Behaviour::remove_peer`].
+    Removed,
+    /// The last address of the peer was removed with [`Behaviour::remove_address`].
+    NoAddresses,
+}
+
 /// The possible outcomes of [`Behaviour::add_address`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RoutingUpdate {
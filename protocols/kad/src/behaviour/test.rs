@@ -800,6 +800,150 @@ fn get_record() {
     }))
 }
 
+#[test]
+fn get_record_with_quorum() {
+    let mut swarms = build_nodes(3);
+
+    // Let first peer know of second peer and second peer know of third peer.
+    for i in 0..2 {
+        let (peer_id, address) = (
+            *Swarm::local_peer_id(&swarms[i + 1].1),
+            swarms[i + 1].0.clone(),
+        );
+        swarms[i].1.behaviour_mut().add_address(&peer_id, address);
+    }
+
+    // Drop the swarm addresses.
+    let mut swarms = swarms
+        .into_iter()
+        .map(|(_addr, swarm)| swarm)
+        .collect::<Vec<_>>();
+
+    let record = Record::new(random_multihash(), vec![4, 5, 6]);
+
+    swarms[1].behaviour_mut().store.put(record.clone()).unwrap();
+    swarms[2].behaviour_mut().store.put(record.clone()).unwrap();
+    let qid = swarms[0].behaviour_mut().get_record_with_options(
+        record.key.clone(),
+        QueryOptions::new().with_quorum(Quorum::One),
+    );
+
+    block_on(poll_fn(move |ctx| {
+        for swarm in &mut swarms {
+            loop {
+                match swarm.poll_next_unpin(ctx) {
+                    Poll::Ready(Some(SwarmEvent::Behaviour(Event::OutboundQueryProgressed {
+                        id,
+                        result: QueryResult::GetRecord(Ok(r)),
+                        step: ProgressStep { count, last },
+                        stats,
+                    }))) => {
+                        assert_eq!(id, qid);
+                        if usize::from(count) == 1 {
+                            assert!(!last);
+                            assert!(matches!(r, GetRecordOk::FoundRecord(_)));
+                        } else {
+                            assert!(last);
+                            assert_eq!(usize::from(count), 2);
+                            assert!(matches!(
+                                r,
+                                GetRecordOk::FinishedWithNoAdditionalRecord { .. }
+                            ));
+                            // The third peer is not queried once the quorum is reached.
+                            assert_eq!(stats.num_requests(), 1);
+                            return Poll::Ready(());
+                        }
+                    }
+                    // Ignore any other event.
+                    Poll::Ready(Some(_)) => (),
+                    e @ Poll::Ready(_) => panic!("Unexpected return value: {e:?}"),
+                    Poll::Pending => break,
+                }
+            }
+        }
+
+        Poll::Pending
+    }))
+}
+
+#[test]
+fn get_record_with_validator() {
+    let mut swarms = build_nodes(3);
+
+    // Let first peer know of second peer and second peer know of third peer.
+    for i in 0..2 {
+        let (peer_id, address) = (
+            *Swarm::local_peer_id(&swarms[i + 1].1),
+            swarms[i + 1].0.clone(),
+        );
+        swarms[i].1.behaviour_mut().add_address(&peer_id, address);
+    }
+
+    // Drop the swarm addresses.
+    let mut swarms = swarms
+        .into_iter()
+        .map(|(_addr, swarm)| swarm)
+        .collect::<Vec<_>>();
+
+    let key = record::Key::from(random_multihash());
+    let invalid = Record::new(key.clone(), vec![0]);
+    let valid = Record::new(key.clone(), vec![1]);
+    swarms[1]
+        .behaviour_mut()
+        .store
+        .put(invalid.clone())
+        .unwrap();
+    swarms[2].behaviour_mut().store.put(valid.clone()).unwrap();
+    let (invalid_peer, valid_peer) = (*swarms[1].local_peer_id(), *swarms[2].local_peer_id());
+
+    let qid = swarms[0].behaviour_mut().get_record_with_options(
+        key,
+        QueryOptions::new()
+            .with_quorum(Quorum::One)
+            .with_record_validator(|record| record.value == [1]),
+    );
+
+    let mut inbound = Vec::new();
+    let mut found = Vec::new();
+    block_on(poll_fn(|ctx| {
+        for swarm in &mut swarms {
+            while let Poll::Ready(Some(event)) = swarm.poll_next_unpin(ctx) {
+                match event {
+                    SwarmEvent::Behaviour(Event::InboundRecordFound { id, record, valid }) => {
+                        assert_eq!(id, qid);
+                        inbound.push((record.peer, valid));
+                    }
+                    SwarmEvent::Behaviour(Event::OutboundQueryProgressed {
+                        id,
+                        result: QueryResult::GetRecord(Ok(ok)),
+                        step,
+                        ..
+                    }) => {
+                        assert_eq!(id, qid);
+                        match ok {
+                            GetRecordOk::FoundRecord(record) => found.push(record),
+                            GetRecordOk::FinishedWithNoAdditionalRecord { .. } => {
+                                assert!(step.last);
+                                return Poll::Ready(());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Poll::Pending
+    }));
+
+    assert_eq!(
+        inbound,
+        vec![(Some(invalid_peer), false), (Some(valid_peer), true)]
+    );
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].peer, Some(valid_peer));
+    assert_eq!(found[0].record, valid);
+}
+
 #[test]
 fn get_record_many() {
     // TODO: Randomise
@@ -1234,10 +1378,11 @@ fn disjoint_query_does_not_finish_before_all_paths_did() {
     }));
 
     assert_eq!(1, records.len());
-    assert!(records.contains(&PeerRecord {
-        peer: Some(*Swarm::local_peer_id(&bob)),
-        record: record_bob,
-    }));
+    assert!(records
+        .iter()
+        .any(|r| r.peer == Some(*Swarm::local_peer_id(&bob))
+            && r.record == record_bob
+            && r.path.is_some()));
 }
 
 /// Tests that peers are not automatically inserted into
@@ -1533,3 +1678,131 @@ fn get_providers_limit_n_2() {
 fn get_providers_limit_n_5() {
     get_providers_limit::<5>();
 }
+
+#[test]
+fn get_providers_writes_back_to_path_cache() {
+    let mut cfg = Config::new(PROTOCOL_NAME);
+    cfg.set_path_caching(Some(PathCacheConfig::default()));
+    let mut swarms = build_nodes_with_config(3, cfg);
+
+    // Let first peer know of second peer and second peer know of third peer.
+    for i in 0..2 {
+        let (peer_id, address) = (
+            *Swarm::local_peer_id(&swarms[i + 1].1),
+            swarms[i + 1].0.clone(),
+        );
+        swarms[i].1.behaviour_mut().add_address(&peer_id, address);
+    }
+
+    // Drop the swarm addresses.
+    let mut swarms = swarms
+        .into_iter()
+        .map(|(_addr, swarm)| swarm)
+        .collect::<Vec<_>>();
+
+    let key = record::Key::from(random_multihash());
+    swarms[2]
+        .behaviour_mut()
+        .start_providing(key.clone())
+        .expect("could not provide");
+
+    // The second peer does not return the provider record found by the first lookup,
+    // hence the record is written back to it.
+    swarms[0].behaviour_mut().get_providers(key.clone());
+    drive_until(&mut swarms, |i, event| {
+        matches!(
+            event,
+            Event::InboundRequest {
+                request: InboundRequest::AddProvider { record: None }
+            } if i == 1
+        )
+        .then_some(())
+    });
+    assert!(swarms[1].behaviour_mut().store.providers(&key).is_empty());
+
+    // A second lookup is answered from the cache of the second peer.
+    swarms[0].behaviour_mut().get_providers(key.clone());
+    let (served_key, entry) = drive_until(&mut swarms, |i, event| match event {
+        Event::ServedFromCache { key, entry, .. } if i == 1 => Some((key, entry)),
+        _ => None,
+    });
+    assert_eq!(served_key, key);
+    assert_eq!(entry, CachedEntry::Providers { num_providers: 1 });
+}
+
+/// Polls all swarms until `f` returns `Some` for a behaviour event of one of them.
+fn drive_until<T>(swarms: &mut [TestSwarm], mut f: impl FnMut(usize, Event) -> Option<T>) -> T {
+    block_on(poll_fn(|ctx| {
+        for (i, swarm) in swarms.iter_mut().enumerate() {
+            while let Poll::Ready(Some(event)) = swarm.poll_next_unpin(ctx) {
+                if let SwarmEvent::Behaviour(event) = event {
+                    if let Some(result) = f(i, event) {
+                        return Poll::Ready(result);
+                    }
+                }
+            }
+        }
+        Poll::Pending
+    }))
+}
+
+#[test]
+fn routing_table_export_and_import() {
+    let (_, mut swarm) = build_node();
+    let peers = (0..10)
+        .map(|i| {
+            let peer = PeerId::random();
+            let address: Multiaddr = format!("/ip4/127.0.0.1/tcp/{}", 1000 + i).parse().unwrap();
+            swarm.behaviour_mut().add_address(&peer, address);
+            peer
+        })
+        .collect::<HashSet<_>>();
+
+    let exported = swarm.behaviour_mut().export_routing_table();
+    assert_eq!(exported.len(), peers.len());
+    assert!(exported
+        .iter()
+        .all(|(peer, addresses)| peers.contains(peer) && addresses.len() == 1));
+
+    let (_, mut restored) = build_node();
+    assert_eq!(
+        restored
+            .behaviour_mut()
+            .import_routing_table(exported.clone()),
+        peers.len()
+    );
+    let mut reexported = restored.behaviour_mut().export_routing_table();
+    let mut exported = exported;
+    exported.sort_by_key(|(peer, _)| *peer);
+    reexported.sort_by_key(|(peer, _)| *peer);
+    assert_eq!(exported, reexported);
+}
+
+#[test]
+fn removing_peer_emits_eviction() {
+    let (_, mut swarm) = build_node();
+    let peer = PeerId::random();
+    let address: Multiaddr = "/ip4/127.0.0.1/tcp/1000".parse().unwrap();
+    swarm.behaviour_mut().add_address(&peer, address.clone());
+    assert!(swarm
+        .behaviour_mut()
+        .remove_address(&peer, &address)
+        .is_some());
+
+    block_on(poll_fn(move |ctx| loop {
+        match swarm.poll_next_unpin(ctx) {
+            Poll::Ready(Some(SwarmEvent::Behaviour(Event::PeerEvicted {
+                peer: evicted,
+                reason,
+                ..
+            }))) => {
+                assert_eq!(evicted, peer);
+                assert_eq!(reason, EvictionReason::NoAddresses);
+                return Poll::Ready(());
+            }
+            Poll::Ready(Some(_)) => {}
+            Poll::Ready(None) => panic!("Swarm terminated"),
+            Poll::Pending => return Poll::Pending,
+        }
+    }))
+}
@@ -0,0 +1,470 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Crawling of the network via a Kademlia [`Behaviour`](crate::Behaviour).
+//!
+//! A crawl repeatedly looks up the closest peers to random keys and records every peer it learns
+//! about, together with its addresses. Combined with `libp2p-identify`, the agent versions of the
+//! peers are recorded as well, see [`Behaviour::on_identify`].
+//!
+//! The crawl finishes once a configured number of lookups has been made or the lookups stopped
+//! discovering new peers, reported via [`Event::Finished`] with a [`Report`] of all discovered
+//! peers.
+
+use crate::record::store::RecordStore;
+use crate::{GetClosestPeersError, GetClosestPeersOk, NoKnownPeers, QueryId, QueryResult};
+use instant::Instant;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::{
+    ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::num::NonZeroUsize;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// The configuration of a crawl.
+#[derive(Debug, Clone)]
+pub struct Config {
+    parallelism: NonZeroUsize,
+    max_queries: Option<NonZeroUsize>,
+    max_unproductive_queries: NonZeroUsize,
+}
+
+impl Config {
+    /// Sets the number of lookups running at the same time.
+    ///
+    /// * Default to `3`.
+    pub fn with_parallelism(mut self, parallelism: NonZeroUsize) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Sets the number of lookups after which the crawl finishes.
+    ///
+    /// * Default to no limit.
+    pub fn with_max_queries(mut self, max_queries: NonZeroUsize) -> Self {
+        self.max_queries = Some(max_queries);
+        self
+    }
+
+    /// Sets the number of consecutive lookups not discovering any new peer after which the
+    /// crawl finishes.
+    ///
+    /// * Default to `10`.
+    pub fn with_max_unproductive_queries(mut self, max_unproductive_queries: NonZeroUsize) -> Self {
+        self.max_unproductive_queries = max_unproductive_queries;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            parallelism: NonZeroUsize::new(3).expect("3 > 0"),
+            max_queries: None,
+            max_unproductive_queries: NonZeroUsize::new(10).expect("10 > 0"),
+        }
+    }
+}
+
+/// A peer discovered during a crawl.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrawledPeer {
+    /// The addresses of the peer.
+    pub addresses: Vec<Multiaddr>,
+    /// The agent version of the peer, if reported via [`Behaviour::on_identify`].
+    pub agent_version: Option<String>,
+    /// When the peer was discovered.
+    pub discovered_at: Instant,
+}
+
+/// The result of a crawl.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// All discovered peers.
+    pub peers: HashMap<PeerId, CrawledPeer>,
+    /// The number of lookups made.
+    pub queries: usize,
+    /// How long the crawl took.
+    pub duration: Duration,
+}
+
+/// The events produced by the crawler [`Behaviour`].
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum Event {
+    /// An event of the wrapped Kademlia behaviour, including those of the lookups of the crawl.
+    Kad(crate::Event),
+    /// A lookup of the crawl has finished.
+    Progress {
+        /// The number of finished lookups so far.
+        queries_finished: usize,
+        /// The number of peers discovered so far.
+        peers: usize,
+        /// The number of peers discovered since the previous lookup finished.
+        new_peers: usize,
+    },
+    /// The crawl has finished.
+    Finished(Report),
+}
+
+/// A [`NetworkBehaviour`] wrapping a Kademlia [`Behaviour`](crate::Behaviour) to crawl the
+/// network.
+///
+/// Outside of a crawl, it behaves like the wrapped behaviour, which is accessible via
+/// [`Behaviour::kad`] and [`Behaviour::kad_mut`].
+pub struct Behaviour<TStore> {
+    kad: crate::Behaviour<TStore>,
+    config: Config,
+    crawl: Option<Crawl>,
+    pending_events: VecDeque<Event>,
+}
+
+#[derive(Debug)]
+struct Crawl {
+    started_at: Instant,
+    peers: HashMap<PeerId, CrawledPeer>,
+    queries: HashSet<QueryId>,
+    queries_started: usize,
+    queries_finished: usize,
+    unproductive_queries: usize,
+    peers_at_last_query: usize,
+}
+
+impl Crawl {
+    fn record(&mut self, peer: PeerId, addresses: impl IntoIterator<Item = Multiaddr>) {
+        let crawled = self.peers.entry(peer).or_insert_with(|| CrawledPeer {
+            addresses: Vec::new(),
+            agent_version: None,
+            discovered_at: Instant::now(),
+        });
+        for address in addresses {
+            if !crawled.addresses.contains(&address) {
+                crawled.addresses.push(address);
+            }
+        }
+    }
+}
+
+impl<TStore> Behaviour<TStore>
+where
+    TStore: RecordStore + Send + 'static,
+{
+    /// Wraps the given Kademlia behaviour.
+    pub fn new(kad: crate::Behaviour<TStore>, config: Config) -> Self {
+        Self {
+            kad,
+            config,
+            crawl: None,
+            pending_events: VecDeque::new(),
+        }
+    }
+
+    /// The wrapped Kademlia behaviour.
+    pub fn kad(&self) -> &crate::Behaviour<TStore> {
+        &self.kad
+    }
+
+    /// The wrapped Kademlia behaviour.
+    pub fn kad_mut(&mut self) -> &mut crate::Behaviour<TStore> {
+        &mut self.kad
+    }
+
+    /// Starts a crawl, replacing the current one, if any.
+    ///
+    /// Returns an error if the routing table is empty.
+    pub fn start(&mut self) -> Result<(), NoKnownPeers> {
+        if self.kad.kbuckets().next().is_none() {
+            return Err(NoKnownPeers());
+        }
+
+        self.crawl = Some(Crawl {
+            started_at: Instant::now(),
+            peers: HashMap::new(),
+            queries: HashSet::new(),
+            queries_started: 0,
+            queries_finished: 0,
+            unproductive_queries: 0,
+            peers_at_last_query: 0,
+        });
+        self.start_queries();
+
+        Ok(())
+    }
+
+    /// Whether a crawl is in progress.
+    pub fn is_crawling(&self) -> bool {
+        self.crawl.is_some()
+    }
+
+    /// Records the information a peer reported about itself via `libp2p-identify`.
+    ///
+    /// Has no effect unless a crawl is in progress.
+    pub fn on_identify(
+        &mut self,
+        peer: PeerId,
+        agent_version: String,
+        listen_addrs: impl IntoIterator<Item = Multiaddr>,
+    ) {
+        if let Some(crawl) = self.crawl.as_mut() {
+            crawl.record(peer, listen_addrs);
+            if let Some(crawled) = crawl.peers.get_mut(&peer) {
+                crawled.agent_version = Some(agent_version);
+            }
+        }
+    }
+
+    /// Starts as many lookups as allowed by the configuration.
+    fn start_queries(&mut self) {
+        let Some(crawl) = self.crawl.as_mut() else {
+            return;
+        };
+
+        while crawl.queries.len() < self.config.parallelism.get()
+            && crawl.unproductive_queries < self.config.max_unproductive_queries.get()
+            && self
+                .config
+                .max_queries
+                .map_or(true, |max| crawl.queries_started < max.get())
+        {
+            let id = self.kad.get_closest_peers(PeerId::random());
+            crawl.queries.insert(id);
+            crawl.queries_started += 1;
+        }
+
+        if crawl.queries.is_empty() {
+            let crawl = self.crawl.take().expect("crawl to be in progress");
+            self.pending_events.push_back(Event::Finished(Report {
+                peers: crawl.peers,
+                queries: crawl.queries_finished,
+                duration: crawl.started_at.elapsed(),
+            }));
+        }
+    }
+
+    fn on_kad_event(&mut self, event: &crate::Event) {
+        let Some(crawl) = self.crawl.as_mut() else {
+            return;
+        };
+
+        match event {
+            crate::Event::RoutingUpdated {
+                peer, addresses, ..
+            } => crawl.record(*peer, addresses.iter().cloned()),
+            crate::Event::RoutablePeer { peer, address }
+            | crate::Event::PendingRoutablePeer { peer, address } => {
+                crawl.record(*peer, [address.clone()])
+            }
+            crate::Event::OutboundQueryProgressed {
+                id,
+                result: QueryResult::GetClosestPeers(result),
+                step,
+                ..
+            } if crawl.queries.contains(id) => {
+                let (Ok(GetClosestPeersOk { peers, .. })
+                | Err(GetClosestPeersError::Timeout { peers, .. })) = result;
+                for peer in peers {
+                    crawl.record(*peer, []);
+                }
+
+                if !step.last {
+                    return;
+                }
+
+                crawl.queries.remove(id);
+                crawl.queries_finished += 1;
+                let new_peers = crawl.peers.len() - crawl.peers_at_last_query;
+                crawl.peers_at_last_query = crawl.peers.len();
+                if new_peers == 0 {
+                    crawl.unproductive_queries += 1;
+                } else {
+                    crawl.unproductive_queries = 0;
+                }
+
+                self.pending_events.push_back(Event::Progress {
+                    queries_finished: crawl.queries_finished,
+                    peers: crawl.peers.len(),
+                    new_peers,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<TStore> NetworkBehaviour for Behaviour<TStore>
+where
+    TStore: RecordStore + Send + 'static,
+{
+    type ConnectionHandler = <crate::Behaviour<TStore> as NetworkBehaviour>::ConnectionHandler;
+    type ToSwarm = Event;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.kad
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.kad
+            .handle_established_inbound_connection(connection_id, peer, local_addr, remote_addr)
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        self.kad.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.kad
+            .handle_established_outbound_connection(connection_id, peer, addr, role_override)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        self.kad.on_swarm_event(event)
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.kad
+            .on_connection_handler_event(peer_id, connection_id, event)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        loop {
+            if let Some(event) = self.pending_events.pop_front() {
+                return Poll::Ready(ToSwarm::GenerateEvent(event));
+            }
+
+            self.start_queries();
+            if !self.pending_events.is_empty() {
+                continue;
+            }
+
+            return match self.kad.poll(cx) {
+                Poll::Ready(ToSwarm::GenerateEvent(event)) => {
+                    self.on_kad_event(&event);
+                    Poll::Ready(ToSwarm::GenerateEvent(Event::Kad(event)))
+                }
+                Poll::Ready(other) => Poll::Ready(other.map_out(Event::Kad)),
+                Poll::Pending => Poll::Pending,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use crate::Mode;
+    use futures::StreamExt;
+    use libp2p_swarm::{Swarm, SwarmEvent};
+    use libp2p_swarm_test::SwarmExt;
+
+    fn new_kad(peer: PeerId) -> crate::Behaviour<MemoryStore> {
+        let mut kad = crate::Behaviour::new(peer, MemoryStore::new(peer));
+        kad.set_mode(Some(Mode::Server));
+        kad
+    }
+
+    #[async_std::test]
+    async fn crawl_discovers_all_peers() {
+        let mut servers = Vec::new();
+        let mut peers = Vec::new();
+        for _ in 0..4 {
+            let mut server = Swarm::new_ephemeral(|key| new_kad(key.public().to_peer_id()));
+            let (address, _) = server.listen().with_memory_addr_external().await;
+            peers.push((*server.local_peer_id(), address));
+            servers.push(server);
+        }
+
+        // Line up the servers such that each only knows the next one.
+        for (server, (peer, address)) in servers.iter_mut().zip(peers.iter().skip(1)) {
+            server.behaviour_mut().add_address(peer, address.clone());
+        }
+        for server in servers {
+            async_std::task::spawn(server.loop_on_next());
+        }
+
+        let mut crawler = Swarm::new_ephemeral(|key| {
+            let peer = key.public().to_peer_id();
+            Behaviour::new(
+                crate::Behaviour::new(peer, MemoryStore::new(peer)),
+                Config::default().with_max_unproductive_queries(NonZeroUsize::new(2).unwrap()),
+            )
+        });
+        let (first, first_address) = peers[0].clone();
+        crawler
+            .behaviour_mut()
+            .kad_mut()
+            .add_address(&first, first_address);
+        crawler.behaviour_mut().start().unwrap();
+
+        let report = loop {
+            if let SwarmEvent::Behaviour(Event::Finished(report)) = crawler.select_next_some().await
+            {
+                break report;
+            }
+        };
+
+        let discovered: HashSet<PeerId> = report.peers.keys().copied().collect();
+        let expected: HashSet<PeerId> = peers.iter().map(|(peer, _)| *peer).collect();
+        assert_eq!(discovered, expected);
+        assert!(report.peers.values().all(|peer| !peer.addresses.is_empty()));
+        assert!(!crawler.behaviour().is_crawling());
+    }
+}
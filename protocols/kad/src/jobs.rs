@@ -66,7 +66,7 @@ use futures::prelude::*;
 use futures_timer::Delay;
 use instant::Instant;
 use libp2p_identity::PeerId;
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Duration;
@@ -106,6 +106,14 @@ impl<T> PeriodicJob<T> {
         }
     }
 
+    /// Schedules the first run of a job that is waiting for its first
+    /// delay to expire right away.
+    fn start_now(&mut self) {
+        if let PeriodicJobState::Waiting(..) = self.state {
+            self.state = PeriodicJobState::Waiting(Delay::new(Duration::ZERO), Instant::now());
+        }
+    }
+
     /// Returns `true` if the job is currently not running but ready
     /// to be run, `false` otherwise.
     fn check_ready(&mut self, cx: &mut Context<'_>, now: Instant) -> bool {
@@ -135,6 +143,8 @@ pub(crate) struct PutRecordJob {
     publish_interval: Option<Duration>,
     record_ttl: Option<Duration>,
     skipped: HashSet<record::Key>,
+    /// The records removed from the store because they expired.
+    expired: VecDeque<Record>,
     inner: PeriodicJob<vec::IntoIter<Record>>,
 }
 
@@ -157,6 +167,7 @@ impl PutRecordJob {
             publish_interval,
             record_ttl,
             skipped: HashSet::new(),
+            expired: VecDeque::new(),
             inner: PeriodicJob {
                 interval: replicate_interval,
                 state: PeriodicJobState::Waiting(delay, deadline),
@@ -176,6 +187,11 @@ impl PutRecordJob {
         self.inner.is_running()
     }
 
+    /// Removes the next record the job removed from the store because it expired.
+    pub(crate) fn pop_expired(&mut self) -> Option<Record> {
+        self.expired.pop_front()
+    }
+
     /// Cuts short the remaining delay, if the job is currently waiting
     /// for the delay to expire.
     ///
@@ -188,6 +204,18 @@ impl PutRecordJob {
         self.inner.asap()
     }
 
+    /// Schedules the first run of the job right away instead of after a full
+    /// replication interval, e.g. for records already in the store on startup.
+    ///
+    /// If `publish` is `true`, the first run also re-publishes the records of
+    /// the local node.
+    pub(crate) fn start_now(&mut self, publish: bool) {
+        if publish && self.publish_interval.is_some() {
+            self.next_publish = Some(Instant::now());
+        }
+        self.inner.start_now()
+    }
+
     /// Polls the job for records to replicate.
     ///
     /// Must be called in the context of a task. When `NotReady` is returned,
@@ -204,25 +232,44 @@ impl PutRecordJob {
     {
         if self.inner.check_ready(cx, now) {
             let publish = self.next_publish.map_or(false, |t_pub| now >= t_pub);
+            let mut refreshed = Vec::new();
             let records = store
                 .records()
                 .filter_map(|r| {
                     let is_publisher = r.publisher.as_ref() == Some(&self.local_id);
                     if self.skipped.contains(&r.key) || (!publish && is_publisher) {
-                        None
-                    } else {
-                        let mut record = r.into_owned();
-                        if publish && is_publisher {
-                            record.expires = record
-                                .expires
-                                .or_else(|| self.record_ttl.map(|ttl| now + ttl));
+                        return None;
+                    }
+                    if !r.is_expired(now) && !store.should_republish(&r) {
+                        return None;
+                    }
+                    let mut record = r.into_owned();
+                    if publish && is_publisher {
+                        match record.ttl {
+                            Some(ttl) if !record.is_expired(now) => {
+                                record.expires = Some(now + ttl);
+                                refreshed.push(record.clone());
+                            }
+                            _ => {
+                                record.expires = record
+                                    .expires
+                                    .or_else(|| self.record_ttl.map(|ttl| now + ttl));
+                            }
                         }
-                        Some(record)
                     }
+                    Some(record)
                 })
                 .collect::<Vec<_>>()
                 .into_iter();
 
+            // Records with a TTL expire later locally too, once re-published.
+            for record in refreshed {
+                let key = record.key.clone();
+                if let Err(e) = store.put(record) {
+                    tracing::warn!(record=?key, "Failed to refresh expiration of record: {e}");
+                }
+            }
+
             // Schedule the next publishing run.
             if publish {
                 self.next_publish = self.publish_interval.map(|i| now + i);
@@ -236,7 +283,8 @@ impl PutRecordJob {
         if let PeriodicJobState::Running(records) = &mut self.inner.state {
             for r in records {
                 if r.is_expired(now) {
-                    store.remove(&r.key)
+                    store.remove(&r.key);
+                    self.expired.push_back(r);
                 } else {
                     return Poll::Ready(r);
                 }
@@ -291,6 +339,12 @@ impl AddProviderJob {
         self.inner.asap()
     }
 
+    /// Schedules the first run of the job right away instead of after a full
+    /// publication interval, e.g. for provider records already in the store on startup.
+    pub(crate) fn start_now(&mut self) {
+        self.inner.start_now()
+    }
+
     /// Polls the job for provider records to replicate.
     ///
     /// Must be called in the context of a task. When `NotReady` is returned,
@@ -392,6 +446,39 @@ mod tests {
         quickcheck(prop as fn(_))
     }
 
+    #[test]
+    fn republish_refreshes_ttl_and_reports_expired_records() {
+        let id = PeerId::random();
+        let interval = Duration::from_secs(1);
+        let mut job = PutRecordJob::new(id, interval, Some(interval), None);
+        let mut store = MemoryStore::new(id);
+
+        let start = Instant::now();
+        let mut published = Record::new(vec![1], vec![]);
+        published.publisher = Some(id);
+        published.ttl = Some(Duration::from_secs(60));
+        published.expires = Some(start + Duration::from_secs(5));
+        store.put(published.clone()).unwrap();
+        let mut expiring = Record::new(vec![2], vec![]);
+        expiring.expires = Some(start);
+        store.put(expiring.clone()).unwrap();
+
+        block_on(poll_fn(|ctx| {
+            let now = start + interval;
+            let Poll::Ready(record) = job.poll(ctx, &mut store, now) else {
+                panic!("Expected the published record to be re-published");
+            };
+            assert_eq!(record.key, published.key);
+            assert_eq!(record.expires, Some(now + Duration::from_secs(60)));
+            assert_eq!(store.get(&published.key).unwrap().expires, record.expires);
+
+            assert_eq!(job.poll(ctx, &mut store, now), Poll::Pending);
+            assert_eq!(job.pop_expired(), Some(expiring.clone()));
+            assert!(store.get(&expiring.key).is_none());
+            Poll::Ready(())
+        }));
+    }
+
     #[test]
     fn run_add_provider_job() {
         fn prop(records: Vec<ProviderRecord>) {
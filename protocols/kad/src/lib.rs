@@ -38,9 +38,12 @@
 mod addresses;
 mod behaviour;
 mod bootstrap;
+pub mod crawler;
 mod handler;
 mod jobs;
 mod kbucket;
+mod load_shedding;
+mod path_cache;
 mod protocol;
 mod query;
 mod record;
@@ -57,11 +60,11 @@ mod proto {
 pub use addresses::Addresses;
 pub use behaviour::{
     AddProviderContext, AddProviderError, AddProviderOk, AddProviderPhase, AddProviderResult,
-    BootstrapError, BootstrapOk, BootstrapResult, GetClosestPeersError, GetClosestPeersOk,
-    GetClosestPeersResult, GetProvidersError, GetProvidersOk, GetProvidersResult, GetRecordError,
-    GetRecordOk, GetRecordResult, InboundRequest, Mode, NoKnownPeers, PeerRecord, PutRecordContext,
-    PutRecordError, PutRecordOk, PutRecordPhase, PutRecordResult, QueryInfo, QueryMut, QueryRef,
-    QueryResult, QueryStats, RoutingUpdate,
+    BootstrapError, BootstrapOk, BootstrapResult, EvictionReason, GetClosestPeersError,
+    GetClosestPeersOk, GetClosestPeersResult, GetProvidersError, GetProvidersOk,
+    GetProvidersResult, GetRecordError, GetRecordOk, GetRecordResult, InboundRequest, Mode,
+    NoKnownPeers, PeerRecord, PutRecordContext, PutRecordError, PutRecordOk, PutRecordPhase,
+    PutRecordResult, QueryInfo, QueryMut, QueryRef, QueryResult, QueryStats, RoutingUpdate,
 };
 pub use behaviour::{
     Behaviour, BucketInserts, Caching, Config, Event, ProgressStep, Quorum, StoreInserts,
@@ -69,8 +72,10 @@ pub use behaviour::{
 pub use kbucket::{
     Distance as KBucketDistance, EntryView, KBucketRef, Key as KBucketKey, NodeStatus,
 };
+pub use load_shedding::{InboundCost, InboundLimits, ThrottleReason};
+pub use path_cache::{CachedEntry, PathCacheConfig};
 pub use protocol::ConnectionType;
-pub use query::QueryId;
+pub use query::{AdaptiveParallelism, QueryId, QueryOptions};
 pub use record::{store, Key as RecordKey, ProviderRecord, Record};
 
 use libp2p_swarm::StreamProtocol;
@@ -0,0 +1,238 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Accounting of the cost of inbound requests per peer, used by DHT servers to shed load.
+//!
+//! Costs are accounted in fixed windows: at the start of every window, the costs of all peers
+//! are reset.
+
+use crate::protocol::KadPeer;
+use crate::record::Record;
+use fnv::FnvHashMap;
+use instant::Instant;
+use libp2p_identity::PeerId;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+/// Limits on the inbound requests a peer can make within an accounting window.
+///
+/// Requests of a peer exceeding a limit are throttled: their stream is reset instead of being
+/// answered and [`Event::InboundRequestThrottled`](crate::Event::InboundRequestThrottled) is
+/// reported.
+///
+/// See [`Config::set_inbound_limits`](crate::Config::set_inbound_limits).
+#[derive(Debug, Clone)]
+pub struct InboundLimits {
+    window: Duration,
+    max_requests: Option<NonZeroUsize>,
+    max_bytes_served: Option<NonZeroUsize>,
+}
+
+impl InboundLimits {
+    /// Creates limits for accounting windows of the given duration, without any limit set.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            max_requests: None,
+            max_bytes_served: None,
+        }
+    }
+
+    /// Sets the maximum number of inbound requests of a peer per window.
+    pub fn with_max_requests(mut self, max_requests: NonZeroUsize) -> Self {
+        self.max_requests = Some(max_requests);
+        self
+    }
+
+    /// Sets the maximum number of bytes of provider records and records served to a peer per
+    /// window.
+    ///
+    /// Once reached, provider and record lookups of the peer are throttled while requests for
+    /// the closest peers to a key are still answered.
+    pub fn with_max_bytes_served(mut self, max_bytes_served: NonZeroUsize) -> Self {
+        self.max_bytes_served = Some(max_bytes_served);
+        self
+    }
+}
+
+impl Default for InboundLimits {
+    /// Accounting windows of 1 minute, without any limit set.
+    fn default() -> Self {
+        Self::new(Duration::from_secs(60))
+    }
+}
+
+/// The cost of the inbound requests of a peer within the current accounting window.
+///
+/// See [`Behaviour::inbound_cost`](crate::Behaviour::inbound_cost).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InboundCost {
+    /// The number of inbound requests, including throttled ones.
+    pub requests: usize,
+    /// The number of bytes of provider records and records served.
+    pub bytes_served: usize,
+}
+
+/// The limit that caused an inbound request to be throttled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleReason {
+    /// The peer made more requests than allowed via [`InboundLimits::with_max_requests`].
+    MaxRequests,
+    /// The peer was served more bytes than allowed via [`InboundLimits::with_max_bytes_served`].
+    MaxBytesServed,
+}
+
+#[derive(Debug)]
+pub(crate) struct InboundAccounting {
+    limits: InboundLimits,
+    window_start: Instant,
+    costs: FnvHashMap<PeerId, InboundCost>,
+}
+
+impl InboundAccounting {
+    pub(crate) fn new(limits: InboundLimits) -> Self {
+        Self {
+            limits,
+            window_start: Instant::now(),
+            costs: Default::default(),
+        }
+    }
+
+    /// Accounts for an inbound request of `peer`.
+    ///
+    /// `is_lookup` denotes a provider or record lookup, which is subject to
+    /// [`InboundLimits::with_max_bytes_served`].
+    pub(crate) fn on_request(
+        &mut self,
+        peer: PeerId,
+        is_lookup: bool,
+        now: Instant,
+    ) -> Result<(), ThrottleReason> {
+        self.roll_window(now);
+        let cost = self.costs.entry(peer).or_default();
+        cost.requests += 1;
+
+        if self
+            .limits
+            .max_requests
+            .is_some_and(|max| cost.requests > max.get())
+        {
+            return Err(ThrottleReason::MaxRequests);
+        }
+        if is_lookup
+            && self
+                .limits
+                .max_bytes_served
+                .is_some_and(|max| cost.bytes_served >= max.get())
+        {
+            return Err(ThrottleReason::MaxBytesServed);
+        }
+
+        Ok(())
+    }
+
+    /// Accounts for the bytes served to `peer` in response to a lookup.
+    pub(crate) fn on_served(&mut self, peer: PeerId, bytes: usize) {
+        if let Some(cost) = self.costs.get_mut(&peer) {
+            cost.bytes_served += bytes;
+        }
+    }
+
+    pub(crate) fn cost(&self, peer: &PeerId, now: Instant) -> Option<InboundCost> {
+        if self.window_expired(now) {
+            return None;
+        }
+        self.costs.get(peer).copied()
+    }
+
+    fn roll_window(&mut self, now: Instant) {
+        if self.window_expired(now) {
+            self.window_start = now;
+            self.costs.clear();
+        }
+    }
+
+    fn window_expired(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.window_start) >= self.limits.window
+    }
+}
+
+/// The number of bytes served with the given provider records.
+pub(crate) fn providers_size(providers: &[KadPeer]) -> usize {
+    providers
+        .iter()
+        .map(|provider| {
+            provider.node_id.to_bytes().len()
+                + provider
+                    .multiaddrs
+                    .iter()
+                    .map(|addr| addr.len())
+                    .sum::<usize>()
+        })
+        .sum()
+}
+
+/// The number of bytes served with the given record.
+pub(crate) fn record_size(record: &Record) -> usize {
+    record.key.as_ref().len()
+        + record.value.len()
+        + record
+            .publisher
+            .map_or(0, |publisher| publisher.to_bytes().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_throttled_within_window() {
+        let limits = InboundLimits::new(Duration::from_secs(60))
+            .with_max_requests(NonZeroUsize::new(2).unwrap())
+            .with_max_bytes_served(NonZeroUsize::new(100).unwrap());
+        let mut accounting = InboundAccounting::new(limits);
+        let peer = PeerId::random();
+        let other = PeerId::random();
+        let now = Instant::now();
+
+        assert_eq!(accounting.on_request(peer, true, now), Ok(()));
+        accounting.on_served(peer, 100);
+        assert_eq!(
+            accounting.on_request(peer, true, now),
+            Err(ThrottleReason::MaxBytesServed)
+        );
+        assert_eq!(
+            accounting.on_request(peer, false, now),
+            Err(ThrottleReason::MaxRequests)
+        );
+        assert_eq!(accounting.on_request(other, true, now), Ok(()));
+        assert_eq!(
+            accounting.cost(&peer, now),
+            Some(InboundCost {
+                requests: 3,
+                bytes_served: 100
+            })
+        );
+
+        let next_window = now + Duration::from_secs(60);
+        assert_eq!(accounting.cost(&peer, next_window), None);
+        assert_eq!(accounting.on_request(peer, true, next_window), Ok(()));
+    }
+}
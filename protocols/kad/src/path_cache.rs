@@ -0,0 +1,268 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Caching of records and provider records at the nodes along the path of a lookup, as
+//! described in the original Kademlia paper.
+//!
+//! Once a lookup succeeds, the records found are written back to the closest nodes queried that
+//! did not return them. These nodes keep the records in a cache of bounded size, separate from
+//! their [`RecordStore`](crate::store::RecordStore), and serve them from there until they expire.
+
+use crate::record::{Key, ProviderRecord, Record};
+use fnv::FnvHashMap;
+use instant::Instant;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+/// The configuration of the path cache, see [`Config::set_path_caching`](crate::Config::set_path_caching).
+#[derive(Debug, Clone)]
+pub struct PathCacheConfig {
+    max_peers: NonZeroUsize,
+    max_entries: NonZeroUsize,
+    ttl: Duration,
+}
+
+impl PathCacheConfig {
+    /// Sets the number of peers the results of a successful lookup are written back to.
+    ///
+    /// These are the peers closest to the key that were queried but did not return a record,
+    /// respectively any provider records.
+    pub fn with_max_peers(mut self, max_peers: NonZeroUsize) -> Self {
+        self.max_peers = max_peers;
+        self
+    }
+
+    /// Sets the maximum number of records and provider records in the cache.
+    ///
+    /// Once full, the entries expiring first are evicted.
+    pub fn with_max_entries(mut self, max_entries: NonZeroUsize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Sets the duration for which records and provider records are cached.
+    ///
+    /// Records expiring earlier are only cached until their own expiration.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+impl Default for PathCacheConfig {
+    /// Writing back to 1 peer and caching up to 1024 entries for 1 hour.
+    fn default() -> Self {
+        Self {
+            max_peers: NonZeroUsize::new(1).expect("1 > 0"),
+            max_entries: NonZeroUsize::new(1024).expect("1024 > 0"),
+            ttl: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// The kind of cache entry served to a remote, see
+/// [`Event::ServedFromCache`](crate::Event::ServedFromCache).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachedEntry {
+    /// The record of the key.
+    Record,
+    /// Provider records of the key, in addition to those in the record store.
+    Providers {
+        /// The number of provider records served from the cache.
+        num_providers: usize,
+    },
+}
+
+#[derive(Debug)]
+pub(crate) struct PathCache {
+    config: PathCacheConfig,
+    records: FnvHashMap<Key, Record>,
+    providers: FnvHashMap<Key, Vec<ProviderRecord>>,
+    len: usize,
+}
+
+impl PathCache {
+    pub(crate) fn new(config: PathCacheConfig) -> Self {
+        Self {
+            config,
+            records: Default::default(),
+            providers: Default::default(),
+            len: 0,
+        }
+    }
+
+    /// The number of peers the results of a lookup are written back to.
+    pub(crate) fn max_peers(&self) -> usize {
+        self.config.max_peers.get()
+    }
+
+    /// Caches a record, replacing a cached record of the same key.
+    pub(crate) fn put(&mut self, mut record: Record, now: Instant) {
+        record.expires = Some(self.expiration(record.expires, now));
+        if self.records.insert(record.key.clone(), record).is_none() {
+            self.len += 1;
+        }
+        self.evict();
+    }
+
+    /// Caches a provider record, replacing a cached record of the same provider.
+    pub(crate) fn add_provider(&mut self, mut record: ProviderRecord, now: Instant) {
+        record.expires = Some(self.expiration(record.expires, now));
+        let providers = self.providers.entry(record.key.clone()).or_default();
+        match providers.iter_mut().find(|p| p.provider == record.provider) {
+            Some(existing) => *existing = record,
+            None => {
+                providers.push(record);
+                self.len += 1;
+            }
+        }
+        self.evict();
+    }
+
+    /// Gets the cached record of the key, unless expired.
+    pub(crate) fn get(&mut self, key: &Key, now: Instant) -> Option<Record> {
+        let record = self.records.get(key)?;
+        if record.is_expired(now) {
+            self.records.remove(key);
+            self.len -= 1;
+            return None;
+        }
+
+        Some(record.clone())
+    }
+
+    /// Gets the cached provider records of the key, removing expired ones.
+    pub(crate) fn providers(&mut self, key: &Key, now: Instant) -> Vec<ProviderRecord> {
+        let Some(providers) = self.providers.get_mut(key) else {
+            return Vec::new();
+        };
+        let len = providers.len();
+        providers.retain(|p| !p.is_expired(now));
+        self.len -= len - providers.len();
+
+        let providers = providers.clone();
+        if providers.is_empty() {
+            self.providers.remove(key);
+        }
+        providers
+    }
+
+    fn expiration(&self, expires: Option<Instant>, now: Instant) -> Instant {
+        let expiration = now + self.config.ttl;
+        expires.map_or(expiration, |expires| expires.min(expiration))
+    }
+
+    /// Evicts the entries expiring first until the cache is within its size limit.
+    fn evict(&mut self) {
+        while self.len > self.config.max_entries.get() {
+            let record = self
+                .records
+                .values()
+                .min_by_key(|r| r.expires)
+                .map(|r| (r.expires, r.key.clone()));
+            let provider = self
+                .providers
+                .values()
+                .flatten()
+                .min_by_key(|p| p.expires)
+                .map(|p| (p.expires, p.key.clone(), p.provider));
+
+            match (record, provider) {
+                (Some((_, key)), None) => {
+                    self.records.remove(&key);
+                }
+                (Some((record_expires, key)), Some((provider_expires, _, _)))
+                    if record_expires <= provider_expires =>
+                {
+                    self.records.remove(&key);
+                }
+                (_, Some((_, key, provider))) => {
+                    let providers = self.providers.get_mut(&key).expect("key to be cached");
+                    providers.retain(|p| p.provider != provider);
+                    if providers.is_empty() {
+                        self.providers.remove(&key);
+                    }
+                }
+                (None, None) => unreachable!("cache to be non-empty"),
+            }
+            self.len -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_identity::PeerId;
+
+    fn config(max_entries: usize) -> PathCacheConfig {
+        PathCacheConfig::default()
+            .with_max_entries(NonZeroUsize::new(max_entries).unwrap())
+            .with_ttl(Duration::from_secs(10))
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let mut cache = PathCache::new(config(10));
+        let now = Instant::now();
+        let key = Key::new(&"key");
+        let record = Record::new(key.clone(), vec![1]);
+        let provider = ProviderRecord::new(key.clone(), PeerId::random(), Vec::new());
+
+        cache.put(record.clone(), now);
+        cache.add_provider(provider.clone(), now);
+        assert_eq!(cache.get(&key, now).unwrap().value, record.value);
+        assert_eq!(cache.providers(&key, now).len(), 1);
+
+        let later = now + Duration::from_secs(10);
+        assert!(cache.get(&key, later).is_none());
+        assert!(cache.providers(&key, later).is_empty());
+        assert_eq!(cache.len, 0);
+    }
+
+    #[test]
+    fn entries_expiring_first_are_evicted() {
+        let mut cache = PathCache::new(config(2));
+        let now = Instant::now();
+        let (first, second) = (Key::new(&"first"), Key::new(&"second"));
+
+        cache.put(Record::new(first.clone(), vec![1]), now);
+        cache.add_provider(
+            ProviderRecord::new(second.clone(), PeerId::random(), Vec::new()),
+            now + Duration::from_secs(1),
+        );
+        cache.add_provider(
+            ProviderRecord::new(second.clone(), PeerId::random(), Vec::new()),
+            now + Duration::from_secs(2),
+        );
+
+        assert!(cache.get(&first, now).is_none());
+        assert_eq!(cache.providers(&second, now).len(), 2);
+        assert_eq!(cache.len, 2);
+
+        let third = Key::new(&"third");
+        cache.put(
+            Record::new(third.clone(), vec![3]),
+            now + Duration::from_secs(3),
+        );
+        assert_eq!(cache.providers(&second, now).len(), 1);
+        assert!(cache.get(&third, now).is_some());
+    }
+}
@@ -559,7 +559,7 @@ fn proto_to_resp_msg(message: proto::Message) -> Result<KadResponseMsg, io::Erro
     }
 }
 
-fn record_from_proto(record: proto::Record) -> Result<Record, io::Error> {
+pub(crate) fn record_from_proto(record: proto::Record) -> Result<Record, io::Error> {
     let key = record::Key::from(record.key);
     let value = record.value;
 
@@ -582,10 +582,11 @@ fn record_from_proto(record: proto::Record) -> Result<Record, io::Error> {
         value,
         publisher,
         expires,
+        ttl: None,
     })
 }
 
-fn record_to_proto(record: Record) -> proto::Record {
+pub(crate) fn record_to_proto(record: Record) -> proto::Record {
     proto::Record {
         key: record.key.to_vec(),
         value: record.value,
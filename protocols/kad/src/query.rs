@@ -27,12 +27,12 @@ use peers::fixed::FixedPeersIter;
 use peers::PeersIterState;
 
 use crate::kbucket::{Key, KeyBytes};
-use crate::{ALPHA_VALUE, K_VALUE};
+use crate::{Quorum, Record, ALPHA_VALUE, K_VALUE};
 use either::Either;
 use fnv::FnvHashMap;
 use instant::Instant;
 use libp2p_identity::PeerId;
-use std::{num::NonZeroUsize, time::Duration};
+use std::{fmt, num::NonZeroUsize, sync::Arc, time::Duration};
 
 /// A `QueryPool` provides an aggregate state machine for driving `Query`s to completion.
 ///
@@ -108,7 +108,7 @@ impl<TInner> QueryPool<TInner> {
         assert!(!self.queries.contains_key(&id));
         let parallelism = self.config.replication_factor;
         let peer_iter = QueryPeerIter::Fixed(FixedPeersIter::new(peers, parallelism));
-        let query = Query::new(id, peer_iter, inner);
+        let query = Query::new(id, peer_iter, self.config.timeout, inner);
         self.queries.insert(id, query);
     }
 
@@ -123,6 +123,25 @@ impl<TInner> QueryPool<TInner> {
         id
     }
 
+    /// Adds a query to the pool that iterates towards the closest peers to the target,
+    /// overriding the pool's configuration with the given options.
+    pub(crate) fn add_iter_closest_with_options<T, I>(
+        &mut self,
+        target: T,
+        peers: I,
+        inner: TInner,
+        options: &QueryOptions,
+    ) -> QueryId
+    where
+        T: Into<KeyBytes> + Clone,
+        I: IntoIterator<Item = Key<PeerId>>,
+    {
+        let id = self.next_query_id();
+        let config = self.config.with_options(options);
+        self.insert_iter_closest(id, &config, target, peers, inner);
+        id
+    }
+
     /// Adds a query to the pool that iterates towards the closest peers to the target.
     pub(crate) fn continue_iter_closest<T, I>(
         &mut self,
@@ -133,22 +152,39 @@ impl<TInner> QueryPool<TInner> {
     ) where
         T: Into<KeyBytes> + Clone,
         I: IntoIterator<Item = Key<PeerId>>,
+    {
+        let config = self.config.clone();
+        self.insert_iter_closest(id, &config, target, peers, inner);
+    }
+
+    fn insert_iter_closest<T, I>(
+        &mut self,
+        id: QueryId,
+        config: &QueryConfig,
+        target: T,
+        peers: I,
+        inner: TInner,
+    ) where
+        T: Into<KeyBytes> + Clone,
+        I: IntoIterator<Item = Key<PeerId>>,
     {
         let cfg = ClosestPeersIterConfig {
-            num_results: self.config.replication_factor,
-            parallelism: self.config.parallelism,
+            num_results: config.replication_factor,
+            parallelism: config.parallelism,
+            adaptive_parallelism: config.adaptive_parallelism,
             ..ClosestPeersIterConfig::default()
         };
 
-        let peer_iter = if self.config.disjoint_query_paths {
-            QueryPeerIter::ClosestDisjoint(ClosestDisjointPeersIter::with_config(
-                cfg, target, peers,
+        let peer_iter = if config.disjoint_query_paths {
+            let num_paths = config.disjoint_path_count.unwrap_or(config.parallelism);
+            QueryPeerIter::ClosestDisjoint(ClosestDisjointPeersIter::with_paths(
+                cfg, num_paths, target, peers,
             ))
         } else {
             QueryPeerIter::Closest(ClosestPeersIter::with_config(cfg, target, peers))
         };
 
-        let query = Query::new(id, peer_iter, inner);
+        let query = Query::new(id, peer_iter, config.timeout, inner);
         self.queries.insert(id, query);
     }
 
@@ -188,7 +224,7 @@ impl<TInner> QueryPool<TInner> {
                 }
                 PeersIterState::Waiting(None) | PeersIterState::WaitingAtCapacity => {
                     let elapsed = now - query.stats.start.unwrap_or(now);
-                    if elapsed >= self.config.timeout {
+                    if elapsed >= query.timeout {
                         timeout = Some(query_id);
                         break;
                     }
@@ -250,6 +286,26 @@ pub(crate) struct QueryConfig {
     ///
     /// See [`crate::behaviour::Config::disjoint_query_paths`] for details.
     pub(crate) disjoint_query_paths: bool,
+    /// The number of disjoint paths, if different from the parallelism.
+    ///
+    /// See [`crate::behaviour::Config::set_disjoint_query_path_count`] for details.
+    pub(crate) disjoint_path_count: Option<NonZeroUsize>,
+    /// Adaptive parallelism for iterative queries, if enabled.
+    ///
+    /// See [`crate::behaviour::Config::set_adaptive_parallelism`] for details.
+    pub(crate) adaptive_parallelism: Option<AdaptiveParallelism>,
+}
+
+impl QueryConfig {
+    /// Returns this configuration with the overrides of the given options applied.
+    pub(crate) fn with_options(&self, options: &QueryOptions) -> Self {
+        QueryConfig {
+            timeout: options.timeout.unwrap_or(self.timeout),
+            parallelism: options.parallelism.unwrap_or(self.parallelism),
+            adaptive_parallelism: options.adaptive_parallelism.or(self.adaptive_parallelism),
+            ..self.clone()
+        }
+    }
 }
 
 impl Default for QueryConfig {
@@ -259,10 +315,137 @@ impl Default for QueryConfig {
             replication_factor: NonZeroUsize::new(K_VALUE.get()).expect("K_VALUE > 0"),
             parallelism: ALPHA_VALUE,
             disjoint_query_paths: false,
+            disjoint_path_count: None,
+            adaptive_parallelism: None,
         }
     }
 }
 
+/// Overrides of the configured query settings for a single query.
+///
+/// See e.g. [`crate::Behaviour::get_record_with_options`] and
+/// [`crate::Behaviour::get_closest_peers_with_options`].
+#[derive(Clone, Default)]
+pub struct QueryOptions {
+    parallelism: Option<NonZeroUsize>,
+    timeout: Option<Duration>,
+    quorum: Option<Quorum>,
+    adaptive_parallelism: Option<AdaptiveParallelism>,
+    record_validator: Option<RecordValidator>,
+}
+
+/// Validates the records found by a record lookup, see [`QueryOptions::with_record_validator`].
+pub(crate) type RecordValidator = Arc<dyn Fn(&Record) -> bool + Send + Sync>;
+
+impl QueryOptions {
+    /// Creates options that don't override any of the configured settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the allowed level of parallelism of the query.
+    ///
+    /// See [`crate::behaviour::Config::set_parallelism`] for details.
+    pub fn with_parallelism(mut self, parallelism: NonZeroUsize) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// Sets the timeout of the query.
+    ///
+    /// See [`crate::behaviour::Config::set_query_timeout`] for details.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the number of records after which a record lookup finishes.
+    ///
+    /// Without a quorum, a record lookup only finishes once the closest peers
+    /// to the key have been queried. Ignored by queries other than record lookups.
+    pub fn with_quorum(mut self, quorum: Quorum) -> Self {
+        self.quorum = Some(quorum);
+        self
+    }
+
+    /// Enables adaptive parallelism for the query.
+    ///
+    /// See [`crate::behaviour::Config::set_adaptive_parallelism`] for details.
+    pub fn with_adaptive_parallelism(mut self, adaptive: AdaptiveParallelism) -> Self {
+        self.adaptive_parallelism = Some(adaptive);
+        self
+    }
+
+    /// Sets a validator for the records found by a record lookup.
+    ///
+    /// Only records passing the validator are reported via
+    /// [`GetRecordOk::FoundRecord`](crate::GetRecordOk::FoundRecord) and count towards the
+    /// quorum, such that the lookup finishes as soon as the quorum of valid records has been
+    /// found. Every record received is reported as it arrives via
+    /// [`Event::InboundRecordFound`](crate::Event::InboundRecordFound), along with whether it
+    /// is valid. Ignored by queries other than record lookups.
+    pub fn with_record_validator<F>(mut self, validator: F) -> Self
+    where
+        F: Fn(&Record) -> bool + Send + Sync + 'static,
+    {
+        self.record_validator = Some(Arc::new(validator));
+        self
+    }
+
+    pub(crate) fn quorum(&self) -> Option<Quorum> {
+        self.quorum
+    }
+
+    pub(crate) fn record_validator(&self) -> Option<RecordValidator> {
+        self.record_validator.clone()
+    }
+}
+
+impl fmt::Debug for QueryOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryOptions")
+            .field("parallelism", &self.parallelism)
+            .field("timeout", &self.timeout)
+            .field("quorum", &self.quorum)
+            .field("adaptive_parallelism", &self.adaptive_parallelism)
+            .field("record_validator", &self.record_validator.is_some())
+            .finish()
+    }
+}
+
+/// Adaptive parallelism of iterative queries.
+///
+/// Each request of a query that did not receive a response within the slow response
+/// threshold allows the query to send one additional request in parallel, up to the
+/// given maximum parallelism. Slow peers thereby don't hold up the query, while the
+/// configured parallelism still applies as long as peers respond quickly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveParallelism {
+    max_parallelism: NonZeroUsize,
+    slow_response_threshold: Duration,
+}
+
+impl AdaptiveParallelism {
+    /// Creates a new adaptive parallelism, raising the parallelism up to `max_parallelism`
+    /// for requests without a response after `slow_response_threshold`.
+    pub fn new(max_parallelism: NonZeroUsize, slow_response_threshold: Duration) -> Self {
+        AdaptiveParallelism {
+            max_parallelism,
+            slow_response_threshold,
+        }
+    }
+
+    /// The maximum parallelism.
+    pub fn max_parallelism(&self) -> NonZeroUsize {
+        self.max_parallelism
+    }
+
+    /// The duration after which a request without a response is considered slow.
+    pub fn slow_response_threshold(&self) -> Duration {
+        self.slow_response_threshold
+    }
+}
+
 /// A query in a `QueryPool`.
 pub(crate) struct Query<TInner> {
     /// The unique ID of the query.
@@ -271,6 +454,8 @@ pub(crate) struct Query<TInner> {
     peer_iter: QueryPeerIter,
     /// Execution statistics of the query.
     stats: QueryStats,
+    /// The timeout of the query.
+    timeout: Duration,
     /// The opaque inner query state.
     pub(crate) inner: TInner,
 }
@@ -284,12 +469,13 @@ enum QueryPeerIter {
 
 impl<TInner> Query<TInner> {
     /// Creates a new query without starting it.
-    fn new(id: QueryId, peer_iter: QueryPeerIter, inner: TInner) -> Self {
+    fn new(id: QueryId, peer_iter: QueryPeerIter, timeout: Duration, inner: TInner) -> Self {
         Query {
             id,
             inner,
             peer_iter,
             stats: QueryStats::empty(),
+            timeout,
         }
     }
 
@@ -332,6 +518,17 @@ impl<TInner> Query<TInner> {
         }
     }
 
+    /// Returns the index of the disjoint path that contacted `peer`.
+    ///
+    /// Returns `None` if the query does not use disjoint paths or
+    /// did not contact `peer`.
+    pub(crate) fn path(&self, peer: &PeerId) -> Option<usize> {
+        match &self.peer_iter {
+            QueryPeerIter::ClosestDisjoint(iter) => iter.path(peer),
+            QueryPeerIter::Closest(_) | QueryPeerIter::Fixed(_) => None,
+        }
+    }
+
     /// Advances the state of the underlying peer iterator.
     fn next(&mut self, now: Instant) -> PeersIterState<'_> {
         let state = match &mut self.peer_iter {
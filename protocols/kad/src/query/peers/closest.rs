@@ -21,6 +21,7 @@
 use super::*;
 
 use crate::kbucket::{Distance, Key, KeyBytes};
+use crate::query::AdaptiveParallelism;
 use crate::{ALPHA_VALUE, K_VALUE};
 use instant::Instant;
 use std::collections::btree_map::{BTreeMap, Entry};
@@ -70,6 +71,13 @@ pub struct ClosestPeersIterConfig {
     /// the peer when evaluating the termination conditions, until and unless a
     /// result is delivered. Defaults to `10` seconds.
     pub peer_timeout: Duration,
+
+    /// Adaptive parallelism, if enabled.
+    ///
+    /// Requests to peers that did not deliver a result within the slow response
+    /// threshold no longer count towards the allowed `parallelism`, up to the
+    /// configured maximum. Defaults to `None`.
+    pub adaptive_parallelism: Option<AdaptiveParallelism>,
 }
 
 impl Default for ClosestPeersIterConfig {
@@ -78,6 +86,7 @@ impl Default for ClosestPeersIterConfig {
             parallelism: ALPHA_VALUE,
             num_results: K_VALUE,
             peer_timeout: Duration::from_secs(10),
+            adaptive_parallelism: None,
         }
     }
 }
@@ -308,7 +317,7 @@ impl ClosestPeersIter {
         let mut result_counter = Some(0);
 
         // Check if the iterator is at capacity w.r.t. the allowed parallelism.
-        let at_capacity = self.at_capacity();
+        let at_capacity = self.at_capacity(now);
 
         for peer in self.closest_peers.values_mut() {
             match peer.state {
@@ -405,16 +414,41 @@ impl ClosestPeersIter {
     /// are allowed. This is a slightly more permissive variant of the
     /// requirement that the initiator "resends the FIND_NODE to all of the
     /// k closest nodes it has not already queried".
-    fn at_capacity(&self) -> bool {
+    fn at_capacity(&self, now: Instant) -> bool {
         match self.state {
             State::Stalled => {
-                self.num_waiting
-                    >= usize::max(self.config.num_results.get(), self.config.parallelism.get())
+                self.num_waiting >= usize::max(self.config.num_results.get(), self.parallelism(now))
             }
-            State::Iterating { .. } => self.num_waiting >= self.config.parallelism.get(),
+            State::Iterating { .. } => self.num_waiting >= self.parallelism(now),
             State::Finished => true,
         }
     }
+
+    /// Returns the currently permitted parallelism.
+    ///
+    /// With adaptive parallelism enabled, every request that is pending for
+    /// longer than the slow response threshold raises the permitted
+    /// parallelism by one, up to the configured maximum.
+    fn parallelism(&self, now: Instant) -> usize {
+        let parallelism = self.config.parallelism.get();
+        let Some(adaptive) = self.config.adaptive_parallelism else {
+            return parallelism;
+        };
+        let num_slow = self
+            .closest_peers
+            .values()
+            .filter(|peer| match peer.state {
+                PeerState::Waiting(timeout) => {
+                    now + self.config.peer_timeout >= timeout + adaptive.slow_response_threshold()
+                }
+                _ => false,
+            })
+            .count();
+        usize::max(
+            parallelism,
+            usize::min(parallelism + num_slow, adaptive.max_parallelism().get()),
+        )
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -538,6 +572,7 @@ mod tests {
                 parallelism: NonZeroUsize::new(g.gen_range(1..10)).unwrap(),
                 num_results: NonZeroUsize::new(g.gen_range(1..25)).unwrap(),
                 peer_timeout: Duration::from_secs(g.gen_range(10..30)),
+                adaptive_parallelism: None,
             };
             ClosestPeersIter::with_config(config, target, known_closest_peers)
         }
@@ -630,7 +665,7 @@ mod tests {
                 assert_eq!(num_waiting, expected.len());
 
                 // Check the bounded parallelism.
-                if iter.at_capacity() {
+                if iter.at_capacity(now) {
                     assert_eq!(iter.next(now), PeersIterState::WaitingAtCapacity)
                 }
 
@@ -810,12 +845,13 @@ mod tests {
     #[test]
     fn stalled_at_capacity() {
         fn prop(mut iter: ClosestPeersIter) {
+            let now = Instant::now();
             iter.state = State::Stalled;
 
             for i in 0..usize::max(iter.config.parallelism.get(), iter.config.num_results.get()) {
                 iter.num_waiting = i;
                 assert!(
-                    !iter.at_capacity(),
+                    !iter.at_capacity(now),
                     "Iterator should not be at capacity if less than \
                      `max(parallelism, num_results)` requests are waiting.",
                 )
@@ -824,7 +860,7 @@ mod tests {
             iter.num_waiting =
                 usize::max(iter.config.parallelism.get(), iter.config.num_results.get());
             assert!(
-                iter.at_capacity(),
+                iter.at_capacity(now),
                 "Iterator should be at capacity if `max(parallelism, num_results)` requests are \
                  waiting.",
             )
@@ -832,4 +868,30 @@ mod tests {
 
         QuickCheck::new().tests(10).quickcheck(prop as fn(_))
     }
+
+    #[test]
+    fn adaptive_parallelism_raised_by_slow_peers() {
+        let now = Instant::now();
+        let target: KeyBytes = Key::from(PeerId::random()).into();
+        let peers = (0..10).map(|_| Key::from(PeerId::random()));
+        let config = ClosestPeersIterConfig {
+            parallelism: NonZeroUsize::new(2).unwrap(),
+            adaptive_parallelism: Some(AdaptiveParallelism::new(
+                NonZeroUsize::new(3).unwrap(),
+                Duration::from_secs(1),
+            )),
+            ..ClosestPeersIterConfig::default()
+        };
+        let mut iter = ClosestPeersIter::with_config(config, target, peers);
+
+        for _ in 0..2 {
+            assert!(matches!(iter.next(now), PeersIterState::Waiting(Some(_))));
+        }
+        assert_eq!(PeersIterState::WaitingAtCapacity, iter.next(now));
+
+        // Both pending requests are slow, but parallelism is capped at 3.
+        let later = now + Duration::from_secs(1);
+        assert!(matches!(iter.next(later), PeersIterState::Waiting(Some(_))));
+        assert_eq!(PeersIterState::WaitingAtCapacity, iter.next(later));
+    }
 }
@@ -59,12 +59,30 @@ impl ClosestDisjointPeersIter {
         )
     }
 
-    /// Creates a new iterator with the given configuration.
+    /// Creates a new iterator with the given configuration, using as many
+    /// disjoint paths as the configured parallelism.
+    #[cfg(test)]
     pub(crate) fn with_config<I, T>(
         config: ClosestPeersIterConfig,
         target: T,
         known_closest_peers: I,
     ) -> Self
+    where
+        I: IntoIterator<Item = Key<PeerId>>,
+        T: Into<KeyBytes> + Clone,
+    {
+        let num_paths = config.parallelism;
+        Self::with_paths(config, num_paths, target, known_closest_peers)
+    }
+
+    /// Creates a new iterator with the given configuration for each of
+    /// `num_paths` disjoint paths.
+    pub(crate) fn with_paths<I, T>(
+        config: ClosestPeersIterConfig,
+        num_paths: NonZeroUsize,
+        target: T,
+        known_closest_peers: I,
+    ) -> Self
     where
         I: IntoIterator<Item = Key<PeerId>>,
         T: Into<KeyBytes> + Clone,
@@ -73,7 +91,7 @@ impl ClosestDisjointPeersIter {
             .into_iter()
             .take(K_VALUE.get())
             .collect::<Vec<_>>();
-        let iters = (0..config.parallelism.get())
+        let iters = (0..num_paths.get())
             // NOTE: All [`ClosestPeersIter`] share the same set of peers at
             // initialization. The [`ClosestDisjointPeersIter.contacted_peers`]
             // mapping ensures that a successful response from a peer is only
@@ -279,6 +297,13 @@ impl ClosestDisjointPeersIter {
         state.unwrap_or(PeersIterState::Finished)
     }
 
+    /// Returns the index of the disjoint path that contacted the given peer, if any.
+    pub(crate) fn path(&self, peer: &PeerId) -> Option<usize> {
+        self.contacted_peers
+            .get(peer)
+            .map(|state| state.initiated_by.0)
+    }
+
     /// Finishes all paths containing one of the given peers.
     ///
     /// See [`crate::query::Query::try_finish`] for details.
@@ -595,6 +620,7 @@ mod tests {
                 parallelism: Parallelism::arbitrary(g).0,
                 num_results: NumResults::arbitrary(g).0,
                 peer_timeout: Duration::from_secs(1),
+                adaptive_parallelism: None,
             }
         }
     }
@@ -982,4 +1008,33 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn path_count_independent_of_parallelism() {
+        let now = Instant::now();
+        let target: KeyBytes = Key::from(PeerId::random()).into();
+        let peers = (0..10)
+            .map(|_| Key::from(PeerId::random()))
+            .collect::<Vec<_>>();
+        let config = ClosestPeersIterConfig {
+            parallelism: NonZeroUsize::new(3).unwrap(),
+            ..ClosestPeersIterConfig::default()
+        };
+
+        let mut iter = ClosestDisjointPeersIter::with_paths(
+            config,
+            NonZeroUsize::new(2).unwrap(),
+            target,
+            peers,
+        );
+        assert_eq!(iter.iters.len(), 2);
+
+        let mut paths = Vec::new();
+        while let PeersIterState::Waiting(Some(peer)) = iter.next(now) {
+            let peer = peer.into_owned();
+            paths.push(iter.path(&peer).unwrap());
+        }
+        assert!(paths.contains(&0) && paths.contains(&1));
+        assert_eq!(iter.path(&PeerId::random()), None);
+    }
 }
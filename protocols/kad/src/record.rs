@@ -30,6 +30,7 @@ use libp2p_identity::PeerId;
 use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use std::hash::{Hash, Hasher};
+use std::time::Duration;
 
 /// The (opaque) key of a record.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -83,6 +84,13 @@ pub struct Record {
     pub publisher: Option<PeerId>,
     /// The expiration time as measured by a local, monotonic clock.
     pub expires: Option<Instant>,
+    /// The time-to-live of the record, overriding the TTL configured via
+    /// [`Config::set_record_ttl`](crate::Config::set_record_ttl).
+    ///
+    /// If set, the expiration is set to the TTL from now whenever the local node publishes the
+    /// record, including its periodic re-publication. Not transmitted to other peers, which only
+    /// learn about the resulting expiration.
+    pub ttl: Option<Duration>,
 }
 
 impl Record {
@@ -96,6 +104,7 @@ impl Record {
             value,
             publisher: None,
             expires: None,
+            ttl: None,
         }
     }
 
@@ -187,6 +196,7 @@ mod tests {
                 } else {
                     None
                 },
+                ttl: None,
             }
         }
     }
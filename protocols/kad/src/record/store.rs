@@ -18,9 +18,15 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+#[cfg(feature = "file-store")]
+mod file;
 mod memory;
+mod persistent;
 
+#[cfg(feature = "file-store")]
+pub use file::FileBackend;
 pub use memory::{MemoryStore, MemoryStoreConfig};
+pub use persistent::{PersistentBackend, PersistentStore};
 use thiserror::Error;
 
 use super::*;
@@ -44,6 +50,10 @@ pub enum Error {
     /// The store cannot store this value because it is too large.
     #[error("the value is too large to be stored")]
     ValueTooLarge,
+
+    /// The backend of a [`PersistentStore`] failed to persist the record.
+    #[error("the record could not be persisted: {0}")]
+    Backend(String),
 }
 
 /// Trait for types implementing a record store.
@@ -84,6 +94,15 @@ pub trait RecordStore {
     /// Gets an iterator over all (value-) records currently stored.
     fn records(&self) -> Self::RecordsIter<'_>;
 
+    /// Checks whether a stored record is to be re-published, if the local node is its publisher,
+    /// or replicated otherwise.
+    ///
+    /// A record that is not re-published keeps its expiration and is removed once it expires.
+    /// All records are re-published by default.
+    fn should_republish(&self, _record: &Record) -> bool {
+        true
+    }
+
     /// Adds a provider record to the store.
     ///
     /// A record store only needs to store a number of provider records
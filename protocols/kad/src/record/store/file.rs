@@ -0,0 +1,386 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use super::*;
+
+use crate::proto;
+use crate::protocol::{record_from_proto, record_to_proto};
+use futures::channel::{mpsc, oneshot};
+use futures::future::{BoxFuture, FutureExt};
+use futures::{SinkExt, StreamExt};
+use libp2p_core::Multiaddr;
+use quick_protobuf::{BytesReader, MessageRead, MessageWrite, Writer};
+use std::fmt::Write as _;
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+const RECORDS_DIR: &str = "records";
+const PROVIDERS_DIR: &str = "providers";
+
+/// The number of writes that can be queued before [`FileBackend`] rejects further ones.
+const WRITE_QUEUE_CAPACITY: usize = 1024;
+
+/// A [`PersistentBackend`] storing every record in a separate file in a directory.
+///
+/// Records are encoded with the protobuf `Record` message of the Kademlia wire protocol. Provider
+/// records reuse the same message, with the provider as publisher and its addresses encoded as
+/// `Peer` message in the value.
+///
+/// The remaining TTL of a record is stored relative to the modification time of its file, so the
+/// time the node was offline counts towards the expiry of the record.
+///
+/// All file system access happens on a dedicated thread, in the order of the calls. Writes are
+/// queued and fail with [`io::ErrorKind::WouldBlock`] once the queue is full. Errors of queued
+/// writes are only logged. Clones share the same thread.
+#[derive(Debug, Clone)]
+pub struct FileBackend {
+    worker: mpsc::Sender<Command>,
+}
+
+#[derive(Debug)]
+enum Command {
+    PutRecord(Record),
+    RemoveRecord(Key),
+    PutProvider(ProviderRecord),
+    RemoveProvider(Key, PeerId),
+    Load(oneshot::Sender<io::Result<(Vec<Record>, Vec<ProviderRecord>)>>),
+    Flush(oneshot::Sender<()>),
+}
+
+impl FileBackend {
+    /// Creates a new `FileBackend` storing records in the given directory.
+    ///
+    /// The directory is created if it doesn't exist.
+    pub fn new(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        fs::create_dir_all(path.join(RECORDS_DIR))?;
+        fs::create_dir_all(path.join(PROVIDERS_DIR))?;
+
+        let (worker, commands) = mpsc::channel(WRITE_QUEUE_CAPACITY);
+        thread::Builder::new()
+            .name("libp2p-kad-file-store".to_owned())
+            .spawn(move || run_worker(&path, commands))?;
+
+        Ok(Self { worker })
+    }
+
+    /// Resolves once all writes queued before the call have been applied.
+    pub fn flush(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut worker = self.worker.clone();
+
+        async move {
+            let (tx, rx) = oneshot::channel();
+            if worker.send(Command::Flush(tx)).await.is_ok() {
+                let _ = rx.await;
+            }
+        }
+    }
+
+    fn queue(&mut self, command: Command) -> io::Result<()> {
+        self.worker.try_send(command).map_err(|e| {
+            if e.is_full() {
+                io::Error::new(io::ErrorKind::WouldBlock, "write queue is full")
+            } else {
+                io::Error::new(io::ErrorKind::BrokenPipe, "file store thread terminated")
+            }
+        })
+    }
+}
+
+impl PersistentBackend for FileBackend {
+    /// Reads the records from the directory on the file store thread, after all queued writes.
+    fn load(&mut self) -> BoxFuture<'static, io::Result<(Vec<Record>, Vec<ProviderRecord>)>> {
+        let mut worker = self.worker.clone();
+
+        async move {
+            let (tx, rx) = oneshot::channel();
+            worker.send(Command::Load(tx)).await.map_err(|_| {
+                io::Error::new(io::ErrorKind::BrokenPipe, "file store thread terminated")
+            })?;
+
+            rx.await.map_err(|_| {
+                io::Error::new(io::ErrorKind::BrokenPipe, "file store thread terminated")
+            })?
+        }
+        .boxed()
+    }
+
+    fn put_record(&mut self, record: &Record) -> io::Result<()> {
+        self.queue(Command::PutRecord(record.clone()))
+    }
+
+    fn remove_record(&mut self, key: &Key) -> io::Result<()> {
+        self.queue(Command::RemoveRecord(key.clone()))
+    }
+
+    fn put_provider(&mut self, record: &ProviderRecord) -> io::Result<()> {
+        self.queue(Command::PutProvider(record.clone()))
+    }
+
+    fn remove_provider(&mut self, key: &Key, provider: &PeerId) -> io::Result<()> {
+        self.queue(Command::RemoveProvider(key.clone(), *provider))
+    }
+}
+
+/// Applies the commands of the [`FileBackend`]s until all of them are dropped.
+fn run_worker(path: &Path, mut commands: mpsc::Receiver<Command>) {
+    while let Some(command) = futures::executor::block_on(commands.next()) {
+        let result = match command {
+            Command::PutRecord(record) => write_record(&record_path(path, &record.key), record),
+            Command::RemoveRecord(key) => remove_file(&record_path(path, &key)),
+            Command::PutProvider(record) => put_provider(path, &record),
+            Command::RemoveProvider(key, provider) => remove_provider(path, &key, &provider),
+            Command::Load(tx) => {
+                let _ = tx.send(load(path));
+                continue;
+            }
+            Command::Flush(tx) => {
+                let _ = tx.send(());
+                continue;
+            }
+        };
+
+        if let Err(e) = result {
+            tracing::warn!(path=%path.display(), "Failed to persist record: {e}");
+        }
+    }
+}
+
+fn record_path(path: &Path, key: &Key) -> PathBuf {
+    path.join(RECORDS_DIR).join(to_hex(key.as_ref()))
+}
+
+fn provider_path(path: &Path, key: &Key, provider: &PeerId) -> PathBuf {
+    path.join(PROVIDERS_DIR)
+        .join(to_hex(key.as_ref()))
+        .join(to_hex(&provider.to_bytes()))
+}
+
+fn put_provider(path: &Path, record: &ProviderRecord) -> io::Result<()> {
+    let path = provider_path(path, &record.key, &record.provider);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    write_record(&path, provider_to_record(record))
+}
+
+fn remove_provider(path: &Path, key: &Key, provider: &PeerId) -> io::Result<()> {
+    let path = provider_path(path, key, provider);
+    remove_file(&path)?;
+
+    // Clean up the directory of the key once its last provider is gone.
+    if let Some(dir) = path.parent() {
+        if fs::read_dir(dir).map_or(false, |mut d| d.next().is_none()) {
+            fs::remove_dir(dir)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn load(path: &Path) -> io::Result<(Vec<Record>, Vec<ProviderRecord>)> {
+    let mut records = Vec::new();
+    for entry in fs::read_dir(path.join(RECORDS_DIR))? {
+        let path = entry?.path();
+        if is_tmp(&path) {
+            continue;
+        }
+        match read_record(&path) {
+            Ok(record) => records.push(record),
+            Err(e) => tracing::warn!(path=%path.display(), "Failed to read record: {e}"),
+        }
+    }
+
+    let mut providers = Vec::new();
+    for key_dir in fs::read_dir(path.join(PROVIDERS_DIR))? {
+        for entry in fs::read_dir(key_dir?.path())? {
+            let path = entry?.path();
+            if is_tmp(&path) {
+                continue;
+            }
+            match read_record(&path).and_then(provider_from_record) {
+                Ok(record) => providers.push(record),
+                Err(e) => {
+                    tracing::warn!(path=%path.display(), "Failed to read provider record: {e}")
+                }
+            }
+        }
+    }
+
+    Ok((records, providers))
+}
+
+/// Whether the file is a leftover of an interrupted [`write_record`].
+fn is_tmp(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "tmp")
+}
+
+fn write_record(path: &Path, record: Record) -> io::Result<()> {
+    let record = record_to_proto(record);
+    let mut buf = Vec::with_capacity(record.get_size());
+    record
+        .write_message(&mut Writer::new(&mut buf))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    // Write to a temporary file first, so a crash doesn't leave a truncated record behind.
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, buf)?;
+    fs::rename(tmp, path)
+}
+
+fn read_record(path: &Path) -> io::Result<Record> {
+    let buf = fs::read(path)?;
+    let mut record = proto::Record::from_reader(&mut BytesReader::from_bytes(&buf), &buf)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    if record.ttl > 0 {
+        let elapsed = fs::metadata(path)?
+            .modified()?
+            .elapsed()
+            .unwrap_or(Duration::ZERO);
+        // A TTL of 0 means "does not expire", hence saturate at 1.
+        record.ttl = record.ttl.saturating_sub(elapsed.as_secs() as u32).max(1);
+    }
+
+    record_from_proto(record)
+}
+
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        res => res,
+    }
+}
+
+fn provider_to_record(record: &ProviderRecord) -> Record {
+    let peer = proto::Peer {
+        id: record.provider.to_bytes(),
+        addrs: record.addresses.iter().map(|a| a.to_vec()).collect(),
+        connection: proto::ConnectionType::NOT_CONNECTED,
+    };
+    let mut value = Vec::with_capacity(peer.get_size());
+    peer.write_message(&mut Writer::new(&mut value))
+        .expect("Encoding to succeed");
+
+    Record {
+        key: record.key.clone(),
+        value,
+        publisher: Some(record.provider),
+        expires: record.expires,
+        ttl: None,
+    }
+}
+
+fn provider_from_record(record: Record) -> io::Result<ProviderRecord> {
+    let provider = record
+        .publisher
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing provider"))?;
+    let peer = proto::Peer::from_reader(&mut BytesReader::from_bytes(&record.value), &record.value)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let addresses = peer
+        .addrs
+        .into_iter()
+        .filter_map(|a| Multiaddr::try_from(a).ok())
+        .collect();
+
+    Ok(ProviderRecord {
+        key: record.key,
+        provider,
+        expires: record.expires,
+        addresses,
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn roundtrip() {
+        let dir = std::env::temp_dir().join(format!("libp2p-kad-file-store-{}", PeerId::random()));
+        let local_id = PeerId::random();
+        let key = Key::new(&"key");
+        let mut record = Record::new(key.clone(), b"value".to_vec());
+        record.publisher = Some(local_id);
+        record.expires = Some(Instant::now() + Duration::from_secs(60));
+        let provider = ProviderRecord::new(
+            key.clone(),
+            local_id,
+            vec!["/ip4/127.0.0.1/tcp/4001".parse().unwrap()],
+        );
+
+        let mut store = PersistentStore::new(local_id, FileBackend::new(&dir).unwrap())
+            .await
+            .unwrap();
+        store.put(record.clone()).unwrap();
+        store.add_provider(provider.clone()).unwrap();
+        store.backend().flush().await;
+        drop(store);
+
+        let store = PersistentStore::new(local_id, FileBackend::new(&dir).unwrap())
+            .await
+            .unwrap();
+        let loaded = store.get(&key).unwrap();
+        assert_eq!(loaded.value, record.value);
+        assert_eq!(loaded.publisher, record.publisher);
+        assert!(loaded.expires.is_some());
+        assert_eq!(store.providers(&key)[0].addresses, provider.addresses);
+        assert_eq!(store.provided().count(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[async_std::test]
+    async fn load_skips_temporary_files() {
+        let dir = std::env::temp_dir().join(format!("libp2p-kad-file-store-{}", PeerId::random()));
+        let mut backend = FileBackend::new(&dir).unwrap();
+        let key = Key::new(&"key");
+        backend
+            .put_record(&Record::new(key.clone(), b"value".to_vec()))
+            .unwrap();
+
+        // A write interrupted before the rename leaves a temporary file behind.
+        let interrupted = Key::new(&"interrupted");
+        backend
+            .put_record(&Record::new(interrupted.clone(), b"value".to_vec()))
+            .unwrap();
+        backend.flush().await;
+        let path = record_path(&dir, &interrupted);
+        fs::rename(&path, path.with_extension("tmp")).unwrap();
+
+        let (records, providers) = backend.load().await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert!(providers.is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,357 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use super::*;
+
+use futures::future::BoxFuture;
+use std::io;
+
+/// A backend persisting the contents of a [`PersistentStore`].
+///
+/// The [`PersistentStore`] serves all reads from memory and only calls into the backend to load
+/// its initial contents and to write through modifications.
+///
+/// Loading is asynchronous, see [`PersistentStore::new`]. The write methods are invoked from
+/// within [`Behaviour::poll`](crate::Behaviour) and must not block. Backends performing slow
+/// I/O should hand the modifications to a background task, e.g. via a bounded channel, and
+/// return an error if they can't accept a modification, in which case the store rolls it back.
+pub trait PersistentBackend {
+    /// Loads all persisted (value-)records and provider records.
+    fn load(&mut self) -> BoxFuture<'static, io::Result<(Vec<Record>, Vec<ProviderRecord>)>>;
+
+    /// Persists a record, replacing any previous record with the same key.
+    fn put_record(&mut self, record: &Record) -> io::Result<()>;
+
+    /// Removes the persisted record with the given key.
+    fn remove_record(&mut self, key: &Key) -> io::Result<()>;
+
+    /// Persists a provider record, replacing any previous record for the same key and provider.
+    fn put_provider(&mut self, record: &ProviderRecord) -> io::Result<()>;
+
+    /// Removes the persisted provider record for the given key and provider.
+    fn remove_provider(&mut self, key: &Key, provider: &PeerId) -> io::Result<()>;
+}
+
+/// A [`RecordStore`] that keeps its records in a [`MemoryStore`] and writes all modifications
+/// through to a [`PersistentBackend`].
+///
+/// Records and provider records loaded from the backend are picked up by the periodic
+/// replication and publication jobs of the [`Behaviour`](crate::Behaviour), so locally
+/// published records survive a restart of the node.
+pub struct PersistentStore<B> {
+    inner: MemoryStore,
+    backend: B,
+}
+
+impl<B> PersistentStore<B>
+where
+    B: PersistentBackend,
+{
+    /// Creates a new `PersistentStore` with a default configuration, loading the records
+    /// persisted in the given backend.
+    pub async fn new(local_id: PeerId, backend: B) -> io::Result<Self> {
+        Self::with_config(local_id, Default::default(), backend).await
+    }
+
+    /// Creates a new `PersistentStore` with the given configuration, loading the records
+    /// persisted in the given backend.
+    ///
+    /// Expired records are dropped from the backend. Records exceeding the limits of the
+    /// configuration are skipped.
+    pub async fn with_config(
+        local_id: PeerId,
+        config: MemoryStoreConfig,
+        mut backend: B,
+    ) -> io::Result<Self> {
+        let (records, providers) = backend.load().await?;
+        let mut inner = MemoryStore::with_config(local_id, config);
+        let now = Instant::now();
+
+        for record in records {
+            if record.is_expired(now) {
+                backend.remove_record(&record.key)?;
+                continue;
+            }
+            if let Err(e) = inner.put(record) {
+                tracing::debug!("Skipping persisted record: {e}");
+            }
+        }
+        for record in providers {
+            if record.is_expired(now) {
+                backend.remove_provider(&record.key, &record.provider)?;
+                continue;
+            }
+            if let Err(e) = inner.add_provider(record) {
+                tracing::debug!("Skipping persisted provider record: {e}");
+            }
+        }
+
+        Ok(Self { inner, backend })
+    }
+
+    /// Returns a reference to the backend of this store.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+}
+
+impl<B> RecordStore for PersistentStore<B>
+where
+    B: PersistentBackend,
+{
+    type RecordsIter<'a>
+        = <MemoryStore as RecordStore>::RecordsIter<'a>
+    where
+        B: 'a;
+    type ProvidedIter<'a>
+        = <MemoryStore as RecordStore>::ProvidedIter<'a>
+    where
+        B: 'a;
+
+    fn get(&self, k: &Key) -> Option<Cow<'_, Record>> {
+        self.inner.get(k)
+    }
+
+    fn put(&mut self, r: Record) -> Result<()> {
+        let previous = self.inner.get(&r.key).map(Cow::into_owned);
+        self.inner.put(r.clone())?;
+
+        if let Err(e) = self.backend.put_record(&r) {
+            // Roll back, such that memory and backend agree.
+            match previous {
+                Some(previous) => self
+                    .inner
+                    .put(previous)
+                    .expect("Restoring the previous record to succeed"),
+                None => self.inner.remove(&r.key),
+            }
+            return Err(Error::Backend(e.to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn remove(&mut self, k: &Key) {
+        self.inner.remove(k);
+        if let Err(e) = self.backend.remove_record(k) {
+            tracing::warn!(key=?k, "Failed to remove persisted record: {e}");
+        }
+    }
+
+    fn records(&self) -> Self::RecordsIter<'_> {
+        self.inner.records()
+    }
+
+    fn add_provider(&mut self, record: ProviderRecord) -> Result<()> {
+        let before = self.inner.providers(&record.key);
+        self.inner.add_provider(record.clone())?;
+        let after = self.inner.providers(&record.key);
+
+        if after.iter().any(|p| p.provider == record.provider) {
+            if let Err(e) = self.backend.put_provider(&record) {
+                // Roll back, such that memory and backend agree.
+                self.inner.remove_provider(&record.key, &record.provider);
+                for previous in before {
+                    if let Err(e) = self.inner.add_provider(previous) {
+                        tracing::debug!("Failed to restore provider record: {e}");
+                    }
+                }
+                return Err(Error::Backend(e.to_string()));
+            }
+        }
+
+        // The memory store only keeps the providers closest to the key, so adding a provider may
+        // have evicted another one.
+        for evicted in before
+            .iter()
+            .filter(|p| !after.iter().any(|a| a.provider == p.provider))
+        {
+            if let Err(e) = self
+                .backend
+                .remove_provider(&evicted.key, &evicted.provider)
+            {
+                tracing::warn!(key=?evicted.key, "Failed to remove persisted provider record: {e}");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn providers(&self, key: &Key) -> Vec<ProviderRecord> {
+        self.inner.providers(key)
+    }
+
+    fn provided(&self) -> Self::ProvidedIter<'_> {
+        self.inner.provided()
+    }
+
+    fn remove_provider(&mut self, k: &Key, p: &PeerId) {
+        self.inner.remove_provider(k, p);
+        if let Err(e) = self.backend.remove_provider(k, p) {
+            tracing::warn!(key=?k, provider=%p, "Failed to remove persisted provider record: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::FutureExt;
+    use std::collections::HashMap;
+
+    #[derive(Default, Clone)]
+    struct TestBackend {
+        records: HashMap<Key, Record>,
+        providers: HashMap<(Key, PeerId), ProviderRecord>,
+        fail_writes: bool,
+    }
+
+    impl PersistentBackend for TestBackend {
+        fn load(&mut self) -> BoxFuture<'static, io::Result<(Vec<Record>, Vec<ProviderRecord>)>> {
+            futures::future::ready(Ok((
+                self.records.values().cloned().collect(),
+                self.providers.values().cloned().collect(),
+            )))
+            .boxed()
+        }
+
+        fn put_record(&mut self, record: &Record) -> io::Result<()> {
+            if self.fail_writes {
+                return Err(io::ErrorKind::Other.into());
+            }
+            self.records.insert(record.key.clone(), record.clone());
+            Ok(())
+        }
+
+        fn remove_record(&mut self, key: &Key) -> io::Result<()> {
+            self.records.remove(key);
+            Ok(())
+        }
+
+        fn put_provider(&mut self, record: &ProviderRecord) -> io::Result<()> {
+            if self.fail_writes {
+                return Err(io::ErrorKind::Other.into());
+            }
+            self.providers
+                .insert((record.key.clone(), record.provider), record.clone());
+            Ok(())
+        }
+
+        fn remove_provider(&mut self, key: &Key, provider: &PeerId) -> io::Result<()> {
+            self.providers.remove(&(key.clone(), *provider));
+            Ok(())
+        }
+    }
+
+    #[async_std::test]
+    async fn records_survive_reopening() {
+        let local_id = PeerId::random();
+        let key = Key::new(&"key");
+        let record = Record::new(key.clone(), b"value".to_vec());
+        let provider = ProviderRecord::new(key.clone(), local_id, Vec::new());
+
+        let mut store = PersistentStore::new(local_id, TestBackend::default())
+            .await
+            .unwrap();
+        store.put(record.clone()).unwrap();
+        store.add_provider(provider.clone()).unwrap();
+
+        let store = PersistentStore::new(local_id, store.backend().clone())
+            .await
+            .unwrap();
+
+        assert_eq!(store.get(&key), Some(Cow::Borrowed(&record)));
+        assert_eq!(store.providers(&key), vec![provider.clone()]);
+        assert_eq!(
+            store.provided().collect::<Vec<_>>(),
+            vec![Cow::Borrowed(&provider)]
+        );
+    }
+
+    #[async_std::test]
+    async fn removal_is_written_through() {
+        let local_id = PeerId::random();
+        let key = Key::new(&"key");
+        let peer = PeerId::random();
+
+        let mut store = PersistentStore::new(local_id, TestBackend::default())
+            .await
+            .unwrap();
+        store
+            .put(Record::new(key.clone(), b"value".to_vec()))
+            .unwrap();
+        store
+            .add_provider(ProviderRecord::new(key.clone(), peer, Vec::new()))
+            .unwrap();
+        store.remove(&key);
+        store.remove_provider(&key, &peer);
+
+        assert!(store.backend().records.is_empty());
+        assert!(store.backend().providers.is_empty());
+    }
+
+    #[async_std::test]
+    async fn expired_records_are_dropped_on_load() {
+        let local_id = PeerId::random();
+        let key = Key::new(&"key");
+        let mut record = Record::new(key.clone(), b"value".to_vec());
+        record.expires = Some(Instant::now());
+
+        let mut backend = TestBackend::default();
+        backend.put_record(&record).unwrap();
+
+        let store = PersistentStore::new(local_id, backend).await.unwrap();
+
+        assert!(store.get(&key).is_none());
+        assert!(store.backend().records.is_empty());
+    }
+
+    #[async_std::test]
+    async fn failed_writes_are_rolled_back() {
+        let local_id = PeerId::random();
+        let key = Key::new(&"key");
+        let record = Record::new(key.clone(), b"value".to_vec());
+        let provider = ProviderRecord::new(key.clone(), local_id, Vec::new());
+
+        let mut store = PersistentStore::new(local_id, TestBackend::default())
+            .await
+            .unwrap();
+        store.put(record.clone()).unwrap();
+        store.backend.fail_writes = true;
+
+        let mut replacement = record.clone();
+        replacement.value = b"other".to_vec();
+        assert!(matches!(store.put(replacement), Err(Error::Backend(_))));
+        assert_eq!(store.get(&key), Some(Cow::Borrowed(&record)));
+
+        assert!(matches!(
+            store.put(Record::new(Key::new(&"other"), b"value".to_vec())),
+            Err(Error::Backend(_))
+        ));
+        assert!(store.get(&Key::new(&"other")).is_none());
+
+        assert!(matches!(
+            store.add_provider(provider),
+            Err(Error::Backend(_))
+        ));
+        assert!(store.providers(&key).is_empty());
+        assert_eq!(store.provided().count(), 0);
+    }
+}
@@ -160,6 +160,35 @@ async fn set_client_to_server_mode() {
         .any(|proto| libp2p_kad::PROTOCOL_NAME.eq(proto)));
 }
 
+#[async_std::test]
+async fn expiring_the_external_address_activates_client_mode() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm = Swarm::new_ephemeral(MyBehaviour::new);
+    let addr: libp2p_core::Multiaddr = "/memory/1234".parse().unwrap();
+
+    swarm.add_external_address(addr.clone());
+    let mode = swarm
+        .wait(|e| match e {
+            SwarmEvent::Behaviour(Kad(ModeChanged { new_mode })) => Some(new_mode),
+            _ => None,
+        })
+        .await;
+    assert_eq!(mode, Mode::Server);
+
+    // E.g. AutoNAT reporting the node as private again.
+    swarm.remove_external_address(&addr);
+    let mode = swarm
+        .wait(|e| match e {
+            SwarmEvent::Behaviour(Kad(ModeChanged { new_mode })) => Some(new_mode),
+            _ => None,
+        })
+        .await;
+    assert_eq!(mode, Mode::Client);
+}
+
 #[derive(libp2p_swarm::NetworkBehaviour)]
 #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
 struct MyBehaviour {
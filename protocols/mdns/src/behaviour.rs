@@ -36,7 +36,10 @@ use libp2p_swarm::{
     THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 use smallvec::SmallVec;
-use std::collections::hash_map::{Entry, HashMap};
+use std::collections::{
+    hash_map::{Entry, HashMap},
+    BTreeMap,
+};
 use std::future::Future;
 use std::sync::{Arc, RwLock};
 use std::{cmp, fmt, io, net::IpAddr, pin::Pin, task::Context, task::Poll, time::Instant};
@@ -151,8 +154,8 @@ where
     /// Handles to tasks running the mDNS queries.
     if_tasks: HashMap<IpAddr, P::TaskHandle>,
 
-    query_response_receiver: mpsc::Receiver<(PeerId, Multiaddr, Instant)>,
-    query_response_sender: mpsc::Sender<(PeerId, Multiaddr, Instant)>,
+    query_response_receiver: mpsc::Receiver<(PeerId, Multiaddr, BTreeMap<String, String>, Instant)>,
+    query_response_sender: mpsc::Sender<(PeerId, Multiaddr, BTreeMap<String, String>, Instant)>,
 
     /// List of nodes that we have discovered, the address, and when their TTL expires.
     ///
@@ -330,7 +333,7 @@ where
         // Emit discovered event.
         let mut discovered = Vec::new();
 
-        while let Poll::Ready(Some((peer, addr, expiration))) =
+        while let Poll::Ready(Some((peer, addr, metadata, expiration))) =
             self.query_response_receiver.poll_next_unpin(cx)
         {
             if let Some((_, _, cur_expires)) = self
@@ -342,7 +345,7 @@ where
             } else {
                 tracing::info!(%peer, address=%addr, "discovered peer on address");
                 self.discovered_nodes.push((peer, addr.clone(), expiration));
-                discovered.push((peer, addr));
+                discovered.push((peer, addr, metadata));
             }
         }
 
@@ -380,8 +383,10 @@ where
 /// Event that can be produced by the `Mdns` behaviour.
 #[derive(Debug, Clone)]
 pub enum Event {
-    /// Discovered nodes through mDNS.
-    Discovered(Vec<(PeerId, Multiaddr)>),
+    /// Discovered nodes through mDNS, along with the metadata they advertise.
+    ///
+    /// See [`Config::metadata`].
+    Discovered(Vec<(PeerId, Multiaddr, BTreeMap<String, String>)>),
 
     /// The given combinations of `PeerId` and `Multiaddr` have expired.
     ///
@@ -34,7 +34,7 @@ use socket2::{Domain, Socket, Type};
 use std::future::Future;
 use std::sync::{Arc, RwLock};
 use std::{
-    collections::VecDeque,
+    collections::{BTreeMap, VecDeque},
     io,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
     pin::Pin,
@@ -79,7 +79,7 @@ pub(crate) struct InterfaceState<U, T> {
 
     listen_addresses: Arc<RwLock<ListenAddresses>>,
 
-    query_response_sender: mpsc::Sender<(PeerId, Multiaddr, Instant)>,
+    query_response_sender: mpsc::Sender<(PeerId, Multiaddr, BTreeMap<String, String>, Instant)>,
 
     /// Buffer used for receiving data from the main socket.
     /// RFC6762 discourages packets larger than the interface MTU, but allows sizes of up to 9000
@@ -97,9 +97,11 @@ pub(crate) struct InterfaceState<U, T> {
     /// Multicast address.
     multicast_addr: IpAddr,
     /// Discovered addresses.
-    discovered: VecDeque<(PeerId, Multiaddr, Instant)>,
+    discovered: VecDeque<(PeerId, Multiaddr, BTreeMap<String, String>, Instant)>,
     /// TTL
     ttl: Duration,
+    /// Metadata to advertise in responses.
+    metadata: BTreeMap<String, String>,
     probe_state: ProbeState,
     local_peer_id: PeerId,
 }
@@ -115,7 +117,7 @@ where
         config: Config,
         local_peer_id: PeerId,
         listen_addresses: Arc<RwLock<ListenAddresses>>,
-        query_response_sender: mpsc::Sender<(PeerId, Multiaddr, Instant)>,
+        query_response_sender: mpsc::Sender<(PeerId, Multiaddr, BTreeMap<String, String>, Instant)>,
     ) -> io::Result<Self> {
         tracing::info!(address=%addr, "creating instance on iface address");
         let recv_socket = match addr {
@@ -178,6 +180,7 @@ where
             timeout: T::interval_at(Instant::now(), INITIAL_TIMEOUT_INTERVAL),
             multicast_addr,
             ttl: config.ttl,
+            metadata: config.metadata,
             probe_state: Default::default(),
             local_peer_id,
         })
@@ -278,6 +281,7 @@ where
                             .read()
                             .unwrap_or_else(|e| e.into_inner())
                             .iter(),
+                        &this.metadata,
                         this.ttl,
                     ));
                     continue;
@@ -25,7 +25,7 @@ use libp2p_core::Multiaddr;
 use libp2p_identity::PeerId;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
-use std::{borrow::Cow, cmp, error, fmt, str, time::Duration};
+use std::{borrow::Cow, cmp, collections::BTreeMap, error, fmt, str, time::Duration};
 
 /// DNS TXT records can have up to 255 characters as a single string value.
 ///
@@ -102,11 +102,15 @@ pub(crate) fn build_query() -> MdnsPacket {
 
 /// Builds the response to an address discovery DNS query.
 ///
+/// The `metadata` is encoded as `key=value` TXT records, which are repeated in every packet so
+/// that each packet describes the peer on its own.
+///
 /// If there are more than 2^16-1 addresses, ignores the rest.
 pub(crate) fn build_query_response<'a>(
     id: u16,
     peer_id: PeerId,
     addresses: impl ExactSizeIterator<Item = &'a Multiaddr>,
+    metadata: &BTreeMap<String, String>,
     ttl: Duration,
 ) -> Vec<MdnsPacket> {
     // Convert the TTL into seconds.
@@ -121,8 +125,24 @@ pub(crate) fn build_query_response<'a>(
     // The accumulated response packets.
     let mut packets = Vec::new();
 
+    let mut metadata_records = Vec::with_capacity(metadata.len());
+    for (key, value) in metadata {
+        let mut txt_record = Vec::with_capacity(key.len() + value.len() + 1);
+        match append_metadata_record(&mut txt_record, &peer_name_bytes, ttl, key, value) {
+            Ok(()) => {
+                metadata_records.push(txt_record);
+            }
+            Err(e) => {
+                tracing::warn!(%key, "Excluding metadata from response: {:?}", e);
+            }
+        }
+    }
+    // Leave room for at least one address per packet.
+    metadata_records.truncate(MAX_RECORDS_PER_PACKET - 1);
+
     // The records accumulated per response packet.
     let mut records = Vec::with_capacity(addresses.len() * MAX_TXT_RECORD_SIZE);
+    records.extend_from_slice(&metadata_records);
 
     // Encode the addresses as TXT records, and multiple TXT records into a
     // response packet.
@@ -141,12 +161,12 @@ pub(crate) fn build_query_response<'a>(
         if records.len() == MAX_RECORDS_PER_PACKET {
             packets.push(query_response_packet(id, &peer_name_bytes, &records, ttl));
             records.clear();
+            records.extend_from_slice(&metadata_records);
         }
     }
 
-    // If there are still unpacked records, i.e. if the number of records is not
-    // a multiple of `MAX_RECORDS_PER_PACKET`, create a final packet.
-    if !records.is_empty() {
+    // If there are still unpacked address records, create a final packet.
+    if records.len() > metadata_records.len() {
         packets.push(query_response_packet(id, &peer_name_bytes, &records, ttl));
     }
 
@@ -338,13 +358,8 @@ fn append_character_string(out: &mut Vec<u8>, ascii_str: &str) -> Result<(), Mdn
     Ok(())
 }
 
-/// Appends a TXT record to `out`.
-fn append_txt_record(
-    out: &mut Vec<u8>,
-    name: &[u8],
-    ttl_secs: u32,
-    value: &str,
-) -> Result<(), MdnsResponseError> {
+/// Appends the name, flags and TTL of a TXT record to `out`.
+fn append_txt_record_header(out: &mut Vec<u8>, name: &[u8], ttl_secs: u32) {
     // The name.
     out.extend_from_slice(name);
 
@@ -356,6 +371,16 @@ fn append_txt_record(
 
     // TTL for the answer
     append_u32(out, ttl_secs);
+}
+
+/// Appends a TXT record to `out`.
+fn append_txt_record(
+    out: &mut Vec<u8>,
+    name: &[u8],
+    ttl_secs: u32,
+    value: &str,
+) -> Result<(), MdnsResponseError> {
+    append_txt_record_header(out, name, ttl_secs);
 
     // Add the strings.
     if value.len() > MAX_TXT_VALUE_LENGTH {
@@ -369,11 +394,40 @@ fn append_txt_record(
     Ok(())
 }
 
+/// Appends a TXT record with a `key=value` metadata entry to `out`.
+///
+/// Unlike addresses, the entry is not quoted, as TXT record strings may contain arbitrary bytes.
+fn append_metadata_record(
+    out: &mut Vec<u8>,
+    name: &[u8],
+    ttl_secs: u32,
+    key: &str,
+    value: &str,
+) -> Result<(), MdnsResponseError> {
+    if key.is_empty() || !key.is_ascii() || key.contains('=') || key == "dnsaddr" {
+        return Err(MdnsResponseError::InvalidMetadataKey);
+    }
+    let len = key.len() + 1 + value.len();
+    if len > MAX_TXT_VALUE_LENGTH {
+        return Err(MdnsResponseError::TxtRecordTooLong);
+    }
+
+    append_txt_record_header(out, name, ttl_secs);
+
+    append_u16(out, len as u16 + 1);
+    out.push(len as u8);
+    out.extend_from_slice(key.as_bytes());
+    out.push(b'=');
+    out.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
 /// Errors that can occur on encoding an MDNS response.
 #[derive(Debug)]
 enum MdnsResponseError {
     TxtRecordTooLong,
     NonAsciiMultiaddr,
+    InvalidMetadataKey,
 }
 
 impl fmt::Display for MdnsResponseError {
@@ -386,6 +440,10 @@ impl fmt::Display for MdnsResponseError {
                 f,
                 "A multiaddr contains non-ASCII characters when serialized"
             ),
+            MdnsResponseError::InvalidMetadataKey => write!(
+                f,
+                "A metadata key is empty, non-ASCII, contains '=' or is reserved"
+            ),
         }
     }
 }
@@ -413,6 +471,7 @@ mod tests {
             0xf8f8,
             my_peer_id,
             vec![&addr1, &addr2].into_iter(),
+            &BTreeMap::new(),
             Duration::from_secs(60),
         );
         for packet in packets {
@@ -420,6 +479,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn build_query_response_repeats_metadata_in_every_packet() {
+        let my_peer_id = identity::Keypair::generate_ed25519().public().to_peer_id();
+        let addresses = (0..2 * MAX_RECORDS_PER_PACKET as u16)
+            .map(|port| format!("/ip4/1.2.3.4/tcp/{port}").parse().unwrap())
+            .collect::<Vec<Multiaddr>>();
+        let metadata = BTreeMap::from([
+            ("role".to_owned(), "relay server".to_owned()),
+            ("invalid=key".to_owned(), "value".to_owned()),
+        ]);
+
+        let packets = build_query_response(
+            0xf8f8,
+            my_peer_id,
+            addresses.iter(),
+            &metadata,
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(packets.len(), 3);
+        for packet in packets {
+            let message = Message::from_vec(&packet).unwrap();
+            let metadata_records = message
+                .additionals()
+                .iter()
+                .filter(|record| {
+                    record
+                        .data()
+                        .and_then(|data| data.as_txt())
+                        .is_some_and(|txt| {
+                            txt.txt_data()
+                                .iter()
+                                .any(|entry| entry.as_ref() == b"role=relay server")
+                        })
+                })
+                .count();
+            assert_eq!(metadata_records, 1);
+        }
+    }
+
     #[test]
     fn build_service_discovery_response_correct() {
         let query = build_service_discovery_response(0x1234, Duration::from_secs(120));
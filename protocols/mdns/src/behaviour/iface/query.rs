@@ -30,7 +30,7 @@ use libp2p_core::{
 };
 use libp2p_identity::PeerId;
 use std::time::Instant;
-use std::{fmt, net::SocketAddr, str, time::Duration};
+use std::{collections::BTreeMap, fmt, net::SocketAddr, str, time::Duration};
 
 /// A valid mDNS packet received by the service.
 #[derive(Debug)]
@@ -171,7 +171,7 @@ impl MdnsResponse {
         &self,
         now: Instant,
         local_peer_id: PeerId,
-    ) -> impl Iterator<Item = (PeerId, Multiaddr, Instant)> + '_ {
+    ) -> impl Iterator<Item = (PeerId, Multiaddr, BTreeMap<String, String>, Instant)> + '_ {
         self.discovered_peers()
             .filter(move |peer| peer.id() != &local_peer_id)
             .flat_map(move |peer| {
@@ -182,7 +182,12 @@ impl MdnsResponse {
                     let new_addr = address_translation(address, &observed)?;
                     let new_addr = new_addr.with_p2p(*peer.id()).ok()?;
 
-                    Some((*peer.id(), new_addr, new_expiration))
+                    Some((
+                        *peer.id(),
+                        new_addr,
+                        peer.metadata().clone(),
+                        new_expiration,
+                    ))
                 })
             })
     }
@@ -220,6 +225,8 @@ impl fmt::Debug for MdnsResponse {
 /// A peer discovered by the service.
 pub(crate) struct MdnsPeer {
     addrs: Vec<Multiaddr>,
+    /// Application-defined `key=value` entries of the TXT records.
+    metadata: BTreeMap<String, String>,
     /// Id of the peer.
     peer_id: PeerId,
     /// TTL of the record in seconds.
@@ -230,6 +237,7 @@ impl MdnsPeer {
     /// Creates a new `MdnsPeer` based on the provided `Packet`.
     pub(crate) fn new(packet: &Message, record_value: &Name, ttl: u32) -> Option<MdnsPeer> {
         let mut my_peer_id: Option<PeerId> = None;
+        let mut metadata = BTreeMap::new();
         let addrs = packet
             .additionals()
             .iter()
@@ -250,6 +258,9 @@ impl MdnsPeer {
                 let addr = dns::decode_character_string(txt).ok()?;
 
                 if !addr.starts_with(b"dnsaddr=") {
+                    if let Some((key, value)) = parse_metadata(txt) {
+                        metadata.insert(key, value);
+                    }
                     return None;
                 }
 
@@ -273,6 +284,7 @@ impl MdnsPeer {
 
         my_peer_id.map(|peer_id| MdnsPeer {
             addrs,
+            metadata,
             peer_id,
             ttl,
         })
@@ -296,6 +308,20 @@ impl MdnsPeer {
     pub(crate) fn addresses(&self) -> &Vec<Multiaddr> {
         &self.addrs
     }
+
+    /// Returns the metadata the peer advertises.
+    pub(crate) fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+}
+
+/// Parses a `key=value` metadata entry of a TXT record.
+fn parse_metadata(txt: &[u8]) -> Option<(String, String)> {
+    let (key, value) = str::from_utf8(txt).ok()?.split_once('=')?;
+    if key.is_empty() {
+        return None;
+    }
+    Some((key.to_owned(), value.to_owned()))
 }
 
 impl fmt::Debug for MdnsPeer {
@@ -325,6 +351,7 @@ mod tests {
             0xf8f8,
             peer_id,
             vec![&addr1, &addr2].into_iter(),
+            &BTreeMap::from([("role".to_owned(), "relay server".to_owned())]),
             Duration::from_secs(60),
         );
 
@@ -347,6 +374,8 @@ mod tests {
 
             let peer = MdnsPeer::new(&packet, record_value, ttl).expect("fail to create peer");
             assert_eq!(peer.peer_id, peer_id);
+            assert_eq!(peer.addrs.len(), 2);
+            assert_eq!(peer.metadata["role"], "relay server");
         }
     }
 }
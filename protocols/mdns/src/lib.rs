@@ -32,9 +32,12 @@
 //! implements the `NetworkBehaviour` trait. This struct will automatically discover other
 //! libp2p nodes on the local network.
 //!
+//! Nodes can advertise application-defined key/value pairs via [`Config::metadata`], e.g. to let
+//! others filter peers by role before dialing them.
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+use std::collections::BTreeMap;
 use std::net::{Ipv4Addr, Ipv6Addr};
 use std::time::Duration;
 
@@ -72,6 +75,12 @@ pub struct Config {
     pub query_interval: Duration,
     /// Use IPv6 instead of IPv4.
     pub enable_ipv6: bool,
+    /// Application-defined metadata to advertise as `key=value` TXT records alongside the
+    /// addresses, surfaced to remotes in [`Event::Discovered`].
+    ///
+    /// Keys must be non-empty ASCII strings without `=`, and each entry must not exceed 254 bytes.
+    /// Invalid entries are not advertised.
+    pub metadata: BTreeMap<String, String>,
 }
 
 impl Default for Config {
@@ -80,6 +89,7 @@ impl Default for Config {
             ttl: Duration::from_secs(6 * 60),
             query_interval: Duration::from_secs(5 * 60),
             enable_ipv6: false,
+            metadata: BTreeMap::new(),
         }
     }
 }
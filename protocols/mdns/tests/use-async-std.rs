@@ -103,7 +103,7 @@ async fn test_no_expiration_on_close_async_std() {
     // 1. Connect via address from mDNS event
     loop {
         if let Event::Discovered(peers) = a.next_behaviour_event().await {
-            if let Some((_, addr)) = peers.into_iter().find(|(p, _)| p == &b_peer_id) {
+            if let Some((_, addr, _)) = peers.into_iter().find(|(p, _, _)| p == &b_peer_id) {
                 a.dial_and_wait(addr).await;
                 break;
             }
@@ -127,6 +127,36 @@ async fn test_no_expiration_on_close_async_std() {
     .await;
 }
 
+#[async_std::test]
+async fn test_discovery_metadata_async_std() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let config = Config {
+        metadata: [("role".to_owned(), "relay server".to_owned())].into(),
+        ..Default::default()
+    };
+
+    let mut a = create_swarm(Config::default()).await;
+
+    let b = create_swarm(config).await;
+    let b_peer_id = *b.local_peer_id();
+    async_std::task::spawn(b.loop_on_next());
+
+    loop {
+        if let Event::Discovered(peers) = a.next_behaviour_event().await {
+            if let Some((_, _, metadata)) = peers.into_iter().find(|(p, _, _)| p == &b_peer_id) {
+                assert_eq!(
+                    metadata.get("role").map(String::as_str),
+                    Some("relay server")
+                );
+                return;
+            }
+        }
+    }
+}
+
 async fn run_discovery_test(config: Config) {
     let mut a = create_swarm(config.clone()).await;
     let a_peer_id = *a.local_peer_id();
@@ -140,12 +170,12 @@ async fn run_discovery_test(config: Config) {
     while !discovered_a && !discovered_b {
         match futures::future::select(a.next_behaviour_event(), b.next_behaviour_event()).await {
             Either::Left((Event::Discovered(peers), _)) => {
-                if peers.into_iter().any(|(p, _)| p == b_peer_id) {
+                if peers.into_iter().any(|(p, _, _)| p == b_peer_id) {
                     discovered_b = true;
                 }
             }
             Either::Right((Event::Discovered(peers), _)) => {
-                if peers.into_iter().any(|(p, _)| p == a_peer_id) {
+                if peers.into_iter().any(|(p, _, _)| p == a_peer_id) {
                     discovered_a = true;
                 }
             }
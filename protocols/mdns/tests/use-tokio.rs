@@ -94,12 +94,12 @@ async fn run_discovery_test(config: Config) {
     while !discovered_a && !discovered_b {
         match futures::future::select(a.next_behaviour_event(), b.next_behaviour_event()).await {
             Either::Left((Event::Discovered(peers), _)) => {
-                if peers.into_iter().any(|(p, _)| p == b_peer_id) {
+                if peers.into_iter().any(|(p, _, _)| p == b_peer_id) {
                     discovered_b = true;
                 }
             }
             Either::Right((Event::Discovered(peers), _)) => {
-                if peers.into_iter().any(|(p, _)| p == a_peer_id) {
+                if peers.into_iter().any(|(p, _, _)| p == a_peer_id) {
                     discovered_a = true;
                 }
             }
@@ -253,7 +253,7 @@ async fn perf(
 ) -> Result<Run> {
     swarm.behaviour_mut().perf(server_peer_id, params)?;
 
-    let duration = loop {
+    let run = loop {
         match swarm.next().await.unwrap() {
             SwarmEvent::Behaviour(client::Event {
                 id: _,
@@ -280,15 +280,14 @@ async fn perf(
             }
             SwarmEvent::Behaviour(client::Event {
                 id: _,
-                result: Ok(RunUpdate::Final(Final { duration })),
-            }) => break duration,
+                result: Ok(RunUpdate::Final(run)),
+            }) => break run,
             e => panic!("{e:?}"),
         };
     };
 
-    let run = Run { params, duration };
-
     tracing::info!("{run}");
+    let Final { run, .. } = run;
 
     Ok(run)
 }
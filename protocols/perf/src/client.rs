@@ -29,7 +29,7 @@ use void::Void;
 
 static NEXT_RUN_ID: AtomicUsize = AtomicUsize::new(1);
 
-/// Connection identifier.
+/// Identifier of a run started via [`Behaviour::perf`].
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RunId(usize);
 
@@ -40,6 +40,7 @@ impl RunId {
     }
 }
 
+/// An error of a run.
 #[derive(thiserror::Error, Debug)]
 pub enum RunError {
     #[error(transparent)]
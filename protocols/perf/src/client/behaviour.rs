@@ -37,12 +37,17 @@ use crate::{client::handler::Handler, RunUpdate};
 
 use super::{RunError, RunId};
 
+/// An update of a run started via [`Behaviour::perf`].
 #[derive(Debug)]
 pub struct Event {
+    /// The run the update belongs to.
     pub id: RunId,
+    /// The progress of the run, or the error that ended it.
     pub result: Result<RunUpdate, RunError>,
 }
 
+/// Measures the throughput and latency of connections to peers running the
+/// [`server::Behaviour`](crate::server::Behaviour).
 #[derive(Default)]
 pub struct Behaviour {
     /// Queue of actions to return when polled.
@@ -56,6 +61,12 @@ impl Behaviour {
         Self::default()
     }
 
+    /// Starts a run on a connection to the given peer, sending and receiving the amount of data
+    /// specified by `params`.
+    ///
+    /// Progress of the run is reported every second via [`RunUpdate::Intermediate`], followed by
+    /// the result of the run via [`RunUpdate::Final`]. Runs on the same connection are executed
+    /// concurrently, each on its own stream.
     pub fn perf(&mut self, server: PeerId, params: RunParams) -> Result<RunId, NotConnected> {
         if !self.connected.contains(&server) {
             return Err(NotConnected {});
@@ -73,6 +84,7 @@ impl Behaviour {
     }
 }
 
+/// The peer passed to [`Behaviour::perf`] is not connected.
 #[derive(thiserror::Error, Debug)]
 pub struct NotConnected();
 
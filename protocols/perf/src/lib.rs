@@ -20,7 +20,12 @@
 
 //! Implementation of the [libp2p perf protocol](https://github.com/libp2p/specs/pull/478/).
 //!
-//! Do not use in untrusted environments.
+//! The protocol measures the throughput and latency of connections in-band. A peer running the
+//! [`client::Behaviour`] uploads and downloads a requested amount of data to and from a connected
+//! peer running the [`server::Behaviour`], reporting [intermediate](Intermediate) progress and
+//! the [final](Final) result of each run.
+//!
+//! Do not use in untrusted environments, as the server sends any requested amount of data.
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
@@ -33,20 +38,28 @@ pub mod client;
 mod protocol;
 pub mod server;
 
+/// The protocol name of the perf protocol.
 pub const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/perf/1.0.0");
 const RUN_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 const MAX_PARALLEL_RUNS_PER_CONNECTION: usize = 1_000;
 
+/// Progress of a run of the [`client::Behaviour`].
 #[derive(Debug, Clone, Copy)]
 pub enum RunUpdate {
+    /// Progress made since the previous update, reported every second.
     Intermediate(Intermediate),
+    /// The result of the completed run.
     Final(Final),
 }
 
+/// Progress of a run since the previous [`RunUpdate::Intermediate`].
 #[derive(Debug, Clone, Copy)]
 pub struct Intermediate {
+    /// The time since the previous update.
     pub duration: Duration,
+    /// The number of bytes sent since the previous update.
     pub sent: usize,
+    /// The number of bytes received since the previous update.
     pub received: usize,
 }
 
@@ -70,9 +83,25 @@ impl Display for Intermediate {
     }
 }
 
+/// The result of a completed run of the [`client::Behaviour`].
 #[derive(Debug, Clone, Copy)]
 pub struct Final {
-    pub duration: RunDuration,
+    /// The data transferred and the time it took.
+    pub run: Run,
+    /// The time from completing the upload until the first response of the server, which
+    /// approximates the round-trip time of the connection.
+    pub latency: Duration,
+}
+
+impl Display for Final {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, latency {:.4} s",
+            self.run,
+            self.latency.as_secs_f64()
+        )
+    }
 }
 
 /// Parameters for a single run, i.e. one stream, sending and receiving data.
@@ -81,23 +110,49 @@ pub struct Final {
 /// send, both as the client and the server.
 #[derive(Debug, Clone, Copy)]
 pub struct RunParams {
+    /// The number of bytes to send.
     pub to_send: usize,
+    /// The number of bytes to receive.
     pub to_receive: usize,
 }
 
 /// Duration for a single run, i.e. one stream, sending and receiving data.
 #[derive(Debug, Clone, Copy)]
 pub struct RunDuration {
+    /// The time it took to send all data.
     pub upload: Duration,
+    /// The time it took to receive all data.
     pub download: Duration,
 }
 
+/// A single run, i.e. one stream, sending and receiving data.
 #[derive(Debug, Clone, Copy)]
 pub struct Run {
+    /// The amount of data sent and received.
     pub params: RunParams,
+    /// The time it took to send and receive the data.
     pub duration: RunDuration,
 }
 
+impl Run {
+    /// The upload throughput in bytes per second.
+    pub fn upload_throughput(&self) -> f64 {
+        throughput(self.params.to_send, self.duration.upload)
+    }
+
+    /// The download throughput in bytes per second.
+    pub fn download_throughput(&self) -> f64 {
+        throughput(self.params.to_receive, self.duration.download)
+    }
+}
+
+fn throughput(bytes: usize, duration: Duration) -> f64 {
+    if duration.is_zero() {
+        return 0.0;
+    }
+    bytes as f64 / duration.as_secs_f64()
+}
+
 const KILO: f64 = 1024.0;
 const MEGA: f64 = KILO * 1024.0;
 const GIGA: f64 = MEGA * 1024.0;
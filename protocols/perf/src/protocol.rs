@@ -117,12 +117,15 @@ async fn send_receive_inner<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
     }
 
     let write_done = Instant::now();
+    let mut latency = None;
     let mut received = 0;
     let mut intermittend_received = 0;
 
-    while received < to_receive {
+    // Read at least once to measure the latency, even if there is nothing to receive, in which
+    // case the server closes the stream right away.
+    while latency.is_none() || received < to_receive {
         let mut read = stream.read(&mut receive_buf);
-        received += loop {
+        let n = loop {
             match select(&mut delay, &mut read).await {
                 Either::Left((_, _)) => {
                     delay.reset(REPORT_INTERVAL);
@@ -140,16 +143,31 @@ async fn send_receive_inner<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
                 }
                 Either::Right((n, _)) => break n?,
             }
+        };
+        latency.get_or_insert_with(|| write_done.elapsed());
+        if n == 0 && received < to_receive {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("stream closed after receiving {received} of {to_receive} bytes"),
+            ));
         }
+        received += n;
     }
 
     let read_done = Instant::now();
 
     Ok(Final {
-        duration: RunDuration {
-            upload: write_done.duration_since(write_start),
-            download: read_done.duration_since(write_done),
+        run: Run {
+            params: RunParams {
+                to_send: sent,
+                to_receive: received,
+            },
+            duration: RunDuration {
+                upload: write_done.duration_since(write_start),
+                download: read_done.duration_since(write_done),
+            },
         },
+        latency: latency.expect("read at least once"),
     })
 }
 
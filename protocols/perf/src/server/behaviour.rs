@@ -33,12 +33,16 @@ use libp2p_swarm::{
 use crate::server::handler::Handler;
 use crate::Run;
 
+/// A run of a client completed.
 #[derive(Debug)]
 pub struct Event {
+    /// The client that started the run.
     pub remote_peer_id: PeerId,
+    /// The data transferred and the time it took, from the perspective of the server.
     pub stats: Run,
 }
 
+/// Serves the runs of peers running the [`client::Behaviour`](crate::client::Behaviour).
 #[derive(Default)]
 pub struct Behaviour {
     /// Queue of actions to return when polled.
@@ -20,7 +20,7 @@
 
 use libp2p_perf::{
     client::{self},
-    server, RunParams,
+    server, Final, RunParams, RunUpdate,
 };
 use libp2p_swarm::{Swarm, SwarmEvent};
 use libp2p_swarm_test::SwarmExt;
@@ -28,6 +28,31 @@ use tracing_subscriber::EnvFilter;
 
 #[tokio::test]
 async fn perf() {
+    let final_update = run(RunParams {
+        to_send: 0,
+        to_receive: 0,
+    })
+    .await;
+    assert_eq!(final_update.run.params.to_send, 0);
+    assert_eq!(final_update.run.params.to_receive, 0);
+}
+
+#[tokio::test]
+async fn reports_throughput_and_latency() {
+    let Final { run, latency } = run(RunParams {
+        to_send: 100 * 1024,
+        to_receive: 200 * 1024,
+    })
+    .await;
+
+    assert_eq!(run.params.to_send, 100 * 1024);
+    assert_eq!(run.params.to_receive, 200 * 1024);
+    assert!(run.upload_throughput() > 0.0);
+    assert!(run.download_throughput() > 0.0);
+    assert!(latency <= run.duration.download);
+}
+
+async fn run(params: RunParams) -> Final {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
         .try_init();
@@ -41,25 +66,22 @@ async fn perf() {
 
     tokio::task::spawn(server.loop_on_next());
 
-    client
-        .behaviour_mut()
-        .perf(
-            server_peer_id,
-            RunParams {
-                to_send: 0,
-                to_receive: 0,
-            },
-        )
-        .unwrap();
+    client.behaviour_mut().perf(server_peer_id, params).unwrap();
 
     client
         .wait(|e| match e {
             SwarmEvent::IncomingConnection { .. } => panic!(),
             SwarmEvent::ConnectionEstablished { .. } => None,
             SwarmEvent::Dialing { .. } => None,
-            SwarmEvent::Behaviour(client::Event { result, .. }) => Some(result),
+            SwarmEvent::Behaviour(client::Event {
+                result: Ok(RunUpdate::Intermediate(_)),
+                ..
+            }) => None,
+            SwarmEvent::Behaviour(client::Event {
+                result: Ok(RunUpdate::Final(final_update)),
+                ..
+            }) => Some(final_update),
             e => panic!("{e:?}"),
         })
         .await
-        .unwrap();
 }
@@ -25,6 +25,7 @@ use futures_timer::Delay;
 use libp2p_core::upgrade::ReadyUpgrade;
 use libp2p_swarm::handler::{
     ConnectionEvent, DialUpgradeError, FullyNegotiatedInbound, FullyNegotiatedOutbound,
+    StreamPriority,
 };
 use libp2p_swarm::{
     ConnectionHandler, ConnectionHandlerEvent, Stream, StreamProtocol, StreamUpgradeError,
@@ -37,7 +38,6 @@ use std::{
     task::{Context, Poll},
     time::Duration,
 };
-use void::Void;
 
 /// The configuration for outbound pings.
 #[derive(Debug, Clone)]
@@ -46,6 +46,10 @@ pub struct Config {
     timeout: Duration,
     /// The duration between outbound pings.
     interval: Duration,
+    /// The bounds of the interval in adaptive mode, see [`Config::with_adaptive_interval`].
+    adaptive_interval: Option<(Duration, Duration)>,
+    /// The number of round-trip times per peer kept for [`RttStats`](crate::RttStats).
+    rtt_window: usize,
 }
 
 impl Config {
@@ -53,16 +57,20 @@ impl Config {
     ///
     ///   * [`Config::with_interval`] 15s
     ///   * [`Config::with_timeout`] 20s
+    ///   * [`Config::with_rtt_window`] 32
     ///
     /// These settings have the following effect:
     ///
     ///   * A ping is sent every 15 seconds on a healthy connection.
     ///   * Every ping sent must yield a response within 20 seconds in order to
     ///     be successful.
+    ///   * Round-trip time statistics are computed over the last 32 pings to a peer.
     pub fn new() -> Self {
         Self {
             timeout: Duration::from_secs(20),
             interval: Duration::from_secs(15),
+            adaptive_interval: None,
+            rtt_window: 32,
         }
     }
 
@@ -73,10 +81,46 @@ impl Config {
     }
 
     /// Sets the ping interval.
+    ///
+    /// In adaptive mode, this is the interval before the first ping on a connection.
     pub fn with_interval(mut self, d: Duration) -> Self {
         self.interval = d;
         self
     }
+
+    /// Enables the adaptive ping interval.
+    ///
+    /// Every successful ping doubles the interval to the next ping, up to `max`, such that stable
+    /// connections are pinged less frequently. A failure resets the interval to `min` to quickly
+    /// detect whether the connection is still alive.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is zero or greater than `max`.
+    pub fn with_adaptive_interval(mut self, min: Duration, max: Duration) -> Self {
+        assert!(
+            !min.is_zero() && min <= max,
+            "min. interval has to be non-zero and not greater than max. interval"
+        );
+        self.adaptive_interval = Some((min, max));
+        self
+    }
+
+    /// Sets the number of most recent round-trip times per peer that
+    /// [`Behaviour::rtt_stats`](crate::Behaviour::rtt_stats) is computed over.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn with_rtt_window(mut self, size: usize) -> Self {
+        assert!(size > 0, "RTT window must not be empty");
+        self.rtt_window = size;
+        self
+    }
+
+    pub(crate) fn rtt_window(&self) -> usize {
+        self.rtt_window
+    }
 }
 
 impl Default for Config {
@@ -100,9 +144,20 @@ pub enum Failure {
 }
 
 impl Failure {
-    fn other(e: impl std::error::Error + Send + Sync + 'static) -> Self {
+    pub(crate) fn other(e: impl std::error::Error + Send + Sync + 'static) -> Self {
         Self::Other { error: Box::new(e) }
     }
+
+    /// Returns a copy of the failure, with the error of [`Failure::Other`] reduced to its message.
+    pub(crate) fn duplicate(&self) -> Self {
+        match self {
+            Failure::Timeout => Failure::Timeout,
+            Failure::Unsupported => Failure::Unsupported,
+            Failure::Other { error } => Failure::Other {
+                error: error.to_string().into(),
+            },
+        }
+    }
 }
 
 impl fmt::Display for Failure {
@@ -132,6 +187,8 @@ pub struct Handler {
     config: Config,
     /// The timer used for the delay to the next ping.
     interval: Delay,
+    /// The current duration between outbound pings, which only changes in adaptive mode.
+    current_interval: Duration,
     /// Outbound ping failures that are pending to be processed by `poll()`.
     pending_errors: VecDeque<Failure>,
     /// The number of consecutive ping failures that occurred.
@@ -146,8 +203,15 @@ pub struct Handler {
     inbound: Option<PongFuture>,
     /// Tracks the state of our handler.
     state: State,
+    /// Whether a ping was requested by the behaviour, whose outcome is reported even if it is
+    /// the first failure.
+    ping_requested: bool,
 }
 
+/// A request of the behaviour to ping the remote right away.
+#[derive(Debug)]
+pub struct PingNow;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum State {
     /// We are inactive because the other peer doesn't support ping.
@@ -165,6 +229,7 @@ impl Handler {
     /// Builds a new [`Handler`] with the given configuration.
     pub fn new(config: Config) -> Self {
         Handler {
+            current_interval: config.interval,
             config,
             interval: Delay::new(Duration::new(0, 0)),
             pending_errors: VecDeque::with_capacity(2),
@@ -172,6 +237,7 @@ impl Handler {
             outbound: None,
             inbound: None,
             state: State::Active,
+            ping_requested: false,
         }
     }
 
@@ -204,10 +270,23 @@ impl Handler {
 
         self.pending_errors.push_front(error);
     }
+
+    /// Resets the timer to the next outbound ping, adapting the interval to the outcome of the
+    /// previous ping if the adaptive mode is enabled.
+    fn reset_interval(&mut self, success: bool) {
+        if let Some((min, max)) = self.config.adaptive_interval {
+            self.current_interval = if success {
+                self.current_interval.saturating_mul(2).clamp(min, max)
+            } else {
+                min
+            };
+        }
+        self.interval.reset(self.current_interval);
+    }
 }
 
 impl ConnectionHandler for Handler {
-    type FromBehaviour = Void;
+    type FromBehaviour = PingNow;
     type ToBehaviour = Result<Duration, Failure>;
     type InboundProtocol = ReadyUpgrade<StreamProtocol>;
     type OutboundProtocol = ReadyUpgrade<StreamProtocol>;
@@ -218,7 +297,20 @@ impl ConnectionHandler for Handler {
         SubstreamProtocol::new(ReadyUpgrade::new(PROTOCOL_NAME), ())
     }
 
-    fn on_behaviour_event(&mut self, _: Void) {}
+    fn on_behaviour_event(&mut self, _: PingNow) {
+        self.ping_requested = true;
+
+        match (&self.state, &self.outbound) {
+            // Report the missing support again.
+            (State::Inactive { .. }, _) => self.state = State::Inactive { reported: false },
+            // Ping right away instead of waiting for the interval to elapse.
+            (State::Active, None | Some(OutboundState::Idle(_))) => {
+                self.interval.reset(Duration::ZERO)
+            }
+            // The outcome of the ongoing ping is reported.
+            (State::Active, Some(OutboundState::OpenStream | OutboundState::Ping(_))) => {}
+        }
+    }
 
     #[tracing::instrument(level = "trace", name = "ConnectionHandler::poll", skip(self, cx))]
     fn poll(
@@ -232,6 +324,7 @@ impl ConnectionHandler for Handler {
             }
             State::Inactive { reported: false } => {
                 self.state = State::Inactive { reported: true };
+                self.ping_requested = false;
                 return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Err(
                     Failure::Unsupported,
                 )));
@@ -268,7 +361,7 @@ impl ConnectionHandler for Handler {
                 // for each ping to have successful ping exchanges with peers
                 // that use a single substream, since every successful ping
                 // resets `failures` to `0`.
-                if self.failures > 1 {
+                if self.failures > 1 || std::mem::take(&mut self.ping_requested) {
                     return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Err(error)));
                 }
             }
@@ -283,12 +376,13 @@ impl ConnectionHandler for Handler {
                     Poll::Ready(Ok((stream, rtt))) => {
                         tracing::debug!(?rtt, "ping succeeded");
                         self.failures = 0;
-                        self.interval.reset(self.config.interval);
+                        self.ping_requested = false;
+                        self.reset_interval(true);
                         self.outbound = Some(OutboundState::Idle(stream));
                         return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Ok(rtt)));
                     }
                     Poll::Ready(Err(e)) => {
-                        self.interval.reset(self.config.interval);
+                        self.reset_interval(false);
                         self.pending_errors.push_front(e);
                     }
                 },
@@ -311,7 +405,9 @@ impl ConnectionHandler for Handler {
                     Poll::Pending => break,
                     Poll::Ready(()) => {
                         self.outbound = Some(OutboundState::OpenStream);
-                        let protocol = SubstreamProtocol::new(ReadyUpgrade::new(PROTOCOL_NAME), ());
+                        // Pings measure latency, they must not queue up behind bulk transfers.
+                        let protocol = SubstreamProtocol::new(ReadyUpgrade::new(PROTOCOL_NAME), ())
+                            .with_priority(StreamPriority::HIGH);
                         return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
                             protocol,
                         });
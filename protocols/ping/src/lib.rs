@@ -42,6 +42,13 @@
 //! - [`Swarm::close_connection`](libp2p_swarm::Swarm::close_connection) to close a specific connection
 //! - [`Swarm::disconnect_peer_id`](libp2p_swarm::Swarm::disconnect_peer_id) to close all connections to a peer
 //!
+//! Round-trip times of the most recent pings to a peer are aggregated into [`RttStats`], see
+//! [`Behaviour::rtt_stats`]. With [`Config::with_adaptive_interval`], the ping interval backs off
+//! on stable connections and becomes aggressive again after a failure.
+//!
+//! To measure the round-trip time on demand, use [`Behaviour::ping_once`], which resolves with
+//! the outcome of the next ping to the peer.
+//!
 //! [`Swarm`]: libp2p_swarm::Swarm
 //! [`Transport`]: libp2p_core::Transport
 
@@ -49,22 +56,30 @@
 
 mod handler;
 mod protocol;
+mod stats;
 
-use handler::Handler;
+use futures::channel::oneshot;
+use futures::prelude::*;
+use handler::{Handler, PingNow};
 use libp2p_core::{Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
-    behaviour::FromSwarm, ConnectionDenied, ConnectionId, NetworkBehaviour, THandler,
-    THandlerInEvent, THandlerOutEvent, ToSwarm,
+    behaviour::{ConnectionClosed, ConnectionEstablished, FromSwarm},
+    ConnectionDenied, ConnectionId, NetworkBehaviour, NotifyHandler, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
 };
+use stats::RttWindow;
 use std::time::Duration;
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
+    io,
+    pin::Pin,
     task::{Context, Poll},
 };
 
 pub use self::protocol::PROTOCOL_NAME;
 pub use handler::{Config, Failure};
+pub use stats::RttStats;
 
 /// A [`NetworkBehaviour`] that responds to inbound pings and
 /// periodically sends outbound pings on every established connection.
@@ -75,6 +90,14 @@ pub struct Behaviour {
     config: Config,
     /// Queue of events to yield to the swarm.
     events: VecDeque<Event>,
+    /// Round-trip times of the most recent successful pings per peer.
+    rtts: HashMap<PeerId, RttWindow>,
+    /// The established connections per peer.
+    connections: HashMap<PeerId, Vec<ConnectionId>>,
+    /// Pings requested via [`Behaviour::ping_once`] to be sent to the handlers.
+    pending_requests: VecDeque<(PeerId, ConnectionId)>,
+    /// The senders of the results of pings requested via [`Behaviour::ping_once`] per connection.
+    pending_results: HashMap<ConnectionId, Vec<oneshot::Sender<Result<Duration, Failure>>>>,
 }
 
 /// Event generated by the `Ping` network behaviour.
@@ -94,6 +117,68 @@ impl Behaviour {
         Self {
             config,
             events: VecDeque::new(),
+            rtts: HashMap::new(),
+            connections: HashMap::new(),
+            pending_requests: VecDeque::new(),
+            pending_results: HashMap::new(),
+        }
+    }
+
+    /// Pings the given peer right away, returning a future that resolves with the outcome.
+    ///
+    /// The ping is sent on an established connection to the peer, instead of waiting for the
+    /// next periodic ping. If a ping is already in progress on the connection, its outcome is
+    /// reported instead. The outcome is also reported as [`Event`].
+    ///
+    /// The future resolves with a [`Failure::Other`] if the peer is not connected or the
+    /// connection is closed before the ping completes.
+    pub fn ping_once(&mut self, peer: PeerId) -> PingOnce {
+        let (tx, rx) = oneshot::channel();
+
+        match self.connections.get(&peer).and_then(|c| c.first()) {
+            Some(connection) => {
+                self.pending_results
+                    .entry(*connection)
+                    .or_default()
+                    .push(tx);
+                self.pending_requests.push_back((peer, *connection));
+            }
+            None => {
+                let _ = tx.send(Err(Failure::other(io::Error::from(
+                    io::ErrorKind::NotConnected,
+                ))));
+            }
+        }
+
+        PingOnce { inner: rx }
+    }
+
+    /// Returns the round-trip time statistics of the given peer over the most recent successful
+    /// pings on any of its connections.
+    ///
+    /// Returns `None` if no ping to the peer succeeded yet. Statistics are discarded once the
+    /// last connection to the peer is closed.
+    pub fn rtt_stats(&self, peer: &PeerId) -> Option<RttStats> {
+        self.rtts.get(peer)?.stats()
+    }
+}
+
+/// The outcome of a ping requested via [`Behaviour::ping_once`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct PingOnce {
+    inner: oneshot::Receiver<Result<Duration, Failure>>,
+}
+
+impl Future for PingOnce {
+    type Output = Result<Duration, Failure>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match futures::ready!(self.inner.poll_unpin(cx)) {
+            Ok(result) => Poll::Ready(result),
+            Err(oneshot::Canceled) => Poll::Ready(Err(Failure::other(io::Error::from(
+                io::ErrorKind::ConnectionAborted,
+            )))),
         }
     }
 }
@@ -134,6 +219,19 @@ impl NetworkBehaviour for Behaviour {
         connection: ConnectionId,
         result: THandlerOutEvent<Self>,
     ) {
+        if let Ok(rtt) = result {
+            self.rtts
+                .entry(peer)
+                .or_insert_with(|| RttWindow::new(self.config.rtt_window()))
+                .push(rtt);
+        }
+        for tx in self.pending_results.remove(&connection).unwrap_or_default() {
+            let result = match &result {
+                Ok(rtt) => Ok(*rtt),
+                Err(failure) => Err(failure.duplicate()),
+            };
+            let _ = tx.send(result);
+        }
         self.events.push_front(Event {
             peer,
             connection,
@@ -143,6 +241,13 @@ impl NetworkBehaviour for Behaviour {
 
     #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
     fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Some((peer_id, connection)) = self.pending_requests.pop_front() {
+            return Poll::Ready(ToSwarm::NotifyHandler {
+                peer_id,
+                handler: NotifyHandler::One(connection),
+                event: PingNow,
+            });
+        }
         if let Some(e) = self.events.pop_back() {
             Poll::Ready(ToSwarm::GenerateEvent(e))
         } else {
@@ -150,5 +255,34 @@ impl NetworkBehaviour for Behaviour {
         }
     }
 
-    fn on_swarm_event(&mut self, _event: FromSwarm) {}
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id,
+                connection_id,
+                ..
+            }) => {
+                self.connections
+                    .entry(peer_id)
+                    .or_default()
+                    .push(connection_id);
+            }
+            FromSwarm::ConnectionClosed(ConnectionClosed {
+                peer_id,
+                connection_id,
+                remaining_established,
+                ..
+            }) => {
+                self.pending_results.remove(&connection_id);
+                if let Some(connections) = self.connections.get_mut(&peer_id) {
+                    connections.retain(|c| *c != connection_id);
+                }
+                if remaining_established == 0 {
+                    self.connections.remove(&peer_id);
+                    self.rtts.remove(&peer_id);
+                }
+            }
+            _ => {}
+        }
+    }
 }
@@ -0,0 +1,126 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::{collections::VecDeque, time::Duration};
+
+/// Round-trip time statistics of a peer over the most recent successful pings.
+///
+/// See [`Behaviour::rtt_stats`](crate::Behaviour::rtt_stats).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RttStats {
+    /// The number of samples the statistics are based on.
+    pub samples: usize,
+    /// The smallest round-trip time.
+    pub min: Duration,
+    /// The mean round-trip time.
+    pub avg: Duration,
+    /// The 95th percentile of the round-trip times.
+    pub p95: Duration,
+    /// The most recent round-trip time.
+    pub latest: Duration,
+}
+
+/// A sliding window over the most recent round-trip times of a peer.
+#[derive(Debug)]
+pub(crate) struct RttWindow {
+    samples: VecDeque<Duration>,
+    capacity: usize,
+}
+
+impl RttWindow {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records a new sample, evicting the oldest one if the window is full.
+    pub(crate) fn push(&mut self, rtt: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(rtt);
+    }
+
+    /// Computes the statistics over the current window, if it holds any samples.
+    pub(crate) fn stats(&self) -> Option<RttStats> {
+        let latest = *self.samples.back()?;
+
+        let mut sorted = self.samples.iter().copied().collect::<Vec<_>>();
+        sorted.sort_unstable();
+
+        let n = sorted.len();
+        let sum = sorted.iter().sum::<Duration>();
+        // Nearest-rank percentile.
+        let p95_rank = (n * 95).div_ceil(100);
+
+        Some(RttStats {
+            samples: n,
+            min: sorted[0],
+            avg: sum / n as u32,
+            p95: sorted[p95_rank - 1],
+            latest,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn empty_window_has_no_stats() {
+        assert_eq!(RttWindow::new(10).stats(), None);
+    }
+
+    #[test]
+    fn computes_min_avg_p95() {
+        let mut window = RttWindow::new(100);
+        for i in (1..=100).rev() {
+            window.push(ms(i));
+        }
+
+        let stats = window.stats().unwrap();
+        assert_eq!(stats.samples, 100);
+        assert_eq!(stats.min, ms(1));
+        assert_eq!(stats.avg, Duration::from_micros(50_500));
+        assert_eq!(stats.p95, ms(95));
+        assert_eq!(stats.latest, ms(1));
+    }
+
+    #[test]
+    fn evicts_oldest_samples() {
+        let mut window = RttWindow::new(3);
+        for rtt in [100, 1, 2, 3] {
+            window.push(ms(rtt));
+        }
+
+        let stats = window.stats().unwrap();
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.min, ms(1));
+        assert_eq!(stats.avg, ms(2));
+        assert_eq!(stats.p95, ms(3));
+    }
+}
@@ -20,6 +20,7 @@
 
 //! Integration tests for the `Ping` network behaviour.
 
+use libp2p_identity::PeerId;
 use libp2p_ping as ping;
 use libp2p_swarm::dummy;
 use libp2p_swarm::{Swarm, SwarmEvent};
@@ -61,6 +62,75 @@ fn assert_ping_rtt_less_than_50ms(e: ping::Event) {
     assert!(rtt < Duration::from_millis(50))
 }
 
+#[test]
+fn rtt_stats_cover_sliding_window() {
+    let cfg = ping::Config::new()
+        .with_interval(Duration::from_millis(10))
+        .with_adaptive_interval(Duration::from_millis(10), Duration::from_millis(20))
+        .with_rtt_window(3);
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+    let mut swarm2 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+
+    async_std::task::block_on(async {
+        swarm1.listen().with_memory_addr_external().await;
+        swarm2.connect(&mut swarm1).await;
+
+        let peer1 = *swarm1.local_peer_id();
+        assert_eq!(swarm2.behaviour().rtt_stats(&peer1), None);
+
+        for expected_samples in [1, 2, 3, 3] {
+            let ([_], [e2]): ([ping::Event; 1], [ping::Event; 1]) =
+                libp2p_swarm_test::drive(&mut swarm1, &mut swarm2).await;
+            let rtt = e2.result.expect("a ping success");
+
+            let stats = swarm2.behaviour().rtt_stats(&peer1).unwrap();
+            assert_eq!(stats.samples, expected_samples);
+            assert_eq!(stats.latest, rtt);
+            assert!(stats.min <= stats.avg && stats.avg <= stats.p95);
+        }
+
+        swarm2.disconnect_peer_id(peer1).unwrap();
+        loop {
+            if let SwarmEvent::ConnectionClosed { .. } = swarm2.next_swarm_event().await {
+                break;
+            }
+        }
+        assert_eq!(swarm2.behaviour().rtt_stats(&peer1), None);
+    });
+}
+
+#[test]
+fn ping_once_resolves_with_rtt() {
+    // Periodic pings after the initial one would not happen within the test.
+    let cfg = ping::Config::new().with_interval(Duration::from_secs(60));
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+    let mut swarm2 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+
+    async_std::task::block_on(async {
+        let unknown_peer = PeerId::random();
+        let result = swarm2.behaviour_mut().ping_once(unknown_peer).await;
+        assert!(matches!(result, Err(ping::Failure::Other { .. })));
+
+        swarm1.listen().with_memory_addr_external().await;
+        swarm2.connect(&mut swarm1).await;
+        let peer1 = *swarm1.local_peer_id();
+
+        // Wait for the initial ping on the new connection.
+        let ([_], [_]): ([ping::Event; 1], [ping::Event; 1]) =
+            libp2p_swarm_test::drive(&mut swarm1, &mut swarm2).await;
+        async_std::task::spawn(swarm1.loop_on_next());
+
+        let ping = swarm2.behaviour_mut().ping_once(peer1);
+        let event = swarm2.next_behaviour_event().await;
+        assert_eq!(event.peer, peer1);
+
+        let rtt = ping.await.expect("a ping success");
+        assert_eq!(rtt, event.result.unwrap());
+    });
+}
+
 #[test]
 fn unsupported_doesnt_fail() {
     let mut swarm1 = Swarm::new_ephemeral(|_| dummy::Behaviour);
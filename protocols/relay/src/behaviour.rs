@@ -22,7 +22,10 @@
 
 pub(crate) mod handler;
 pub(crate) mod rate_limiter;
+pub(crate) mod stats;
 use crate::behaviour::handler::Handler;
+use crate::behaviour::stats::{DenialReason, Stats, StatsStream, Subscribers};
+use crate::copy_future::RelayedBytes;
 use crate::multiaddr_ext::MultiaddrExt;
 use crate::proto;
 use crate::protocol::{inbound_hop, outbound_stop};
@@ -38,6 +41,7 @@ use libp2p_swarm::{
 use std::collections::{hash_map, HashMap, HashSet, VecDeque};
 use std::num::NonZeroU32;
 use std::ops::Add;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use web_time::Instant;
@@ -92,6 +96,46 @@ impl Config {
             ));
         self
     }
+
+    /// Denies reservations of peers that received more than `limit` bytes
+    /// via circuits within the last `interval`.
+    pub fn reservation_bytes_per_peer(mut self, limit: u64, interval: Duration) -> Self {
+        self.reservation_rate_limiters
+            .push(rate_limiter::new_bytes_per_peer(
+                rate_limiter::ByteQuotaConfig { limit, interval },
+            ));
+        self
+    }
+
+    /// Denies reservations from IP addresses that received more than `limit`
+    /// bytes via circuits within the last `interval`.
+    pub fn reservation_bytes_per_ip(mut self, limit: u64, interval: Duration) -> Self {
+        self.reservation_rate_limiters
+            .push(rate_limiter::new_bytes_per_ip(
+                rate_limiter::ByteQuotaConfig { limit, interval },
+            ));
+        self
+    }
+
+    /// Denies circuits of source peers that relayed more than `limit` bytes
+    /// within the last `interval`.
+    pub fn circuit_src_bytes_per_peer(mut self, limit: u64, interval: Duration) -> Self {
+        self.circuit_src_rate_limiters
+            .push(rate_limiter::new_bytes_per_peer(
+                rate_limiter::ByteQuotaConfig { limit, interval },
+            ));
+        self
+    }
+
+    /// Denies circuits from source IP addresses that relayed more than `limit`
+    /// bytes within the last `interval`.
+    pub fn circuit_src_bytes_per_ip(mut self, limit: u64, interval: Duration) -> Self {
+        self.circuit_src_rate_limiters
+            .push(rate_limiter::new_bytes_per_ip(
+                rate_limiter::ByteQuotaConfig { limit, interval },
+            ));
+        self
+    }
 }
 
 impl std::fmt::Debug for Config {
@@ -225,6 +269,17 @@ pub enum Event {
         dst_peer_id: PeerId,
         error: inbound_hop::Error,
     },
+    /// The number of bytes relayed on a circuit that has closed, e.g. for billing or monitoring.
+    ///
+    /// Emitted right before the corresponding [`Event::CircuitClosed`].
+    CircuitBytesRelayed {
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+        /// The bytes sent from the source to the destination.
+        src_to_dst: u64,
+        /// The bytes sent from the destination to the source.
+        dst_to_src: u64,
+    },
     /// An inbound circuit has closed.
     CircuitClosed {
         src_peer_id: PeerId,
@@ -247,6 +302,13 @@ pub struct Behaviour {
     queued_actions: VecDeque<ToSwarm<Event, THandlerInEvent<Self>>>,
 
     external_addresses: ExternalAddresses,
+
+    /// Remote addresses of the direct connections, used to account relayed bytes.
+    connection_addresses: HashMap<ConnectionId, Multiaddr>,
+
+    /// Cumulative counters, the bytes relayed excluding those of active circuits.
+    stats: Stats,
+    stats_subscribers: Subscribers,
 }
 
 impl Behaviour {
@@ -258,9 +320,30 @@ impl Behaviour {
             circuits: Default::default(),
             queued_actions: Default::default(),
             external_addresses: Default::default(),
+            connection_addresses: Default::default(),
+            stats: Default::default(),
+            stats_subscribers: Default::default(),
         }
     }
 
+    /// Returns a snapshot of the reservations, circuits and denied requests of the relay.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            active_reservations: self.reservations.values().map(|cs| cs.len()).sum(),
+            active_circuits: self.circuits.len(),
+            bytes_relayed: self.stats.bytes_relayed + self.circuits.relayed_bytes(),
+            ..self.stats.clone()
+        }
+    }
+
+    /// Subscribes to snapshots of the [`Stats`] of the relay, taken every `interval`, e.g. to
+    /// export them to a monitoring system.
+    ///
+    /// The subscription ends when the returned [`StatsStream`] is dropped.
+    pub fn subscribe_stats(&mut self, interval: Duration) -> StatsStream {
+        self.stats_subscribers.subscribe(interval)
+    }
+
     fn on_connection_closed(
         &mut self,
         ConnectionClosed {
@@ -279,10 +362,11 @@ impl Behaviour {
         for circuit in self
             .circuits
             .remove_by_connection(peer_id, connection_id)
-            .iter()
+            .iter_mut()
             // Only emit [`CircuitClosed`] for accepted requests.
             .filter(|c| matches!(c.status, CircuitStatus::Accepted))
         {
+            self.on_circuit_closed(circuit);
             self.queued_actions
                 .push_back(ToSwarm::GenerateEvent(Event::CircuitClosed {
                     src_peer_id: circuit.src_peer_id,
@@ -290,6 +374,35 @@ impl Behaviour {
                     error: Some(std::io::ErrorKind::ConnectionAborted.into()),
                 }));
         }
+
+        self.connection_addresses.remove(&connection_id);
+    }
+
+    /// Accounts the bytes relayed on the active circuits since they were last accounted to the
+    /// source and destination peers, such that long-lived circuits count towards their quota
+    /// before they close.
+    fn account_relayed_bytes(&mut self, now: Instant) {
+        for circuit in self.circuits.circuits.values_mut() {
+            record_relayed_bytes(&mut self.config, &self.connection_addresses, circuit, now);
+        }
+    }
+
+    /// Accounts the bytes relayed on a closed circuit to the source and destination peer.
+    fn on_circuit_closed(&mut self, circuit: &mut Circuit) {
+        let now = Instant::now();
+        let src_to_dst = circuit.relayed_bytes.src_to_dst();
+        let dst_to_src = circuit.relayed_bytes.dst_to_src();
+        self.stats.bytes_relayed += src_to_dst + dst_to_src;
+
+        record_relayed_bytes(&mut self.config, &self.connection_addresses, circuit, now);
+
+        self.queued_actions
+            .push_back(ToSwarm::GenerateEvent(Event::CircuitBytesRelayed {
+                src_peer_id: circuit.src_peer_id,
+                dst_peer_id: circuit.dst_peer_id,
+                src_to_dst,
+                dst_to_src,
+            }));
     }
 }
 
@@ -299,7 +412,7 @@ impl NetworkBehaviour for Behaviour {
 
     fn handle_established_inbound_connection(
         &mut self,
-        _: ConnectionId,
+        connection_id: ConnectionId,
         _: PeerId,
         local_addr: &Multiaddr,
         remote_addr: &Multiaddr,
@@ -309,6 +422,9 @@ impl NetworkBehaviour for Behaviour {
             return Ok(Either::Right(dummy::ConnectionHandler));
         }
 
+        self.connection_addresses
+            .insert(connection_id, remote_addr.clone());
+
         Ok(Either::Left(Handler::new(
             handler::Config {
                 reservation_duration: self.config.reservation_duration,
@@ -324,7 +440,7 @@ impl NetworkBehaviour for Behaviour {
 
     fn handle_established_outbound_connection(
         &mut self,
-        _: ConnectionId,
+        connection_id: ConnectionId,
         _: PeerId,
         addr: &Multiaddr,
         role_override: Endpoint,
@@ -334,6 +450,9 @@ impl NetworkBehaviour for Behaviour {
             return Ok(Either::Right(dummy::ConnectionHandler));
         }
 
+        self.connection_addresses
+            .insert(connection_id, addr.clone());
+
         Ok(Either::Left(Handler::new(
             handler::Config {
                 reservation_duration: self.config.reservation_duration,
@@ -373,6 +492,7 @@ impl NetworkBehaviour for Behaviour {
                 renewed,
             } => {
                 let now = Instant::now();
+                self.account_relayed_bytes(now);
 
                 assert!(
                     !endpoint.is_relayed(),
@@ -380,30 +500,41 @@ impl NetworkBehaviour for Behaviour {
                      denies all inbound substreams."
                 );
 
-                let action = if
+                let denial = if
                 // Deny if it is a new reservation and exceeds `max_reservations_per_peer`.
-                (!renewed
+                !renewed
                     && self
                         .reservations
                         .get(&event_source)
                         .map(|cs| cs.len())
                         .unwrap_or(0)
-                        > self.config.max_reservations_per_peer)
-                    // Deny if it exceeds `max_reservations`.
-                    || self
-                        .reservations
-                        .values()
-                        .map(|cs| cs.len())
-                        .sum::<usize>()
-                        >= self.config.max_reservations
-                    // Deny if it exceeds the allowed rate of reservations.
-                    || !self
-                        .config
-                        .reservation_rate_limiters
-                        .iter_mut()
-                        .all(|limiter| {
-                            limiter.try_next(event_source, endpoint.get_remote_address(), now)
-                        }) {
+                        > self.config.max_reservations_per_peer
+                {
+                    Some(DenialReason::PeerLimit)
+                }
+                // Deny if it exceeds `max_reservations`.
+                else if self.reservations.values().map(|cs| cs.len()).sum::<usize>()
+                    >= self.config.max_reservations
+                {
+                    Some(DenialReason::TotalLimit)
+                }
+                // Deny if it exceeds the allowed rate of reservations.
+                else if !self
+                    .config
+                    .reservation_rate_limiters
+                    .iter_mut()
+                    .all(|limiter| {
+                        limiter.try_next(event_source, endpoint.get_remote_address(), now)
+                    })
+                {
+                    Some(DenialReason::RateLimit)
+                } else {
+                    None
+                };
+
+                let action = if let Some(reason) = denial {
+                    *self.stats.reservations_denied.entry(reason).or_default() += 1;
+
                     ToSwarm::NotifyHandler {
                         handler: NotifyHandler::One(connection),
                         peer_id: event_source,
@@ -441,6 +572,8 @@ impl NetworkBehaviour for Behaviour {
                 self.queued_actions.push_back(action);
             }
             handler::Event::ReservationReqAccepted { renewed } => {
+                self.stats.reservations_accepted += 1;
+
                 // Ensure local eventual consistent reservation state matches handler (source of
                 // truth).
                 self.reservations
@@ -507,6 +640,7 @@ impl NetworkBehaviour for Behaviour {
                 endpoint,
             } => {
                 let now = Instant::now();
+                self.account_relayed_bytes(now);
 
                 assert!(
                     !endpoint.is_relayed(),
@@ -514,16 +648,28 @@ impl NetworkBehaviour for Behaviour {
                      denies all inbound substreams."
                 );
 
-                let action = if self.circuits.num_circuits_of_peer(event_source)
+                let denial = if self.circuits.num_circuits_of_peer(event_source)
                     > self.config.max_circuits_per_peer
-                    || self.circuits.len() >= self.config.max_circuits
-                    || !self
-                        .config
-                        .circuit_src_rate_limiters
-                        .iter_mut()
-                        .all(|limiter| {
-                            limiter.try_next(event_source, endpoint.get_remote_address(), now)
-                        }) {
+                {
+                    Some(DenialReason::PeerLimit)
+                } else if self.circuits.len() >= self.config.max_circuits {
+                    Some(DenialReason::TotalLimit)
+                } else if !self
+                    .config
+                    .circuit_src_rate_limiters
+                    .iter_mut()
+                    .all(|limiter| {
+                        limiter.try_next(event_source, endpoint.get_remote_address(), now)
+                    })
+                {
+                    Some(DenialReason::RateLimit)
+                } else {
+                    None
+                };
+
+                let action = if let Some(reason) = denial {
+                    *self.stats.circuits_denied.entry(reason).or_default() += 1;
+
                     // Deny circuit exceeding limits.
                     ToSwarm::NotifyHandler {
                         handler: NotifyHandler::One(connection),
@@ -544,8 +690,11 @@ impl NetworkBehaviour for Behaviour {
                         status: CircuitStatus::Accepting,
                         src_peer_id: event_source,
                         src_connection_id: connection,
+                        src_addr: endpoint.get_remote_address().clone(),
                         dst_peer_id: inbound_circuit_req.dst(),
                         dst_connection_id: *dst_conn,
+                        relayed_bytes: Default::default(),
+                        accounted_bytes: 0,
                     });
 
                     ToSwarm::NotifyHandler {
@@ -559,6 +708,12 @@ impl NetworkBehaviour for Behaviour {
                         }),
                     }
                 } else {
+                    *self
+                        .stats
+                        .circuits_denied
+                        .entry(DenialReason::NoReservation)
+                        .or_default() += 1;
+
                     // Deny circuit request if no reservation present.
                     ToSwarm::NotifyHandler {
                         handler: NotifyHandler::One(connection),
@@ -612,6 +767,12 @@ impl NetworkBehaviour for Behaviour {
                 dst_stream,
                 dst_pending_data,
             } => {
+                let relayed_bytes = self
+                    .circuits
+                    .get(circuit_id)
+                    .map(|c| c.relayed_bytes.clone())
+                    .unwrap_or_default();
+
                 self.queued_actions.push_back(ToSwarm::NotifyHandler {
                     handler: NotifyHandler::One(src_connection_id),
                     peer_id: src_peer_id,
@@ -621,6 +782,7 @@ impl NetworkBehaviour for Behaviour {
                         inbound_circuit_req,
                         dst_stream,
                         dst_pending_data,
+                        relayed_bytes,
                     }),
                 });
             }
@@ -632,6 +794,12 @@ impl NetworkBehaviour for Behaviour {
                 status,
                 error,
             } => {
+                *self
+                    .stats
+                    .circuits_denied
+                    .entry(DenialReason::ConnectionFailed)
+                    .or_default() += 1;
+
                 self.queued_actions.push_back(ToSwarm::NotifyHandler {
                     handler: NotifyHandler::One(src_connection_id),
                     peer_id: src_peer_id,
@@ -654,6 +822,7 @@ impl NetworkBehaviour for Behaviour {
                 dst_peer_id,
                 circuit_id,
             } => {
+                self.stats.circuits_accepted += 1;
                 self.circuits.accepted(circuit_id);
                 self.queued_actions
                     .push_back(ToSwarm::GenerateEvent(Event::CircuitReqAccepted {
@@ -681,7 +850,9 @@ impl NetworkBehaviour for Behaviour {
                 circuit_id,
                 error,
             } => {
-                self.circuits.remove(circuit_id);
+                if let Some(mut circuit) = self.circuits.remove(circuit_id) {
+                    self.on_circuit_closed(&mut circuit);
+                }
 
                 self.queued_actions
                     .push_back(ToSwarm::GenerateEvent(Event::CircuitClosed {
@@ -694,7 +865,14 @@ impl NetworkBehaviour for Behaviour {
     }
 
     #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
-    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        let mut subscribers = std::mem::take(&mut self.stats_subscribers);
+        subscribers.poll(cx, || self.stats());
+        self.stats_subscribers = subscribers;
+
         if let Some(to_swarm) = self.queued_actions.pop_front() {
             return Poll::Ready(to_swarm);
         }
@@ -703,6 +881,31 @@ impl NetworkBehaviour for Behaviour {
     }
 }
 
+/// Records the bytes relayed on a circuit since they were last recorded with the rate limiters
+/// of the source and destination peer.
+fn record_relayed_bytes(
+    config: &mut Config,
+    connection_addresses: &HashMap<ConnectionId, Multiaddr>,
+    circuit: &mut Circuit,
+    now: Instant,
+) {
+    let total = circuit.relayed_bytes.src_to_dst() + circuit.relayed_bytes.dst_to_src();
+    let bytes = total.saturating_sub(circuit.accounted_bytes);
+    if bytes == 0 {
+        return;
+    }
+    circuit.accounted_bytes = total;
+
+    for limiter in config.circuit_src_rate_limiters.iter_mut() {
+        limiter.record_relayed_bytes(circuit.src_peer_id, &circuit.src_addr, bytes, now);
+    }
+    if let Some(dst_addr) = connection_addresses.get(&circuit.dst_connection_id) {
+        for limiter in config.reservation_rate_limiters.iter_mut() {
+            limiter.record_relayed_bytes(circuit.dst_peer_id, dst_addr, bytes, now);
+        }
+    }
+}
+
 #[derive(Default)]
 struct CircuitsTracker {
     next_id: CircuitId,
@@ -729,6 +932,10 @@ impl CircuitsTracker {
         };
     }
 
+    fn get(&self, circuit_id: CircuitId) -> Option<&Circuit> {
+        self.circuits.get(&circuit_id)
+    }
+
     fn remove(&mut self, circuit_id: CircuitId) -> Option<Circuit> {
         self.circuits.remove(&circuit_id)
     }
@@ -759,6 +966,14 @@ impl CircuitsTracker {
         removed
     }
 
+    /// The bytes relayed on the active circuits.
+    fn relayed_bytes(&self) -> u64 {
+        self.circuits
+            .values()
+            .map(|c| c.relayed_bytes.src_to_dst() + c.relayed_bytes.dst_to_src())
+            .sum()
+    }
+
     fn num_circuits_of_peer(&self, peer: PeerId) -> usize {
         self.circuits
             .iter()
@@ -771,9 +986,13 @@ impl CircuitsTracker {
 struct Circuit {
     src_peer_id: PeerId,
     src_connection_id: ConnectionId,
+    src_addr: Multiaddr,
     dst_peer_id: PeerId,
     dst_connection_id: ConnectionId,
     status: CircuitStatus,
+    relayed_bytes: Arc<RelayedBytes>,
+    /// The bytes of `relayed_bytes` already recorded with the rate limiters.
+    accounted_bytes: u64,
 }
 
 #[derive(Clone)]
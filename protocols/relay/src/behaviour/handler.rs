@@ -19,7 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::behaviour::CircuitId;
-use crate::copy_future::CopyFuture;
+use crate::copy_future::{CopyFuture, RelayedBytes};
 use crate::protocol::{inbound_hop, outbound_stop};
 use crate::{proto, HOP_PROTOCOL_NAME, STOP_PROTOCOL_NAME};
 use bytes::Bytes;
@@ -39,6 +39,7 @@ use libp2p_swarm::{
     StreamUpgradeError, SubstreamProtocol,
 };
 use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use std::{fmt, io};
@@ -80,6 +81,7 @@ pub enum In {
         inbound_circuit_req: inbound_hop::CircuitReq,
         dst_stream: Stream,
         dst_pending_data: Bytes,
+        relayed_bytes: Arc<RelayedBytes>,
     },
 }
 
@@ -126,6 +128,7 @@ impl fmt::Debug for In {
                 dst_peer_id,
                 dst_stream: _,
                 dst_pending_data: _,
+                relayed_bytes: _,
             } => f
                 .debug_struct("In::AcceptAndDriveCircuit")
                 .field("circuit_id", circuit_id)
@@ -558,6 +561,7 @@ impl ConnectionHandler for Handler {
                 inbound_circuit_req,
                 dst_stream,
                 dst_pending_data,
+                relayed_bytes,
             } => {
                 self.circuit_accept_futures.push(
                     inbound_circuit_req
@@ -570,6 +574,7 @@ impl ConnectionHandler for Handler {
                             dst_peer_id,
                             dst_stream,
                             dst_pending_data,
+                            relayed_bytes,
                         })
                         .map_err(move |e| (circuit_id, dst_peer_id, e))
                         .boxed(),
@@ -750,6 +755,7 @@ impl ConnectionHandler for Handler {
                         dst_peer_id,
                         mut dst_stream,
                         dst_pending_data,
+                        relayed_bytes,
                     } = parts;
                     let max_circuit_duration = self.config.max_circuit_duration;
                     let max_circuit_bytes = self.config.max_circuit_bytes;
@@ -762,12 +768,15 @@ impl ConnectionHandler for Handler {
                         .await;
                         result_1?;
                         result_2?;
+                        relayed_bytes.add_src_to_dst(src_pending_data.len() as u64);
+                        relayed_bytes.add_dst_to_src(dst_pending_data.len() as u64);
 
                         CopyFuture::new(
                             src_stream,
                             dst_stream,
                             max_circuit_duration,
                             max_circuit_bytes,
+                            relayed_bytes,
                         )
                         .await?;
 
@@ -903,6 +912,7 @@ struct CircuitParts {
     dst_peer_id: PeerId,
     dst_stream: Stream,
     dst_pending_data: Bytes,
+    relayed_bytes: Arc<RelayedBytes>,
 }
 
 /// Holds everything we know about a to-be-issued `CONNECT` request to a peer.
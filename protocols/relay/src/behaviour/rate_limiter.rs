@@ -35,6 +35,17 @@ use web_time::Instant;
 // number of a peers IP address.
 pub trait RateLimiter: Send {
     fn try_next(&mut self, peer: PeerId, addr: &Multiaddr, now: Instant) -> bool;
+
+    /// Informs the rate limiter about the number of bytes relayed on behalf of a peer,
+    /// allowing decisions in [`RateLimiter::try_next`] based on historical usage.
+    fn record_relayed_bytes(
+        &mut self,
+        _peer: PeerId,
+        _addr: &Multiaddr,
+        _bytes: u64,
+        _now: Instant,
+    ) {
+    }
 }
 
 pub(crate) fn new_per_peer(config: GenericRateLimiterConfig) -> Box<dyn RateLimiter> {
@@ -51,6 +62,20 @@ pub(crate) fn new_per_ip(config: GenericRateLimiterConfig) -> Box<dyn RateLimite
     })
 }
 
+pub(crate) fn new_bytes_per_peer(config: ByteQuotaConfig) -> Box<dyn RateLimiter> {
+    Box::new(ByteQuotaRateLimiter {
+        quota: ByteQuota::new(config),
+        id: |peer_id, _addr: &Multiaddr| Some(peer_id),
+    })
+}
+
+pub(crate) fn new_bytes_per_ip(config: ByteQuotaConfig) -> Box<dyn RateLimiter> {
+    Box::new(ByteQuotaRateLimiter {
+        quota: ByteQuota::new(config),
+        id: |_peer_id, addr: &Multiaddr| multiaddr_to_ip(addr),
+    })
+}
+
 impl<T: FnMut(PeerId, &Multiaddr, Instant) -> bool + Send> RateLimiter for T {
     fn try_next(&mut self, peer: PeerId, addr: &Multiaddr, now: Instant) -> bool {
         self(peer, addr, now)
@@ -168,6 +193,99 @@ impl<Id: Eq + PartialEq + Hash + Clone> GenericRateLimiter<Id> {
     }
 }
 
+/// [`RateLimiter`] denying access once the bytes relayed for an id exceed a [`ByteQuota`].
+struct ByteQuotaRateLimiter<Id, F> {
+    quota: ByteQuota<Id>,
+    id: F,
+}
+
+impl<Id, F> RateLimiter for ByteQuotaRateLimiter<Id, F>
+where
+    Id: Eq + Hash + Clone + Send,
+    F: Fn(PeerId, &Multiaddr) -> Option<Id> + Send,
+{
+    fn try_next(&mut self, peer: PeerId, addr: &Multiaddr, now: Instant) -> bool {
+        (self.id)(peer, addr)
+            .map(|id| self.quota.try_next(&id, now))
+            .unwrap_or(true)
+    }
+
+    fn record_relayed_bytes(&mut self, peer: PeerId, addr: &Multiaddr, bytes: u64, now: Instant) {
+        if let Some(id) = (self.id)(peer, addr) {
+            self.quota.record(id, bytes, now);
+        }
+    }
+}
+
+/// Quota on the number of bytes relayed per id within a sliding window.
+pub(crate) struct ByteQuota<Id> {
+    limit: u64,
+    interval: Duration,
+
+    usage: VecDeque<(Instant, Id, u64)>,
+    totals: HashMap<Id, u64>,
+}
+
+/// Configuration for a [`ByteQuota`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ByteQuotaConfig {
+    // The maximum number of bytes relayed within `interval`.
+    pub(crate) limit: u64,
+    // The length of the sliding window.
+    pub(crate) interval: Duration,
+}
+
+impl<Id: Eq + PartialEq + Hash + Clone> ByteQuota<Id> {
+    pub(crate) fn new(config: ByteQuotaConfig) -> Self {
+        assert!(!config.interval.is_zero());
+
+        Self {
+            limit: config.limit,
+            interval: config.interval,
+            usage: Default::default(),
+            totals: Default::default(),
+        }
+    }
+
+    /// Whether the bytes relayed for `id` within the current window are below the limit.
+    pub(crate) fn try_next(&mut self, id: &Id, now: Instant) -> bool {
+        self.expire(now);
+
+        self.totals.get(id).copied().unwrap_or_default() < self.limit
+    }
+
+    pub(crate) fn record(&mut self, id: Id, bytes: u64, now: Instant) {
+        self.expire(now);
+
+        if bytes == 0 {
+            return;
+        }
+
+        let total = self.totals.entry(id.clone()).or_default();
+        *total = total.saturating_add(bytes);
+        self.usage.push_back((now, id, bytes));
+    }
+
+    fn expire(&mut self, now: Instant) {
+        // Items in `usage` are sorted, thus, if the first ain't expired, none of them are.
+        while let Some((at, _, _)) = self.usage.front() {
+            if now.duration_since(*at) < self.interval {
+                return;
+            }
+
+            let (_, id, bytes) = self.usage.pop_front().expect("Queue not to be empty.");
+            let total = self
+                .totals
+                .get_mut(&id)
+                .expect("Entry can only be removed via expire.");
+            *total = total.saturating_sub(bytes);
+            if *total == 0 {
+                self.totals.remove(&id);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -295,4 +413,31 @@ mod tests {
 
         QuickCheck::new().quickcheck(prop as fn(_, _, _) -> _)
     }
+
+    #[test]
+    fn byte_quota_limits_and_expires() {
+        let now = Instant::now();
+        let mut q = ByteQuota::new(ByteQuotaConfig {
+            limit: 100,
+            interval: Duration::from_secs(10),
+        });
+
+        assert!(q.try_next(&1, now));
+        q.record(1, 60, now);
+        assert!(q.try_next(&1, now));
+
+        let now = now + Duration::from_secs(5);
+        q.record(1, 40, now);
+        assert!(!q.try_next(&1, now));
+        assert!(q.try_next(&2, now));
+
+        // The first 60 bytes leave the window.
+        let now = now + Duration::from_secs(5);
+        assert!(q.try_next(&1, now));
+
+        let now = now + Duration::from_secs(5);
+        assert!(q.try_next(&1, now));
+        assert!(q.totals.is_empty());
+        assert!(q.usage.is_empty());
+    }
 }
@@ -0,0 +1,134 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Statistics of the relay [`Behaviour`](super::Behaviour).
+
+use futures::channel::mpsc;
+use futures::{FutureExt, Stream, StreamExt};
+use futures_timer::Delay;
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// A snapshot of the state and activity of a relay [`Behaviour`](super::Behaviour).
+///
+/// Counters are cumulative since the creation of the behaviour.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of active reservations.
+    pub active_reservations: usize,
+    /// The number of active circuits, including circuits being established.
+    pub active_circuits: usize,
+    /// The number of accepted reservation requests, including renewals.
+    pub reservations_accepted: u64,
+    /// The number of accepted circuit requests.
+    pub circuits_accepted: u64,
+    /// The number of bytes relayed in both directions, including the bytes relayed on active
+    /// circuits.
+    pub bytes_relayed: u64,
+    /// The number of denied reservation requests per reason.
+    pub reservations_denied: BTreeMap<DenialReason, u64>,
+    /// The number of denied circuit requests per reason.
+    pub circuits_denied: BTreeMap<DenialReason, u64>,
+}
+
+/// The reason a reservation or circuit request was denied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum DenialReason {
+    /// The peer exceeded the maximum number of reservations or circuits per peer.
+    PeerLimit,
+    /// The relay reached the maximum number of reservations or circuits.
+    TotalLimit,
+    /// The peer exceeded a rate or byte quota of the [`Config`](super::Config).
+    RateLimit,
+    /// The destination of the circuit has no reservation.
+    NoReservation,
+    /// Connecting to the destination of the circuit failed.
+    ConnectionFailed,
+}
+
+/// A stream of [`Stats`] snapshots, see
+/// [`Behaviour::subscribe_stats`](super::Behaviour::subscribe_stats).
+///
+/// Snapshots are skipped while the previous one has not been consumed yet.
+#[derive(Debug)]
+pub struct StatsStream {
+    receiver: mpsc::Receiver<Stats>,
+}
+
+impl Stream for StatsStream {
+    type Item = Stats;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+struct Subscriber {
+    sender: mpsc::Sender<Stats>,
+    interval: Duration,
+    delay: Delay,
+}
+
+/// Subscribers to periodic [`Stats`] snapshots.
+#[derive(Default)]
+pub(crate) struct Subscribers {
+    subscribers: Vec<Subscriber>,
+}
+
+impl Subscribers {
+    pub(crate) fn subscribe(&mut self, interval: Duration) -> StatsStream {
+        let (sender, receiver) = mpsc::channel(0);
+        self.subscribers.push(Subscriber {
+            sender,
+            interval,
+            delay: Delay::new(interval),
+        });
+
+        StatsStream { receiver }
+    }
+
+    /// Sends a snapshot to all subscribers whose interval elapsed, dropping closed subscriptions.
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>, stats: impl Fn() -> Stats) {
+        let mut snapshot = None;
+
+        self.subscribers.retain_mut(|subscriber| {
+            if subscriber.sender.is_closed() {
+                return false;
+            }
+            if subscriber.delay.poll_unpin(cx).is_pending() {
+                return true;
+            }
+
+            subscriber.delay.reset(subscriber.interval);
+            if subscriber.delay.poll_unpin(cx).is_ready() {
+                cx.waker().wake_by_ref();
+            }
+
+            let stats = snapshot.get_or_insert_with(&stats).clone();
+            match subscriber.sender.try_send(stats) {
+                Ok(()) => true,
+                Err(e) => e.is_full(),
+            }
+        });
+    }
+}
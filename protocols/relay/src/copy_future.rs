@@ -32,9 +32,39 @@ use futures::ready;
 use futures_timer::Delay;
 use std::io;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 
+/// The number of bytes relayed in either direction of a circuit.
+///
+/// Shared between the [`CopyFuture`] driving the circuit and the relay
+/// [`Behaviour`](crate::Behaviour), which reads it once the circuit closes.
+#[derive(Debug, Default)]
+pub struct RelayedBytes {
+    src_to_dst: AtomicU64,
+    dst_to_src: AtomicU64,
+}
+
+impl RelayedBytes {
+    pub(crate) fn add_src_to_dst(&self, n: u64) {
+        self.src_to_dst.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_dst_to_src(&self, n: u64) {
+        self.dst_to_src.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub(crate) fn src_to_dst(&self) -> u64 {
+        self.src_to_dst.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn dst_to_src(&self) -> u64 {
+        self.dst_to_src.load(Ordering::Relaxed)
+    }
+}
+
 pub(crate) struct CopyFuture<S, D> {
     src: BufReader<S>,
     dst: BufReader<D>,
@@ -42,6 +72,7 @@ pub(crate) struct CopyFuture<S, D> {
     max_circuit_duration: Delay,
     max_circuit_bytes: u64,
     bytes_sent: u64,
+    relayed_bytes: Arc<RelayedBytes>,
 }
 
 impl<S: AsyncRead, D: AsyncRead> CopyFuture<S, D> {
@@ -50,6 +81,7 @@ impl<S: AsyncRead, D: AsyncRead> CopyFuture<S, D> {
         dst: D,
         max_circuit_duration: Duration,
         max_circuit_bytes: u64,
+        relayed_bytes: Arc<RelayedBytes>,
     ) -> Self {
         CopyFuture {
             src: BufReader::new(src),
@@ -57,6 +89,7 @@ impl<S: AsyncRead, D: AsyncRead> CopyFuture<S, D> {
             max_circuit_duration: Delay::new(max_circuit_duration),
             max_circuit_bytes,
             bytes_sent: Default::default(),
+            relayed_bytes,
         }
     }
 }
@@ -90,6 +123,7 @@ where
                 Poll::Ready(Ok(0)) => Status::Done,
                 Poll::Ready(Ok(i)) => {
                     this.bytes_sent += i;
+                    this.relayed_bytes.add_src_to_dst(i);
                     Status::Progressed
                 }
                 Poll::Pending => Status::Pending,
@@ -100,6 +134,7 @@ where
                 Poll::Ready(Ok(0)) => Status::Done,
                 Poll::Ready(Ok(i)) => {
                     this.bytes_sent += i;
+                    this.relayed_bytes.add_dst_to_src(i);
                     Status::Progressed
                 }
                 Poll::Pending => Status::Pending,
@@ -222,17 +257,21 @@ mod tests {
                 write: Vec::new(),
             };
 
+            let relayed_bytes = Arc::new(RelayedBytes::default());
             let mut copy_future = CopyFuture::new(
                 connection_a,
                 connection_b,
                 Duration::from_secs(60),
                 max_circuit_bytes,
+                relayed_bytes.clone(),
             );
 
             match block_on(&mut copy_future) {
                 Ok(()) => {
                     assert_eq!(copy_future.src.into_inner().write, b);
                     assert_eq!(copy_future.dst.into_inner().write, a);
+                    assert_eq!(relayed_bytes.src_to_dst(), a.len() as u64);
+                    assert_eq!(relayed_bytes.dst_to_src(), b.len() as u64);
                 }
                 Err(error) => {
                     assert_eq!(error.kind(), ErrorKind::Other);
@@ -288,6 +327,7 @@ mod tests {
             PendingConnection {},
             Duration::from_millis(1),
             u64::MAX,
+            Default::default(),
         );
 
         std::thread::sleep(Duration::from_millis(2));
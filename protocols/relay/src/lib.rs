@@ -39,7 +39,11 @@ mod proto {
     };
 }
 
-pub use behaviour::{rate_limiter::RateLimiter, Behaviour, CircuitId, Config, Event};
+pub use behaviour::{
+    rate_limiter::RateLimiter,
+    stats::{DenialReason, Stats, StatsStream},
+    Behaviour, CircuitId, Config, Event,
+};
 pub use protocol::{HOP_PROTOCOL_NAME, STOP_PROTOCOL_NAME};
 
 /// Types related to the relay protocol inbound.
@@ -64,7 +68,16 @@ pub mod outbound {
 
 /// Everything related to the relay protocol from a client's perspective.
 pub mod client {
-    pub use crate::priv_client::{new, transport::Transport, Behaviour, Connection, Event};
+    pub use crate::priv_client::auto_relay::Behaviour as AutoRelay;
+    pub use crate::priv_client::{
+        new, transport::Transport, Behaviour, Connection, Event, InboundCircuitReq,
+        InboundCircuitStats,
+    };
+
+    /// Automatic selection of relays and maintenance of reservations, see [`AutoRelay`].
+    pub mod auto_relay {
+        pub use crate::priv_client::auto_relay::{Behaviour, Config, Event};
+    }
 
     pub mod transport {
         pub use crate::priv_client::transport::Error;
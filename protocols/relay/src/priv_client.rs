@@ -20,6 +20,7 @@
 
 //! [`NetworkBehaviour`] to act as a circuit relay v2 **client**.
 
+pub(crate) mod auto_relay;
 pub(crate) mod handler;
 pub(crate) mod transport;
 
@@ -43,8 +44,11 @@ use libp2p_swarm::{
     NotifyHandler, Stream, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 use std::collections::{hash_map, HashMap, VecDeque};
+use std::fmt;
 use std::io::{Error, ErrorKind, IoSlice};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use transport::Transport;
 use void::Void;
@@ -68,6 +72,68 @@ pub enum Event {
         src_peer_id: PeerId,
         limit: Option<protocol::Limit>,
     },
+    /// An inbound circuit has been denied, see [`Behaviour::with_inbound_circuit_policy`].
+    InboundCircuitDenied {
+        src_peer_id: PeerId,
+        relay_peer_id: PeerId,
+    },
+}
+
+/// A request of a remote to establish an inbound circuit via a relay, as passed to the policy
+/// set via [`Behaviour::with_inbound_circuit_policy`].
+#[derive(Debug, Clone)]
+pub struct InboundCircuitReq {
+    pub(crate) src_peer_id: PeerId,
+    pub(crate) relay_peer_id: PeerId,
+    pub(crate) relay_addr: Multiaddr,
+    pub(crate) active_circuits: usize,
+}
+
+impl InboundCircuitReq {
+    /// The peer initiating the circuit.
+    pub fn src_peer_id(&self) -> PeerId {
+        self.src_peer_id
+    }
+
+    /// The relay the circuit is established via.
+    pub fn relay_peer_id(&self) -> PeerId {
+        self.relay_peer_id
+    }
+
+    /// The address of the connection to the relay.
+    pub fn relay_addr(&self) -> &Multiaddr {
+        &self.relay_addr
+    }
+
+    /// The number of established inbound relayed connections, across all relays.
+    pub fn active_circuits(&self) -> usize {
+        self.active_circuits
+    }
+}
+
+/// The number of inbound circuits accepted and denied by the local node.
+///
+/// See [`Behaviour::inbound_circuit_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InboundCircuitStats {
+    pub accepted: u64,
+    pub denied: u64,
+}
+
+/// The policy deciding which inbound circuits to accept.
+#[derive(Clone)]
+pub(crate) struct InboundCircuitPolicy(Arc<dyn Fn(&InboundCircuitReq) -> bool + Send + Sync>);
+
+impl InboundCircuitPolicy {
+    pub(crate) fn accepts(&self, req: &InboundCircuitReq) -> bool {
+        (self.0)(req)
+    }
+}
+
+impl fmt::Debug for InboundCircuitPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("InboundCircuitPolicy").finish()
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -95,6 +161,11 @@ pub struct Behaviour {
     queued_actions: VecDeque<ToSwarm<Event, Either<handler::In, Void>>>,
 
     pending_handler_commands: HashMap<ConnectionId, handler::In>,
+
+    inbound_circuit_policy: Option<InboundCircuitPolicy>,
+    /// The number of established inbound relayed connections, shared with the handlers.
+    active_inbound_circuits: Arc<AtomicUsize>,
+    inbound_circuit_stats: InboundCircuitStats,
 }
 
 /// Create a new client relay [`Behaviour`] with it's corresponding [`Transport`].
@@ -107,11 +178,43 @@ pub fn new(local_peer_id: PeerId) -> (Transport, Behaviour) {
         reservation_addresses: Default::default(),
         queued_actions: Default::default(),
         pending_handler_commands: Default::default(),
+        inbound_circuit_policy: None,
+        active_inbound_circuits: Default::default(),
+        inbound_circuit_stats: Default::default(),
     };
     (transport, behaviour)
 }
 
 impl Behaviour {
+    /// Sets a policy deciding whether to accept an inbound circuit, e.g. based on the
+    /// initiating peer, the relay used or the number of active inbound circuits.
+    ///
+    /// Denied circuits are answered with a `PERMISSION_DENIED` status and reported via
+    /// [`Event::InboundCircuitDenied`]. By default, all circuits via relays with a reservation
+    /// are accepted.
+    pub fn with_inbound_circuit_policy<F>(mut self, policy: F) -> Self
+    where
+        F: Fn(&InboundCircuitReq) -> bool + Send + Sync + 'static,
+    {
+        self.inbound_circuit_policy = Some(InboundCircuitPolicy(Arc::new(policy)));
+        self
+    }
+
+    /// Returns the number of inbound circuits accepted and denied so far.
+    pub fn inbound_circuit_stats(&self) -> InboundCircuitStats {
+        self.inbound_circuit_stats
+    }
+
+    fn new_handler(&self, peer: PeerId, remote_addr: Multiaddr) -> Handler {
+        Handler::new(
+            self.local_peer_id,
+            peer,
+            remote_addr,
+            self.inbound_circuit_policy.clone(),
+            self.active_inbound_circuits.clone(),
+        )
+    }
+
     fn on_connection_closed(
         &mut self,
         ConnectionClosed {
@@ -121,6 +224,9 @@ impl Behaviour {
             ..
         }: ConnectionClosed,
     ) {
+        if endpoint.is_relayed() && endpoint.is_listener() {
+            self.active_inbound_circuits.fetch_sub(1, Ordering::Relaxed);
+        }
         if !endpoint.is_relayed() {
             match self.directly_connected_peers.entry(peer_id) {
                 hash_map::Entry::Occupied(mut connections) => {
@@ -163,7 +269,7 @@ impl NetworkBehaviour for Behaviour {
         if local_addr.is_relayed() {
             return Ok(Either::Right(dummy::ConnectionHandler));
         }
-        let mut handler = Handler::new(self.local_peer_id, peer, remote_addr.clone());
+        let mut handler = self.new_handler(peer, remote_addr.clone());
 
         if let Some(event) = self.pending_handler_commands.remove(&connection_id) {
             handler.on_behaviour_event(event)
@@ -183,7 +289,7 @@ impl NetworkBehaviour for Behaviour {
             return Ok(Either::Right(dummy::ConnectionHandler));
         }
 
-        let mut handler = Handler::new(self.local_peer_id, peer, addr.clone());
+        let mut handler = self.new_handler(peer, addr.clone());
 
         if let Some(event) = self.pending_handler_commands.remove(&connection_id) {
             handler.on_behaviour_event(event)
@@ -200,6 +306,9 @@ impl NetworkBehaviour for Behaviour {
                 endpoint,
                 ..
             }) => {
+                if endpoint.is_relayed() && endpoint.is_listener() {
+                    self.active_inbound_circuits.fetch_add(1, Ordering::Relaxed);
+                }
                 if !endpoint.is_relayed() {
                     self.directly_connected_peers
                         .entry(peer_id)
@@ -263,8 +372,16 @@ impl NetworkBehaviour for Behaviour {
                 }
             }
             handler::Event::InboundCircuitEstablished { src_peer_id, limit } => {
+                self.inbound_circuit_stats.accepted += 1;
                 Event::InboundCircuitEstablished { src_peer_id, limit }
             }
+            handler::Event::InboundCircuitDenied { src_peer_id } => {
+                self.inbound_circuit_stats.denied += 1;
+                Event::InboundCircuitDenied {
+                    src_peer_id,
+                    relay_peer_id: event_source,
+                }
+            }
         };
 
         self.queued_actions.push_back(ToSwarm::GenerateEvent(event));
@@ -0,0 +1,323 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! [`NetworkBehaviour`] maintaining reservations on a set of automatically selected relays.
+//!
+//! Relays are discovered by watching which connected peers advertise the relay hop
+//! protocol, e.g. as learned via identify, and can be added explicitly via
+//! [`Behaviour::add_candidate`]. Their addresses are taken from
+//! [`FromSwarm::NewExternalAddrOfPeer`], e.g. as reported by identify or kademlia.
+//!
+//! Reservations are made by listening on the `/p2p-circuit` address of a relay, which
+//! requires the [`client::Transport`](crate::client::Transport) and
+//! [`client::Behaviour`](crate::client::Behaviour) to be part of the same `Swarm`.
+//! The client renews each reservation before it expires. Once a reservation is lost, the
+//! relay is put into a backoff and another candidate is selected.
+
+mod handler;
+
+use crate::multiaddr_ext::MultiaddrExt;
+use futures::stream::{FuturesUnordered, StreamExt};
+use futures_timer::Delay;
+use handler::Handler;
+use libp2p_core::multiaddr::Protocol;
+use libp2p_core::transport::ListenerId;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::behaviour::{
+    ExpiredListenAddr, FromSwarm, ListenerClosed, ListenerError, NewListenAddr, NewListener,
+};
+use libp2p_swarm::{
+    ConnectionDenied, ConnectionId, ListenOpts, NetworkBehaviour, PeerAddresses, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use std::collections::{HashMap, VecDeque};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use void::Void;
+use web_time::Instant;
+
+/// Configuration for the [`Behaviour`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The number of reservations to maintain at the same time.
+    pub max_reservations: usize,
+    /// The time to wait before selecting a relay again after a reservation
+    /// on it failed or was lost.
+    pub backoff: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_reservations: 2,
+            backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+/// The events produced by the [`Behaviour`].
+#[derive(Debug)]
+pub enum Event {
+    /// A reservation on a relay has been accepted and is reachable under the given address.
+    ReservationEstablished {
+        relay_peer_id: PeerId,
+        address: Multiaddr,
+    },
+    /// A reservation on a relay failed or was lost.
+    ///
+    /// The relay is not selected again before [`Config::backoff`] elapsed.
+    ReservationClosed { relay_peer_id: PeerId },
+    /// The set of relayed addresses under which the local node is reachable changed.
+    RelayedAddressesChanged { addresses: Vec<Multiaddr> },
+}
+
+/// A relay that may be selected for a reservation.
+#[derive(Debug, Default)]
+struct Candidate {
+    /// The point in time before which the relay is not selected.
+    backoff_until: Option<Instant>,
+}
+
+/// A reservation requested via [`ToSwarm::ListenOn`].
+#[derive(Debug)]
+struct Reservation {
+    relay_peer_id: PeerId,
+    /// Whether the listener has been registered with the transport.
+    listening: bool,
+    /// The relayed addresses reported by the listener.
+    addresses: Vec<Multiaddr>,
+}
+
+/// [`NetworkBehaviour`] maintaining reservations on a configurable number of relays.
+pub struct Behaviour {
+    config: Config,
+    /// Peers known to support the relay hop protocol.
+    candidates: HashMap<PeerId, Candidate>,
+    /// Known addresses of peers.
+    addresses: PeerAddresses,
+    /// Pending and established reservations, by the listener that requested them.
+    reservations: HashMap<ListenerId, Reservation>,
+    /// Timers waking up the behaviour once the backoff of a candidate elapsed.
+    backoff_timers: FuturesUnordered<Delay>,
+    /// Queue of actions to return when polled.
+    queued_actions: VecDeque<ToSwarm<Event, Void>>,
+}
+
+impl Behaviour {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            candidates: Default::default(),
+            addresses: Default::default(),
+            reservations: Default::default(),
+            backoff_timers: Default::default(),
+            queued_actions: Default::default(),
+        }
+    }
+
+    /// Adds a relay that may be selected for a reservation, reachable under the given address.
+    pub fn add_candidate(&mut self, relay_peer_id: PeerId, address: Multiaddr) {
+        self.addresses.add(relay_peer_id, address);
+        self.candidates.entry(relay_peer_id).or_default();
+    }
+
+    /// Returns the relayed addresses under which the local node is currently reachable.
+    pub fn relayed_addresses(&self) -> impl Iterator<Item = &Multiaddr> {
+        self.reservations.values().flat_map(|r| r.addresses.iter())
+    }
+
+    /// Selects candidates for new reservations until the configured number of reservations is reached.
+    fn select_relays(&mut self) {
+        let now = Instant::now();
+
+        while self.reservations.len() < self.config.max_reservations {
+            let reserved = self
+                .reservations
+                .values()
+                .map(|r| r.relay_peer_id)
+                .collect::<Vec<_>>();
+            let addresses = &mut self.addresses;
+            let selected = self
+                .candidates
+                .iter()
+                .filter(|(peer, candidate)| {
+                    !reserved.contains(peer)
+                        && candidate.backoff_until.map_or(true, |until| until <= now)
+                })
+                .find_map(|(peer, _)| {
+                    let address = addresses.get(peer).find(|a| !a.is_relayed())?;
+                    Some((*peer, address))
+                });
+            let Some((relay_peer_id, address)) = selected else {
+                return;
+            };
+
+            // Addresses in `PeerAddresses` already end with the `/p2p` of the relay.
+            let opts = ListenOpts::new(address.with(Protocol::P2pCircuit));
+            tracing::debug!(relay=%relay_peer_id, address=%opts.address(), "Requesting reservation");
+            self.reservations.insert(
+                opts.listener_id(),
+                Reservation {
+                    relay_peer_id,
+                    listening: false,
+                    addresses: Vec::new(),
+                },
+            );
+            self.queued_actions.push_back(ToSwarm::ListenOn { opts });
+        }
+    }
+
+    /// Puts the relay of a failed or lost reservation into backoff.
+    fn on_reservation_closed(&mut self, reservation: Reservation) {
+        let relay_peer_id = reservation.relay_peer_id;
+        tracing::debug!(relay=%relay_peer_id, "Reservation closed");
+
+        if let Some(candidate) = self.candidates.get_mut(&relay_peer_id) {
+            candidate.backoff_until = Some(Instant::now() + self.config.backoff);
+            self.backoff_timers.push(Delay::new(self.config.backoff));
+        }
+        if !reservation.addresses.is_empty() {
+            self.queue_addresses_changed();
+        }
+        self.queued_actions
+            .push_back(ToSwarm::GenerateEvent(Event::ReservationClosed {
+                relay_peer_id,
+            }));
+    }
+
+    fn queue_addresses_changed(&mut self) {
+        let addresses = self.relayed_addresses().cloned().collect();
+        self.queued_actions
+            .push_back(ToSwarm::GenerateEvent(Event::RelayedAddressesChanged {
+                addresses,
+            }));
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = Handler;
+    type ToSwarm = Event;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(Handler::new())
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(Handler::new())
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        self.addresses.on_swarm_event(&event);
+        match event {
+            FromSwarm::NewListener(NewListener { listener_id }) => {
+                if let Some(reservation) = self.reservations.get_mut(&listener_id) {
+                    reservation.listening = true;
+                }
+            }
+            FromSwarm::NewListenAddr(NewListenAddr { listener_id, addr }) => {
+                if let Some(reservation) = self.reservations.get_mut(&listener_id) {
+                    reservation.addresses.push(addr.clone());
+                    let relay_peer_id = reservation.relay_peer_id;
+                    self.queued_actions.push_back(ToSwarm::GenerateEvent(
+                        Event::ReservationEstablished {
+                            relay_peer_id,
+                            address: addr.clone(),
+                        },
+                    ));
+                    self.queue_addresses_changed();
+                }
+            }
+            FromSwarm::ExpiredListenAddr(ExpiredListenAddr { listener_id, addr }) => {
+                if let Some(reservation) = self.reservations.get_mut(&listener_id) {
+                    let len = reservation.addresses.len();
+                    reservation.addresses.retain(|a| a != addr);
+                    if reservation.addresses.len() != len {
+                        self.queue_addresses_changed();
+                    }
+                }
+            }
+            FromSwarm::ListenerClosed(ListenerClosed { listener_id, .. }) => {
+                if let Some(reservation) = self.reservations.remove(&listener_id) {
+                    self.on_reservation_closed(reservation);
+                }
+            }
+            // An error before `NewListener` means that the transport refused to listen at all.
+            FromSwarm::ListenerError(ListenerError { listener_id, .. })
+                if self
+                    .reservations
+                    .get(&listener_id)
+                    .is_some_and(|r| !r.listening) =>
+            {
+                let reservation = self
+                    .reservations
+                    .remove(&listener_id)
+                    .expect("reservation to exist");
+                self.on_reservation_closed(reservation);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {
+            handler::Event::RelaySupported => {
+                self.candidates.entry(peer_id).or_default();
+            }
+            handler::Event::RelayUnsupported => {
+                self.candidates.remove(&peer_id);
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self, cx))]
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        // Elapsed timers only need to wake us up to reconsider candidates.
+        while let Poll::Ready(Some(())) = self.backoff_timers.poll_next_unpin(cx) {}
+
+        self.select_relays();
+
+        if let Some(action) = self.queued_actions.pop_front() {
+            return Poll::Ready(action);
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::HOP_PROTOCOL_NAME;
+use libp2p_core::upgrade::DeniedUpgrade;
+use libp2p_swarm::handler::{ConnectionEvent, DialUpgradeError, FullyNegotiatedInbound};
+use libp2p_swarm::{
+    ConnectionHandler, ConnectionHandlerEvent, SubstreamProtocol, SupportedProtocols,
+};
+use std::task::{Context, Poll};
+use void::Void;
+
+/// Events reported by the [`Handler`] to the [`Behaviour`](super::Behaviour).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The remote started advertising the relay hop protocol.
+    RelaySupported,
+    /// The remote stopped advertising the relay hop protocol.
+    RelayUnsupported,
+}
+
+/// A connection handler that opens no streams and only watches whether the
+/// remote advertises the relay hop protocol, e.g. as learned via identify.
+pub struct Handler {
+    remote_supported_protocols: SupportedProtocols,
+    /// Whether the remote supports the hop protocol, as last reported to the behaviour.
+    relay_supported: bool,
+    pending_event: Option<Event>,
+}
+
+impl Handler {
+    pub(super) fn new() -> Self {
+        Self {
+            remote_supported_protocols: SupportedProtocols::default(),
+            relay_supported: false,
+            pending_event: None,
+        }
+    }
+}
+
+impl ConnectionHandler for Handler {
+    type FromBehaviour = Void;
+    type ToBehaviour = Event;
+    type InboundProtocol = DeniedUpgrade;
+    type OutboundProtocol = DeniedUpgrade;
+    type InboundOpenInfo = ();
+    type OutboundOpenInfo = Void;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        SubstreamProtocol::new(DeniedUpgrade, ())
+    }
+
+    fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
+        void::unreachable(event)
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
+    > {
+        if let Some(event) = self.pending_event.take() {
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event));
+        }
+
+        Poll::Pending
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+        match event {
+            ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound {
+                protocol, ..
+            }) => void::unreachable(protocol),
+            ConnectionEvent::DialUpgradeError(DialUpgradeError { info, .. }) => {
+                void::unreachable(info)
+            }
+            ConnectionEvent::RemoteProtocolsChange(change) => {
+                if !self.remote_supported_protocols.on_protocols_change(change) {
+                    return;
+                }
+
+                let relay_supported = self
+                    .remote_supported_protocols
+                    .iter()
+                    .any(|p| p == &HOP_PROTOCOL_NAME);
+                if relay_supported != self.relay_supported {
+                    self.relay_supported = relay_supported;
+                    // Only the latest state is of interest to the behaviour.
+                    self.pending_event = Some(if relay_supported {
+                        Event::RelaySupported
+                    } else {
+                        Event::RelayUnsupported
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -21,6 +21,7 @@
 use crate::client::Connection;
 use crate::priv_client::transport;
 use crate::priv_client::transport::ToListenerMsg;
+use crate::priv_client::{InboundCircuitPolicy, InboundCircuitReq};
 use crate::protocol::{self, inbound_stop, outbound_hop};
 use crate::{priv_client, proto, HOP_PROTOCOL_NAME, STOP_PROTOCOL_NAME};
 use futures::channel::mpsc::Sender;
@@ -37,6 +38,8 @@ use libp2p_swarm::{
     SubstreamProtocol,
 };
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use std::{fmt, io};
@@ -90,6 +93,8 @@ pub enum Event {
         src_peer_id: PeerId,
         limit: Option<protocol::Limit>,
     },
+    /// An inbound circuit has been denied by the [`InboundCircuitPolicy`].
+    InboundCircuitDenied { src_peer_id: PeerId },
 }
 
 pub struct Handler {
@@ -125,14 +130,26 @@ pub struct Handler {
         futures_bounded::FuturesSet<Result<(), inbound_stop::Error>>,
 
     reservation: Reservation,
+
+    inbound_circuit_policy: Option<InboundCircuitPolicy>,
+    /// The number of established inbound relayed connections, shared with the behaviour.
+    active_inbound_circuits: Arc<AtomicUsize>,
 }
 
 impl Handler {
-    pub fn new(local_peer_id: PeerId, remote_peer_id: PeerId, remote_addr: Multiaddr) -> Self {
+    pub(crate) fn new(
+        local_peer_id: PeerId,
+        remote_peer_id: PeerId,
+        remote_addr: Multiaddr,
+        inbound_circuit_policy: Option<InboundCircuitPolicy>,
+        active_inbound_circuits: Arc<AtomicUsize>,
+    ) -> Self {
         Self {
             local_peer_id,
             remote_peer_id,
             remote_addr,
+            inbound_circuit_policy,
+            active_inbound_circuits,
             queued_events: Default::default(),
             pending_streams: Default::default(),
             inflight_reserve_requests: futures_bounded::FuturesTupleSet::new(
@@ -155,12 +172,12 @@ impl Handler {
         }
     }
 
-    fn insert_to_deny_futs(&mut self, circuit: inbound_stop::Circuit) {
+    fn insert_to_deny_futs(&mut self, circuit: inbound_stop::Circuit, status: proto::Status) {
         let src_peer_id = circuit.src_peer_id();
 
         if self
             .inflight_outbound_circuit_deny_requests
-            .try_push(circuit.deny(proto::Status::NO_RESERVATION))
+            .try_push(circuit.deny(status))
             .is_err()
         {
             tracing::warn!(
@@ -365,6 +382,23 @@ impl ConnectionHandler for Handler {
                         let src_peer_id = circuit.src_peer_id();
                         let limit = circuit.limit();
 
+                        let req = InboundCircuitReq {
+                            src_peer_id,
+                            relay_peer_id: self.remote_peer_id,
+                            relay_addr: self.remote_addr.clone(),
+                            active_circuits: self.active_inbound_circuits.load(Ordering::Relaxed),
+                        };
+                        if self
+                            .inbound_circuit_policy
+                            .as_ref()
+                            .is_some_and(|policy| !policy.accepts(&req))
+                        {
+                            self.insert_to_deny_futs(circuit, proto::Status::PERMISSION_DENIED);
+                            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                                Event::InboundCircuitDenied { src_peer_id },
+                            ));
+                        }
+
                         let connection = super::ConnectionState::new_inbound(circuit);
 
                         pending_msgs.push_back(
@@ -380,7 +414,7 @@ impl ConnectionHandler for Handler {
                         ));
                     }
                     Reservation::None => {
-                        self.insert_to_deny_futs(circuit);
+                        self.insert_to_deny_futs(circuit, proto::Status::NO_RESERVATION);
                         continue;
                     }
                 },
@@ -21,6 +21,7 @@
 use futures::executor::LocalPool;
 use futures::future::FutureExt;
 use futures::io::{AsyncRead, AsyncWrite};
+use futures::sink::SinkExt;
 use futures::stream::StreamExt;
 use futures::task::Spawn;
 use libp2p_core::multiaddr::{Multiaddr, Protocol};
@@ -226,6 +227,182 @@ fn connect() {
     ));
 }
 
+#[test]
+fn connect_with_relay_fallback() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay();
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    spawn_swarm_on_pool(&pool, relay);
+
+    let mut dst = build_client();
+    let dst_peer_id = *dst.local_peer_id();
+    let dst_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(dst_peer_id));
+
+    dst.listen_on(dst_addr.clone()).unwrap();
+
+    assert!(pool.run_until(wait_for_dial(&mut dst, relay_peer_id)));
+
+    pool.run_until(wait_for_reservation(
+        &mut dst,
+        dst_addr.clone(),
+        relay_peer_id,
+        false, // No renewal.
+    ));
+
+    let mut src = build_client();
+    let src_peer_id = *src.local_peer_id();
+
+    // The direct address is unreachable, thus the relayed one is dialed next.
+    let direct_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    src.dial(
+        DialOpts::peer_id(dst_peer_id)
+            .addresses(vec![dst_addr, direct_addr])
+            .with_relay_fallback(true)
+            .build(),
+    )
+    .unwrap();
+
+    pool.run_until(futures::future::join(
+        connection_established_to(&mut src, relay_peer_id, dst_peer_id),
+        connection_established_to(&mut dst, relay_peer_id, src_peer_id),
+    ));
+}
+
+#[test]
+fn relay_fallback_timeout_starts_with_dial() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    // The reservation is not renewed while the direct dial times out.
+    let mut relay = build_relay_with_config(relay::Config {
+        reservation_duration: Duration::from_secs(60),
+        ..Default::default()
+    });
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    spawn_swarm_on_pool(&pool, relay);
+
+    let mut dst = build_client();
+    let dst_peer_id = *dst.local_peer_id();
+    let dst_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(dst_peer_id));
+
+    dst.listen_on(dst_addr.clone()).unwrap();
+
+    assert!(pool.run_until(wait_for_dial(&mut dst, relay_peer_id)));
+
+    pool.run_until(wait_for_reservation(
+        &mut dst,
+        dst_addr.clone(),
+        relay_peer_id,
+        false, // No renewal.
+    ));
+
+    // The direct address is never polled, thus its dial only fails once it timed out.
+    let direct_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut unresponsive = build_client();
+    unresponsive.listen_on(direct_addr.clone()).unwrap();
+
+    let mut src = build_client();
+    let src_peer_id = *src.local_peer_id();
+
+    // The timeout of the relayed address elapses before the direct dial fails, unless it only
+    // starts with the dial of the relayed address.
+    src.dial(
+        DialOpts::peer_id(dst_peer_id)
+            .addresses(vec![dst_addr.clone(), direct_addr.clone()])
+            .with_relay_fallback(true)
+            .address_timeout(direct_addr, Duration::from_secs(3))
+            .address_timeout(dst_addr, Duration::from_secs(2))
+            .build(),
+    )
+    .unwrap();
+
+    pool.run_until(futures::future::join(
+        connection_established_to(&mut src, relay_peer_id, dst_peer_id),
+        connection_established_to(&mut dst, relay_peer_id, src_peer_id),
+    ));
+}
+
+#[test]
+fn deny_inbound_circuit_by_policy() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay();
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    spawn_swarm_on_pool(&pool, relay);
+
+    let mut src = build_client();
+    let src_peer_id = *src.local_peer_id();
+
+    let mut dst = build_client_with(Config::with_async_std_executor(), |behaviour| {
+        behaviour.with_inbound_circuit_policy(move |req| req.src_peer_id() != src_peer_id)
+    });
+    let dst_peer_id = *dst.local_peer_id();
+    let dst_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(dst_peer_id));
+
+    dst.listen_on(dst_addr.clone()).unwrap();
+
+    assert!(pool.run_until(wait_for_dial(&mut dst, relay_peer_id)));
+
+    pool.run_until(wait_for_reservation(
+        &mut dst,
+        dst_addr.clone(),
+        relay_peer_id,
+        false, // No renewal.
+    ));
+
+    src.dial(dst_addr).unwrap();
+    spawn_swarm_on_pool(&pool, src);
+
+    pool.run_until(dst.wait(|e| match e {
+        SwarmEvent::Behaviour(ClientEvent::Relay(relay::client::Event::InboundCircuitDenied {
+            src_peer_id: peer,
+            relay_peer_id: relay,
+        })) => {
+            assert_eq!(peer, src_peer_id);
+            assert_eq!(relay, relay_peer_id);
+            Some(())
+        }
+        _ => None,
+    }));
+    assert_eq!(
+        dst.behaviour().relay.inbound_circuit_stats(),
+        relay::client::InboundCircuitStats {
+            accepted: 0,
+            denied: 1
+        }
+    );
+}
+
 async fn connection_established_to(
     swarm: &mut Swarm<Client>,
     relay_peer_id: PeerId,
@@ -433,6 +610,335 @@ fn reuse_connection() {
     ));
 }
 
+#[test]
+fn auto_relay_maintains_reservation() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay();
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    spawn_swarm_on_pool(&pool, relay);
+
+    // Nothing listens on this address, thus the reservation on it fails.
+    let unreachable_relay_peer_id = PeerId::random();
+    let unreachable_relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+
+    let mut client = build_auto_relay_client(relay::client::auto_relay::Config {
+        max_reservations: 1,
+        ..Default::default()
+    });
+    let client_peer_id = *client.local_peer_id();
+    let client_addr = relay_addr
+        .clone()
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(client_peer_id));
+
+    let auto_relay = &mut client.behaviour_mut().auto_relay;
+    auto_relay.add_candidate(unreachable_relay_peer_id, unreachable_relay_addr);
+    auto_relay.add_candidate(relay_peer_id, relay_addr);
+
+    pool.run_until(async {
+        let mut reservation_established = false;
+        let mut addresses_changed = false;
+        while !(reservation_established && addresses_changed) {
+            match client.select_next_some().await {
+                SwarmEvent::Behaviour(AutoRelayClientEvent::AutoRelay(
+                    relay::client::auto_relay::Event::ReservationClosed { relay_peer_id },
+                )) => {
+                    assert_eq!(relay_peer_id, unreachable_relay_peer_id);
+                }
+                SwarmEvent::Behaviour(AutoRelayClientEvent::AutoRelay(
+                    relay::client::auto_relay::Event::ReservationEstablished {
+                        relay_peer_id: peer_id,
+                        address,
+                    },
+                )) => {
+                    assert_eq!(peer_id, relay_peer_id);
+                    assert_eq!(address, client_addr);
+                    reservation_established = true;
+                }
+                SwarmEvent::Behaviour(AutoRelayClientEvent::AutoRelay(
+                    relay::client::auto_relay::Event::RelayedAddressesChanged { addresses },
+                )) => {
+                    assert_eq!(addresses, vec![client_addr.clone()]);
+                    addresses_changed = true;
+                }
+                _ => {}
+            }
+        }
+    });
+
+    assert_eq!(
+        client
+            .behaviour()
+            .auto_relay
+            .relayed_addresses()
+            .collect::<Vec<_>>(),
+        vec![&client_addr]
+    );
+}
+
+#[test]
+fn relayed_bytes_count_towards_circuit_quota() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay_with_config(
+        relay::Config {
+            reservation_duration: Duration::from_secs(2),
+            ..Default::default()
+        }
+        .circuit_src_bytes_per_peer(1, Duration::from_secs(60)),
+    );
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    let (mut relay_events_tx, mut relay_events) = futures::channel::mpsc::channel(16);
+    pool.spawner()
+        .spawn_obj(
+            async move {
+                loop {
+                    if let SwarmEvent::Behaviour(RelayEvent::Relay(e)) =
+                        relay.select_next_some().await
+                    {
+                        let _ = relay_events_tx.send(e).await;
+                    }
+                }
+            }
+            .boxed()
+            .into(),
+        )
+        .unwrap();
+
+    let mut dst = build_client();
+    let dst_peer_id = *dst.local_peer_id();
+    let dst_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(dst_peer_id));
+
+    dst.listen_on(dst_addr.clone()).unwrap();
+    assert!(pool.run_until(wait_for_dial(&mut dst, relay_peer_id)));
+    pool.run_until(wait_for_reservation(
+        &mut dst,
+        dst_addr.clone(),
+        relay_peer_id,
+        false, // No renewal.
+    ));
+    spawn_swarm_on_pool(&pool, dst);
+
+    let mut src = build_client();
+    let src_peer_id = *src.local_peer_id();
+
+    src.dial(dst_addr.clone()).unwrap();
+    pool.run_until(connection_established_to(
+        &mut src,
+        relay_peer_id,
+        dst_peer_id,
+    ));
+
+    assert!(src.disconnect_peer_id(dst_peer_id).is_ok());
+    pool.run_until(futures::future::join(
+        async {
+            loop {
+                if let SwarmEvent::ConnectionClosed { peer_id, .. } = src.select_next_some().await {
+                    if peer_id == dst_peer_id {
+                        break;
+                    }
+                }
+            }
+        },
+        async {
+            loop {
+                if let relay::Event::CircuitBytesRelayed {
+                    src_peer_id: src,
+                    dst_peer_id: dst,
+                    src_to_dst,
+                    dst_to_src,
+                } = relay_events.next().await.unwrap()
+                {
+                    assert_eq!(src, src_peer_id);
+                    assert_eq!(dst, dst_peer_id);
+                    assert!(src_to_dst > 0);
+                    assert!(dst_to_src > 0);
+                    break;
+                }
+            }
+        },
+    ));
+
+    // The bytes relayed on the first circuit exhaust the quota of `src`.
+    src.dial(dst_addr).unwrap();
+    pool.run_until(async {
+        loop {
+            match src.select_next_some().await {
+                SwarmEvent::OutgoingConnectionError { peer_id, .. } => {
+                    assert_eq!(peer_id, Some(dst_peer_id));
+                    break;
+                }
+                SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                    assert_ne!(peer_id, dst_peer_id)
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+#[test]
+fn bytes_of_open_circuits_count_towards_circuit_quota() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay_with_config(
+        relay::Config {
+            reservation_duration: Duration::from_secs(60),
+            ..Default::default()
+        }
+        .circuit_src_bytes_per_peer(1, Duration::from_secs(60)),
+    );
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    spawn_swarm_on_pool(&pool, relay);
+
+    let mut dst = build_client();
+    let dst_peer_id = *dst.local_peer_id();
+    let dst_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(dst_peer_id));
+
+    dst.listen_on(dst_addr.clone()).unwrap();
+    assert!(pool.run_until(wait_for_dial(&mut dst, relay_peer_id)));
+    pool.run_until(wait_for_reservation(
+        &mut dst,
+        dst_addr.clone(),
+        relay_peer_id,
+        false, // No renewal.
+    ));
+    spawn_swarm_on_pool(&pool, dst);
+
+    let mut src = build_client();
+
+    src.dial(dst_addr.clone()).unwrap();
+    pool.run_until(connection_established_to(
+        &mut src,
+        relay_peer_id,
+        dst_peer_id,
+    ));
+
+    // The bytes relayed on the still open first circuit exhaust the quota of `src`.
+    src.dial(dst_addr).unwrap();
+    pool.run_until(async {
+        loop {
+            match src.select_next_some().await {
+                SwarmEvent::OutgoingConnectionError { peer_id, .. } => {
+                    assert_eq!(peer_id, Some(dst_peer_id));
+                    break;
+                }
+                SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                    assert_ne!(peer_id, dst_peer_id)
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+#[test]
+fn stats_report_reservations_circuits_and_denials() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay();
+    let relay_peer_id = *relay.local_peer_id();
+    let mut stats = relay
+        .behaviour_mut()
+        .relay
+        .subscribe_stats(Duration::from_millis(10));
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    spawn_swarm_on_pool(&pool, relay);
+
+    let mut dst = build_client();
+    let dst_peer_id = *dst.local_peer_id();
+    let dst_addr = relay_addr
+        .clone()
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(dst_peer_id));
+
+    dst.listen_on(dst_addr.clone()).unwrap();
+    assert!(pool.run_until(wait_for_dial(&mut dst, relay_peer_id)));
+    pool.run_until(wait_for_reservation(
+        &mut dst,
+        dst_addr.clone(),
+        relay_peer_id,
+        false, // No renewal.
+    ));
+    spawn_swarm_on_pool(&pool, dst);
+
+    let mut src = build_client();
+
+    // No peer holds a reservation for a random peer ID, thus the relay denies the circuit.
+    let unknown_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(PeerId::random()));
+    src.dial(unknown_addr).unwrap();
+    pool.run_until(src.wait(|e| match e {
+        SwarmEvent::OutgoingConnectionError { .. } => Some(()),
+        _ => None,
+    }));
+
+    src.dial(dst_addr).unwrap();
+    pool.run_until(connection_established_to(
+        &mut src,
+        relay_peer_id,
+        dst_peer_id,
+    ));
+    spawn_swarm_on_pool(&pool, src);
+
+    let stats = pool.run_until(async {
+        loop {
+            let stats = stats.next().await.unwrap();
+            if stats.circuits_accepted > 0 && stats.bytes_relayed > 0 {
+                break stats;
+            }
+        }
+    });
+
+    assert_eq!(stats.active_reservations, 1);
+    assert_eq!(stats.reservations_accepted, 1);
+    assert_eq!(stats.circuits_accepted, 1);
+    assert!(stats.bytes_relayed > 0);
+    assert!(stats.reservations_denied.is_empty());
+    assert_eq!(
+        stats.circuits_denied,
+        [(relay::DenialReason::NoReservation, 1)].into()
+    );
+}
+
 fn build_relay() -> Swarm<Relay> {
     build_relay_with_config(relay::Config {
         reservation_duration: Duration::from_secs(2),
@@ -462,10 +968,18 @@ fn build_client() -> Swarm<Client> {
 }
 
 fn build_client_with_config(config: Config) -> Swarm<Client> {
+    build_client_with(config, |behaviour| behaviour)
+}
+
+fn build_client_with(
+    config: Config,
+    configure: impl FnOnce(relay::client::Behaviour) -> relay::client::Behaviour,
+) -> Swarm<Client> {
     let local_key = identity::Keypair::generate_ed25519();
     let local_peer_id = local_key.public().to_peer_id();
 
     let (relay_transport, behaviour) = relay::client::new(local_peer_id);
+    let behaviour = configure(behaviour);
     let transport = upgrade_transport(
         OrTransport::new(relay_transport, MemoryTransport::default()).boxed(),
         &local_key,
@@ -482,6 +996,27 @@ fn build_client_with_config(config: Config) -> Swarm<Client> {
     )
 }
 
+fn build_auto_relay_client(config: relay::client::auto_relay::Config) -> Swarm<AutoRelayClient> {
+    let local_key = identity::Keypair::generate_ed25519();
+    let local_peer_id = local_key.public().to_peer_id();
+
+    let (relay_transport, behaviour) = relay::client::new(local_peer_id);
+    let transport = upgrade_transport(
+        OrTransport::new(relay_transport, MemoryTransport::default()).boxed(),
+        &local_key,
+    );
+
+    Swarm::new(
+        transport,
+        AutoRelayClient {
+            relay: behaviour,
+            auto_relay: relay::client::AutoRelay::new(config),
+        },
+        local_peer_id,
+        Config::with_async_std_executor(),
+    )
+}
+
 fn upgrade_transport<StreamSink>(
     transport: Boxed<StreamSink>,
     identity: &identity::Keypair,
@@ -510,6 +1045,13 @@ struct Client {
     ping: ping::Behaviour,
 }
 
+#[derive(NetworkBehaviour)]
+#[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+struct AutoRelayClient {
+    relay: relay::client::Behaviour,
+    auto_relay: relay::client::AutoRelay,
+}
+
 fn spawn_swarm_on_pool<B: NetworkBehaviour + Send>(pool: &LocalPool, swarm: Swarm<B>) {
     pool.spawner()
         .spawn_obj(swarm.collect::<Vec<_>>().map(|_| ()).boxed().into())
@@ -43,6 +43,8 @@ pub struct Behaviour {
 
     waiting_for_register: HashMap<OutboundRequestId, (PeerId, Namespace)>,
     waiting_for_discovery: HashMap<OutboundRequestId, (PeerId, Option<Namespace>)>,
+    /// Paginated discoveries, along with the requested page size.
+    waiting_for_page: HashMap<OutboundRequestId, (PeerId, Option<Namespace>, u64)>,
 
     /// Hold addresses of all peers that we have discovered so far.
     ///
@@ -69,6 +71,7 @@ impl Behaviour {
             keypair,
             waiting_for_register: Default::default(),
             waiting_for_discovery: Default::default(),
+            waiting_for_page: Default::default(),
             discovered_peers: Default::default(),
             registered_namespaces: Default::default(),
             expiring_registrations: FuturesUnordered::from_iter(vec![
@@ -139,6 +142,36 @@ impl Behaviour {
         self.waiting_for_discovery
             .insert(req_id, (rendezvous_node, namespace));
     }
+
+    /// Discover other peers at a given rendezvous peer page by page.
+    ///
+    /// Each page of at most `page_size` registrations is reported as [`Event::DiscoveredPage`].
+    /// The next page is requested automatically until the rendezvous peer returns fewer
+    /// registrations than requested, which is marked as the last page. The cookie of the last
+    /// page can be passed to [`Behaviour::discover`] or [`Behaviour::discover_paginated`] to
+    /// only fetch registrations added afterwards.
+    ///
+    /// Note that a rendezvous peer may cap the page size below `page_size`, in which case the
+    /// first page is considered the last one.
+    pub fn discover_paginated(
+        &mut self,
+        namespace: Option<Namespace>,
+        cookie: Option<Cookie>,
+        page_size: u64,
+        rendezvous_node: PeerId,
+    ) {
+        let req_id = self.inner.send_request(
+            &rendezvous_node,
+            Discover {
+                namespace: namespace.clone(),
+                cookie,
+                limit: Some(page_size),
+            },
+        );
+
+        self.waiting_for_page
+            .insert(req_id, (rendezvous_node, namespace, page_size));
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -158,6 +191,17 @@ pub enum Event {
         registrations: Vec<Registration>,
         cookie: Cookie,
     },
+    /// We received a page of registrations from the contained rendezvous node.
+    ///
+    /// See [`Behaviour::discover_paginated`].
+    DiscoveredPage {
+        rendezvous_node: PeerId,
+        namespace: Option<Namespace>,
+        registrations: Vec<Registration>,
+        cookie: Cookie,
+        /// Whether this is the last page, i.e. no further page will be requested.
+        is_last: bool,
+    },
     /// We failed to discover other nodes on the contained rendezvous node.
     DiscoverFailed {
         rendezvous_node: PeerId,
@@ -345,9 +389,41 @@ impl Behaviour {
             });
         };
 
+        if let Some((rendezvous_node, namespace, _)) = self.waiting_for_page.remove(req_id) {
+            return Some(Event::DiscoverFailed {
+                rendezvous_node,
+                namespace,
+                error: ErrorCode::Unavailable,
+            });
+        };
+
         None
     }
 
+    /// Stores the addresses of discovered peers until their registration expires.
+    fn on_discovered(&mut self, registrations: &[Registration]) {
+        self.discovered_peers
+            .extend(registrations.iter().map(|registration| {
+                let peer_id = registration.record.peer_id();
+                let namespace = registration.namespace.clone();
+
+                let addresses = registration.record.addresses().to_vec();
+
+                ((peer_id, namespace), addresses)
+            }));
+
+        self.expiring_registrations
+            .extend(registrations.iter().cloned().map(|registration| {
+                async move {
+                    // if the timer errors we consider it expired
+                    futures_timer::Delay::new(Duration::from_secs(registration.ttl)).await;
+
+                    (registration.record.peer_id(), registration.namespace)
+                }
+                .boxed()
+            }));
+    }
+
     fn handle_response(
         &mut self,
         request_id: &OutboundRequestId,
@@ -386,32 +462,36 @@ impl Behaviour {
             DiscoverResponse(Ok((registrations, cookie))) => {
                 if let Some((rendezvous_node, _ns)) = self.waiting_for_discovery.remove(request_id)
                 {
-                    self.discovered_peers
-                        .extend(registrations.iter().map(|registration| {
-                            let peer_id = registration.record.peer_id();
-                            let namespace = registration.namespace.clone();
-
-                            let addresses = registration.record.addresses().to_vec();
-
-                            ((peer_id, namespace), addresses)
-                        }));
+                    self.on_discovered(&registrations);
 
-                    self.expiring_registrations
-                        .extend(registrations.iter().cloned().map(|registration| {
-                            async move {
-                                // if the timer errors we consider it expired
-                                futures_timer::Delay::new(Duration::from_secs(registration.ttl))
-                                    .await;
+                    return Some(Event::Discovered {
+                        rendezvous_node,
+                        registrations,
+                        cookie,
+                    });
+                }
 
-                                (registration.record.peer_id(), registration.namespace)
-                            }
-                            .boxed()
-                        }));
+                if let Some((rendezvous_node, namespace, page_size)) =
+                    self.waiting_for_page.remove(request_id)
+                {
+                    self.on_discovered(&registrations);
+
+                    let is_last = (registrations.len() as u64) < page_size;
+                    if !is_last {
+                        self.discover_paginated(
+                            namespace.clone(),
+                            Some(cookie.clone()),
+                            page_size,
+                            rendezvous_node,
+                        );
+                    }
 
-                    return Some(Event::Discovered {
+                    return Some(Event::DiscoveredPage {
                         rendezvous_node,
+                        namespace,
                         registrations,
                         cookie,
+                        is_last,
                     });
                 }
 
@@ -426,6 +506,14 @@ impl Behaviour {
                     });
                 }
 
+                if let Some((rendezvous_node, ns, _)) = self.waiting_for_page.remove(request_id) {
+                    return Some(Event::DiscoverFailed {
+                        rendezvous_node,
+                        namespace: ns,
+                        error: error_code,
+                    });
+                }
+
                 None
             }
             _ => unreachable!("rendezvous clients never receive requests"),
@@ -395,13 +395,7 @@ impl From<Message> for proto::Message {
                 discoverResponse: Some(proto::DiscoverResponse {
                     registrations: registrations
                         .into_iter()
-                        .map(|reggo| proto::Register {
-                            ns: Some(reggo.namespace.into()),
-                            ttl: Some(reggo.ttl),
-                            signedPeerRecord: Some(
-                                reggo.record.into_signed_envelope().into_protobuf_encoding(),
-                            ),
-                        })
+                        .map(proto::Register::from)
                         .collect(),
                     status: Some(proto::ResponseStatus::OK),
                     statusText: None,
@@ -485,23 +479,7 @@ impl TryFrom<proto::Message> for Message {
             } => {
                 let registrations = registrations
                     .into_iter()
-                    .map(|reggo| {
-                        Ok(Registration {
-                            namespace: reggo
-                                .ns
-                                .map(Namespace::new)
-                                .transpose()?
-                                .ok_or(ConversionError::MissingNamespace)?,
-                            record: PeerRecord::from_signed_envelope(
-                                SignedEnvelope::from_protobuf_encoding(
-                                    &reggo
-                                        .signedPeerRecord
-                                        .ok_or(ConversionError::MissingSignedPeerRecord)?,
-                                )?,
-                            )?,
-                            ttl: reggo.ttl.ok_or(ConversionError::MissingTtl)?,
-                        })
-                    })
+                    .map(Registration::try_from)
                     .collect::<Result<Vec<_>, ConversionError>>()?;
                 let cookie = Cookie::from_wire_encoding(cookie)?;
 
@@ -541,6 +519,79 @@ impl TryFrom<proto::Message> for Message {
     }
 }
 
+impl From<Registration> for proto::Register {
+    fn from(registration: Registration) -> Self {
+        proto::Register {
+            ns: Some(registration.namespace.into()),
+            ttl: Some(registration.ttl),
+            signedPeerRecord: Some(
+                registration
+                    .record
+                    .into_signed_envelope()
+                    .into_protobuf_encoding(),
+            ),
+        }
+    }
+}
+
+impl TryFrom<proto::Register> for Registration {
+    type Error = ConversionError;
+
+    fn try_from(register: proto::Register) -> Result<Self, Self::Error> {
+        Ok(Registration {
+            namespace: register
+                .ns
+                .map(Namespace::new)
+                .transpose()?
+                .ok_or(ConversionError::MissingNamespace)?,
+            record: PeerRecord::from_signed_envelope(SignedEnvelope::from_protobuf_encoding(
+                &register
+                    .signedPeerRecord
+                    .ok_or(ConversionError::MissingSignedPeerRecord)?,
+            )?)?,
+            ttl: register.ttl.ok_or(ConversionError::MissingTtl)?,
+        })
+    }
+}
+
+/// Encodes registrations as a sequence of length-prefixed `Register` messages.
+#[cfg(feature = "file-store")]
+pub(crate) fn encode_registrations(
+    registrations: impl IntoIterator<Item = Registration>,
+) -> Vec<u8> {
+    use quick_protobuf::Writer;
+
+    let mut buf = Vec::new();
+    let mut writer = Writer::new(&mut buf);
+    for registration in registrations {
+        writer
+            .write_message(&proto::Register::from(registration))
+            .expect("Encoding to succeed");
+    }
+
+    buf
+}
+
+/// Decodes registrations encoded with [`encode_registrations`].
+#[cfg(feature = "file-store")]
+pub(crate) fn decode_registrations(bytes: &[u8]) -> io::Result<Vec<Registration>> {
+    use quick_protobuf::BytesReader;
+
+    let mut reader = BytesReader::from_bytes(bytes);
+    let mut registrations = Vec::new();
+    while !reader.is_eof() {
+        let register = reader
+            .read_message::<proto::Register>(bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        registrations.push(
+            Registration::try_from(register)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+        );
+    }
+
+    Ok(registrations)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ConversionError {
     #[error("The wire message is consistent")]
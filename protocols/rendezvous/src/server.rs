@@ -32,20 +32,26 @@ use libp2p_swarm::{
     ConnectionDenied, ConnectionId, NetworkBehaviour, THandler, THandlerInEvent, THandlerOutEvent,
     ToSwarm,
 };
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
 use std::iter;
+use std::ops::Bound;
 use std::task::{ready, Context, Poll};
 use std::time::Duration;
+use store::{MemoryStore, Store};
 
-pub struct Behaviour {
+pub mod store;
+
+pub struct Behaviour<S = MemoryStore> {
     inner: libp2p_request_response::Behaviour<crate::codec::Codec>,
 
-    registrations: Registrations,
+    registrations: Registrations<S>,
 }
 
 pub struct Config {
     min_ttl: Ttl,
     max_ttl: Ttl,
+    max_page_size: Option<u64>,
 }
 
 impl Config {
@@ -58,6 +64,16 @@ impl Config {
         self.max_ttl = max_ttl;
         self
     }
+
+    /// Caps the number of registrations returned for a single DISCOVER request, regardless of
+    /// the limit requested by the client.
+    ///
+    /// Clients can fetch the remaining registrations page by page via the returned [`Cookie`].
+    /// By default, the number of registrations is not capped.
+    pub fn with_max_page_size(mut self, max_page_size: u64) -> Self {
+        self.max_page_size = Some(max_page_size);
+        self
+    }
 }
 
 impl Default for Config {
@@ -65,6 +81,7 @@ impl Default for Config {
         Self {
             min_ttl: MIN_TTL,
             max_ttl: MAX_TTL,
+            max_page_size: None,
         }
     }
 }
@@ -72,6 +89,25 @@ impl Default for Config {
 impl Behaviour {
     /// Create a new instance of the rendezvous [`NetworkBehaviour`].
     pub fn new(config: Config) -> Self {
+        Self::with_registrations(Registrations::with_config(config))
+    }
+}
+
+impl<S> Behaviour<S>
+where
+    S: Store,
+{
+    /// Create a new instance of the rendezvous [`NetworkBehaviour`], persisting registrations in
+    /// the given [`Store`].
+    ///
+    /// Registrations previously persisted in the store are served right away.
+    pub fn with_store(config: Config, store: S) -> io::Result<Self> {
+        Ok(Self::with_registrations(Registrations::with_store(
+            config, store,
+        )?))
+    }
+
+    fn with_registrations(registrations: Registrations<S>) -> Self {
         Self {
             inner: libp2p_request_response::Behaviour::with_codec(
                 crate::codec::Codec::default(),
@@ -79,9 +115,19 @@ impl Behaviour {
                 libp2p_request_response::Config::default(),
             ),
 
-            registrations: Registrations::with_config(config),
+            registrations,
         }
     }
+
+    /// Returns the number of active registrations in the given namespace.
+    pub fn registration_count(&self, namespace: &Namespace) -> usize {
+        self.registrations.count(namespace)
+    }
+
+    /// Returns all namespaces with active registrations, along with the number of registrations.
+    pub fn namespaces(&self) -> impl Iterator<Item = (&Namespace, usize)> {
+        self.registrations.namespaces()
+    }
 }
 
 #[derive(Debug)]
@@ -111,7 +157,10 @@ pub enum Event {
     RegistrationExpired(Registration),
 }
 
-impl NetworkBehaviour for Behaviour {
+impl<S> NetworkBehaviour for Behaviour<S>
+where
+    S: Store + 'static,
+{
     type ConnectionHandler = <libp2p_request_response::Behaviour<
         crate::codec::Codec,
     > as NetworkBehaviour>::ConnectionHandler;
@@ -232,10 +281,10 @@ impl NetworkBehaviour for Behaviour {
     }
 }
 
-fn handle_request(
+fn handle_request<S: Store>(
     peer_id: PeerId,
     message: Message,
-    registrations: &mut Registrations,
+    registrations: &mut Registrations<S>,
 ) -> Option<(Event, Option<Message>)> {
     match message {
         Message::Register(registration) => {
@@ -324,25 +373,30 @@ fn handle_request(
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+/// Identifies a registration.
+///
+/// Ids are assigned in ascending order, which gives DISCOVER responses a stable order: A
+/// [`Cookie`] points to the last registration returned so far and the next page continues with
+/// all registrations added after it.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone)]
 struct RegistrationId(u64);
 
-impl RegistrationId {
-    fn new() -> Self {
-        Self(rand::random())
-    }
-}
-
 #[derive(Debug, PartialEq)]
 struct ExpiredRegistration(Registration);
 
-pub struct Registrations {
+pub struct Registrations<S = MemoryStore> {
     registrations_for_peer: BiMap<(PeerId, Namespace), RegistrationId>,
-    registrations: HashMap<RegistrationId, Registration>,
-    cookies: HashMap<Cookie, HashSet<RegistrationId>>,
+    registrations: BTreeMap<RegistrationId, Registration>,
+    /// The number of registrations per namespace.
+    counts: HashMap<Namespace, usize>,
+    /// The last registration returned for a cookie.
+    cookies: HashMap<Cookie, RegistrationId>,
+    next_registration_id: u64,
     min_ttl: Ttl,
     max_ttl: Ttl,
+    max_page_size: Option<u64>,
     next_expiry: FuturesUnordered<BoxFuture<'static, RegistrationId>>,
+    store: S,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -361,13 +415,46 @@ impl Default for Registrations {
 
 impl Registrations {
     pub fn with_config(config: Config) -> Self {
+        Self::new(config, MemoryStore)
+    }
+}
+
+impl<S> Registrations<S>
+where
+    S: Store,
+{
+    /// Creates a new instance, loading the registrations persisted in the given [`Store`].
+    ///
+    /// Registrations that expired in the meantime are removed from the store.
+    pub fn with_store(config: Config, mut store: S) -> io::Result<Self> {
+        let persisted = store.load()?;
+        let mut registrations = Self::new(config, store);
+
+        for registration in persisted {
+            if registration.ttl == 0 {
+                registrations
+                    .store
+                    .remove(&registration.namespace, &registration.record.peer_id())?;
+                continue;
+            }
+            registrations.insert(registration);
+        }
+
+        Ok(registrations)
+    }
+
+    fn new(config: Config, store: S) -> Self {
         Self {
             registrations_for_peer: Default::default(),
             registrations: Default::default(),
+            counts: Default::default(),
             min_ttl: config.min_ttl,
             max_ttl: config.max_ttl,
+            max_page_size: config.max_page_size,
             cookies: Default::default(),
+            next_registration_id: 0,
             next_expiry: FuturesUnordered::from_iter(vec![futures::future::pending().boxed()]),
+            store,
         }
     }
 
@@ -389,46 +476,84 @@ impl Registrations {
             });
         }
 
-        let namespace = new_registration.namespace;
-        let registration_id = RegistrationId::new();
-
-        if let Some(old_registration) = self
-            .registrations_for_peer
-            .get_by_left(&(new_registration.record.peer_id(), namespace.clone()))
-        {
-            self.registrations.remove(old_registration);
-        }
-
-        self.registrations_for_peer.insert(
-            (new_registration.record.peer_id(), namespace.clone()),
-            registration_id,
-        );
-
         let registration = Registration {
-            namespace,
+            namespace: new_registration.namespace,
             record: new_registration.record,
             ttl,
         };
-        self.registrations
-            .insert(registration_id, registration.clone());
+        if let Err(e) = self.store.put(&registration) {
+            tracing::warn!(namespace=%registration.namespace, "Failed to persist registration: {e}");
+        }
+        self.insert(registration.clone());
+
+        Ok(registration)
+    }
+
+    /// Inserts a registration, replacing any previous registration of the same peer in the same
+    /// namespace.
+    fn insert(&mut self, registration: Registration) {
+        let registration_id = RegistrationId(self.next_registration_id);
+        self.next_registration_id += 1;
+
+        let key = (
+            registration.record.peer_id(),
+            registration.namespace.clone(),
+        );
+        if let Some((_, old_registration)) = self.registrations_for_peer.remove_by_left(&key) {
+            self.remove_registration(&old_registration);
+        }
+        self.registrations_for_peer.insert(key, registration_id);
 
-        let next_expiry = futures_timer::Delay::new(Duration::from_secs(ttl))
+        let next_expiry = futures_timer::Delay::new(Duration::from_secs(registration.ttl))
             .map(move |_| registration_id)
             .boxed();
-
         self.next_expiry.push(next_expiry);
 
-        Ok(registration)
+        *self
+            .counts
+            .entry(registration.namespace.clone())
+            .or_default() += 1;
+        self.registrations.insert(registration_id, registration);
     }
 
     pub fn remove(&mut self, namespace: Namespace, peer_id: PeerId) {
+        if let Err(e) = self.store.remove(&namespace, &peer_id) {
+            tracing::warn!(%namespace, peer=%peer_id, "Failed to remove persisted registration: {e}");
+        }
+
         let reggo_to_remove = self
             .registrations_for_peer
             .remove_by_left(&(peer_id, namespace));
 
         if let Some((_, reggo_to_remove)) = reggo_to_remove {
-            self.registrations.remove(&reggo_to_remove);
+            self.remove_registration(&reggo_to_remove);
+        }
+    }
+
+    /// Returns the number of registrations in the given namespace.
+    pub fn count(&self, namespace: &Namespace) -> usize {
+        self.counts.get(namespace).copied().unwrap_or_default()
+    }
+
+    /// Returns all namespaces with registrations, along with the number of registrations.
+    pub fn namespaces(&self) -> impl Iterator<Item = (&Namespace, usize)> {
+        self.counts
+            .iter()
+            .map(|(namespace, count)| (namespace, *count))
+    }
+
+    /// Removes a registration from the in-memory indices, keeping it in the store.
+    fn remove_registration(&mut self, id: &RegistrationId) -> Option<Registration> {
+        let registration = self.registrations.remove(id)?;
+
+        if let Some(count) = self.counts.get_mut(&registration.namespace) {
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(&registration.namespace);
+            }
         }
+
+        Some(registration)
     }
 
     pub fn get(
@@ -450,40 +575,34 @@ impl Registrations {
             _ => {}
         }
 
-        let mut reggos_of_last_discover = cookie
-            .and_then(|cookie| self.cookies.get(&cookie))
-            .cloned()
-            .unwrap_or_default();
+        let last_discovered = cookie.and_then(|cookie| self.cookies.get(&cookie).copied());
+        let limit = limit
+            .unwrap_or(u64::MAX)
+            .min(self.max_page_size.unwrap_or(u64::MAX));
 
         let ids = self
-            .registrations_for_peer
-            .iter()
-            .filter_map({
-                |((_, namespace), registration_id)| {
-                    if reggos_of_last_discover.contains(registration_id) {
-                        return None;
-                    }
-
-                    match discover_namespace.as_ref() {
-                        Some(discover_namespace) if discover_namespace == namespace => {
-                            Some(registration_id)
-                        }
-                        Some(_) => None,
-                        None => Some(registration_id),
+            .registrations
+            .range((
+                last_discovered.map_or(Bound::Unbounded, Bound::Excluded),
+                Bound::Unbounded,
+            ))
+            .filter_map(
+                |(registration_id, registration)| match discover_namespace.as_ref() {
+                    Some(discover_namespace) if discover_namespace != &registration.namespace => {
+                        None
                     }
-                }
-            })
-            .take(limit.unwrap_or(u64::MAX) as usize)
-            .cloned()
+                    _ => Some(*registration_id),
+                },
+            )
+            .take(limit.try_into().unwrap_or(usize::MAX))
             .collect::<Vec<_>>();
 
-        reggos_of_last_discover.extend(&ids);
-
         let new_cookie = discover_namespace
             .map(Cookie::for_namespace)
             .unwrap_or_else(Cookie::for_all_namespaces);
-        self.cookies
-            .insert(new_cookie.clone(), reggos_of_last_discover);
+        if let Some(last_discovered) = ids.last().copied().or(last_discovered) {
+            self.cookies.insert(new_cookie.clone(), last_discovered);
+        }
 
         let regs = &self.registrations;
         let registrations = ids
@@ -499,21 +618,28 @@ impl Registrations {
                 "This stream should never finish because it is initialised with a pending future",
             );
 
-            // clean up our cookies
-            self.cookies.retain(|_, registrations| {
-                registrations.remove(&expired_registration);
-
-                // retain all cookies where there are still registrations left
-                !registrations.is_empty()
-            });
-
             self.registrations_for_peer
                 .remove_by_right(&expired_registration);
-            match self.registrations.remove(&expired_registration) {
+            let expired = self.remove_registration(&expired_registration);
+
+            // Clean up our cookies. A cookie pointing before the oldest registration is
+            // equivalent to no cookie at all.
+            match self.registrations.keys().next() {
+                Some(&oldest) => self.cookies.retain(|_, last| *last >= oldest),
+                None => self.cookies.clear(),
+            }
+
+            match expired {
                 None => {
                     continue;
                 }
                 Some(registration) => {
+                    if let Err(e) = self
+                        .store
+                        .remove(&registration.namespace, &registration.record.peer_id())
+                    {
+                        tracing::warn!(namespace=%registration.namespace, "Failed to remove expired registration: {e}");
+                    }
                     return Poll::Ready(ExpiredRegistration(registration));
                 }
             }
@@ -635,6 +761,7 @@ mod tests {
         let mut registrations = Registrations::with_config(Config {
             min_ttl: 0,
             max_ttl: 4,
+            ..Default::default()
         });
 
         let start_time = SystemTime::now();
@@ -671,6 +798,7 @@ mod tests {
         let mut registrations = Registrations::with_config(Config {
             min_ttl: 1,
             max_ttl: 10,
+            ..Default::default()
         });
         let dummy_registration = new_dummy_registration_with_ttl("foo", 2);
         let namespace = dummy_registration.namespace.clone();
@@ -693,6 +821,7 @@ mod tests {
         let mut registrations = Registrations::with_config(Config {
             min_ttl: 0,
             max_ttl: 10,
+            ..Default::default()
         });
         let dummy_registration = new_dummy_registration_with_ttl("foo", 1);
 
@@ -710,6 +839,7 @@ mod tests {
         let mut registrations = Registrations::with_config(Config {
             min_ttl: 1,
             max_ttl: 10,
+            ..Default::default()
         });
 
         registrations
@@ -748,6 +878,126 @@ mod tests {
         assert_eq!(discover2.count(), 1);
     }
 
+    #[test]
+    fn pages_are_stable_when_registrations_are_added() {
+        let mut registrations = Registrations::default();
+        let first = registrations.add(new_dummy_registration("foo")).unwrap();
+        let second = registrations.add(new_dummy_registration("foo")).unwrap();
+
+        let (discover1, cookie) = registrations.get(None, None, Some(1)).unwrap();
+        assert_eq!(discover1.cloned().collect::<Vec<_>>(), vec![first]);
+
+        let third = registrations.add(new_dummy_registration("foo")).unwrap();
+
+        let (discover2, cookie) = registrations.get(None, Some(cookie), Some(1)).unwrap();
+        assert_eq!(discover2.cloned().collect::<Vec<_>>(), vec![second]);
+
+        let (discover3, _) = registrations.get(None, Some(cookie), Some(1)).unwrap();
+        assert_eq!(discover3.cloned().collect::<Vec<_>>(), vec![third]);
+    }
+
+    #[test]
+    fn max_page_size_caps_limit() {
+        let mut registrations = Registrations::with_config(Config::default().with_max_page_size(1));
+        registrations.add(new_dummy_registration("foo")).unwrap();
+        registrations.add(new_dummy_registration("foo")).unwrap();
+
+        let (discover, _) = registrations.get(None, None, Some(2)).unwrap();
+        assert_eq!(discover.count(), 1);
+    }
+
+    #[test]
+    fn counts_registrations_per_namespace() {
+        let alice = identity::Keypair::generate_ed25519();
+        let mut registrations = Registrations::default();
+        registrations
+            .add(new_registration("foo", alice.clone(), None))
+            .unwrap();
+        registrations
+            .add(new_registration("foo", alice.clone(), None))
+            .unwrap();
+        registrations.add(new_dummy_registration("foo")).unwrap();
+        registrations.add(new_dummy_registration("bar")).unwrap();
+
+        assert_eq!(registrations.count(&Namespace::from_static("foo")), 2);
+        assert_eq!(registrations.count(&Namespace::from_static("bar")), 1);
+
+        registrations.remove(Namespace::from_static("bar"), alice.public().to_peer_id());
+        assert_eq!(registrations.count(&Namespace::from_static("bar")), 1);
+
+        registrations.remove(Namespace::from_static("foo"), alice.public().to_peer_id());
+        assert_eq!(registrations.count(&Namespace::from_static("foo")), 1);
+        assert_eq!(registrations.namespaces().count(), 2);
+    }
+
+    #[derive(Default, Clone)]
+    struct TestStore {
+        registrations: HashMap<(PeerId, Namespace), Registration>,
+    }
+
+    impl Store for TestStore {
+        fn load(&mut self) -> io::Result<Vec<Registration>> {
+            Ok(self.registrations.values().cloned().collect())
+        }
+
+        fn put(&mut self, registration: &Registration) -> io::Result<()> {
+            self.registrations.insert(
+                (
+                    registration.record.peer_id(),
+                    registration.namespace.clone(),
+                ),
+                registration.clone(),
+            );
+            Ok(())
+        }
+
+        fn remove(&mut self, namespace: &Namespace, peer: &PeerId) -> io::Result<()> {
+            self.registrations.remove(&(*peer, namespace.clone()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn registrations_survive_reloading_store() {
+        let alice = identity::Keypair::generate_ed25519();
+        let mut registrations =
+            Registrations::with_store(Config::default(), TestStore::default()).unwrap();
+        registrations
+            .add(new_registration("foo", alice.clone(), None))
+            .unwrap();
+        registrations.add(new_dummy_registration("bar")).unwrap();
+        registrations.remove(Namespace::from_static("bar"), PeerId::random());
+
+        let mut registrations =
+            Registrations::with_store(Config::default(), registrations.store.clone()).unwrap();
+
+        assert_eq!(registrations.count(&Namespace::from_static("foo")), 1);
+        assert_eq!(registrations.count(&Namespace::from_static("bar")), 1);
+
+        registrations.remove(Namespace::from_static("foo"), alice.public().to_peer_id());
+        assert_eq!(registrations.store.registrations.len(), 1);
+    }
+
+    #[test]
+    fn expired_registrations_are_dropped_on_load() {
+        let mut registration = Registration {
+            namespace: Namespace::from_static("foo"),
+            record: PeerRecord::new(&identity::Keypair::generate_ed25519(), vec![]).unwrap(),
+            ttl: 0,
+        };
+        let mut store = TestStore::default();
+        store.put(&registration).unwrap();
+        registration.ttl = 60;
+        registration.namespace = Namespace::from_static("bar");
+        store.put(&registration).unwrap();
+
+        let registrations = Registrations::with_store(Config::default(), store).unwrap();
+
+        assert_eq!(registrations.count(&Namespace::from_static("foo")), 0);
+        assert_eq!(registrations.count(&Namespace::from_static("bar")), 1);
+        assert_eq!(registrations.store.registrations.len(), 1);
+    }
+
     fn new_dummy_registration(namespace: &'static str) -> NewRegistration {
         let identity = identity::Keypair::generate_ed25519();
 
@@ -0,0 +1,74 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Storage of registrations beyond the lifetime of a rendezvous server.
+
+#[cfg(feature = "file-store")]
+mod file;
+
+#[cfg(feature = "file-store")]
+pub use file::FileStore;
+
+use crate::codec::Registration;
+use crate::Namespace;
+use libp2p_identity::PeerId;
+use std::io;
+
+/// Persists the registrations of a rendezvous server, such that they survive restarts.
+///
+/// The server keeps all registrations in memory and writes every modification through to its
+/// store. On startup, the registrations are loaded from the store, see
+/// [`Behaviour::with_store`](super::Behaviour::with_store).
+///
+/// The TTL of a [`Registration`] passed to the store is relative to the time of the call. Stores
+/// are expected to return the remaining TTL on [`Store::load`].
+pub trait Store {
+    /// Loads all persisted registrations.
+    ///
+    /// Registrations whose TTL expired while the server was offline may be returned with a TTL
+    /// of 0, in which case they are removed from the store.
+    fn load(&mut self) -> io::Result<Vec<Registration>>;
+
+    /// Persists a registration, replacing any previous registration of the same peer in the same
+    /// namespace.
+    fn put(&mut self, registration: &Registration) -> io::Result<()>;
+
+    /// Removes the persisted registration of the given peer in the given namespace.
+    fn remove(&mut self, namespace: &Namespace, peer: &PeerId) -> io::Result<()>;
+}
+
+/// A [`Store`] that doesn't persist anything, i.e. registrations are kept in memory only and
+/// lost when the server restarts.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MemoryStore;
+
+impl Store for MemoryStore {
+    fn load(&mut self) -> io::Result<Vec<Registration>> {
+        Ok(Vec::new())
+    }
+
+    fn put(&mut self, _: &Registration) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn remove(&mut self, _: &Namespace, _: &PeerId) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use super::Store;
+use crate::codec::{decode_registrations, encode_registrations, Registration};
+use crate::Namespace;
+use libp2p_identity::PeerId;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A [`Store`] keeping the registrations of every peer in a separate file in a directory.
+///
+/// Registrations are encoded with the protobuf `Register` message of the rendezvous wire
+/// protocol. The TTL of a registration is stored relative to the modification time of its file,
+/// so the time the server was offline counts towards the expiry of the registration.
+///
+/// All file system operations are blocking.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    /// Creates a new `FileStore` storing registrations in the given directory.
+    ///
+    /// The directory is created if it doesn't exist.
+    pub fn new(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        fs::create_dir_all(&path)?;
+
+        Ok(Self { path })
+    }
+
+    fn peer_path(&self, peer: &PeerId) -> PathBuf {
+        self.path.join(to_hex(&peer.to_bytes()))
+    }
+
+    /// Replaces the registrations of a peer with the ones returned by `update`.
+    fn update(&self, peer: &PeerId, update: impl FnOnce(&mut Vec<Registration>)) -> io::Result<()> {
+        let path = self.peer_path(peer);
+        let mut registrations = match read_registrations(&path) {
+            Ok(registrations) => registrations,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        registrations.retain(|r| r.ttl > 0);
+        update(&mut registrations);
+
+        if registrations.is_empty() {
+            return remove_file(&path);
+        }
+
+        // Write to a temporary file first, so a crash doesn't leave a truncated file behind.
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, encode_registrations(registrations))?;
+        fs::rename(tmp, path)
+    }
+}
+
+impl Store for FileStore {
+    fn load(&mut self) -> io::Result<Vec<Registration>> {
+        let mut registrations = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path.extension().is_some() {
+                continue; // Leftover temporary file.
+            }
+            match read_registrations(&path) {
+                Ok(r) => registrations.extend(r),
+                Err(e) => tracing::warn!(path=%path.display(), "Failed to read registrations: {e}"),
+            }
+        }
+
+        Ok(registrations)
+    }
+
+    fn put(&mut self, registration: &Registration) -> io::Result<()> {
+        self.update(&registration.record.peer_id(), |registrations| {
+            registrations.retain(|r| r.namespace != registration.namespace);
+            registrations.push(registration.clone());
+        })
+    }
+
+    fn remove(&mut self, namespace: &Namespace, peer: &PeerId) -> io::Result<()> {
+        self.update(peer, |registrations| {
+            registrations.retain(|r| &r.namespace != namespace);
+        })
+    }
+}
+
+/// Reads the registrations of a file, with their TTL relative to now.
+fn read_registrations(path: &Path) -> io::Result<Vec<Registration>> {
+    let buf = fs::read(path)?;
+    let mut registrations = decode_registrations(&buf)?;
+
+    let elapsed = fs::metadata(path)?
+        .modified()?
+        .elapsed()
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    for registration in &mut registrations {
+        registration.ttl = registration.ttl.saturating_sub(elapsed);
+    }
+
+    Ok(registrations)
+}
+
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        res => res,
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_core::PeerRecord;
+    use libp2p_identity::Keypair;
+
+    #[test]
+    fn roundtrip() {
+        let dir =
+            std::env::temp_dir().join(format!("libp2p-rendezvous-file-store-{}", PeerId::random()));
+        let keypair = Keypair::generate_ed25519();
+        let record =
+            PeerRecord::new(&keypair, vec!["/ip4/127.0.0.1/tcp/1234".parse().unwrap()]).unwrap();
+        let foo = Registration {
+            namespace: Namespace::from_static("foo"),
+            record: record.clone(),
+            ttl: 60,
+        };
+        let bar = Registration {
+            namespace: Namespace::from_static("bar"),
+            record,
+            ttl: 60,
+        };
+
+        let mut store = FileStore::new(&dir).unwrap();
+        store.put(&foo).unwrap();
+        store.put(&bar).unwrap();
+        store
+            .remove(&bar.namespace, &keypair.public().to_peer_id())
+            .unwrap();
+
+        let loaded = FileStore::new(&dir).unwrap().load().unwrap();
+        assert_eq!(loaded, vec![foo.clone()]);
+
+        store
+            .remove(&foo.namespace, &keypair.public().to_peer_id())
+            .unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -85,6 +85,73 @@ async fn given_successful_registration_then_successful_discovery() {
     }
 }
 
+#[tokio::test]
+async fn given_registrations_then_paginated_discovery_returns_pages() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let namespace = rendezvous::Namespace::from_static("some-namespace");
+    let ([mut alice, mut bob, mut carol, mut dave], mut robert) =
+        new_server_with_connected_clients(rendezvous::server::Config::default()).await;
+
+    for client in [&mut alice, &mut bob, &mut carol] {
+        client
+            .behaviour_mut()
+            .register(namespace.clone(), *robert.local_peer_id(), None)
+            .unwrap();
+
+        let ([_], [_]): (
+            [rendezvous::client::Event; 1],
+            [rendezvous::server::Event; 1],
+        ) = libp2p_swarm_test::drive(client, &mut robert).await;
+    }
+    assert_eq!(robert.behaviour().registration_count(&namespace), 3);
+    assert_eq!(
+        robert.behaviour().namespaces().collect::<Vec<_>>(),
+        vec![(&namespace, 3)]
+    );
+
+    dave.behaviour_mut().discover_paginated(
+        Some(namespace.clone()),
+        None,
+        2,
+        *robert.local_peer_id(),
+    );
+
+    match libp2p_swarm_test::drive(&mut dave, &mut robert).await {
+        (
+            [rendezvous::client::Event::DiscoveredPage {
+                registrations: first_page,
+                is_last: false,
+                ..
+            }, rendezvous::client::Event::DiscoveredPage {
+                registrations: second_page,
+                is_last: true,
+                ..
+            }],
+            [rendezvous::server::Event::DiscoverServed { .. }, rendezvous::server::Event::DiscoverServed { .. }],
+        ) => {
+            assert_eq!(first_page.len(), 2);
+            assert_eq!(second_page.len(), 1);
+
+            let mut discovered = first_page
+                .iter()
+                .chain(&second_page)
+                .map(|registration| registration.record.peer_id())
+                .collect::<Vec<_>>();
+            discovered.sort();
+            let mut expected = vec![
+                *alice.local_peer_id(),
+                *bob.local_peer_id(),
+                *carol.local_peer_id(),
+            ];
+            expected.sort();
+            assert_eq!(discovered, expected);
+        }
+        events => panic!("Unexpected events: {events:?}"),
+    }
+}
+
 #[tokio::test]
 async fn should_return_error_when_no_external_addresses() {
     let _ = tracing_subscriber::fmt()
@@ -0,0 +1,74 @@
+// Copyright 2024 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::{InboundRequestId, ResponseChannel};
+use instant::Instant;
+use libp2p_identity::PeerId;
+use libp2p_swarm::ConnectionId;
+use std::{fmt, time::Duration};
+
+/// What to do with inbound requests exceeding the limits set via
+/// [`Config::with_max_concurrent_inbound_requests`](crate::Config::with_max_concurrent_inbound_requests)
+/// and
+/// [`Config::with_max_concurrent_inbound_requests_per_peer`](crate::Config::with_max_concurrent_inbound_requests_per_peer).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InboundOverflow {
+    /// Shed the request right away, closing its stream without a response.
+    #[default]
+    Reject,
+    /// Queue the request until it is within the limits, or shed it if that takes longer than
+    /// `timeout`.
+    ///
+    /// Queued requests are still subject to the request timeout, which should thus exceed
+    /// `timeout`.
+    Queue { timeout: Duration },
+}
+
+/// The reason for shedding an inbound request, reported via
+/// [`InboundFailure::Shed`](crate::InboundFailure::Shed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShedReason {
+    /// The peer reached its limit of concurrent inbound requests.
+    PeerLimit,
+    /// The limit of concurrent inbound requests across all peers was reached.
+    GlobalLimit,
+    /// The request timed out while queued, see [`InboundOverflow::Queue`].
+    QueueTimeout,
+}
+
+impl fmt::Display for ShedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShedReason::PeerLimit => write!(f, "the peer has too many concurrent requests"),
+            ShedReason::GlobalLimit => write!(f, "there are too many concurrent requests"),
+            ShedReason::QueueTimeout => write!(f, "the request timed out while queued"),
+        }
+    }
+}
+
+/// An inbound request waiting for capacity.
+pub(crate) struct QueuedRequest<TRequest, TResponse> {
+    pub(crate) peer: PeerId,
+    pub(crate) connection: ConnectionId,
+    pub(crate) request_id: InboundRequestId,
+    pub(crate) request: TRequest,
+    pub(crate) channel: ResponseChannel<TResponse>,
+    pub(crate) deadline: Instant,
+}
@@ -43,26 +43,73 @@
 /// ```
 pub type Behaviour<Req, Resp> = crate::Behaviour<codec::Codec<Req, Resp>>;
 
+pub use codec::Codec;
+
 mod codec {
+    use crate::framing::{Framing, Schema};
     use async_trait::async_trait;
     use cbor4ii::core::error::DecodeError;
     use futures::prelude::*;
     use libp2p_swarm::StreamProtocol;
     use serde::{de::DeserializeOwned, Serialize};
-    use std::{collections::TryReserveError, convert::Infallible, io, marker::PhantomData};
-
-    /// Max request size in bytes
-    const REQUEST_SIZE_MAXIMUM: u64 = 1024 * 1024;
-    /// Max response size in bytes
-    const RESPONSE_SIZE_MAXIMUM: u64 = 10 * 1024 * 1024;
+    use std::{
+        collections::TryReserveError, convert::Infallible, io, marker::PhantomData, sync::Arc,
+    };
 
+    /// A [`Codec`](crate::Codec) encoding messages with [`cbor4ii::serde`].
+    ///
+    /// Use it with [`Behaviour::with_codec`](crate::Behaviour::with_codec) to configure the
+    /// framing and versioning of the messages.
     pub struct Codec<Req, Resp> {
+        framing: Framing,
         phantom: PhantomData<(Req, Resp)>,
     }
 
+    impl<Req, Resp> Codec<Req, Resp> {
+        /// Sets the maximum size of a request in bytes (default is 1 MiB).
+        pub fn set_request_size_maximum(mut self, request_size_maximum: u64) -> Self {
+            self.framing.request_size_maximum = request_size_maximum;
+            self
+        }
+
+        /// Sets the maximum size of a response in bytes (default is 10 MiB).
+        pub fn set_response_size_maximum(mut self, response_size_maximum: u64) -> Self {
+            self.framing.response_size_maximum = response_size_maximum;
+            self
+        }
+
+        /// Sets whether messages are prefixed with their unsigned varint encoded length, as done
+        /// by go-libp2p (default is `false`).
+        ///
+        /// Otherwise a message extends until the end of the stream. Both peers have to agree on
+        /// the framing.
+        pub fn set_length_prefix(mut self, length_prefix: bool) -> Self {
+            self.framing.length_prefix = length_prefix;
+            self
+        }
+
+        /// Sends the given schema version with each request and rejects requests with a version
+        /// for which `supports` returns `false`.
+        ///
+        /// A rejected request fails with
+        /// [`OutboundFailure::UnsupportedSchema`](crate::OutboundFailure::UnsupportedSchema) on
+        /// the requester. Both peers have to configure a schema version.
+        pub fn set_schema_version<F>(mut self, version: u32, supports: F) -> Self
+        where
+            F: Fn(u32) -> bool + Send + Sync + 'static,
+        {
+            self.framing.schema = Some(Schema {
+                version,
+                supports: Arc::new(supports),
+            });
+            self
+        }
+    }
+
     impl<Req, Resp> Default for Codec<Req, Resp> {
         fn default() -> Self {
             Codec {
+                framing: Framing::default(),
                 phantom: PhantomData,
             }
         }
@@ -70,7 +117,10 @@ mod codec {
 
     impl<Req, Resp> Clone for Codec<Req, Resp> {
         fn clone(&self) -> Self {
-            Self::default()
+            Codec {
+                framing: self.framing.clone(),
+                phantom: PhantomData,
+            }
         }
     }
 
@@ -88,9 +138,7 @@ mod codec {
         where
             T: AsyncRead + Unpin + Send,
         {
-            let mut vec = Vec::new();
-
-            io.take(REQUEST_SIZE_MAXIMUM).read_to_end(&mut vec).await?;
+            let vec = self.framing.read_request(io).await?;
 
             cbor4ii::serde::from_slice(vec.as_slice()).map_err(decode_into_io_error)
         }
@@ -99,9 +147,7 @@ mod codec {
         where
             T: AsyncRead + Unpin + Send,
         {
-            let mut vec = Vec::new();
-
-            io.take(RESPONSE_SIZE_MAXIMUM).read_to_end(&mut vec).await?;
+            let vec = self.framing.read_response(io).await?;
 
             cbor4ii::serde::from_slice(vec.as_slice()).map_err(decode_into_io_error)
         }
@@ -118,7 +164,7 @@ mod codec {
             let data: Vec<u8> =
                 cbor4ii::serde::to_vec(Vec::new(), &req).map_err(encode_into_io_error)?;
 
-            io.write_all(data.as_ref()).await?;
+            self.framing.write_request(io, data.as_ref()).await?;
 
             Ok(())
         }
@@ -135,10 +181,22 @@ mod codec {
             let data: Vec<u8> =
                 cbor4ii::serde::to_vec(Vec::new(), &resp).map_err(encode_into_io_error)?;
 
-            io.write_all(data.as_ref()).await?;
+            self.framing.write_response(io, data.as_ref()).await?;
 
             Ok(())
         }
+
+        async fn write_request_rejection<T>(
+            &mut self,
+            _: &Self::Protocol,
+            io: &mut T,
+            error: &io::Error,
+        ) -> io::Result<()>
+        where
+            T: AsyncWrite + Unpin + Send,
+        {
+            self.framing.write_request_rejection(io, error).await
+        }
     }
 
     fn decode_into_io_error(err: cbor4ii::serde::DecodeError<Infallible>) -> io::Error {
@@ -215,6 +273,39 @@ mod tests {
         assert_eq!(actual_response, expected_response);
     }
 
+    #[async_std::test]
+    async fn test_length_prefix() {
+        let request = TestRequest {
+            payload: "test_payload".to_string(),
+        };
+        let protocol = StreamProtocol::new("/test_cbor/1");
+        let mut codec = Codec::default().set_length_prefix(true);
+
+        let mut buf = Vec::new();
+        codec
+            .write_request(&protocol, &mut buf, request.clone())
+            .await
+            .expect("Should write request");
+        let payload = cbor4ii::serde::to_vec(Vec::new(), &request).unwrap();
+        assert_eq!(buf[0] as usize, payload.len());
+        assert_eq!(&buf[1..], payload);
+
+        // Trailing data after the framed request is not read.
+        buf.extend_from_slice(b"trailing");
+        let actual_request = codec
+            .read_request(&protocol, &mut futures::io::Cursor::new(&buf))
+            .await
+            .expect("Should read request");
+        assert_eq!(actual_request, request);
+
+        let mut codec: Codec<TestRequest, TestResponse> = codec.set_request_size_maximum(2);
+        let error = codec
+            .read_request(&protocol, &mut futures::io::Cursor::new(&buf))
+            .await
+            .expect_err("Should reject oversized request");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
     #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     struct TestRequest {
         payload: String,
@@ -75,4 +75,20 @@ pub trait Codec {
     ) -> io::Result<()>
     where
         T: AsyncWrite + Unpin + Send;
+
+    /// Writes a response rejecting a request that [`Codec::read_request`] failed to read with
+    /// the given error, if the protocol supports such responses.
+    ///
+    /// By default, nothing is written and the stream is closed.
+    async fn write_request_rejection<T>(
+        &mut self,
+        _protocol: &Self::Protocol,
+        _io: &mut T,
+        _error: &io::Error,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        Ok(())
+    }
 }
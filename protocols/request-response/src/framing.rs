@@ -0,0 +1,239 @@
+// Copyright 2024 Protocol Labs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Framing and schema versioning of the messages of the [`cbor`](crate::cbor) and
+//! [`json`](crate::json) codecs.
+//!
+//! With a schema version configured, a request is preceded by the unsigned varint encoded
+//! version of the requester and a response by a status byte. A status of [`STATUS_OK`] is
+//! followed by the response, a status of [`STATUS_UNSUPPORTED_SCHEMA`] by the unsigned varint
+//! encoded version of the responder.
+//!
+//! With length-prefix framing enabled, each message is preceded by its unsigned varint encoded
+//! length, as done by go-libp2p. Otherwise a message extends until the end of the stream.
+
+use crate::UnsupportedSchema;
+use futures::prelude::*;
+use std::{fmt, io, sync::Arc};
+
+/// Max request size in bytes
+const REQUEST_SIZE_MAXIMUM: u64 = 1024 * 1024;
+/// Max response size in bytes
+const RESPONSE_SIZE_MAXIMUM: u64 = 10 * 1024 * 1024;
+
+const STATUS_OK: u8 = 0;
+const STATUS_UNSUPPORTED_SCHEMA: u8 = 1;
+
+/// How the messages of a codec are framed and versioned.
+#[derive(Clone)]
+pub(crate) struct Framing {
+    pub(crate) request_size_maximum: u64,
+    pub(crate) response_size_maximum: u64,
+    pub(crate) length_prefix: bool,
+    pub(crate) schema: Option<Schema>,
+}
+
+/// The schema version of the local node and which versions of remotes it supports.
+#[derive(Clone)]
+pub(crate) struct Schema {
+    pub(crate) version: u32,
+    pub(crate) supports: Arc<dyn Fn(u32) -> bool + Send + Sync>,
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Self {
+            request_size_maximum: REQUEST_SIZE_MAXIMUM,
+            response_size_maximum: RESPONSE_SIZE_MAXIMUM,
+            length_prefix: false,
+            schema: None,
+        }
+    }
+}
+
+impl Framing {
+    pub(crate) async fn read_request<T>(&self, io: &mut T) -> io::Result<Vec<u8>>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        if let Some(schema) = &self.schema {
+            let version = read_version(io).await?;
+            if !(schema.supports)(version) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    UnsupportedRequestSchema { version },
+                ));
+            }
+        }
+
+        self.read_message(io, self.request_size_maximum).await
+    }
+
+    pub(crate) async fn read_response<T>(&self, io: &mut T) -> io::Result<Vec<u8>>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        if self.schema.is_some() {
+            let mut status = [0];
+            io.read_exact(&mut status).await?;
+            match status[0] {
+                STATUS_OK => {}
+                STATUS_UNSUPPORTED_SCHEMA => {
+                    let remote_version = read_version(io).await?;
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        UnsupportedSchema { remote_version },
+                    ));
+                }
+                status => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid response status {status}"),
+                    ))
+                }
+            }
+        }
+
+        self.read_message(io, self.response_size_maximum).await
+    }
+
+    pub(crate) async fn write_request<T>(&self, io: &mut T, data: &[u8]) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        if let Some(schema) = &self.schema {
+            write_varint(io, schema.version.into()).await?;
+        }
+
+        self.write_message(io, data).await
+    }
+
+    pub(crate) async fn write_response<T>(&self, io: &mut T, data: &[u8]) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        if self.schema.is_some() {
+            io.write_all(&[STATUS_OK]).await?;
+        }
+
+        self.write_message(io, data).await
+    }
+
+    /// Answers a request that failed to be read because of its unsupported schema version.
+    pub(crate) async fn write_request_rejection<T>(
+        &self,
+        io: &mut T,
+        error: &io::Error,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let Some(schema) = &self.schema else {
+            return Ok(());
+        };
+        let is_unsupported_schema = error
+            .get_ref()
+            .is_some_and(|e| e.is::<UnsupportedRequestSchema>());
+        if !is_unsupported_schema {
+            return Ok(());
+        }
+
+        io.write_all(&[STATUS_UNSUPPORTED_SCHEMA]).await?;
+        write_varint(io, schema.version.into()).await
+    }
+
+    async fn read_message<T>(&self, io: &mut T, max_size: u64) -> io::Result<Vec<u8>>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let mut vec = Vec::new();
+
+        if !self.length_prefix {
+            io.take(max_size).read_to_end(&mut vec).await?;
+            return Ok(vec);
+        }
+
+        let len = unsigned_varint::aio::read_u64(&mut *io)
+            .await
+            .map_err(varint_into_io_error)?;
+        if len > max_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("message of {len} bytes exceeds the maximum of {max_size} bytes"),
+            ));
+        }
+        io.take(len).read_to_end(&mut vec).await?;
+        if vec.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(vec)
+    }
+
+    async fn write_message<T>(&self, io: &mut T, data: &[u8]) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        if self.length_prefix {
+            write_varint(io, data.len() as u64).await?;
+        }
+
+        io.write_all(data).await
+    }
+}
+
+/// The error of reading a request whose schema version is not supported.
+#[derive(Debug)]
+struct UnsupportedRequestSchema {
+    version: u32,
+}
+
+impl fmt::Display for UnsupportedRequestSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "schema version {} is not supported", self.version)
+    }
+}
+
+impl std::error::Error for UnsupportedRequestSchema {}
+
+async fn read_version<T>(io: &mut T) -> io::Result<u32>
+where
+    T: AsyncRead + Unpin + Send,
+{
+    unsigned_varint::aio::read_u32(io)
+        .await
+        .map_err(varint_into_io_error)
+}
+
+async fn write_varint<T>(io: &mut T, n: u64) -> io::Result<()>
+where
+    T: AsyncWrite + Unpin + Send,
+{
+    let mut buf = unsigned_varint::encode::u64_buffer();
+    io.write_all(unsigned_varint::encode::u64(n, &mut buf))
+        .await
+}
+
+fn varint_into_io_error(err: unsigned_varint::io::ReadError) -> io::Error {
+    match err {
+        unsigned_varint::io::ReadError::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e),
+    }
+}
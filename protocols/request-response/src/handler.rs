@@ -140,7 +140,17 @@ where
             let (rs_send, rs_recv) = oneshot::channel();
 
             let read = codec.read_request(&protocol, &mut stream);
-            let request = read.await?;
+            let request = match read.await {
+                Ok(request) => request,
+                Err(error) => {
+                    let reject = codec.write_request_rejection(&protocol, &mut stream, &error);
+                    if let Err(e) = reject.await {
+                        tracing::debug!("Failed to reject inbound request: {e}");
+                    }
+                    let _ = stream.close().await;
+                    return Err(error);
+                }
+            };
             sender
                 .send((request_id, request, rs_send))
                 .await
@@ -47,6 +47,20 @@
 //! - [`cbor::Behaviour`] for CBOR-encoded messages
 //! - [`json::Behaviour`] for JSON-encoded messages
 //!
+//! ## Streaming
+//!
+//! Requests and responses that are too large to be buffered as a whole, e.g. files,
+//! can be exchanged via [`streaming::Behaviour`], which passes the message bodies
+//! as byte streams.
+//!
+//! ## Retries
+//!
+//! Requests sent via [`Behaviour::send_request_with_retry`] are retried after
+//! dial failures and timeouts according to the [`RetryPolicy`] set with
+//! [`Config::with_retry_policy`]. All attempts of such a request share an
+//! [`IdempotencyKey`] that can be embedded in the request for the remote to
+//! detect duplicate deliveries.
+//!
 //! ## Protocol Families
 //!
 //! A single [`Behaviour`] instance can be used with an entire
@@ -66,27 +80,37 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+mod admission;
 #[cfg(feature = "cbor")]
 pub mod cbor;
 mod codec;
+#[cfg(any(feature = "cbor", feature = "json"))]
+mod framing;
 mod handler;
 #[cfg(feature = "json")]
 pub mod json;
+mod retry;
+pub mod streaming;
 
+pub use admission::{InboundOverflow, ShedReason};
 pub use codec::Codec;
 pub use handler::ProtocolSupport;
+pub use retry::{IdempotencyKey, RetryPolicy};
 
+use crate::admission::QueuedRequest;
 use crate::handler::OutboundMessage;
 use futures::channel::oneshot;
 use handler::Handler;
+use instant::Instant;
 use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
     behaviour::{AddressChange, ConnectionClosed, DialFailure, FromSwarm},
     dial_opts::DialOpts,
     ConnectionDenied, ConnectionHandler, ConnectionId, NetworkBehaviour, NotifyHandler,
-    PeerAddresses, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    PeerAddresses, THandler, THandlerInEvent, THandlerOutEvent, Timers, ToSwarm,
 };
+use retry::PendingRetry;
 use smallvec::SmallVec;
 use std::{
     collections::{HashMap, HashSet, VecDeque},
@@ -181,10 +205,32 @@ pub enum OutboundFailure {
     ConnectionClosed,
     /// The remote supports none of the requested protocols.
     UnsupportedProtocols,
+    /// The remote rejected the request because it does not support its schema version.
+    ///
+    /// Reported if the [`Codec`] fails to read the response with an [`io::Error`] wrapping
+    /// [`UnsupportedSchema`].
+    UnsupportedSchema {
+        /// The schema version of the remote.
+        remote_version: u32,
+    },
     /// An IO failure happened on an outbound stream.
     Io(io::Error),
 }
 
+impl OutboundFailure {
+    fn from_io(error: io::Error) -> Self {
+        match error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<UnsupportedSchema>())
+        {
+            Some(UnsupportedSchema { remote_version }) => OutboundFailure::UnsupportedSchema {
+                remote_version: *remote_version,
+            },
+            None => OutboundFailure::Io(error),
+        }
+    }
+}
+
 impl fmt::Display for OutboundFailure {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -196,6 +242,10 @@ impl fmt::Display for OutboundFailure {
             OutboundFailure::UnsupportedProtocols => {
                 write!(f, "The remote supports none of the requested protocols")
             }
+            OutboundFailure::UnsupportedSchema { remote_version } => write!(
+                f,
+                "The remote does not support the schema version of the request, it uses version {remote_version}"
+            ),
             OutboundFailure::Io(e) => write!(f, "IO error on outbound stream: {e}"),
         }
     }
@@ -203,6 +253,29 @@ impl fmt::Display for OutboundFailure {
 
 impl std::error::Error for OutboundFailure {}
 
+/// The error of a [`Codec`] reading a response that rejects the request because the remote
+/// does not support its schema version.
+///
+/// Reported as [`OutboundFailure::UnsupportedSchema`] if wrapped in the [`io::Error`] returned
+/// by [`Codec::read_response`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedSchema {
+    /// The schema version of the remote.
+    pub remote_version: u32,
+}
+
+impl fmt::Display for UnsupportedSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "remote does not support the schema version of the request, it uses version {}",
+            self.remote_version
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedSchema {}
+
 /// Possible failures occurring in the context of receiving an
 /// inbound request and sending a response.
 #[derive(Debug)]
@@ -223,6 +296,12 @@ pub enum InboundFailure {
     ResponseOmission,
     /// An IO failure happened on an inbound stream.
     Io(io::Error),
+    /// The inbound request was shed because of the limits of concurrent inbound requests, see
+    /// [`Config::with_max_concurrent_inbound_requests`] and
+    /// [`Config::with_max_concurrent_inbound_requests_per_peer`].
+    ///
+    /// The stream of the request is closed without a response.
+    Shed(ShedReason),
 }
 
 impl fmt::Display for InboundFailure {
@@ -243,6 +322,7 @@ impl fmt::Display for InboundFailure {
                 "The response channel was dropped without sending a response to the remote"
             ),
             InboundFailure::Io(e) => write!(f, "IO error on inbound stream: {e}"),
+            InboundFailure::Shed(reason) => write!(f, "Inbound request was shed: {reason}"),
         }
     }
 }
@@ -301,6 +381,10 @@ impl fmt::Display for OutboundRequestId {
 pub struct Config {
     request_timeout: Duration,
     max_concurrent_streams: usize,
+    retry_policy: Option<RetryPolicy>,
+    max_concurrent_inbound_requests: Option<usize>,
+    max_concurrent_inbound_requests_per_peer: Option<usize>,
+    inbound_overflow: InboundOverflow,
 }
 
 impl Default for Config {
@@ -308,6 +392,10 @@ impl Default for Config {
         Self {
             request_timeout: Duration::from_secs(10),
             max_concurrent_streams: 100,
+            retry_policy: None,
+            max_concurrent_inbound_requests: None,
+            max_concurrent_inbound_requests_per_peer: None,
+            inbound_overflow: InboundOverflow::default(),
         }
     }
 }
@@ -331,6 +419,42 @@ impl Config {
         self.max_concurrent_streams = num_streams;
         self
     }
+
+    /// Sets the policy for retrying requests sent via [`Behaviour::send_request_with_retry`].
+    ///
+    /// Without a policy, which is the default, such requests are only attempted once.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Sets the maximum number of inbound requests across all peers that are emitted via
+    /// [`Message::Request`] but not yet answered.
+    ///
+    /// Requests exceeding the limit are handled according to
+    /// [`Config::with_inbound_overflow`]. Unlimited by default.
+    pub fn with_max_concurrent_inbound_requests(mut self, max: usize) -> Self {
+        self.max_concurrent_inbound_requests = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of inbound requests of a single peer that are emitted via
+    /// [`Message::Request`] but not yet answered.
+    ///
+    /// Requests exceeding the limit are handled according to
+    /// [`Config::with_inbound_overflow`]. Unlimited by default.
+    pub fn with_max_concurrent_inbound_requests_per_peer(mut self, max: usize) -> Self {
+        self.max_concurrent_inbound_requests_per_peer = Some(max);
+        self
+    }
+
+    /// Sets how inbound requests exceeding the limits of concurrent inbound requests are handled.
+    ///
+    /// Defaults to [`InboundOverflow::Reject`].
+    pub fn with_inbound_overflow(mut self, overflow: InboundOverflow) -> Self {
+        self.inbound_overflow = overflow;
+        self
+    }
 }
 
 /// A request/response protocol for some message codec.
@@ -361,6 +485,21 @@ where
     /// Requests that have not yet been sent and are waiting for a connection
     /// to be established.
     pending_outbound_requests: HashMap<PeerId, SmallVec<[OutboundMessage<TCodec>; 10]>>,
+    /// Requests sent via `send_request_with_retry` that have not yet succeeded
+    /// or ultimately failed.
+    pending_retries: HashMap<OutboundRequestId, PendingRetry<TCodec::Request>>,
+    /// Inbound requests waiting for capacity, see [`InboundOverflow::Queue`].
+    inbound_queue: VecDeque<QueuedRequest<TCodec::Request, TCodec::Response>>,
+    timers: Timers<Timer>,
+}
+
+/// The timers of a [`Behaviour`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Timer {
+    /// The backoff of a request waiting for its next attempt.
+    Retry(OutboundRequestId),
+    /// The deadline of the front of the inbound queue.
+    InboundQueue,
 }
 
 impl<TCodec> Behaviour<TCodec>
@@ -407,6 +546,9 @@ where
             connected: HashMap::new(),
             pending_outbound_requests: HashMap::new(),
             addresses: PeerAddresses::default(),
+            pending_retries: HashMap::new(),
+            inbound_queue: VecDeque::new(),
+            timers: Timers::new(),
         }
     }
 
@@ -424,25 +566,51 @@ where
     /// > [`Behaviour::remove_address`].
     pub fn send_request(&mut self, peer: &PeerId, request: TCodec::Request) -> OutboundRequestId {
         let request_id = self.next_outbound_request_id();
-        let request = OutboundMessage {
-            request_id,
-            request,
-            protocols: self.outbound_protocols.clone(),
-        };
+        self.send_outbound_message(peer, request_id, request);
 
-        if let Some(request) = self.try_send_request(peer, request) {
-            self.pending_events.push_back(ToSwarm::Dial {
-                opts: DialOpts::peer_id(*peer).build(),
-            });
-            self.pending_outbound_requests
-                .entry(*peer)
-                .or_default()
-                .push(request);
-        }
+        request_id
+    }
+
+    /// Initiates sending a request that is retried according to the
+    /// [`RetryPolicy`] of the [`Config`].
+    ///
+    /// The request is built by `request` from an [`IdempotencyKey`] which is
+    /// shared by all attempts, so that the remote can detect duplicate
+    /// deliveries if the key is embedded in the request. The key can also
+    /// be looked up via [`Behaviour::idempotency_key`] while the request is pending.
+    ///
+    /// All attempts use the returned [`OutboundRequestId`]. An
+    /// [`Event::OutboundFailure`] is only emitted once the request failed
+    /// with an error that is not retried or the attempts are exhausted.
+    pub fn send_request_with_retry<F>(&mut self, peer: &PeerId, request: F) -> OutboundRequestId
+    where
+        F: FnOnce(IdempotencyKey) -> TCodec::Request,
+        TCodec::Request: Clone + 'static,
+    {
+        let request_id = self.next_outbound_request_id();
+        let key = IdempotencyKey::random();
+        let request = request(key);
+        self.send_outbound_message(peer, request_id, request.clone());
+
+        self.pending_retries.insert(
+            request_id,
+            PendingRetry {
+                peer: *peer,
+                key,
+                request: Box::new(move || request.clone()),
+                attempt: 1,
+            },
+        );
 
         request_id
     }
 
+    /// Returns the [`IdempotencyKey`] of a pending request sent via
+    /// [`Behaviour::send_request_with_retry`].
+    pub fn idempotency_key(&self, request_id: &OutboundRequestId) -> Option<IdempotencyKey> {
+        self.pending_retries.get(request_id).map(|r| r.key)
+    }
+
     /// Initiates sending a response to an inbound request.
     ///
     /// If the [`ResponseChannel`] is already closed due to a timeout or the
@@ -509,8 +677,14 @@ where
             .get(peer)
             .map(|rps| rps.iter().any(|rp| rp.request_id == *request_id))
             .unwrap_or(false);
+        // Check if request is waiting for its next attempt.
+        let pen_retry = self
+            .pending_retries
+            .get(request_id)
+            .map(|r| r.peer == *peer)
+            .unwrap_or(false);
 
-        est_conn || pen_conn
+        est_conn || pen_conn || pen_retry
     }
 
     /// Checks whether an inbound request from the peer with the provided
@@ -533,6 +707,70 @@ where
         request_id
     }
 
+    /// Sends a request on an established connection or, if the peer is not
+    /// connected, dials the peer and queues the request until the connection
+    /// is established.
+    fn send_outbound_message(
+        &mut self,
+        peer: &PeerId,
+        request_id: OutboundRequestId,
+        request: TCodec::Request,
+    ) {
+        let request = OutboundMessage {
+            request_id,
+            request,
+            protocols: self.outbound_protocols.clone(),
+        };
+
+        if let Some(request) = self.try_send_request(peer, request) {
+            self.pending_events.push_back(ToSwarm::Dial {
+                opts: DialOpts::peer_id(*peer).build(),
+            });
+            self.pending_outbound_requests
+                .entry(*peer)
+                .or_default()
+                .push(request);
+        }
+    }
+
+    /// Reports the failure of an outbound request, unless the request was sent
+    /// via `send_request_with_retry` and is to be retried, in which case the
+    /// next attempt is scheduled instead.
+    fn on_outbound_failure(
+        &mut self,
+        peer: PeerId,
+        request_id: OutboundRequestId,
+        error: OutboundFailure,
+    ) {
+        if let Some(retry) = self.pending_retries.get_mut(&request_id) {
+            let backoff = self
+                .config
+                .retry_policy
+                .as_ref()
+                .and_then(|policy| policy.next_backoff(retry.attempt, &error));
+
+            if let Some(backoff) = backoff {
+                tracing::debug!(
+                    %peer,
+                    "Outbound request {request_id} failed on attempt {}: {error}, retrying in {backoff:?}",
+                    retry.attempt
+                );
+                retry.attempt += 1;
+                self.timers.insert(Timer::Retry(request_id), backoff);
+                return;
+            }
+
+            self.pending_retries.remove(&request_id);
+        }
+
+        self.pending_events
+            .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
+                peer,
+                request_id,
+                error,
+            }));
+    }
+
     /// Tries to send a request by queueing an appropriate event to be
     /// emitted to the `Swarm`. If the peer is not currently connected,
     /// the given request is return unchanged.
@@ -587,10 +825,131 @@ where
         request: InboundRequestId,
     ) -> bool {
         self.get_connection_mut(peer, connection)
-            .map(|c| c.pending_inbound_responses.remove(&request))
+            .map(|c| {
+                c.queued_inbound_requests.remove(&request);
+                c.pending_inbound_responses.remove(&request)
+            })
             .unwrap_or(false)
     }
 
+    /// Checks whether another inbound request of the given peer can be emitted without exceeding
+    /// the limits of concurrent inbound requests.
+    fn check_inbound_capacity(&self, peer: &PeerId) -> Result<(), ShedReason> {
+        let in_flight = |connections: &SmallVec<[Connection; 2]>| -> usize {
+            connections
+                .iter()
+                .map(|c| c.pending_inbound_responses.len() - c.queued_inbound_requests.len())
+                .sum()
+        };
+
+        if let Some(max) = self.config.max_concurrent_inbound_requests_per_peer {
+            if self.connected.get(peer).map_or(0, in_flight) >= max {
+                return Err(ShedReason::PeerLimit);
+            }
+        }
+        if let Some(max) = self.config.max_concurrent_inbound_requests {
+            if self.connected.values().map(in_flight).sum::<usize>() >= max {
+                return Err(ShedReason::GlobalLimit);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sheds an inbound request, dropping its [`ResponseChannel`].
+    fn shed_inbound_request(
+        &mut self,
+        peer: PeerId,
+        connection: ConnectionId,
+        request_id: InboundRequestId,
+        reason: ShedReason,
+    ) {
+        tracing::debug!(%peer, %request_id, "Shedding inbound request: {reason}");
+
+        if self.remove_pending_inbound_response(&peer, connection, request_id) {
+            self.pending_events
+                .push_back(ToSwarm::GenerateEvent(Event::InboundFailure {
+                    peer,
+                    request_id,
+                    error: InboundFailure::Shed(reason),
+                }));
+        }
+    }
+
+    /// Sheds queued inbound requests that timed out and emits those within the limits of
+    /// concurrent inbound requests.
+    fn poll_inbound_queue(&mut self) {
+        let now = Instant::now();
+
+        // The queue timeout is the same for all requests, hence their deadlines are ordered.
+        while let Some(queued) = self.inbound_queue.front() {
+            if self.is_queued(queued) && queued.deadline > now {
+                break;
+            }
+            let queued = self.inbound_queue.pop_front().expect("front to exist");
+            if self.is_queued(&queued) {
+                self.shed_inbound_request(
+                    queued.peer,
+                    queued.connection,
+                    queued.request_id,
+                    ShedReason::QueueTimeout,
+                );
+            }
+        }
+
+        let mut i = 0;
+        while i < self.inbound_queue.len() {
+            let queued = &self.inbound_queue[i];
+            if !self.is_queued(queued) {
+                self.inbound_queue.remove(i);
+                continue;
+            }
+            match self.check_inbound_capacity(&queued.peer) {
+                Ok(()) => {}
+                Err(ShedReason::PeerLimit) => {
+                    i += 1;
+                    continue;
+                }
+                Err(_) => break,
+            }
+
+            let queued = self.inbound_queue.remove(i).expect("index to be in bounds");
+            if let Some(connection) = self.get_connection_mut(&queued.peer, queued.connection) {
+                connection
+                    .queued_inbound_requests
+                    .remove(&queued.request_id);
+            }
+            let message = Message::Request {
+                request_id: queued.request_id,
+                request: queued.request,
+                channel: queued.channel,
+            };
+            self.pending_events
+                .push_back(ToSwarm::GenerateEvent(Event::Message {
+                    peer: queued.peer,
+                    message,
+                }));
+        }
+
+        match self.inbound_queue.front().map(|q| q.deadline) {
+            Some(deadline) if self.timers.deadline(&Timer::InboundQueue) != Some(deadline) => {
+                self.timers.insert_at(Timer::InboundQueue, deadline);
+            }
+            Some(_) => {}
+            None => {
+                self.timers.remove(&Timer::InboundQueue);
+            }
+        }
+    }
+
+    /// Whether the given request is still queued, i.e. has neither failed nor been emitted.
+    fn is_queued(&self, queued: &QueuedRequest<TCodec::Request, TCodec::Response>) -> bool {
+        self.connected
+            .get(&queued.peer)
+            .and_then(|connections| connections.iter().find(|c| c.id == queued.connection))
+            .is_some_and(|c| c.queued_inbound_requests.contains(&queued.request_id))
+    }
+
     /// Returns a mutable reference to the connection in `self.connected`
     /// corresponding to the given [`PeerId`] and [`ConnectionId`].
     fn get_connection_mut(
@@ -663,12 +1022,7 @@ where
         }
 
         for request_id in connection.pending_outbound_responses {
-            self.pending_events
-                .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
-                    peer: peer_id,
-                    request_id,
-                    error: OutboundFailure::ConnectionClosed,
-                }));
+            self.on_outbound_failure(peer_id, request_id, OutboundFailure::ConnectionClosed);
         }
     }
 
@@ -682,12 +1036,11 @@ where
             // another, concurrent dialing attempt ongoing.
             if let Some(pending) = self.pending_outbound_requests.remove(&peer) {
                 for request in pending {
-                    self.pending_events
-                        .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
-                            peer,
-                            request_id: request.request_id,
-                            error: OutboundFailure::DialFailure,
-                        }));
+                    self.on_outbound_failure(
+                        peer,
+                        request.request_id,
+                        OutboundFailure::DialFailure,
+                    );
                 }
             }
         }
@@ -820,6 +1173,7 @@ where
                     removed,
                     "Expect request_id to be pending before receiving response.",
                 );
+                self.pending_retries.remove(&request_id);
 
                 let message = Message::Response {
                     request_id,
@@ -832,24 +1186,43 @@ where
                 request_id,
                 request,
                 sender,
-            } => match self.get_connection_mut(&peer, connection) {
-                Some(connection) => {
-                    let inserted = connection.pending_inbound_responses.insert(request_id);
-                    debug_assert!(inserted, "Expect id of new request to be unknown.");
-
-                    let channel = ResponseChannel { sender };
-                    let message = Message::Request {
-                        request_id,
-                        request,
-                        channel,
-                    };
-                    self.pending_events
-                        .push_back(ToSwarm::GenerateEvent(Event::Message { peer, message }));
-                }
-                None => {
+            } => {
+                let capacity = self.check_inbound_capacity(&peer);
+                let overflow = self.config.inbound_overflow;
+                let Some(conn) = self.get_connection_mut(&peer, connection) else {
                     tracing::debug!("Connection ({connection}) closed after `Event::Request` ({request_id}) has been emitted.");
+                    return;
+                };
+                let inserted = conn.pending_inbound_responses.insert(request_id);
+                debug_assert!(inserted, "Expect id of new request to be unknown.");
+
+                let channel = ResponseChannel { sender };
+                match (capacity, overflow) {
+                    (Ok(()), _) => {
+                        let message = Message::Request {
+                            request_id,
+                            request,
+                            channel,
+                        };
+                        self.pending_events
+                            .push_back(ToSwarm::GenerateEvent(Event::Message { peer, message }));
+                    }
+                    (Err(reason), InboundOverflow::Reject) => {
+                        self.shed_inbound_request(peer, connection, request_id, reason);
+                    }
+                    (Err(_), InboundOverflow::Queue { timeout }) => {
+                        conn.queued_inbound_requests.insert(request_id);
+                        self.inbound_queue.push_back(QueuedRequest {
+                            peer,
+                            connection,
+                            request_id,
+                            request,
+                            channel,
+                            deadline: Instant::now() + timeout,
+                        });
+                    }
                 }
-            },
+            }
             handler::Event::ResponseSent(request_id) => {
                 let removed = self.remove_pending_inbound_response(&peer, connection, request_id);
                 debug_assert!(
@@ -865,17 +1238,18 @@ where
             }
             handler::Event::ResponseOmission(request_id) => {
                 let removed = self.remove_pending_inbound_response(&peer, connection, request_id);
-                debug_assert!(
-                    removed,
-                    "Expect request_id to be pending before response is omitted.",
-                );
 
-                self.pending_events
-                    .push_back(ToSwarm::GenerateEvent(Event::InboundFailure {
-                        peer,
-                        request_id,
-                        error: InboundFailure::ResponseOmission,
-                    }));
+                if removed {
+                    self.pending_events
+                        .push_back(ToSwarm::GenerateEvent(Event::InboundFailure {
+                            peer,
+                            request_id,
+                            error: InboundFailure::ResponseOmission,
+                        }));
+                } else {
+                    // This happens when the request has been shed.
+                    tracing::debug!("Response omitted for an unknown request_id ({request_id})");
+                }
             }
             handler::Event::OutboundTimeout(request_id) => {
                 let removed = self.remove_pending_outbound_response(&peer, connection, request_id);
@@ -884,12 +1258,7 @@ where
                     "Expect request_id to be pending before request times out."
                 );
 
-                self.pending_events
-                    .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
-                        peer,
-                        request_id,
-                        error: OutboundFailure::Timeout,
-                    }));
+                self.on_outbound_failure(peer, request_id, OutboundFailure::Timeout);
             }
             handler::Event::OutboundUnsupportedProtocols(request_id) => {
                 let removed = self.remove_pending_outbound_response(&peer, connection, request_id);
@@ -898,23 +1267,13 @@ where
                     "Expect request_id to be pending before failing to connect.",
                 );
 
-                self.pending_events
-                    .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
-                        peer,
-                        request_id,
-                        error: OutboundFailure::UnsupportedProtocols,
-                    }));
+                self.on_outbound_failure(peer, request_id, OutboundFailure::UnsupportedProtocols);
             }
             handler::Event::OutboundStreamFailed { request_id, error } => {
                 let removed = self.remove_pending_outbound_response(&peer, connection, request_id);
                 debug_assert!(removed, "Expect request_id to be pending upon failure");
 
-                self.pending_events
-                    .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
-                        peer,
-                        request_id,
-                        error: OutboundFailure::Io(error),
-                    }))
+                self.on_outbound_failure(peer, request_id, OutboundFailure::from_io(error));
             }
             handler::Event::InboundTimeout(request_id) => {
                 let removed = self.remove_pending_inbound_response(&peer, connection, request_id);
@@ -952,7 +1311,25 @@ where
     }
 
     #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
-    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        self.poll_inbound_queue();
+
+        while let Poll::Ready(timer) = self.timers.poll_expired(cx) {
+            match timer {
+                Timer::Retry(request_id) => {
+                    if let Some(retry) = self.pending_retries.get(&request_id) {
+                        let peer = retry.peer;
+                        let request = (retry.request)();
+                        self.send_outbound_message(&peer, request_id, request);
+                    }
+                }
+                Timer::InboundQueue => self.poll_inbound_queue(),
+            }
+        }
+
         if let Some(ev) = self.pending_events.pop_front() {
             return Poll::Ready(ev);
         } else if self.pending_events.capacity() > EMPTY_QUEUE_SHRINK_THRESHOLD {
@@ -980,6 +1357,9 @@ struct Connection {
     /// Pending inbound responses for previously sent requests on this
     /// connection.
     pending_inbound_responses: HashSet<InboundRequestId>,
+    /// The subset of `pending_inbound_responses` waiting for capacity in the inbound queue of
+    /// the behaviour, i.e. not yet emitted via `poll`.
+    queued_inbound_requests: HashSet<InboundRequestId>,
 }
 
 impl Connection {
@@ -989,6 +1369,7 @@ impl Connection {
             remote_address,
             pending_outbound_responses: Default::default(),
             pending_inbound_responses: Default::default(),
+            queued_inbound_requests: Default::default(),
         }
     }
 }
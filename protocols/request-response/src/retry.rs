@@ -0,0 +1,187 @@
+// Copyright 2024 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::OutboundFailure;
+use libp2p_identity::PeerId;
+use std::{fmt, time::Duration};
+
+/// The policy for retrying requests sent via
+/// [`Behaviour::send_request_with_retry`](crate::Behaviour::send_request_with_retry).
+///
+/// Failed attempts are retried after an exponential backoff, starting at
+/// [`RetryPolicy::with_initial_backoff`] and doubling with every attempt up to
+/// [`RetryPolicy::with_max_backoff`].
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    retry_on_dial_failure: bool,
+    retry_on_timeout: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            retry_on_dial_failure: true,
+            retry_on_timeout: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sets the maximum number of attempts per request, including the first one.
+    ///
+    /// A value of `1` disables retries.
+    pub fn with_max_attempts(mut self, v: u32) -> Self {
+        self.max_attempts = v.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn with_initial_backoff(mut self, v: Duration) -> Self {
+        self.initial_backoff = v;
+        self
+    }
+
+    /// Sets the upper bound for the delay between two attempts.
+    pub fn with_max_backoff(mut self, v: Duration) -> Self {
+        self.max_backoff = v;
+        self
+    }
+
+    /// Sets whether requests are retried after [`OutboundFailure::DialFailure`].
+    pub fn with_retry_on_dial_failure(mut self, v: bool) -> Self {
+        self.retry_on_dial_failure = v;
+        self
+    }
+
+    /// Sets whether requests are retried after [`OutboundFailure::Timeout`].
+    pub fn with_retry_on_timeout(mut self, v: bool) -> Self {
+        self.retry_on_timeout = v;
+        self
+    }
+
+    /// Returns the delay before the next attempt if a request that failed
+    /// with `error` on its `attempt`-th attempt is to be retried.
+    pub(crate) fn next_backoff(&self, attempt: u32, error: &OutboundFailure) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let retryable = match error {
+            OutboundFailure::DialFailure => self.retry_on_dial_failure,
+            OutboundFailure::Timeout => self.retry_on_timeout,
+            _ => false,
+        };
+        if !retryable {
+            return None;
+        }
+
+        let factor = 1u32.checked_shl(attempt - 1).unwrap_or(u32::MAX);
+        Some(
+            self.initial_backoff
+                .saturating_mul(factor)
+                .min(self.max_backoff),
+        )
+    }
+}
+
+/// A key identifying all attempts of a request sent via
+/// [`Behaviour::send_request_with_retry`](crate::Behaviour::send_request_with_retry).
+///
+/// The key is randomly generated and stays the same across retries. Embedding it
+/// in the request allows the remote to detect duplicate deliveries, e.g. when an
+/// attempt timed out after the remote already processed the request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IdempotencyKey(u64);
+
+impl IdempotencyKey {
+    pub(crate) fn random() -> Self {
+        Self(rand::random())
+    }
+
+    /// Creates a key from its integer representation, e.g. after decoding it from a request.
+    pub fn from_u64(v: u64) -> Self {
+        Self(v)
+    }
+
+    /// Returns the integer representation of the key, e.g. for encoding it in a request.
+    pub fn into_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for IdempotencyKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Internal state of a request sent via
+/// [`Behaviour::send_request_with_retry`](crate::Behaviour::send_request_with_retry).
+pub(crate) struct PendingRetry<TRequest> {
+    pub(crate) peer: PeerId,
+    pub(crate) key: IdempotencyKey,
+    /// Produces a copy of the request for the next attempt.
+    pub(crate) request: Box<dyn Fn() -> TRequest + Send>,
+    /// The number of attempts made so far.
+    pub(crate) attempt: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = RetryPolicy::default()
+            .with_max_attempts(10)
+            .with_initial_backoff(Duration::from_secs(1))
+            .with_max_backoff(Duration::from_secs(5));
+
+        let backoffs = (1..10)
+            .map(|attempt| policy.next_backoff(attempt, &OutboundFailure::Timeout))
+            .collect::<Vec<_>>();
+
+        assert_eq!(backoffs[0], Some(Duration::from_secs(1)));
+        assert_eq!(backoffs[1], Some(Duration::from_secs(2)));
+        assert_eq!(backoffs[2], Some(Duration::from_secs(4)));
+        assert!(backoffs[3..]
+            .iter()
+            .all(|b| *b == Some(Duration::from_secs(5))));
+        assert_eq!(policy.next_backoff(10, &OutboundFailure::Timeout), None);
+    }
+
+    #[test]
+    fn only_configured_failures_are_retried() {
+        let policy = RetryPolicy::default().with_retry_on_timeout(false);
+
+        assert!(policy
+            .next_backoff(1, &OutboundFailure::DialFailure)
+            .is_some());
+        assert!(policy.next_backoff(1, &OutboundFailure::Timeout).is_none());
+        assert!(policy
+            .next_backoff(1, &OutboundFailure::ConnectionClosed)
+            .is_none());
+    }
+}
@@ -0,0 +1,236 @@
+// Copyright 2024 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Integration tests for the limits of concurrent inbound requests.
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p_identity::PeerId;
+use libp2p_request_response as request_response;
+use libp2p_request_response::{
+    Codec, Event, InboundFailure, InboundOverflow, Message, ProtocolSupport, ShedReason,
+};
+use libp2p_swarm::{StreamProtocol, Swarm};
+use libp2p_swarm_test::SwarmExt;
+use std::time::Duration;
+use std::{io, iter};
+use tracing_subscriber::EnvFilter;
+
+#[async_std::test]
+async fn sheds_requests_exceeding_peer_limit() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let (peer1_id, mut swarm1) = new_swarm(
+        request_response::Config::default().with_max_concurrent_inbound_requests_per_peer(1),
+    );
+    let (peer2_id, mut swarm2) = new_swarm(request_response::Config::default());
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    swarm2.behaviour_mut().send_request(&peer1_id, 1);
+    swarm2.behaviour_mut().send_request(&peer1_id, 2);
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    let channel = match swarm1.next_behaviour_event().await {
+        Event::Message {
+            peer,
+            message: Message::Request {
+                request, channel, ..
+            },
+        } => {
+            assert_eq!(peer, peer2_id);
+            assert!(request == 1 || request == 2);
+            channel
+        }
+        e => panic!("Unexpected event: {e:?}"),
+    };
+    match swarm1.next_behaviour_event().await {
+        Event::InboundFailure {
+            peer,
+            error: InboundFailure::Shed(ShedReason::PeerLimit),
+            ..
+        } => assert_eq!(peer, peer2_id),
+        e => panic!("Unexpected event: {e:?}"),
+    }
+
+    // Once answered, the peer is within its limit again.
+    swarm1.behaviour_mut().send_response(channel, 1).unwrap();
+    swarm1
+        .wait(|e| match e.try_into_behaviour_event() {
+            Ok(Event::ResponseSent { .. }) => Some(()),
+            _ => None,
+        })
+        .await;
+}
+
+#[async_std::test]
+async fn queues_requests_exceeding_global_limit() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let (peer1_id, mut swarm1) = new_swarm(
+        request_response::Config::default()
+            .with_max_concurrent_inbound_requests(1)
+            .with_inbound_overflow(InboundOverflow::Queue {
+                timeout: Duration::from_secs(5),
+            }),
+    );
+    let (_, mut swarm2) = new_swarm(request_response::Config::default());
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    swarm2.behaviour_mut().send_request(&peer1_id, 1);
+    swarm2.behaviour_mut().send_request(&peer1_id, 2);
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    let (first, channel) = next_request(&mut swarm1).await;
+
+    // The second request is held back until the first one is answered.
+    let held_back =
+        async_std::future::timeout(Duration::from_millis(200), next_request(&mut swarm1));
+    assert!(held_back.await.is_err());
+
+    swarm1
+        .behaviour_mut()
+        .send_response(channel, first)
+        .unwrap();
+    let (second, _) = next_request(&mut swarm1).await;
+    assert_eq!(first + second, 3);
+}
+
+#[async_std::test]
+async fn sheds_queued_requests_after_timeout() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let (peer1_id, mut swarm1) = new_swarm(
+        request_response::Config::default()
+            .with_max_concurrent_inbound_requests(1)
+            .with_inbound_overflow(InboundOverflow::Queue {
+                timeout: Duration::from_millis(100),
+            }),
+    );
+    let (peer2_id, mut swarm2) = new_swarm(request_response::Config::default());
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    swarm2.behaviour_mut().send_request(&peer1_id, 1);
+    swarm2.behaviour_mut().send_request(&peer1_id, 2);
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    let (_, _channel) = next_request(&mut swarm1).await;
+    match swarm1.next_behaviour_event().await {
+        Event::InboundFailure {
+            peer,
+            error: InboundFailure::Shed(ShedReason::QueueTimeout),
+            ..
+        } => assert_eq!(peer, peer2_id),
+        e => panic!("Unexpected event: {e:?}"),
+    }
+}
+
+async fn next_request(
+    swarm: &mut Swarm<request_response::Behaviour<NumberCodec>>,
+) -> (u64, request_response::ResponseChannel<u64>) {
+    loop {
+        match swarm.next_behaviour_event().await {
+            Event::Message {
+                message:
+                    Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            } => return (request, channel),
+            Event::ResponseSent { .. } => {}
+            e => panic!("Unexpected event: {e:?}"),
+        }
+    }
+}
+
+fn new_swarm(
+    cfg: request_response::Config,
+) -> (PeerId, Swarm<request_response::Behaviour<NumberCodec>>) {
+    let protocols = iter::once((StreamProtocol::new("/number/1"), ProtocolSupport::Full));
+    let swarm =
+        Swarm::new_ephemeral(|_| request_response::Behaviour::<NumberCodec>::new(protocols, cfg));
+    let peer_id = *swarm.local_peer_id();
+
+    (peer_id, swarm)
+}
+
+/// A codec for requests and responses consisting of a single number.
+#[derive(Clone, Default)]
+struct NumberCodec;
+
+#[async_trait]
+impl Codec for NumberCodec {
+    type Protocol = StreamProtocol;
+    type Request = u64;
+    type Response = u64;
+
+    async fn read_request<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<u64>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_number(io).await
+    }
+
+    async fn read_response<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<u64>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_number(io).await
+    }
+
+    async fn write_request<T>(&mut self, _: &StreamProtocol, io: &mut T, req: u64) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        io.write_all(&req.to_be_bytes()).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        res: u64,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        io.write_all(&res.to_be_bytes()).await
+    }
+}
+
+async fn read_number<T>(io: &mut T) -> io::Result<u64>
+where
+    T: AsyncRead + Unpin + Send,
+{
+    let mut buf = [0; 8];
+    io.read_exact(&mut buf).await?;
+    Ok(u64::from_be_bytes(buf))
+}
@@ -297,6 +297,50 @@ async fn emits_inbound_connection_closed_if_channel_is_dropped() {
     ));
 }
 
+#[async_std::test]
+#[cfg(feature = "cbor")]
+async fn reports_unsupported_schema_version() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let ping = Ping("ping".to_string().into_bytes());
+    let protocols = iter::once((StreamProtocol::new("/ping/1"), ProtocolSupport::Full));
+    let cfg = request_response::Config::default();
+    let codec = |version| {
+        request_response::cbor::Codec::<Ping, Pong>::default()
+            .set_length_prefix(true)
+            .set_schema_version(version, move |v| v == version)
+    };
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| {
+        request_response::Behaviour::with_codec(codec(1), protocols.clone(), cfg.clone())
+    });
+    let peer1_id = *swarm1.local_peer_id();
+    let mut swarm2 =
+        Swarm::new_ephemeral(|_| request_response::Behaviour::with_codec(codec(2), protocols, cfg));
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    swarm2.behaviour_mut().send_request(&peer1_id, ping);
+    async_std::task::spawn(swarm1.loop_on_next());
+
+    let error = swarm2
+        .wait(|event| match event {
+            SwarmEvent::Behaviour(request_response::Event::OutboundFailure { error, .. }) => {
+                Some(error)
+            }
+            _ => None,
+        })
+        .await;
+
+    assert!(matches!(
+        error,
+        request_response::OutboundFailure::UnsupportedSchema { remote_version: 1 }
+    ));
+}
+
 // Simple Ping-Pong Protocol
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct Ping(Vec<u8>);
@@ -0,0 +1,219 @@
+// Copyright 2024 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Integration tests for retrying requests sent via `send_request_with_retry`.
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p_identity::PeerId;
+use libp2p_request_response as request_response;
+use libp2p_request_response::{
+    Codec, Event, IdempotencyKey, Message, OutboundFailure, ProtocolSupport, RetryPolicy,
+};
+use libp2p_swarm::{StreamProtocol, Swarm};
+use libp2p_swarm_test::SwarmExt;
+use std::pin::pin;
+use std::time::{Duration, Instant};
+use std::{io, iter};
+use tracing_subscriber::EnvFilter;
+
+/// The first attempt times out because the server holds back the response,
+/// the second attempt carries the same key and is answered.
+#[async_std::test]
+async fn retries_timed_out_request_with_same_key() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let policy = RetryPolicy::default().with_initial_backoff(Duration::from_millis(10));
+    // `swarm1` needs to have a bigger timeout to not time out the held back request itself.
+    let (peer1_id, mut swarm1) =
+        new_swarm(request_response::Config::default().with_request_timeout(Duration::from_secs(5)));
+    let (_, mut swarm2) = new_swarm(
+        request_response::Config::default()
+            .with_request_timeout(Duration::from_millis(100))
+            .with_retry_policy(policy),
+    );
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    let server_task = async move {
+        let mut held_back = Vec::new();
+        let mut seen_keys = Vec::new();
+        loop {
+            if let Ok(Event::Message {
+                message:
+                    Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            }) = swarm1.next_swarm_event().await.try_into_behaviour_event()
+            {
+                if seen_keys.contains(&request) {
+                    swarm1
+                        .behaviour_mut()
+                        .send_response(channel, request)
+                        .unwrap();
+                } else {
+                    seen_keys.push(request);
+                    held_back.push(channel);
+                }
+            }
+        }
+    };
+
+    let client_task = async move {
+        let req_id = swarm2
+            .behaviour_mut()
+            .send_request_with_retry(&peer1_id, IdempotencyKey::into_u64);
+        let key = swarm2.behaviour().idempotency_key(&req_id).unwrap();
+
+        loop {
+            match swarm2.next_swarm_event().await.try_into_behaviour_event() {
+                Ok(Event::Message {
+                    peer,
+                    message:
+                        Message::Response {
+                            request_id,
+                            response,
+                        },
+                }) => {
+                    assert_eq!(peer, peer1_id);
+                    assert_eq!(request_id, req_id);
+                    assert_eq!(response, key.into_u64());
+                    break;
+                }
+                Ok(e) => panic!("Unexpected event: {e:?}"),
+                Err(..) => {}
+            }
+        }
+
+        assert!(!swarm2.behaviour().is_pending_outbound(&peer1_id, &req_id));
+        assert_eq!(swarm2.behaviour().idempotency_key(&req_id), None);
+    };
+
+    let server_task = pin!(server_task);
+    let client_task = pin!(client_task);
+    futures::future::select(server_task, client_task).await;
+}
+
+#[async_std::test]
+async fn reports_dial_failure_once_attempts_are_exhausted() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let policy = RetryPolicy::default()
+        .with_max_attempts(3)
+        .with_initial_backoff(Duration::from_millis(10));
+    let (_, mut swarm) = new_swarm(request_response::Config::default().with_retry_policy(policy));
+
+    let unknown_peer = PeerId::random();
+    let start = Instant::now();
+    let req_id = swarm
+        .behaviour_mut()
+        .send_request_with_retry(&unknown_peer, IdempotencyKey::into_u64);
+
+    loop {
+        match swarm.next_swarm_event().await.try_into_behaviour_event() {
+            Ok(Event::OutboundFailure {
+                peer,
+                request_id,
+                error: OutboundFailure::DialFailure,
+            }) => {
+                assert_eq!(peer, unknown_peer);
+                assert_eq!(request_id, req_id);
+                break;
+            }
+            Ok(e) => panic!("Unexpected event: {e:?}"),
+            Err(..) => {}
+        }
+    }
+    // The failure is only reported after both backoffs of 10ms and 20ms.
+    assert!(start.elapsed() >= Duration::from_millis(30));
+    assert!(!swarm
+        .behaviour()
+        .is_pending_outbound(&unknown_peer, &req_id));
+}
+
+fn new_swarm(
+    cfg: request_response::Config,
+) -> (PeerId, Swarm<request_response::Behaviour<KeyCodec>>) {
+    let protocols = iter::once((StreamProtocol::new("/key/1"), ProtocolSupport::Full));
+    let swarm =
+        Swarm::new_ephemeral(|_| request_response::Behaviour::<KeyCodec>::new(protocols, cfg));
+    let peer_id = *swarm.local_peer_id();
+
+    (peer_id, swarm)
+}
+
+/// A codec for requests and responses consisting of an idempotency key only.
+#[derive(Clone, Default)]
+struct KeyCodec;
+
+#[async_trait]
+impl Codec for KeyCodec {
+    type Protocol = StreamProtocol;
+    type Request = u64;
+    type Response = u64;
+
+    async fn read_request<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<u64>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_key(io).await
+    }
+
+    async fn read_response<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<u64>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_key(io).await
+    }
+
+    async fn write_request<T>(&mut self, _: &StreamProtocol, io: &mut T, req: u64) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        io.write_all(&req.to_be_bytes()).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        res: u64,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        io.write_all(&res.to_be_bytes()).await
+    }
+}
+
+async fn read_key<T>(io: &mut T) -> io::Result<u64>
+where
+    T: AsyncRead + Unpin + Send,
+{
+    let mut buf = [0; 8];
+    io.read_exact(&mut buf).await?;
+    Ok(u64::from_be_bytes(buf))
+}
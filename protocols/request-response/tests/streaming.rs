@@ -0,0 +1,249 @@
+// Copyright 2024 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Integration tests for the streaming `Behaviour`.
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p_request_response::streaming::{self, Codec};
+use libp2p_request_response::{self as request_response, InboundFailure, ProtocolSupport};
+use libp2p_swarm::{StreamProtocol, Swarm};
+use libp2p_swarm_test::SwarmExt;
+use rand::RngCore;
+use std::{io, iter};
+use tracing_subscriber::EnvFilter;
+
+/// Transfers bodies larger than any single read or write in both directions.
+#[async_std::test]
+async fn streams_request_and_response_bodies() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let request_body = random_bytes(1024 * 1024);
+    let response_body = random_bytes(2 * 1024 * 1024);
+
+    let (peer1_id, mut swarm1) = new_swarm();
+    let (peer2_id, mut swarm2) = new_swarm();
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    let expected_request_body = request_body.clone();
+    let server_response_body = response_body.clone();
+    let peer1 = async move {
+        loop {
+            match swarm1.next_swarm_event().await.try_into_behaviour_event() {
+                Ok(streaming::Event::Message {
+                    peer,
+                    message:
+                        streaming::Message::Request {
+                            request,
+                            mut body,
+                            channel,
+                            ..
+                        },
+                }) => {
+                    assert_eq!(peer, peer2_id);
+                    assert_eq!(request, expected_request_body.len() as u64);
+
+                    let mut received = Vec::new();
+                    body.read_to_end(&mut received).await.unwrap();
+                    assert_eq!(received, expected_request_body);
+
+                    swarm1
+                        .behaviour_mut()
+                        .send_response(
+                            channel,
+                            server_response_body.len() as u64,
+                            futures::io::Cursor::new(server_response_body.clone()),
+                        )
+                        .unwrap();
+                }
+                Ok(streaming::Event::ResponseSent { peer, .. }) => {
+                    assert_eq!(peer, peer2_id);
+                }
+                Ok(e) => panic!("Peer1: Unexpected event: {e:?}"),
+                Err(..) => {}
+            }
+        }
+    };
+    async_std::task::spawn(Box::pin(peer1));
+
+    let req_id = swarm2.behaviour_mut().send_request(
+        &peer1_id,
+        request_body.len() as u64,
+        futures::io::Cursor::new(request_body),
+    );
+    assert!(swarm2.behaviour().is_pending_outbound(&peer1_id, &req_id));
+
+    match swarm2
+        .next_swarm_event()
+        .await
+        .try_into_behaviour_event()
+        .unwrap()
+    {
+        streaming::Event::Message {
+            peer,
+            message:
+                streaming::Message::Response {
+                    request_id,
+                    response,
+                    mut body,
+                },
+        } => {
+            assert_eq!(peer, peer1_id);
+            assert_eq!(request_id, req_id);
+            assert_eq!(response, response_body.len() as u64);
+
+            let mut received = Vec::new();
+            body.read_to_end(&mut received).await.unwrap();
+            assert_eq!(received, response_body);
+        }
+        e => panic!("Peer2: Unexpected event: {e:?}"),
+    }
+    assert!(!swarm2.behaviour().is_pending_outbound(&peer1_id, &req_id));
+}
+
+#[async_std::test]
+async fn reports_response_omission() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let (peer1_id, mut swarm1) = new_swarm();
+    let (peer2_id, mut swarm2) = new_swarm();
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    swarm2
+        .behaviour_mut()
+        .send_request(&peer1_id, 0, futures::io::empty());
+
+    let peer2 = async move {
+        loop {
+            swarm2.next_swarm_event().await;
+        }
+    };
+    async_std::task::spawn(Box::pin(peer2));
+
+    let request_id = match swarm1
+        .next_swarm_event()
+        .await
+        .try_into_behaviour_event()
+        .unwrap()
+    {
+        streaming::Event::Message {
+            message: streaming::Message::Request { request_id, .. },
+            ..
+        } => request_id,
+        e => panic!("Peer1: Unexpected event: {e:?}"),
+    };
+
+    match swarm1
+        .next_swarm_event()
+        .await
+        .try_into_behaviour_event()
+        .unwrap()
+    {
+        streaming::Event::InboundFailure {
+            peer,
+            request_id: failed_id,
+            error: InboundFailure::ResponseOmission,
+        } => {
+            assert_eq!(peer, peer2_id);
+            assert_eq!(failed_id, request_id);
+        }
+        e => panic!("Peer1: Unexpected event: {e:?}"),
+    }
+}
+
+fn new_swarm() -> (
+    libp2p_identity::PeerId,
+    Swarm<streaming::Behaviour<LengthCodec>>,
+) {
+    let protocols = iter::once((StreamProtocol::new("/length/1"), ProtocolSupport::Full));
+    let swarm = Swarm::new_ephemeral(|_| {
+        streaming::Behaviour::<LengthCodec>::new(protocols, request_response::Config::default())
+    });
+    let peer_id = *swarm.local_peer_id();
+
+    (peer_id, swarm)
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+/// A codec for headers announcing the length of the body as a fixed-size integer.
+#[derive(Clone, Default)]
+struct LengthCodec;
+
+#[async_trait]
+impl Codec for LengthCodec {
+    type Protocol = StreamProtocol;
+    type Request = u64;
+    type Response = u64;
+
+    async fn read_request<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<u64>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_length(io).await
+    }
+
+    async fn read_response<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<u64>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_length(io).await
+    }
+
+    async fn write_request<T>(&mut self, _: &StreamProtocol, io: &mut T, req: u64) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        io.write_all(&req.to_be_bytes()).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        res: u64,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        io.write_all(&res.to_be_bytes()).await
+    }
+}
+
+async fn read_length<T>(io: &mut T) -> io::Result<u64>
+where
+    T: AsyncRead + Unpin + Send,
+{
+    let mut buf = [0; 8];
+    io.read_exact(&mut buf).await?;
+    Ok(u64::from_be_bytes(buf))
+}
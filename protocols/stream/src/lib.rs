@@ -3,8 +3,10 @@
 mod behaviour;
 mod control;
 mod handler;
+mod registry;
 mod shared;
 mod upgrade;
 
 pub use behaviour::{AlreadyRegistered, Behaviour};
 pub use control::{Control, IncomingStreams, OpenStreamError};
+pub use registry::Registry;
@@ -0,0 +1,246 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt as _, StreamExt as _};
+use instant::Instant;
+use libp2p_identity::PeerId;
+use libp2p_swarm::{Stream, StreamProtocol};
+use prometheus_client::{
+    encoding::EncodeLabelSet,
+    metrics::{
+        counter::Counter,
+        family::Family,
+        gauge::Gauge,
+        histogram::{exponential_buckets, Histogram},
+    },
+    registry::Registry as MetricsRegistry,
+};
+
+use crate::{AlreadyRegistered, Control, IncomingStreams};
+
+/// A registry of handlers for inbound streams, one per protocol.
+///
+/// Each handler is an async closure, called with every inbound stream of its protocol. The
+/// number of streams a handler processes concurrently is limited per protocol, inbound streams
+/// exceeding the limit are dropped.
+///
+/// The [`Registry`] is a [`Future`] that drives the handlers and never completes. It is to be
+/// spawned onto an executor once all handlers are registered.
+#[must_use = "Handlers do nothing unless the registry is polled."]
+pub struct Registry {
+    control: Control,
+    services: HashMap<StreamProtocol, Service>,
+    metrics: Option<Metrics>,
+}
+
+impl Registry {
+    /// Creates an empty registry, accepting inbound streams through the given [`Control`].
+    pub fn new(control: Control) -> Self {
+        Self {
+            control,
+            services: HashMap::new(),
+            metrics: None,
+        }
+    }
+
+    /// Records metrics of the handlers in the given registry, labelled by protocol.
+    pub fn with_metrics(mut self, registry: &mut MetricsRegistry) -> Self {
+        self.metrics = Some(Metrics::new(registry));
+        self
+    }
+
+    /// Registers the handler for inbound streams of `protocol`, processing at most
+    /// `max_concurrent_streams` streams at a time.
+    ///
+    /// A handler returning an error is recorded in the metrics and logged.
+    pub fn register<F, Fut>(
+        &mut self,
+        protocol: StreamProtocol,
+        max_concurrent_streams: usize,
+        mut handler: F,
+    ) -> Result<(), AlreadyRegistered>
+    where
+        F: FnMut(PeerId, Stream) -> Fut + Send + 'static,
+        Fut: Future<Output = io::Result<()>> + Send + 'static,
+    {
+        if self.services.contains_key(&protocol) {
+            return Err(AlreadyRegistered);
+        }
+        let incoming = self.control.accept(protocol.clone())?;
+
+        self.services.insert(
+            protocol.clone(),
+            Service {
+                label: ProtocolLabel {
+                    protocol: protocol.to_string(),
+                },
+                incoming,
+                handler: Box::new(move |peer, stream| handler(peer, stream).boxed()),
+                max_concurrent_streams,
+                active: FuturesUnordered::new(),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// The protocols with a registered handler.
+    pub fn protocols(&self) -> impl Iterator<Item = &StreamProtocol> {
+        self.services.keys()
+    }
+}
+
+impl Future for Registry {
+    type Output = void::Void;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        for (protocol, service) in this.services.iter_mut() {
+            service.poll(protocol, this.metrics.as_ref(), cx);
+        }
+
+        Poll::Pending
+    }
+}
+
+/// The handler of a protocol and the streams it is processing.
+struct Service {
+    label: ProtocolLabel,
+    incoming: IncomingStreams,
+    handler: Box<dyn FnMut(PeerId, Stream) -> BoxFuture<'static, io::Result<()>> + Send>,
+    max_concurrent_streams: usize,
+    active: FuturesUnordered<BoxFuture<'static, (PeerId, Instant, io::Result<()>)>>,
+}
+
+impl Service {
+    fn poll(&mut self, protocol: &StreamProtocol, metrics: Option<&Metrics>, cx: &mut Context<'_>) {
+        // Streams whose handler finished free up capacity for the inbound streams to come.
+        self.poll_active(protocol, metrics, cx);
+
+        let mut accepted_any = false;
+        while let Poll::Ready(Some((peer, stream))) = self.incoming.poll_next_unpin(cx) {
+            if self.active.len() >= self.max_concurrent_streams {
+                tracing::debug!(
+                    %peer,
+                    %protocol,
+                    "Dropping inbound stream, reached limit of {} concurrent streams",
+                    self.max_concurrent_streams
+                );
+                if let Some(metrics) = metrics {
+                    metrics.rejected.get_or_create(&self.label).inc();
+                }
+                continue;
+            }
+
+            if let Some(metrics) = metrics {
+                metrics.accepted.get_or_create(&self.label).inc();
+                metrics.active.get_or_create(&self.label).inc();
+            }
+            let started = Instant::now();
+            self.active.push(
+                (self.handler)(peer, stream)
+                    .map(move |result| (peer, started, result))
+                    .boxed(),
+            );
+            accepted_any = true;
+        }
+
+        if accepted_any {
+            self.poll_active(protocol, metrics, cx);
+        }
+    }
+
+    fn poll_active(
+        &mut self,
+        protocol: &StreamProtocol,
+        metrics: Option<&Metrics>,
+        cx: &mut Context<'_>,
+    ) {
+        while let Poll::Ready(Some((peer, started, result))) = self.active.poll_next_unpin(cx) {
+            if let Some(metrics) = metrics {
+                metrics.on_stream_finished(&self.label, started, result.is_err());
+            }
+            if let Err(e) = result {
+                tracing::debug!(%peer, %protocol, "Stream handler failed: {e}");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+struct ProtocolLabel {
+    protocol: String,
+}
+
+struct Metrics {
+    /// The number of inbound streams passed to a handler.
+    accepted: Family<ProtocolLabel, Counter>,
+    /// The number of inbound streams dropped because the handler was at its limit.
+    rejected: Family<ProtocolLabel, Counter>,
+    /// The number of handlers that returned an error.
+    failed: Family<ProtocolLabel, Counter>,
+    /// The number of streams being processed by a handler.
+    active: Family<ProtocolLabel, Gauge>,
+    /// The time handlers took to process a stream.
+    duration: Family<ProtocolLabel, Histogram>,
+}
+
+impl Metrics {
+    fn new(registry: &mut MetricsRegistry) -> Self {
+        let accepted = Family::default();
+        registry.register(
+            "inbound_streams_accepted",
+            "Number of inbound streams passed to the handler of their protocol",
+            accepted.clone(),
+        );
+        let rejected = Family::default();
+        registry.register(
+            "inbound_streams_rejected",
+            "Number of inbound streams dropped because the handler of their protocol was at its limit",
+            rejected.clone(),
+        );
+        let failed = Family::default();
+        registry.register(
+            "inbound_streams_failed",
+            "Number of inbound streams whose handler returned an error",
+            failed.clone(),
+        );
+        let active = Family::default();
+        registry.register(
+            "inbound_streams_active",
+            "Number of inbound streams being processed by the handler of their protocol",
+            active.clone(),
+        );
+        let duration: Family<_, _> =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(0.001, 2.0, 16)));
+        registry.register(
+            "inbound_stream_duration_seconds",
+            "Time the handler took to process an inbound stream",
+            duration.clone(),
+        );
+
+        Self {
+            accepted,
+            rejected,
+            failed,
+            active,
+            duration,
+        }
+    }
+
+    fn on_stream_finished(&self, label: &ProtocolLabel, started: Instant, failed: bool) {
+        self.active.get_or_create(label).dec();
+        self.duration
+            .get_or_create(label)
+            .observe(started.elapsed().as_secs_f64());
+        if failed {
+            self.failed.get_or_create(label).inc();
+        }
+    }
+}
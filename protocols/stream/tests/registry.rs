@@ -0,0 +1,77 @@
+use futures::{AsyncReadExt as _, AsyncWriteExt as _};
+use libp2p_stream as stream;
+use libp2p_swarm::{StreamProtocol, Swarm};
+use libp2p_swarm_test::SwarmExt as _;
+use prometheus_client::{encoding::text::encode, registry::Registry};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::EnvFilter;
+
+const ECHO: StreamProtocol = StreamProtocol::new("/echo");
+const HOLD: StreamProtocol = StreamProtocol::new("/hold");
+
+#[tokio::test]
+async fn registry_runs_handlers_within_limits() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::DEBUG.into())
+                .from_env()
+                .unwrap(),
+        )
+        .with_test_writer()
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| stream::Behaviour::new());
+    let mut swarm2 = Swarm::new_ephemeral(|_| stream::Behaviour::new());
+
+    let mut metrics = Registry::default();
+    let mut registry =
+        stream::Registry::new(swarm2.behaviour().new_control()).with_metrics(&mut metrics);
+    registry
+        .register(ECHO, 10, |_, mut stream| async move {
+            let mut buf = [0u8; 1];
+            stream.read_exact(&mut buf).await?;
+            stream.write_all(&buf).await?;
+            stream.close().await
+        })
+        .unwrap();
+    registry
+        .register(HOLD, 1, |_, stream| async move {
+            let _stream = stream;
+            futures::future::pending::<()>().await;
+            Ok(())
+        })
+        .unwrap();
+    assert!(registry.register(ECHO, 1, |_, _| async { Ok(()) }).is_err());
+
+    let mut control = swarm1.behaviour().new_control();
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+    let swarm2_peer_id = *swarm2.local_peer_id();
+
+    tokio::spawn(registry);
+    tokio::spawn(swarm1.loop_on_next());
+    tokio::spawn(swarm2.loop_on_next());
+
+    let mut stream = control.open_stream(swarm2_peer_id, ECHO).await.unwrap();
+    stream.write_all(&[42]).await.unwrap();
+    let mut buf = [0u8; 1];
+    stream.read_exact(&mut buf).await.unwrap();
+    assert_eq!([42], buf);
+
+    // The second stream exceeds the limit of the handler and is dropped.
+    let mut held = control.open_stream(swarm2_peer_id, HOLD).await.unwrap();
+    held.write_all(&[1]).await.unwrap();
+    held.flush().await.unwrap();
+    let mut dropped = control.open_stream(swarm2_peer_id, HOLD).await.unwrap();
+    let mut buf = Vec::new();
+    let _ = dropped.read_to_end(&mut buf).await;
+    assert!(buf.is_empty());
+
+    let mut encoded = String::new();
+    encode(&mut encoded, &metrics).unwrap();
+    assert!(encoded.contains(r#"inbound_streams_accepted_total{protocol="/echo"} 1"#));
+    assert!(encoded.contains(r#"inbound_streams_accepted_total{protocol="/hold"} 1"#));
+    assert!(encoded.contains(r#"inbound_streams_rejected_total{protocol="/hold"} 1"#));
+    assert!(encoded.contains(r#"inbound_streams_active{protocol="/hold"} 1"#));
+}
@@ -22,7 +22,7 @@
 
 use std::{
     borrow::Borrow,
-    collections::{HashMap, VecDeque},
+    collections::{hash_map::Entry, HashMap, VecDeque},
     error::Error,
     hash::{Hash, Hasher},
     net::{self, IpAddr, SocketAddr, SocketAddrV4},
@@ -35,19 +35,18 @@ use std::{
 use crate::tokio::{is_addr_global, Gateway};
 use futures::{channel::oneshot, Future, StreamExt};
 use futures_timer::Delay;
+use if_watch::{tokio::IfWatcher, IfEvent, IpNet, Ipv4Net};
 use igd_next::PortMappingProtocol;
 use libp2p_core::{multiaddr, transport::ListenerId, Endpoint, Multiaddr};
 use libp2p_swarm::{
     derive_prelude::PeerId, dummy, ConnectionDenied, ConnectionId, ExpiredListenAddr, FromSwarm,
     NetworkBehaviour, NewListenAddr, ToSwarm,
 };
+use void::Void;
 
 /// The duration in seconds of a port mapping on the gateway.
 const MAPPING_DURATION: u32 = 3600;
 
-/// Renew the Mapping every half of `MAPPING_DURATION` to avoid the port being unmapped.
-const MAPPING_TIMEOUT: u64 = MAPPING_DURATION as u64 / 2;
-
 /// A [`Gateway`] Request.
 #[derive(Debug)]
 pub(crate) enum GatewayRequest {
@@ -58,14 +57,20 @@ pub(crate) enum GatewayRequest {
 /// A [`Gateway`] event.
 #[derive(Debug)]
 pub(crate) enum GatewayEvent {
-    /// Port was successfully mapped.
-    Mapped(Mapping),
+    /// Port was successfully mapped to the external address for the duration in seconds.
+    Mapped {
+        mapping: Mapping,
+        external_addr: SocketAddr,
+        duration: u32,
+    },
     /// There was a failure mapping port.
     MapFailure(Mapping, Box<dyn Error + Send + Sync + 'static>),
     /// Port was successfully removed.
     Removed(Mapping),
     /// There was a failure removing the mapped port.
     RemovalFailure(Mapping, Box<dyn Error + Send + Sync + 'static>),
+    /// The gateway restarted, losing the port mappings.
+    Restarted,
 }
 
 /// Mapping of a Protocol and Port on the gateway.
@@ -78,15 +83,21 @@ pub(crate) struct Mapping {
 }
 
 impl Mapping {
-    /// Given the input gateway address, calculate the
+    /// Given the address mapped on the gateway, calculate the
     /// open external `Multiaddr`.
-    fn external_addr(&self, gateway_addr: IpAddr) -> Multiaddr {
-        let addr = match gateway_addr {
+    fn external_addr(&self, external_addr: SocketAddr) -> Multiaddr {
+        let addr = match external_addr.ip() {
             net::IpAddr::V4(ip) => multiaddr::Protocol::Ip4(ip),
             net::IpAddr::V6(ip) => multiaddr::Protocol::Ip6(ip),
         };
+        // NAT-PMP and PCP gateways may map a different external port.
+        let port = match self.protocol {
+            PortMappingProtocol::TCP => multiaddr::Protocol::Tcp(external_addr.port()),
+            PortMappingProtocol::UDP => multiaddr::Protocol::Udp(external_addr.port()),
+        };
         self.multiaddr
             .replace(0, |_| Some(addr))
+            .and_then(|multiaddr| multiaddr.replace(1, |_| Some(port)))
             .expect("multiaddr should be valid")
     }
 }
@@ -118,12 +129,26 @@ enum MappingState {
     Inactive,
     /// Port mapping/removal has been requested on the gateway.
     Pending,
-    /// Port mapping is active with the inner timeout.
-    Active(Delay),
+    /// Port mapping is active on the external address until the renewal timeout.
+    Active {
+        timeout: Delay,
+        external_addr: Multiaddr,
+    },
     /// Port mapping failed, we will try again.
     Failed,
 }
 
+/// The port mapping protocol spoken by the gateway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GatewayProtocol {
+    /// UPnP Internet Gateway Device protocol.
+    Igd,
+    /// Port Control Protocol, see [RFC 6887](https://www.rfc-editor.org/rfc/rfc6887).
+    Pcp,
+    /// NAT Port Mapping Protocol, see [RFC 6886](https://www.rfc-editor.org/rfc/rfc6886).
+    NatPmp,
+}
+
 /// Current state of the UPnP [`Gateway`].
 enum GatewayState {
     Searching(oneshot::Receiver<Result<Gateway, Box<dyn std::error::Error + Send + Sync>>>),
@@ -139,10 +164,25 @@ pub enum Event {
     NewExternalAddr(Multiaddr),
     /// The renewal of the multiaddress on the gateway failed.
     ExpiredExternalAddr(Multiaddr),
-    /// The IGD gateway was not found.
-    GatewayNotFound,
-    /// The Gateway is not exposed directly to the public network.
-    NonRoutableGateway,
+    /// A gateway was found on the network of the local interface.
+    GatewayFound {
+        /// The address of the local interface.
+        interface: IpAddr,
+        /// The address of the gateway on the local network.
+        gateway: IpAddr,
+        /// The port mapping protocol spoken by the gateway.
+        protocol: GatewayProtocol,
+    },
+    /// The gateway on the network of the local interface restarted.
+    ///
+    /// Its port mappings are requested again.
+    GatewayRestarted { interface: IpAddr },
+    /// No gateway speaking UPnP IGD, PCP or NAT-PMP was found on the network of the local
+    /// interface.
+    GatewayNotFound { interface: IpAddr },
+    /// The gateway on the network of the local interface is not exposed directly to the public
+    /// network.
+    NonRoutableGateway { interface: IpAddr },
 }
 
 /// A list of port mappings and its state.
@@ -183,7 +223,7 @@ impl MappingList {
                     }
                     *state = MappingState::Pending;
                 }
-                MappingState::Active(timeout) => {
+                MappingState::Active { timeout, .. } => {
                     if Pin::new(timeout).poll(cx).is_ready() {
                         let duration = MAPPING_DURATION;
                         if let Err(err) = gateway.sender.try_send(GatewayRequest::AddMapping {
@@ -204,24 +244,334 @@ impl MappingList {
     }
 }
 
-/// A [`NetworkBehaviour`] for UPnP port mapping. Automatically tries to map the external port
-/// to an internal address on the gateway on a [`FromSwarm::NewListenAddr`].
-pub struct Behaviour {
+/// A local network interface and the state of the gateway of its network.
+struct Interface {
     /// UPnP interface state.
     state: GatewayState,
 
-    /// List of port mappings.
+    /// List of port mappings on the gateway.
     mappings: MappingList,
+}
+
+impl Interface {
+    fn new(net: Ipv4Net) -> Self {
+        Self {
+            state: GatewayState::Searching(crate::tokio::search_gateway(net)),
+            mappings: Default::default(),
+        }
+    }
+
+    /// Maps the port of a listen address of this interface on the gateway.
+    fn add_mapping(&mut self, mapping: Mapping) {
+        if let Some((existing, _state)) = self.mappings.iter().find(|(existing, _state)| {
+            existing.internal_addr.port() == mapping.internal_addr.port()
+        }) {
+            tracing::debug!(
+                multiaddress=%mapping.multiaddr,
+                mapped_multiaddress=%existing.multiaddr,
+                "port from multiaddress is already being mapped"
+            );
+            return;
+        }
+
+        match &mut self.state {
+            GatewayState::Searching(_) => {
+                // As the gateway is not yet available we add the mapping with `MappingState::Inactive`
+                // so that when and if it becomes available we map it.
+                self.mappings.insert(mapping, MappingState::Inactive);
+            }
+            GatewayState::Available(ref mut gateway) => {
+                let duration = MAPPING_DURATION;
+                if let Err(err) = gateway.sender.try_send(GatewayRequest::AddMapping {
+                    mapping: mapping.clone(),
+                    duration,
+                }) {
+                    tracing::debug!(
+                        multiaddress=%mapping.multiaddr,
+                        "could not request port mapping for multiaddress on the gateway: {}",
+                        err
+                    );
+                }
+
+                self.mappings.insert(mapping, MappingState::Pending);
+            }
+            GatewayState::GatewayNotFound => {
+                tracing::debug!(
+                    multiaddres=%mapping.multiaddr,
+                    "network gateway not found, UPnP port mapping of multiaddres discarded"
+                );
+            }
+            GatewayState::NonRoutableGateway(addr) => {
+                tracing::debug!(
+                    multiaddress=%mapping.multiaddr,
+                    network_gateway_ip=%addr,
+                    "the network gateway is not exposed to the public network. /
+                     UPnP port mapping of multiaddress discarded"
+                );
+            }
+        };
+    }
+
+    /// Removes the mapping of an expired listen address from the gateway.
+    fn remove_mapping(&mut self, listener_id: ListenerId) {
+        let Some((mapping, _state)) = self.mappings.remove_entry(&listener_id) else {
+            return;
+        };
+        if let GatewayState::Available(ref mut gateway) = &mut self.state {
+            if let Err(err) = gateway
+                .sender
+                .try_send(GatewayRequest::RemoveMapping(mapping.clone()))
+            {
+                tracing::debug!(
+                    multiaddress=%mapping.multiaddr,
+                    "could not request port removal for multiaddress on the gateway: {}",
+                    err
+                );
+            }
+            self.mappings.insert(mapping, MappingState::Pending);
+        }
+    }
+
+    fn poll(
+        &mut self,
+        interface: IpAddr,
+        pending_events: &mut VecDeque<ToSwarm<Event, Void>>,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Event, Void>> {
+        match self.state {
+            GatewayState::Searching(ref mut fut) => match Pin::new(fut).poll(cx) {
+                Poll::Ready(result) => match result.expect("sender shouldn't have been dropped") {
+                    Ok(gateway) => {
+                        if let Some(addr) = gateway.external_addr {
+                            if !is_addr_global(addr) {
+                                self.state = GatewayState::NonRoutableGateway(addr);
+                                tracing::debug!(
+                                    gateway_address=%addr,
+                                    "the gateway is not routable"
+                                );
+                                return Poll::Ready(ToSwarm::GenerateEvent(
+                                    Event::NonRoutableGateway { interface },
+                                ));
+                            }
+                        }
+                        let event = Event::GatewayFound {
+                            interface,
+                            gateway: gateway.addr,
+                            protocol: gateway.protocol,
+                        };
+                        tracing::debug!(%interface, gateway=%gateway.addr, protocol=?gateway.protocol, "found gateway");
+                        self.state = GatewayState::Available(gateway);
+                        Poll::Ready(ToSwarm::GenerateEvent(event))
+                    }
+                    Err(err) => {
+                        tracing::debug!(%interface, "could not find gateway: {err}");
+                        self.state = GatewayState::GatewayNotFound;
+                        Poll::Ready(ToSwarm::GenerateEvent(Event::GatewayNotFound { interface }))
+                    }
+                },
+                Poll::Pending => Poll::Pending,
+            },
+            GatewayState::Available(ref mut gateway) => {
+                // Poll pending mapping requests.
+                while let Poll::Ready(Some(result)) = gateway.receiver.poll_next_unpin(cx) {
+                    match result {
+                        GatewayEvent::Mapped {
+                            mapping,
+                            external_addr,
+                            duration,
+                        } => {
+                            // PCP gateways only report their external address on a mapping.
+                            if !is_addr_global(external_addr.ip()) {
+                                self.mappings
+                                    .insert(mapping, MappingState::Failed)
+                                    .expect("mapping should exist");
+                                self.state = GatewayState::NonRoutableGateway(external_addr.ip());
+                                tracing::debug!(
+                                    gateway_address=%external_addr.ip(),
+                                    "the gateway is not routable"
+                                );
+                                return Poll::Ready(ToSwarm::GenerateEvent(
+                                    Event::NonRoutableGateway { interface },
+                                ));
+                            }
+
+                            let external_multiaddr = mapping.external_addr(external_addr);
+                            // Renew the mapping every half of its duration to avoid the port
+                            // being unmapped.
+                            let new_state = MappingState::Active {
+                                timeout: Delay::new(Duration::from_secs(duration as u64 / 2)),
+                                external_addr: external_multiaddr.clone(),
+                            };
+
+                            match self
+                                .mappings
+                                .insert(mapping.clone(), new_state)
+                                .expect("mapping should exist")
+                            {
+                                MappingState::Pending => {
+                                    pending_events.push_back(ToSwarm::GenerateEvent(
+                                        Event::NewExternalAddr(external_multiaddr.clone()),
+                                    ));
+                                    tracing::debug!(
+                                        address=%mapping.internal_addr,
+                                        protocol=%mapping.protocol,
+                                        "successfully mapped UPnP for protocol"
+                                    );
+                                    return Poll::Ready(ToSwarm::ExternalAddrConfirmed(
+                                        external_multiaddr,
+                                    ));
+                                }
+                                MappingState::Active {
+                                    external_addr: previous_multiaddr,
+                                    ..
+                                } if previous_multiaddr != external_multiaddr => {
+                                    tracing::debug!(
+                                        address=%mapping.internal_addr,
+                                        protocol=%mapping.protocol,
+                                        "renewed UPnP mapping for protocol on a different external address"
+                                    );
+                                    pending_events.extend([
+                                        ToSwarm::GenerateEvent(Event::ExpiredExternalAddr(
+                                            previous_multiaddr.clone(),
+                                        )),
+                                        ToSwarm::ExternalAddrConfirmed(external_multiaddr.clone()),
+                                        ToSwarm::GenerateEvent(Event::NewExternalAddr(
+                                            external_multiaddr,
+                                        )),
+                                    ]);
+                                    return Poll::Ready(ToSwarm::ExternalAddrExpired(
+                                        previous_multiaddr,
+                                    ));
+                                }
+                                MappingState::Active { .. } => {
+                                    tracing::debug!(
+                                        address=%mapping.internal_addr,
+                                        protocol=%mapping.protocol,
+                                        "successfully renewed UPnP mapping for protocol"
+                                    );
+                                }
+                                _ => unreachable!(),
+                            }
+                        }
+                        GatewayEvent::MapFailure(mapping, err) => {
+                            match self
+                                .mappings
+                                .insert(mapping.clone(), MappingState::Failed)
+                                .expect("mapping should exist")
+                            {
+                                MappingState::Active {
+                                    external_addr: external_multiaddr,
+                                    ..
+                                } => {
+                                    tracing::debug!(
+                                        address=%mapping.internal_addr,
+                                        protocol=%mapping.protocol,
+                                        "failed to remap UPnP mapped for protocol: {err}"
+                                    );
+                                    pending_events.push_back(ToSwarm::GenerateEvent(
+                                        Event::ExpiredExternalAddr(external_multiaddr.clone()),
+                                    ));
+                                    return Poll::Ready(ToSwarm::ExternalAddrExpired(
+                                        external_multiaddr,
+                                    ));
+                                }
+                                MappingState::Pending => {
+                                    tracing::debug!(
+                                        address=%mapping.internal_addr,
+                                        protocol=%mapping.protocol,
+                                        "failed to map UPnP mapped for protocol: {err}"
+                                    );
+                                }
+                                _ => {
+                                    unreachable!()
+                                }
+                            }
+                        }
+                        GatewayEvent::Removed(mapping) => {
+                            tracing::debug!(
+                                address=%mapping.internal_addr,
+                                protocol=%mapping.protocol,
+                                "successfully removed UPnP mapping for protocol"
+                            );
+                            self.mappings
+                                .remove(&mapping)
+                                .expect("mapping should exist");
+                        }
+                        GatewayEvent::RemovalFailure(mapping, err) => {
+                            tracing::debug!(
+                                address=%mapping.internal_addr,
+                                protocol=%mapping.protocol,
+                                "could not remove UPnP mapping for protocol: {err}"
+                            );
+                            if let Err(err) = gateway
+                                .sender
+                                .try_send(GatewayRequest::RemoveMapping(mapping.clone()))
+                            {
+                                tracing::debug!(
+                                    multiaddress=%mapping.multiaddr,
+                                    "could not request port removal for multiaddress on the gateway: {}",
+                                    err
+                                );
+                            }
+                        }
+                        GatewayEvent::Restarted => {
+                            tracing::debug!(gateway=%gateway.addr, "gateway restarted, renewing UPnP mappings");
+                            // The gateway lost the mappings, renew them right away.
+                            for state in self.mappings.values_mut() {
+                                if let MappingState::Active { timeout, .. } = state {
+                                    *timeout = Delay::new(Duration::ZERO);
+                                }
+                            }
+                            return Poll::Ready(ToSwarm::GenerateEvent(Event::GatewayRestarted {
+                                interface,
+                            }));
+                        }
+                    }
+                }
+
+                // Renew expired and request inactive mappings.
+                self.mappings.renew(gateway, cx);
+                Poll::Pending
+            }
+            _ => Poll::Pending,
+        }
+    }
+}
+
+/// A [`NetworkBehaviour`] for UPnP port mapping. Automatically tries to map the external port
+/// to an internal address on the gateway on a [`FromSwarm::NewListenAddr`].
+///
+/// A gateway is searched on the network of every local interface with a private IPv4 address,
+/// and the listen addresses of an interface are mapped on the gateway of its network.
+pub struct Behaviour {
+    /// Watcher of the local network interfaces.
+    if_watch: Option<IfWatcher>,
+
+    /// The local interfaces by their address.
+    interfaces: HashMap<IpAddr, Interface>,
+
+    /// Listen addresses to be mapped on the gateway of their interface.
+    listen_addrs: Vec<Mapping>,
 
     /// Pending behaviour events to be emitted.
-    pending_events: VecDeque<Event>,
+    pending_events: VecDeque<ToSwarm<Event, Void>>,
 }
 
 impl Default for Behaviour {
     fn default() -> Self {
+        let if_watch = match IfWatcher::new() {
+            Ok(if_watch) => Some(if_watch),
+            Err(err) => {
+                tracing::error!(
+                    "failed to watch network interfaces, no gateway will be searched: {err}"
+                );
+                None
+            }
+        };
         Self {
-            state: GatewayState::Searching(crate::tokio::search_gateway()),
-            mappings: Default::default(),
+            if_watch,
+            interfaces: HashMap::new(),
+            listen_addrs: Vec::new(),
             pending_events: VecDeque::new(),
         }
     }
@@ -266,88 +616,29 @@ impl NetworkBehaviour for Behaviour {
                     }
                 };
 
-                if let Some((mapping, _state)) = self
-                    .mappings
-                    .iter()
-                    .find(|(mapping, _state)| mapping.internal_addr.port() == addr.port())
-                {
-                    tracing::debug!(
-                        multiaddress=%multiaddr,
-                        mapped_multiaddress=%mapping.multiaddr,
-                        "port from multiaddress is already being mapped"
-                    );
-                    return;
-                }
-
-                match &mut self.state {
-                    GatewayState::Searching(_) => {
-                        // As the gateway is not yet available we add the mapping with `MappingState::Inactive`
-                        // so that when and if it becomes available we map it.
-                        self.mappings.insert(
-                            Mapping {
-                                listener_id,
-                                protocol,
-                                internal_addr: addr,
-                                multiaddr: multiaddr.clone(),
-                            },
-                            MappingState::Inactive,
-                        );
-                    }
-                    GatewayState::Available(ref mut gateway) => {
-                        let mapping = Mapping {
-                            listener_id,
-                            protocol,
-                            internal_addr: addr,
-                            multiaddr: multiaddr.clone(),
-                        };
-
-                        let duration = MAPPING_DURATION;
-                        if let Err(err) = gateway.sender.try_send(GatewayRequest::AddMapping {
-                            mapping: mapping.clone(),
-                            duration,
-                        }) {
-                            tracing::debug!(
-                                multiaddress=%mapping.multiaddr,
-                                "could not request port mapping for multiaddress on the gateway: {}",
-                                err
-                            );
-                        }
-
-                        self.mappings.insert(mapping, MappingState::Pending);
-                    }
-                    GatewayState::GatewayNotFound => {
-                        tracing::debug!(
-                            multiaddres=%multiaddr,
-                            "network gateway not found, UPnP port mapping of multiaddres discarded"
-                        );
-                    }
-                    GatewayState::NonRoutableGateway(addr) => {
-                        tracing::debug!(
-                            multiaddress=%multiaddr,
-                            network_gateway_ip=%addr,
-                            "the network gateway is not exposed to the public network. /
-                             UPnP port mapping of multiaddress discarded"
-                        );
-                    }
+                let mapping = Mapping {
+                    listener_id,
+                    protocol,
+                    internal_addr: addr,
+                    multiaddr: multiaddr.clone(),
                 };
+                // The interface may not have been reported yet, in which case the address is
+                // mapped once it is.
+                if let Some(interface) = self.interfaces.get_mut(&addr.ip()) {
+                    interface.add_mapping(mapping.clone());
+                }
+                self.listen_addrs.push(mapping);
             }
             FromSwarm::ExpiredListenAddr(ExpiredListenAddr {
                 listener_id,
-                addr: _addr,
+                addr: multiaddr,
             }) => {
-                if let GatewayState::Available(ref mut gateway) = &mut self.state {
-                    if let Some((mapping, _state)) = self.mappings.remove_entry(&listener_id) {
-                        if let Err(err) = gateway
-                            .sender
-                            .try_send(GatewayRequest::RemoveMapping(mapping.clone()))
-                        {
-                            tracing::debug!(
-                                multiaddress=%mapping.multiaddr,
-                                "could not request port removal for multiaddress on the gateway: {}",
-                                err
-                            );
-                        }
-                        self.mappings.insert(mapping, MappingState::Pending);
+                self.listen_addrs.retain(|mapping| {
+                    mapping.listener_id != listener_id || mapping.multiaddr != *multiaddr
+                });
+                if let Ok((addr, _protocol)) = multiaddr_to_socketaddr_protocol(multiaddr.clone()) {
+                    if let Some(interface) = self.interfaces.get_mut(&addr.ip()) {
+                        interface.remove_mapping(listener_id);
                     }
                 }
             }
@@ -371,148 +662,58 @@ impl NetworkBehaviour for Behaviour {
     ) -> Poll<ToSwarm<Self::ToSwarm, libp2p_swarm::THandlerInEvent<Self>>> {
         // If there are pending addresses to be emitted we emit them.
         if let Some(event) = self.pending_events.pop_front() {
-            return Poll::Ready(ToSwarm::GenerateEvent(event));
+            return Poll::Ready(event);
         }
 
-        // Loop through the gateway state so that if it changes from `Searching` to `Available`
-        // we poll the pending mapping requests.
-        loop {
-            match self.state {
-                GatewayState::Searching(ref mut fut) => match Pin::new(fut).poll(cx) {
-                    Poll::Ready(result) => {
-                        match result.expect("sender shouldn't have been dropped") {
-                            Ok(gateway) => {
-                                if !is_addr_global(gateway.external_addr) {
-                                    self.state =
-                                        GatewayState::NonRoutableGateway(gateway.external_addr);
-                                    tracing::debug!(
-                                        gateway_address=%gateway.external_addr,
-                                        "the gateway is not routable"
-                                    );
-                                    return Poll::Ready(ToSwarm::GenerateEvent(
-                                        Event::NonRoutableGateway,
-                                    ));
-                                }
-                                self.state = GatewayState::Available(gateway);
-                            }
-                            Err(err) => {
-                                tracing::debug!("could not find gateway: {err}");
-                                self.state = GatewayState::GatewayNotFound;
-                                return Poll::Ready(ToSwarm::GenerateEvent(Event::GatewayNotFound));
-                            }
+        // Poll ifwatch.
+        while let Some(Poll::Ready(Some(event))) = self
+            .if_watch
+            .as_mut()
+            .map(|if_watch| if_watch.poll_next_unpin(cx))
+        {
+            match event {
+                // Idg only supports Ipv4.
+                Ok(IfEvent::Up(IpNet::V4(net))) if net.addr().is_private() => {
+                    let addr = IpAddr::V4(net.addr());
+                    let Entry::Vacant(entry) = self.interfaces.entry(addr) else {
+                        continue;
+                    };
+                    let interface = entry.insert(Interface::new(net));
+                    for mapping in &self.listen_addrs {
+                        if mapping.internal_addr.ip() == addr {
+                            interface.add_mapping(mapping.clone());
                         }
                     }
-                    Poll::Pending => return Poll::Pending,
-                },
-                GatewayState::Available(ref mut gateway) => {
-                    // Poll pending mapping requests.
-                    if let Poll::Ready(Some(result)) = gateway.receiver.poll_next_unpin(cx) {
-                        match result {
-                            GatewayEvent::Mapped(mapping) => {
-                                let new_state = MappingState::Active(Delay::new(
-                                    Duration::from_secs(MAPPING_TIMEOUT),
-                                ));
-
-                                match self
-                                    .mappings
-                                    .insert(mapping.clone(), new_state)
-                                    .expect("mapping should exist")
-                                {
-                                    MappingState::Pending => {
-                                        let external_multiaddr =
-                                            mapping.external_addr(gateway.external_addr);
-                                        self.pending_events.push_back(Event::NewExternalAddr(
-                                            external_multiaddr.clone(),
-                                        ));
-                                        tracing::debug!(
-                                            address=%mapping.internal_addr,
-                                            protocol=%mapping.protocol,
-                                            "successfully mapped UPnP for protocol"
-                                        );
-                                        return Poll::Ready(ToSwarm::ExternalAddrConfirmed(
-                                            external_multiaddr,
-                                        ));
-                                    }
-                                    MappingState::Active(_) => {
-                                        tracing::debug!(
-                                            address=%mapping.internal_addr,
-                                            protocol=%mapping.protocol,
-                                            "successfully renewed UPnP mapping for protocol"
-                                        );
-                                    }
-                                    _ => unreachable!(),
-                                }
-                            }
-                            GatewayEvent::MapFailure(mapping, err) => {
-                                match self
-                                    .mappings
-                                    .insert(mapping.clone(), MappingState::Failed)
-                                    .expect("mapping should exist")
-                                {
-                                    MappingState::Active(_) => {
-                                        tracing::debug!(
-                                            address=%mapping.internal_addr,
-                                            protocol=%mapping.protocol,
-                                            "failed to remap UPnP mapped for protocol: {err}"
-                                        );
-                                        let external_multiaddr =
-                                            mapping.external_addr(gateway.external_addr);
-                                        self.pending_events.push_back(Event::ExpiredExternalAddr(
-                                            external_multiaddr.clone(),
-                                        ));
-                                        return Poll::Ready(ToSwarm::ExternalAddrExpired(
-                                            external_multiaddr,
-                                        ));
-                                    }
-                                    MappingState::Pending => {
-                                        tracing::debug!(
-                                            address=%mapping.internal_addr,
-                                            protocol=%mapping.protocol,
-                                            "failed to map UPnP mapped for protocol: {err}"
-                                        );
-                                    }
-                                    _ => {
-                                        unreachable!()
-                                    }
-                                }
-                            }
-                            GatewayEvent::Removed(mapping) => {
-                                tracing::debug!(
-                                    address=%mapping.internal_addr,
-                                    protocol=%mapping.protocol,
-                                    "successfully removed UPnP mapping for protocol"
-                                );
-                                self.mappings
-                                    .remove(&mapping)
-                                    .expect("mapping should exist");
-                            }
-                            GatewayEvent::RemovalFailure(mapping, err) => {
-                                tracing::debug!(
-                                    address=%mapping.internal_addr,
-                                    protocol=%mapping.protocol,
-                                    "could not remove UPnP mapping for protocol: {err}"
-                                );
-                                if let Err(err) = gateway
-                                    .sender
-                                    .try_send(GatewayRequest::RemoveMapping(mapping.clone()))
-                                {
-                                    tracing::debug!(
-                                        multiaddress=%mapping.multiaddr,
-                                        "could not request port removal for multiaddress on the gateway: {}",
-                                        err
-                                    );
-                                }
-                            }
+                }
+                Ok(IfEvent::Up(_)) => {}
+                Ok(IfEvent::Down(net)) => {
+                    let Some(interface) = self.interfaces.remove(&net.addr()) else {
+                        continue;
+                    };
+                    tracing::debug!(interface=%net.addr(), "dropping gateway of interface");
+                    for state in interface.mappings.0.into_values() {
+                        if let MappingState::Active { external_addr, .. } = state {
+                            self.pending_events.extend([
+                                ToSwarm::ExternalAddrExpired(external_addr.clone()),
+                                ToSwarm::GenerateEvent(Event::ExpiredExternalAddr(external_addr)),
+                            ]);
                         }
                     }
-
-                    // Renew expired and request inactive mappings.
-                    self.mappings.renew(gateway, cx);
-                    return Poll::Pending;
                 }
-                _ => return Poll::Pending,
+                Err(err) => tracing::error!("if watch returned an error: {}", err),
             }
         }
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        for (addr, interface) in self.interfaces.iter_mut() {
+            if let Poll::Ready(event) = interface.poll(*addr, &mut self.pending_events, cx) {
+                return Poll::Ready(event);
+            }
+        }
+
+        Poll::Pending
     }
 }
 
@@ -25,13 +25,22 @@
 //! This struct will automatically try to map the ports externally to internal
 //! addresses on the gateway.
 //!
+//! Gateways speaking UPnP IGD are preferred. If none is found, the default gateway is asked to map
+//! ports via PCP or, failing that, NAT-PMP.
+//!
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 #[cfg(feature = "tokio")]
 mod behaviour;
 #[cfg(feature = "tokio")]
+mod nat_pmp;
+#[cfg(feature = "tokio")]
+mod pcp;
+#[cfg(feature = "tokio")]
+mod ssdp;
+#[cfg(feature = "tokio")]
 pub mod tokio;
 
 #[cfg(feature = "tokio")]
-pub use behaviour::Event;
+pub use behaviour::{Event, GatewayProtocol};
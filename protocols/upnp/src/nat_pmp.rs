@@ -0,0 +1,195 @@
+// Copyright 2023 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Messages of the NAT Port Mapping Protocol, see [RFC 6886](https://www.rfc-editor.org/rfc/rfc6886).
+
+use std::{fmt, net::Ipv4Addr};
+
+use igd_next::PortMappingProtocol;
+
+/// The NAT-PMP version.
+const VERSION: u8 = 0;
+
+const OPCODE_EXTERNAL_ADDRESS: u8 = 0;
+const OPCODE_MAP_UDP: u8 = 1;
+const OPCODE_MAP_TCP: u8 = 2;
+
+/// The result code of an unsupported version.
+const RESULT_UNSUPPORTED_VERSION: u16 = 1;
+
+/// A successful response to a mapping request.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct MapResponse {
+    /// The port mapped on the gateway.
+    pub(crate) external_port: u16,
+    /// The lifetime of the mapping in seconds, which may differ from the requested one.
+    pub(crate) lifetime: u32,
+    /// The seconds since the gateway started or reset its mappings.
+    pub(crate) epoch: u32,
+}
+
+/// An unsuccessful NAT-PMP response.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Error {
+    /// The response is too short or has an unexpected opcode.
+    Malformed,
+    /// The gateway doesn't speak this version of NAT-PMP.
+    UnsupportedVersion,
+    /// The gateway refused the request with the given result code.
+    Failure(u16),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Malformed => write!(f, "malformed NAT-PMP response"),
+            Error::UnsupportedVersion => write!(f, "NAT-PMP version not supported by the gateway"),
+            Error::Failure(code) => write!(f, "NAT-PMP request failed with result code {code}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Request for the external address of the gateway.
+pub(crate) fn external_address_request() -> [u8; 2] {
+    [VERSION, OPCODE_EXTERNAL_ADDRESS]
+}
+
+/// Request for mapping `internal_port` for `lifetime` seconds, preferably to `external_port`.
+///
+/// A `lifetime` of zero removes the mapping.
+pub(crate) fn map_request(
+    protocol: PortMappingProtocol,
+    internal_port: u16,
+    external_port: u16,
+    lifetime: u32,
+) -> [u8; 12] {
+    let mut request = [0; 12];
+    request[0] = VERSION;
+    request[1] = map_opcode(protocol);
+    request[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    request[6..8].copy_from_slice(&external_port.to_be_bytes());
+    request[8..12].copy_from_slice(&lifetime.to_be_bytes());
+    request
+}
+
+/// Decodes the response to an [`external_address_request`].
+pub(crate) fn decode_external_address_response(response: &[u8]) -> Result<Ipv4Addr, Error> {
+    check_header(response, OPCODE_EXTERNAL_ADDRESS)?;
+    let addr: [u8; 4] = response
+        .get(8..12)
+        .ok_or(Error::Malformed)?
+        .try_into()
+        .expect("slice has length 4");
+    Ok(Ipv4Addr::from(addr))
+}
+
+/// Decodes the response to a [`map_request`] for `protocol`.
+pub(crate) fn decode_map_response(
+    response: &[u8],
+    protocol: PortMappingProtocol,
+) -> Result<MapResponse, Error> {
+    check_header(response, map_opcode(protocol))?;
+    if response.len() < 16 {
+        return Err(Error::Malformed);
+    }
+    Ok(MapResponse {
+        external_port: u16::from_be_bytes([response[10], response[11]]),
+        epoch: u32::from_be_bytes([response[4], response[5], response[6], response[7]]),
+        lifetime: u32::from_be_bytes([response[12], response[13], response[14], response[15]]),
+    })
+}
+
+fn map_opcode(protocol: PortMappingProtocol) -> u8 {
+    match protocol {
+        PortMappingProtocol::UDP => OPCODE_MAP_UDP,
+        PortMappingProtocol::TCP => OPCODE_MAP_TCP,
+    }
+}
+
+/// Checks the version, opcode and result code of a response.
+fn check_header(response: &[u8], opcode: u8) -> Result<(), Error> {
+    if response.len() < 4 || response[1] != opcode | 0x80 {
+        return Err(Error::Malformed);
+    }
+    let result = u16::from_be_bytes([response[2], response[3]]);
+    if response[0] != VERSION || result == RESULT_UNSUPPORTED_VERSION {
+        return Err(Error::UnsupportedVersion);
+    }
+    if result != 0 {
+        return Err(Error::Failure(result));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_map_request() {
+        assert_eq!(
+            map_request(PortMappingProtocol::TCP, 4001, 4002, 3600),
+            [0, 2, 0, 0, 0x0f, 0xa1, 0x0f, 0xa2, 0, 0, 0x0e, 0x10]
+        );
+    }
+
+    #[test]
+    fn decodes_responses() {
+        let response = [0, 128, 0, 0, 0, 0, 0, 42, 203, 0, 113, 7];
+        assert_eq!(
+            decode_external_address_response(&response),
+            Ok(Ipv4Addr::new(203, 0, 113, 7))
+        );
+
+        let response = [
+            0, 129, 0, 0, 0, 0, 0, 42, 0x0f, 0xa1, 0x0f, 0xa2, 0, 0, 0, 120,
+        ];
+        assert_eq!(
+            decode_map_response(&response, PortMappingProtocol::UDP),
+            Ok(MapResponse {
+                external_port: 4002,
+                lifetime: 120,
+                epoch: 42,
+            })
+        );
+        assert_eq!(
+            decode_map_response(&response, PortMappingProtocol::TCP),
+            Err(Error::Malformed)
+        );
+    }
+
+    #[test]
+    fn decodes_failures() {
+        assert_eq!(
+            decode_external_address_response(&[0, 128, 0, 2, 0, 0, 0, 42]),
+            Err(Error::Failure(2))
+        );
+        assert_eq!(
+            decode_external_address_response(&[0, 128, 0, 1, 0, 0, 0, 42]),
+            Err(Error::UnsupportedVersion)
+        );
+        assert_eq!(
+            decode_external_address_response(&[0, 128, 0]),
+            Err(Error::Malformed)
+        );
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright 2023 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Messages of the Port Control Protocol, see [RFC 6887](https://www.rfc-editor.org/rfc/rfc6887).
+
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use igd_next::PortMappingProtocol;
+
+/// The PCP version.
+const VERSION: u8 = 2;
+
+const OPCODE_ANNOUNCE: u8 = 0;
+const OPCODE_MAP: u8 = 1;
+
+/// The result code of an unsupported version.
+const RESULT_UNSUPPORTED_VERSION: u8 = 1;
+
+/// Length of the common request and response header.
+const HEADER_LEN: usize = 24;
+/// Length of a request or response to the MAP opcode.
+const MAP_LEN: usize = HEADER_LEN + 36;
+
+/// A successful response to a mapping request.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct MapResponse {
+    /// The address mapped on the gateway.
+    pub(crate) external_addr: SocketAddr,
+    /// The lifetime of the mapping in seconds, which may differ from the requested one.
+    pub(crate) lifetime: u32,
+    /// The seconds since the gateway started or reset its mappings.
+    pub(crate) epoch: u32,
+}
+
+/// An unsuccessful PCP response.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Error {
+    /// The response is too short, has an unexpected opcode or doesn't match the request's nonce.
+    Malformed,
+    /// The gateway doesn't speak this version of PCP, e.g. because it only speaks NAT-PMP.
+    UnsupportedVersion,
+    /// The gateway refused the request with the given result code.
+    Failure(u8),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Malformed => write!(f, "malformed PCP response"),
+            Error::UnsupportedVersion => write!(f, "PCP version not supported by the gateway"),
+            Error::Failure(code) => write!(f, "PCP request failed with result code {code}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Request announcing the client to the gateway, used to detect whether it speaks PCP.
+pub(crate) fn announce_request(client_addr: IpAddr) -> [u8; HEADER_LEN] {
+    let mut request = [0; HEADER_LEN];
+    encode_header(&mut request, OPCODE_ANNOUNCE, 0, client_addr);
+    request
+}
+
+/// Request for mapping `internal_port` for `lifetime` seconds, preferably to the same external
+/// port.
+///
+/// Subsequent requests for the same mapping must use the same `nonce`. A `lifetime` of zero
+/// removes the mapping.
+pub(crate) fn map_request(
+    nonce: [u8; 12],
+    protocol: PortMappingProtocol,
+    internal_port: u16,
+    lifetime: u32,
+    client_addr: IpAddr,
+) -> [u8; MAP_LEN] {
+    let mut request = [0; MAP_LEN];
+    encode_header(&mut request, OPCODE_MAP, lifetime, client_addr);
+    request[24..36].copy_from_slice(&nonce);
+    request[36] = protocol_number(protocol);
+    request[40..42].copy_from_slice(&internal_port.to_be_bytes());
+    request[42..44].copy_from_slice(&internal_port.to_be_bytes());
+    // Leave the suggested external address to the gateway.
+    request[44..60].copy_from_slice(&Ipv6Addr::UNSPECIFIED.octets());
+    request
+}
+
+/// Decodes the response to an [`announce_request`].
+pub(crate) fn decode_announce_response(response: &[u8]) -> Result<(), Error> {
+    check_header(response, OPCODE_ANNOUNCE)
+}
+
+/// Decodes the response to a [`map_request`] with the given `nonce`.
+pub(crate) fn decode_map_response(response: &[u8], nonce: [u8; 12]) -> Result<MapResponse, Error> {
+    check_header(response, OPCODE_MAP)?;
+    if response.len() < MAP_LEN || response[24..36] != nonce {
+        return Err(Error::Malformed);
+    }
+    let ip: [u8; 16] = response[44..60].try_into().expect("slice has length 16");
+    // IPv4 addresses are mapped into IPv6 (`::ffff:0:0/96`).
+    let ip = match ip {
+        [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, a, b, c, d] => {
+            IpAddr::V4(Ipv4Addr::new(a, b, c, d))
+        }
+        ip => IpAddr::V6(Ipv6Addr::from(ip)),
+    };
+    Ok(MapResponse {
+        external_addr: SocketAddr::new(ip, u16::from_be_bytes([response[42], response[43]])),
+        lifetime: u32::from_be_bytes([response[4], response[5], response[6], response[7]]),
+        epoch: u32::from_be_bytes([response[8], response[9], response[10], response[11]]),
+    })
+}
+
+fn encode_header(request: &mut [u8], opcode: u8, lifetime: u32, client_addr: IpAddr) {
+    let client_addr = match client_addr {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    };
+    request[0] = VERSION;
+    request[1] = opcode;
+    request[4..8].copy_from_slice(&lifetime.to_be_bytes());
+    request[8..24].copy_from_slice(&client_addr.octets());
+}
+
+/// Checks the version, opcode and result code of a response.
+///
+/// A gateway only speaking NAT-PMP answers with a NAT-PMP response, which is reported as
+/// [`Error::UnsupportedVersion`].
+fn check_header(response: &[u8], opcode: u8) -> Result<(), Error> {
+    if response.len() < 4 || response[1] != opcode | 0x80 {
+        return Err(Error::Malformed);
+    }
+    if response[0] != VERSION {
+        return Err(Error::UnsupportedVersion);
+    }
+    if response.len() < HEADER_LEN {
+        return Err(Error::Malformed);
+    }
+    match response[3] {
+        0 => Ok(()),
+        RESULT_UNSUPPORTED_VERSION => Err(Error::UnsupportedVersion),
+        code => Err(Error::Failure(code)),
+    }
+}
+
+fn protocol_number(protocol: PortMappingProtocol) -> u8 {
+    match protocol {
+        PortMappingProtocol::TCP => 6,
+        PortMappingProtocol::UDP => 17,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONCE: [u8; 12] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+
+    #[test]
+    fn encodes_map_request() {
+        let request = map_request(
+            NONCE,
+            PortMappingProtocol::UDP,
+            4001,
+            3600,
+            IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)),
+        );
+
+        assert_eq!(request[..8], [2, 1, 0, 0, 0, 0, 0x0e, 0x10]);
+        assert_eq!(
+            request[8..24],
+            Ipv4Addr::new(192, 168, 1, 10).to_ipv6_mapped().octets()
+        );
+        assert_eq!(request[24..36], NONCE);
+        assert_eq!(request[36], 17);
+        assert_eq!(request[40..44], [0x0f, 0xa1, 0x0f, 0xa1]);
+    }
+
+    #[test]
+    fn decodes_map_response() {
+        let mut response = [0; MAP_LEN];
+        response[0] = VERSION;
+        response[1] = 0x81;
+        response[4..8].copy_from_slice(&120u32.to_be_bytes());
+        response[8..12].copy_from_slice(&42u32.to_be_bytes());
+        response[24..36].copy_from_slice(&NONCE);
+        response[42..44].copy_from_slice(&4002u16.to_be_bytes());
+        response[44..60].copy_from_slice(&Ipv4Addr::new(203, 0, 113, 7).to_ipv6_mapped().octets());
+
+        assert_eq!(
+            decode_map_response(&response, NONCE),
+            Ok(MapResponse {
+                external_addr: "203.0.113.7:4002".parse().unwrap(),
+                lifetime: 120,
+                epoch: 42,
+            })
+        );
+        assert_eq!(
+            decode_map_response(&response, [0; 12]),
+            Err(Error::Malformed)
+        );
+
+        response[3] = 2;
+        assert_eq!(
+            decode_map_response(&response, NONCE),
+            Err(Error::Failure(2))
+        );
+    }
+
+    #[test]
+    fn nat_pmp_response_is_unsupported_version() {
+        // A NAT-PMP gateway answers with its own version and an "unsupported version" result.
+        let response = [0, 0x80, 0, 1, 0, 0, 0, 42];
+
+        assert_eq!(
+            decode_announce_response(&response),
+            Err(Error::UnsupportedVersion)
+        );
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright 2023 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Announcements of UPnP devices, multicast via SSDP.
+
+use std::net::Ipv4Addr;
+
+/// The multicast address SSDP announcements are sent to.
+pub(crate) const MULTICAST_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+
+/// The port SSDP announcements are sent to.
+pub(crate) const PORT: u16 = 1900;
+
+/// A `NOTIFY` announcement of a device.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Announcement {
+    /// Whether the device announced being available (`ssdp:alive`) or leaving (`ssdp:byebye`).
+    pub(crate) alive: bool,
+    /// The boot ID of the device, increased by UPnP 1.1 devices on every restart.
+    pub(crate) boot_id: Option<u32>,
+}
+
+/// Parses a `NOTIFY` announcement, ignoring any other SSDP message.
+pub(crate) fn parse_notify(datagram: &[u8]) -> Option<Announcement> {
+    let datagram = std::str::from_utf8(datagram).ok()?;
+    let mut lines = datagram.lines();
+    if !lines.next()?.starts_with("NOTIFY ") {
+        return None;
+    }
+
+    let mut alive = None;
+    let mut boot_id = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, value) = (name.trim(), value.trim());
+        if name.eq_ignore_ascii_case("NTS") {
+            alive = match value {
+                "ssdp:alive" => Some(true),
+                "ssdp:byebye" => Some(false),
+                _ => None,
+            };
+        } else if name.eq_ignore_ascii_case("BOOTID.UPNP.ORG") {
+            boot_id = value.parse().ok();
+        }
+    }
+
+    Some(Announcement {
+        alive: alive?,
+        boot_id,
+    })
+}
+
+/// Detects restarts of a device from its announcements.
+#[derive(Debug, Default)]
+pub(crate) struct RestartDetector {
+    /// The boot ID of the latest announcement.
+    boot_id: Option<u32>,
+    /// Whether the device announced leaving.
+    left: bool,
+}
+
+impl RestartDetector {
+    /// Returns whether the announcement reveals that the device restarted, i.e. it is alive
+    /// again after leaving or announces a different boot ID.
+    pub(crate) fn on_announcement(&mut self, announcement: &Announcement) -> bool {
+        if !announcement.alive {
+            self.left = true;
+            return false;
+        }
+
+        let restarted = std::mem::take(&mut self.left)
+            || matches!(
+                (self.boot_id, announcement.boot_id),
+                (Some(previous), Some(current)) if previous != current
+            );
+        if announcement.boot_id.is_some() {
+            self.boot_id = announcement.boot_id;
+        }
+        restarted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notify(nts: &str, boot_id: Option<u32>) -> Vec<u8> {
+        let mut datagram = format!(
+            "NOTIFY * HTTP/1.1\r\n\
+             HOST: 239.255.255.250:1900\r\n\
+             NT: upnp:rootdevice\r\n\
+             nts: {nts}\r\n"
+        );
+        if let Some(boot_id) = boot_id {
+            datagram.push_str(&format!("BOOTID.UPNP.ORG: {boot_id}\r\n"));
+        }
+        datagram.push_str("\r\n");
+        datagram.into_bytes()
+    }
+
+    #[test]
+    fn parses_notify() {
+        assert_eq!(
+            parse_notify(&notify("ssdp:alive", Some(7))),
+            Some(Announcement {
+                alive: true,
+                boot_id: Some(7)
+            })
+        );
+        assert_eq!(
+            parse_notify(&notify("ssdp:byebye", None)),
+            Some(Announcement {
+                alive: false,
+                boot_id: None
+            })
+        );
+        assert_eq!(parse_notify(&notify("ssdp:update", Some(7))), None);
+        assert_eq!(
+            parse_notify(b"M-SEARCH * HTTP/1.1\r\nNTS: ssdp:alive\r\n\r\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn detects_restarts() {
+        let alive = |boot_id| Announcement {
+            alive: true,
+            boot_id,
+        };
+        let byebye = Announcement {
+            alive: false,
+            boot_id: None,
+        };
+        let mut detector = RestartDetector::default();
+
+        assert!(!detector.on_announcement(&alive(Some(1))));
+        assert!(!detector.on_announcement(&alive(Some(1))));
+        assert!(detector.on_announcement(&alive(Some(2))));
+        assert!(!detector.on_announcement(&alive(None)));
+
+        assert!(!detector.on_announcement(&byebye));
+        assert!(detector.on_announcement(&alive(None)));
+        assert!(!detector.on_announcement(&alive(None)));
+    }
+}
@@ -18,17 +18,42 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use std::{error::Error, net::IpAddr};
+use std::{
+    collections::HashMap,
+    error::Error,
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    pin::pin,
+    time::{Duration, Instant},
+};
 
-use crate::behaviour::{GatewayEvent, GatewayRequest};
+use crate::{
+    behaviour::{GatewayEvent, GatewayProtocol, GatewayRequest, Mapping},
+    nat_pmp, pcp,
+    ssdp::{self, RestartDetector},
+};
 use futures::{
     channel::{mpsc, oneshot},
+    future::{self, Either},
     SinkExt, StreamExt,
 };
-use igd_next::SearchOptions;
+use if_watch::Ipv4Net;
+use igd_next::{aio::tokio::Tokio, SearchOptions};
+use libp2p_core::transport::ListenerId;
+use socket2::{Domain, Socket, Type};
+use tokio::net::UdpSocket;
 
 pub use crate::behaviour::Behaviour;
 
+/// The port of PCP and NAT-PMP servers.
+const PCP_SERVER_PORT: u16 = 5351;
+
+/// The timeout for the first response to a PCP or NAT-PMP request, doubled on every retransmission.
+const INITIAL_RETRANSMISSION_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// How often a PCP or NAT-PMP request is sent before giving up.
+const MAX_TRANSMISSIONS: u32 = 4;
+
 //TODO: remove when `IpAddr::is_global` stabilizes.
 pub(crate) fn is_addr_global(addr: IpAddr) -> bool {
     match addr {
@@ -87,30 +112,393 @@ pub(crate) fn is_addr_global(addr: IpAddr) -> bool {
 pub(crate) struct Gateway {
     pub(crate) sender: mpsc::Sender<GatewayRequest>,
     pub(crate) receiver: mpsc::Receiver<GatewayEvent>,
-    pub(crate) external_addr: IpAddr,
+    /// The address of the gateway on the local network.
+    pub(crate) addr: IpAddr,
+    /// The external address of the gateway, if known before mapping a port.
+    pub(crate) external_addr: Option<IpAddr>,
+    pub(crate) protocol: GatewayProtocol,
+}
+
+/// Client of a gateway speaking one of the supported port mapping protocols.
+enum Client {
+    Igd {
+        gateway: igd_next::aio::Gateway<Tokio>,
+        external_addr: IpAddr,
+    },
+    Pcp {
+        socket: UdpSocket,
+        client_addr: IpAddr,
+        /// The nonces identifying our mappings on the gateway.
+        nonces: HashMap<ListenerId, [u8; 12]>,
+        epoch: Option<Epoch>,
+    },
+    NatPmp {
+        socket: UdpSocket,
+        external_addr: Ipv4Addr,
+        epoch: Option<Epoch>,
+    },
+}
+
+/// A mapping granted by the gateway.
+struct Lease {
+    external_addr: SocketAddr,
+    /// The duration of the lease in seconds.
+    duration: u32,
+    /// Whether the gateway restarted since the previous mapping, losing all other mappings.
+    gateway_restarted: bool,
+}
+
+impl Client {
+    /// Searches for an IGD gateway on the network of `interface`, falling back to PCP and NAT-PMP
+    /// if the default gateway is on that network.
+    async fn search(interface: Ipv4Net) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let options = SearchOptions {
+            bind_addr: SocketAddr::new(IpAddr::V4(interface.addr()), 0),
+            ..Default::default()
+        };
+        let err = match igd_next::aio::tokio::search_gateway(options).await {
+            Ok(gateway) => {
+                let external_addr = gateway.get_external_ip().await?;
+                return Ok(Client::Igd {
+                    gateway,
+                    external_addr,
+                });
+            }
+            Err(err) => err,
+        };
+
+        let gateway_addr = default_gateway()?;
+        if !interface.contains(&gateway_addr) {
+            return Err(err.into());
+        }
+        tracing::debug!(
+            gateway=%gateway_addr,
+            "could not find IGD gateway, trying PCP and NAT-PMP: {err}"
+        );
+        let socket = UdpSocket::bind((interface.addr(), 0)).await?;
+        socket.connect((gateway_addr, PCP_SERVER_PORT)).await?;
+        let client_addr = socket.local_addr()?.ip();
+
+        let response = send_request(&socket, &pcp::announce_request(client_addr)).await?;
+        match pcp::decode_announce_response(&response) {
+            Ok(()) => {
+                return Ok(Client::Pcp {
+                    socket,
+                    client_addr,
+                    nonces: HashMap::new(),
+                    epoch: None,
+                })
+            }
+            Err(err) => {
+                tracing::debug!(gateway=%gateway_addr, "could not use PCP, trying NAT-PMP: {err}");
+            }
+        }
+
+        let response = send_request(&socket, &nat_pmp::external_address_request()).await?;
+        let external_addr = nat_pmp::decode_external_address_response(&response)?;
+        Ok(Client::NatPmp {
+            socket,
+            external_addr,
+            epoch: None,
+        })
+    }
+
+    fn protocol(&self) -> GatewayProtocol {
+        match self {
+            Client::Igd { .. } => GatewayProtocol::Igd,
+            Client::Pcp { .. } => GatewayProtocol::Pcp,
+            Client::NatPmp { .. } => GatewayProtocol::NatPmp,
+        }
+    }
+
+    /// The address of the gateway on the local network.
+    fn addr(&self) -> io::Result<IpAddr> {
+        match self {
+            Client::Igd { gateway, .. } => Ok(gateway.addr.ip()),
+            Client::Pcp { socket, .. } | Client::NatPmp { socket, .. } => {
+                Ok(socket.peer_addr()?.ip())
+            }
+        }
+    }
+
+    /// The external address of the gateway. PCP only reports it on a mapping.
+    fn external_addr(&self) -> Option<IpAddr> {
+        match self {
+            Client::Igd { external_addr, .. } => Some(*external_addr),
+            Client::Pcp { .. } => None,
+            Client::NatPmp { external_addr, .. } => Some(IpAddr::V4(*external_addr)),
+        }
+    }
+
+    /// Maps the port of `mapping` for `duration` seconds.
+    async fn add_mapping(
+        &mut self,
+        mapping: &Mapping,
+        duration: u32,
+    ) -> Result<Lease, Box<dyn Error + Send + Sync>> {
+        let port = mapping.internal_addr.port();
+        match self {
+            Client::Igd {
+                gateway,
+                external_addr,
+            } => {
+                gateway
+                    .add_port(
+                        mapping.protocol,
+                        port,
+                        mapping.internal_addr,
+                        duration,
+                        "rust-libp2p mapping",
+                    )
+                    .await?;
+                // Restarts of IGD gateways are detected from their announcements.
+                Ok(Lease {
+                    external_addr: SocketAddr::new(*external_addr, port),
+                    duration,
+                    gateway_restarted: false,
+                })
+            }
+            Client::Pcp {
+                socket,
+                client_addr,
+                nonces,
+                epoch,
+            } => {
+                let nonce = *nonces
+                    .entry(mapping.listener_id)
+                    .or_insert_with(rand::random);
+                let request =
+                    pcp::map_request(nonce, mapping.protocol, port, duration, *client_addr);
+                let response =
+                    pcp::decode_map_response(&send_request(socket, &request).await?, nonce)?;
+                Ok(Lease {
+                    external_addr: response.external_addr,
+                    duration: response.lifetime,
+                    gateway_restarted: Epoch::update(epoch, response.epoch, Instant::now()),
+                })
+            }
+            Client::NatPmp {
+                socket,
+                external_addr,
+                epoch,
+            } => {
+                let request = nat_pmp::map_request(mapping.protocol, port, port, duration);
+                let response = nat_pmp::decode_map_response(
+                    &send_request(socket, &request).await?,
+                    mapping.protocol,
+                )?;
+                Ok(Lease {
+                    external_addr: SocketAddr::new(
+                        IpAddr::V4(*external_addr),
+                        response.external_port,
+                    ),
+                    duration: response.lifetime,
+                    gateway_restarted: Epoch::update(epoch, response.epoch, Instant::now()),
+                })
+            }
+        }
+    }
+
+    async fn remove_mapping(
+        &mut self,
+        mapping: &Mapping,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let port = mapping.internal_addr.port();
+        match self {
+            Client::Igd { gateway, .. } => {
+                gateway.remove_port(mapping.protocol, port).await?;
+            }
+            Client::Pcp {
+                socket,
+                client_addr,
+                nonces,
+                ..
+            } => {
+                // Without a nonce the port was never mapped.
+                if let Some(nonce) = nonces.get(&mapping.listener_id).copied() {
+                    let request = pcp::map_request(nonce, mapping.protocol, port, 0, *client_addr);
+                    pcp::decode_map_response(&send_request(socket, &request).await?, nonce)?;
+                    nonces.remove(&mapping.listener_id);
+                }
+            }
+            Client::NatPmp { socket, .. } => {
+                let request = nat_pmp::map_request(mapping.protocol, port, 0, 0);
+                nat_pmp::decode_map_response(
+                    &send_request(socket, &request).await?,
+                    mapping.protocol,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The epoch reported by a PCP or NAT-PMP gateway, i.e. the seconds since it started.
+#[derive(Debug, Clone, Copy)]
+struct Epoch {
+    epoch: u32,
+    received: Instant,
+}
+
+impl Epoch {
+    /// Updates the previously received epoch, returning whether the gateway restarted in between.
+    ///
+    /// The gateway restarted if its epoch advanced noticeably less than our clock did.
+    fn update(previous: &mut Option<Epoch>, epoch: u32, now: Instant) -> bool {
+        let restarted = previous.map_or(false, |previous| {
+            let elapsed = now.duration_since(previous.received).as_secs();
+            // Allow for clock drift of 1/16 and rounding of 2 seconds.
+            u64::from(epoch) + 2 < u64::from(previous.epoch) + elapsed - elapsed / 16
+        });
+        *previous = Some(Epoch {
+            epoch,
+            received: now,
+        });
+        restarted
+    }
+}
+
+/// Announcements of an IGD gateway, used to detect its restarts.
+struct Announcements {
+    socket: UdpSocket,
+    gateway: IpAddr,
+    detector: RestartDetector,
+}
+
+impl Announcements {
+    /// Listens for SSDP announcements on the network of `interface`.
+    fn new(interface: Ipv4Addr, gateway: IpAddr) -> io::Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(socket2::Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        socket.bind(&SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), ssdp::PORT).into())?;
+        socket.join_multicast_v4(&ssdp::MULTICAST_ADDR, &interface)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket: UdpSocket::from_std(socket.into())?,
+            gateway,
+            detector: RestartDetector::default(),
+        })
+    }
+
+    /// Waits until the gateway announces that it restarted.
+    async fn restarted(&mut self) -> io::Result<()> {
+        let mut buf = [0; 2048];
+        loop {
+            let (len, from) = self.socket.recv_from(&mut buf).await?;
+            if from.ip() != self.gateway {
+                continue;
+            }
+            if let Some(announcement) = ssdp::parse_notify(&buf[..len]) {
+                if self.detector.on_announcement(&announcement) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Sends a PCP or NAT-PMP request to the gateway, retransmitting it until a response arrives.
+async fn send_request(socket: &UdpSocket, request: &[u8]) -> io::Result<Vec<u8>> {
+    // The maximum size of a PCP message.
+    let mut buf = [0; 1100];
+    let mut timeout = INITIAL_RETRANSMISSION_TIMEOUT;
+    for _ in 0..MAX_TRANSMISSIONS {
+        socket.send(request).await?;
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            match tokio::time::timeout_at(deadline, socket.recv(&mut buf)).await {
+                // Both protocols set the most significant bit of the request's opcode in
+                // responses, which tells apart late responses to previous requests.
+                Ok(Ok(len)) if len >= 2 && buf[1] == request[1] | 0x80 => {
+                    return Ok(buf[..len].to_vec())
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => return Err(err),
+                Err(_) => break,
+            }
+        }
+        timeout *= 2;
+    }
+    Err(io::Error::new(
+        io::ErrorKind::TimedOut,
+        "gateway did not respond",
+    ))
+}
+
+/// Returns the default IPv4 gateway from the kernel's routing table.
+#[cfg(target_os = "linux")]
+fn default_gateway() -> io::Result<Ipv4Addr> {
+    let routes = std::fs::read_to_string("/proc/net/route")?;
+    parse_default_gateway(&routes)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no default IPv4 gateway"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn default_gateway() -> io::Result<Ipv4Addr> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "default gateway discovery is not supported on this platform",
+    ))
+}
+
+/// Parses the default gateway from the contents of `/proc/net/route`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_default_gateway(routes: &str) -> Option<Ipv4Addr> {
+    // The route is up and uses a gateway.
+    const FLAGS: u32 = 0x1 | 0x2;
+
+    routes.lines().skip(1).find_map(|line| {
+        let mut fields = line.split_whitespace().skip(1);
+        let destination = fields.next()?;
+        let gateway = u32::from_str_radix(fields.next()?, 16).ok()?;
+        let flags = u32::from_str_radix(fields.next()?, 16).ok()?;
+        if destination != "00000000" || flags & FLAGS != FLAGS {
+            return None;
+        }
+        // Addresses are in the host's byte order.
+        Some(Ipv4Addr::from(gateway.to_ne_bytes()))
+    })
 }
 
-pub(crate) fn search_gateway() -> oneshot::Receiver<Result<Gateway, Box<dyn Error + Send + Sync>>> {
+/// Searches for the gateway of the network of `interface`.
+pub(crate) fn search_gateway(
+    interface: Ipv4Net,
+) -> oneshot::Receiver<Result<Gateway, Box<dyn Error + Send + Sync>>> {
     let (search_result_sender, search_result_receiver) = oneshot::channel();
 
     let (events_sender, mut task_receiver) = mpsc::channel(10);
     let (mut task_sender, events_queue) = mpsc::channel(0);
 
     tokio::spawn(async move {
-        let gateway = match igd_next::aio::tokio::search_gateway(SearchOptions::default()).await {
-            Ok(gateway) => gateway,
+        let (mut client, addr) = match Client::search(interface).await {
+            Ok(client) => match client.addr() {
+                Ok(addr) => (client, addr),
+                Err(err) => {
+                    let _ = search_result_sender.send(Err(err.into()));
+                    return;
+                }
+            },
             Err(err) => {
-                let _ = search_result_sender.send(Err(err.into()));
+                let _ = search_result_sender.send(Err(err));
                 return;
             }
         };
 
-        let external_addr = match gateway.get_external_ip().await {
-            Ok(addr) => addr,
-            Err(err) => {
-                let _ = search_result_sender.send(Err(err.into()));
-                return;
-            }
+        let mut announcements = match client {
+            Client::Igd { .. } => match Announcements::new(interface.addr(), addr) {
+                Ok(announcements) => Some(announcements),
+                Err(err) => {
+                    tracing::debug!(
+                        gateway=%addr,
+                        "could not listen for gateway announcements, restarts won't be detected: {err}"
+                    );
+                    None
+                }
+            },
+            Client::Pcp { .. } | Client::NatPmp { .. } => None,
         };
 
         // Check if receiver dropped.
@@ -118,7 +506,9 @@ pub(crate) fn search_gateway() -> oneshot::Receiver<Result<Gateway, Box<dyn Erro
             .send(Ok(Gateway {
                 sender: events_sender,
                 receiver: events_queue,
-                external_addr,
+                addr,
+                external_addr: client.external_addr(),
+                protocol: client.protocol(),
             }))
             .is_err()
         {
@@ -126,35 +516,58 @@ pub(crate) fn search_gateway() -> oneshot::Receiver<Result<Gateway, Box<dyn Erro
         }
 
         loop {
+            let next = match announcements.as_mut() {
+                Some(announcements) => {
+                    let request = task_receiver.next();
+                    let restarted = announcements.restarted();
+                    match future::select(request, pin!(restarted)).await {
+                        Either::Left((req, _)) => Ok(req),
+                        Either::Right((restarted, _)) => Err(restarted),
+                    }
+                }
+                None => Ok(task_receiver.next().await),
+            };
+            let req = match next {
+                Ok(req) => req,
+                Err(Ok(())) => {
+                    // Gateway was dropped.
+                    if task_sender.send(GatewayEvent::Restarted).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                Err(Err(err)) => {
+                    tracing::debug!(
+                        gateway=%addr,
+                        "stopped listening for gateway announcements: {err}"
+                    );
+                    announcements = None;
+                    continue;
+                }
+            };
             // The task sender has dropped so we can return.
-            let Some(req) = task_receiver.next().await else {
+            let Some(req) = req else {
                 return;
             };
+            let mut gateway_restarted = false;
             let event = match req {
                 GatewayRequest::AddMapping { mapping, duration } => {
-                    let gateway = gateway.clone();
-                    match gateway
-                        .add_port(
-                            mapping.protocol,
-                            mapping.internal_addr.port(),
-                            mapping.internal_addr,
-                            duration,
-                            "rust-libp2p mapping",
-                        )
-                        .await
-                    {
-                        Ok(()) => GatewayEvent::Mapped(mapping),
-                        Err(err) => GatewayEvent::MapFailure(mapping, err.into()),
+                    match client.add_mapping(&mapping, duration).await {
+                        Ok(lease) => {
+                            gateway_restarted = lease.gateway_restarted;
+                            GatewayEvent::Mapped {
+                                mapping,
+                                external_addr: lease.external_addr,
+                                duration: lease.duration,
+                            }
+                        }
+                        Err(err) => GatewayEvent::MapFailure(mapping, err),
                     }
                 }
                 GatewayRequest::RemoveMapping(mapping) => {
-                    let gateway = gateway.clone();
-                    match gateway
-                        .remove_port(mapping.protocol, mapping.internal_addr.port())
-                        .await
-                    {
+                    match client.remove_mapping(&mapping).await {
                         Ok(()) => GatewayEvent::Removed(mapping),
-                        Err(err) => GatewayEvent::RemovalFailure(mapping, err.into()),
+                        Err(err) => GatewayEvent::RemovalFailure(mapping, err),
                     }
                 }
             };
@@ -162,8 +575,50 @@ pub(crate) fn search_gateway() -> oneshot::Receiver<Result<Gateway, Box<dyn Erro
             if task_sender.send(event).await.is_err() {
                 return;
             }
+            if gateway_restarted && task_sender.send(GatewayEvent::Restarted).await.is_err() {
+                return;
+            }
         }
     });
 
     search_result_receiver
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The kernel prints addresses in the host's byte order.
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn parses_default_gateway() {
+        let routes =
+            "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+                      eth0\t0000A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0\n\
+                      eth0\t00000000\t0101A8C0\t0003\t0\t0\t0\t00000000\t0\t0\t0\n";
+
+        assert_eq!(
+            parse_default_gateway(routes),
+            Some(Ipv4Addr::new(192, 168, 1, 1))
+        );
+        assert_eq!(parse_default_gateway(routes.lines().next().unwrap()), None);
+    }
+
+    #[test]
+    fn detects_restart_from_epoch() {
+        let start = Instant::now();
+        let mut epoch = None;
+
+        assert!(!Epoch::update(&mut epoch, 1000, start));
+        assert!(!Epoch::update(
+            &mut epoch,
+            1599,
+            start + Duration::from_secs(600)
+        ));
+        assert!(Epoch::update(
+            &mut epoch,
+            30,
+            start + Duration::from_secs(1200)
+        ));
+    }
+}
@@ -77,6 +77,8 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
     let endpoint = quote! { #prelude_path::Endpoint };
     let connection_denied = quote! { #prelude_path::ConnectionDenied };
 
+    let toggle_groups = build_toggle_groups(ast, data_struct)?;
+
     // Build the generics.
     let impl_generics = {
         let tp = ast.generics.type_params();
@@ -413,6 +415,8 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
     let final_quote = quote! {
         #out_event_definition
 
+        #toggle_groups
+
         impl #impl_generics #trait_to_impl for #name #ty_generics
         #where_clause
         {
@@ -489,6 +493,136 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
     Ok(final_quote.into())
 }
 
+/// Generates methods to enable, disable and inspect the fields of each
+/// `#[behaviour(toggle_group = "...")]` together.
+///
+/// Fields of a group have to be of type `Toggle<T>`. The generated `enable_<group>` method takes
+/// the new behaviours of the group in the order of their fields.
+fn build_toggle_groups(
+    ast: &DeriveInput,
+    data_struct: &DataStruct,
+) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let mut groups: Vec<(String, Vec<(&syn::Ident, &syn::Type)>)> = Vec::new();
+
+    for field in data_struct.fields.iter() {
+        let Some(group) = parse_toggle_group(field)? else {
+            continue;
+        };
+        let ident = field
+            .ident
+            .as_ref()
+            .expect("Fields of NetworkBehaviour implementation to be named.");
+        let inner = toggle_inner_type(&field.ty).ok_or_else(|| {
+            syn::Error::new_spanned(
+                &field.ty,
+                "Fields with a `toggle_group` attribute must be of type `Toggle<_>`",
+            )
+        })?;
+
+        match groups.iter_mut().find(|(name, _)| *name == group) {
+            Some((_, fields)) => fields.push((ident, inner)),
+            None => groups.push((group, vec![(ident, inner)])),
+        }
+    }
+
+    if groups.is_empty() {
+        return Ok(None);
+    }
+
+    let name = &ast.ident;
+    let visibility = &ast.vis;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let methods = groups.iter().map(|(group, fields)| {
+        let enable = quote::format_ident!("enable_{}", group);
+        let disable = quote::format_ident!("disable_{}", group);
+        let is_enabled = quote::format_ident!("is_{}_enabled", group);
+        let idents = fields.iter().map(|(ident, _)| ident).collect::<Vec<_>>();
+        let types = fields.iter().map(|(_, ty)| ty);
+        let (first, rest) = idents.split_first().expect("groups to have at least one field");
+
+        let enable_doc = format!(
+            "Enables the behaviours of the `{group}` group, replacing the current ones, if any."
+        );
+        let disable_doc = format!(
+            "Disables the behaviours of the `{group}` group, tearing down their connection handlers."
+        );
+        let is_enabled_doc =
+            format!("Returns `true` if all behaviours of the `{group}` group are enabled.");
+
+        quote! {
+            #[doc = #enable_doc]
+            #visibility fn #enable(&mut self, #(#idents: #types),*) {
+                #(self.#idents.enable(#idents);)*
+            }
+
+            #[doc = #disable_doc]
+            #visibility fn #disable(&mut self) {
+                #(self.#idents.disable();)*
+            }
+
+            #[doc = #is_enabled_doc]
+            #visibility fn #is_enabled(&self) -> bool {
+                self.#first.is_enabled() #(&& self.#rest.is_enabled())*
+            }
+        }
+    });
+
+    Ok(Some(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#methods)*
+        }
+    }))
+}
+
+/// Parses the `toggle_group` of a field from its `#[behaviour]` attribute, if any.
+fn parse_toggle_group(field: &syn::Field) -> syn::Result<Option<String>> {
+    let mut group = None;
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("behaviour"))
+    {
+        let nested = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+
+        for meta in nested {
+            if meta.path().is_ident("toggle_group") {
+                let value = meta.require_name_value()?.value.require_str_lit()?;
+
+                group = Some(syn::parse_str::<syn::Ident>(&value)?.to_string());
+
+                continue;
+            }
+
+            return Err(syn::Error::new_spanned(
+                meta.path(),
+                "Unknown field attribute, expected `toggle_group`",
+            ));
+        }
+    }
+
+    Ok(group)
+}
+
+/// Returns `T` if `ty` is a `Toggle<T>`.
+fn toggle_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Toggle" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        syn::GenericArgument::Type(inner) if arguments.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
+
 struct BehaviourAttributes {
     prelude_path: syn::Path,
     user_specified_out_event: Option<syn::Type>,
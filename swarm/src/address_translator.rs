@@ -0,0 +1,141 @@
+use libp2p_core::multiaddr::Protocol;
+use libp2p_core::Multiaddr;
+use std::collections::HashMap;
+
+/// Translation of the addresses remotes observe for the local node into candidates for
+/// external addresses.
+///
+/// Configured via [`Config::with_address_translator`](crate::Config::with_address_translator).
+/// For every [`ToSwarm::NewExternalAddrCandidate`](crate::ToSwarm::NewExternalAddrCandidate),
+/// the observed address is translated once per listen address. If no listen address yields a
+/// translation, the observed address itself is reported as candidate.
+pub trait AddressTranslator: Send + 'static {
+    /// Translates the `observed` address based on the `listen` address.
+    ///
+    /// `translated` is the translation of the transport, see
+    /// [`Transport::address_translation`](libp2p_core::Transport::address_translation).
+    /// Returning [`None`] yields no candidate for this listen address.
+    fn translate(
+        &mut self,
+        listen: &Multiaddr,
+        observed: &Multiaddr,
+        translated: Option<Multiaddr>,
+    ) -> Option<Multiaddr>;
+}
+
+/// Translates addresses for listen ports forwarded to different external ports, e.g. by a NAT.
+///
+/// The port of the translated address is replaced by the external port the listen port is
+/// forwarded to, either configured per port or by a fixed offset. Addresses of listen ports
+/// without a forwarding keep the translation of the transport.
+#[derive(Debug, Clone, Default)]
+pub struct PortForwarding {
+    ports: HashMap<u16, u16>,
+    offset: Option<i32>,
+}
+
+impl PortForwarding {
+    /// Forwards the external port `external` to the listen port `listen`.
+    pub fn with_port(mut self, listen: u16, external: u16) -> Self {
+        self.ports.insert(listen, external);
+        self
+    }
+
+    /// Forwards the external port `listen + offset` to every listen port not configured via
+    /// [`PortForwarding::with_port`].
+    pub fn with_offset(mut self, offset: i32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    fn external_port(&self, listen: u16) -> Option<u16> {
+        if let Some(port) = self.ports.get(&listen) {
+            return Some(*port);
+        }
+
+        u16::try_from(i32::from(listen) + self.offset?).ok()
+    }
+}
+
+impl AddressTranslator for PortForwarding {
+    fn translate(
+        &mut self,
+        listen: &Multiaddr,
+        observed: &Multiaddr,
+        translated: Option<Multiaddr>,
+    ) -> Option<Multiaddr> {
+        let translated =
+            translated.or_else(|| libp2p_core::address_translation(listen, observed))?;
+        let Some(external) = port(listen).and_then(|port| self.external_port(port)) else {
+            return Some(translated);
+        };
+
+        let mut replaced = false;
+        let address = translated
+            .into_iter()
+            .map(|protocol| match protocol {
+                Protocol::Tcp(_) if !replaced => {
+                    replaced = true;
+                    Protocol::Tcp(external)
+                }
+                Protocol::Udp(_) if !replaced => {
+                    replaced = true;
+                    Protocol::Udp(external)
+                }
+                protocol => protocol,
+            })
+            .collect();
+
+        Some(address)
+    }
+}
+
+/// The first TCP or UDP port of the address.
+fn port(address: &Multiaddr) -> Option<u16> {
+    address.iter().find_map(|protocol| match protocol {
+        Protocol::Tcp(port) | Protocol::Udp(port) => Some(port),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_forwarding_replaces_forwarded_ports() {
+        let mut forwarding = PortForwarding::default()
+            .with_port(4001, 14001)
+            .with_offset(1000);
+        let observed: Multiaddr = "/ip4/1.2.3.4/tcp/54321".parse().unwrap();
+
+        let translate = |forwarding: &mut PortForwarding, listen: &str| {
+            forwarding.translate(&listen.parse().unwrap(), &observed, None)
+        };
+
+        assert_eq!(
+            translate(&mut forwarding, "/ip4/192.168.1.2/tcp/4001"),
+            Some("/ip4/1.2.3.4/tcp/14001".parse().unwrap())
+        );
+        assert_eq!(
+            translate(&mut forwarding, "/ip4/192.168.1.2/tcp/5001"),
+            Some("/ip4/1.2.3.4/tcp/6001".parse().unwrap())
+        );
+        assert_eq!(
+            translate(&mut forwarding, "/ip4/192.168.1.2/tcp/65000"),
+            Some("/ip4/1.2.3.4/tcp/65000".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn port_forwarding_applies_to_translation_of_transport() {
+        let mut forwarding = PortForwarding::default().with_port(4001, 14001);
+        let listen: Multiaddr = "/ip4/192.168.1.2/udp/4001/quic-v1".parse().unwrap();
+        let observed: Multiaddr = "/ip4/1.2.3.4/udp/4001/quic-v1".parse().unwrap();
+
+        assert_eq!(
+            forwarding.translate(&listen, &observed, Some(observed.clone())),
+            Some("/ip4/1.2.3.4/udp/14001/quic-v1".parse().unwrap())
+        );
+    }
+}
@@ -22,14 +22,17 @@ mod either;
 mod external_addresses;
 mod listen_addresses;
 mod peer_addresses;
+pub mod pinned;
+mod timers;
 pub mod toggle;
 
 pub use external_addresses::ExternalAddresses;
 pub use listen_addresses::ListenAddresses;
 pub use peer_addresses::PeerAddresses;
+pub use timers::Timers;
 
 use crate::connection::ConnectionId;
-use crate::dial_opts::DialOpts;
+use crate::dial_opts::{DialOpts, Priority};
 use crate::listen_opts::ListenOpts;
 use crate::{
     ConnectionDenied, ConnectionHandler, DialError, ListenError, THandler, THandlerInEvent,
@@ -90,6 +93,11 @@ use std::{task::Context, task::Poll};
 /// custom `to_swarm` is handled by [`From`] implementations which the user needs to define in
 /// addition to the event `enum` itself.
 ///
+/// Members of type [`Toggle`](crate::behaviour::toggle::Toggle) can be marked with
+/// `#[behaviour(toggle_group = "name")]`. For each group, the derive macro generates the methods
+/// `enable_<name>`, taking the new behaviours in the order of their members, `disable_<name>` and
+/// `is_<name>_enabled`, turning the members of the group on and off together at runtime.
+///
 /// ``` rust
 /// # use libp2p_identify as identify;
 /// # use libp2p_ping as ping;
@@ -308,6 +316,20 @@ pub enum ToSwarm<TOutEvent, TInEvent> {
 
     /// Reports external address of a remote peer to the [`Swarm`](crate::Swarm) and through that to other [`NetworkBehaviour`]s.
     NewExternalAddrOfPeer { peer_id: PeerId, address: Multiaddr },
+
+    /// Instructs the [`Swarm`](crate::Swarm) to stop upgrading new inbound connections, e.g. to
+    /// shed load.
+    ///
+    /// Inbound connections accepted by the listeners are held back, without being upgraded, until
+    /// [`ToSwarm::ResumeInboundUpgrades`] is issued. Inbound connections exceeding
+    /// [`Config::with_max_paused_incoming`](crate::Config::with_max_paused_incoming) are denied
+    /// meanwhile. The pause is shared by all
+    /// [`NetworkBehaviour`]s, i.e. the latest of these commands takes effect.
+    PauseInboundUpgrades,
+
+    /// Instructs the [`Swarm`](crate::Swarm) to upgrade the inbound connections held back since
+    /// [`ToSwarm::PauseInboundUpgrades`] and to resume upgrading new inbound connections.
+    ResumeInboundUpgrades,
 }
 
 impl<TOutEvent, TInEventOld> ToSwarm<TOutEvent, TInEventOld> {
@@ -347,6 +369,8 @@ impl<TOutEvent, TInEventOld> ToSwarm<TOutEvent, TInEventOld> {
                 address: addr,
                 peer_id,
             },
+            ToSwarm::PauseInboundUpgrades => ToSwarm::PauseInboundUpgrades,
+            ToSwarm::ResumeInboundUpgrades => ToSwarm::ResumeInboundUpgrades,
         }
     }
 }
@@ -385,6 +409,8 @@ impl<TOutEvent, THandlerIn> ToSwarm<TOutEvent, THandlerIn> {
                 address: addr,
                 peer_id,
             },
+            ToSwarm::PauseInboundUpgrades => ToSwarm::PauseInboundUpgrades,
+            ToSwarm::ResumeInboundUpgrades => ToSwarm::ResumeInboundUpgrades,
         }
     }
 }
@@ -427,6 +453,11 @@ pub enum FromSwarm<'a> {
     /// Informs the behaviour that the dial to a known
     /// or unknown node failed.
     DialFailure(DialFailure<'a>),
+    /// Informs the behaviour about the [`Priority`] of a new dial.
+    ///
+    /// Only reported for dials with a priority other than [`Priority::Normal`], right before
+    /// [`NetworkBehaviour::handle_pending_outbound_connection`] is called for the dial.
+    DialPriority(DialPriority),
     /// Informs the behaviour that an error
     /// happened on an incoming connection during its initial handshake.
     ///
@@ -497,6 +528,14 @@ pub struct DialFailure<'a> {
     pub connection_id: ConnectionId,
 }
 
+/// [`FromSwarm`] variant that informs the behaviour about the priority of a new dial.
+#[derive(Debug, Clone, Copy)]
+pub struct DialPriority {
+    pub peer_id: Option<PeerId>,
+    pub connection_id: ConnectionId,
+    pub priority: Priority,
+}
+
 /// [`FromSwarm`] variant that informs the behaviour that an error
 /// happened on an incoming connection during its initial handshake.
 ///
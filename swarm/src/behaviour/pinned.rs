@@ -0,0 +1,532 @@
+//! A [`NetworkBehaviour`] maintaining connections to a set of pinned peers.
+//!
+//! Connections to pinned peers are kept alive. When the last connection to a pinned peer closes
+//! or dialing it fails, the peer is redialed after an exponential backoff with jitter.
+
+use crate::behaviour::{ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm};
+use crate::connection::ConnectionId;
+use crate::dial_opts::{DialOpts, PeerCondition};
+use crate::handler::{
+    ConnectionEvent, DialUpgradeError, FullyNegotiatedInbound, FullyNegotiatedOutbound,
+};
+use crate::{
+    ConnectionDenied, ConnectionHandlerEvent, DialError, NetworkBehaviour, NotifyHandler,
+    SubstreamProtocol, THandler, THandlerInEvent, THandlerOutEvent, Timers, ToSwarm,
+};
+use libp2p_core::upgrade::DeniedUpgrade;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use rand::Rng;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+use void::Void;
+
+/// The configuration of the redial backoff of the [`Behaviour`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5 * 60),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl Config {
+    /// Sets the backoff before the first redial, doubled with every failed dial.
+    ///
+    /// Defaults to 1 second.
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the maximum backoff between redials.
+    ///
+    /// Defaults to 5 minutes.
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Sets the fraction by which a backoff is randomly lengthened or shortened, clamped to
+    /// `0.0..=1.0`.
+    ///
+    /// Defaults to `0.2`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `jitter` is NaN or infinite.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        assert!(jitter.is_finite(), "jitter must be finite, got {jitter}");
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The backoff after `failures` consecutive failed dials, without jitter.
+    fn backoff(&self, failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.min(31));
+
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    fn backoff_with_jitter(&self, failures: u32) -> Duration {
+        let backoff = self.backoff(failures);
+        if self.jitter == 0.0 {
+            return backoff;
+        }
+
+        let factor = rand::thread_rng().gen_range(1.0 - self.jitter..=1.0 + self.jitter);
+        // `Duration::mul_f64` panics on overflow, e.g. with a `max_backoff` of `Duration::MAX`.
+        Duration::try_from_secs_f64(backoff.as_secs_f64() * factor).unwrap_or(self.max_backoff)
+    }
+}
+
+/// The state of the connection to a pinned peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// At least one connection to the peer is established.
+    Connected,
+    /// The peer is being dialed.
+    Dialing {
+        /// The number of consecutive failed dials before this one.
+        failures: u32,
+    },
+    /// The peer is redialed once the backoff elapsed.
+    Backoff {
+        /// The number of consecutive failed dials.
+        failures: u32,
+    },
+}
+
+/// Events emitted by the [`Behaviour`] when the [`State`] of a pinned peer changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The first connection to a pinned peer was established.
+    Connected { peer_id: PeerId },
+    /// The last connection to a pinned peer closed, it is redialed after `retry_in`.
+    Disconnected { peer_id: PeerId, retry_in: Duration },
+    /// Dialing a pinned peer failed, it is redialed after `retry_in`.
+    DialFailed {
+        peer_id: PeerId,
+        /// The number of consecutive failed dials.
+        failures: u32,
+        retry_in: Duration,
+    },
+}
+
+/// A [`NetworkBehaviour`] keeping connections to pinned peers alive and redialing them.
+///
+/// Pinned peers are dialed by their [`PeerId`], using the addresses passed to
+/// [`Behaviour::pin_with_addresses`] as well as those of other behaviours.
+pub struct Behaviour {
+    config: Config,
+    pinned: HashMap<PeerId, Pinned>,
+    connections: HashMap<PeerId, HashSet<ConnectionId>>,
+    redials: Timers<PeerId>,
+    pending_events: VecDeque<ToSwarm<Event, bool>>,
+    waker: Option<Waker>,
+}
+
+struct Pinned {
+    state: State,
+    addresses: Vec<Multiaddr>,
+}
+
+impl Behaviour {
+    /// Creates a [`Behaviour`] without pinned peers.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            pinned: HashMap::new(),
+            connections: HashMap::new(),
+            redials: Timers::new(),
+            pending_events: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    /// Pins a peer, dialing it unless already connected.
+    ///
+    /// Returns whether the peer was not pinned before.
+    pub fn pin(&mut self, peer_id: PeerId) -> bool {
+        self.pin_with_addresses(peer_id, Vec::new())
+    }
+
+    /// Pins a peer, dialing it at the given addresses unless already connected.
+    ///
+    /// Pinning a pinned peer again replaces its addresses. Returns whether the peer was not
+    /// pinned before.
+    pub fn pin_with_addresses(&mut self, peer_id: PeerId, addresses: Vec<Multiaddr>) -> bool {
+        if let Some(pinned) = self.pinned.get_mut(&peer_id) {
+            pinned.addresses = addresses;
+            return false;
+        }
+
+        let connections = self.connections.get(&peer_id).cloned().unwrap_or_default();
+        let state = if connections.is_empty() {
+            self.dial(peer_id);
+            State::Dialing { failures: 0 }
+        } else {
+            State::Connected
+        };
+        self.pinned.insert(peer_id, Pinned { state, addresses });
+        for connection_id in connections {
+            self.notify_keep_alive(peer_id, connection_id, true);
+        }
+
+        true
+    }
+
+    /// Unpins a peer, no longer keeping its connections alive nor redialing it.
+    ///
+    /// Returns whether the peer was pinned.
+    pub fn unpin(&mut self, peer_id: &PeerId) -> bool {
+        if self.pinned.remove(peer_id).is_none() {
+            return false;
+        }
+        self.redials.remove(peer_id);
+        let connections = self.connections.get(peer_id).cloned().unwrap_or_default();
+        for connection_id in connections {
+            self.notify_keep_alive(*peer_id, connection_id, false);
+        }
+
+        true
+    }
+
+    /// The [`State`] of a pinned peer.
+    pub fn state(&self, peer_id: &PeerId) -> Option<State> {
+        self.pinned.get(peer_id).map(|pinned| pinned.state)
+    }
+
+    /// The pinned peers.
+    pub fn pinned_peers(&self) -> impl Iterator<Item = &PeerId> {
+        self.pinned.keys()
+    }
+
+    fn dial(&mut self, peer_id: PeerId) {
+        self.push(ToSwarm::Dial {
+            opts: DialOpts::peer_id(peer_id)
+                .condition(PeerCondition::DisconnectedAndNotDialing)
+                .build(),
+        });
+    }
+
+    fn notify_keep_alive(&mut self, peer_id: PeerId, connection_id: ConnectionId, keep: bool) {
+        self.push(ToSwarm::NotifyHandler {
+            peer_id,
+            handler: NotifyHandler::One(connection_id),
+            event: keep,
+        });
+    }
+
+    fn schedule_redial(&mut self, peer_id: PeerId, failures: u32) -> Option<Duration> {
+        let pinned = self.pinned.get_mut(&peer_id)?;
+        pinned.state = State::Backoff { failures };
+        let retry_in = self.config.backoff_with_jitter(failures);
+        self.redials.insert(peer_id, retry_in);
+
+        Some(retry_in)
+    }
+
+    fn on_connection_established(&mut self, peer_id: PeerId, connection_id: ConnectionId) {
+        self.connections
+            .entry(peer_id)
+            .or_default()
+            .insert(connection_id);
+
+        let Some(pinned) = self.pinned.get_mut(&peer_id) else {
+            return;
+        };
+        if pinned.state == State::Connected {
+            return;
+        }
+        pinned.state = State::Connected;
+        self.redials.remove(&peer_id);
+        tracing::debug!(peer=%peer_id, "Connected to pinned peer");
+        self.push(ToSwarm::GenerateEvent(Event::Connected { peer_id }));
+    }
+
+    fn on_connection_closed(&mut self, peer_id: PeerId, connection_id: ConnectionId) {
+        if let Some(connections) = self.connections.get_mut(&peer_id) {
+            connections.remove(&connection_id);
+            if !connections.is_empty() {
+                return;
+            }
+            self.connections.remove(&peer_id);
+        }
+
+        if self.state(&peer_id) != Some(State::Connected) {
+            return;
+        }
+        if let Some(retry_in) = self.schedule_redial(peer_id, 0) {
+            tracing::debug!(peer=%peer_id, "Disconnected from pinned peer, redialing in {retry_in:?}");
+            self.push(ToSwarm::GenerateEvent(Event::Disconnected {
+                peer_id,
+                retry_in,
+            }));
+        }
+    }
+
+    fn on_dial_failure(&mut self, peer_id: PeerId, error: &DialError) {
+        // Another dial of the peer is in progress, its outcome determines the state.
+        if matches!(error, DialError::DialPeerConditionFalse(_)) {
+            return;
+        }
+        let Some(State::Dialing { failures }) = self.state(&peer_id) else {
+            return;
+        };
+
+        let failures = failures + 1;
+        if let Some(retry_in) = self.schedule_redial(peer_id, failures) {
+            tracing::debug!(peer=%peer_id, %failures, "Failed to dial pinned peer, redialing in {retry_in:?}: {error}");
+            self.push(ToSwarm::GenerateEvent(Event::DialFailed {
+                peer_id,
+                failures,
+                retry_in,
+            }));
+        }
+    }
+
+    fn push(&mut self, event: ToSwarm<Event, bool>) {
+        self.pending_events.push_back(event);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = Handler;
+    type ToSwarm = Event;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        peer: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(Handler {
+            keep_alive: self.pinned.contains_key(&peer),
+        })
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _: &[Multiaddr],
+        _: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        Ok(maybe_peer
+            .and_then(|peer| self.pinned.get(&peer))
+            .map(|pinned| pinned.addresses.clone())
+            .unwrap_or_default())
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        peer: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(Handler {
+            keep_alive: self.pinned.contains_key(&peer),
+        })
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id,
+                connection_id,
+                ..
+            }) => self.on_connection_established(peer_id, connection_id),
+            FromSwarm::ConnectionClosed(ConnectionClosed {
+                peer_id,
+                connection_id,
+                ..
+            }) => self.on_connection_closed(peer_id, connection_id),
+            FromSwarm::DialFailure(DialFailure {
+                peer_id: Some(peer_id),
+                error,
+                ..
+            }) => self.on_dial_failure(peer_id, error),
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(event)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        while let Poll::Ready(peer_id) = self.redials.poll_expired(cx) {
+            let Some(pinned) = self.pinned.get_mut(&peer_id) else {
+                continue;
+            };
+            let State::Backoff { failures } = pinned.state else {
+                continue;
+            };
+            pinned.state = State::Dialing { failures };
+            self.dial(peer_id);
+        }
+
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(event);
+        }
+        self.waker = Some(cx.waker().clone());
+
+        Poll::Pending
+    }
+}
+
+/// A [`ConnectionHandler`](crate::ConnectionHandler) that keeps connections to pinned peers
+/// alive.
+///
+/// The [`Behaviour`] notifies the handler whether its peer is pinned.
+#[derive(Debug, Clone)]
+pub struct Handler {
+    keep_alive: bool,
+}
+
+impl crate::handler::ConnectionHandler for Handler {
+    type FromBehaviour = bool;
+    type ToBehaviour = Void;
+    type InboundProtocol = DeniedUpgrade;
+    type OutboundProtocol = DeniedUpgrade;
+    type InboundOpenInfo = ();
+    type OutboundOpenInfo = Void;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        SubstreamProtocol::new(DeniedUpgrade, ())
+    }
+
+    fn on_behaviour_event(&mut self, keep_alive: Self::FromBehaviour) {
+        self.keep_alive = keep_alive;
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        self.keep_alive
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
+    > {
+        Poll::Pending
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+        match event {
+            ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound {
+                protocol, ..
+            }) => void::unreachable(protocol),
+            ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
+                protocol, ..
+            }) => void::unreachable(protocol),
+            ConnectionEvent::DialUpgradeError(DialUpgradeError { info, .. }) => {
+                void::unreachable(info)
+            }
+            ConnectionEvent::AddressChange(_)
+            | ConnectionEvent::ListenUpgradeError(_)
+            | ConnectionEvent::LocalProtocolsChange(_)
+            | ConnectionEvent::RemoteProtocolsChange(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_maximum() {
+        let config = Config::default()
+            .with_initial_backoff(Duration::from_secs(1))
+            .with_max_backoff(Duration::from_secs(10));
+
+        let backoffs = (0..6)
+            .map(|f| config.backoff(f).as_secs())
+            .collect::<Vec<_>>();
+
+        assert_eq!(backoffs, vec![1, 2, 4, 8, 10, 10]);
+        assert_eq!(config.backoff(u32::MAX), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn jitter_stays_within_fraction() {
+        let config = Config::default()
+            .with_initial_backoff(Duration::from_secs(10))
+            .with_jitter(0.5);
+
+        for _ in 0..100 {
+            let backoff = config.backoff_with_jitter(0);
+            assert!(backoff >= Duration::from_secs(5) && backoff <= Duration::from_secs(15));
+        }
+    }
+
+    #[test]
+    fn jitter_saturates_at_maximum() {
+        let config = Config::default()
+            .with_initial_backoff(Duration::MAX)
+            .with_max_backoff(Duration::MAX)
+            .with_jitter(1.0);
+
+        for _ in 0..100 {
+            assert!(config.backoff_with_jitter(0) <= Duration::MAX);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "jitter must be finite")]
+    fn rejects_nan_jitter() {
+        let _ = Config::default().with_jitter(f64::NAN);
+    }
+
+    #[test]
+    fn pinning_disconnected_peer_dials_it() {
+        let mut behaviour = Behaviour::new(Config::default());
+        let peer = PeerId::random();
+
+        assert!(behaviour.pin(peer));
+        assert!(!behaviour.pin(peer));
+
+        assert_eq!(behaviour.state(&peer), Some(State::Dialing { failures: 0 }));
+        assert!(matches!(
+            behaviour.pending_events.pop_front(),
+            Some(ToSwarm::Dial { opts }) if opts.get_peer_id() == Some(peer)
+        ));
+        assert!(behaviour.pending_events.is_empty());
+    }
+}
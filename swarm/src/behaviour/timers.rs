@@ -0,0 +1,244 @@
+use futures::{FutureExt, Stream};
+use futures_timer::Delay;
+use instant::Instant;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// A set of named timers for use within a [`NetworkBehaviour`](crate::NetworkBehaviour).
+///
+/// Instead of a [`Delay`] per deadline, all timers share a single [`Delay`] for the earliest
+/// deadline. Keys of expired timers are returned by [`Timers::poll_expired`], which is to be
+/// called from [`NetworkBehaviour::poll`](crate::NetworkBehaviour::poll). [`Timers`] is also a
+/// [`Stream`] of these keys, which never terminates.
+///
+/// With [`Timers::with_coalescing`], timers expiring shortly after one another are returned
+/// together, reducing the number of wake-ups.
+///
+/// ```
+/// # use libp2p_swarm::behaviour::Timers;
+/// # use std::time::Duration;
+/// #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// enum Timer {
+///     Refresh,
+///     Cleanup,
+/// }
+///
+/// let mut timers = Timers::new();
+/// timers.insert(Timer::Refresh, Duration::from_secs(30));
+/// timers.insert(Timer::Cleanup, Duration::from_secs(60));
+/// assert!(timers.contains(&Timer::Cleanup));
+/// ```
+pub struct Timers<K> {
+    /// The keys ordered by their deadline, made unique by the sequence number of the insertion.
+    deadlines: BTreeMap<(Instant, u64), K>,
+    keys: HashMap<K, (Instant, u64)>,
+    next_seq: u64,
+    /// Timers expiring within this window after the earliest one are returned together.
+    coalescing: Duration,
+    /// The timer for the earliest deadline.
+    delay: Option<(Instant, Delay)>,
+    /// The waker of the last call to [`Timers::poll_expired`], woken when an earlier deadline is
+    /// inserted.
+    waker: Option<Waker>,
+}
+
+impl<K> Timers<K>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Creates an empty set of timers.
+    pub fn new() -> Self {
+        Self::with_coalescing(Duration::ZERO)
+    }
+
+    /// Creates an empty set of timers, returning timers expiring within `window` after the
+    /// earliest expired one together.
+    pub fn with_coalescing(window: Duration) -> Self {
+        Self {
+            deadlines: BTreeMap::new(),
+            keys: HashMap::new(),
+            next_seq: 0,
+            coalescing: window,
+            delay: None,
+            waker: None,
+        }
+    }
+
+    /// Sets the timer of `key` to expire after `duration`.
+    ///
+    /// Returns the previous deadline of the timer, if any.
+    pub fn insert(&mut self, key: K, duration: Duration) -> Option<Instant> {
+        self.insert_at(key, Instant::now() + duration)
+    }
+
+    /// Sets the timer of `key` to expire at `deadline`.
+    ///
+    /// Returns the previous deadline of the timer, if any.
+    pub fn insert_at(&mut self, key: K, deadline: Instant) -> Option<Instant> {
+        let previous = self.remove(&key);
+
+        let entry = (deadline, self.next_seq);
+        self.next_seq += 1;
+        self.deadlines.insert(entry, key.clone());
+        self.keys.insert(key, entry);
+
+        if self.delay.as_ref().map_or(true, |(d, _)| deadline < *d) {
+            self.delay = None;
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+
+        previous
+    }
+
+    /// Cancels the timer of `key`, returning its deadline if it was set.
+    pub fn remove(&mut self, key: &K) -> Option<Instant> {
+        let entry = self.keys.remove(key)?;
+        self.deadlines.remove(&entry);
+
+        Some(entry.0)
+    }
+
+    /// Returns the deadline of the timer of `key`, if set.
+    pub fn deadline(&self, key: &K) -> Option<Instant> {
+        self.keys.get(key).map(|(deadline, _)| *deadline)
+    }
+
+    /// Returns whether the timer of `key` is set.
+    pub fn contains(&self, key: &K) -> bool {
+        self.keys.contains_key(key)
+    }
+
+    /// Returns the number of timers set.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns whether no timer is set.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Cancels all timers.
+    pub fn clear(&mut self) {
+        self.deadlines.clear();
+        self.keys.clear();
+        self.delay = None;
+    }
+
+    /// Returns the key of an expired timer, removing the timer.
+    ///
+    /// Keys of timers expiring at the same time are returned in the order of their insertion.
+    pub fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<K> {
+        loop {
+            let Some((&(deadline, seq), _)) = self.deadlines.first_key_value() else {
+                self.delay = None;
+                self.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            };
+
+            if deadline <= Instant::now() + self.coalescing {
+                let key = self
+                    .deadlines
+                    .remove(&(deadline, seq))
+                    .expect("first entry to exist");
+                self.keys.remove(&key);
+                return Poll::Ready(key);
+            }
+
+            if self.delay.as_ref().map_or(true, |(d, _)| *d != deadline) {
+                let delay = Delay::new(deadline.saturating_duration_since(Instant::now()));
+                self.delay = Some((deadline, delay));
+            }
+            let (_, delay) = self.delay.as_mut().expect("delay to be set");
+            if delay.poll_unpin(cx).is_ready() {
+                self.delay = None;
+                continue;
+            }
+
+            self.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+    }
+}
+
+impl<K> Default for Timers<K>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> std::fmt::Debug for Timers<K>
+where
+    K: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Timers")
+            .field("deadlines", &self.deadlines)
+            .field("coalescing", &self.coalescing)
+            .finish()
+    }
+}
+
+impl<K> Stream for Timers<K>
+where
+    K: Clone + Eq + Hash + Unpin,
+{
+    type Item = K;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_expired(cx).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[async_std::test]
+    async fn timers_expire_in_order_of_deadline() {
+        let mut timers = Timers::new();
+        timers.insert("b", Duration::from_millis(20));
+        timers.insert("a", Duration::from_millis(10));
+        timers.insert("c", Duration::from_millis(30));
+        assert!(timers.remove(&"c").is_some());
+
+        assert_eq!(timers.next().await, Some("a"));
+        assert_eq!(timers.next().await, Some("b"));
+        assert!(timers.is_empty());
+    }
+
+    #[async_std::test]
+    async fn reinserting_replaces_deadline() {
+        let mut timers = Timers::new();
+        timers.insert("a", Duration::from_secs(60));
+        let previous = timers.insert("a", Duration::from_millis(10));
+
+        assert!(previous.is_some());
+        assert_eq!(timers.len(), 1);
+        assert_eq!(timers.next().await, Some("a"));
+    }
+
+    #[async_std::test]
+    async fn coalesced_timers_expire_together() {
+        let mut timers = Timers::with_coalescing(Duration::from_secs(60));
+        timers.insert("a", Duration::from_millis(10));
+        timers.insert("b", Duration::from_secs(30));
+        timers.insert("c", Duration::from_secs(120));
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert_eq!(timers.poll_expired(&mut cx), Poll::Ready("a"));
+        assert_eq!(timers.poll_expired(&mut cx), Poll::Ready("b"));
+        assert_eq!(timers.poll_expired(&mut cx), Poll::Pending);
+        assert!(timers.contains(&"c"));
+    }
+}
@@ -32,13 +32,37 @@ use either::Either;
 use futures::future;
 use libp2p_core::{upgrade::DeniedUpgrade, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
-use std::{task::Context, task::Poll};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
 
 /// Implementation of `NetworkBehaviour` that can be either in the disabled or enabled state.
 ///
-/// The state can only be chosen at initialization.
+/// The state can be chosen at initialization and changed at runtime via [`Toggle::enable`] and
+/// [`Toggle::disable`], e.g. to turn a feature on or off on a configuration reload. Disabling
+/// the behaviour tears down its connection handlers on all existing connections. Enabling it
+/// only installs its connection handlers on connections initiated afterwards; events of earlier
+/// connections are not forwarded to it.
 pub struct Toggle<TBehaviour> {
     inner: Option<TBehaviour>,
+    shared: Arc<Mutex<Shared>>,
+    /// Pending and established connections reported to the current inner behaviour.
+    connections: HashSet<ConnectionId>,
+    /// Pending and established connections that predate the current inner behaviour.
+    ///
+    /// Their events are not forwarded, as the current inner behaviour never saw them.
+    stale_connections: HashSet<ConnectionId>,
+}
+
+/// State shared between a [`Toggle`] and its [`ToggleConnectionHandler`]s.
+#[derive(Default)]
+struct Shared {
+    /// Incremented whenever the inner behaviour is disabled or replaced.
+    ///
+    /// Handlers created for an earlier generation tear down their inner handler.
+    generation: u64,
+    /// Wakers of the handlers with an inner handler, woken to tear it down.
+    wakers: HashMap<ConnectionId, Waker>,
 }
 
 impl<TBehaviour> Toggle<TBehaviour> {
@@ -47,6 +71,32 @@ impl<TBehaviour> Toggle<TBehaviour> {
         self.inner.is_some()
     }
 
+    /// Enables the given behaviour, replacing and disabling the current one, if any.
+    ///
+    /// Existing and pending connections do not get a handler of the new behaviour.
+    pub fn enable(&mut self, behaviour: TBehaviour) {
+        self.disable();
+        self.inner = Some(behaviour);
+    }
+
+    /// Disables the behaviour, returning it if it was enabled.
+    ///
+    /// The handlers of the behaviour on all existing connections are torn down, closing their
+    /// streams. Connections that were only kept alive by these handlers may be closed.
+    pub fn disable(&mut self) -> Option<TBehaviour> {
+        let inner = self.inner.take()?;
+
+        self.stale_connections.extend(self.connections.drain());
+
+        let mut shared = self.shared.lock().expect("lock not to be poisoned");
+        shared.generation += 1;
+        for (_, waker) in shared.wakers.drain() {
+            waker.wake();
+        }
+
+        Some(inner)
+    }
+
     /// Returns a reference to the inner `NetworkBehaviour`.
     pub fn as_ref(&self) -> Option<&TBehaviour> {
         self.inner.as_ref()
@@ -60,7 +110,12 @@ impl<TBehaviour> Toggle<TBehaviour> {
 
 impl<TBehaviour> From<Option<TBehaviour>> for Toggle<TBehaviour> {
     fn from(inner: Option<TBehaviour>) -> Self {
-        Toggle { inner }
+        Toggle {
+            inner,
+            shared: Default::default(),
+            connections: Default::default(),
+            stale_connections: Default::default(),
+        }
     }
 }
 
@@ -77,12 +132,13 @@ where
         local_addr: &Multiaddr,
         remote_addr: &Multiaddr,
     ) -> Result<(), ConnectionDenied> {
-        let inner = match self.inner.as_mut() {
-            None => return Ok(()),
-            Some(inner) => inner,
+        let Some(inner) = self.inner.as_mut() else {
+            self.stale_connections.insert(connection_id);
+            return Ok(());
         };
 
         inner.handle_pending_inbound_connection(connection_id, local_addr, remote_addr)?;
+        self.connections.insert(connection_id);
 
         Ok(())
     }
@@ -95,8 +151,11 @@ where
         remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
         let inner = match self.inner.as_mut() {
-            None => return Ok(ToggleConnectionHandler { inner: None }),
-            Some(inner) => inner,
+            Some(inner) if !self.stale_connections.contains(&connection_id) => inner,
+            _ => {
+                self.stale_connections.insert(connection_id);
+                return Ok(ToggleConnectionHandler::disabled());
+            }
         };
 
         let handler = inner.handle_established_inbound_connection(
@@ -105,10 +164,13 @@ where
             local_addr,
             remote_addr,
         )?;
+        self.connections.insert(connection_id);
 
-        Ok(ToggleConnectionHandler {
-            inner: Some(handler),
-        })
+        Ok(ToggleConnectionHandler::enabled(
+            handler,
+            connection_id,
+            &self.shared,
+        ))
     }
 
     fn handle_pending_outbound_connection(
@@ -118,9 +180,9 @@ where
         addresses: &[Multiaddr],
         effective_role: Endpoint,
     ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
-        let inner = match self.inner.as_mut() {
-            None => return Ok(vec![]),
-            Some(inner) => inner,
+        let Some(inner) = self.inner.as_mut() else {
+            self.stale_connections.insert(connection_id);
+            return Ok(vec![]);
         };
 
         let addresses = inner.handle_pending_outbound_connection(
@@ -129,6 +191,7 @@ where
             addresses,
             effective_role,
         )?;
+        self.connections.insert(connection_id);
 
         Ok(addresses)
     }
@@ -141,8 +204,11 @@ where
         role_override: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
         let inner = match self.inner.as_mut() {
-            None => return Ok(ToggleConnectionHandler { inner: None }),
-            Some(inner) => inner,
+            Some(inner) if !self.stale_connections.contains(&connection_id) => inner,
+            _ => {
+                self.stale_connections.insert(connection_id);
+                return Ok(ToggleConnectionHandler::disabled());
+            }
         };
 
         let handler = inner.handle_established_outbound_connection(
@@ -151,13 +217,38 @@ where
             addr,
             role_override,
         )?;
+        self.connections.insert(connection_id);
 
-        Ok(ToggleConnectionHandler {
-            inner: Some(handler),
-        })
+        Ok(ToggleConnectionHandler::enabled(
+            handler,
+            connection_id,
+            &self.shared,
+        ))
     }
 
     fn on_swarm_event(&mut self, event: FromSwarm) {
+        let (connection_id, is_last_event) = match &event {
+            FromSwarm::ConnectionEstablished(e) => (Some(e.connection_id), false),
+            FromSwarm::AddressChange(e) => (Some(e.connection_id), false),
+            FromSwarm::ConnectionClosed(e) => (Some(e.connection_id), true),
+            FromSwarm::DialFailure(e) => (Some(e.connection_id), true),
+            FromSwarm::ListenFailure(e) => (Some(e.connection_id), true),
+            _ => (None, false),
+        };
+
+        if let Some(connection_id) = connection_id {
+            let is_stale = if is_last_event {
+                self.connections.remove(&connection_id);
+                self.stale_connections.remove(&connection_id)
+            } else {
+                self.stale_connections.contains(&connection_id)
+            };
+
+            if is_stale {
+                return;
+            }
+        }
+
         if let Some(behaviour) = &mut self.inner {
             behaviour.on_swarm_event(event);
         }
@@ -169,6 +260,11 @@ where
         connection_id: ConnectionId,
         event: THandlerOutEvent<Self>,
     ) {
+        // Handlers of a replaced inner behaviour may still report events until torn down.
+        if self.stale_connections.contains(&connection_id) {
+            return;
+        }
+
         if let Some(behaviour) = &mut self.inner {
             behaviour.on_connection_handler_event(peer_id, connection_id, event)
         }
@@ -189,6 +285,86 @@ where
 /// Implementation of [`ConnectionHandler`] that can be in the disabled state.
 pub struct ToggleConnectionHandler<TInner> {
     inner: Option<TInner>,
+    /// Set as long as `inner` is set.
+    registration: Option<Registration>,
+}
+
+/// The registration of an enabled [`ToggleConnectionHandler`] with its [`Toggle`].
+struct Registration {
+    shared: Arc<Mutex<Shared>>,
+    generation: u64,
+    connection_id: ConnectionId,
+}
+
+impl<TInner> ToggleConnectionHandler<TInner> {
+    fn disabled() -> Self {
+        Self {
+            inner: None,
+            registration: None,
+        }
+    }
+
+    fn enabled(inner: TInner, connection_id: ConnectionId, shared: &Arc<Mutex<Shared>>) -> Self {
+        let generation = shared.lock().expect("lock not to be poisoned").generation;
+
+        Self {
+            inner: Some(inner),
+            registration: Some(Registration {
+                shared: shared.clone(),
+                generation,
+                connection_id,
+            }),
+        }
+    }
+
+    /// Whether the [`Toggle`] got disabled since this handler was created.
+    fn is_outdated(&self) -> bool {
+        self.registration.as_ref().is_some_and(|registration| {
+            registration
+                .shared
+                .lock()
+                .expect("lock not to be poisoned")
+                .generation
+                != registration.generation
+        })
+    }
+
+    /// Tears down the inner handler if the [`Toggle`] got disabled, otherwise registers the
+    /// waker to be woken once it does.
+    fn poll_teardown(&mut self, cx: &mut Context<'_>) {
+        let Some(registration) = self.registration.as_ref() else {
+            return;
+        };
+
+        let mut shared = registration.shared.lock().expect("lock not to be poisoned");
+        if shared.generation == registration.generation {
+            shared
+                .wakers
+                .entry(registration.connection_id)
+                .and_modify(|waker| waker.clone_from(cx.waker()))
+                .or_insert_with(|| cx.waker().clone());
+            return;
+        }
+        drop(shared);
+
+        tracing::debug!(
+            connection=%registration.connection_id,
+            "Behaviour got disabled, tearing down its connection handler"
+        );
+        self.registration = None;
+        self.inner = None;
+    }
+}
+
+impl<TInner> Drop for ToggleConnectionHandler<TInner> {
+    fn drop(&mut self) {
+        if let Some(registration) = self.registration.take() {
+            let mut shared = registration.shared.lock().expect("lock not to be poisoned");
+            if shared.generation == registration.generation {
+                shared.wakers.remove(&registration.connection_id);
+            }
+        }
+    }
 }
 
 impl<TInner> ToggleConnectionHandler<TInner>
@@ -210,19 +386,22 @@ where
             future::Either::Right(v) => void::unreachable(v),
         };
 
-        if let Either::Left(info) = info {
-            self.inner
-                .as_mut()
-                .expect("Can't receive an inbound substream if disabled; QED")
-                .on_connection_event(ConnectionEvent::FullyNegotiatedInbound(
-                    FullyNegotiatedInbound {
-                        protocol: out,
-                        info,
-                    },
-                ));
-        } else {
+        let Either::Left(info) = info else {
             panic!("Unexpected Either::Right in enabled `on_fully_negotiated_inbound`.")
-        }
+        };
+
+        // The inner handler may have been torn down while the stream was negotiated.
+        let Some(inner) = self.inner.as_mut() else {
+            tracing::debug!("Dropping inbound stream of disabled behaviour");
+            return;
+        };
+
+        inner.on_connection_event(ConnectionEvent::FullyNegotiatedInbound(
+            FullyNegotiatedInbound {
+                protocol: out,
+                info,
+            },
+        ));
     }
 
     fn on_listen_upgrade_error(
@@ -240,10 +419,8 @@ where
                 "Unexpected `Either::Right` inbound info through \
                  `on_listen_upgrade_error` in enabled state.",
             ),
-            (None, Either::Left(_)) => panic!(
-                "Unexpected `Either::Left` inbound info through \
-                 `on_listen_upgrade_error` in disabled state.",
-            ),
+            // The inner handler may have been torn down while the stream was negotiated.
+            (None, Either::Left(_)) => return,
         };
 
         let err = match err {
@@ -270,7 +447,7 @@ where
     type InboundOpenInfo = Either<TInner::InboundOpenInfo, ()>;
 
     fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
-        if let Some(inner) = self.inner.as_ref() {
+        if let Some(inner) = self.inner.as_ref().filter(|_| !self.is_outdated()) {
             inner
                 .listen_protocol()
                 .map_upgrade(|u| Either::Left(SendWrapper(u)))
@@ -281,13 +458,21 @@ where
     }
 
     fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
-        self.inner
-            .as_mut()
-            .expect("Can't receive events if disabled; QED")
-            .on_behaviour_event(event)
+        // Events may be addressed to a torn down handler, or to the disabled handler of a
+        // connection established before the behaviour got enabled.
+        let Some(inner) = self.inner.as_mut() else {
+            tracing::debug!("Dropping event for disabled behaviour");
+            return;
+        };
+
+        inner.on_behaviour_event(event)
     }
 
     fn connection_keep_alive(&self) -> bool {
+        if self.is_outdated() {
+            return false;
+        }
+
         self.inner
             .as_ref()
             .map(|h| h.connection_keep_alive())
@@ -300,6 +485,8 @@ where
     ) -> Poll<
         ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
     > {
+        self.poll_teardown(cx);
+
         if let Some(inner) = self.inner.as_mut() {
             inner.poll(cx)
         } else {
@@ -323,16 +510,17 @@ where
             ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
                 protocol: out,
                 info,
-            }) => self
-                .inner
-                .as_mut()
-                .expect("Can't receive an outbound substream if disabled; QED")
-                .on_connection_event(ConnectionEvent::FullyNegotiatedOutbound(
-                    FullyNegotiatedOutbound {
-                        protocol: out,
-                        info,
-                    },
-                )),
+            }) => {
+                // The inner handler may have been torn down while the stream was negotiated.
+                if let Some(inner) = self.inner.as_mut() {
+                    inner.on_connection_event(ConnectionEvent::FullyNegotiatedOutbound(
+                        FullyNegotiatedOutbound {
+                            protocol: out,
+                            info,
+                        },
+                    ))
+                }
+            }
             ConnectionEvent::AddressChange(address_change) => {
                 if let Some(inner) = self.inner.as_mut() {
                     inner.on_connection_event(ConnectionEvent::AddressChange(AddressChange {
@@ -340,14 +528,14 @@ where
                     }));
                 }
             }
-            ConnectionEvent::DialUpgradeError(DialUpgradeError { info, error: err }) => self
-                .inner
-                .as_mut()
-                .expect("Can't receive an outbound substream if disabled; QED")
-                .on_connection_event(ConnectionEvent::DialUpgradeError(DialUpgradeError {
-                    info,
-                    error: err,
-                })),
+            ConnectionEvent::DialUpgradeError(DialUpgradeError { info, error: err }) => {
+                if let Some(inner) = self.inner.as_mut() {
+                    inner.on_connection_event(ConnectionEvent::DialUpgradeError(DialUpgradeError {
+                        info,
+                        error: err,
+                    }))
+                }
+            }
             ConnectionEvent::ListenUpgradeError(listen_upgrade_error) => {
                 self.on_listen_upgrade_error(listen_upgrade_error)
             }
@@ -372,3 +560,55 @@ where
         inner.poll_close(cx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dummy;
+    use futures::task::noop_waker_ref;
+
+    #[test]
+    fn disabling_tears_down_existing_handlers() {
+        let mut toggle = Toggle::from(Some(dummy::Behaviour));
+        let mut handler = new_handler(&mut toggle, ConnectionId::new_unchecked(0));
+        assert!(matches!(handler.listen_protocol().info(), Either::Left(())));
+
+        let _ = handler.poll(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(toggle.shared.lock().unwrap().wakers.len(), 1);
+
+        assert!(toggle.disable().is_some());
+        assert!(toggle.shared.lock().unwrap().wakers.is_empty());
+        assert!(matches!(
+            handler.listen_protocol().info(),
+            Either::Right(())
+        ));
+
+        let _ = handler.poll(&mut Context::from_waker(noop_waker_ref()));
+        assert!(handler.inner.is_none());
+    }
+
+    #[test]
+    fn enabling_only_affects_new_connections() {
+        let mut toggle = Toggle::from(None);
+        let mut before = new_handler(&mut toggle, ConnectionId::new_unchecked(0));
+
+        toggle.enable(dummy::Behaviour);
+        let mut after = new_handler(&mut toggle, ConnectionId::new_unchecked(1));
+
+        let _ = before.poll(&mut Context::from_waker(noop_waker_ref()));
+        let _ = after.poll(&mut Context::from_waker(noop_waker_ref()));
+        assert!(before.inner.is_none());
+        assert!(after.inner.is_some());
+    }
+
+    fn new_handler(
+        toggle: &mut Toggle<dummy::Behaviour>,
+        connection_id: ConnectionId,
+    ) -> THandler<Toggle<dummy::Behaviour>> {
+        let addr: Multiaddr = "/memory/1".parse().unwrap();
+
+        toggle
+            .handle_established_inbound_connection(connection_id, PeerId::random(), &addr, &addr)
+            .unwrap()
+    }
+}
@@ -19,6 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 mod error;
+mod negotiation;
 
 pub(crate) mod pool;
 mod supported_protocols;
@@ -27,6 +28,7 @@ pub use error::ConnectionError;
 pub(crate) use error::{
     PendingConnectionError, PendingInboundConnectionError, PendingOutboundConnectionError,
 };
+pub use negotiation::{NegotiatedProtocol, NegotiationOutcome, StreamNegotiation};
 pub use supported_protocols::SupportedProtocols;
 
 use crate::handler::{
@@ -37,21 +39,25 @@ use crate::handler::{
 use crate::stream::ActiveStreamCounter;
 use crate::upgrade::{InboundUpgradeSend, OutboundUpgradeSend};
 use crate::{
-    ConnectionHandlerEvent, Stream, StreamProtocol, StreamUpgradeError, SubstreamProtocol,
+    spans, ConnectionHandlerEvent, Stream, StreamProtocol, StreamUpgradeError, SubstreamProtocol,
 };
 use futures::future::BoxFuture;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
-use futures::{stream, FutureExt};
+use futures::{stream, FutureExt, TryFutureExt};
 use futures_timer::Delay;
 use instant::Instant;
 use libp2p_core::connection::ConnectedPoint;
 use libp2p_core::multiaddr::Multiaddr;
-use libp2p_core::muxing::{StreamMuxerBox, StreamMuxerEvent, StreamMuxerExt, SubstreamBox};
+use libp2p_core::muxing::{
+    StreamMuxerBox, StreamMuxerEvent, StreamMuxerExt, StreamPriority, SubstreamBox,
+};
 use libp2p_core::upgrade;
 use libp2p_core::upgrade::{NegotiationError, ProtocolError};
 use libp2p_core::Endpoint;
 use libp2p_identity::PeerId;
+use multistream_select::NegotiationStats;
+use negotiation::NegotiationReporter;
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::future::Future;
@@ -59,6 +65,7 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::task::Waker;
 use std::time::Duration;
 use std::{fmt, io, mem, pin::Pin, task::Context, task::Poll};
+use tracing::Instrument;
 
 static NEXT_CONNECTION_ID: AtomicUsize = AtomicUsize::new(1);
 
@@ -105,6 +112,12 @@ pub(crate) enum Event<T> {
     Handler(T),
     /// Address of the remote has changed.
     AddressChange(Multiaddr),
+    /// The protocol of a stream has been negotiated.
+    StreamNegotiated(StreamNegotiation),
+    /// A protocol has been negotiated for the first time on the connection.
+    ProtocolNegotiated(NegotiatedProtocol),
+    /// The protocols supported by the remote have changed.
+    RemoteProtocolsChanged(ProtocolSupport),
 }
 
 /// A multiplexed connection to a peer with an associated [`ConnectionHandler`].
@@ -157,6 +170,7 @@ where
     remote_supported_protocols: HashSet<StreamProtocol>,
     idle_timeout: Duration,
     stream_counter: ActiveStreamCounter,
+    negotiations: NegotiationReporter,
 }
 
 impl<THandler> fmt::Debug for Connection<THandler>
@@ -185,6 +199,8 @@ where
         substream_upgrade_protocol_override: Option<upgrade::Version>,
         max_negotiating_inbound_streams: usize,
         idle_timeout: Duration,
+        stream_negotiation_events: bool,
+        slow_negotiation_threshold: Option<Duration>,
     ) -> Self {
         let initial_protocols = gather_supported_protocols(&handler);
         if !initial_protocols.is_empty() {
@@ -205,6 +221,10 @@ where
             remote_supported_protocols: Default::default(),
             idle_timeout,
             stream_counter: ActiveStreamCounter::default(),
+            negotiations: NegotiationReporter::new(
+                stream_negotiation_events,
+                slow_negotiation_threshold,
+            ),
         }
     }
 
@@ -252,10 +272,18 @@ where
             remote_supported_protocols,
             idle_timeout,
             stream_counter,
+            negotiations,
             ..
         } = self.get_mut();
 
         loop {
+            if let Some(negotiation) = negotiations.next() {
+                return Poll::Ready(Ok(Event::StreamNegotiated(negotiation)));
+            }
+            if let Some(negotiated) = negotiations.next_newly_negotiated() {
+                return Poll::Ready(Ok(Event::ProtocolNegotiated(negotiated)));
+            }
+
             match requested_substreams.poll_next_unpin(cx) {
                 Poll::Ready(Some(Ok(()))) => continue,
                 Poll::Ready(Some(Err(info))) => {
@@ -275,9 +303,12 @@ where
                 Poll::Pending => {}
                 Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest { protocol }) => {
                     let timeout = *protocol.timeout();
+                    let priority = protocol.priority();
                     let (upgrade, user_data) = protocol.into_upgrade();
 
-                    requested_substreams.push(SubstreamRequested::new(user_data, timeout, upgrade));
+                    requested_substreams.push(SubstreamRequested::new(
+                        user_data, timeout, upgrade, priority,
+                    ));
                     continue; // Poll handler until exhausted.
                 }
                 Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event)) => {
@@ -290,7 +321,15 @@ where
                         ProtocolsChange::add(remote_supported_protocols, &protocols)
                     {
                         handler.on_connection_event(ConnectionEvent::RemoteProtocolsChange(added));
+                        let added = protocols
+                            .difference(remote_supported_protocols)
+                            .cloned()
+                            .collect();
                         remote_supported_protocols.extend(protocols);
+
+                        return Poll::Ready(Ok(Event::RemoteProtocolsChanged(
+                            ProtocolSupport::Added(added),
+                        )));
                     }
 
                     continue;
@@ -303,7 +342,15 @@ where
                     {
                         handler
                             .on_connection_event(ConnectionEvent::RemoteProtocolsChange(removed));
+                        let removed = protocols
+                            .intersection(remote_supported_protocols)
+                            .cloned()
+                            .collect();
                         remote_supported_protocols.retain(|p| !protocols.contains(p));
+
+                        return Poll::Ready(Ok(Event::RemoteProtocolsChanged(
+                            ProtocolSupport::Removed(removed),
+                        )));
                     }
 
                     continue;
@@ -313,13 +360,15 @@ where
             // In case the [`ConnectionHandler`] can not make any more progress, poll the negotiating outbound streams.
             match negotiating_out.poll_next_unpin(cx) {
                 Poll::Pending | Poll::Ready(None) => {}
-                Poll::Ready(Some((info, Ok(protocol)))) => {
+                Poll::Ready(Some((info, negotiation, Ok(protocol)))) => {
+                    negotiations.report(negotiation);
                     handler.on_connection_event(ConnectionEvent::FullyNegotiatedOutbound(
                         FullyNegotiatedOutbound { protocol, info },
                     ));
                     continue;
                 }
-                Poll::Ready(Some((info, Err(error)))) => {
+                Poll::Ready(Some((info, negotiation, Err(error)))) => {
+                    negotiations.report(negotiation);
                     handler.on_connection_event(ConnectionEvent::DialUpgradeError(
                         DialUpgradeError { info, error },
                     ));
@@ -331,28 +380,30 @@ where
             // make any more progress, poll the negotiating inbound streams.
             match negotiating_in.poll_next_unpin(cx) {
                 Poll::Pending | Poll::Ready(None) => {}
-                Poll::Ready(Some((info, Ok(protocol)))) => {
-                    handler.on_connection_event(ConnectionEvent::FullyNegotiatedInbound(
-                        FullyNegotiatedInbound { protocol, info },
-                    ));
-                    continue;
-                }
-                Poll::Ready(Some((info, Err(StreamUpgradeError::Apply(error))))) => {
-                    handler.on_connection_event(ConnectionEvent::ListenUpgradeError(
-                        ListenUpgradeError { info, error },
-                    ));
-                    continue;
-                }
-                Poll::Ready(Some((_, Err(StreamUpgradeError::Io(e))))) => {
-                    tracing::debug!("failed to upgrade inbound stream: {e}");
-                    continue;
-                }
-                Poll::Ready(Some((_, Err(StreamUpgradeError::NegotiationFailed)))) => {
-                    tracing::debug!("no protocol could be agreed upon for inbound stream");
-                    continue;
-                }
-                Poll::Ready(Some((_, Err(StreamUpgradeError::Timeout)))) => {
-                    tracing::debug!("inbound stream upgrade timed out");
+                Poll::Ready(Some((info, negotiation, result))) => {
+                    negotiations.report(negotiation);
+
+                    match result {
+                        Ok(protocol) => {
+                            handler.on_connection_event(ConnectionEvent::FullyNegotiatedInbound(
+                                FullyNegotiatedInbound { protocol, info },
+                            ));
+                        }
+                        Err(StreamUpgradeError::Apply(error)) => {
+                            handler.on_connection_event(ConnectionEvent::ListenUpgradeError(
+                                ListenUpgradeError { info, error },
+                            ));
+                        }
+                        Err(StreamUpgradeError::Io(e)) => {
+                            tracing::debug!("failed to upgrade inbound stream: {e}");
+                        }
+                        Err(StreamUpgradeError::NegotiationFailed) => {
+                            tracing::debug!("no protocol could be agreed upon for inbound stream");
+                        }
+                        Err(StreamUpgradeError::Timeout) => {
+                            tracing::debug!("inbound stream upgrade timed out");
+                        }
+                    }
                     continue;
                 }
             }
@@ -395,7 +446,9 @@ where
             }
 
             if let Some(requested_substream) = requested_substreams.iter_mut().next() {
-                match muxing.poll_outbound_unpin(cx)? {
+                match muxing
+                    .poll_outbound_with_priority_unpin(cx, requested_substream.priority())?
+                {
                     Poll::Pending => {}
                     Poll::Ready(substream) => {
                         let (user_data, timeout, upgrade) = requested_substream.extract();
@@ -517,7 +570,30 @@ impl<'a> IncomingInfo<'a> {
 struct StreamUpgrade<UserData, TOk, TErr> {
     user_data: Option<UserData>,
     timeout: Delay,
-    upgrade: BoxFuture<'static, Result<TOk, StreamUpgradeError<TErr>>>,
+    endpoint: Endpoint,
+    /// The negotiation of the stream, once completed.
+    negotiation: Option<StreamNegotiation>,
+    state: UpgradeState<TOk, TErr>,
+}
+
+type UpgradeFuture<TOk, TErr> = BoxFuture<'static, Result<TOk, StreamUpgradeError<TErr>>>;
+
+type NegotiationFuture<TOk, TErr> = BoxFuture<
+    'static,
+    (
+        NegotiationStats,
+        Result<(String, UpgradeFuture<TOk, TErr>), StreamUpgradeError<TErr>>,
+    ),
+>;
+
+enum UpgradeState<TOk, TErr> {
+    /// Negotiating the protocol of the stream.
+    Negotiating {
+        started: Instant,
+        future: NegotiationFuture<TOk, TErr>,
+    },
+    /// Applying the upgrade of the negotiated protocol.
+    Upgrading(UpgradeFuture<TOk, TErr>),
 }
 
 impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
@@ -545,27 +621,47 @@ impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
             _ => upgrade::Version::default(),
         };
         let protocols = upgrade.protocol_info();
+        let connection_span = tracing::Span::current();
+        let span = spans::stream_negotiation(Endpoint::Dialer);
 
-        Self {
-            user_data: Some(user_data),
-            timeout,
-            upgrade: Box::pin(async move {
-                let (info, stream) = multistream_select::dialer_select_proto(
+        let negotiation = {
+            let span = span.clone();
+
+            async move {
+                let negotiation = multistream_select::dialer_select_proto(
                     substream,
                     protocols,
                     effective_version,
-                )
-                .await
-                .map_err(to_stream_upgrade_error)?;
+                );
+                futures::pin_mut!(negotiation);
+                let (info, stream) = match negotiation.as_mut().await {
+                    Ok(negotiated) => negotiated,
+                    Err(e) => return (negotiation.stats(), Err(to_stream_upgrade_error(e))),
+                };
 
-                let output = upgrade
-                    .upgrade_outbound(Stream::new(stream, counter), info)
-                    .await
-                    .map_err(StreamUpgradeError::Apply)?;
+                tracing::Span::current().record("libp2p.protocol", info.as_ref());
+                let protocol = info.as_ref().to_owned();
+                let stream_span = spans::stream(&connection_span, Endpoint::Dialer, &protocol);
 
-                Ok(output)
-            }),
-        }
+                let stream = Stream::new(stream, counter, stream_span);
+                let upgrade = async move {
+                    upgrade
+                        .upgrade_outbound(stream, info)
+                        .await
+                        .map_err(StreamUpgradeError::Apply)
+                }
+                .inspect_err({
+                    let span = span.clone();
+                    move |e| spans::record_stream_upgrade_error(&span, e)
+                })
+                .instrument(span)
+                .boxed();
+
+                (negotiation.stats(), Ok((protocol, upgrade)))
+            }
+        };
+
+        Self::new(user_data, timeout, Endpoint::Dialer, negotiation, span)
     }
 }
 
@@ -581,23 +677,90 @@ impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
         let timeout = *protocol.timeout();
         let (upgrade, open_info) = protocol.into_upgrade();
         let protocols = upgrade.protocol_info();
+        let connection_span = tracing::Span::current();
+        let span = spans::stream_negotiation(Endpoint::Listener);
+
+        let negotiation = {
+            let span = span.clone();
+
+            async move {
+                let negotiation = multistream_select::listener_select_proto(substream, protocols);
+                futures::pin_mut!(negotiation);
+                let (info, stream) = match negotiation.as_mut().await {
+                    Ok(negotiated) => negotiated,
+                    Err(e) => return (negotiation.stats(), Err(to_stream_upgrade_error(e))),
+                };
 
-        Self {
-            user_data: Some(open_info),
-            timeout: Delay::new(timeout),
-            upgrade: Box::pin(async move {
-                let (info, stream) =
-                    multistream_select::listener_select_proto(substream, protocols)
+                tracing::Span::current().record("libp2p.protocol", info.as_ref());
+                let protocol = info.as_ref().to_owned();
+                let stream_span = spans::stream(&connection_span, Endpoint::Listener, &protocol);
+
+                let stream = Stream::new(stream, counter, stream_span);
+                let upgrade = async move {
+                    upgrade
+                        .upgrade_inbound(stream, info)
                         .await
-                        .map_err(to_stream_upgrade_error)?;
+                        .map_err(StreamUpgradeError::Apply)
+                }
+                .inspect_err({
+                    let span = span.clone();
+                    move |e| spans::record_stream_upgrade_error(&span, e)
+                })
+                .instrument(span)
+                .boxed();
+
+                (negotiation.stats(), Ok((protocol, upgrade)))
+            }
+        };
 
-                let output = upgrade
-                    .upgrade_inbound(Stream::new(stream, counter), info)
-                    .await
-                    .map_err(StreamUpgradeError::Apply)?;
+        Self::new(
+            open_info,
+            Delay::new(timeout),
+            Endpoint::Listener,
+            negotiation,
+            span,
+        )
+    }
+}
 
-                Ok(output)
-            }),
+impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
+    fn new<F>(
+        user_data: UserData,
+        timeout: Delay,
+        endpoint: Endpoint,
+        negotiation: F,
+        span: tracing::Span,
+    ) -> Self
+    where
+        F: Future<
+                Output = (
+                    NegotiationStats,
+                    Result<(String, UpgradeFuture<TOk, TErr>), StreamUpgradeError<TErr>>,
+                ),
+            > + Send
+            + 'static,
+    {
+        let future = negotiation
+            .inspect({
+                let span = span.clone();
+                move |(_, result)| {
+                    if let Err(e) = result {
+                        spans::record_stream_upgrade_error(&span, e)
+                    }
+                }
+            })
+            .instrument(span)
+            .boxed();
+
+        Self {
+            user_data: Some(user_data),
+            timeout,
+            endpoint,
+            negotiation: None,
+            state: UpgradeState::Negotiating {
+                started: Instant::now(),
+                future,
+            },
         }
     }
 }
@@ -615,29 +778,90 @@ fn to_stream_upgrade_error<T>(e: NegotiationError) -> StreamUpgradeError<T> {
 impl<UserData, TOk, TErr> Unpin for StreamUpgrade<UserData, TOk, TErr> {}
 
 impl<UserData, TOk, TErr> Future for StreamUpgrade<UserData, TOk, TErr> {
-    type Output = (UserData, Result<TOk, StreamUpgradeError<TErr>>);
+    type Output = (
+        UserData,
+        StreamNegotiation,
+        Result<TOk, StreamUpgradeError<TErr>>,
+    );
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-        match self.timeout.poll_unpin(cx) {
+        let this = &mut *self;
+
+        match this.timeout.poll_unpin(cx) {
             Poll::Ready(()) => {
+                let negotiation = match &this.state {
+                    UpgradeState::Negotiating { started, .. } => StreamNegotiation {
+                        endpoint: this.endpoint,
+                        outcome: NegotiationOutcome::Timeout,
+                        duration: started.elapsed(),
+                        rejected_protocols: 0,
+                        ls_requests: 0,
+                    },
+                    UpgradeState::Upgrading(_) => this
+                        .negotiation
+                        .take()
+                        .expect("negotiation to be completed when upgrading"),
+                };
+
                 return Poll::Ready((
-                    self.user_data
+                    this.user_data
                         .take()
                         .expect("Future not to be polled again once ready."),
+                    negotiation,
                     Err(StreamUpgradeError::Timeout),
-                ))
+                ));
             }
 
             Poll::Pending => {}
         }
 
-        let result = futures::ready!(self.upgrade.poll_unpin(cx));
-        let user_data = self
-            .user_data
-            .take()
-            .expect("Future not to be polled again once ready.");
+        loop {
+            match &mut this.state {
+                UpgradeState::Negotiating { started, future } => {
+                    let (stats, result) = futures::ready!(future.poll_unpin(cx));
+                    let outcome = match &result {
+                        Ok((protocol, _)) => NegotiationOutcome::Negotiated(protocol.clone()),
+                        Err(StreamUpgradeError::NegotiationFailed) => NegotiationOutcome::Failed,
+                        Err(_) => NegotiationOutcome::Error,
+                    };
+                    let negotiation = StreamNegotiation {
+                        endpoint: this.endpoint,
+                        outcome,
+                        duration: started.elapsed(),
+                        rejected_protocols: stats.rejected_protocols,
+                        ls_requests: stats.ls_requests,
+                    };
 
-        Poll::Ready((user_data, result))
+                    match result {
+                        Ok((_, upgrade)) => {
+                            this.negotiation = Some(negotiation);
+                            this.state = UpgradeState::Upgrading(upgrade);
+                        }
+                        Err(e) => {
+                            let user_data = this
+                                .user_data
+                                .take()
+                                .expect("Future not to be polled again once ready.");
+
+                            return Poll::Ready((user_data, negotiation, Err(e)));
+                        }
+                    }
+                }
+                UpgradeState::Upgrading(upgrade) => {
+                    let result = futures::ready!(upgrade.poll_unpin(cx));
+                    let user_data = this
+                        .user_data
+                        .take()
+                        .expect("Future not to be polled again once ready.");
+                    let negotiation = this
+                        .negotiation
+                        .take()
+                        .expect("negotiation to be completed when upgrading");
+
+                    return Poll::Ready((user_data, negotiation, result));
+                }
+            }
+        }
     }
 }
 
@@ -646,6 +870,7 @@ enum SubstreamRequested<UserData, Upgrade> {
         user_data: UserData,
         timeout: Delay,
         upgrade: Upgrade,
+        priority: StreamPriority,
         /// A waker to notify our [`FuturesUnordered`] that we have extracted the data.
         ///
         /// This will ensure that we will get polled again in the next iteration which allows us to
@@ -656,15 +881,28 @@ enum SubstreamRequested<UserData, Upgrade> {
 }
 
 impl<UserData, Upgrade> SubstreamRequested<UserData, Upgrade> {
-    fn new(user_data: UserData, timeout: Duration, upgrade: Upgrade) -> Self {
+    fn new(
+        user_data: UserData,
+        timeout: Duration,
+        upgrade: Upgrade,
+        priority: StreamPriority,
+    ) -> Self {
         Self::Waiting {
             user_data,
             timeout: Delay::new(timeout),
             upgrade,
+            priority,
             extracted_waker: None,
         }
     }
 
+    fn priority(&self) -> StreamPriority {
+        match self {
+            SubstreamRequested::Waiting { priority, .. } => *priority,
+            SubstreamRequested::Done => StreamPriority::DEFAULT,
+        }
+    }
+
     fn extract(&mut self) -> (UserData, Delay, Upgrade) {
         match mem::replace(self, Self::Done) {
             SubstreamRequested::Waiting {
@@ -672,6 +910,7 @@ impl<UserData, Upgrade> SubstreamRequested<UserData, Upgrade> {
                 timeout,
                 upgrade,
                 extracted_waker: waker,
+                ..
             } => {
                 if let Some(waker) = waker {
                     waker.wake();
@@ -697,6 +936,7 @@ impl<UserData, Upgrade> Future for SubstreamRequested<UserData, Upgrade> {
                 user_data,
                 upgrade,
                 mut timeout,
+                priority,
                 ..
             } => match timeout.poll_unpin(cx) {
                 Poll::Ready(()) => Poll::Ready(Err(user_data)),
@@ -705,6 +945,7 @@ impl<UserData, Upgrade> Future for SubstreamRequested<UserData, Upgrade> {
                         user_data,
                         upgrade,
                         timeout,
+                        priority,
                         extracted_waker: Some(cx.waker().clone()),
                     };
                     Poll::Pending
@@ -744,7 +985,7 @@ mod tests {
     use libp2p_core::upgrade::{DeniedUpgrade, InboundUpgrade, OutboundUpgrade, UpgradeInfo};
     use libp2p_core::StreamMuxer;
     use quickcheck::*;
-    use std::sync::{Arc, Weak};
+    use std::sync::{Arc, Mutex, Weak};
     use std::time::Instant;
     use tracing_subscriber::EnvFilter;
     use void::Void;
@@ -767,6 +1008,8 @@ mod tests {
                 None,
                 max_negotiating_inbound_streams,
                 Duration::ZERO,
+                false,
+                None,
             );
 
             let result = connection.poll_noop_waker();
@@ -791,6 +1034,8 @@ mod tests {
             None,
             2,
             Duration::ZERO,
+            false,
+            None,
         );
 
         connection.handler.open_new_outbound();
@@ -806,6 +1051,28 @@ mod tests {
         ))
     }
 
+    #[test]
+    fn outbound_stream_is_opened_with_requested_priority() {
+        let requested = Arc::new(Mutex::new(None));
+        let mut connection = Connection::new(
+            StreamMuxerBox::new(PriorityRecordingStreamMuxer {
+                requested: requested.clone(),
+            }),
+            MockConnectionHandler::new(Duration::from_secs(10)),
+            None,
+            2,
+            Duration::ZERO,
+            false,
+            None,
+        );
+
+        connection.handler.outbound_priority = StreamPriority::HIGH;
+        connection.handler.open_new_outbound();
+        let _ = connection.poll_noop_waker();
+
+        assert_eq!(*requested.lock().unwrap(), Some(StreamPriority::HIGH));
+    }
+
     #[test]
     fn propagates_changes_to_supported_inbound_protocols() {
         let mut connection = Connection::new(
@@ -814,6 +1081,8 @@ mod tests {
             None,
             0,
             Duration::ZERO,
+            false,
+            None,
         );
 
         // First, start listening on a single protocol.
@@ -853,6 +1122,8 @@ mod tests {
             None,
             0,
             Duration::ZERO,
+            false,
+            None,
         );
 
         // First, remote supports a single protocol.
@@ -906,6 +1177,8 @@ mod tests {
             None,
             0,
             idle_timeout,
+            false,
+            None,
         );
 
         assert!(connection.poll_noop_waker().is_pending());
@@ -1048,6 +1321,51 @@ mod tests {
         }
     }
 
+    /// A [`StreamMuxer`] which never returns a stream but records the priority of the last
+    /// requested outbound stream.
+    struct PriorityRecordingStreamMuxer {
+        requested: Arc<Mutex<Option<StreamPriority>>>,
+    }
+
+    impl StreamMuxer for PriorityRecordingStreamMuxer {
+        type Substream = PendingSubstream;
+        type Error = Void;
+
+        fn poll_inbound(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<Self::Substream, Self::Error>> {
+            Poll::Pending
+        }
+
+        fn poll_outbound(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Result<Self::Substream, Self::Error>> {
+            self.poll_outbound_with_priority(cx, StreamPriority::DEFAULT)
+        }
+
+        fn poll_outbound_with_priority(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            priority: StreamPriority,
+        ) -> Poll<Result<Self::Substream, Self::Error>> {
+            *self.requested.lock().unwrap() = Some(priority);
+            Poll::Pending
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Pending
+        }
+
+        fn poll(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+            Poll::Pending
+        }
+    }
+
     struct PendingSubstream {
         _weak: Weak<()>,
     }
@@ -1082,6 +1400,7 @@ mod tests {
 
     struct MockConnectionHandler {
         outbound_requested: bool,
+        outbound_priority: StreamPriority,
         error: Option<StreamUpgradeError<Void>>,
         upgrade_timeout: Duration,
     }
@@ -1090,6 +1409,7 @@ mod tests {
         fn new(upgrade_timeout: Duration) -> Self {
             Self {
                 outbound_requested: false,
+                outbound_priority: StreamPriority::DEFAULT,
                 error: None,
                 upgrade_timeout,
             }
@@ -1198,7 +1518,8 @@ mod tests {
                 self.outbound_requested = false;
                 return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
                     protocol: SubstreamProtocol::new(DeniedUpgrade, ())
-                        .with_timeout(self.upgrade_timeout),
+                        .with_timeout(self.upgrade_timeout)
+                        .with_priority(self.outbound_priority),
                 });
             }
 
@@ -0,0 +1,116 @@
+use instant::Instant;
+use libp2p_core::Endpoint;
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+/// The protocol negotiation of a stream on a connection.
+///
+/// Reported via [`SwarmEvent::StreamNegotiated`](crate::SwarmEvent::StreamNegotiated) if enabled
+/// via [`Config::with_stream_negotiation_events`](crate::Config::with_stream_negotiation_events).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamNegotiation {
+    /// Whether the stream was opened by us ([`Endpoint::Dialer`]) or by the remote
+    /// ([`Endpoint::Listener`]).
+    pub endpoint: Endpoint,
+    /// The outcome of the negotiation.
+    pub outcome: NegotiationOutcome,
+    /// How long the negotiation took.
+    pub duration: Duration,
+    /// The number of protocols proposed by the dialer and rejected by the listener before the
+    /// negotiation completed, i.e. how often the dialer fell back to its next protocol.
+    pub rejected_protocols: usize,
+    /// The number of `ls` requests for the protocols we support, received as listener.
+    pub ls_requests: usize,
+}
+
+/// The outcome of a [`StreamNegotiation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NegotiationOutcome {
+    /// The given protocol was agreed upon.
+    Negotiated(String),
+    /// No protocol could be agreed upon.
+    Failed,
+    /// The negotiation did not complete within the upgrade timeout of the stream.
+    Timeout,
+    /// The negotiation failed with an I/O or protocol error.
+    Error,
+}
+
+/// A protocol successfully negotiated on a connection.
+///
+/// Part of the [`ConnectionDetails`](crate::ConnectionDetails) of a connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegotiatedProtocol {
+    /// The negotiated protocol.
+    pub protocol: String,
+    /// Whether the protocol was negotiated on streams opened by us ([`Endpoint::Dialer`]) or by
+    /// the remote ([`Endpoint::Listener`]).
+    pub endpoint: Endpoint,
+    /// When the protocol was first negotiated on the connection.
+    pub first_negotiated: Instant,
+}
+
+/// Collects the [`StreamNegotiation`]s of a connection to be reported.
+#[derive(Debug)]
+pub(crate) struct NegotiationReporter {
+    events_enabled: bool,
+    slow_threshold: Option<Duration>,
+    pending: VecDeque<StreamNegotiation>,
+    /// The protocols negotiated so far, per endpoint.
+    negotiated: HashSet<(String, Endpoint)>,
+    newly_negotiated: VecDeque<NegotiatedProtocol>,
+}
+
+impl NegotiationReporter {
+    pub(crate) fn new(events_enabled: bool, slow_threshold: Option<Duration>) -> Self {
+        Self {
+            events_enabled,
+            slow_threshold,
+            pending: VecDeque::new(),
+            negotiated: HashSet::new(),
+            newly_negotiated: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn report(&mut self, negotiation: StreamNegotiation) {
+        if self
+            .slow_threshold
+            .is_some_and(|threshold| negotiation.duration > threshold)
+        {
+            tracing::warn!(
+                endpoint=?negotiation.endpoint,
+                outcome=?negotiation.outcome,
+                duration=?negotiation.duration,
+                rejected_protocols=%negotiation.rejected_protocols,
+                ls_requests=%negotiation.ls_requests,
+                "Slow stream protocol negotiation"
+            );
+        }
+
+        if let NegotiationOutcome::Negotiated(protocol) = &negotiation.outcome {
+            if self
+                .negotiated
+                .insert((protocol.clone(), negotiation.endpoint))
+            {
+                self.newly_negotiated.push_back(NegotiatedProtocol {
+                    protocol: protocol.clone(),
+                    endpoint: negotiation.endpoint,
+                    first_negotiated: Instant::now(),
+                });
+            }
+        }
+
+        if self.events_enabled {
+            self.pending.push_back(negotiation);
+        }
+    }
+
+    pub(crate) fn next(&mut self) -> Option<StreamNegotiation> {
+        self.pending.pop_front()
+    }
+
+    /// The next protocol negotiated for the first time on the connection.
+    pub(crate) fn next_newly_negotiated(&mut self) -> Option<NegotiatedProtocol> {
+        self.newly_negotiated.pop_front()
+    }
+}
@@ -18,14 +18,17 @@
 // LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
-use crate::connection::{Connection, ConnectionId, PendingPoint};
+use crate::connection::{Connection, ConnectionId, PendingPoint, StreamNegotiation};
 use crate::{
     connection::{
-        Connected, ConnectionError, IncomingInfo, PendingConnectionError,
+        Connected, ConnectionError, IncomingInfo, NegotiatedProtocol, PendingConnectionError,
         PendingInboundConnectionError, PendingOutboundConnectionError,
     },
+    handler::ProtocolSupport,
+    spans,
     transport::TransportError,
-    ConnectedPoint, ConnectionHandler, Executor, Multiaddr, PeerId,
+    ConnectedPoint, ConnectionDetails, ConnectionHandler, DialReport, Executor, Multiaddr, PeerId,
+    StreamProtocol,
 };
 use concurrent_dial::ConcurrentDial;
 use fnv::FnvHashMap;
@@ -33,7 +36,7 @@ use futures::prelude::*;
 use futures::stream::SelectAll;
 use futures::{
     channel::{mpsc, oneshot},
-    future::{poll_fn, BoxFuture, Either},
+    future::{poll_fn, Either},
     ready,
     stream::FuturesUnordered,
 };
@@ -42,7 +45,7 @@ use libp2p_core::connection::Endpoint;
 use libp2p_core::muxing::{StreamMuxerBox, StreamMuxerExt};
 use std::task::Waker;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt,
     num::{NonZeroU8, NonZeroUsize},
     pin::Pin,
@@ -139,11 +142,21 @@ where
 
     /// How long a connection should be kept alive once it starts idling.
     idle_connection_timeout: Duration,
+
+    /// Whether to report the protocol negotiations of streams.
+    stream_negotiation_events: bool,
+
+    /// The duration after which a stream protocol negotiation is logged as slow, if any.
+    slow_negotiation_threshold: Option<Duration>,
 }
 
 #[derive(Debug)]
 pub(crate) struct EstablishedConnection<TInEvent> {
     endpoint: ConnectedPoint,
+    /// The protocols negotiated on the connection so far, in the order they were first negotiated.
+    negotiated_protocols: Vec<NegotiatedProtocol>,
+    /// The protocols the remote supports, as reported by the connection handler.
+    remote_supported_protocols: HashSet<StreamProtocol>,
     /// Channel endpoint to send commands to the task.
     sender: mpsc::Sender<task::Command<TInEvent>>,
 }
@@ -237,6 +250,9 @@ pub(crate) enum PoolEvent<ToBehaviour> {
         /// Addresses are dialed in parallel. Contains the addresses and errors
         /// of dial attempts that failed before the one successful dial.
         concurrent_dial_errors: Option<Vec<(Multiaddr, TransportError<std::io::Error>)>>,
+        /// [`Some`] when the new connection is an outgoing connection.
+        /// The report of all dial attempts of the connection.
+        dial_report: Option<DialReport>,
         /// How long it took to establish this connection.
         established_in: std::time::Duration,
     },
@@ -271,6 +287,8 @@ pub(crate) enum PoolEvent<ToBehaviour> {
         error: PendingOutboundConnectionError,
         /// The (expected) peer of the failed connection.
         peer: Option<PeerId>,
+        /// The report of all dial attempts of the failed connection.
+        dial_report: DialReport,
     },
 
     /// An inbound connection attempt failed.
@@ -302,6 +320,20 @@ pub(crate) enum PoolEvent<ToBehaviour> {
         /// The old endpoint.
         old_endpoint: ConnectedPoint,
     },
+
+    /// The protocol of a stream on a connection has been negotiated.
+    StreamNegotiated {
+        id: ConnectionId,
+        peer_id: PeerId,
+        negotiation: StreamNegotiation,
+    },
+
+    /// The protocols supported by the remote of a connection have changed.
+    RemoteProtocolsChanged {
+        id: ConnectionId,
+        peer_id: PeerId,
+        change: ProtocolSupport,
+    },
 }
 
 impl<THandler> Pool<THandler>
@@ -326,6 +358,8 @@ where
             max_negotiating_inbound_streams: config.max_negotiating_inbound_streams,
             per_connection_event_buffer_size: config.per_connection_event_buffer_size,
             idle_connection_timeout: config.idle_connection_timeout,
+            stream_negotiation_events: config.stream_negotiation_events,
+            slow_negotiation_threshold: config.slow_negotiation_threshold,
             executor,
             pending_connection_events_tx,
             pending_connection_events_rx,
@@ -350,6 +384,19 @@ where
             .find_map(|connections| connections.get_mut(&id))
     }
 
+    /// Returns information about an established connection.
+    pub(crate) fn connection_info(&self, id: ConnectionId) -> Option<ConnectionDetails> {
+        self.established.iter().find_map(|(peer_id, connections)| {
+            let connection = connections.get(&id)?;
+            Some(ConnectionDetails {
+                peer_id: *peer_id,
+                endpoint: connection.endpoint.clone(),
+                negotiated_protocols: connection.negotiated_protocols.clone(),
+                remote_supported_protocols: connection.remote_supported_protocols.clone(),
+            })
+        })
+    }
+
     /// Returns true if we are connected to the given peer.
     ///
     /// This will return true only after a `NodeReached` event has been produced by `poll()`.
@@ -410,25 +457,21 @@ where
 
     /// Adds a pending outgoing connection to the pool in the form of a `Future`
     /// that establishes and negotiates the connection.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn add_outgoing(
         &mut self,
-        dials: Vec<
-            BoxFuture<
-                'static,
-                (
-                    Multiaddr,
-                    Result<(PeerId, StreamMuxerBox), TransportError<std::io::Error>>,
-                ),
-            >,
-        >,
+        dials: Vec<(Multiaddr, concurrent_dial::Dial)>,
+        fallback_dials: Vec<(Multiaddr, concurrent_dial::Dial)>,
         peer: Option<PeerId>,
         role_override: Endpoint,
         dial_concurrency_factor_override: Option<NonZeroU8>,
+        stagger_delay: Option<Duration>,
         connection_id: ConnectionId,
+        connection_span: tracing::Span,
     ) {
         let concurrency_factor =
             dial_concurrency_factor_override.unwrap_or(self.dial_concurrency_factor);
-        let span = tracing::debug_span!(parent: tracing::Span::none(), "new_outgoing_connection", %concurrency_factor, num_dials=%dials.len(), id = %connection_id);
+        let span = tracing::debug_span!(parent: tracing::Span::none(), "new_outgoing_connection", %concurrency_factor, num_dials=%dials.len(), num_fallback_dials=%fallback_dials.len(), id = %connection_id);
         span.follows_from(tracing::Span::current());
 
         let (abort_notifier, abort_receiver) = oneshot::channel();
@@ -436,11 +479,12 @@ where
         self.executor.spawn(
             task::new_for_pending_outgoing_connection(
                 connection_id,
-                ConcurrentDial::new(dials, concurrency_factor),
+                ConcurrentDial::new(dials, fallback_dials, concurrency_factor, stagger_delay),
                 abort_receiver,
                 self.pending_connection_events_tx.clone(),
             )
-            .instrument(span),
+            .instrument(span)
+            .instrument(connection_span),
         );
 
         let endpoint = PendingPoint::Dialer { role_override };
@@ -481,7 +525,12 @@ where
                 abort_receiver,
                 self.pending_connection_events_tx.clone(),
             )
-            .instrument(span),
+            .instrument(span)
+            .instrument(spans::incoming_connection(
+                connection_id,
+                info.local_addr,
+                info.send_back_addr,
+            )),
         );
 
         self.counters.inc_pending_incoming();
@@ -515,6 +564,8 @@ where
             id,
             EstablishedConnection {
                 endpoint: endpoint.clone(),
+                negotiated_protocols: Vec::new(),
+                remote_supported_protocols: HashSet::new(),
                 sender: command_sender,
             },
         );
@@ -529,6 +580,8 @@ where
             self.substream_upgrade_protocol_override,
             self.max_negotiating_inbound_streams,
             self.idle_connection_timeout,
+            self.stream_negotiation_events,
+            self.slow_negotiation_threshold,
         );
 
         let span = tracing::debug_span!(parent: tracing::Span::none(), "new_established_connection", remote_addr = %endpoint.get_remote_address(), %id, peer = %obtained_peer_id);
@@ -542,7 +595,12 @@ where
                 command_receiver,
                 event_sender,
             )
-            .instrument(span),
+            .instrument(span)
+            .instrument(spans::established_connection(
+                id,
+                obtained_peer_id,
+                endpoint,
+            )),
         )
     }
 
@@ -589,6 +647,60 @@ where
                     old_endpoint,
                 });
             }
+            Poll::Ready(Some(task::EstablishedConnectionEvent::StreamNegotiated {
+                id,
+                peer_id,
+                negotiation,
+            })) => {
+                return Poll::Ready(PoolEvent::StreamNegotiated {
+                    peer_id,
+                    id,
+                    negotiation,
+                });
+            }
+            Poll::Ready(Some(task::EstablishedConnectionEvent::ProtocolNegotiated {
+                id,
+                peer_id,
+                negotiated,
+            })) => {
+                self.established
+                    .get_mut(&peer_id)
+                    .expect("Receive `ProtocolNegotiated` event for established peer.")
+                    .get_mut(&id)
+                    .expect("Receive `ProtocolNegotiated` event from established connection")
+                    .negotiated_protocols
+                    .push(negotiated);
+
+                // Nothing to report, make sure we are polled again for the next event.
+                cx.waker().wake_by_ref();
+            }
+            Poll::Ready(Some(task::EstablishedConnectionEvent::RemoteProtocolsChanged {
+                id,
+                peer_id,
+                change,
+            })) => {
+                let remote_supported_protocols = &mut self
+                    .established
+                    .get_mut(&peer_id)
+                    .expect("Receive `RemoteProtocolsChanged` event for established peer.")
+                    .get_mut(&id)
+                    .expect("Receive `RemoteProtocolsChanged` event from established connection")
+                    .remote_supported_protocols;
+                match &change {
+                    ProtocolSupport::Added(added) => {
+                        remote_supported_protocols.extend(added.iter().cloned())
+                    }
+                    ProtocolSupport::Removed(removed) => {
+                        remote_supported_protocols.retain(|p| !removed.contains(p))
+                    }
+                }
+
+                return Poll::Ready(PoolEvent::RemoteProtocolsChanged {
+                    peer_id,
+                    id,
+                    change,
+                });
+            }
             Poll::Ready(Some(task::EstablishedConnectionEvent::Closed { id, peer_id, error })) => {
                 let connections = self
                     .established
@@ -648,13 +760,18 @@ where
 
                     self.counters.dec_pending(&endpoint);
 
-                    let (endpoint, concurrent_dial_errors) = match (endpoint, outgoing) {
-                        (PendingPoint::Dialer { role_override }, Some((address, errors))) => (
+                    let (endpoint, concurrent_dial_errors, dial_report) = match (endpoint, outgoing)
+                    {
+                        (
+                            PendingPoint::Dialer { role_override },
+                            Some((address, errors, dial_report)),
+                        ) => (
                             ConnectedPoint::Dialer {
                                 address,
                                 role_override,
                             },
                             Some(errors),
+                            Some(dial_report),
                         ),
                         (
                             PendingPoint::Listener {
@@ -668,6 +785,7 @@ where
                                 send_back_addr,
                             },
                             None,
+                            None,
                         ),
                         (PendingPoint::Dialer { .. }, None) => unreachable!(
                             "Established incoming connection via pending outgoing connection."
@@ -716,6 +834,7 @@ where
                                     error: error
                                         .map(|t| vec![(endpoint.get_remote_address().clone(), t)]),
                                     peer: expected_peer_id.or(Some(obtained_peer_id)),
+                                    dial_report: dial_report.unwrap_or_default(),
                                 })
                             }
                             ConnectedPoint::Listener {
@@ -743,10 +862,15 @@ where
                         id,
                         connection,
                         concurrent_dial_errors,
+                        dial_report,
                         established_in,
                     });
                 }
-                task::PendingConnectionEvent::PendingFailed { id, error } => {
+                task::PendingConnectionEvent::PendingFailed {
+                    id,
+                    error,
+                    dial_report,
+                } => {
                     if let Some(PendingConnection {
                         peer_id,
                         endpoint,
@@ -762,6 +886,7 @@ where
                                     id,
                                     error,
                                     peer: peer_id,
+                                    dial_report: dial_report.unwrap_or_default(),
                                 });
                             }
                             (
@@ -968,6 +1093,10 @@ pub(crate) struct PoolConfig {
     ///
     /// See [`Connection::max_negotiating_inbound_streams`].
     max_negotiating_inbound_streams: usize,
+    /// Whether to report the protocol negotiations of streams.
+    pub(crate) stream_negotiation_events: bool,
+    /// The duration after which a stream protocol negotiation is logged as slow, if any.
+    pub(crate) slow_negotiation_threshold: Option<Duration>,
 }
 
 impl PoolConfig {
@@ -980,6 +1109,8 @@ impl PoolConfig {
             idle_connection_timeout: Duration::ZERO,
             substream_upgrade_protocol_override: None,
             max_negotiating_inbound_streams: 128,
+            stream_negotiation_events: false,
+            slow_negotiation_threshold: None,
         }
     }
 
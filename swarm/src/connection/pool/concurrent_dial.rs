@@ -18,53 +18,109 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::{transport::TransportError, Multiaddr};
+use crate::{dial_report::DialReportRecorder, transport::TransportError, DialReport, Multiaddr};
 use futures::{
-    future::{BoxFuture, Future},
-    ready,
+    future::{BoxFuture, Future, FutureExt},
     stream::{FuturesUnordered, StreamExt},
 };
+use futures_timer::Delay;
 use libp2p_core::muxing::StreamMuxerBox;
 use libp2p_identity::PeerId;
 use std::{
     num::NonZeroU8,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 
-type Dial = BoxFuture<
-    'static,
-    (
-        Multiaddr,
-        Result<(PeerId, StreamMuxerBox), TransportError<std::io::Error>>,
-    ),
->;
+pub(crate) type Dial =
+    BoxFuture<'static, Result<(PeerId, StreamMuxerBox), TransportError<std::io::Error>>>;
 
 pub(crate) struct ConcurrentDial {
-    dials: FuturesUnordered<Dial>,
-    pending_dials: Box<dyn Iterator<Item = Dial> + Send>,
+    dials: FuturesUnordered<
+        BoxFuture<
+            'static,
+            (
+                usize,
+                Result<(PeerId, StreamMuxerBox), TransportError<std::io::Error>>,
+            ),
+        >,
+    >,
+    pending_dials: Box<dyn Iterator<Item = (Multiaddr, Dial)> + Send>,
+    /// Dials only started once all of `pending_dials` failed.
+    fallback_dials: Vec<(Multiaddr, Dial)>,
+    concurrency_factor: NonZeroU8,
+    /// The delay after which the next dial is started and its timer, if dials are staggered.
+    stagger: Option<(Duration, Delay)>,
     errors: Vec<(Multiaddr, TransportError<std::io::Error>)>,
+    report: DialReportRecorder,
 }
 
 impl Unpin for ConcurrentDial {}
 
 impl ConcurrentDial {
-    pub(crate) fn new(pending_dials: Vec<Dial>, concurrency_factor: NonZeroU8) -> Self {
-        let mut pending_dials = pending_dials.into_iter();
+    pub(crate) fn new(
+        pending_dials: Vec<(Multiaddr, Dial)>,
+        fallback_dials: Vec<(Multiaddr, Dial)>,
+        concurrency_factor: NonZeroU8,
+        stagger_delay: Option<Duration>,
+    ) -> Self {
+        let mut this = Self {
+            dials: FuturesUnordered::new(),
+            errors: Default::default(),
+            pending_dials: Box::new(pending_dials.into_iter()),
+            fallback_dials,
+            concurrency_factor,
+            stagger: stagger_delay.map(|delay| (delay, Delay::new(delay))),
+            report: DialReportRecorder::new(),
+        };
+
+        this.start_initial_dials();
+
+        this
+    }
 
-        let dials = FuturesUnordered::new();
-        for dial in pending_dials.by_ref() {
-            dials.push(dial);
-            if dials.len() == concurrency_factor.get() as usize {
+    fn start_initial_dials(&mut self) {
+        // Staggered dials are started one after the other.
+        let initial_dials = if self.stagger.is_some() {
+            1
+        } else {
+            self.concurrency_factor.get() as usize
+        };
+        while self.dials.len() < initial_dials {
+            if !self.start_next_dial() {
                 break;
             }
         }
+    }
 
-        Self {
-            dials,
-            errors: Default::default(),
-            pending_dials: Box::new(pending_dials),
+    /// Starts the fallback dials once all other dials failed.
+    ///
+    /// Returns `false` if there are no fallback dials.
+    fn start_fallback_dials(&mut self) -> bool {
+        if self.fallback_dials.is_empty() {
+            return false;
+        }
+        self.pending_dials = Box::new(std::mem::take(&mut self.fallback_dials).into_iter());
+        self.start_initial_dials();
+        true
+    }
+
+    /// Returns the report of all dials started so far.
+    pub(crate) fn take_report(&mut self) -> DialReport {
+        self.report.report()
+    }
+
+    fn start_next_dial(&mut self) -> bool {
+        let Some((address, dial)) = self.pending_dials.next() else {
+            return false;
+        };
+        let index = self.report.start(address);
+        self.dials.push(dial.map(move |r| (index, r)).boxed());
+        if let Some((delay, timer)) = self.stagger.as_mut() {
+            timer.reset(*delay);
         }
+        true
     }
 }
 
@@ -83,21 +139,116 @@ impl Future for ConcurrentDial {
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         loop {
-            match ready!(self.dials.poll_next_unpin(cx)) {
-                Some((addr, Ok(output))) => {
+            let next = match self.dials.poll_next_unpin(cx) {
+                Poll::Ready(next) => next,
+                Poll::Pending => {
+                    let this = &mut *self;
+                    let stagger_elapsed = this
+                        .stagger
+                        .as_mut()
+                        .is_some_and(|(_, timer)| timer.poll_unpin(cx).is_ready());
+                    if stagger_elapsed
+                        && this.dials.len() < this.concurrency_factor.get() as usize
+                        && this.start_next_dial()
+                    {
+                        continue;
+                    }
+                    return Poll::Pending;
+                }
+            };
+            match next {
+                Some((index, Ok(output))) => {
+                    let addr = self.report.finish(index, Ok(()));
                     let errors = std::mem::take(&mut self.errors);
                     return Poll::Ready(Ok((addr, output, errors)));
                 }
-                Some((addr, Err(e))) => {
+                Some((index, Err(e))) => {
+                    let addr = self.report.finish(index, Err(&e));
                     self.errors.push((addr, e));
-                    if let Some(dial) = self.pending_dials.next() {
-                        self.dials.push(dial)
-                    }
+                    self.start_next_dial();
                 }
                 None => {
+                    if self.start_fallback_dials() {
+                        continue;
+                    }
                     return Poll::Ready(Err(std::mem::take(&mut self.errors)));
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::oneshot;
+
+    fn pending_dial() -> Dial {
+        futures::future::pending().boxed()
+    }
+
+    fn num_started(dial: &ConcurrentDial) -> usize {
+        dial.dials.len() + dial.errors.len()
+    }
+
+    #[async_std::test]
+    async fn staggered_dials_start_after_delay_or_failure() {
+        let (fail_tx, fail_rx) = oneshot::channel::<()>();
+        let failing_dial = async move {
+            let _ = fail_rx.await;
+            Err(TransportError::Other(std::io::ErrorKind::Other.into()))
+        }
+        .boxed();
+        let dials = vec![
+            ("/memory/1".parse().unwrap(), failing_dial),
+            ("/memory/2".parse().unwrap(), pending_dial()),
+            ("/memory/3".parse().unwrap(), pending_dial()),
+        ];
+        let mut dial = ConcurrentDial::new(
+            dials,
+            vec![],
+            NonZeroU8::new(8).unwrap(),
+            Some(Duration::from_secs(60)),
+        );
+        assert_eq!(num_started(&dial), 1);
+
+        // The next dial starts as soon as the previous one failed.
+        fail_tx.send(()).unwrap();
+        futures::future::poll_fn(|cx| {
+            let _ = dial.poll_unpin(cx);
+            Poll::Ready(())
+        })
+        .await;
+        assert_eq!(num_started(&dial), 2);
+
+        // The remaining dial starts after the delay.
+        dial.stagger = Some((
+            Duration::from_millis(10),
+            Delay::new(Duration::from_millis(10)),
+        ));
+        let _ = futures::future::select(&mut dial, Delay::new(Duration::from_millis(200))).await;
+        assert_eq!(num_started(&dial), 3);
+    }
+
+    #[async_std::test]
+    async fn fallback_dials_start_after_all_dials_failed() {
+        let (fail_tx, fail_rx) = oneshot::channel::<()>();
+        let failing_dial = async move {
+            let _ = fail_rx.await;
+            Err(TransportError::Other(std::io::ErrorKind::Other.into()))
+        }
+        .boxed();
+        let dials = vec![("/memory/1".parse().unwrap(), failing_dial)];
+        let fallback_dials = vec![("/memory/2".parse().unwrap(), pending_dial())];
+        let mut dial = ConcurrentDial::new(dials, fallback_dials, NonZeroU8::new(8).unwrap(), None);
+        assert_eq!(num_started(&dial), 1);
+
+        fail_tx.send(()).unwrap();
+        futures::future::poll_fn(|cx| {
+            assert!(dial.poll_unpin(cx).is_pending());
+            Poll::Ready(())
+        })
+        .await;
+        assert_eq!(num_started(&dial), 2);
+    }
+}
@@ -24,11 +24,12 @@
 use super::concurrent_dial::ConcurrentDial;
 use crate::{
     connection::{
-        self, ConnectionError, ConnectionId, PendingInboundConnectionError,
-        PendingOutboundConnectionError,
+        self, ConnectionError, ConnectionId, NegotiatedProtocol, PendingInboundConnectionError,
+        PendingOutboundConnectionError, StreamNegotiation,
     },
+    handler::ProtocolSupport,
     transport::TransportError,
-    ConnectionHandler, Multiaddr, PeerId,
+    ConnectionHandler, DialReport, Multiaddr, PeerId,
 };
 use futures::{
     channel::{mpsc, oneshot},
@@ -55,13 +56,20 @@ pub(crate) enum PendingConnectionEvent {
         output: (PeerId, StreamMuxerBox),
         /// [`Some`] when the new connection is an outgoing connection.
         /// Addresses are dialed in parallel. Contains the addresses and errors
-        /// of dial attempts that failed before the one successful dial.
-        outgoing: Option<(Multiaddr, Vec<(Multiaddr, TransportError<std::io::Error>)>)>,
+        /// of dial attempts that failed before the one successful dial, as well
+        /// as the report of all dial attempts.
+        outgoing: Option<(
+            Multiaddr,
+            Vec<(Multiaddr, TransportError<std::io::Error>)>,
+            DialReport,
+        )>,
     },
     /// A pending connection failed.
     PendingFailed {
         id: ConnectionId,
         error: Either<PendingOutboundConnectionError, PendingInboundConnectionError>,
+        /// [`Some`] when the failed connection is an outgoing connection.
+        dial_report: Option<DialReport>,
     },
 }
 
@@ -79,6 +87,24 @@ pub(crate) enum EstablishedConnectionEvent<ToBehaviour> {
         peer_id: PeerId,
         event: ToBehaviour,
     },
+    /// The protocol of a stream on the connection has been negotiated.
+    StreamNegotiated {
+        id: ConnectionId,
+        peer_id: PeerId,
+        negotiation: StreamNegotiation,
+    },
+    /// A protocol has been negotiated for the first time on the connection.
+    ProtocolNegotiated {
+        id: ConnectionId,
+        peer_id: PeerId,
+        negotiated: NegotiatedProtocol,
+    },
+    /// The protocols supported by the remote have changed.
+    RemoteProtocolsChanged {
+        id: ConnectionId,
+        peer_id: PeerId,
+        change: ProtocolSupport,
+    },
     /// A connection closed, possibly due to an error.
     ///
     /// If `error` is `None`, the connection has completed
@@ -92,16 +118,17 @@ pub(crate) enum EstablishedConnectionEvent<ToBehaviour> {
 
 pub(crate) async fn new_for_pending_outgoing_connection(
     connection_id: ConnectionId,
-    dial: ConcurrentDial,
+    mut dial: ConcurrentDial,
     abort_receiver: oneshot::Receiver<Void>,
     mut events: mpsc::Sender<PendingConnectionEvent>,
 ) {
-    match futures::future::select(abort_receiver, Box::pin(dial)).await {
+    match futures::future::select(abort_receiver, &mut dial).await {
         Either::Left((Err(oneshot::Canceled), _)) => {
             let _ = events
                 .send(PendingConnectionEvent::PendingFailed {
                     id: connection_id,
                     error: Either::Left(PendingOutboundConnectionError::Aborted),
+                    dial_report: Some(dial.take_report()),
                 })
                 .await;
         }
@@ -111,7 +138,7 @@ pub(crate) async fn new_for_pending_outgoing_connection(
                 .send(PendingConnectionEvent::ConnectionEstablished {
                     id: connection_id,
                     output,
-                    outgoing: Some((address, errors)),
+                    outgoing: Some((address, errors, dial.take_report())),
                 })
                 .await;
         }
@@ -120,6 +147,7 @@ pub(crate) async fn new_for_pending_outgoing_connection(
                 .send(PendingConnectionEvent::PendingFailed {
                     id: connection_id,
                     error: Either::Left(PendingOutboundConnectionError::Transport(e)),
+                    dial_report: Some(dial.take_report()),
                 })
                 .await;
         }
@@ -140,6 +168,7 @@ pub(crate) async fn new_for_pending_incoming_connection<TFut>(
                 .send(PendingConnectionEvent::PendingFailed {
                     id: connection_id,
                     error: Either::Right(PendingInboundConnectionError::Aborted),
+                    dial_report: None,
                 })
                 .await;
         }
@@ -160,6 +189,7 @@ pub(crate) async fn new_for_pending_incoming_connection<TFut>(
                     error: Either::Right(PendingInboundConnectionError::Transport(
                         TransportError::Other(e),
                     )),
+                    dial_report: None,
                 })
                 .await;
         }
@@ -234,6 +264,33 @@ pub(crate) async fn new_for_established_connection<THandler>(
                             })
                             .await;
                     }
+                    Ok(connection::Event::StreamNegotiated(negotiation)) => {
+                        let _ = events
+                            .send(EstablishedConnectionEvent::StreamNegotiated {
+                                id: connection_id,
+                                peer_id,
+                                negotiation,
+                            })
+                            .await;
+                    }
+                    Ok(connection::Event::ProtocolNegotiated(negotiated)) => {
+                        let _ = events
+                            .send(EstablishedConnectionEvent::ProtocolNegotiated {
+                                id: connection_id,
+                                peer_id,
+                                negotiated,
+                            })
+                            .await;
+                    }
+                    Ok(connection::Event::RemoteProtocolsChanged(change)) => {
+                        let _ = events
+                            .send(EstablishedConnectionEvent::RemoteProtocolsChanged {
+                                id: connection_id,
+                                peer_id,
+                                change,
+                            })
+                            .await;
+                    }
                     Err(error) => {
                         command_receiver.close();
                         let (remaining_events, _closing_muxer) = connection.close();
@@ -0,0 +1,253 @@
+use crate::ConnectionId;
+use futures::FutureExt;
+use futures_timer::Delay;
+use instant::Instant;
+use libp2p_core::ConnectedPoint;
+use libp2p_identity::PeerId;
+use std::{
+    collections::HashMap,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// Policy for consolidating multiple established connections to the same peer.
+///
+/// Once a peer has more than one established connection and the grace period configured via
+/// [`Config::with_redundant_connection_grace_period`](crate::Config::with_redundant_connection_grace_period)
+/// passed without a new connection being established, the policy selects which connection to
+/// keep. All other connections to the peer are closed.
+///
+/// If both peers consolidate their connections, they might each keep a connection the other one
+/// closes. Hence only the peer with the lower [`PeerId`] consolidates after the grace period.
+/// The other peer waits for twice the grace period, only consolidating connections that are
+/// still redundant by then, e.g. because the remote has no policy.
+pub trait ConnectionPolicy: Send + 'static {
+    /// Selects the connection to keep among the established connections to `peer`.
+    ///
+    /// `connections` always contains at least two connections. Returning [`None`] keeps all of
+    /// them.
+    fn select(&mut self, peer: &PeerId, connections: &[ConnectionInfo]) -> Option<ConnectionId>;
+}
+
+/// Keeps the most recently established connection.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PreferNewest;
+
+impl ConnectionPolicy for PreferNewest {
+    fn select(&mut self, _: &PeerId, connections: &[ConnectionInfo]) -> Option<ConnectionId> {
+        connections
+            .iter()
+            .max_by_key(|info| info.established_at)
+            .map(|info| info.id)
+    }
+}
+
+/// Keeps the connection with the lowest latency, falling back to the most recently established
+/// connection if the latencies are equal or unknown.
+///
+/// The latency of a connection is the time it took to establish it, unless reported otherwise
+/// via [`Swarm::report_connection_latency`](crate::Swarm::report_connection_latency).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PreferLowestLatency;
+
+impl ConnectionPolicy for PreferLowestLatency {
+    fn select(&mut self, _: &PeerId, connections: &[ConnectionInfo]) -> Option<ConnectionId> {
+        connections
+            .iter()
+            .min_by(|a, b| {
+                let latency = |info: &ConnectionInfo| info.latency.unwrap_or(Duration::MAX);
+                latency(a)
+                    .cmp(&latency(b))
+                    .then(b.established_at.cmp(&a.established_at))
+            })
+            .map(|info| info.id)
+    }
+}
+
+/// Information about an established connection, passed to a [`ConnectionPolicy`].
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    id: ConnectionId,
+    endpoint: ConnectedPoint,
+    established_at: Instant,
+    latency: Option<Duration>,
+}
+
+impl ConnectionInfo {
+    /// The identifier of the connection.
+    pub fn id(&self) -> ConnectionId {
+        self.id
+    }
+
+    /// The endpoint of the connection.
+    pub fn endpoint(&self) -> &ConnectedPoint {
+        &self.endpoint
+    }
+
+    /// When the connection was established.
+    pub fn established_at(&self) -> Instant {
+        self.established_at
+    }
+
+    /// The latency of the connection, if known.
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+}
+
+/// Redundant connections to be consolidated by a [`ConnectionPolicy`].
+pub(crate) struct Consolidation {
+    pub(crate) peer: PeerId,
+    pub(crate) kept: ConnectionId,
+    pub(crate) closed: Vec<ConnectionId>,
+}
+
+/// Tracks the established connections of peers to consolidate them via a [`ConnectionPolicy`].
+pub(crate) struct Deduplication {
+    policy: Box<dyn ConnectionPolicy>,
+    local_peer_id: PeerId,
+    grace_period: Duration,
+    connections: HashMap<PeerId, Vec<ConnectionInfo>>,
+    /// Peers with redundant connections, consolidated once the timer fires.
+    timers: HashMap<PeerId, Delay>,
+}
+
+impl Deduplication {
+    pub(crate) fn new(
+        policy: Box<dyn ConnectionPolicy>,
+        local_peer_id: PeerId,
+        grace_period: Duration,
+    ) -> Self {
+        Self {
+            policy,
+            local_peer_id,
+            grace_period,
+            connections: HashMap::new(),
+            timers: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn on_connection_established(
+        &mut self,
+        peer: PeerId,
+        id: ConnectionId,
+        endpoint: ConnectedPoint,
+        established_in: Duration,
+    ) {
+        let connections = self.connections.entry(peer).or_default();
+        connections.push(ConnectionInfo {
+            id,
+            endpoint,
+            established_at: Instant::now(),
+            latency: Some(established_in),
+        });
+        if connections.len() > 1 {
+            // Leave the consolidation to the peer with the lower peer ID, see `ConnectionPolicy`.
+            let delay = if self.local_peer_id < peer {
+                self.grace_period
+            } else {
+                self.grace_period * 2
+            };
+            self.timers.insert(peer, Delay::new(delay));
+        }
+    }
+
+    pub(crate) fn on_connection_closed(&mut self, peer: PeerId, id: ConnectionId) {
+        let Some(connections) = self.connections.get_mut(&peer) else {
+            return;
+        };
+        connections.retain(|info| info.id != id);
+        if connections.len() < 2 {
+            self.timers.remove(&peer);
+        }
+        if connections.is_empty() {
+            self.connections.remove(&peer);
+        }
+    }
+
+    /// Sets the latency of a connection, returning whether the connection is established.
+    pub(crate) fn set_latency(&mut self, id: ConnectionId, latency: Duration) -> bool {
+        match self
+            .connections
+            .values_mut()
+            .flatten()
+            .find(|info| info.id == id)
+        {
+            Some(info) => {
+                info.latency = Some(latency);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Consolidation> {
+        let Some(peer) = self
+            .timers
+            .iter_mut()
+            .find_map(|(peer, timer)| timer.poll_unpin(cx).is_ready().then_some(*peer))
+        else {
+            return Poll::Pending;
+        };
+        self.timers.remove(&peer);
+
+        let connections = self
+            .connections
+            .get(&peer)
+            .expect("peers with timers have connections");
+        let Some(kept) = self.policy.select(&peer, connections) else {
+            return Poll::Pending;
+        };
+        if !connections.iter().any(|info| info.id == kept) {
+            tracing::warn!(%peer, connection=%kept, "Connection policy selected unknown connection");
+            return Poll::Pending;
+        }
+        let closed = connections
+            .iter()
+            .map(|info| info.id)
+            .filter(|id| *id != kept)
+            .collect();
+
+        Poll::Ready(Consolidation { peer, kept, closed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(latency: Option<u64>, established_at: Instant) -> ConnectionInfo {
+        ConnectionInfo {
+            id: ConnectionId::next(),
+            endpoint: ConnectedPoint::Dialer {
+                address: "/memory/1234".parse().unwrap(),
+                role_override: libp2p_core::Endpoint::Dialer,
+            },
+            established_at,
+            latency: latency.map(Duration::from_millis),
+        }
+    }
+
+    #[test]
+    fn prefer_lowest_latency_selects_fastest_then_newest() {
+        let peer = PeerId::random();
+        let now = Instant::now();
+        let later = now + Duration::from_secs(1);
+
+        let connections = [info(Some(50), now), info(Some(10), now), info(None, later)];
+        assert_eq!(
+            PreferLowestLatency.select(&peer, &connections),
+            Some(connections[1].id)
+        );
+
+        let connections = [info(Some(10), now), info(Some(10), later)];
+        assert_eq!(
+            PreferLowestLatency.select(&peer, &connections),
+            Some(connections[1].id)
+        );
+        assert_eq!(
+            PreferNewest.select(&peer, &connections),
+            Some(connections[1].id)
+        );
+    }
+}
@@ -25,6 +25,7 @@ use libp2p_core::multiaddr::Protocol;
 use libp2p_core::Multiaddr;
 use libp2p_identity::PeerId;
 use std::num::NonZeroU8;
+use std::time::Duration;
 
 /// Options to configure a dial to a known or unknown peer.
 ///
@@ -45,6 +46,10 @@ pub struct DialOpts {
     role_override: Endpoint,
     dial_concurrency_factor_override: Option<NonZeroU8>,
     connection_id: ConnectionId,
+    priority: Priority,
+    relay_fallback: bool,
+    timeout: Option<Duration>,
+    address_timeouts: Vec<(Multiaddr, Duration)>,
 }
 
 impl DialOpts {
@@ -65,6 +70,9 @@ impl DialOpts {
             condition: Default::default(),
             role_override: Endpoint::Dialer,
             dial_concurrency_factor_override: Default::default(),
+            priority: Default::default(),
+            relay_fallback: false,
+            timeout: None,
         }
     }
 
@@ -105,6 +113,11 @@ impl DialOpts {
         self.connection_id
     }
 
+    /// Get the [`Priority`] of this dial attempt.
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
     pub(crate) fn get_addresses(&self) -> Vec<Multiaddr> {
         self.addresses.clone()
     }
@@ -124,6 +137,30 @@ impl DialOpts {
     pub(crate) fn role_override(&self) -> Endpoint {
         self.role_override
     }
+
+    pub(crate) fn relay_fallback(&self) -> bool {
+        self.relay_fallback
+    }
+
+    /// The timeout of the dial of the given address, if any.
+    ///
+    /// Addresses are compared regardless of a trailing `/p2p` protocol.
+    pub(crate) fn timeout_for(&self, address: &Multiaddr) -> Option<Duration> {
+        fn without_p2p(address: &Multiaddr) -> Multiaddr {
+            let mut address = address.clone();
+            if let Some(Protocol::P2p(_)) = address.iter().last() {
+                address.pop();
+            }
+            address
+        }
+
+        let address = without_p2p(address);
+        self.address_timeouts
+            .iter()
+            .find(|(a, _)| without_p2p(a) == address)
+            .map(|(_, timeout)| *timeout)
+            .or(self.timeout)
+    }
 }
 
 impl From<Multiaddr> for DialOpts {
@@ -144,6 +181,9 @@ pub struct WithPeerId {
     condition: PeerCondition,
     role_override: Endpoint,
     dial_concurrency_factor_override: Option<NonZeroU8>,
+    priority: Priority,
+    relay_fallback: bool,
+    timeout: Option<Duration>,
 }
 
 impl WithPeerId {
@@ -169,6 +209,10 @@ impl WithPeerId {
             extend_addresses_through_behaviour: false,
             role_override: self.role_override,
             dial_concurrency_factor_override: self.dial_concurrency_factor_override,
+            priority: self.priority,
+            relay_fallback: self.relay_fallback,
+            timeout: self.timeout,
+            address_timeouts: Vec::new(),
         }
     }
 
@@ -183,6 +227,34 @@ impl WithPeerId {
         self
     }
 
+    /// Specify the [`Priority`] of the dial.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Dial addresses relayed via a [circuit relay](https://github.com/libp2p/specs/blob/master/relay/circuit-v2.md),
+    /// i.e. containing `/p2p-circuit`, only once the dials of all other addresses failed.
+    ///
+    /// With a relayed connection established, hole punching via DCUtR is initiated by the
+    /// remote, given both nodes use the `libp2p-dcutr` behaviour.
+    pub fn with_relay_fallback(mut self, enabled: bool) -> Self {
+        self.relay_fallback = enabled;
+        self
+    }
+
+    /// Specify a timeout for the dial of each address, covering the establishment of the
+    /// connection and its upgrades.
+    ///
+    /// A dial that does not complete in time fails with an error of kind
+    /// [`std::io::ErrorKind::TimedOut`]. The timeouts of the transport still apply.
+    /// The timeout of an address only starts with its dial, not while the dial is queued behind
+    /// others, e.g. a relayed address waiting for direct dials to fail.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     /// Build the final [`DialOpts`].
     pub fn build(self) -> DialOpts {
         DialOpts {
@@ -193,6 +265,10 @@ impl WithPeerId {
             role_override: self.role_override,
             dial_concurrency_factor_override: self.dial_concurrency_factor_override,
             connection_id: ConnectionId::next(),
+            priority: self.priority,
+            relay_fallback: self.relay_fallback,
+            timeout: self.timeout,
+            address_timeouts: Vec::new(),
         }
     }
 }
@@ -205,6 +281,10 @@ pub struct WithPeerIdWithAddresses {
     extend_addresses_through_behaviour: bool,
     role_override: Endpoint,
     dial_concurrency_factor_override: Option<NonZeroU8>,
+    priority: Priority,
+    relay_fallback: bool,
+    timeout: Option<Duration>,
+    address_timeouts: Vec<(Multiaddr, Duration)>,
 }
 
 impl WithPeerIdWithAddresses {
@@ -239,6 +319,42 @@ impl WithPeerIdWithAddresses {
         self
     }
 
+    /// Specify the [`Priority`] of the dial.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Dial addresses relayed via a [circuit relay](https://github.com/libp2p/specs/blob/master/relay/circuit-v2.md),
+    /// i.e. containing `/p2p-circuit`, only once the dials of all other addresses failed.
+    ///
+    /// With a relayed connection established, hole punching via DCUtR is initiated by the
+    /// remote, given both nodes use the `libp2p-dcutr` behaviour.
+    pub fn with_relay_fallback(mut self, enabled: bool) -> Self {
+        self.relay_fallback = enabled;
+        self
+    }
+
+    /// Specify a timeout for the dial of each address, covering the establishment of the
+    /// connection and its upgrades.
+    ///
+    /// A dial that does not complete in time fails with an error of kind
+    /// [`std::io::ErrorKind::TimedOut`]. The timeouts of the transport still apply.
+    /// The timeout of an address only starts with its dial, not while the dial is queued behind
+    /// others, e.g. a relayed address waiting for direct dials to fail.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Override the [timeout](WithPeerIdWithAddresses::timeout) of the dial of a single
+    /// address, e.g. of a relayed address that takes longer to establish.
+    pub fn address_timeout(mut self, address: Multiaddr, timeout: Duration) -> Self {
+        self.address_timeouts.retain(|(a, _)| a != &address);
+        self.address_timeouts.push((address, timeout));
+        self
+    }
+
     /// Build the final [`DialOpts`].
     pub fn build(self) -> DialOpts {
         DialOpts {
@@ -249,6 +365,10 @@ impl WithPeerIdWithAddresses {
             role_override: self.role_override,
             dial_concurrency_factor_override: self.dial_concurrency_factor_override,
             connection_id: ConnectionId::next(),
+            priority: self.priority,
+            relay_fallback: self.relay_fallback,
+            timeout: self.timeout,
+            address_timeouts: self.address_timeouts,
         }
     }
 }
@@ -262,6 +382,8 @@ impl WithoutPeerId {
         WithoutPeerIdWithAddress {
             address,
             role_override: Endpoint::Dialer,
+            priority: Default::default(),
+            timeout: None,
         }
     }
 }
@@ -270,6 +392,8 @@ impl WithoutPeerId {
 pub struct WithoutPeerIdWithAddress {
     address: Multiaddr,
     role_override: Endpoint,
+    priority: Priority,
+    timeout: Option<Duration>,
 }
 
 impl WithoutPeerIdWithAddress {
@@ -283,6 +407,25 @@ impl WithoutPeerIdWithAddress {
         self.role_override = Endpoint::Listener;
         self
     }
+
+    /// Specify the [`Priority`] of the dial.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Specify a timeout for the dial of each address, covering the establishment of the
+    /// connection and its upgrades.
+    ///
+    /// A dial that does not complete in time fails with an error of kind
+    /// [`std::io::ErrorKind::TimedOut`]. The timeouts of the transport still apply.
+    /// The timeout of an address only starts with its dial, not while the dial is queued behind
+    /// others, e.g. a relayed address waiting for direct dials to fail.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     /// Build the final [`DialOpts`].
     pub fn build(self) -> DialOpts {
         DialOpts {
@@ -293,6 +436,10 @@ impl WithoutPeerIdWithAddress {
             role_override: self.role_override,
             dial_concurrency_factor_override: None,
             connection_id: ConnectionId::next(),
+            priority: self.priority,
+            relay_fallback: false,
+            timeout: self.timeout,
+            address_timeouts: Vec::new(),
         }
     }
 }
@@ -327,3 +474,31 @@ pub enum PeerCondition {
     /// configured connection limits.
     Always,
 }
+
+/// The priority of a dial, relative to other connections.
+///
+/// The [`Swarm`](crate::Swarm) itself treats all dials equally. The priority is reported to the
+/// [`NetworkBehaviour`](crate::NetworkBehaviour) via
+/// [`FromSwarm::DialPriority`](crate::FromSwarm::DialPriority), allowing e.g. connection
+/// management behaviours to close less important connections in favor of the new one.
+///
+/// Inbound connections always have the [`Normal`](Priority::Normal) priority.
+///
+/// ```
+/// # use libp2p_swarm::dial_opts::{DialOpts, Priority};
+/// # use libp2p_identity::PeerId;
+/// #
+/// DialOpts::peer_id(PeerId::random())
+///    .priority(Priority::High)
+///    .build();
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// The connection may be sacrificed for connections of higher priority.
+    Low,
+    /// The priority of dials that do not specify one.
+    #[default]
+    Normal,
+    /// The connection is more important than most others.
+    High,
+}
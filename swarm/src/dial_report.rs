@@ -0,0 +1,130 @@
+use crate::{transport::TransportError, Multiaddr};
+use instant::Instant;
+use std::{io, time::Duration};
+
+/// Report of the addresses attempted while establishing an outgoing connection.
+///
+/// Attached to [`SwarmEvent::OutgoingConnectionError`](crate::SwarmEvent::OutgoingConnectionError).
+#[derive(Debug, Clone, Default)]
+pub struct DialReport {
+    attempts: Vec<DialAttempt>,
+}
+
+impl DialReport {
+    /// The attempted addresses, in the order their dials were started.
+    ///
+    /// Addresses that were never dialed, e.g. because a dial of another address succeeded
+    /// before, are not included.
+    pub fn attempts(&self) -> &[DialAttempt] {
+        &self.attempts
+    }
+}
+
+/// The dial of a single address as part of a [`DialReport`].
+#[derive(Debug, Clone)]
+pub struct DialAttempt {
+    address: Multiaddr,
+    started_after: Duration,
+    duration: Option<Duration>,
+    outcome: DialOutcome,
+}
+
+impl DialAttempt {
+    /// The dialed address.
+    pub fn address(&self) -> &Multiaddr {
+        &self.address
+    }
+
+    /// The protocols of the transport used to dial the address, e.g. `/ip4/tcp/ws`.
+    pub fn transport(&self) -> String {
+        self.address
+            .protocol_stack()
+            .filter(|tag| *tag != "p2p")
+            .fold(String::new(), |mut transport, tag| {
+                transport.push('/');
+                transport.push_str(tag);
+                transport
+            })
+    }
+
+    /// The time between the start of the first dial of the connection and the start of this dial.
+    pub fn started_after(&self) -> Duration {
+        self.started_after
+    }
+
+    /// How long the dial took, including the security and muxer handshakes.
+    ///
+    /// [`None`] if the dial did not finish.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// The outcome of the dial.
+    pub fn outcome(&self) -> &DialOutcome {
+        &self.outcome
+    }
+}
+
+/// The outcome of a [`DialAttempt`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DialOutcome {
+    /// The address was reached and the connection upgraded.
+    ///
+    /// The connection may still have failed afterwards, e.g. because it was denied.
+    Succeeded,
+    /// The dial failed with the given error.
+    Failed(String),
+    /// The dial did not finish, e.g. because the connection attempt was aborted.
+    Unfinished,
+}
+
+/// Records the [`DialAttempt`]s of a connection attempt.
+pub(crate) struct DialReportRecorder {
+    started: Instant,
+    attempts: Vec<(DialAttempt, Instant)>,
+}
+
+impl DialReportRecorder {
+    pub(crate) fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            attempts: Vec::new(),
+        }
+    }
+
+    /// Records the start of a dial, returning the index of the attempt.
+    pub(crate) fn start(&mut self, address: Multiaddr) -> usize {
+        let now = Instant::now();
+        self.attempts.push((
+            DialAttempt {
+                address,
+                started_after: now.duration_since(self.started),
+                duration: None,
+                outcome: DialOutcome::Unfinished,
+            },
+            now,
+        ));
+        self.attempts.len() - 1
+    }
+
+    /// Records the outcome of the dial with the given index, returning the dialed address.
+    pub(crate) fn finish(
+        &mut self,
+        index: usize,
+        result: Result<(), &TransportError<io::Error>>,
+    ) -> Multiaddr {
+        let (attempt, started) = &mut self.attempts[index];
+        attempt.duration = Some(started.elapsed());
+        attempt.outcome = match result {
+            Ok(()) => DialOutcome::Succeeded,
+            Err(e) => DialOutcome::Failed(e.to_string()),
+        };
+        attempt.address.clone()
+    }
+
+    pub(crate) fn report(&mut self) -> DialReport {
+        DialReport {
+            attempts: self.attempts.drain(..).map(|(a, _)| a).collect(),
+        }
+    }
+}
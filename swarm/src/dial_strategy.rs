@@ -0,0 +1,125 @@
+use libp2p_core::multiaddr::Protocol;
+use libp2p_core::Multiaddr;
+use libp2p_identity::PeerId;
+use std::{collections::VecDeque, time::Duration};
+
+/// Strategy for ordering and scheduling the dials to the addresses of a peer.
+///
+/// Configured via [`Config::with_dial_strategy`](crate::Config::with_dial_strategy). The
+/// addresses are dialed in the ranked order, at most as many at the same time as the dial
+/// concurrency factor allows. Once one dial succeeds, all other dials are cancelled.
+pub trait DialStrategy: Send + 'static {
+    /// Orders the addresses to dial, the most preferred address first.
+    fn rank(&mut self, peer: Option<PeerId>, addresses: &mut [Multiaddr]);
+
+    /// The delay after which the next address is dialed while the previous dials are still
+    /// pending.
+    ///
+    /// The next address is dialed right away when a dial fails. Returning [`None`] starts dials
+    /// up to the dial concurrency factor at once.
+    fn stagger_delay(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Dials addresses in the style of [Happy Eyeballs](https://datatracker.ietf.org/doc/html/rfc8305).
+///
+/// IPv6 and IPv4 addresses are interleaved, starting with IPv6. Addresses of neither family,
+/// e.g. `/dns` addresses, are dialed last. Each subsequent dial is started after a delay of
+/// 250ms by default, or as soon as the previous dial failed.
+#[derive(Debug, Clone, Copy)]
+pub struct HappyEyeballs {
+    stagger_delay: Duration,
+}
+
+impl HappyEyeballs {
+    /// Sets the delay after which the next address is dialed.
+    pub fn with_stagger_delay(mut self, delay: Duration) -> Self {
+        self.stagger_delay = delay;
+        self
+    }
+}
+
+impl Default for HappyEyeballs {
+    fn default() -> Self {
+        Self {
+            stagger_delay: Duration::from_millis(250),
+        }
+    }
+}
+
+impl DialStrategy for HappyEyeballs {
+    fn rank(&mut self, _: Option<PeerId>, addresses: &mut [Multiaddr]) {
+        let mut ipv6 = VecDeque::new();
+        let mut ipv4 = VecDeque::new();
+        let mut other = Vec::new();
+        for address in addresses.iter() {
+            match address_family(address) {
+                Some(Family::Ipv6) => ipv6.push_back(address.clone()),
+                Some(Family::Ipv4) => ipv4.push_back(address.clone()),
+                None => other.push(address.clone()),
+            }
+        }
+
+        let mut ranked = Vec::with_capacity(addresses.len());
+        while !ipv6.is_empty() || !ipv4.is_empty() {
+            ranked.extend(ipv6.pop_front());
+            ranked.extend(ipv4.pop_front());
+        }
+        ranked.extend(other);
+
+        addresses.clone_from_slice(&ranked);
+    }
+
+    fn stagger_delay(&self) -> Option<Duration> {
+        Some(self.stagger_delay)
+    }
+}
+
+enum Family {
+    Ipv6,
+    Ipv4,
+}
+
+fn address_family(address: &Multiaddr) -> Option<Family> {
+    match address.iter().next()? {
+        Protocol::Ip6(_) | Protocol::Dns6(_) => Some(Family::Ipv6),
+        Protocol::Ip4(_) | Protocol::Dns4(_) => Some(Family::Ipv4),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn happy_eyeballs_interleaves_address_families() {
+        let mut addresses: Vec<Multiaddr> = [
+            "/ip4/1.1.1.1/tcp/1",
+            "/dns/example.com/tcp/1",
+            "/ip4/2.2.2.2/tcp/1",
+            "/ip4/3.3.3.3/tcp/1",
+            "/ip6/::1/tcp/1",
+            "/dns6/example.com/tcp/1",
+        ]
+        .into_iter()
+        .map(|a| a.parse().unwrap())
+        .collect();
+
+        HappyEyeballs::default().rank(None, &mut addresses);
+
+        let expected: Vec<Multiaddr> = [
+            "/ip6/::1/tcp/1",
+            "/ip4/1.1.1.1/tcp/1",
+            "/dns6/example.com/tcp/1",
+            "/ip4/2.2.2.2/tcp/1",
+            "/ip4/3.3.3.3/tcp/1",
+            "/dns/example.com/tcp/1",
+        ]
+        .into_iter()
+        .map(|a| a.parse().unwrap())
+        .collect();
+        assert_eq!(addresses, expected);
+    }
+}
@@ -47,6 +47,7 @@ mod pending;
 mod select;
 
 pub use crate::upgrade::{InboundUpgradeSend, OutboundUpgradeSend, SendWrapper, UpgradeInfoSend};
+pub use libp2p_core::muxing::StreamPriority;
 pub use map_in::MapInEvent;
 pub use map_out::MapOutEvent;
 pub use one_shot::{OneShotHandler, OneShotHandlerConfig};
@@ -468,6 +469,7 @@ pub struct SubstreamProtocol<TUpgrade, TInfo> {
     upgrade: TUpgrade,
     info: TInfo,
     timeout: Duration,
+    priority: StreamPriority,
 }
 
 impl<TUpgrade, TInfo> SubstreamProtocol<TUpgrade, TInfo> {
@@ -480,6 +482,7 @@ impl<TUpgrade, TInfo> SubstreamProtocol<TUpgrade, TInfo> {
             upgrade,
             info,
             timeout: Duration::from_secs(10),
+            priority: StreamPriority::DEFAULT,
         }
     }
 
@@ -492,6 +495,7 @@ impl<TUpgrade, TInfo> SubstreamProtocol<TUpgrade, TInfo> {
             upgrade: f(self.upgrade),
             info: self.info,
             timeout: self.timeout,
+            priority: self.priority,
         }
     }
 
@@ -504,6 +508,7 @@ impl<TUpgrade, TInfo> SubstreamProtocol<TUpgrade, TInfo> {
             upgrade: self.upgrade,
             info: f(self.info),
             timeout: self.timeout,
+            priority: self.priority,
         }
     }
 
@@ -513,6 +518,15 @@ impl<TUpgrade, TInfo> SubstreamProtocol<TUpgrade, TInfo> {
         self
     }
 
+    /// Sets the priority of the substream relative to the other substreams of the connection.
+    ///
+    /// Only applies to outbound substreams and only if the muxer of the connection supports
+    /// prioritizing substreams, see [`StreamMuxer::poll_outbound_with_priority`](libp2p_core::StreamMuxer::poll_outbound_with_priority).
+    pub fn with_priority(mut self, priority: StreamPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
     /// Borrows the contained protocol upgrade.
     pub fn upgrade(&self) -> &TUpgrade {
         &self.upgrade
@@ -528,6 +542,11 @@ impl<TUpgrade, TInfo> SubstreamProtocol<TUpgrade, TInfo> {
         &self.timeout
     }
 
+    /// The priority of the substream.
+    pub fn priority(&self) -> StreamPriority {
+        self.priority
+    }
+
     /// Converts the substream protocol configuration into the contained upgrade.
     pub fn into_upgrade(self) -> (TUpgrade, TInfo) {
         (self.upgrade, self.info)
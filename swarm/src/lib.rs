@@ -55,8 +55,13 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+mod address_translator;
 mod connection;
+mod connection_policy;
+mod dial_report;
+mod dial_strategy;
 mod executor;
+mod spans;
 mod stream;
 mod stream_protocol;
 #[cfg(test)]
@@ -68,6 +73,7 @@ pub mod dial_opts;
 pub mod dummy;
 pub mod handler;
 mod listen_opts;
+mod listener;
 
 /// Bundles all symbols required for the [`libp2p_swarm_derive::NetworkBehaviour`] macro.
 #[doc(hidden)]
@@ -76,6 +82,7 @@ pub mod derive_prelude {
     pub use crate::behaviour::ConnectionClosed;
     pub use crate::behaviour::ConnectionEstablished;
     pub use crate::behaviour::DialFailure;
+    pub use crate::behaviour::DialPriority;
     pub use crate::behaviour::ExpiredListenAddr;
     pub use crate::behaviour::ExternalAddrConfirmed;
     pub use crate::behaviour::ExternalAddrExpired;
@@ -106,14 +113,21 @@ pub mod derive_prelude {
     pub use libp2p_identity::PeerId;
 }
 
+pub use address_translator::{AddressTranslator, PortForwarding};
 pub use behaviour::{
-    AddressChange, CloseConnection, ConnectionClosed, DialFailure, ExpiredListenAddr,
+    AddressChange, CloseConnection, ConnectionClosed, DialFailure, DialPriority, ExpiredListenAddr,
     ExternalAddrExpired, ExternalAddresses, FromSwarm, ListenAddresses, ListenFailure,
     ListenerClosed, ListenerError, NetworkBehaviour, NewExternalAddrCandidate,
-    NewExternalAddrOfPeer, NewListenAddr, NotifyHandler, PeerAddresses, ToSwarm,
+    NewExternalAddrOfPeer, NewListenAddr, NotifyHandler, PeerAddresses, Timers, ToSwarm,
 };
 pub use connection::pool::ConnectionCounters;
-pub use connection::{ConnectionError, ConnectionId, SupportedProtocols};
+pub use connection::{
+    ConnectionError, ConnectionId, NegotiatedProtocol, NegotiationOutcome, StreamNegotiation,
+    SupportedProtocols,
+};
+pub use connection_policy::{ConnectionInfo, ConnectionPolicy, PreferLowestLatency, PreferNewest};
+pub use dial_report::{DialAttempt, DialOutcome, DialReport};
+pub use dial_strategy::{DialStrategy, HappyEyeballs};
 pub use executor::Executor;
 pub use handler::{
     ConnectionHandler, ConnectionHandlerEvent, ConnectionHandlerSelect, OneShotHandler,
@@ -122,20 +136,24 @@ pub use handler::{
 #[cfg(feature = "macros")]
 pub use libp2p_swarm_derive::NetworkBehaviour;
 pub use listen_opts::ListenOpts;
+use listener::ListenerState;
+pub use listener::{ListenerDenied, ListenerInfo};
 pub use stream::Stream;
 pub use stream_protocol::{InvalidProtocol, StreamProtocol};
 
 use crate::behaviour::ExternalAddrConfirmed;
-use crate::handler::UpgradeInfoSend;
+use crate::handler::{ProtocolSupport, UpgradeInfoSend};
 use connection::pool::{EstablishedConnection, Pool, PoolConfig, PoolEvent};
 use connection::IncomingInfo;
 use connection::{
     PendingConnectionError, PendingInboundConnectionError, PendingOutboundConnectionError,
 };
-use dial_opts::{DialOpts, PeerCondition};
+use connection_policy::{Consolidation, Deduplication};
+use dial_opts::{DialOpts, PeerCondition, Priority};
 use futures::{prelude::*, stream::FusedStream};
 use libp2p_core::{
     connection::ConnectedPoint,
+    multiaddr::Protocol,
     muxing::StreamMuxerBox,
     transport::{self, ListenerId, TransportError, TransportEvent},
     Endpoint, Multiaddr, Transport,
@@ -205,6 +223,42 @@ pub enum SwarmEvent<TBehaviourOutEvent> {
         /// active close.
         cause: Option<ConnectionError>,
     },
+    /// Redundant connections to the given peer are being closed by the configured
+    /// [`ConnectionPolicy`].
+    ///
+    /// A [`ConnectionClosed`](SwarmEvent::ConnectionClosed) event is reported for each of the
+    /// closed connections once they are closed.
+    ConnectionsConsolidated {
+        /// Identity of the peer the connections are established to.
+        peer_id: PeerId,
+        /// The connection selected to be kept.
+        kept: ConnectionId,
+        /// The connections being closed.
+        closed: Vec<ConnectionId>,
+    },
+    /// The protocol of a stream on a connection has been negotiated, successfully or not.
+    ///
+    /// Only reported if enabled via [`Config::with_stream_negotiation_events`].
+    StreamNegotiated {
+        /// Identity of the peer the connection is established to.
+        peer_id: PeerId,
+        /// Identifier of the connection.
+        connection_id: ConnectionId,
+        /// The negotiation.
+        negotiation: StreamNegotiation,
+    },
+    /// The protocols supported by the remote of a connection have changed, as reported by its
+    /// [`ConnectionHandler`], e.g. based on an identify exchange.
+    ///
+    /// Only contains the protocols that were actually added or removed.
+    RemoteProtocolsChanged {
+        /// Identity of the peer the connection is established to.
+        peer_id: PeerId,
+        /// Identifier of the connection.
+        connection_id: ConnectionId,
+        /// The added or removed protocols.
+        change: ProtocolSupport,
+    },
     /// A new connection arrived on a listener and is in the process of protocol negotiation.
     ///
     /// A corresponding [`ConnectionEstablished`](SwarmEvent::ConnectionEstablished) or
@@ -245,6 +299,20 @@ pub enum SwarmEvent<TBehaviourOutEvent> {
         /// Error that has been encountered.
         error: DialError,
     },
+    /// Report of the addresses attempted for an outbound connection that failed, reported right
+    /// after the corresponding [`SwarmEvent::OutgoingConnectionError`].
+    ///
+    /// Only reported if enabled via [`Config::with_dial_report_events`].
+    OutgoingConnectionReport {
+        /// Identifier of the connection.
+        connection_id: ConnectionId,
+        /// If known, [`PeerId`] of the peer we tried to reach.
+        peer_id: Option<PeerId>,
+        /// The addresses attempted while establishing the connection.
+        ///
+        /// Empty if the connection failed before any address was dialed.
+        report: DialReport,
+    },
     /// One of our listeners has reported a new local listening address.
     NewListenAddr {
         /// The listener that is listening on the new address.
@@ -342,12 +410,42 @@ where
     /// Multiaddresses that our listeners are listening on,
     listened_addrs: HashMap<ListenerId, SmallVec<[Multiaddr; 1]>>,
 
+    /// The state and configuration of our listeners.
+    listener_states: HashMap<ListenerId, ListenerState>,
+
     /// Pending event to be delivered to connection handlers
     /// (or dropped if the peer disconnected) before the `behaviour`
     /// can be polled again.
     pending_handler_event: Option<(PeerId, PendingNotifyHandler, THandlerInEvent<TBehaviour>)>,
 
     pending_swarm_events: VecDeque<SwarmEvent<TBehaviour::ToSwarm>>,
+
+    /// Consolidation of redundant connections to peers, if a [`ConnectionPolicy`] is configured.
+    deduplication: Option<Deduplication>,
+
+    /// Whether upgrading inbound connections is paused, see [`ToSwarm::PauseInboundUpgrades`].
+    inbound_upgrades_paused: bool,
+    /// Inbound connections held back while upgrading them is paused.
+    paused_incoming: VecDeque<PausedIncoming>,
+    /// The maximum number of inbound connections held back while upgrading them is paused.
+    max_paused_incoming: usize,
+
+    /// Orders and schedules the dials to the addresses of a peer, if configured.
+    dial_strategy: Option<Box<dyn DialStrategy>>,
+
+    /// Translates observed addresses into external address candidates, if configured.
+    address_translator: Option<Box<dyn AddressTranslator>>,
+
+    /// Whether to report [`SwarmEvent::OutgoingConnectionReport`]s.
+    dial_report_events: bool,
+}
+
+/// An inbound connection held back while upgrading inbound connections is paused.
+struct PausedIncoming {
+    connection_id: ConnectionId,
+    upgrade: <transport::Boxed<(PeerId, StreamMuxerBox)> as Transport>::ListenerUpgrade,
+    local_addr: Multiaddr,
+    send_back_addr: Multiaddr,
 }
 
 impl<TBehaviour> Unpin for Swarm<TBehaviour> where TBehaviour: NetworkBehaviour {}
@@ -374,8 +472,22 @@ where
             supported_protocols: Default::default(),
             confirmed_external_addr: Default::default(),
             listened_addrs: HashMap::new(),
+            listener_states: HashMap::new(),
             pending_handler_event: None,
             pending_swarm_events: VecDeque::default(),
+            deduplication: config.connection_policy.map(|policy| {
+                Deduplication::new(
+                    policy,
+                    local_peer_id,
+                    config.redundant_connection_grace_period,
+                )
+            }),
+            inbound_upgrades_paused: false,
+            paused_incoming: VecDeque::default(),
+            max_paused_incoming: config.max_paused_incoming,
+            dial_strategy: config.dial_strategy,
+            dial_report_events: config.dial_report_events,
+            address_translator: config.address_translator,
         }
     }
 
@@ -395,7 +507,17 @@ where
     /// Listeners report their new listening addresses as [`SwarmEvent::NewListenAddr`].
     /// Depending on the underlying transport, one listener may have multiple listening addresses.
     pub fn listen_on(&mut self, addr: Multiaddr) -> Result<ListenerId, TransportError<io::Error>> {
-        let opts = ListenOpts::new(addr);
+        self.listen_with_opts(ListenOpts::new(addr))
+    }
+
+    /// Starts listening with the given [`ListenOpts`], e.g. to limit the number of inbound
+    /// connections accepted by the listener.
+    ///
+    /// See also [`Swarm::listen_on`].
+    pub fn listen_with_opts(
+        &mut self,
+        opts: ListenOpts,
+    ) -> Result<ListenerId, TransportError<io::Error>> {
         let id = opts.listener_id();
         self.add_listener(opts)?;
         Ok(id)
@@ -409,6 +531,50 @@ where
         self.transport.remove_listener(listener_id)
     }
 
+    /// Pauses a listener, denying inbound connections with [`ListenerDenied::Paused`] until it is
+    /// resumed via [`Swarm::resume_listener`].
+    ///
+    /// The listener keeps listening on its addresses and existing connections are unaffected.
+    ///
+    /// Returns `true` if there was a listener with this ID, `false` otherwise.
+    pub fn pause_listener(&mut self, listener_id: ListenerId) -> bool {
+        self.set_listener_paused(listener_id, true)
+    }
+
+    /// Resumes a listener paused via [`Swarm::pause_listener`].
+    ///
+    /// Returns `true` if there was a listener with this ID, `false` otherwise.
+    pub fn resume_listener(&mut self, listener_id: ListenerId) -> bool {
+        self.set_listener_paused(listener_id, false)
+    }
+
+    fn set_listener_paused(&mut self, listener_id: ListenerId, paused: bool) -> bool {
+        let Some(state) = self.listener_states.get_mut(&listener_id) else {
+            return false;
+        };
+        tracing::debug!(listener=?listener_id, %paused, "Setting listener paused");
+        state.paused = paused;
+        true
+    }
+
+    /// Sets the maximum number of pending and established inbound connections accepted by a
+    /// listener, see [`ListenOpts::with_max_inbound_connections`].
+    ///
+    /// Lowering the limit does not close existing connections.
+    ///
+    /// Returns `true` if there was a listener with this ID, `false` otherwise.
+    pub fn set_listener_max_inbound_connections(
+        &mut self,
+        listener_id: ListenerId,
+        limit: Option<u32>,
+    ) -> bool {
+        let Some(state) = self.listener_states.get_mut(&listener_id) else {
+            return false;
+        };
+        state.set_max_inbound(limit);
+        true
+    }
+
     /// Dial a known or unknown peer.
     ///
     /// See also [`DialOpts`].
@@ -466,6 +632,16 @@ where
             return Err(e);
         }
 
+        let priority = dial_opts.priority();
+        if priority != Priority::Normal {
+            self.behaviour
+                .on_swarm_event(FromSwarm::DialPriority(DialPriority {
+                    peer_id,
+                    connection_id,
+                    priority,
+                }));
+        }
+
         let addresses = {
             let mut addresses_from_opts = dial_opts.get_addresses();
 
@@ -521,47 +697,79 @@ where
                 return Err(error);
             };
 
+            if let Some(strategy) = self.dial_strategy.as_mut() {
+                strategy.rank(peer_id, &mut addresses_from_opts);
+            }
+
             addresses_from_opts
         };
 
-        let dials = addresses
-            .into_iter()
-            .map(|a| match peer_id.map_or(Ok(a.clone()), |p| a.with_p2p(p)) {
-                Ok(address) => {
-                    let (dial, span) = match dial_opts.role_override() {
-                        Endpoint::Dialer => (
-                            self.transport.dial(address.clone()),
-                            tracing::debug_span!(parent: tracing::Span::none(), "Transport::dial", %address),
-                        ),
-                        Endpoint::Listener => (
-                            self.transport.dial_as_listener(address.clone()),
-                            tracing::debug_span!(parent: tracing::Span::none(), "Transport::dial_as_listener", %address),
-                        ),
-                    };
-                    span.follows_from(tracing::Span::current());
-
-                    match dial {
-                        Ok(fut) => fut
-                            .map(|r| (address, r.map_err(TransportError::Other)))
-                            .instrument(span)
-                            .boxed(),
-                        Err(err) => futures::future::ready((address, Err(err))).boxed(),
+        let (fallback_addresses, addresses): (Vec<_>, Vec<_>) = if dial_opts.relay_fallback() {
+            addresses
+                .into_iter()
+                .partition(|a| a.iter().any(|p| p == Protocol::P2pCircuit))
+        } else {
+            (Vec::new(), addresses)
+        };
+
+        let connection_span =
+            spans::outgoing_connection(connection_id, peer_id, dial_opts.role_override());
+
+        let mut dial = |a: Multiaddr| match peer_id.map_or(Ok(a.clone()), |p| a.with_p2p(p)) {
+            Ok(address) => {
+                let dial_span = spans::dial(&connection_span, &address);
+                let (dial, span) = match dial_opts.role_override() {
+                    Endpoint::Dialer => (
+                        self.transport.dial(address.clone()),
+                        tracing::debug_span!(parent: tracing::Span::none(), "Transport::dial", %address),
+                    ),
+                    Endpoint::Listener => (
+                        self.transport.dial_as_listener(address.clone()),
+                        tracing::debug_span!(parent: tracing::Span::none(), "Transport::dial_as_listener", %address),
+                    ),
+                };
+                span.follows_from(tracing::Span::current());
+
+                let dial = match dial {
+                    Ok(fut) => with_dial_timeout(fut, dial_opts.timeout_for(&address))
+                        .map({
+                            let dial_span = dial_span.clone();
+                            move |r| {
+                                if let Err(e) = &r {
+                                    spans::record_error(&dial_span, e);
+                                }
+                                r.map_err(TransportError::Other)
+                            }
+                        })
+                        .instrument(span)
+                        .instrument(dial_span)
+                        .boxed(),
+                    Err(err) => {
+                        spans::record_error(&dial_span, &err);
+                        futures::future::ready(Err(err)).boxed()
                     }
-                }
-                Err(address) => futures::future::ready((
-                    address.clone(),
-                    Err(TransportError::MultiaddrNotSupported(address)),
-                ))
-                .boxed(),
-            })
-            .collect();
+                };
+                (address, dial)
+            }
+            Err(address) => (
+                address.clone(),
+                futures::future::ready(Err(TransportError::MultiaddrNotSupported(address))).boxed(),
+            ),
+        };
+        let dials = addresses.into_iter().map(&mut dial).collect();
+        let fallback_dials = fallback_addresses.into_iter().map(&mut dial).collect();
 
         self.pool.add_outgoing(
             dials,
+            fallback_dials,
             peer_id,
             dial_opts.role_override(),
             dial_opts.dial_concurrency_override(),
+            self.dial_strategy
+                .as_ref()
+                .and_then(|strategy| strategy.stagger_delay()),
             connection_id,
+            connection_span,
         );
 
         Ok(())
@@ -572,6 +780,16 @@ where
         self.listened_addrs.values().flatten()
     }
 
+    /// Returns an iterator over the state of our listeners, including their addresses, the last
+    /// error they reported and their inbound connections.
+    pub fn listeners_detailed(&self) -> impl Iterator<Item = ListenerInfo<'_>> {
+        self.listener_states.iter().map(|(id, state)| ListenerInfo {
+            id: *id,
+            state,
+            listen_addrs: self.listened_addrs.get(id).map_or(&[], |addrs| addrs),
+        })
+    }
+
     /// Returns the peer ID of the swarm passed as parameter.
     pub fn local_peer_id(&self) -> &PeerId {
         &self.local_peer_id
@@ -596,6 +814,10 @@ where
             return Err(e);
         }
 
+        self.listener_states.insert(
+            listener_id,
+            ListenerState::new(addr.clone(), opts.max_inbound_connections()),
+        );
         self.behaviour
             .on_swarm_event(FromSwarm::NewListener(behaviour::NewListener {
                 listener_id,
@@ -672,6 +894,33 @@ where
         false
     }
 
+    /// Reports the latency of an established connection, e.g. as measured by a ping protocol.
+    ///
+    /// Used by the [`ConnectionPolicy`] to select among redundant connections, see
+    /// [`PreferLowestLatency`].
+    ///
+    /// # Returns
+    ///
+    /// - `true` if the connection is established and a [`ConnectionPolicy`] is configured.
+    /// - `false` otherwise.
+    pub fn report_connection_latency(
+        &mut self,
+        connection_id: ConnectionId,
+        latency: Duration,
+    ) -> bool {
+        self.deduplication
+            .as_mut()
+            .is_some_and(|deduplication| deduplication.set_latency(connection_id, latency))
+    }
+
+    /// Returns information about an established connection, including the protocols negotiated
+    /// on it and the protocols supported by the remote.
+    ///
+    /// Returns [`None`] if there is no established connection with the given ID.
+    pub fn connection_info(&self, connection_id: ConnectionId) -> Option<ConnectionDetails> {
+        self.pool.connection_info(connection_id)
+    }
+
     /// Checks whether there is an established connection to a peer.
     pub fn is_connected(&self, peer_id: &PeerId) -> bool {
         self.pool.is_connected(*peer_id)
@@ -692,6 +941,39 @@ where
         &mut self.behaviour
     }
 
+    fn handle_consolidation(&mut self, consolidation: Consolidation) {
+        let Consolidation { peer, kept, closed } = consolidation;
+        tracing::debug!(%peer, %kept, ?closed, "Closing redundant connections");
+        for id in &closed {
+            if let Some(established) = self.pool.get_established(*id) {
+                established.start_close();
+            }
+        }
+        self.pending_swarm_events
+            .push_back(SwarmEvent::ConnectionsConsolidated {
+                peer_id: peer,
+                kept,
+                closed,
+            });
+    }
+
+    /// Reports the attempted addresses of a failed dial, if enabled.
+    fn report_failed_dial(
+        &mut self,
+        connection_id: ConnectionId,
+        peer_id: Option<PeerId>,
+        report: DialReport,
+    ) {
+        if self.dial_report_events {
+            self.pending_swarm_events
+                .push_back(SwarmEvent::OutgoingConnectionReport {
+                    connection_id,
+                    peer_id,
+                    report,
+                });
+        }
+    }
+
     fn handle_pool_event(&mut self, event: PoolEvent<THandlerOutEvent<TBehaviour>>) {
         match event {
             PoolEvent::ConnectionEstablished {
@@ -700,6 +982,7 @@ where
                 endpoint,
                 connection,
                 concurrent_dial_errors,
+                dial_report,
                 established_in,
             } => {
                 let handler = match endpoint.clone() {
@@ -731,6 +1014,11 @@ where
                                         error: dial_error,
                                     },
                                 );
+                                self.report_failed_dial(
+                                    id,
+                                    Some(peer_id),
+                                    dial_report.unwrap_or_default(),
+                                );
                                 return;
                             }
                         }
@@ -747,6 +1035,7 @@ where
                         ) {
                             Ok(handler) => handler,
                             Err(cause) => {
+                                self.remove_inbound_connection(id);
                                 let listen_error = ListenError::Denied { cause };
                                 self.behaviour.on_swarm_event(FromSwarm::ListenFailure(
                                     ListenFailure {
@@ -788,6 +1077,14 @@ where
 
                 self.pool
                     .spawn_connection(id, peer_id, &endpoint, connection, handler);
+                if let Some(deduplication) = self.deduplication.as_mut() {
+                    deduplication.on_connection_established(
+                        peer_id,
+                        id,
+                        endpoint.clone(),
+                        established_in,
+                    );
+                }
 
                 tracing::debug!(
                     peer=%peer_id,
@@ -829,6 +1126,7 @@ where
                 id: connection_id,
                 error,
                 peer,
+                dial_report,
             } => {
                 let error = error.into();
 
@@ -851,6 +1149,7 @@ where
                         connection_id,
                         error,
                     });
+                self.report_failed_dial(connection_id, peer, dial_report);
             }
             PoolEvent::PendingInboundConnectionError {
                 id,
@@ -861,6 +1160,7 @@ where
                 let error = error.into();
 
                 tracing::debug!("Incoming connection failed: {:?}", error);
+                self.remove_inbound_connection(id);
                 self.behaviour
                     .on_swarm_event(FromSwarm::ListenFailure(ListenFailure {
                         local_addr: &local_addr,
@@ -901,6 +1201,12 @@ where
                 let endpoint = connected.endpoint;
                 let num_established =
                     u32::try_from(remaining_established_connection_ids.len()).unwrap();
+                if let Some(deduplication) = self.deduplication.as_mut() {
+                    deduplication.on_connection_closed(peer_id, id);
+                }
+                if endpoint.is_listener() {
+                    self.remove_inbound_connection(id);
+                }
 
                 self.behaviour
                     .on_swarm_event(FromSwarm::ConnectionClosed(ConnectionClosed {
@@ -922,6 +1228,30 @@ where
                 self.behaviour
                     .on_connection_handler_event(peer_id, id, event);
             }
+            PoolEvent::StreamNegotiated {
+                peer_id,
+                id,
+                negotiation,
+            } => {
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::StreamNegotiated {
+                        peer_id,
+                        connection_id: id,
+                        negotiation,
+                    });
+            }
+            PoolEvent::RemoteProtocolsChanged {
+                peer_id,
+                id,
+                change,
+            } => {
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::RemoteProtocolsChanged {
+                        peer_id,
+                        connection_id: id,
+                        change,
+                    });
+            }
             PoolEvent::AddressChange {
                 peer_id,
                 id,
@@ -939,6 +1269,40 @@ where
         }
     }
 
+    /// Removes a closed or failed inbound connection from the listener that accepted it.
+    fn remove_inbound_connection(&mut self, connection_id: ConnectionId) {
+        for state in self.listener_states.values_mut() {
+            if state.inbound.remove(&connection_id) {
+                return;
+            }
+        }
+    }
+
+    fn add_incoming(&mut self, incoming: PausedIncoming) {
+        let PausedIncoming {
+            connection_id,
+            upgrade,
+            local_addr,
+            send_back_addr,
+        } = incoming;
+
+        self.pool.add_incoming(
+            upgrade,
+            IncomingInfo {
+                local_addr: &local_addr,
+                send_back_addr: &send_back_addr,
+            },
+            connection_id,
+        );
+
+        self.pending_swarm_events
+            .push_back(SwarmEvent::IncomingConnection {
+                connection_id,
+                local_addr,
+                send_back_addr,
+            })
+    }
+
     fn handle_transport_event(
         &mut self,
         event: TransportEvent<
@@ -948,18 +1312,34 @@ where
     ) {
         match event {
             TransportEvent::Incoming {
-                listener_id: _,
+                listener_id,
                 upgrade,
                 local_addr,
                 send_back_addr,
             } => {
                 let connection_id = ConnectionId::next();
 
-                match self.behaviour.handle_pending_inbound_connection(
-                    connection_id,
-                    &local_addr,
-                    &send_back_addr,
-                ) {
+                let denied = match self.listener_states.get(&listener_id) {
+                    Some(state) => state.check_inbound().map_err(ConnectionDenied::new),
+                    None => Ok(()),
+                }
+                .and_then(|()| {
+                    if self.inbound_upgrades_paused
+                        && self.paused_incoming.len() >= self.max_paused_incoming
+                    {
+                        return Err(ConnectionDenied::new(PausedIncomingLimitExceeded {
+                            limit: self.max_paused_incoming,
+                        }));
+                    }
+                    Ok(())
+                });
+                match denied.and_then(|()| {
+                    self.behaviour.handle_pending_inbound_connection(
+                        connection_id,
+                        &local_addr,
+                        &send_back_addr,
+                    )
+                }) {
                     Ok(()) => {}
                     Err(cause) => {
                         let listen_error = ListenError::Denied { cause };
@@ -983,21 +1363,24 @@ where
                     }
                 }
 
-                self.pool.add_incoming(
-                    upgrade,
-                    IncomingInfo {
-                        local_addr: &local_addr,
-                        send_back_addr: &send_back_addr,
-                    },
+                if let Some(state) = self.listener_states.get_mut(&listener_id) {
+                    state.inbound.insert(connection_id);
+                }
+                let incoming = PausedIncoming {
                     connection_id,
-                );
-
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::IncomingConnection {
-                        connection_id,
-                        local_addr,
-                        send_back_addr,
-                    })
+                    upgrade,
+                    local_addr,
+                    send_back_addr,
+                };
+                if self.inbound_upgrades_paused {
+                    tracing::debug!(
+                        connection=%connection_id,
+                        "Holding back inbound connection while upgrades are paused"
+                    );
+                    self.paused_incoming.push_back(incoming);
+                    return;
+                }
+                self.add_incoming(incoming);
             }
             TransportEvent::NewAddress {
                 listener_id,
@@ -1056,6 +1439,7 @@ where
                     "Listener closed"
                 );
                 let addrs = self.listened_addrs.remove(&listener_id).unwrap_or_default();
+                self.listener_states.remove(&listener_id);
                 for addr in addrs.iter() {
                     self.behaviour.on_swarm_event(FromSwarm::ExpiredListenAddr(
                         ExpiredListenAddr { listener_id, addr },
@@ -1074,6 +1458,9 @@ where
                     })
             }
             TransportEvent::ListenerError { listener_id, error } => {
+                if let Some(state) = self.listener_states.get_mut(&listener_id) {
+                    state.last_error = Some(io::Error::new(error.kind(), error.to_string()));
+                }
                 self.behaviour
                     .on_swarm_event(FromSwarm::ListenerError(ListenerError {
                         listener_id,
@@ -1138,7 +1525,13 @@ where
                         .listened_addrs
                         .values()
                         .flatten()
-                        .filter_map(|server| self.transport.address_translation(server, &addr))
+                        .filter_map(|server| {
+                            let translated = self.transport.address_translation(server, &addr);
+                            match self.address_translator.as_mut() {
+                                Some(translator) => translator.translate(server, &addr, translated),
+                                None => translated,
+                            }
+                        })
                         .collect();
 
                     // remove duplicates
@@ -1198,6 +1591,15 @@ where
                 self.pending_swarm_events
                     .push_back(SwarmEvent::NewExternalAddrOfPeer { peer_id, address });
             }
+            ToSwarm::PauseInboundUpgrades => {
+                self.inbound_upgrades_paused = true;
+            }
+            ToSwarm::ResumeInboundUpgrades => {
+                self.inbound_upgrades_paused = false;
+                while let Some(incoming) = self.paused_incoming.pop_front() {
+                    self.add_incoming(incoming);
+                }
+            }
         }
     }
 
@@ -1272,6 +1674,16 @@ where
                 }
             }
 
+            // Close redundant connections to peers.
+            if let Some(Poll::Ready(consolidation)) = this
+                .deduplication
+                .as_mut()
+                .map(|deduplication| deduplication.poll(cx))
+            {
+                this.handle_consolidation(consolidation);
+                continue;
+            }
+
             // Poll the listener(s) for new connections.
             match Pin::new(&mut this.transport).poll(cx) {
                 Poll::Pending => {}
@@ -1372,6 +1784,34 @@ where
     })
 }
 
+/// Fails the dial of an address with an error of kind [`io::ErrorKind::TimedOut`] if it does
+/// not complete within the given timeout, see [`DialOpts`].
+///
+/// The timer starts when the dial is first polled, i.e. when it is started, not while it is
+/// queued behind other dials.
+fn with_dial_timeout<F, T>(
+    dial: F,
+    timeout: Option<Duration>,
+) -> future::BoxFuture<'static, Result<T, io::Error>>
+where
+    F: Future<Output = Result<T, io::Error>> + Send + 'static,
+    T: 'static,
+{
+    let Some(timeout) = timeout else {
+        return dial.boxed();
+    };
+    async move {
+        match future::select(dial.boxed(), futures_timer::Delay::new(timeout)).await {
+            future::Either::Left((result, _)) => result,
+            future::Either::Right(_) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("dial did not complete within {timeout:?}"),
+            )),
+        }
+    }
+    .boxed()
+}
+
 /// Stream of events returned by [`Swarm`].
 ///
 /// Includes events from the [`NetworkBehaviour`] as well as events about
@@ -1402,6 +1842,12 @@ where
 
 pub struct Config {
     pool_config: PoolConfig,
+    connection_policy: Option<Box<dyn ConnectionPolicy>>,
+    redundant_connection_grace_period: Duration,
+    dial_strategy: Option<Box<dyn DialStrategy>>,
+    address_translator: Option<Box<dyn AddressTranslator>>,
+    dial_report_events: bool,
+    max_paused_incoming: usize,
 }
 
 impl Config {
@@ -1410,6 +1856,12 @@ impl Config {
     pub fn with_executor(executor: impl Executor + Send + 'static) -> Self {
         Self {
             pool_config: PoolConfig::new(Some(Box::new(executor))),
+            connection_policy: None,
+            redundant_connection_grace_period: Duration::from_secs(10),
+            dial_strategy: None,
+            address_translator: None,
+            dial_report_events: false,
+            max_paused_incoming: 64,
         }
     }
 
@@ -1520,6 +1972,84 @@ impl Config {
         self.pool_config.idle_connection_timeout = timeout;
         self
     }
+
+    /// Whether to report the protocol negotiation of every stream via
+    /// [`SwarmEvent::StreamNegotiated`], e.g. to record its latency and outcome as metrics.
+    ///
+    /// Defaults to `false`.
+    pub fn with_stream_negotiation_events(mut self, enabled: bool) -> Self {
+        self.pool_config.stream_negotiation_events = enabled;
+        self
+    }
+
+    /// Logs a warning for every stream whose protocol negotiation takes longer than the given
+    /// duration.
+    ///
+    /// By default, slow negotiations are not logged.
+    pub fn with_slow_negotiation_threshold(mut self, threshold: Duration) -> Self {
+        self.pool_config.slow_negotiation_threshold = Some(threshold);
+        self
+    }
+
+    /// Consolidates multiple established connections to the same peer with the given
+    /// [`ConnectionPolicy`], closing all but the selected connection.
+    ///
+    /// Consolidations are reported via [`SwarmEvent::ConnectionsConsolidated`].
+    ///
+    /// By default, all connections to a peer are kept.
+    pub fn with_connection_policy(mut self, policy: impl ConnectionPolicy) -> Self {
+        self.connection_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// How long to wait after the latest connection to a peer was established before
+    /// consolidating its connections via the [`ConnectionPolicy`].
+    ///
+    /// Defaults to 10 seconds.
+    pub fn with_redundant_connection_grace_period(mut self, period: Duration) -> Self {
+        self.redundant_connection_grace_period = period;
+        self
+    }
+
+    /// Orders and schedules the dials to the addresses of a peer with the given
+    /// [`DialStrategy`], e.g. [`HappyEyeballs`].
+    ///
+    /// By default, addresses are dialed in the order they are provided, as many at once as the
+    /// dial concurrency factor allows.
+    pub fn with_dial_strategy(mut self, strategy: impl DialStrategy) -> Self {
+        self.dial_strategy = Some(Box::new(strategy));
+        self
+    }
+
+    /// Translates the addresses remotes observe for the local node into candidates for external
+    /// addresses with the given [`AddressTranslator`], e.g. [`PortForwarding`].
+    ///
+    /// By default, the translation of the transport is used.
+    pub fn with_address_translator(mut self, translator: impl AddressTranslator) -> Self {
+        self.address_translator = Some(Box::new(translator));
+        self
+    }
+
+    /// Enables or disables [`SwarmEvent::OutgoingConnectionReport`], listing the addresses
+    /// attempted for a failed outbound connection.
+    ///
+    /// Disabled by default.
+    pub fn with_dial_report_events(mut self, enabled: bool) -> Self {
+        self.dial_report_events = enabled;
+        self
+    }
+
+    /// Sets the maximum number of inbound connections held back while upgrading them is paused,
+    /// see [`ToSwarm::PauseInboundUpgrades`].
+    ///
+    /// Further inbound connections are denied with [`PausedIncomingLimitExceeded`] until upgrades
+    /// are resumed.
+    ///
+    /// Defaults to 64.
+    pub fn with_max_paused_incoming(mut self, n: usize) -> Self {
+        self.max_paused_incoming = n;
+        self
+    }
 }
 
 /// Possible errors when trying to establish or upgrade an outbound connection.
@@ -1745,6 +2275,27 @@ impl error::Error for ConnectionDenied {
     }
 }
 
+/// The cause of [`ListenError::Denied`] for an inbound connection exceeding the limit of
+/// connections held back while upgrading inbound connections is paused, see
+/// [`Config::with_max_paused_incoming`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PausedIncomingLimitExceeded {
+    /// The maximum number of inbound connections held back.
+    pub limit: usize,
+}
+
+impl fmt::Display for PausedIncomingLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "reached the limit of {} inbound connections held back while upgrades are paused",
+            self.limit
+        )
+    }
+}
+
+impl error::Error for PausedIncomingLimitExceeded {}
+
 /// Information about the connections obtained by [`Swarm::network_info()`].
 #[derive(Clone, Debug)]
 pub struct NetworkInfo {
@@ -1767,6 +2318,42 @@ impl NetworkInfo {
     }
 }
 
+/// Information about an established connection obtained by [`Swarm::connection_info()`].
+#[derive(Clone, Debug)]
+pub struct ConnectionDetails {
+    pub(crate) peer_id: PeerId,
+    pub(crate) endpoint: ConnectedPoint,
+    pub(crate) negotiated_protocols: Vec<NegotiatedProtocol>,
+    pub(crate) remote_supported_protocols: HashSet<StreamProtocol>,
+}
+
+impl ConnectionDetails {
+    /// The peer the connection is established to.
+    pub fn peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+
+    /// The endpoint of the connection.
+    pub fn endpoint(&self) -> &ConnectedPoint {
+        &self.endpoint
+    }
+
+    /// The protocols successfully negotiated on the connection so far, in the order they were
+    /// first negotiated.
+    ///
+    /// A protocol negotiated both on streams opened by us and on streams opened by the remote is
+    /// listed once per [`Endpoint`].
+    pub fn negotiated_protocols(&self) -> &[NegotiatedProtocol] {
+        &self.negotiated_protocols
+    }
+
+    /// The protocols the remote supports, as reported by the [`ConnectionHandler`] via
+    /// [`ConnectionHandlerEvent::ReportRemoteProtocols`].
+    pub fn remote_supported_protocols(&self) -> &HashSet<StreamProtocol> {
+        &self.remote_supported_protocols
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1847,6 +2434,21 @@ mod tests {
             && !swarm2.is_connected(swarm1.local_peer_id())
     }
 
+    /// Polls both swarms until the second one reports an event.
+    async fn next_event_of_second<TBehaviour>(
+        swarm1: &mut Swarm<TBehaviour>,
+        swarm2: &mut Swarm<TBehaviour>,
+    ) -> SwarmEvent<TBehaviour::ToSwarm>
+    where
+        TBehaviour: NetworkBehaviour,
+    {
+        future::poll_fn(|cx| {
+            while Swarm::poll_next_event(Pin::new(&mut *swarm1), cx).is_ready() {}
+            Swarm::poll_next_event(Pin::new(&mut *swarm2), cx)
+        })
+        .await
+    }
+
     /// Establishes multiple connections between two peers,
     /// after which one peer disconnects the other using [`Swarm::disconnect_peer_id`].
     ///
@@ -2251,6 +2853,151 @@ mod tests {
         assert!(!swarm.is_connected(&peer_id));
     }
 
+    /// Establishes multiple connections between two peers, of which the peer with a
+    /// [`ConnectionPolicy`] closes all but one after the grace period.
+    #[tokio::test]
+    async fn connection_policy_closes_redundant_connections() {
+        let mut swarm1 = new_test_swarm(
+            Config::with_tokio_executor()
+                .with_connection_policy(PreferNewest)
+                .with_redundant_connection_grace_period(Duration::from_millis(100)),
+        );
+        let mut swarm2 = new_test_swarm(Config::with_tokio_executor());
+
+        let addr2: Multiaddr = multiaddr::Protocol::Memory(rand::random::<u64>()).into();
+        swarm2.listen_on(addr2.clone()).unwrap();
+        let swarm2_id = *swarm2.local_peer_id();
+
+        let num_connections = 3;
+        for _ in 0..num_connections {
+            swarm1.dial(addr2.clone()).unwrap();
+        }
+
+        let mut num_closed = 0;
+        future::poll_fn(|cx| loop {
+            let poll1 = Swarm::poll_next_event(Pin::new(&mut swarm1), cx);
+            let poll2 = Swarm::poll_next_event(Pin::new(&mut swarm2), cx);
+            if let Poll::Ready(SwarmEvent::ConnectionsConsolidated {
+                peer_id, closed, ..
+            }) = &poll1
+            {
+                assert_eq!(*peer_id, swarm2_id);
+                num_closed += closed.len();
+            }
+            if num_closed == num_connections - 1 && swarms_connected(&swarm1, &swarm2, 1) {
+                return Poll::Ready(());
+            }
+
+            if poll1.is_pending() && poll2.is_pending() {
+                return Poll::Pending;
+            }
+        })
+        .await
+    }
+
+    /// Establishes multiple connections between two peers that both have a
+    /// [`ConnectionPolicy`], which must not close all connections between them.
+    #[tokio::test]
+    async fn connection_policies_on_both_sides_keep_one_connection() {
+        let grace_period = Duration::from_millis(100);
+        let mut swarm1 = new_test_swarm(
+            Config::with_tokio_executor()
+                .with_connection_policy(PreferNewest)
+                .with_redundant_connection_grace_period(grace_period),
+        );
+        let mut swarm2 = new_test_swarm(
+            Config::with_tokio_executor()
+                .with_connection_policy(PreferLowestLatency)
+                .with_redundant_connection_grace_period(grace_period),
+        );
+
+        let addr2: Multiaddr = multiaddr::Protocol::Memory(rand::random::<u64>()).into();
+        swarm2.listen_on(addr2.clone()).unwrap();
+
+        let num_connections = 3;
+        for _ in 0..num_connections {
+            swarm1.dial(addr2.clone()).unwrap();
+        }
+
+        // Keep both swarms running well beyond the grace periods of both peers.
+        let mut timeout = futures_timer::Delay::new(grace_period * 10);
+        let mut num_closed = 0;
+        future::poll_fn(|cx| loop {
+            let poll1 = Swarm::poll_next_event(Pin::new(&mut swarm1), cx);
+            let poll2 = Swarm::poll_next_event(Pin::new(&mut swarm2), cx);
+            for poll in [&poll1, &poll2] {
+                if let Poll::Ready(SwarmEvent::ConnectionsConsolidated { closed, .. }) = poll {
+                    num_closed += closed.len();
+                }
+            }
+            if timeout.poll_unpin(cx).is_ready() {
+                return Poll::Ready(());
+            }
+
+            if poll1.is_pending() && poll2.is_pending() {
+                return Poll::Pending;
+            }
+        })
+        .await;
+
+        assert_eq!(num_closed, num_connections - 1);
+        assert!(swarms_connected(&swarm1, &swarm2, 1));
+    }
+
+    #[tokio::test]
+    async fn listener_denies_inbound_connections_beyond_limit_and_while_paused() {
+        let mut swarm1 = new_test_swarm(Config::with_tokio_executor());
+        let mut swarm2 = new_test_swarm(Config::with_tokio_executor());
+
+        let addr2: Multiaddr = multiaddr::Protocol::Memory(rand::random::<u64>()).into();
+        let listener_id = swarm2
+            .listen_with_opts(ListenOpts::new(addr2.clone()).with_max_inbound_connections(1))
+            .unwrap();
+        swarm1.dial(addr2.clone()).unwrap();
+        swarm1.dial(addr2.clone()).unwrap();
+
+        let mut denied = Vec::new();
+        let mut num_established = 0;
+        while denied.is_empty() || num_established == 0 {
+            match next_event_of_second(&mut swarm1, &mut swarm2).await {
+                SwarmEvent::ConnectionEstablished { .. } => num_established += 1,
+                SwarmEvent::IncomingConnectionError {
+                    error: ListenError::Denied { cause },
+                    ..
+                } => denied.push(*cause.downcast_ref::<ListenerDenied>().unwrap()),
+                _ => {}
+            }
+        }
+        assert_eq!(denied, [ListenerDenied::LimitExceeded { limit: 1 }]);
+
+        let listeners = swarm2.listeners_detailed().collect::<Vec<_>>();
+        assert_eq!(listeners.len(), 1);
+        assert_eq!(listeners[0].id(), listener_id);
+        assert_eq!(listeners[0].transport(), "/memory");
+        assert_eq!(listeners[0].listen_addrs(), std::slice::from_ref(&addr2));
+        assert_eq!(listeners[0].num_inbound_connections(), 1);
+        assert_eq!(listeners[0].max_inbound_connections(), Some(1));
+        assert!(!listeners[0].is_paused());
+
+        assert!(swarm2.set_listener_max_inbound_connections(listener_id, None));
+        assert!(swarm2.pause_listener(listener_id));
+        swarm1.dial(addr2).unwrap();
+        loop {
+            if let SwarmEvent::IncomingConnectionError {
+                error: ListenError::Denied { cause },
+                ..
+            } = next_event_of_second(&mut swarm1, &mut swarm2).await
+            {
+                assert_eq!(
+                    cause.downcast_ref::<ListenerDenied>(),
+                    Some(&ListenerDenied::Paused)
+                );
+                break;
+            }
+        }
+        assert!(swarm2.listeners_detailed().next().unwrap().is_paused());
+    }
+
     #[tokio::test]
     async fn multiple_addresses_err() {
         // Tries dialing multiple addresses, and makes sure there's one dialing error per address.
@@ -2299,13 +3046,54 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn dial_report_lists_every_attempted_address() {
+        let target = PeerId::random();
+
+        let mut swarm = new_test_swarm(Config::with_tokio_executor().with_dial_report_events(true));
+
+        let addresses = vec![
+            multiaddr![Ip4([0, 0, 0, 0]), Tcp(rand::random::<u16>())],
+            multiaddr![Udp(rand::random::<u16>())],
+        ];
+
+        swarm
+            .dial(
+                DialOpts::peer_id(target)
+                    .addresses(addresses.clone())
+                    .build(),
+            )
+            .unwrap();
+
+        match swarm.next().await.unwrap() {
+            SwarmEvent::OutgoingConnectionError { .. } => {}
+            e => panic!("Unexpected event: {e:?}"),
+        }
+        match swarm.next().await.unwrap() {
+            SwarmEvent::OutgoingConnectionReport { report, .. } => {
+                let attempts = report.attempts();
+                assert_eq!(attempts.len(), addresses.len());
+                assert_eq!(attempts[0].transport(), "/ip4/tcp");
+                assert_eq!(attempts[1].transport(), "/udp");
+
+                for (attempt, address) in attempts.iter().zip(addresses) {
+                    assert_eq!(attempt.address(), &address.with_p2p(target).unwrap());
+                    assert!(attempt.duration().is_some());
+                    assert!(matches!(attempt.outcome(), DialOutcome::Failed(_)));
+                }
+            }
+            e => panic!("Unexpected event: {e:?}"),
+        }
+    }
+
     #[tokio::test]
     async fn aborting_pending_connection_surfaces_error() {
         let _ = tracing_subscriber::fmt()
             .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
             .try_init();
 
-        let mut dialer = new_test_swarm(Config::with_tokio_executor());
+        let mut dialer =
+            new_test_swarm(Config::with_tokio_executor().with_dial_report_events(true));
         let mut listener = new_test_swarm(Config::with_tokio_executor());
 
         let listener_peer_id = *listener.local_peer_id();
@@ -2334,6 +3122,51 @@ mod tests {
             } => {}
             e => panic!("Unexpected swarm event {e:?}."),
         }
+        match dialer.next().await.unwrap() {
+            SwarmEvent::OutgoingConnectionReport { report, .. } => {
+                assert_eq!(report.attempts().len(), 1);
+                assert_eq!(report.attempts()[0].outcome(), &DialOutcome::Unfinished);
+            }
+            e => panic!("Unexpected swarm event {e:?}."),
+        }
+    }
+
+    #[tokio::test]
+    async fn dial_times_out() {
+        let mut dialer = new_test_swarm(Config::with_tokio_executor());
+        let mut listener = new_test_swarm(Config::with_tokio_executor());
+
+        let listener_peer_id = *listener.local_peer_id();
+        listener.listen_on(multiaddr![Memory(0u64)]).unwrap();
+        let listener_address = match listener.next().await.unwrap() {
+            SwarmEvent::NewListenAddr { address, .. } => address,
+            e => panic!("Unexpected network event: {e:?}"),
+        };
+
+        // The listener is not polled anymore, thus the upgrade of the connection never completes.
+        dialer
+            .dial(
+                DialOpts::peer_id(listener_peer_id)
+                    .addresses(vec![listener_address.clone()])
+                    .timeout(Duration::from_secs(3600))
+                    .address_timeout(listener_address, Duration::from_millis(100))
+                    .build(),
+            )
+            .unwrap();
+
+        match dialer.next().await.unwrap() {
+            SwarmEvent::OutgoingConnectionError {
+                error: DialError::Transport(errors),
+                ..
+            } => {
+                assert_eq!(errors.len(), 1);
+                match &errors[0].1 {
+                    TransportError::Other(e) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+                    e => panic!("Unexpected transport error: {e:?}"),
+                }
+            }
+            e => panic!("Unexpected swarm event {e:?}."),
+        }
     }
 
     #[test]
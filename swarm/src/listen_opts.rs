@@ -5,6 +5,7 @@ use libp2p_core::Multiaddr;
 pub struct ListenOpts {
     id: ListenerId,
     address: Multiaddr,
+    max_inbound_connections: Option<u32>,
 }
 
 impl ListenOpts {
@@ -12,9 +13,18 @@ impl ListenOpts {
         ListenOpts {
             id: ListenerId::next(),
             address,
+            max_inbound_connections: None,
         }
     }
 
+    /// Limits the number of pending and established inbound connections accepted by the listener.
+    ///
+    /// Further inbound connections are denied with [`ListenerDenied::LimitExceeded`](crate::ListenerDenied::LimitExceeded).
+    pub fn with_max_inbound_connections(mut self, limit: u32) -> Self {
+        self.max_inbound_connections = Some(limit);
+        self
+    }
+
     /// Get the [`ListenerId`] of this listen attempt
     pub fn listener_id(&self) -> ListenerId {
         self.id
@@ -24,6 +34,11 @@ impl ListenOpts {
     pub fn address(&self) -> &Multiaddr {
         &self.address
     }
+
+    /// Get the maximum number of inbound connections of the listener, if limited
+    pub fn max_inbound_connections(&self) -> Option<u32> {
+        self.max_inbound_connections
+    }
 }
 
 impl From<Multiaddr> for ListenOpts {
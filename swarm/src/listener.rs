@@ -0,0 +1,131 @@
+use crate::{ConnectionId, ListenerId, Multiaddr};
+use std::collections::HashSet;
+use std::{error, fmt, io};
+
+/// The state of a listener, see [`Swarm::listeners_detailed`](crate::Swarm::listeners_detailed).
+#[derive(Debug)]
+pub struct ListenerInfo<'a> {
+    pub(crate) id: ListenerId,
+    pub(crate) state: &'a ListenerState,
+    pub(crate) listen_addrs: &'a [Multiaddr],
+}
+
+impl<'a> ListenerInfo<'a> {
+    /// The ID of the listener.
+    pub fn id(&self) -> ListenerId {
+        self.id
+    }
+
+    /// The address the listener was started on.
+    pub fn address(&self) -> &'a Multiaddr {
+        &self.state.address
+    }
+
+    /// The protocols of the transport listening, e.g. `/ip4/tcp/ws`.
+    pub fn transport(&self) -> String {
+        self.state
+            .address
+            .protocol_stack()
+            .filter(|tag| *tag != "p2p")
+            .fold(String::new(), |mut transport, tag| {
+                transport.push('/');
+                transport.push_str(tag);
+                transport
+            })
+    }
+
+    /// The addresses the listener is currently listening on.
+    pub fn listen_addrs(&self) -> &'a [Multiaddr] {
+        self.listen_addrs
+    }
+
+    /// Whether the listener is paused, see [`Swarm::pause_listener`](crate::Swarm::pause_listener).
+    pub fn is_paused(&self) -> bool {
+        self.state.paused
+    }
+
+    /// The number of pending and established inbound connections accepted by the listener.
+    pub fn num_inbound_connections(&self) -> u32 {
+        self.state.inbound.len() as u32
+    }
+
+    /// The maximum number of pending and established inbound connections of the listener.
+    pub fn max_inbound_connections(&self) -> Option<u32> {
+        self.state.max_inbound
+    }
+
+    /// The last non-fatal error reported by the listener, see
+    /// [`SwarmEvent::ListenerError`](crate::SwarmEvent::ListenerError).
+    pub fn last_error(&self) -> Option<&'a io::Error> {
+        self.state.last_error.as_ref()
+    }
+}
+
+/// The reason a listener denied an inbound connection.
+///
+/// Reported as the cause of [`ListenError::Denied`](crate::ListenError::Denied).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerDenied {
+    /// The listener is paused.
+    Paused,
+    /// The listener reached its maximum number of inbound connections.
+    LimitExceeded {
+        /// The maximum number of inbound connections of the listener.
+        limit: u32,
+    },
+}
+
+impl fmt::Display for ListenerDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenerDenied::Paused => f.write_str("listener is paused"),
+            ListenerDenied::LimitExceeded { limit } => {
+                write!(
+                    f,
+                    "listener reached its limit of {limit} inbound connections"
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for ListenerDenied {}
+
+#[derive(Debug)]
+pub(crate) struct ListenerState {
+    address: Multiaddr,
+    max_inbound: Option<u32>,
+    pub(crate) paused: bool,
+    /// The pending and established inbound connections accepted by the listener.
+    pub(crate) inbound: HashSet<ConnectionId>,
+    pub(crate) last_error: Option<io::Error>,
+}
+
+impl ListenerState {
+    pub(crate) fn new(address: Multiaddr, max_inbound: Option<u32>) -> Self {
+        Self {
+            address,
+            max_inbound,
+            paused: false,
+            inbound: HashSet::new(),
+            last_error: None,
+        }
+    }
+
+    pub(crate) fn set_max_inbound(&mut self, max_inbound: Option<u32>) {
+        self.max_inbound = max_inbound;
+    }
+
+    /// Checks whether a new inbound connection may be accepted.
+    pub(crate) fn check_inbound(&self) -> Result<(), ListenerDenied> {
+        if self.paused {
+            return Err(ListenerDenied::Paused);
+        }
+        match self.max_inbound {
+            Some(limit) if self.inbound.len() >= limit as usize => {
+                Err(ListenerDenied::LimitExceeded { limit })
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Spans covering the lifecycle of dials, connections and streams.
+//!
+//! With the `lifecycle-spans` feature enabled, these spans are emitted at `INFO` level with
+//! stable names and `libp2p.*` fields, e.g. for exporting them to OpenTelemetry via
+//! `tracing-opentelemetry`. Without the feature, all of them are disabled.
+//!
+//! - `libp2p.connection.outgoing`: An outgoing connection, from the first dial until it is
+//!   established or failed. Parent of the `libp2p.dial` spans.
+//! - `libp2p.dial`: A dial of a single address, including the security and muxer handshakes.
+//! - `libp2p.connection.incoming`: An incoming connection, until the security and muxer
+//!   handshakes completed or failed.
+//! - `libp2p.connection`: An established connection, until it is closed.
+//! - `libp2p.stream.negotiation`: The protocol negotiation and upgrade of a stream.
+//! - `libp2p.stream`: A negotiated stream, until it is dropped.
+
+use crate::{ConnectionId, StreamUpgradeError};
+use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use std::fmt::Display;
+use tracing::Span;
+
+/// Creates the span of an outgoing connection.
+pub(crate) fn outgoing_connection(
+    id: ConnectionId,
+    peer: Option<PeerId>,
+    role_override: Endpoint,
+) -> Span {
+    #[cfg(feature = "lifecycle-spans")]
+    {
+        let span = tracing::info_span!(
+            parent: Span::none(),
+            "libp2p.connection.outgoing",
+            libp2p.connection_id = %id,
+            libp2p.peer_id = peer.map(tracing::field::display),
+            libp2p.role_override = ?role_override,
+        );
+        span.follows_from(Span::current());
+        span
+    }
+    #[cfg(not(feature = "lifecycle-spans"))]
+    {
+        let _ = (id, peer, role_override);
+        Span::none()
+    }
+}
+
+/// Creates the span of a dial of a single address, as part of the given outgoing connection.
+pub(crate) fn dial(connection: &Span, address: &Multiaddr) -> Span {
+    #[cfg(feature = "lifecycle-spans")]
+    {
+        tracing::info_span!(
+            parent: connection,
+            "libp2p.dial",
+            libp2p.remote_addr = %address,
+            libp2p.error = tracing::field::Empty,
+        )
+    }
+    #[cfg(not(feature = "lifecycle-spans"))]
+    {
+        let _ = (connection, address);
+        Span::none()
+    }
+}
+
+/// Creates the span of an incoming connection.
+pub(crate) fn incoming_connection(
+    id: ConnectionId,
+    local_addr: &Multiaddr,
+    send_back_addr: &Multiaddr,
+) -> Span {
+    #[cfg(feature = "lifecycle-spans")]
+    {
+        let span = tracing::info_span!(
+            parent: Span::none(),
+            "libp2p.connection.incoming",
+            libp2p.connection_id = %id,
+            libp2p.local_addr = %local_addr,
+            libp2p.remote_addr = %send_back_addr,
+        );
+        span.follows_from(Span::current());
+        span
+    }
+    #[cfg(not(feature = "lifecycle-spans"))]
+    {
+        let _ = (id, local_addr, send_back_addr);
+        Span::none()
+    }
+}
+
+/// Creates the span of an established connection.
+pub(crate) fn established_connection(
+    id: ConnectionId,
+    peer: PeerId,
+    endpoint: &ConnectedPoint,
+) -> Span {
+    #[cfg(feature = "lifecycle-spans")]
+    {
+        let span = tracing::info_span!(
+            parent: Span::none(),
+            "libp2p.connection",
+            libp2p.connection_id = %id,
+            libp2p.peer_id = %peer,
+            libp2p.remote_addr = %endpoint.get_remote_address(),
+            libp2p.endpoint = if endpoint.is_dialer() { "dialer" } else { "listener" },
+        );
+        span.follows_from(Span::current());
+        span
+    }
+    #[cfg(not(feature = "lifecycle-spans"))]
+    {
+        let _ = (id, peer, endpoint);
+        Span::none()
+    }
+}
+
+/// Creates the span of the negotiation and upgrade of a stream of the current connection.
+pub(crate) fn stream_negotiation(direction: Endpoint) -> Span {
+    #[cfg(feature = "lifecycle-spans")]
+    {
+        tracing::info_span!(
+            "libp2p.stream.negotiation",
+            libp2p.direction = stream_direction(direction),
+            libp2p.protocol = tracing::field::Empty,
+            libp2p.error = tracing::field::Empty,
+        )
+    }
+    #[cfg(not(feature = "lifecycle-spans"))]
+    {
+        let _ = direction;
+        Span::none()
+    }
+}
+
+/// Creates the span of a negotiated stream of the given connection.
+pub(crate) fn stream(connection: &Span, direction: Endpoint, protocol: &str) -> Span {
+    #[cfg(feature = "lifecycle-spans")]
+    {
+        tracing::info_span!(
+            parent: connection,
+            "libp2p.stream",
+            libp2p.direction = stream_direction(direction),
+            libp2p.protocol = protocol,
+        )
+    }
+    #[cfg(not(feature = "lifecycle-spans"))]
+    {
+        let _ = (connection, direction, protocol);
+        Span::none()
+    }
+}
+
+/// Records the error a dial or negotiation failed with on its span.
+pub(crate) fn record_error(span: &Span, error: &impl Display) {
+    span.record("libp2p.error", tracing::field::display(error));
+}
+
+/// Records the error the negotiation or upgrade of a stream failed with on its span.
+pub(crate) fn record_stream_upgrade_error<TErr>(span: &Span, error: &StreamUpgradeError<TErr>) {
+    match error {
+        StreamUpgradeError::Timeout => record_error(span, &"timeout"),
+        StreamUpgradeError::Apply(_) => record_error(span, &"upgrade failed"),
+        StreamUpgradeError::NegotiationFailed => record_error(span, &"negotiation failed"),
+        StreamUpgradeError::Io(e) => record_error(span, e),
+    }
+}
+
+#[cfg(feature = "lifecycle-spans")]
+fn stream_direction(direction: Endpoint) -> &'static str {
+    match direction {
+        Endpoint::Dialer => "outbound",
+        Endpoint::Listener => "inbound",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "lifecycle-spans"))]
+    #[test]
+    fn spans_are_disabled_without_feature() {
+        let address: Multiaddr = "/memory/1234".parse().unwrap();
+        let connection = outgoing_connection(ConnectionId::next(), None, Endpoint::Dialer);
+
+        assert!(connection.is_disabled());
+        assert!(dial(&connection, &address).is_disabled());
+        assert!(stream_negotiation(Endpoint::Dialer).is_disabled());
+    }
+
+    #[cfg(feature = "lifecycle-spans")]
+    mod lifecycle_spans {
+        use super::*;
+        use std::{
+            collections::HashMap,
+            fmt::Debug,
+            sync::{Arc, Mutex},
+        };
+        use tracing::{
+            field::{Field, Visit},
+            span::{Attributes, Id, Record},
+            Subscriber,
+        };
+        use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+        /// The name, parent and fields of a span.
+        #[derive(Debug, Default, Clone)]
+        struct RecordedSpan {
+            name: &'static str,
+            parent: Option<&'static str>,
+            fields: HashMap<&'static str, String>,
+        }
+
+        impl Visit for RecordedSpan {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.fields.insert(field.name(), format!("{value:?}"));
+            }
+
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.fields.insert(field.name(), value.to_owned());
+            }
+        }
+
+        #[derive(Default, Clone)]
+        struct Recorder(Arc<Mutex<HashMap<Id, RecordedSpan>>>);
+
+        impl Recorder {
+            fn span(&self, name: &str) -> RecordedSpan {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .find(|span| span.name == name)
+                    .cloned()
+                    .unwrap_or_else(|| panic!("no span {name}"))
+            }
+        }
+
+        impl<S> Layer<S> for Recorder
+        where
+            S: Subscriber + for<'a> LookupSpan<'a>,
+        {
+            fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+                let mut span = RecordedSpan {
+                    name: attrs.metadata().name(),
+                    parent: ctx.span(id).and_then(|s| s.parent()).map(|p| p.name()),
+                    ..Default::default()
+                };
+                attrs.record(&mut span);
+                self.0.lock().unwrap().insert(id.clone(), span);
+            }
+
+            fn on_record(&self, id: &Id, values: &Record<'_>, _: Context<'_, S>) {
+                if let Some(span) = self.0.lock().unwrap().get_mut(id) {
+                    values.record(span);
+                }
+            }
+        }
+
+        fn with_recorder(f: impl FnOnce()) -> Recorder {
+            let recorder = Recorder::default();
+            let subscriber = tracing_subscriber::registry().with(recorder.clone());
+            tracing::subscriber::with_default(subscriber, f);
+            recorder
+        }
+
+        #[test]
+        fn dial_spans_are_children_of_outgoing_connection() {
+            let address: Multiaddr = "/memory/1234".parse().unwrap();
+            let peer = PeerId::random();
+
+            let recorder = with_recorder(|| {
+                let connection =
+                    outgoing_connection(ConnectionId::next(), Some(peer), Endpoint::Dialer);
+                let dial = dial(&connection, &address);
+                record_error(&dial, &"connection refused");
+            });
+
+            let connection = recorder.span("libp2p.connection.outgoing");
+            assert_eq!(connection.parent, None);
+            assert_eq!(connection.fields["libp2p.peer_id"], peer.to_string());
+
+            let dial = recorder.span("libp2p.dial");
+            assert_eq!(dial.parent, Some("libp2p.connection.outgoing"));
+            assert_eq!(dial.fields["libp2p.remote_addr"], address.to_string());
+            assert_eq!(dial.fields["libp2p.error"], "connection refused");
+        }
+
+        #[test]
+        fn stream_spans_record_direction_protocol_and_errors() {
+            let endpoint = ConnectedPoint::Listener {
+                local_addr: "/memory/1".parse().unwrap(),
+                send_back_addr: "/memory/2".parse().unwrap(),
+            };
+
+            let recorder = with_recorder(|| {
+                let connection =
+                    established_connection(ConnectionId::next(), PeerId::random(), &endpoint);
+                let _entered = connection.enter();
+                let negotiation = stream_negotiation(Endpoint::Listener);
+                record_stream_upgrade_error::<()>(&negotiation, &StreamUpgradeError::Timeout);
+                let _stream = stream(&connection, Endpoint::Dialer, "/ping/1.0.0");
+            });
+
+            let connection = recorder.span("libp2p.connection");
+            assert_eq!(connection.fields["libp2p.endpoint"], "listener");
+            assert_eq!(connection.fields["libp2p.remote_addr"], "/memory/2");
+
+            let negotiation = recorder.span("libp2p.stream.negotiation");
+            assert_eq!(negotiation.parent, Some("libp2p.connection"));
+            assert_eq!(negotiation.fields["libp2p.direction"], "inbound");
+            assert_eq!(negotiation.fields["libp2p.error"], "timeout");
+
+            let stream = recorder.span("libp2p.stream");
+            assert_eq!(stream.parent, Some("libp2p.connection"));
+            assert_eq!(stream.fields["libp2p.direction"], "outbound");
+            assert_eq!(stream.fields["libp2p.protocol"], "/ping/1.0.0");
+        }
+    }
+}
@@ -26,17 +26,29 @@ impl ActiveStreamCounter {
     }
 }
 
+/// A negotiated stream of a connection.
+///
+/// Implements [`AsyncRead`] and [`AsyncWrite`] of `futures`. With the `tokio` feature, it
+/// additionally implements their `tokio` counterparts, so it can be used with `tokio` APIs
+/// without a compatibility wrapper.
 #[derive(Debug)]
 pub struct Stream {
     stream: Negotiated<SubstreamBox>,
     counter: Option<ActiveStreamCounter>,
+    /// The `libp2p.stream` span, closed once the stream is dropped.
+    _span: tracing::Span,
 }
 
 impl Stream {
-    pub(crate) fn new(stream: Negotiated<SubstreamBox>, counter: ActiveStreamCounter) -> Self {
+    pub(crate) fn new(
+        stream: Negotiated<SubstreamBox>,
+        counter: ActiveStreamCounter,
+        span: tracing::Span,
+    ) -> Self {
         Self {
             stream,
             counter: Some(counter),
+            _span: span,
         }
     }
 
@@ -96,3 +108,54 @@ impl AsyncWrite for Stream {
         Pin::new(&mut self.get_mut().stream).poll_close(cx)
     }
 }
+
+#[cfg(all(
+    feature = "tokio",
+    not(any(target_os = "emscripten", target_os = "wasi", target_os = "unknown"))
+))]
+impl tokio::io::AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let n = futures::ready!(AsyncRead::poll_read(self, cx, buf.initialize_unfilled()))?;
+        buf.advance(n);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(all(
+    feature = "tokio",
+    not(any(target_os = "emscripten", target_os = "wasi", target_os = "unknown"))
+))]
+impl tokio::io::AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        AsyncWrite::poll_write_vectored(self, cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        AsyncWrite::poll_flush(self, cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        AsyncWrite::poll_close(self, cx)
+    }
+}
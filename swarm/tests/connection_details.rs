@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p_core::{transport::MemoryTransport, upgrade::Version, Endpoint, Transport};
+use libp2p_identity::{Keypair, PeerId};
+use libp2p_swarm::{
+    handler::ProtocolSupport, Config, NetworkBehaviour, StreamProtocol, Swarm, SwarmEvent,
+};
+
+#[derive(NetworkBehaviour)]
+#[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+struct Behaviour {
+    identify: libp2p_identify::Behaviour,
+    ping: libp2p_ping::Behaviour,
+}
+
+#[async_std::test]
+async fn connection_details_track_negotiated_and_remote_protocols() {
+    let mut listener = new_swarm();
+    let mut dialer = new_swarm();
+
+    listener.listen_on("/memory/0".parse().unwrap()).unwrap();
+    let listen_addr = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = listener.select_next_some().await {
+            break address;
+        }
+    };
+    dialer.dial(listen_addr).unwrap();
+    async_std::task::spawn(listener.collect::<Vec<_>>());
+
+    let ping = StreamProtocol::new("/ipfs/ping/1.0.0");
+    let mut connection_id = None;
+    let mut remote_protocols_reported = false;
+    let mut pinged = false;
+    while !(remote_protocols_reported && pinged) {
+        match dialer.select_next_some().await {
+            SwarmEvent::RemoteProtocolsChanged {
+                connection_id: id,
+                change: ProtocolSupport::Added(added),
+                ..
+            } => {
+                assert!(added.contains(&ping));
+                connection_id = Some(id);
+                remote_protocols_reported = true;
+            }
+            SwarmEvent::Behaviour(BehaviourEvent::Ping(libp2p_ping::Event {
+                connection,
+                result: Ok(_),
+                ..
+            })) => {
+                connection_id = Some(connection);
+                pinged = true;
+            }
+            _ => {}
+        }
+    }
+
+    let details = dialer.connection_info(connection_id.unwrap()).unwrap();
+    assert!(details.endpoint().is_dialer());
+    assert!(details.remote_supported_protocols().contains(&ping));
+    assert!(details
+        .negotiated_protocols()
+        .iter()
+        .any(|negotiated| negotiated.protocol == ping.as_ref()
+            && negotiated.endpoint == Endpoint::Dialer));
+}
+
+fn new_swarm() -> Swarm<Behaviour> {
+    let identity = Keypair::generate_ed25519();
+    let peer_id = PeerId::from(identity.public());
+    let transport = MemoryTransport::default()
+        .upgrade(Version::V1)
+        .authenticate(libp2p_plaintext::Config::new(&identity))
+        .multiplex(libp2p_yamux::Config::default())
+        .boxed();
+
+    Swarm::new(
+        transport,
+        Behaviour {
+            identify: libp2p_identify::Behaviour::new(libp2p_identify::Config::new(
+                "/test/1.0.0".to_owned(),
+                identity.public(),
+            )),
+            ping: libp2p_ping::Behaviour::default(),
+        },
+        peer_id,
+        Config::with_async_std_executor().with_idle_connection_timeout(Duration::from_secs(5)),
+    )
+}
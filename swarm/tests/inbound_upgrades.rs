@@ -0,0 +1,160 @@
+use std::{
+    collections::VecDeque,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use libp2p_core::{transport::MemoryTransport, upgrade::Version, Endpoint, Multiaddr, Transport};
+use libp2p_identity::{Keypair, PeerId};
+use libp2p_swarm::{
+    dummy, Config, ConnectionDenied, ConnectionId, FromSwarm, ListenError, NetworkBehaviour,
+    PausedIncomingLimitExceeded, Swarm, SwarmEvent, THandler, THandlerInEvent, THandlerOutEvent,
+    ToSwarm,
+};
+use libp2p_swarm_test::SwarmExt;
+
+#[async_std::test]
+async fn paused_inbound_connections_are_upgraded_once_resumed() {
+    let mut dialer = Swarm::new_ephemeral(|_| Behaviour::default());
+    let mut listener = Swarm::new_ephemeral(|_| Behaviour::default());
+    let (listen_addr, _) = listener.listen().await;
+
+    listener.behaviour_mut().pause();
+    dialer.dial(listen_addr).unwrap();
+    async_std::task::spawn(dialer.loop_on_next());
+
+    let incoming = async_std::future::timeout(
+        Duration::from_millis(500),
+        listener.wait(|e| match e {
+            SwarmEvent::IncomingConnection { .. } => Some(()),
+            _ => None,
+        }),
+    )
+    .await;
+    assert!(incoming.is_err(), "inbound connection was not held back");
+
+    listener.behaviour_mut().resume();
+    listener
+        .wait(|e| match e {
+            SwarmEvent::ConnectionEstablished { endpoint, .. } => {
+                assert!(endpoint.is_listener());
+                Some(())
+            }
+            _ => None,
+        })
+        .await;
+}
+
+#[async_std::test]
+async fn paused_inbound_connections_beyond_limit_are_denied() {
+    let identity = Keypair::generate_ed25519();
+    let mut listener = Swarm::new(
+        MemoryTransport::default()
+            .upgrade(Version::V1)
+            .authenticate(libp2p_plaintext::Config::new(&identity))
+            .multiplex(libp2p_yamux::Config::default())
+            .boxed(),
+        Behaviour::default(),
+        identity.public().to_peer_id(),
+        Config::with_async_std_executor().with_max_paused_incoming(1),
+    );
+    listener.listen_on("/memory/0".parse().unwrap()).unwrap();
+    let listen_addr = listener
+        .wait(|e| match e {
+            SwarmEvent::NewListenAddr { address, .. } => Some(address),
+            _ => None,
+        })
+        .await;
+
+    listener.behaviour_mut().pause();
+    for _ in 0..2 {
+        let mut dialer = Swarm::new_ephemeral(|_| Behaviour::default());
+        dialer.dial(listen_addr.clone()).unwrap();
+        async_std::task::spawn(dialer.loop_on_next());
+    }
+
+    let cause = listener
+        .wait(|e| match e {
+            SwarmEvent::IncomingConnectionError {
+                error: ListenError::Denied { cause },
+                ..
+            } => Some(cause),
+            SwarmEvent::IncomingConnection { .. } => panic!("inbound connection was not held back"),
+            _ => None,
+        })
+        .await;
+    assert_eq!(
+        cause.downcast::<PausedIncomingLimitExceeded>().unwrap(),
+        PausedIncomingLimitExceeded { limit: 1 }
+    );
+
+    // The connection held back is still upgraded once resumed.
+    listener.behaviour_mut().resume();
+    listener
+        .wait(|e| match e {
+            SwarmEvent::ConnectionEstablished { endpoint, .. } => {
+                assert!(endpoint.is_listener());
+                Some(())
+            }
+            _ => None,
+        })
+        .await;
+}
+
+#[derive(Default)]
+struct Behaviour {
+    events: VecDeque<ToSwarm<<Self as NetworkBehaviour>::ToSwarm, THandlerInEvent<Self>>>,
+}
+
+impl Behaviour {
+    fn pause(&mut self) {
+        self.events.push_back(ToSwarm::PauseInboundUpgrades);
+    }
+
+    fn resume(&mut self) {
+        self.events.push_back(ToSwarm::ResumeInboundUpgrades);
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = void::Void;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        _: THandlerOutEvent<Self>,
+    ) {
+    }
+
+    fn on_swarm_event(&mut self, _: FromSwarm) {}
+
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,87 @@
+use libp2p_swarm::behaviour::pinned;
+use libp2p_swarm::{Swarm, SwarmEvent};
+use libp2p_swarm_test::SwarmExt;
+use std::time::Duration;
+
+#[async_std::test]
+async fn redials_pinned_peer_after_disconnect() {
+    let config = pinned::Config::default()
+        .with_initial_backoff(Duration::from_millis(10))
+        .with_jitter(0.0);
+    let mut dialer = Swarm::new_ephemeral(|_| pinned::Behaviour::new(config));
+    let mut listener = Swarm::new_ephemeral(|_| pinned::Behaviour::new(pinned::Config::default()));
+    let (address, _) = listener.listen().await;
+    let listener_id = *listener.local_peer_id();
+    let dialer_id = *dialer.local_peer_id();
+
+    dialer
+        .behaviour_mut()
+        .pin_with_addresses(listener_id, vec![address]);
+
+    let ([connected], []): ([pinned::Event; 1], [pinned::Event; 0]) =
+        libp2p_swarm_test::drive(&mut dialer, &mut listener).await;
+    assert_eq!(
+        connected,
+        pinned::Event::Connected {
+            peer_id: listener_id
+        }
+    );
+    assert_eq!(
+        dialer.behaviour().state(&listener_id),
+        Some(pinned::State::Connected)
+    );
+
+    // The dialer may report the connection before the listener established it.
+    if !listener.is_connected(&dialer_id) {
+        listener
+            .wait(|e| matches!(e, SwarmEvent::ConnectionEstablished { .. }).then_some(()))
+            .await;
+    }
+    listener.disconnect_peer_id(dialer_id).unwrap();
+
+    let ([disconnected, reconnected], []): ([pinned::Event; 2], [pinned::Event; 0]) =
+        libp2p_swarm_test::drive(&mut dialer, &mut listener).await;
+    assert_eq!(
+        disconnected,
+        pinned::Event::Disconnected {
+            peer_id: listener_id,
+            retry_in: Duration::from_millis(10)
+        }
+    );
+    assert_eq!(
+        reconnected,
+        pinned::Event::Connected {
+            peer_id: listener_id
+        }
+    );
+}
+
+#[async_std::test]
+async fn backs_off_after_failed_dials() {
+    let config = pinned::Config::default()
+        .with_initial_backoff(Duration::from_millis(10))
+        .with_jitter(0.0);
+    let mut dialer = Swarm::new_ephemeral(|_| pinned::Behaviour::new(config));
+    let peer_id = libp2p_identity::PeerId::random();
+
+    dialer
+        .behaviour_mut()
+        .pin_with_addresses(peer_id, vec!["/memory/1234".parse().unwrap()]);
+
+    for failures in 1..=2 {
+        let event = dialer
+            .wait(|event| match event {
+                SwarmEvent::Behaviour(event @ pinned::Event::DialFailed { .. }) => Some(event),
+                _ => None,
+            })
+            .await;
+        assert_eq!(
+            event,
+            pinned::Event::DialFailed {
+                peer_id,
+                failures,
+                retry_in: Duration::from_millis(10) * 2u32.pow(failures)
+            }
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p_core::{transport::MemoryTransport, upgrade::Version, Endpoint, Transport};
+use libp2p_identity::{Keypair, PeerId};
+use libp2p_swarm::{Config, NegotiationOutcome, StreamNegotiation, Swarm, SwarmEvent};
+
+#[async_std::test]
+async fn stream_negotiations_are_reported() {
+    let mut listener = new_swarm();
+    let mut dialer = new_swarm();
+
+    listener.listen_on("/memory/0".parse().unwrap()).unwrap();
+    let listen_addr = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = listener.select_next_some().await {
+            break address;
+        }
+    };
+    dialer.dial(listen_addr).unwrap();
+
+    // Both peers open a ping stream, thus observe a negotiation as dialer and as listener.
+    let (outbound, inbound) = futures::future::join(
+        next_negotiation(&mut dialer, Endpoint::Dialer),
+        next_negotiation(&mut listener, Endpoint::Listener),
+    )
+    .await;
+
+    for negotiation in [outbound, inbound] {
+        assert_eq!(
+            negotiation.outcome,
+            NegotiationOutcome::Negotiated("/ipfs/ping/1.0.0".to_owned())
+        );
+        assert_eq!(negotiation.rejected_protocols, 0);
+        assert_eq!(negotiation.ls_requests, 0);
+    }
+}
+
+async fn next_negotiation(
+    swarm: &mut Swarm<libp2p_ping::Behaviour>,
+    endpoint: Endpoint,
+) -> StreamNegotiation {
+    loop {
+        if let SwarmEvent::StreamNegotiated { negotiation, .. } = swarm.select_next_some().await {
+            if negotiation.endpoint == endpoint {
+                return negotiation;
+            }
+        }
+    }
+}
+
+fn new_swarm() -> Swarm<libp2p_ping::Behaviour> {
+    let identity = Keypair::generate_ed25519();
+    let peer_id = PeerId::from(identity.public());
+    let transport = MemoryTransport::default()
+        .upgrade(Version::V1)
+        .authenticate(libp2p_plaintext::Config::new(&identity))
+        .multiplex(libp2p_yamux::Config::default())
+        .boxed();
+
+    Swarm::new(
+        transport,
+        libp2p_ping::Behaviour::default(),
+        peer_id,
+        Config::with_async_std_executor()
+            .with_idle_connection_timeout(Duration::from_secs(5))
+            .with_stream_negotiation_events(true),
+    )
+}
@@ -319,6 +319,37 @@ fn with_toggle() {
     }
 }
 
+#[test]
+fn with_toggle_group() {
+    use libp2p_swarm::behaviour::toggle::Toggle;
+
+    #[derive(NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    struct Foo {
+        identify: Toggle<identify::Behaviour>,
+        #[behaviour(toggle_group = "optional")]
+        ping: Toggle<ping::Behaviour>,
+        #[behaviour(toggle_group = "optional")]
+        dummy: Toggle<libp2p_swarm::dummy::Behaviour>,
+    }
+
+    let mut foo = Foo {
+        identify: None.into(),
+        ping: None.into(),
+        dummy: None.into(),
+    };
+    assert!(!foo.is_optional_enabled());
+
+    foo.enable_optional(ping::Behaviour::default(), libp2p_swarm::dummy::Behaviour);
+    assert!(foo.is_optional_enabled());
+    assert!(foo.ping.is_enabled() && foo.dummy.is_enabled());
+    assert!(!foo.identify.is_enabled());
+
+    foo.disable_optional();
+    assert!(!foo.is_optional_enabled());
+    assert!(!foo.ping.is_enabled() && !foo.dummy.is_enabled());
+}
+
 #[test]
 fn with_either() {
     use either::Either;
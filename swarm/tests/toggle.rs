@@ -0,0 +1,67 @@
+use libp2p_request_response as request_response;
+use libp2p_swarm::behaviour::toggle::Toggle;
+use libp2p_swarm::{StreamProtocol, Swarm, SwarmEvent};
+use libp2p_swarm_test::SwarmExt;
+
+type Behaviour = Toggle<request_response::json::Behaviour<String, String>>;
+
+fn request_response() -> request_response::json::Behaviour<String, String> {
+    request_response::json::Behaviour::new(
+        [(
+            StreamProtocol::new("/echo/1"),
+            request_response::ProtocolSupport::Full,
+        )],
+        request_response::Config::default(),
+    )
+}
+
+#[async_std::test]
+async fn enabled_behaviour_does_not_see_earlier_connections() {
+    let mut swarm1 = Swarm::new_ephemeral(|_| Behaviour::from(None));
+    let mut swarm2 = Swarm::new_ephemeral(|_| Behaviour::from(Some(request_response())));
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+    let peer2 = *swarm2.local_peer_id();
+
+    swarm1.behaviour_mut().enable(request_response());
+
+    // The inner behaviour never saw this connection and must not learn about its closing.
+    swarm1.disconnect_peer_id(peer2).unwrap();
+    swarm1
+        .wait(|e| matches!(e, SwarmEvent::ConnectionClosed { .. }).then_some(()))
+        .await;
+
+    swarm1.connect(&mut swarm2).await;
+    swarm1
+        .behaviour_mut()
+        .as_mut()
+        .unwrap()
+        .send_request(&peer2, "ping".to_owned());
+
+    async_std::task::spawn(async move {
+        loop {
+            if let SwarmEvent::Behaviour(request_response::Event::Message {
+                message: request_response::Message::Request { channel, .. },
+                ..
+            }) = swarm2.next_swarm_event().await
+            {
+                let _ = swarm2
+                    .behaviour_mut()
+                    .as_mut()
+                    .unwrap()
+                    .send_response(channel, "pong".to_owned());
+            }
+        }
+    });
+
+    let response = swarm1
+        .wait(|e| match e {
+            SwarmEvent::Behaviour(request_response::Event::Message {
+                message: request_response::Message::Response { response, .. },
+                ..
+            }) => Some(response),
+            _ => None,
+        })
+        .await;
+    assert_eq!(response, "pong");
+}
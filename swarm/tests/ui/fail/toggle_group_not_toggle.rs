@@ -0,0 +1,12 @@
+use libp2p_ping as ping;
+
+#[derive(libp2p_swarm::NetworkBehaviour)]
+#[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+struct Foo {
+    #[behaviour(toggle_group = "optional")]
+    ping: ping::Behaviour,
+}
+
+fn main() {
+
+}
@@ -0,0 +1,175 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use hickory_resolver::config::ResolverOpts;
+use hickory_resolver::error::ResolveError;
+use libp2p_core::multiaddr::Protocol;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Configuration of the cache of DNS lookups, including `/dnsaddr` TXT records.
+///
+/// Lookups are cached for the TTL of their records, clamped to the configured bounds. Raising the
+/// minimum TTL reduces the number of lookups, e.g. when name servers are slow or expensive to
+/// reach, at the cost of reacting to changed records later.
+///
+/// Applied to the [`ResolverOpts`] passed to the constructors of the [`Transport`](crate::Transport).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    size: usize,
+    min_ttl: Option<Duration>,
+    max_ttl: Option<Duration>,
+    negative_min_ttl: Option<Duration>,
+    negative_max_ttl: Option<Duration>,
+}
+
+impl CacheConfig {
+    /// Sets the maximum number of cached lookups.
+    ///
+    /// Defaults to 32.
+    pub fn with_size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Clamps the TTL of successful lookups to the given bounds.
+    ///
+    /// By default, the TTL of the records is used, between 0 seconds and 1 day.
+    pub fn with_ttl_bounds(mut self, min: Duration, max: Duration) -> Self {
+        self.min_ttl = Some(min);
+        self.max_ttl = Some(max);
+        self
+    }
+
+    /// Clamps the TTL of lookups that did not yield any records to the given bounds.
+    ///
+    /// By default, the TTL reported by the name server is used, between 0 seconds and 1 day.
+    pub fn with_negative_ttl_bounds(mut self, min: Duration, max: Duration) -> Self {
+        self.negative_min_ttl = Some(min);
+        self.negative_max_ttl = Some(max);
+        self
+    }
+
+    /// Applies the configuration to the given resolver options.
+    pub fn apply(&self, opts: &mut ResolverOpts) {
+        opts.cache_size = self.size;
+        opts.positive_min_ttl = self.min_ttl;
+        opts.positive_max_ttl = self.max_ttl;
+        opts.negative_min_ttl = self.negative_min_ttl;
+        opts.negative_max_ttl = self.negative_max_ttl;
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            size: 32,
+            min_ttl: None,
+            max_ttl: None,
+            negative_min_ttl: None,
+            negative_max_ttl: None,
+        }
+    }
+}
+
+/// Caches failed lookups of DNS names, regardless of the cause of the failure.
+///
+/// Unlike the cache of the resolver, this includes e.g. lookups that timed out.
+#[derive(Debug, Default)]
+pub(crate) struct NegativeCache {
+    ttl: Option<Duration>,
+    entries: Mutex<HashMap<String, (Instant, ResolveError)>>,
+}
+
+impl NegativeCache {
+    pub(crate) fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Returns the error of the failed lookup of the given DNS protocol component, if cached.
+    pub(crate) fn get(&self, name: &Protocol<'_>) -> Option<ResolveError> {
+        self.ttl?;
+
+        let mut entries = self.entries.lock();
+        let key = name.to_string();
+        match entries.get(&key) {
+            Some((expires, error)) if *expires > Instant::now() => Some(error.clone()),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Caches the error of the failed lookup of the given DNS protocol component.
+    pub(crate) fn insert(&self, name: &Protocol<'_>, error: ResolveError) {
+        let Some(ttl) = self.ttl else {
+            return;
+        };
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        entries.retain(|_, (expires, _)| *expires > now);
+        entries.insert(name.to_string(), (now + ttl, error));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_matches_resolver_opts() {
+        let mut opts = ResolverOpts::default();
+        CacheConfig::default().apply(&mut opts);
+
+        let default = ResolverOpts::default();
+        assert_eq!(opts.cache_size, default.cache_size);
+        assert_eq!(opts.positive_min_ttl, default.positive_min_ttl);
+        assert_eq!(opts.positive_max_ttl, default.positive_max_ttl);
+        assert_eq!(opts.negative_min_ttl, default.negative_min_ttl);
+        assert_eq!(opts.negative_max_ttl, default.negative_max_ttl);
+    }
+
+    #[test]
+    fn negative_cache_expires() {
+        let name = Protocol::Dns("example.com".into());
+        let error = ResolveError::from("No records");
+
+        let disabled = NegativeCache::new(None);
+        disabled.insert(&name, error.clone());
+        assert!(disabled.get(&name).is_none());
+
+        let cache = NegativeCache::new(Some(Duration::from_millis(50)));
+        cache.insert(&name, error);
+        assert!(cache.get(&name).is_some());
+        assert!(cache.get(&Protocol::Dns4("example.com".into())).is_none());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(cache.get(&name).is_none());
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Resolver configurations for DNS-over-TLS and DNS-over-HTTPS.
+//!
+//! The name servers are addressed by their IP addresses, i.e. resolving does not depend on an
+//! unencrypted DNS lookup of the name servers themselves. Their certificates are verified
+//! against the Mozilla root certificates.
+
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig};
+use std::net::IpAddr;
+
+/// A public DNS resolver supporting DNS-over-TLS and DNS-over-HTTPS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    /// Cloudflare's `1.1.1.1` resolver.
+    Cloudflare,
+    /// Google's `8.8.8.8` resolver.
+    Google,
+    /// Quad9's `9.9.9.9` resolver.
+    Quad9,
+}
+
+impl Provider {
+    /// The configuration to resolve via DNS-over-TLS with this provider.
+    #[cfg(feature = "dns-over-tls")]
+    pub fn dns_over_tls(self) -> ResolverConfig {
+        match self {
+            Provider::Cloudflare => ResolverConfig::cloudflare_tls(),
+            Provider::Google => ResolverConfig::google_tls(),
+            Provider::Quad9 => ResolverConfig::quad9_tls(),
+        }
+    }
+
+    /// The configuration to resolve via DNS-over-HTTPS with this provider.
+    #[cfg(feature = "dns-over-https")]
+    pub fn dns_over_https(self) -> ResolverConfig {
+        match self {
+            Provider::Cloudflare => ResolverConfig::cloudflare_https(),
+            Provider::Google => ResolverConfig::google_https(),
+            Provider::Quad9 => ResolverConfig::quad9_https(),
+        }
+    }
+}
+
+/// The configuration to resolve via DNS-over-TLS with the name server reachable at the given
+/// bootstrap IP addresses on port 853.
+///
+/// `server_name` is the name the certificate of the name server is verified against.
+#[cfg(feature = "dns-over-tls")]
+pub fn dns_over_tls(bootstrap_ips: &[IpAddr], server_name: &str) -> ResolverConfig {
+    ResolverConfig::from_parts(
+        None,
+        Vec::new(),
+        NameServerConfigGroup::from_ips_tls(bootstrap_ips, 853, server_name.to_owned(), true),
+    )
+}
+
+/// The configuration to resolve via DNS-over-HTTPS with the name server reachable at the given
+/// bootstrap IP addresses on port 443.
+///
+/// `server_name` is the name the certificate of the name server is verified against.
+#[cfg(feature = "dns-over-https")]
+pub fn dns_over_https(bootstrap_ips: &[IpAddr], server_name: &str) -> ResolverConfig {
+    ResolverConfig::from_parts(
+        None,
+        Vec::new(),
+        NameServerConfigGroup::from_ips_https(bootstrap_ips, 443, server_name.to_owned(), true),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::config::Protocol;
+
+    #[test]
+    #[cfg(feature = "dns-over-tls")]
+    fn dns_over_tls_uses_bootstrap_ips() {
+        let ips = [
+            "1.1.1.1".parse().unwrap(),
+            "2606:4700:4700::1111".parse().unwrap(),
+        ];
+        let config = dns_over_tls(&ips, "cloudflare-dns.com");
+
+        assert_eq!(config.name_servers().len(), 2);
+        for (name_server, ip) in config.name_servers().iter().zip(ips) {
+            assert_eq!(name_server.socket_addr, (ip, 853).into());
+            assert_eq!(name_server.protocol, Protocol::Tls);
+            assert_eq!(
+                name_server.tls_dns_name.as_deref(),
+                Some("cloudflare-dns.com")
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "dns-over-https")]
+    fn providers_use_https() {
+        for provider in [Provider::Cloudflare, Provider::Google, Provider::Quad9] {
+            let config = provider.dns_over_https();
+            assert!(!config.name_servers().is_empty());
+            assert!(config
+                .name_servers()
+                .iter()
+                .all(|name_server| name_server.protocol == Protocol::Https));
+        }
+    }
+}
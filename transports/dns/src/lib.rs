@@ -33,10 +33,13 @@
 //!
 //! The `async-std` feature and hence the [`async_std::Transport`] are
 //! enabled by default. Tokio users can furthermore opt-in
-//! to the `tokio-dns-over-rustls` and `tokio-dns-over-https-rustls`
-//! features. For more information about these features, please
+//! to the `dns-over-tls` and `dns-over-https` features, providing
+//! resolver configurations for encrypted DNS, e.g. via `Provider`.
+//! For more information about these features, please
 //! refer to the documentation of [trust-dns-resolver].
 //!
+//! Lookups are cached by the resolver, see [`CacheConfig`].
+//!
 //! On Unix systems, if no custom configuration is given, [trust-dns-resolver]
 //! will try to parse the `/etc/resolv.conf` file. This approach comes with a
 //! few caveats to be aware of:
@@ -65,8 +68,7 @@ pub mod async_std {
         config::{ResolverConfig, ResolverOpts},
         system_conf,
     };
-    use parking_lot::Mutex;
-    use std::{io, sync::Arc};
+    use std::io;
 
     /// A `Transport` wrapper for performing DNS lookups when dialing `Multiaddr`esses
     /// using `async-std` for all async I/O.
@@ -81,34 +83,31 @@ pub mod async_std {
 
         /// Creates a [`Transport`] with a custom resolver configuration and options.
         pub async fn custom(inner: T, cfg: ResolverConfig, opts: ResolverOpts) -> Transport<T> {
-            Transport {
-                inner: Arc::new(Mutex::new(inner)),
-                resolver: async_std_resolver::resolver(cfg, opts).await,
-            }
+            Transport::new(inner, async_std_resolver::resolver(cfg, opts).await)
         }
 
         // TODO: Replace `system` implementation with this
         #[doc(hidden)]
         pub fn system2(inner: T) -> Result<Transport<T>, io::Error> {
-            Ok(Transport {
-                inner: Arc::new(Mutex::new(inner)),
-                resolver: async_std_resolver::resolver_from_system_conf()
+            Ok(Transport::new(
+                inner,
+                async_std_resolver::resolver_from_system_conf()
                     .now_or_never()
                     .expect(
                         "async_std_resolver::resolver_from_system_conf did not resolve immediately",
                     )?,
-            })
+            ))
         }
 
         // TODO: Replace `custom` implementation with this
         #[doc(hidden)]
         pub fn custom2(inner: T, cfg: ResolverConfig, opts: ResolverOpts) -> Transport<T> {
-            Transport {
-                inner: Arc::new(Mutex::new(inner)),
-                resolver: async_std_resolver::resolver(cfg, opts)
+            Transport::new(
+                inner,
+                async_std_resolver::resolver(cfg, opts)
                     .now_or_never()
                     .expect("async_std_resolver::resolver did not resolve immediately"),
-            }
+            )
         }
     }
 }
@@ -116,8 +115,6 @@ pub mod async_std {
 #[cfg(feature = "tokio")]
 pub mod tokio {
     use hickory_resolver::{system_conf, TokioAsyncResolver};
-    use parking_lot::Mutex;
-    use std::sync::Arc;
 
     /// A `Transport` wrapper for performing DNS lookups when dialing `Multiaddr`esses
     /// using `tokio` for all async I/O.
@@ -137,15 +134,17 @@ pub mod tokio {
             cfg: hickory_resolver::config::ResolverConfig,
             opts: hickory_resolver::config::ResolverOpts,
         ) -> Transport<T> {
-            Transport {
-                inner: Arc::new(Mutex::new(inner)),
-                resolver: TokioAsyncResolver::tokio(cfg, opts),
-            }
+            Transport::new(inner, TokioAsyncResolver::tokio(cfg, opts))
         }
     }
 }
 
+mod cache;
+#[cfg(any(feature = "dns-over-tls", feature = "dns-over-https"))]
+mod encrypted;
+
 use async_trait::async_trait;
+use cache::NegativeCache;
 use futures::{future::BoxFuture, prelude::*};
 use libp2p_core::{
     connection::Endpoint,
@@ -163,8 +162,16 @@ use std::{
     str,
     sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
+pub use cache::CacheConfig;
+#[cfg(feature = "dns-over-https")]
+pub use encrypted::dns_over_https;
+#[cfg(feature = "dns-over-tls")]
+pub use encrypted::dns_over_tls;
+#[cfg(any(feature = "dns-over-tls", feature = "dns-over-https"))]
+pub use encrypted::Provider;
 pub use hickory_resolver::config::{ResolverConfig, ResolverOpts};
 pub use hickory_resolver::error::{ResolveError, ResolveErrorKind};
 use hickory_resolver::lookup::{Ipv4Lookup, Ipv6Lookup, TxtLookup};
@@ -190,14 +197,83 @@ const MAX_DNS_LOOKUPS: usize = 32;
 /// result of a single `/dnsaddr` lookup.
 const MAX_TXT_RECORDS: usize = 16;
 
+/// A callback invoked with a `/dnsaddr` address and the addresses it expanded to.
+type DnsaddrCallback = Arc<dyn Fn(&Multiaddr, &[Multiaddr]) + Send + Sync>;
+
 /// A [`Transport`] for performing DNS lookups when dialing `Multiaddr`esses.
 /// You shouldn't need to use this type directly. Use [`tokio::Transport`] or [`async_std::Transport`] instead.
-#[derive(Debug)]
 pub struct Transport<T, R> {
     /// The underlying transport.
     inner: Arc<Mutex<T>>,
     /// The DNS resolver used when dialing addresses with DNS components.
     resolver: R,
+    /// The maximum number of nested `/dnsaddr` lookups for an address.
+    max_dnsaddr_depth: usize,
+    /// Failed lookups, if enabled.
+    negative_cache: Arc<NegativeCache>,
+    /// Invoked whenever a `/dnsaddr` has been expanded, if set.
+    on_dnsaddr_expanded: Option<DnsaddrCallback>,
+}
+
+impl<T, R> Transport<T, R> {
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    fn new(inner: T, resolver: R) -> Self {
+        Transport {
+            inner: Arc::new(Mutex::new(inner)),
+            resolver,
+            max_dnsaddr_depth: MAX_DNS_LOOKUPS,
+            negative_cache: Arc::new(NegativeCache::new(None)),
+            on_dnsaddr_expanded: None,
+        }
+    }
+
+    /// Sets the maximum depth of nested `/dnsaddr` records followed when resolving an address,
+    /// i.e. of `/dnsaddr` TXT records resolving to further `/dnsaddr` addresses.
+    ///
+    /// Addresses nested deeper are dropped. Independently, at most 32 DNS lookups are performed
+    /// when dialing an address, which is also the default depth.
+    pub fn with_max_dnsaddr_depth(mut self, depth: usize) -> Self {
+        self.max_dnsaddr_depth = depth;
+        self
+    }
+
+    /// Caches failed lookups for the given duration, failing dials to addresses with the same
+    /// DNS names without performing the lookup again.
+    ///
+    /// In contrast to the cache of the resolver, see [`CacheConfig`], all failed lookups are
+    /// cached, including those that timed out.
+    ///
+    /// By default, failed lookups are not cached.
+    pub fn with_negative_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_cache = Arc::new(NegativeCache::new(Some(ttl)));
+        self
+    }
+
+    /// Sets a callback invoked whenever a `/dnsaddr` address has been resolved, with the address
+    /// and the addresses obtained from its TXT records that are dialed, e.g. to debug stale
+    /// records.
+    pub fn on_dnsaddr_expanded(
+        mut self,
+        callback: impl Fn(&Multiaddr, &[Multiaddr]) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_dnsaddr_expanded = Some(Arc::new(callback));
+        self
+    }
+}
+
+impl<T, R> fmt::Debug for Transport<T, R>
+where
+    T: fmt::Debug,
+    R: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transport")
+            .field("inner", &self.inner)
+            .field("resolver", &self.resolver)
+            .field("max_dnsaddr_depth", &self.max_dnsaddr_depth)
+            .field("negative_cache", &self.negative_cache)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<T, R> libp2p_core::Transport for Transport<T, R>
@@ -275,6 +351,9 @@ where
     > {
         let resolver = self.resolver.clone();
         let inner = self.inner.clone();
+        let max_dnsaddr_depth = self.max_dnsaddr_depth;
+        let negative_cache = self.negative_cache.clone();
+        let on_dnsaddr_expanded = self.on_dnsaddr_expanded.clone();
 
         // Asynchronously resolve all DNS names in the address before proceeding
         // with dialing on the underlying transport.
@@ -284,13 +363,14 @@ where
             let mut dial_attempts = 0;
             // We optimise for the common case of a single DNS component
             // in the address that is resolved with a single lookup.
-            let mut unresolved = SmallVec::<[Multiaddr; 1]>::new();
-            unresolved.push(addr.clone());
+            // Each address is tracked with the number of `/dnsaddr` lookups it resulted from.
+            let mut unresolved = SmallVec::<[(Multiaddr, usize); 1]>::new();
+            unresolved.push((addr.clone(), 0));
 
             // Resolve (i.e. replace) all DNS protocol components, initiating
             // dialing attempts as soon as there is another fully resolved
             // address.
-            while let Some(addr) = unresolved.pop() {
+            while let Some((addr, depth)) = unresolved.pop() {
                 if let Some((i, name)) = addr.iter().enumerate().find(|(_, p)| {
                     matches!(
                         p,
@@ -307,8 +387,26 @@ where
                         // so keep going until `unresolved` is empty.
                         continue;
                     }
-                    dns_lookups += 1;
-                    match resolve(&name, &resolver).await {
+                    if matches!(name, Protocol::Dnsaddr(_)) && depth == max_dnsaddr_depth {
+                        tracing::debug!(address=%addr, "Too deeply nested dnsaddr, dropping unresolved address");
+                        last_err = Some(Error::TooManyLookups);
+                        continue;
+                    }
+                    let resolved = match negative_cache.get(&name) {
+                        Some(e) => {
+                            tracing::trace!(protocol=%name, "Lookup failed recently");
+                            Err(Error::ResolveError(e))
+                        }
+                        None => {
+                            dns_lookups += 1;
+                            let resolved = resolve(&name, &resolver).await;
+                            if let Err(Error::ResolveError(e)) = &resolved {
+                                negative_cache.insert(&name, e.clone());
+                            }
+                            resolved
+                        }
+                    };
+                    match resolved {
                         Err(e) => {
                             if unresolved.is_empty() {
                                 return Err(e);
@@ -320,28 +418,27 @@ where
                         Ok(Resolved::One(ip)) => {
                             tracing::trace!(protocol=%name, resolved=%ip);
                             let addr = addr.replace(i, |_| Some(ip)).expect("`i` is a valid index");
-                            unresolved.push(addr);
+                            unresolved.push((addr, depth));
                         }
                         Ok(Resolved::Many(ips)) => {
                             for ip in ips {
                                 tracing::trace!(protocol=%name, resolved=%ip);
                                 let addr =
                                     addr.replace(i, |_| Some(ip)).expect("`i` is a valid index");
-                                unresolved.push(addr);
+                                unresolved.push((addr, depth));
                             }
                         }
                         Ok(Resolved::Addrs(addrs)) => {
                             let suffix = addr.iter().skip(i + 1).collect::<Multiaddr>();
                             let prefix = addr.iter().take(i).collect::<Multiaddr>();
-                            let mut n = 0;
+                            let mut expanded = Vec::new();
                             for a in addrs {
                                 if a.ends_with(&suffix) {
-                                    if n < MAX_TXT_RECORDS {
-                                        n += 1;
+                                    if expanded.len() < MAX_TXT_RECORDS {
                                         tracing::trace!(protocol=%name, resolved=%a);
-                                        let addr =
-                                            prefix.iter().chain(a.iter()).collect::<Multiaddr>();
-                                        unresolved.push(addr);
+                                        expanded.push(
+                                            prefix.iter().chain(a.iter()).collect::<Multiaddr>(),
+                                        );
                                     } else {
                                         tracing::debug!(
                                             resolved=%a,
@@ -350,6 +447,10 @@ where
                                     }
                                 }
                             }
+                            if let Some(callback) = &on_dnsaddr_expanded {
+                                callback(&addr, &expanded);
+                            }
+                            unresolved.extend(expanded.into_iter().map(|a| (a, depth + 1)));
                         }
                     }
                 } else {
@@ -421,10 +522,11 @@ pub enum Error<TErr> {
     MultiaddrNotSupported(Multiaddr),
     /// DNS resolution involved too many lookups.
     ///
-    /// DNS resolution on dialing performs up to 32 DNS lookups. If these
-    /// are not sufficient to obtain a fully-resolved address, this error
-    /// is returned and the DNS records for the domain(s) being dialed
-    /// should be investigated.
+    /// DNS resolution on dialing performs up to 32 DNS lookups and follows
+    /// nested `/dnsaddr` records up to the depth configured via
+    /// [`Transport::with_max_dnsaddr_depth`]. If these are not sufficient to
+    /// obtain a fully-resolved address, this error is returned and the DNS
+    /// records for the domain(s) being dialed should be investigated.
     TooManyLookups,
 }
 
@@ -786,4 +888,145 @@ mod tests {
             rt.block_on(run(tokio::Transport::custom(CustomTransport, config, opts)));
         }
     }
+
+    /// A resolver serving `/dnsaddr` TXT records from memory, failing all other lookups.
+    #[derive(Clone, Default)]
+    struct TxtResolver {
+        records: Arc<std::collections::HashMap<String, Vec<String>>>,
+        lookups: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl TxtResolver {
+        fn new(records: &[(&str, &[&str])]) -> Self {
+            Self {
+                records: Arc::new(
+                    records
+                        .iter()
+                        .map(|(name, txts)| {
+                            let txts = txts.iter().map(|t| format!("dnsaddr={t}")).collect();
+                            (format!("{DNSADDR_PREFIX}{name}"), txts)
+                        })
+                        .collect(),
+                ),
+                lookups: Default::default(),
+            }
+        }
+
+        fn lookups(&self) -> usize {
+            self.lookups.load(std::sync::atomic::Ordering::SeqCst)
+        }
+
+        fn no_records() -> ResolveError {
+            ResolveErrorKind::Message("No records").into()
+        }
+    }
+
+    #[async_trait]
+    impl Resolver for TxtResolver {
+        async fn lookup_ip(&self, _: String) -> Result<LookupIp, ResolveError> {
+            self.lookups
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(Self::no_records())
+        }
+
+        async fn ipv4_lookup(&self, _: String) -> Result<Ipv4Lookup, ResolveError> {
+            self.lookups
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(Self::no_records())
+        }
+
+        async fn ipv6_lookup(&self, _: String) -> Result<Ipv6Lookup, ResolveError> {
+            self.lookups
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(Self::no_records())
+        }
+
+        async fn txt_lookup(&self, name: String) -> Result<TxtLookup, ResolveError> {
+            use hickory_resolver::{
+                lookup::Lookup,
+                proto::{
+                    op::Query,
+                    rr::{rdata::TXT, RData, RecordType},
+                },
+                Name,
+            };
+
+            self.lookups
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let txts = self.records.get(&name).ok_or_else(Self::no_records)?;
+            let query = Query::query(Name::from_ascii(&name).unwrap(), RecordType::TXT);
+            let records = txts
+                .iter()
+                .map(|txt| {
+                    let rdata = RData::TXT(TXT::new(vec![txt.clone()]));
+                    hickory_resolver::proto::rr::Record::from_rdata(query.name().clone(), 60, rdata)
+                })
+                .collect();
+            Ok(Lookup::new_with_max_ttl(query, records).into())
+        }
+    }
+
+    #[test]
+    fn nested_dnsaddr_depth_is_limited() {
+        let resolver = TxtResolver::new(&[
+            ("a.com", &["/dnsaddr/b.com"]),
+            ("b.com", &["/ip4/1.2.3.4/tcp/1"]),
+        ]);
+        let dial = |depth| {
+            let expansions = Arc::new(Mutex::new(Vec::new()));
+            let mut transport = super::Transport::new(
+                libp2p_core::transport::MemoryTransport::default(),
+                resolver.clone(),
+            )
+            .with_max_dnsaddr_depth(depth)
+            .on_dnsaddr_expanded({
+                let expansions = expansions.clone();
+                move |addr, expanded| expansions.lock().push((addr.clone(), expanded.to_vec()))
+            });
+            let result = futures::executor::block_on(
+                transport.dial("/dnsaddr/a.com".parse().unwrap()).unwrap(),
+            );
+            let expansions = expansions.lock().clone();
+            (result.map(|_| ()), expansions)
+        };
+
+        let (result, expansions) = dial(1);
+        assert!(matches!(result, Err(Error::TooManyLookups)));
+        assert_eq!(
+            expansions,
+            vec![(
+                "/dnsaddr/a.com".parse().unwrap(),
+                vec!["/dnsaddr/b.com".parse().unwrap()]
+            )]
+        );
+
+        let (result, expansions) = dial(2);
+        match result {
+            Err(Error::MultiaddrNotSupported(addr)) => {
+                assert_eq!(addr, "/ip4/1.2.3.4/tcp/1".parse().unwrap())
+            }
+            r => panic!("Unexpected result: {r:?}"),
+        }
+        assert_eq!(expansions.len(), 2);
+    }
+
+    #[test]
+    fn failed_lookups_are_cached() {
+        let resolver = TxtResolver::default();
+        let mut transport = super::Transport::new(
+            libp2p_core::transport::MemoryTransport::default(),
+            resolver.clone(),
+        )
+        .with_negative_cache_ttl(std::time::Duration::from_secs(60));
+
+        for _ in 0..2 {
+            let result = futures::executor::block_on(
+                transport
+                    .dial("/dns4/example.com/tcp/1".parse().unwrap())
+                    .unwrap(),
+            );
+            assert!(matches!(result, Err(Error::ResolveError(_))));
+        }
+        assert_eq!(resolver.lookups(), 1);
+    }
 }
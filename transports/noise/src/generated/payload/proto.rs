@@ -18,6 +18,7 @@ use super::super::*;
 pub struct NoiseExtensions {
     pub webtransport_certhashes: Vec<Vec<u8>>,
     pub stream_muxers: Vec<String>,
+    pub early_data: Vec<u8>,
 }
 
 impl<'a> MessageRead<'a> for NoiseExtensions {
@@ -27,6 +28,7 @@ impl<'a> MessageRead<'a> for NoiseExtensions {
             match r.next_tag(bytes) {
                 Ok(10) => msg.webtransport_certhashes.push(r.read_bytes(bytes)?.to_owned()),
                 Ok(18) => msg.stream_muxers.push(r.read_string(bytes)?.to_owned()),
+                Ok(8194) => msg.early_data = r.read_bytes(bytes)?.to_owned(),
                 Ok(t) => { r.read_unknown(bytes, t)?; }
                 Err(e) => return Err(e),
             }
@@ -40,11 +42,13 @@ impl MessageWrite for NoiseExtensions {
         0
         + self.webtransport_certhashes.iter().map(|s| 1 + sizeof_len((s).len())).sum::<usize>()
         + self.stream_muxers.iter().map(|s| 1 + sizeof_len((s).len())).sum::<usize>()
+        + if self.early_data.is_empty() { 0 } else { 2 + sizeof_len((&self.early_data).len()) }
     }
 
     fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
         for s in &self.webtransport_certhashes { w.write_with_tag(10, |w| w.write_bytes(&**s))?; }
         for s in &self.stream_muxers { w.write_with_tag(18, |w| w.write_string(&**s))?; }
+        if !self.early_data.is_empty() { w.write_with_tag(8194, |w| w.write_bytes(&**&self.early_data))?; }
         Ok(())
     }
 }
@@ -43,6 +43,7 @@ pub struct Output<T> {
     recv_offset: usize,
     send_buffer: Vec<u8>,
     send_offset: usize,
+    remote_early_data: Option<Vec<u8>>,
 }
 
 impl<T> fmt::Debug for Output<T> {
@@ -52,15 +53,23 @@ impl<T> fmt::Debug for Output<T> {
 }
 
 impl<T> Output<T> {
-    fn new(io: Framed<T, Codec<snow::TransportState>>) -> Self {
+    fn new(io: Framed<T, Codec<snow::TransportState>>, remote_early_data: Option<Vec<u8>>) -> Self {
         Output {
             io,
             recv_buffer: Bytes::new(),
             recv_offset: 0,
             send_buffer: Vec::new(),
             send_offset: 0,
+            remote_early_data,
         }
     }
+
+    /// The early data the remote sent as part of the handshake, if any.
+    ///
+    /// See [`Config::with_early_data`](crate::Config::with_early_data).
+    pub fn remote_early_data(&self) -> Option<&[u8]> {
+        self.remote_early_data.as_deref()
+    }
 }
 
 impl<T: AsyncRead + Unpin> AsyncRead for Output<T> {
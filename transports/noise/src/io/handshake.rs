@@ -55,6 +55,8 @@ pub(crate) struct State<T> {
     id_remote_pubkey: Option<identity::PublicKey>,
     /// The WebTransport certhashes of the responder, if any.
     responder_webtransport_certhashes: Option<HashSet<Multihash<64>>>,
+    /// The early data to send to the remote, if any.
+    early_data: Option<Vec<u8>>,
     /// The received extensions of the remote, if any.
     remote_extensions: Option<Extensions>,
 }
@@ -62,6 +64,7 @@ pub(crate) struct State<T> {
 /// Extensions
 struct Extensions {
     webtransport_certhashes: HashSet<Multihash<64>>,
+    early_data: Option<Vec<u8>>,
 }
 
 impl<T> State<T>
@@ -80,6 +83,7 @@ where
         identity: KeypairIdentity,
         expected_remote_key: Option<identity::PublicKey>,
         responder_webtransport_certhashes: Option<HashSet<Multihash<64>>>,
+        early_data: Option<Vec<u8>>,
     ) -> Self {
         Self {
             identity,
@@ -87,6 +91,7 @@ where
             dh_remote_pubkey_sig: None,
             id_remote_pubkey: expected_remote_key,
             responder_webtransport_certhashes,
+            early_data,
             remote_extensions: None,
         }
     }
@@ -119,14 +124,14 @@ where
         if is_initiator {
             // We check only if we care (i.e. Config::with_webtransport_certhashes was used).
             if let Some(expected_certhashes) = self.responder_webtransport_certhashes {
-                let ext = self.remote_extensions.ok_or_else(|| {
+                let ext = self.remote_extensions.as_ref().ok_or_else(|| {
                     Error::UnknownWebTransportCerthashes(
                         expected_certhashes.to_owned(),
                         HashSet::new(),
                     )
                 })?;
 
-                let received_certhashes = ext.webtransport_certhashes;
+                let received_certhashes = ext.webtransport_certhashes.clone();
 
                 // Expected WebTransport certhashes must be a strict subset
                 // of the reported ones.
@@ -139,7 +144,9 @@ where
             }
         }
 
-        Ok((id_pk, Output::new(framed)))
+        let remote_early_data = self.remote_extensions.and_then(|ext| ext.early_data);
+
+        Ok((id_pk, Output::new(framed, remote_early_data)))
     }
 }
 
@@ -175,6 +182,7 @@ impl From<proto::NoiseExtensions> for Extensions {
                 .into_iter()
                 .filter_map(|bytes| Multihash::read(&bytes[..]).ok())
                 .collect(),
+            early_data: Some(value.early_data).filter(|data| !data.is_empty()),
         }
     }
 }
@@ -261,6 +269,14 @@ where
         }
     }
 
+    if let Some(ref early_data) = state.early_data {
+        let ext = pb
+            .extensions
+            .get_or_insert_with(proto::NoiseExtensions::default);
+
+        ext.early_data.clone_from(early_data);
+    }
+
     state.io.send(&pb).await?;
 
     Ok(())
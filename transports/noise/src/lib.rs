@@ -89,6 +89,9 @@ pub struct Config {
     ///
     /// For further information, see <https://noiseprotocol.org/noise.html#prologue>.
     prologue: Vec<u8>,
+
+    /// Application data to piggyback on the handshake, if any.
+    early_data: Option<Vec<u8>>,
 }
 
 impl Config {
@@ -96,12 +99,28 @@ impl Config {
     pub fn new(identity: &identity::Keypair) -> Result<Self, Error> {
         let noise_keys = Keypair::new().into_authentic(identity)?;
 
-        Ok(Self {
-            dh_keys: noise_keys,
+        Ok(Self::from_keys(noise_keys))
+    }
+
+    /// Construct a new configuration for the noise handshake using the XX handshake pattern,
+    /// with the identity keypair accessed through a [`Signer`](identity::Signer).
+    ///
+    /// This allows the private key to be held outside of memory, e.g. in a hardware security
+    /// module. The signer is only used once, to sign the static DH key of the handshakes.
+    pub async fn with_signer(signer: &(impl identity::Signer + ?Sized)) -> Result<Self, Error> {
+        let noise_keys = Keypair::new().into_authentic_with_signer(signer).await?;
+
+        Ok(Self::from_keys(noise_keys))
+    }
+
+    fn from_keys(dh_keys: AuthenticKeypair) -> Self {
+        Self {
+            dh_keys,
             params: PARAMS_XX.clone(),
             webtransport_certhashes: None,
             prologue: vec![],
-        })
+            early_data: None,
+        }
     }
 
     /// Set the noise prologue.
@@ -121,6 +140,23 @@ impl Config {
         self
     }
 
+    /// Set early data to send to the remote as part of the handshake.
+    ///
+    /// This allows protocols to piggyback e.g. muxer negotiation hints or identify data on the
+    /// handshake instead of spending a round trip after it. The data received from the remote is
+    /// available via [`Output::remote_early_data`] once the handshake completed successfully.
+    ///
+    /// The early data is always encrypted. Note however that a responder sends it before the
+    /// initiator authenticated itself, i.e. it must not contain anything meant only for
+    /// authenticated peers. The data has to fit into a single noise handshake message together
+    /// with the identity payload, otherwise the handshake fails.
+    ///
+    /// Remotes that do not support early data ignore it.
+    pub fn with_early_data(mut self, early_data: Vec<u8>) -> Self {
+        self.early_data = Some(early_data).filter(|d| !d.is_empty());
+        self
+    }
+
     fn into_responder<S: AsyncRead + AsyncWrite>(self, socket: S) -> Result<State<S>, Error> {
         let session = noise_params_into_builder(
             self.params,
@@ -136,6 +172,7 @@ impl Config {
             self.dh_keys.identity,
             None,
             self.webtransport_certhashes,
+            self.early_data,
         );
 
         Ok(state)
@@ -156,6 +193,7 @@ impl Config {
             self.dh_keys.identity,
             None,
             self.webtransport_certhashes,
+            self.early_data,
         );
 
         Ok(state)
@@ -102,6 +102,27 @@ impl Keypair {
         })
     }
 
+    /// Turn this DH keypair into a [`AuthenticKeypair`] by signing the DH public key with a
+    /// [`Signer`](identity::Signer), whose private key may not be held in memory.
+    pub(crate) async fn into_authentic_with_signer(
+        self,
+        signer: &(impl identity::Signer + ?Sized),
+    ) -> Result<AuthenticKeypair, Error> {
+        let sig = signer
+            .sign(&[STATIC_KEY_DOMAIN.as_bytes(), self.public.as_ref()].concat())
+            .await?;
+
+        let identity = KeypairIdentity {
+            public: signer.public(),
+            signature: sig,
+        };
+
+        Ok(AuthenticKeypair {
+            keypair: self,
+            identity,
+        })
+    }
+
     /// An "empty" keypair as a starting state for DH computations in `snow`,
     /// which get manipulated through the `snow::types::Dh` interface.
     pub(crate) fn empty() -> Self {
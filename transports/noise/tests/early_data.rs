@@ -0,0 +1,63 @@
+use libp2p_core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade};
+use libp2p_identity as identity;
+use libp2p_noise as noise;
+
+#[test]
+fn both_sides_send_early_data() {
+    let (client, server) = handshake_with_early_data(Some(b"hello"), Some(b"world")).unwrap();
+
+    assert_eq!(server.as_deref(), Some(&b"hello"[..]));
+    assert_eq!(client.as_deref(), Some(&b"world"[..]));
+}
+
+#[test]
+fn only_one_side_sends_early_data() {
+    let (client, server) = handshake_with_early_data(None, Some(b"world")).unwrap();
+
+    assert_eq!(server, None);
+    assert_eq!(client.as_deref(), Some(&b"world"[..]));
+}
+
+#[test]
+fn no_early_data() {
+    let (client, server) = handshake_with_early_data(None, None).unwrap();
+
+    assert_eq!(server, None);
+    assert_eq!(client, None);
+}
+
+/// Returns the early data received by the client and by the server.
+#[allow(clippy::type_complexity)]
+fn handshake_with_early_data(
+    client_early_data: Option<&[u8]>,
+    server_early_data: Option<&[u8]>,
+) -> Result<(Option<Vec<u8>>, Option<Vec<u8>>), noise::Error> {
+    let client_id = identity::Keypair::generate_ed25519();
+    let server_id = identity::Keypair::generate_ed25519();
+
+    let (client, server) = futures_ringbuf::Endpoint::pair(100, 100);
+
+    futures::executor::block_on(async move {
+        let mut client_config = noise::Config::new(&client_id)?;
+        let mut server_config = noise::Config::new(&server_id)?;
+
+        if let Some(data) = client_early_data {
+            client_config = client_config.with_early_data(data.to_vec());
+        }
+
+        if let Some(data) = server_early_data {
+            server_config = server_config.with_early_data(data.to_vec());
+        }
+
+        let ((_, server_session), (_, client_session)) = futures::future::try_join(
+            server_config.upgrade_inbound(server, ""),
+            client_config.upgrade_outbound(client, ""),
+        )
+        .await?;
+
+        Ok((
+            client_session.remote_early_data().map(<[u8]>::to_vec),
+            server_session.remote_early_data().map(<[u8]>::to_vec),
+        ))
+    })
+}
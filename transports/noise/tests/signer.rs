@@ -0,0 +1,71 @@
+use libp2p_core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade};
+use libp2p_identity as identity;
+use libp2p_identity::{PeerId, SignFuture, Signer, SigningError};
+use libp2p_noise as noise;
+
+/// A [`Signer`] standing in for a key held outside of memory, e.g. in a HSM.
+struct ExternalSigner(identity::Keypair);
+
+impl Signer for ExternalSigner {
+    fn public(&self) -> identity::PublicKey {
+        self.0.public()
+    }
+
+    fn sign(&self, msg: &[u8]) -> SignFuture {
+        let signature = self.0.sign(msg);
+        Box::pin(async move { signature })
+    }
+}
+
+/// A [`Signer`] whose key isn't accessible.
+struct UnavailableSigner(identity::PublicKey);
+
+impl Signer for UnavailableSigner {
+    fn public(&self) -> identity::PublicKey {
+        self.0.clone()
+    }
+
+    fn sign(&self, _: &[u8]) -> SignFuture {
+        Box::pin(async { Err(SigningError::new("signer unavailable")) })
+    }
+}
+
+#[test]
+fn handshake_with_external_signer() {
+    let client_id = identity::Keypair::generate_ed25519();
+    let server_id = identity::Keypair::generate_ed25519();
+    let expected_server = server_id.public().to_peer_id();
+    let expected_client = client_id.public().to_peer_id();
+
+    let (client, server) = futures_ringbuf::Endpoint::pair(100, 100);
+
+    let (client_remote, server_remote): (PeerId, PeerId) =
+        futures::executor::block_on(async move {
+            let client_config = noise::Config::new(&client_id).unwrap();
+            let server_config = noise::Config::with_signer(&ExternalSigner(server_id))
+                .await
+                .unwrap();
+
+            let ((client_remote, _), (server_remote, _)) = futures::future::try_join(
+                client_config.upgrade_outbound(client, ""),
+                server_config.upgrade_inbound(server, ""),
+            )
+            .await
+            .unwrap();
+
+            (client_remote, server_remote)
+        });
+
+    assert_eq!(client_remote, expected_server);
+    assert_eq!(server_remote, expected_client);
+}
+
+#[test]
+fn signer_failure_is_reported() {
+    let public = identity::Keypair::generate_ed25519().public();
+
+    let result =
+        futures::executor::block_on(noise::Config::with_signer(&UnavailableSigner(public)));
+
+    assert!(matches!(result, Err(noise::Error::SigningError(_))));
+}
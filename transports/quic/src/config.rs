@@ -18,7 +18,9 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use crate::{InboundRateLimit, InboundStats};
 use quinn::{
+    congestion,
     crypto::rustls::{QuicClientConfig, QuicServerConfig},
     MtuDiscoveryConfig, VarInt,
 };
@@ -50,6 +52,34 @@ pub struct Config {
     /// of a connection.
     pub max_connection_data: u32,
 
+    /// The round-trip time assumed before it is measured on a connection.
+    ///
+    /// Lower values speed up loss recovery during the handshake on low-latency networks.
+    /// Defaults to 333ms as recommended by RFC 9002.
+    pub initial_rtt: Duration,
+
+    /// The congestion controller of connections. See [`CongestionController`] for details.
+    pub congestion_controller: CongestionController,
+
+    /// The max UDP payload size used before MTU discovery finishes.
+    ///
+    /// Must be at least 1200, which is the default. Higher values are reduced to the path's MTU
+    /// via black hole detection if they turn out to be unsupported, bounded by
+    /// [`Config::min_mtu`].
+    pub initial_mtu: u16,
+
+    /// The max UDP payload size guaranteed to be supported by the network path.
+    ///
+    /// Must be at least 1200, which is the default. Packets are lost without repair if the
+    /// path does not support it, so only raise it in networks whose MTU is known.
+    pub min_mtu: u16,
+
+    /// Whether to send batches of packets via Generic Segmentation Offload where supported.
+    ///
+    /// This considerably lowers the CPU load of bulk transfers, but is not supported by all
+    /// network interface drivers. Enabled by default.
+    pub segmentation_offload: bool,
+
     /// Support QUIC version draft-29 for dialing and listening.
     ///
     /// Per default only QUIC Version 1 / [`libp2p_core::multiaddr::Protocol::QuicV1`]
@@ -60,6 +90,51 @@ pub struct Config {
     /// As client the version is chosen based on the remote's address.
     pub support_draft_29: bool,
 
+    /// Keep connections alive when the address of either side changes, e.g. because
+    /// the local network interface changed or a NAT rebinding occurred.
+    ///
+    /// When enabled, listeners accept packets of established connections from new remote
+    /// addresses and the sockets used for dialing are rebound once a network interface
+    /// goes down. Address changes are reported via
+    /// [`StreamMuxerEvent::AddressChange`](libp2p_core::muxing::StreamMuxerEvent::AddressChange).
+    ///
+    /// Disabled by default.
+    pub connection_migration: bool,
+
+    /// Require clients to prove that they own their address via a stateless retry before a
+    /// connection attempt is accepted, at the cost of an additional round trip.
+    ///
+    /// This keeps attackers spoofing source addresses from making listeners do cryptographic
+    /// work and keep state. Until the address of a client is validated, QUIC anyway limits the
+    /// data sent to it to three times the data received, which prevents listeners from being
+    /// abused for amplification attacks.
+    ///
+    /// Disabled by default.
+    pub stateless_retry: bool,
+
+    /// Duration for which the tokens issued in stateless retries are valid.
+    pub retry_token_lifetime: Duration,
+
+    /// Maximum number of connection attempts of a listener that are not yet accepted, retried
+    /// or refused. Further attempts are refused immediately.
+    pub max_incoming: u32,
+
+    /// Maximum number of bytes buffered for a single connection attempt until it is accepted.
+    pub incoming_buffer_size: u64,
+
+    /// Maximum number of bytes buffered for all connection attempts of a listener until they
+    /// are accepted.
+    pub incoming_buffer_size_total: u64,
+
+    /// Limit of the rate of inbound connections accepted by each listener.
+    ///
+    /// With [`Config::stateless_retry`] enabled, only connection attempts from validated
+    /// addresses count towards the limit. No limit by default.
+    pub inbound_rate_limit: Option<InboundRateLimit>,
+
+    /// Counters of inbound connection attempts, see [`Config::inbound_stats`].
+    inbound_stats: InboundStats,
+
     /// TLS client config for the inner [`quinn::ClientConfig`].
     client_tls_config: Arc<QuicClientConfig>,
     /// TLS server config for the inner [`quinn::ServerConfig`].
@@ -69,6 +144,9 @@ pub struct Config {
 
     /// Parameters governing MTU discovery. See [`MtuDiscoveryConfig`] for details.
     mtu_discovery_config: Option<MtuDiscoveryConfig>,
+
+    /// Whether 0-RTT session resumption is enabled. See [`Config::enable_0rtt`].
+    pub(crate) zero_rtt: bool,
 }
 
 impl Config {
@@ -85,19 +163,73 @@ impl Config {
             client_tls_config,
             server_tls_config,
             support_draft_29: false,
+            connection_migration: false,
+            stateless_retry: false,
+            retry_token_lifetime: Duration::from_secs(15),
+            max_incoming: 1 << 16,
+            incoming_buffer_size: 10 << 20,
+            incoming_buffer_size_total: 100 << 20,
+            inbound_rate_limit: None,
+            inbound_stats: InboundStats::default(),
             handshake_timeout: Duration::from_secs(5),
             max_idle_timeout: 10 * 1000,
             max_concurrent_stream_limit: 256,
             keep_alive_interval: Duration::from_secs(5),
             max_connection_data: 15_000_000,
+            initial_rtt: Duration::from_millis(333),
+            congestion_controller: CongestionController::default(),
+            initial_mtu: 1200,
+            min_mtu: 1200,
+            segmentation_offload: true,
 
             // Ensure that one stream is not consuming the whole connection.
             max_stream_data: 10_000_000,
             keypair: keypair.clone(),
             mtu_discovery_config: Some(Default::default()),
+            zero_rtt: false,
         }
     }
 
+    /// Enable 0-RTT session resumption (it is disabled by default).
+    ///
+    /// Session tickets received from a peer are cached by its [`PeerId`](libp2p_identity::PeerId).
+    /// Subsequent dials to an address that includes the same peer ID then send data, e.g. the
+    /// multistream-select and identify messages, in the very first flight instead of waiting for
+    /// the handshake to complete. Whether a connection uses early data is reported by
+    /// [`Connection::is_0rtt`](crate::Connection::is_0rtt).
+    ///
+    /// As a listener, early data of resuming clients is accepted.
+    ///
+    /// # Security
+    ///
+    /// 0-RTT data is not protected against replay attacks, hence protocols should only act
+    /// on it if they are idempotent. Furthermore the peer ID of the remote is sent in clear text
+    /// as TLS server name, which reveals the identity of the dialed peer to on-path observers.
+    ///
+    /// If the remote rejects the early data, streams opened before the handshake completed fail.
+    pub fn enable_0rtt(mut self) -> Self {
+        let mut client_tls_config = libp2p_tls::make_client_config(&self.keypair, None).unwrap();
+        client_tls_config.enable_early_data = true;
+        self.client_tls_config = Arc::new(QuicClientConfig::try_from(client_tls_config).unwrap());
+
+        // Quinn only accepts either no early data or an unlimited amount, with the actual
+        // amount being bound by the flow control limits.
+        let mut server_tls_config = libp2p_tls::make_server_config(&self.keypair).unwrap();
+        server_tls_config.max_early_data_size = u32::MAX;
+        self.server_tls_config = Arc::new(QuicServerConfig::try_from(server_tls_config).unwrap());
+
+        self.zero_rtt = true;
+        self
+    }
+
+    /// Returns a handle to the counters of inbound connection attempts of the transport
+    /// created from this config, e.g. for export as metrics.
+    ///
+    /// All clones of the config share the same counters.
+    pub fn inbound_stats(&self) -> InboundStats {
+        self.inbound_stats.clone()
+    }
+
     /// Set the upper bound to the max UDP payload size that MTU discovery will search for.
     pub fn mtu_upper_bound(mut self, value: u16) -> Self {
         self.mtu_discovery_config
@@ -106,6 +238,30 @@ impl Config {
         self
     }
 
+    /// Set the time to wait after MTU discovery completed before running it again.
+    pub fn mtu_discovery_interval(mut self, value: Duration) -> Self {
+        self.mtu_discovery_config
+            .get_or_insert_with(Default::default)
+            .interval(value);
+        self
+    }
+
+    /// Set the time to wait after a black hole was detected before running MTU discovery again.
+    pub fn mtu_black_hole_cooldown(mut self, value: Duration) -> Self {
+        self.mtu_discovery_config
+            .get_or_insert_with(Default::default)
+            .black_hole_cooldown(value);
+        self
+    }
+
+    /// Set the minimum change of the MTU at which MTU discovery stops searching.
+    pub fn mtu_minimum_change(mut self, value: u16) -> Self {
+        self.mtu_discovery_config
+            .get_or_insert_with(Default::default)
+            .minimum_change(value);
+        self
+    }
+
     /// Disable MTU path discovery (it is enabled by default).
     pub fn disable_path_mtu_discovery(mut self) -> Self {
         self.mtu_discovery_config = None;
@@ -113,6 +269,33 @@ impl Config {
     }
 }
 
+/// The congestion control algorithm of QUIC connections.
+///
+/// Independent of the algorithm, packets are always paced over the round-trip time to avoid
+/// bursts, and ECN marks are reacted to where the platform supports them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CongestionController {
+    /// CUBIC as specified in RFC 9438, the default.
+    #[default]
+    Cubic,
+    /// NewReno as specified in RFC 9002.
+    NewReno,
+    /// An experimental implementation of BBR, which makes better use of the bandwidth of
+    /// paths with high latency or random packet loss.
+    Bbr,
+}
+
+impl CongestionController {
+    fn factory(self) -> Arc<dyn congestion::ControllerFactory + Send + Sync> {
+        match self {
+            CongestionController::Cubic => Arc::new(congestion::CubicConfig::default()),
+            CongestionController::NewReno => Arc::new(congestion::NewRenoConfig::default()),
+            CongestionController::Bbr => Arc::new(congestion::BbrConfig::default()),
+        }
+    }
+}
+
 /// Represents the inner configuration for [`quinn`].
 #[derive(Debug, Clone)]
 pub(crate) struct QuinnConfig {
@@ -131,10 +314,24 @@ impl From<Config> for QuinnConfig {
             keep_alive_interval,
             max_connection_data,
             max_stream_data,
+            initial_rtt,
+            congestion_controller,
+            initial_mtu,
+            min_mtu,
+            segmentation_offload,
             support_draft_29,
+            connection_migration,
+            stateless_retry: _,
+            retry_token_lifetime,
+            max_incoming,
+            incoming_buffer_size,
+            incoming_buffer_size_total,
+            inbound_rate_limit: _,
+            inbound_stats: _,
             handshake_timeout: _,
             keypair,
             mtu_discovery_config,
+            zero_rtt: _,
         } = config;
         let mut transport = quinn::TransportConfig::default();
         // Disable uni-directional streams.
@@ -147,15 +344,22 @@ impl From<Config> for QuinnConfig {
         transport.allow_spin(false);
         transport.stream_receive_window(max_stream_data.into());
         transport.receive_window(max_connection_data.into());
+        transport.initial_rtt(initial_rtt);
+        transport.congestion_controller_factory(congestion_controller.factory());
+        transport.initial_mtu(initial_mtu);
+        transport.min_mtu(min_mtu);
         transport.mtu_discovery_config(mtu_discovery_config);
+        transport.enable_segmentation_offload(segmentation_offload);
         let transport = Arc::new(transport);
 
         let mut server_config = quinn::ServerConfig::with_crypto(server_tls_config);
         server_config.transport = Arc::clone(&transport);
-        // Disables connection migration.
-        // Long-term this should be enabled, however we then need to handle address change
-        // on connections in the `Connection`.
-        server_config.migration(false);
+        server_config
+            .migration(connection_migration)
+            .retry_token_lifetime(retry_token_lifetime)
+            .max_incoming(max_incoming as usize)
+            .incoming_buffer_size(incoming_buffer_size)
+            .incoming_buffer_size_total(incoming_buffer_size_total);
 
         let mut client_config = quinn::ClientConfig::new(client_tls_config);
         client_config.transport_config(transport);
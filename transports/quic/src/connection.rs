@@ -24,11 +24,13 @@ mod stream;
 pub use connecting::Connecting;
 pub use stream::Stream;
 
+use crate::transport::{socketaddr_to_multiaddr, ProtocolVersion};
 use crate::{ConnectionError, Error};
 
 use futures::{future::BoxFuture, FutureExt};
-use libp2p_core::muxing::{StreamMuxer, StreamMuxerEvent};
+use libp2p_core::muxing::{StreamMuxer, StreamMuxerEvent, StreamPriority};
 use std::{
+    net::SocketAddr,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -47,6 +49,15 @@ pub struct Connection {
     >,
     /// Future to wait for the connection to be closed.
     closing: Option<BoxFuture<'static, quinn::ConnectionError>>,
+    /// The remote address last reported, to detect connection migrations.
+    remote_address: SocketAddr,
+    /// Version of the quic protocol, to report address changes.
+    version: ProtocolVersion,
+    /// Future resolving once the handshake of a 0-RTT connection completed, to whether the
+    /// early data was accepted by the remote.
+    zero_rtt_accepted: Option<quinn::ZeroRttAccepted>,
+    /// Whether the connection uses 0-RTT data.
+    is_0rtt: bool,
 }
 
 impl Connection {
@@ -54,14 +65,43 @@ impl Connection {
     ///
     /// This function assumes that the [`quinn::Connection`] is completely fresh and none of
     /// its methods has ever been called. Failure to comply might lead to logic errors and panics.
-    fn new(connection: quinn::Connection) -> Self {
+    fn new(connection: quinn::Connection, version: ProtocolVersion) -> Self {
         Self {
+            remote_address: connection.remote_address(),
             connection,
             incoming: None,
             outgoing: None,
             closing: None,
+            version,
+            zero_rtt_accepted: None,
+            is_0rtt: false,
         }
     }
+
+    /// Build a [`Connection`] from an outbound connection that sends 0-RTT data while its
+    /// handshake is still ongoing.
+    pub(crate) fn new_0rtt(
+        connection: quinn::Connection,
+        accepted: quinn::ZeroRttAccepted,
+        version: ProtocolVersion,
+    ) -> Self {
+        Self {
+            zero_rtt_accepted: Some(accepted),
+            is_0rtt: true,
+            ..Self::new(connection, version)
+        }
+    }
+
+    /// Whether this connection used 0-RTT session resumption.
+    ///
+    /// Data sent in 0-RTT may be replayed by an attacker. Before the handshake completed this
+    /// returns `true` if early data is sent, afterwards whether the remote accepted it.
+    ///
+    /// Always `false` for inbound connections, given that it is not reported whether a
+    /// resuming client sent early data.
+    pub fn is_0rtt(&self) -> bool {
+        self.is_0rtt
+    }
 }
 
 impl StreamMuxer for Connection {
@@ -102,12 +142,46 @@ impl StreamMuxer for Connection {
         Poll::Ready(Ok(stream))
     }
 
+    fn poll_outbound_with_priority(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        priority: StreamPriority,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let stream = futures::ready!(self.poll_outbound(cx))?;
+        stream.set_priority(priority.value());
+        Poll::Ready(Ok(stream))
+    }
+
     fn poll(
         self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
+        cx: &mut Context<'_>,
     ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
-        // TODO: If connection migration is enabled (currently disabled) address
-        // change on the connection needs to be handled.
+        let this = self.get_mut();
+
+        if let Some(accepted) = this.zero_rtt_accepted.as_mut() {
+            if let Poll::Ready(accepted) = accepted.poll_unpin(cx) {
+                tracing::debug!(%accepted, "0-RTT handshake completed");
+                this.zero_rtt_accepted = None;
+                this.is_0rtt = accepted;
+            }
+        }
+
+        // The remote address only changes if connection migration is enabled. There is no
+        // notification for it, but the migration is triggered by packets from the new address,
+        // which in turn wake the connection.
+        let remote_address = this.connection.remote_address();
+        if remote_address != this.remote_address {
+            tracing::debug!(
+                old=%this.remote_address,
+                new=%remote_address,
+                "Connection migrated to new remote address"
+            );
+            this.remote_address = remote_address;
+            return Poll::Ready(Ok(StreamMuxerEvent::AddressChange(
+                socketaddr_to_multiaddr(&remote_address, this.version),
+            )));
+        }
+
         Poll::Pending
     }
 
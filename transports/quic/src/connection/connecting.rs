@@ -20,6 +20,7 @@
 
 //! Future that drives a QUIC connection until is has performed its TLS handshake.
 
+use crate::transport::ProtocolVersion;
 use crate::{Connection, ConnectionError, Error};
 
 use futures::{
@@ -39,12 +40,18 @@ use std::{
 #[derive(Debug)]
 pub struct Connecting {
     connecting: Select<quinn::Connecting, Delay>,
+    version: ProtocolVersion,
 }
 
 impl Connecting {
-    pub(crate) fn new(connection: quinn::Connecting, timeout: Duration) -> Self {
+    pub(crate) fn new(
+        connection: quinn::Connecting,
+        timeout: Duration,
+        version: ProtocolVersion,
+    ) -> Self {
         Connecting {
             connecting: select(connection, Delay::new(timeout)),
+            version,
         }
     }
 }
@@ -77,7 +84,7 @@ impl Future for Connecting {
         };
 
         let peer_id = Self::remote_peer_id(&connection);
-        let muxer = Connection::new(connection);
+        let muxer = Connection::new(connection, self.version);
         Poll::Ready(Ok((peer_id, muxer)))
     }
 }
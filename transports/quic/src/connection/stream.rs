@@ -44,6 +44,13 @@ impl Stream {
             close_result: None,
         }
     }
+
+    /// Sets the priority of sending data on this stream, relative to the other streams.
+    pub(super) fn set_priority(&self, priority: i32) {
+        if self.send.set_priority(priority).is_err() {
+            tracing::debug!("failed to set priority of closed stream");
+        }
+    }
 }
 
 impl AsyncRead for Stream {
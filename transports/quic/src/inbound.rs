@@ -0,0 +1,153 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Defenses of listeners against floods of inbound connection attempts.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A limit of the rate of inbound connections accepted by a listener.
+///
+/// Connection attempts exceeding the rate are refused before any cryptographic work is done.
+/// The limit allows bursts of up to `max_connections` connections, replenishing at a rate of
+/// `max_connections` per `interval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InboundRateLimit {
+    /// The maximum number of connections accepted within `interval`.
+    pub max_connections: u32,
+    /// The interval over which `max_connections` are accepted.
+    pub interval: Duration,
+}
+
+/// Counters of the inbound connection attempts of all listeners of a transport, see
+/// [`Config::inbound_stats`](crate::Config::inbound_stats).
+///
+/// The counters are shared by all clones of the handle and suitable for export as metrics.
+#[derive(Debug, Clone, Default)]
+pub struct InboundStats {
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    accepted: AtomicU64,
+    retried: AtomicU64,
+    refused: AtomicU64,
+}
+
+impl InboundStats {
+    /// The number of connection attempts accepted, i.e. whose handshake was started.
+    pub fn accepted(&self) -> u64 {
+        self.counters.accepted.load(Ordering::Relaxed)
+    }
+
+    /// The number of connection attempts answered with a stateless retry, see
+    /// [`Config::stateless_retry`](crate::Config::stateless_retry).
+    pub fn retried(&self) -> u64 {
+        self.counters.retried.load(Ordering::Relaxed)
+    }
+
+    /// The number of connection attempts refused for exceeding the
+    /// [`Config::inbound_rate_limit`](crate::Config::inbound_rate_limit).
+    pub fn refused(&self) -> u64 {
+        self.counters.refused.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn on_accepted(&self) {
+        self.counters.accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_retried(&self) {
+        self.counters.retried.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_refused(&self) {
+        self.counters.refused.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A token bucket enforcing an [`InboundRateLimit`].
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    limit: InboundRateLimit,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: InboundRateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: f64::from(limit.max_connections),
+            last_refill: now,
+        }
+    }
+
+    /// Takes a token for a new connection, returning `false` if the rate is exceeded.
+    pub(crate) fn try_acquire(&mut self, now: Instant) -> bool {
+        let max_tokens = f64::from(self.limit.max_connections);
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.last_refill = now;
+        self.tokens = if self.limit.interval.is_zero() {
+            max_tokens
+        } else {
+            let refill = elapsed.as_secs_f64() / self.limit.interval.as_secs_f64() * max_tokens;
+            (self.tokens + refill).min(max_tokens)
+        };
+
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_replenishes_tokens() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(
+            InboundRateLimit {
+                max_connections: 2,
+                interval: Duration::from_secs(1),
+            },
+            now,
+        );
+
+        assert!(limiter.try_acquire(now));
+        assert!(limiter.try_acquire(now));
+        assert!(!limiter.try_acquire(now));
+
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.try_acquire(later));
+        assert!(!limiter.try_acquire(later));
+
+        // Tokens do not accumulate beyond the burst size.
+        let much_later = later + Duration::from_secs(10);
+        assert!(limiter.try_acquire(much_later));
+        assert!(limiter.try_acquire(much_later));
+        assert!(!limiter.try_acquire(much_later));
+    }
+}
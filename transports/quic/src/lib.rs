@@ -60,13 +60,15 @@
 mod config;
 mod connection;
 mod hole_punching;
+mod inbound;
 mod provider;
 mod transport;
 
 use std::net::SocketAddr;
 
-pub use config::Config;
+pub use config::{Config, CongestionController};
 pub use connection::{Connecting, Connection, Stream};
+pub use inbound::{InboundRateLimit, InboundStats};
 
 #[cfg(feature = "async-std")]
 pub use provider::async_std;
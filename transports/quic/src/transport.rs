@@ -20,8 +20,9 @@
 
 use crate::config::{Config, QuinnConfig};
 use crate::hole_punching::hole_puncher;
+use crate::inbound::RateLimiter;
 use crate::provider::Provider;
-use crate::{ConnectError, Connecting, Connection, Error};
+use crate::{ConnectError, Connecting, Connection, Error, InboundRateLimit, InboundStats};
 
 use futures::channel::oneshot;
 use futures::future::{BoxFuture, Either};
@@ -42,7 +43,7 @@ use std::collections::hash_map::{DefaultHasher, Entry};
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{fmt, io};
 use std::{
     net::SocketAddr,
@@ -77,6 +78,24 @@ pub struct GenTransport<P: Provider> {
     waker: Option<Waker>,
     /// Holepunching attempts
     hole_punch_attempts: HashMap<SocketAddr, oneshot::Sender<Connecting>>,
+    /// Whether connections are kept alive across address changes.
+    connection_migration: bool,
+    /// Watcher for network interface changes, to rebind the dialer endpoints.
+    ///
+    /// Only set if connection migration is enabled and a dialer exists.
+    if_watcher: Option<P::IfWatcher>,
+    /// Whether to attempt 0-RTT session resumption when dialing a known peer.
+    zero_rtt: bool,
+    /// How listeners handle inbound connection attempts.
+    inbound: InboundPolicy,
+}
+
+/// How listeners handle inbound connection attempts.
+#[derive(Debug, Clone)]
+struct InboundPolicy {
+    stateless_retry: bool,
+    rate_limit: Option<InboundRateLimit>,
+    stats: InboundStats,
 }
 
 impl<P: Provider> GenTransport<P> {
@@ -84,6 +103,13 @@ impl<P: Provider> GenTransport<P> {
     pub fn new(config: Config) -> Self {
         let handshake_timeout = config.handshake_timeout;
         let support_draft_29 = config.support_draft_29;
+        let connection_migration = config.connection_migration;
+        let zero_rtt = config.zero_rtt;
+        let inbound = InboundPolicy {
+            stateless_retry: config.stateless_retry,
+            rate_limit: config.inbound_rate_limit,
+            stats: config.inbound_stats(),
+        };
         let quinn_config = config.into();
         Self {
             listeners: SelectAll::new(),
@@ -93,6 +119,63 @@ impl<P: Provider> GenTransport<P> {
             waker: None,
             support_draft_29,
             hole_punch_attempts: Default::default(),
+            connection_migration,
+            if_watcher: None,
+            zero_rtt,
+            inbound,
+        }
+    }
+
+    /// Rebind the endpoints used for dialing to new UDP sockets.
+    ///
+    /// Connections established through these endpoints migrate to the new socket if
+    /// [`Config::connection_migration`] is enabled on the remote. This is done automatically
+    /// when a network interface goes down, but may also be triggered by the application, e.g.
+    /// if the operating system reports a network change.
+    ///
+    /// Endpoints that are shared with a listener are not rebound, given that they need to
+    /// keep their port.
+    pub fn rebind(&mut self) -> Result<(), Error> {
+        for (socket_family, endpoint) in self.dialer.iter() {
+            let socket = UdpSocket::bind(socket_family.unspecified())?;
+            tracing::debug!(
+                address=?socket.local_addr(),
+                "Rebinding dialer endpoint"
+            );
+            endpoint.rebind(socket)?;
+        }
+        Ok(())
+    }
+
+    /// Poll the interface watcher and rebind the dialer endpoints once an interface goes down.
+    fn poll_if_watcher(&mut self, cx: &mut Context<'_>) {
+        if self.dialer.is_empty() {
+            self.if_watcher = None;
+            return;
+        }
+        let Some(if_watcher) = self.if_watcher.as_mut() else {
+            return;
+        };
+        let mut interface_down = false;
+        loop {
+            match P::poll_if_event(if_watcher, cx) {
+                Poll::Ready(Ok(IfEvent::Down(inet))) => {
+                    tracing::debug!(address=%inet.addr(), "Network interface went down");
+                    interface_down = true;
+                }
+                Poll::Ready(Ok(IfEvent::Up(_))) => {}
+                Poll::Ready(Err(error)) => {
+                    tracing::debug!("Failed to watch network interfaces: {error}");
+                    self.if_watcher = None;
+                    break;
+                }
+                Poll::Pending => break,
+            }
+        }
+        if interface_down {
+            if let Err(error) = self.rebind() {
+                tracing::warn!("Failed to rebind dialer endpoints: {error}");
+            }
         }
     }
 
@@ -221,6 +304,7 @@ impl<P: Provider> Transport for GenTransport<P> {
             endpoint,
             self.handshake_timeout,
             version,
+            self.inbound.clone(),
         )?;
         self.listeners.push(listener);
 
@@ -257,7 +341,7 @@ impl<P: Provider> Transport for GenTransport<P> {
     }
 
     fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
-        let (socket_addr, version, _peer_id) = self.remote_multiaddr_to_socketaddr(addr, true)?;
+        let (socket_addr, version, peer_id) = self.remote_multiaddr_to_socketaddr(addr, true)?;
 
         let endpoint = match self.eligible_listener(&socket_addr) {
             None => {
@@ -269,15 +353,20 @@ impl<P: Provider> Transport for GenTransport<P> {
                         if let Some(waker) = self.waker.take() {
                             waker.wake();
                         }
-                        let listen_socket_addr = match socket_family {
-                            SocketFamily::Ipv4 => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
-                            SocketFamily::Ipv6 => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
-                        };
-                        let socket =
-                            UdpSocket::bind(listen_socket_addr).map_err(Self::Error::from)?;
+                        let socket = UdpSocket::bind(socket_family.unspecified())
+                            .map_err(Self::Error::from)?;
                         let endpoint_config = self.quinn_config.endpoint_config.clone();
                         let endpoint = Self::new_endpoint(endpoint_config, None, socket)?;
 
+                        if self.connection_migration && self.if_watcher.is_none() {
+                            match P::new_if_watcher() {
+                                Ok(if_watcher) => self.if_watcher = Some(if_watcher),
+                                Err(error) => {
+                                    tracing::debug!("Failed to watch network interfaces: {error}")
+                                }
+                            }
+                        }
+
                         vacant.insert(endpoint.clone());
                         endpoint
                     }
@@ -291,14 +380,27 @@ impl<P: Provider> Transport for GenTransport<P> {
         if version == ProtocolVersion::Draft29 {
             client_config.version(0xff00_001d);
         }
+        // Session tickets are cached by the server name, thus resumption is only attempted
+        // if the peer ID is known, which is then used as server name.
+        let zero_rtt_peer_id = peer_id.filter(|_| self.zero_rtt);
         Ok(Box::pin(async move {
             // This `"l"` seems necessary because an empty string is an invalid domain
             // name. While we don't use domain names, the underlying rustls library
             // is based upon the assumption that we do.
+            let server_name = zero_rtt_peer_id.map_or_else(|| "l".to_owned(), |p| p.to_base58());
             let connecting = endpoint
-                .connect_with(client_config, socket_addr, "l")
+                .connect_with(client_config, socket_addr, &server_name)
                 .map_err(ConnectError)?;
-            Connecting::new(connecting, handshake_timeout).await
+            let Some(peer_id) = zero_rtt_peer_id else {
+                return Connecting::new(connecting, handshake_timeout, version).await;
+            };
+            match connecting.into_0rtt() {
+                Ok((connection, accepted)) => {
+                    tracing::debug!(peer=%peer_id, "Sending 0-RTT data");
+                    Ok((peer_id, Connection::new_0rtt(connection, accepted, version)))
+                }
+                Err(connecting) => Connecting::new(connecting, handshake_timeout, version).await,
+            }
         }))
     }
 
@@ -366,6 +468,8 @@ impl<P: Provider> Transport for GenTransport<P> {
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+        self.poll_if_watcher(cx);
+
         while let Poll::Ready(Some(ev)) = self.listeners.poll_next_unpin(cx) {
             match ev {
                 TransportEvent::Incoming {
@@ -429,6 +533,11 @@ struct Listener<P: Provider> {
     /// Timeout for connection establishment on inbound connections.
     handshake_timeout: Duration,
 
+    /// How inbound connection attempts are handled.
+    inbound: InboundPolicy,
+    /// Enforces the rate limit of inbound connections, if any.
+    rate_limiter: Option<RateLimiter>,
+
     /// Watcher for network interface changes.
     ///
     /// None if we are only listening on a single interface.
@@ -453,6 +562,7 @@ impl<P: Provider> Listener<P> {
         endpoint: quinn::Endpoint,
         handshake_timeout: Duration,
         version: ProtocolVersion,
+        inbound: InboundPolicy,
     ) -> Result<Self, Error> {
         let if_watcher;
         let pending_event;
@@ -473,6 +583,9 @@ impl<P: Provider> Listener<P> {
 
         let endpoint_c = endpoint.clone();
         let accept = async move { endpoint_c.accept().await }.boxed();
+        let rate_limiter = inbound
+            .rate_limit
+            .map(|limit| RateLimiter::new(limit, Instant::now()));
 
         Ok(Listener {
             endpoint,
@@ -481,6 +594,8 @@ impl<P: Provider> Listener<P> {
             listener_id,
             version,
             handshake_timeout,
+            inbound,
+            rate_limiter,
             if_watcher,
             is_closed: false,
             pending_event,
@@ -587,6 +702,30 @@ impl<P: Provider> Stream for Listener<P> {
                     let endpoint = self.endpoint.clone();
                     self.accept = async move { endpoint.accept().await }.boxed();
 
+                    if self.inbound.stateless_retry && !incoming.remote_address_validated() {
+                        tracing::trace!(
+                            remote_address=%incoming.remote_address(),
+                            "Validating address of incoming connection via stateless retry"
+                        );
+                        if let Err(e) = incoming.retry() {
+                            e.into_incoming().refuse();
+                        }
+                        self.inbound.stats.on_retried();
+                        continue;
+                    }
+                    if let Some(rate_limiter) = self.rate_limiter.as_mut() {
+                        if !rate_limiter.try_acquire(Instant::now()) {
+                            tracing::debug!(
+                                remote_address=%incoming.remote_address(),
+                                "Refusing incoming connection exceeding rate limit"
+                            );
+                            incoming.refuse();
+                            self.inbound.stats.on_refused();
+                            continue;
+                        }
+                    }
+                    self.inbound.stats.on_accepted();
+
                     let connecting = match incoming.accept() {
                         Ok(connecting) => connecting,
                         Err(error) => {
@@ -602,7 +741,7 @@ impl<P: Provider> Stream for Listener<P> {
                     let send_back_addr = socketaddr_to_multiaddr(&remote_addr, self.version);
 
                     let event = TransportEvent::Incoming {
-                        upgrade: Connecting::new(connecting, self.handshake_timeout),
+                        upgrade: Connecting::new(connecting, self.handshake_timeout, self.version),
                         local_addr,
                         send_back_addr,
                         listener_id: self.listener_id,
@@ -647,6 +786,14 @@ pub(crate) enum SocketFamily {
 }
 
 impl SocketFamily {
+    /// The unspecified address of this family with port 0.
+    fn unspecified(&self) -> SocketAddr {
+        match self {
+            SocketFamily::Ipv4 => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
+            SocketFamily::Ipv6 => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
+        }
+    }
+
     fn is_same(a: &IpAddr, b: &IpAddr) -> bool {
         matches!(
             (a, b),
@@ -750,7 +897,10 @@ fn is_quic_addr(addr: &Multiaddr, support_draft_29: bool) -> bool {
 }
 
 /// Turns an IP address and port into the corresponding QUIC multiaddr.
-fn socketaddr_to_multiaddr(socket_addr: &SocketAddr, version: ProtocolVersion) -> Multiaddr {
+pub(crate) fn socketaddr_to_multiaddr(
+    socket_addr: &SocketAddr,
+    version: ProtocolVersion,
+) -> Multiaddr {
     let quic_proto = match version {
         ProtocolVersion::V1 => Protocol::QuicV1,
         ProtocolVersion::Draft29 => Protocol::Quic,
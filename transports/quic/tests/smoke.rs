@@ -6,7 +6,7 @@ use futures::future::{poll_fn, Either};
 use futures::stream::StreamExt;
 use futures::{future, AsyncReadExt, AsyncWriteExt, FutureExt, SinkExt};
 use futures_timer::Delay;
-use libp2p_core::muxing::{StreamMuxerBox, StreamMuxerExt, SubstreamBox};
+use libp2p_core::muxing::{StreamMuxerBox, StreamMuxerEvent, StreamMuxerExt, SubstreamBox};
 use libp2p_core::transport::{Boxed, OrTransport, TransportEvent};
 use libp2p_core::transport::{ListenerId, TransportError};
 use libp2p_core::{multiaddr::Protocol, upgrade, Multiaddr, Transport};
@@ -458,6 +458,187 @@ async fn test_local_listener_reuse() {
     assert_eq!(send_back_addr, a_listen_addr);
 }
 
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn connection_migration() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let (_, mut a_transport) = create_transport::<quic::tokio::Provider>(|cfg| {
+        cfg.connection_migration = true;
+    });
+    let mut b_transport = quic::tokio::Transport::new(quic::Config::new(&generate_tls_keypair()));
+
+    let a_addr = start_listening(&mut a_transport, "/ip4/127.0.0.1/udp/0/quic-v1").await;
+    let ((b_send_back_addr, mut conn_a), (_, mut conn_b)) = future::join(
+        async {
+            let (upgrade, send_back_addr) = a_transport
+                .select_next_some()
+                .await
+                .into_incoming()
+                .unwrap();
+            let (_, connection) = upgrade.await.unwrap();
+            (send_back_addr, connection)
+        },
+        async { b_transport.dial(a_addr).unwrap().await.unwrap() },
+    )
+    .await;
+
+    b_transport.rebind().unwrap();
+
+    // Send data from the new socket so that `a` notices the new address.
+    let mut stream_b = poll_fn(|cx| conn_b.poll_outbound_unpin(cx)).await.unwrap();
+    stream_b.write_all(&[0]).await.unwrap();
+
+    let b_new_addr = poll_fn(|cx| {
+        let _ = conn_a.poll_inbound_unpin(cx);
+        match conn_a.poll_unpin(cx) {
+            Poll::Ready(Ok(StreamMuxerEvent::AddressChange(addr))) => Poll::Ready(addr),
+            Poll::Ready(e) => panic!("Unexpected event: {e:?}"),
+            Poll::Pending => Poll::Pending,
+        }
+    })
+    .await;
+
+    assert_ne!(b_new_addr, b_send_back_addr);
+    assert!(matches!(b_new_addr.iter().last(), Some(Protocol::QuicV1)));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn zero_rtt_resumption() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let (a_peer_id, mut a_transport) = create_transport::<quic::tokio::Provider>(|cfg| {
+        *cfg = cfg.clone().enable_0rtt();
+    });
+    let mut b_transport =
+        quic::tokio::Transport::new(quic::Config::new(&generate_tls_keypair()).enable_0rtt());
+
+    let a_addr = start_listening(&mut a_transport, "/ip4/127.0.0.1/udp/0/quic-v1")
+        .await
+        .with(Protocol::P2p(a_peer_id));
+
+    // The first connection requires a full handshake, through which `b` obtains a session ticket.
+    let (mut conn_a, (_, mut conn_b)) = future::join(accept(&mut a_transport), async {
+        b_transport.dial(a_addr.clone()).unwrap().await.unwrap()
+    })
+    .await;
+    assert!(!conn_b.is_0rtt());
+    ping(&mut conn_a, &mut conn_b).await;
+    conn_a.close().await.unwrap();
+    drop(conn_b);
+
+    let (mut conn_a, (peer_id, mut conn_b)) = future::join(accept(&mut a_transport), async {
+        b_transport.dial(a_addr).unwrap().await.unwrap()
+    })
+    .await;
+    assert_eq!(peer_id, a_peer_id);
+    assert!(conn_b.is_0rtt());
+
+    ping(&mut conn_a, &mut conn_b).await;
+    // Let the connection observe that the handshake completed.
+    poll_fn(|cx| {
+        let _ = conn_b.poll_unpin(cx);
+        Poll::Ready(())
+    })
+    .await;
+    assert!(conn_b.is_0rtt(), "Expected the early data to be accepted");
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn tuned_transport() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let tune = |cfg: &mut quic::Config| {
+        cfg.initial_rtt = Duration::from_millis(10);
+        cfg.congestion_controller = quic::CongestionController::Bbr;
+        cfg.initial_mtu = 1400;
+        cfg.segmentation_offload = false;
+        *cfg = cfg
+            .clone()
+            .mtu_upper_bound(9000)
+            .mtu_discovery_interval(Duration::from_secs(60))
+            .mtu_minimum_change(10);
+    };
+    let (a_peer_id, mut a_transport) = create_transport::<quic::tokio::Provider>(tune);
+    let mut b_transport = {
+        let mut config = quic::Config::new(&generate_tls_keypair());
+        tune(&mut config);
+        quic::tokio::Transport::new(config)
+    };
+
+    let a_addr = start_listening(&mut a_transport, "/ip4/127.0.0.1/udp/0/quic-v1").await;
+    let (mut conn_a, (peer_id, mut conn_b)) = future::join(accept(&mut a_transport), async {
+        b_transport.dial(a_addr).unwrap().await.unwrap()
+    })
+    .await;
+    assert_eq!(peer_id, a_peer_id);
+    ping(&mut conn_a, &mut conn_b).await;
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn stateless_retry_and_inbound_rate_limit() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let keypair = generate_tls_keypair();
+    let a_peer_id = keypair.public().to_peer_id();
+    let mut config = quic::Config::new(&keypair);
+    config.stateless_retry = true;
+    config.inbound_rate_limit = Some(quic::InboundRateLimit {
+        max_connections: 1,
+        interval: Duration::from_secs(3600),
+    });
+    let stats = config.inbound_stats();
+    let mut a_transport = quic::tokio::Transport::new(config)
+        .map(|(p, c), _| (p, StreamMuxerBox::new(c)))
+        .boxed();
+    let (_, mut b_transport) = create_default_transport::<quic::tokio::Provider>();
+
+    let a_addr = start_listening(&mut a_transport, "/ip4/127.0.0.1/udp/0/quic-v1")
+        .await
+        .with(Protocol::P2p(a_peer_id));
+
+    let (_conn_a, (peer_id, _conn_b)) = future::join(accept(&mut a_transport), async {
+        b_transport.dial(a_addr.clone()).unwrap().await.unwrap()
+    })
+    .await;
+    assert_eq!(peer_id, a_peer_id);
+    assert_eq!(stats.retried(), 1);
+    assert_eq!(stats.accepted(), 1);
+
+    // The second connection attempt exceeds the rate limit once its address is validated.
+    match future::select(b_transport.dial(a_addr).unwrap(), a_transport.next()).await {
+        Either::Left((result, _)) => assert!(result.is_err()),
+        Either::Right((event, _)) => panic!("Unexpected event: {event:?}"),
+    }
+    assert_eq!(stats.retried(), 2);
+    assert_eq!(stats.refused(), 1);
+    assert_eq!(stats.accepted(), 1);
+}
+
+async fn accept(transport: &mut Boxed<(PeerId, StreamMuxerBox)>) -> StreamMuxerBox {
+    let (upgrade, _) = transport.select_next_some().await.into_incoming().unwrap();
+    upgrade.await.unwrap().1
+}
+
+/// Exchange a message on a new stream opened by `b`.
+async fn ping(conn_a: &mut StreamMuxerBox, conn_b: &mut quic::Connection) {
+    let mut stream_b = poll_fn(|cx| conn_b.poll_outbound_unpin(cx)).await.unwrap();
+    stream_b.write_all(&[1]).await.unwrap();
+    let mut stream_a = poll_fn(|cx| conn_a.poll_inbound_unpin(cx)).await.unwrap();
+    let mut buf = [0];
+    stream_a.read_exact(&mut buf).await.unwrap();
+    stream_a.write_all(&buf).await.unwrap();
+    stream_b.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [1]);
+}
+
 async fn smoke<P: Provider>() {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
@@ -29,6 +29,7 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 mod provider;
+mod proxy;
 
 #[cfg(feature = "async-io")]
 pub use provider::async_io;
@@ -36,6 +37,8 @@ pub use provider::async_io;
 #[cfg(feature = "tokio")]
 pub use provider::tokio;
 
+pub use proxy::{Proxy, ProxyProtocol};
+
 use futures::{future::Ready, prelude::*, stream::SelectAll};
 use futures_timer::Delay;
 use if_watch::IfEvent;
@@ -48,7 +51,7 @@ use provider::{Incoming, Provider};
 use socket2::{Domain, Socket, Type};
 use std::{
     collections::{HashSet, VecDeque},
-    io,
+    fmt, io,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener},
     pin::Pin,
     sync::{Arc, RwLock},
@@ -57,7 +60,7 @@ use std::{
 };
 
 /// The configuration for a TCP/IP transport capability for libp2p.
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct Config {
     /// TTL to set for opened sockets, or `None` to keep default.
     ttl: Option<u32>,
@@ -67,8 +70,20 @@ pub struct Config {
     backlog: u32,
     /// Whether port reuse should be enabled.
     enable_port_reuse: bool,
+    /// Whether TCP Fast Open should be enabled.
+    fast_open: bool,
+    /// `TCP_USER_TIMEOUT` to set for opened sockets, or `None` to keep default.
+    user_timeout: Option<Duration>,
+    /// Hook to configure new sockets before they are bound, connected or set to listen.
+    socket_hook: Option<SocketHook>,
+    /// Proxy to make outgoing connections through, or `None` to connect directly.
+    proxy: Option<Proxy>,
+    /// Timeout for establishing outgoing connections, or `None` to rely on the OS.
+    connect_timeout: Option<Duration>,
 }
 
+type SocketHook = Arc<dyn Fn(&Socket) -> io::Result<()> + Send + Sync>;
+
 type Port = u16;
 
 /// The configuration for port reuse of listening sockets.
@@ -164,6 +179,11 @@ impl Config {
             nodelay: None,
             backlog: 1024,
             enable_port_reuse: false,
+            fast_open: false,
+            user_timeout: None,
+            socket_hook: None,
+            proxy: None,
+            connect_timeout: None,
         }
     }
 
@@ -185,6 +205,66 @@ impl Config {
         self
     }
 
+    /// Configures TCP Fast Open ([RFC 7413](https://tools.ietf.org/html/rfc7413)) for new
+    /// sockets, i.e. the `TCP_FASTOPEN` option for listen sockets and `TCP_FASTOPEN_CONNECT`
+    /// for dial sockets.
+    ///
+    /// With TCP Fast Open, data written right after dialing a remote that was connected to
+    /// before is already sent in the SYN, saving a round trip. The option is only supported
+    /// on Linux and Android and ignored on other platforms.
+    ///
+    /// > **Note**: Data sent in the SYN may be duplicated by the network. Furthermore a failure
+    /// > to connect to the remote is only reported once the first data is written.
+    pub fn fast_open(mut self, value: bool) -> Self {
+        self.fast_open = value;
+        self
+    }
+
+    /// Configures the `TCP_USER_TIMEOUT` option for new sockets, i.e. the maximum time that
+    /// transmitted data may remain unacknowledged before the connection is closed.
+    ///
+    /// The option is only supported on Linux, Android and Fuchsia and ignored on other
+    /// platforms.
+    pub fn user_timeout(mut self, timeout: Duration) -> Self {
+        self.user_timeout = Some(timeout);
+        self
+    }
+
+    /// Configures a hook that is called with each new socket before it is bound, connected or
+    /// set to listen.
+    ///
+    /// This allows setting socket options that are not covered by [`Config`]. The hook is
+    /// called after all options of the [`Config`] were applied. An error returned by the hook
+    /// fails the respective dial or listen attempt.
+    pub fn socket_hook(
+        mut self,
+        hook: impl Fn(&Socket) -> io::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.socket_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Configures a proxy that outgoing connections are made through.
+    ///
+    /// Besides `/ip4` and `/ip6` addresses, `/dns`, `/dns4` and `/dns6` addresses can be
+    /// dialed, leaving the resolution of the host name to the proxy. Note that these are
+    /// resolved locally if the transport is wrapped in a DNS transport. Port reuse does not
+    /// apply to connections to the proxy.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Configures a timeout for establishing outgoing connections, including the handshake
+    /// with the [proxy](Config::proxy), if any.
+    ///
+    /// Dials that do not complete in time fail with an error of kind
+    /// [`io::ErrorKind::TimedOut`]. By default, only the connect timeout of the OS applies.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
     /// Configures port reuse for local sockets, which implies
     /// reuse of listening ports for outgoing connections to
     /// enhance NAT traversal capabilities.
@@ -292,6 +372,22 @@ impl Default for Config {
     }
 }
 
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("ttl", &self.ttl)
+            .field("nodelay", &self.nodelay)
+            .field("backlog", &self.backlog)
+            .field("enable_port_reuse", &self.enable_port_reuse)
+            .field("fast_open", &self.fast_open)
+            .field("user_timeout", &self.user_timeout)
+            .field("socket_hook", &self.socket_hook.is_some())
+            .field("proxy", &self.proxy)
+            .field("connect_timeout", &self.connect_timeout)
+            .finish()
+    }
+}
+
 /// An abstract [`libp2p_core::Transport`] implementation.
 ///
 /// You shouldn't need to use this type directly. Use one of the following instead:
@@ -357,11 +453,18 @@ where
         if let Some(nodelay) = self.config.nodelay {
             socket.set_nodelay(nodelay)?;
         }
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if let Some(user_timeout) = self.config.user_timeout {
+            socket.set_tcp_user_timeout(Some(user_timeout))?;
+        }
         socket.set_reuse_address(true)?;
         #[cfg(unix)]
         if let PortReuse::Enabled { .. } = &self.port_reuse {
             socket.set_reuse_port(true)?;
         }
+        if let Some(hook) = &self.config.socket_hook {
+            hook(&socket)?;
+        }
         Ok(socket)
     }
 
@@ -372,6 +475,11 @@ where
     ) -> io::Result<ListenStream<T>> {
         let socket = self.create_socket(socket_addr)?;
         socket.bind(&socket_addr.into())?;
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        if self.config.fast_open {
+            // The value is the maximum length of the queue of pending Fast Open requests.
+            set_tcp_fast_open(&socket, libc::TCP_FASTOPEN, self.config.backlog as _)?;
+        }
         socket.listen(self.config.backlog as _)?;
         socket.set_nonblocking(true)?;
         let listener: TcpListener = socket.into();
@@ -457,13 +565,19 @@ where
     }
 
     fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
-        let socket_addr = if let Ok(socket_addr) = multiaddr_to_socketaddr(addr.clone()) {
-            if socket_addr.port() == 0 || socket_addr.ip().is_unspecified() {
-                return Err(TransportError::MultiaddrNotSupported(addr));
-            }
-            socket_addr
-        } else {
-            return Err(TransportError::MultiaddrNotSupported(addr));
+        let (socket_addr, proxy_target) = match &self.config.proxy {
+            Some(proxy) => match multiaddr_to_host_port(addr.clone()) {
+                Ok((host, port)) if port != 0 => (proxy.addr(), Some((proxy.clone(), host, port))),
+                _ => return Err(TransportError::MultiaddrNotSupported(addr)),
+            },
+            None => match multiaddr_to_socketaddr(addr.clone()) {
+                Ok(socket_addr)
+                    if socket_addr.port() != 0 && !socket_addr.ip().is_unspecified() =>
+                {
+                    (socket_addr, None)
+                }
+                _ => return Err(TransportError::MultiaddrNotSupported(addr)),
+            },
         };
         tracing::debug!(address=%socket_addr, "dialing address");
 
@@ -471,7 +585,16 @@ where
             .create_socket(socket_addr)
             .map_err(TransportError::Other)?;
 
-        if let Some(addr) = self.port_reuse.local_dial_addr(&socket_addr.ip()) {
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        if self.config.fast_open {
+            set_tcp_fast_open(&socket, libc::TCP_FASTOPEN_CONNECT, 1)
+                .map_err(TransportError::Other)?;
+        }
+
+        if let (None, Some(addr)) = (
+            &proxy_target,
+            self.port_reuse.local_dial_addr(&socket_addr.ip()),
+        ) {
             tracing::trace!(address=%addr, "Binding dial socket to listen socket address");
             socket.bind(&addr.into()).map_err(TransportError::Other)?;
         }
@@ -480,7 +603,7 @@ where
             .set_nonblocking(true)
             .map_err(TransportError::Other)?;
 
-        Ok(async move {
+        let connect = async move {
             // [`Transport::dial`] should do no work unless the returned [`Future`] is polled. Thus
             // do the `connect` call within the [`Future`].
             match socket.connect(&socket_addr.into()) {
@@ -490,8 +613,23 @@ where
                 Err(err) => return Err(err),
             };
 
-            let stream = T::new_stream(socket.into()).await?;
+            let mut stream = T::new_stream(socket.into()).await?;
+            if let Some((proxy, host, port)) = proxy_target {
+                proxy.connect(&mut stream, &host, port).await?;
+            }
             Ok(stream)
+        };
+        let Some(timeout) = self.config.connect_timeout else {
+            return Ok(connect.boxed());
+        };
+        Ok(async move {
+            match future::select(connect.boxed(), Delay::new(timeout)).await {
+                future::Either::Left((result, _)) => result,
+                future::Either::Right(_) => Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("connection was not established within {timeout:?}"),
+                )),
+            }
         }
         .boxed())
     }
@@ -814,6 +952,30 @@ fn multiaddr_to_socketaddr(mut addr: Multiaddr) -> Result<SocketAddr, ()> {
     Err(())
 }
 
+/// Extracts the host and port to connect to via a proxy from a [`Multiaddr`], where the host is
+/// either an IP address or a host name.
+fn multiaddr_to_host_port(mut addr: Multiaddr) -> Result<(String, u16), ()> {
+    let mut port = None;
+    while let Some(proto) = addr.pop() {
+        let host = match proto {
+            Protocol::Ip4(ipv4) => ipv4.to_string(),
+            Protocol::Ip6(ipv6) => ipv6.to_string(),
+            Protocol::Dns(host) | Protocol::Dns4(host) | Protocol::Dns6(host) => host.into_owned(),
+            Protocol::Tcp(portnum) => match port {
+                Some(_) => return Err(()),
+                None => {
+                    port = Some(portnum);
+                    continue;
+                }
+            },
+            Protocol::P2p(_) if port.is_none() => continue,
+            _ => return Err(()),
+        };
+        return port.map(|port| (host, port)).ok_or(());
+    }
+    Err(())
+}
+
 // Create a [`Multiaddr`] from the given IP address and port number.
 fn ip_to_multiaddr(ip: IpAddr, port: u16) -> Multiaddr {
     Multiaddr::empty().with(ip.into()).with(Protocol::Tcp(port))
@@ -836,6 +998,28 @@ fn is_tcp_addr(addr: &Multiaddr) -> bool {
     matches!(first, Ip4(_) | Ip6(_) | Dns(_) | Dns4(_) | Dns6(_)) && matches!(second, Tcp(_))
 }
 
+/// Sets one of the TCP Fast Open socket options, see `tcp(7)`.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn set_tcp_fast_open(socket: &Socket, option: libc::c_int, value: libc::c_int) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: The socket is a valid file descriptor and the option value is a `c_int`, whose
+    // size is passed along.
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            option,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -966,6 +1150,162 @@ mod tests {
         test("/ip6/::1/tcp/0".parse().unwrap());
     }
 
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn socket_options() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let hook_calls = Arc::new(AtomicUsize::new(0));
+        let config = Config::new()
+            .fast_open(true)
+            .user_timeout(Duration::from_secs(10))
+            .socket_hook({
+                let hook_calls = hook_calls.clone();
+                move |socket| {
+                    hook_calls.fetch_add(1, Ordering::SeqCst);
+                    socket.set_keepalive(true)
+                }
+            });
+
+        let rt = ::tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let mut listener = Transport::<tokio::Tcp>::new(config.clone()).boxed();
+            listener
+                .listen_on(ListenerId::next(), "/ip4/127.0.0.1/tcp/0".parse().unwrap())
+                .unwrap();
+            let addr = match listener.select_next_some().await {
+                TransportEvent::NewAddress { listen_addr, .. } => listen_addr,
+                e => panic!("Unexpected transport event: {e:?}"),
+            };
+
+            let mut dialer = Transport::<tokio::Tcp>::new(config);
+            let (mut incoming, mut outgoing) = futures::future::join(
+                async {
+                    match listener.select_next_some().await {
+                        TransportEvent::Incoming { upgrade, .. } => upgrade.await.unwrap(),
+                        e => panic!("Unexpected transport event: {e:?}"),
+                    }
+                },
+                async {
+                    let mut stream = dialer.dial(addr).unwrap().await.unwrap();
+                    stream.write_all(&[1, 2, 3]).await.unwrap();
+                    stream
+                },
+            )
+            .await;
+
+            let mut buf = [0u8; 3];
+            incoming.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [1, 2, 3]);
+            incoming.write_all(&[4, 5, 6]).await.unwrap();
+            outgoing.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [4, 5, 6]);
+        });
+
+        // Once for the listen socket and once for the dial socket.
+        assert_eq!(hook_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn dialing_via_proxy() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let rt = ::tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            // The proxy tunnels connections to itself.
+            let mut proxy = Transport::<tokio::Tcp>::default().boxed();
+            proxy
+                .listen_on(ListenerId::next(), "/ip4/127.0.0.1/tcp/0".parse().unwrap())
+                .unwrap();
+            let proxy_addr = match proxy.select_next_some().await {
+                TransportEvent::NewAddress { listen_addr, .. } => {
+                    multiaddr_to_socketaddr(listen_addr).unwrap()
+                }
+                e => panic!("Unexpected transport event: {e:?}"),
+            };
+
+            let mut dialer =
+                Transport::<tokio::Tcp>::new(Config::new().proxy(Proxy::http_connect(proxy_addr)));
+            assert!(dialer
+                .dial("/ip4/127.0.0.1/udp/443".parse().unwrap())
+                .is_err());
+            let (mut incoming, mut outgoing) = futures::future::join(
+                async {
+                    let mut stream = match proxy.select_next_some().await {
+                        TransportEvent::Incoming { upgrade, .. } => upgrade.await.unwrap(),
+                        e => panic!("Unexpected transport event: {e:?}"),
+                    };
+                    let mut request = Vec::new();
+                    while !request.ends_with(b"\r\n\r\n") {
+                        let mut byte = [0];
+                        stream.read_exact(&mut byte).await.unwrap();
+                        request.extend_from_slice(&byte);
+                    }
+                    assert_eq!(
+                        request,
+                        b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n"
+                    );
+                    stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await.unwrap();
+                    stream
+                },
+                async {
+                    let addr = "/dns/example.com/tcp/443".parse().unwrap();
+                    let mut stream = dialer.dial(addr).unwrap().await.unwrap();
+                    stream.write_all(&[1, 2, 3]).await.unwrap();
+                    stream
+                },
+            )
+            .await;
+
+            let mut buf = [0u8; 3];
+            incoming.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [1, 2, 3]);
+            incoming.write_all(&[4, 5, 6]).await.unwrap();
+            outgoing.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [4, 5, 6]);
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn dial_times_out() {
+        let rt = ::tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            // A proxy that never answers keeps the dial from completing.
+            let proxy = ::tokio::net::TcpListener::bind("127.0.0.1:0")
+                .await
+                .unwrap();
+            let proxy_addr = proxy.local_addr().unwrap();
+
+            let mut dialer = Transport::<tokio::Tcp>::new(
+                Config::new()
+                    .proxy(Proxy::socks5(proxy_addr))
+                    .connect_timeout(Duration::from_millis(100)),
+            );
+            let dial = dialer.dial("/dns/example.com/tcp/443".parse().unwrap());
+            let (error, _stream) =
+                futures::future::join(async { dial.unwrap().await.unwrap_err() }, proxy.accept())
+                    .await;
+            assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        });
+    }
+
     #[test]
     fn wildcard_expansion() {
         let _ = tracing_subscriber::fmt()
@@ -0,0 +1,451 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Dialing via SOCKS5 ([RFC 1928]) and HTTP `CONNECT` ([RFC 9110]) proxies.
+//!
+//! [RFC 1928]: https://www.rfc-editor.org/rfc/rfc1928
+//! [RFC 9110]: https://www.rfc-editor.org/rfc/rfc9110#name-connect
+
+use base64::Engine as _;
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use std::{
+    fmt, io,
+    net::{IpAddr, SocketAddr},
+};
+
+const SOCKS_VERSION: u8 = 5;
+const SOCKS_NO_AUTHENTICATION: u8 = 0;
+const SOCKS_USERNAME_PASSWORD: u8 = 2;
+const SOCKS_NO_ACCEPTABLE_METHOD: u8 = 0xff;
+const SOCKS_CONNECT: u8 = 1;
+const SOCKS_ADDRESS_IPV4: u8 = 1;
+const SOCKS_ADDRESS_DOMAIN: u8 = 3;
+const SOCKS_ADDRESS_IPV6: u8 = 4;
+
+/// The maximum size of the response of an HTTP proxy to a `CONNECT` request.
+const MAX_HTTP_RESPONSE_SIZE: usize = 8 * 1024;
+
+/// The protocol spoken with a [`Proxy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyProtocol {
+    /// SOCKS version 5, optionally with username and password authentication ([RFC 1929]).
+    ///
+    /// [RFC 1929]: https://www.rfc-editor.org/rfc/rfc1929
+    Socks5,
+    /// An HTTP `CONNECT` request, optionally with basic authentication.
+    HttpConnect,
+}
+
+/// A proxy that outgoing connections are made through.
+#[derive(Clone)]
+pub struct Proxy {
+    addr: SocketAddr,
+    protocol: ProxyProtocol,
+    credentials: Option<(String, String)>,
+}
+
+impl Proxy {
+    /// Creates a SOCKS5 proxy listening on `addr`.
+    pub fn socks5(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            protocol: ProxyProtocol::Socks5,
+            credentials: None,
+        }
+    }
+
+    /// Creates an HTTP proxy listening on `addr`, supporting `CONNECT` requests.
+    pub fn http_connect(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            protocol: ProxyProtocol::HttpConnect,
+            credentials: None,
+        }
+    }
+
+    /// Sets the username and password to authenticate to the proxy with.
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// The address of the proxy.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The protocol spoken with the proxy.
+    pub fn protocol(&self) -> ProxyProtocol {
+        self.protocol
+    }
+
+    /// Requests the proxy on the other end of `stream` to connect to `host` and `port`.
+    ///
+    /// The `host` is either an IP address or a domain name, which is resolved by the proxy.
+    /// Once this returns, `stream` is connected to the target.
+    pub async fn connect<S>(&self, stream: &mut S, host: &str, port: u16) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        tracing::trace!(proxy=%self.addr, %host, %port, "Connecting via proxy");
+
+        match self.protocol {
+            ProxyProtocol::Socks5 => self.socks5_connect(stream, host, port).await,
+            ProxyProtocol::HttpConnect => self.http_connect_request(stream, host, port).await,
+        }
+    }
+
+    async fn socks5_connect<S>(&self, stream: &mut S, host: &str, port: u16) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let method = match self.credentials {
+            Some(_) => SOCKS_USERNAME_PASSWORD,
+            None => SOCKS_NO_AUTHENTICATION,
+        };
+        stream.write_all(&[SOCKS_VERSION, 1, method]).await?;
+        let mut reply = [0; 2];
+        stream.read_exact(&mut reply).await?;
+        if reply[0] != SOCKS_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unexpected SOCKS version",
+            ));
+        }
+        match (reply[1], &self.credentials) {
+            (SOCKS_NO_AUTHENTICATION, _) => {}
+            (SOCKS_USERNAME_PASSWORD, Some((username, password))) => {
+                let username_len = u8::try_from(username.len()).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "SOCKS username too long")
+                })?;
+                let password_len = u8::try_from(password.len()).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "SOCKS password too long")
+                })?;
+                let mut request = vec![1, username_len];
+                request.extend_from_slice(username.as_bytes());
+                request.push(password_len);
+                request.extend_from_slice(password.as_bytes());
+                stream.write_all(&request).await?;
+
+                let mut reply = [0; 2];
+                stream.read_exact(&mut reply).await?;
+                if reply[1] != 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "SOCKS proxy rejected credentials",
+                    ));
+                }
+            }
+            (SOCKS_NO_ACCEPTABLE_METHOD, _) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "SOCKS proxy rejected authentication method",
+                ))
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unexpected SOCKS authentication method",
+                ))
+            }
+        }
+
+        let mut request = vec![SOCKS_VERSION, SOCKS_CONNECT, 0];
+        match host.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => {
+                request.push(SOCKS_ADDRESS_IPV4);
+                request.extend_from_slice(&ip.octets());
+            }
+            Ok(IpAddr::V6(ip)) => {
+                request.push(SOCKS_ADDRESS_IPV6);
+                request.extend_from_slice(&ip.octets());
+            }
+            Err(_) => {
+                let host_len = u8::try_from(host.len()).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "host name too long")
+                })?;
+                request.extend_from_slice(&[SOCKS_ADDRESS_DOMAIN, host_len]);
+                request.extend_from_slice(host.as_bytes());
+            }
+        }
+        request.extend_from_slice(&port.to_be_bytes());
+        stream.write_all(&request).await?;
+
+        let mut reply = [0; 4];
+        stream.read_exact(&mut reply).await?;
+        if reply[0] != SOCKS_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unexpected SOCKS version",
+            ));
+        }
+        if reply[1] != 0 {
+            return Err(socks5_reply_error(reply[1]));
+        }
+
+        // Skip the address the proxy bound to.
+        let address_len = match reply[3] {
+            SOCKS_ADDRESS_IPV4 => 4,
+            SOCKS_ADDRESS_IPV6 => 16,
+            SOCKS_ADDRESS_DOMAIN => {
+                let mut len = [0];
+                stream.read_exact(&mut len).await?;
+                usize::from(len[0])
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unexpected SOCKS address type",
+                ))
+            }
+        };
+        let mut bound = vec![0; address_len + 2];
+        stream.read_exact(&mut bound).await?;
+
+        Ok(())
+    }
+
+    async fn http_connect_request<S>(&self, stream: &mut S, host: &str, port: u16) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let authority = match host.parse::<IpAddr>() {
+            Ok(IpAddr::V6(ip)) => format!("[{ip}]:{port}"),
+            _ => format!("{host}:{port}"),
+        };
+        let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+        if let Some((username, password)) = &self.credentials {
+            let credentials =
+                base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+            request.push_str(&format!("Proxy-Authorization: Basic {credentials}\r\n"));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+        stream.flush().await?;
+
+        // Read the response byte by byte, as anything following it belongs to the tunnel.
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            if response.len() == MAX_HTTP_RESPONSE_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "HTTP proxy response too large",
+                ));
+            }
+            let mut byte = [0];
+            stream.read_exact(&mut byte).await?;
+            response.push(byte[0]);
+        }
+
+        let status = std::str::from_utf8(&response)
+            .ok()
+            .and_then(|response| response.split_whitespace().nth(1))
+            .and_then(|status| status.parse::<u16>().ok())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP proxy response")
+            })?;
+        match status {
+            200..=299 => Ok(()),
+            407 => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "HTTP proxy requires authentication",
+            )),
+            status => Err(io::Error::other(format!(
+                "HTTP proxy failed to connect with status {status}"
+            ))),
+        }
+    }
+}
+
+impl fmt::Debug for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Proxy")
+            .field("addr", &self.addr)
+            .field("protocol", &self.protocol)
+            .field(
+                "username",
+                &self.credentials.as_ref().map(|(username, _)| username),
+            )
+            .finish()
+    }
+}
+
+fn socks5_reply_error(code: u8) -> io::Error {
+    let (kind, message) = match code {
+        2 => (io::ErrorKind::PermissionDenied, "connection not allowed"),
+        3 => (io::ErrorKind::Other, "network unreachable"),
+        4 => (io::ErrorKind::Other, "host unreachable"),
+        5 => (io::ErrorKind::ConnectionRefused, "connection refused"),
+        6 => (io::ErrorKind::TimedOut, "TTL expired"),
+        _ => (io::ErrorKind::Other, "general SOCKS server failure"),
+    };
+
+    io::Error::new(kind, format!("SOCKS proxy failed to connect: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// A stream reading the replies of a proxy given upfront, recording what is written.
+    struct MockStream {
+        replies: Cursor<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl MockStream {
+        fn new(replies: &[u8]) -> Self {
+            Self {
+                replies: Cursor::new(replies.to_vec()),
+                written: Vec::new(),
+            }
+        }
+    }
+
+    impl AsyncRead for MockStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.replies).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for MockStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.written.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn proxy_addr() -> SocketAddr {
+        "127.0.0.1:1080".parse().unwrap()
+    }
+
+    #[test]
+    fn socks5_with_credentials() {
+        let proxy = Proxy::socks5(proxy_addr()).with_credentials("user", "pass");
+        let mut stream = MockStream::new(&[
+            SOCKS_VERSION,
+            SOCKS_USERNAME_PASSWORD,
+            1,
+            0,
+            SOCKS_VERSION,
+            0,
+            0,
+            SOCKS_ADDRESS_DOMAIN,
+            4,
+            b'h',
+            b'o',
+            b's',
+            b't',
+            0,
+            80,
+            b'!',
+        ]);
+
+        futures::executor::block_on(proxy.connect(&mut stream, "example.com", 443)).unwrap();
+
+        let mut expected = vec![SOCKS_VERSION, 1, SOCKS_USERNAME_PASSWORD];
+        expected.extend_from_slice(b"\x01\x04user\x04pass");
+        expected.extend_from_slice(&[SOCKS_VERSION, SOCKS_CONNECT, 0, SOCKS_ADDRESS_DOMAIN, 11]);
+        expected.extend_from_slice(b"example.com");
+        expected.extend_from_slice(&443u16.to_be_bytes());
+        assert_eq!(stream.written, expected);
+        // The data following the reply is left to the tunnel.
+        assert_eq!(stream.replies.position(), 15);
+    }
+
+    #[test]
+    fn socks5_reports_refused_connections() {
+        let proxy = Proxy::socks5(proxy_addr());
+        let mut stream = MockStream::new(&[
+            SOCKS_VERSION,
+            SOCKS_NO_AUTHENTICATION,
+            SOCKS_VERSION,
+            5,
+            0,
+            SOCKS_ADDRESS_IPV4,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ]);
+
+        let error =
+            futures::executor::block_on(proxy.connect(&mut stream, "::1", 4001)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+
+        let mut expected = vec![SOCKS_VERSION, 1, SOCKS_NO_AUTHENTICATION];
+        expected.extend_from_slice(&[SOCKS_VERSION, SOCKS_CONNECT, 0, SOCKS_ADDRESS_IPV6]);
+        expected.extend_from_slice(&"::1".parse::<std::net::Ipv6Addr>().unwrap().octets());
+        expected.extend_from_slice(&4001u16.to_be_bytes());
+        assert_eq!(stream.written, expected);
+    }
+
+    #[test]
+    fn http_connect_with_credentials() {
+        let proxy = Proxy::http_connect(proxy_addr()).with_credentials("user", "pass");
+        let mut stream =
+            MockStream::new(b"HTTP/1.1 200 Connection established\r\nVia: proxy\r\n\r\n!");
+
+        futures::executor::block_on(proxy.connect(&mut stream, "::1", 4001)).unwrap();
+
+        assert_eq!(
+            stream.written,
+            b"CONNECT [::1]:4001 HTTP/1.1\r\nHost: [::1]:4001\r\n\
+              Proxy-Authorization: Basic dXNlcjpwYXNz\r\n\r\n"
+        );
+        // The data following the response is left to the tunnel.
+        assert_eq!(stream.replies.position(), 51);
+    }
+
+    #[test]
+    fn http_connect_reports_missing_authentication() {
+        let proxy = Proxy::http_connect(proxy_addr());
+        let mut stream = MockStream::new(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n");
+
+        let error = futures::executor::block_on(proxy.connect(&mut stream, "example.com", 443))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
@@ -21,6 +21,10 @@
 //! X.509 certificate handling for libp2p
 //!
 //! This module handles generation, signing, and verification of certificates.
+//!
+//! The host key embedded in a certificate may be of any type supported by [`libp2p_identity`].
+//! Certificates of hosts with an RSA or ECDSA (P-256) key can only be verified with the `rsa` and
+//! `ecdsa` features enabled respectively.
 
 use libp2p_identity as identity;
 use libp2p_identity::PeerId;
@@ -44,6 +48,9 @@ static P2P_SIGNATURE_ALGORITHM: &rcgen::SignatureAlgorithm = &rcgen::PKCS_ECDSA_
 
 /// Generates a self-signed TLS certificate that includes a libp2p-specific
 /// certificate extension containing the public key of the given keypair.
+///
+/// The certificate itself is always signed with a freshly generated ECDSA P-256 key,
+/// independent of the type of the host key.
 pub fn generate(
     identity_keypair: &identity::Keypair,
 ) -> Result<
@@ -53,12 +60,54 @@ pub fn generate(
     ),
     GenError,
 > {
+    let certificate_keypair = generate_certificate_keypair()?;
+    let signature = identity_keypair
+        .sign(&extension_signing_message(&certificate_keypair))
+        .map_err(|_| rcgen::RcgenError::RingUnspecified)?;
+
+    build_certificate(certificate_keypair, identity_keypair.public(), signature)
+}
+
+/// Generates a self-signed TLS certificate like [`generate`], signing the libp2p-specific
+/// certificate extension with a [`Signer`](identity::Signer) instead of an in-memory keypair.
+pub async fn generate_with_signer(
+    signer: &(impl identity::Signer + ?Sized),
+) -> Result<
+    (
+        rustls::pki_types::CertificateDer<'static>,
+        rustls::pki_types::PrivateKeyDer<'static>,
+    ),
+    GenError,
+> {
+    let certificate_keypair = generate_certificate_keypair()?;
+    let signature = signer
+        .sign(&extension_signing_message(&certificate_keypair))
+        .await
+        .map_err(|_| rcgen::RcgenError::RingUnspecified)?;
+
+    build_certificate(certificate_keypair, signer.public(), signature)
+}
+
+fn generate_certificate_keypair() -> Result<rcgen::KeyPair, rcgen::RcgenError> {
     // Keypair used to sign the certificate.
     // SHOULD NOT be related to the host's key.
     // Endpoints MAY generate a new key and certificate
     // for every connection attempt, or they MAY reuse the same key
     // and certificate for multiple connections.
-    let certificate_keypair = rcgen::KeyPair::generate(P2P_SIGNATURE_ALGORITHM)?;
+    rcgen::KeyPair::generate(P2P_SIGNATURE_ALGORITHM)
+}
+
+fn build_certificate(
+    certificate_keypair: rcgen::KeyPair,
+    identity_public: identity::PublicKey,
+    signature: Vec<u8>,
+) -> Result<
+    (
+        rustls::pki_types::CertificateDer<'static>,
+        rustls::pki_types::PrivateKeyDer<'static>,
+    ),
+    GenError,
+> {
     let rustls_key = rustls::pki_types::PrivateKeyDer::from(
         rustls::pki_types::PrivatePkcs8KeyDer::from(certificate_keypair.serialize_der()),
     );
@@ -66,10 +115,9 @@ pub fn generate(
     let certificate = {
         let mut params = rcgen::CertificateParams::new(vec![]);
         params.distinguished_name = rcgen::DistinguishedName::new();
-        params.custom_extensions.push(make_libp2p_extension(
-            identity_keypair,
-            &certificate_keypair,
-        )?);
+        params
+            .custom_extensions
+            .push(make_libp2p_extension(&identity_public, signature));
         params.alg = P2P_SIGNATURE_ALGORITHM;
         params.key_pair = Some(certificate_keypair);
         rcgen::Certificate::from_params(params)?
@@ -200,23 +248,21 @@ fn parse_unverified(der_input: &[u8]) -> Result<P2pCertificate, webpki::Error> {
     Ok(certificate)
 }
 
-fn make_libp2p_extension(
-    identity_keypair: &identity::Keypair,
-    certificate_keypair: &rcgen::KeyPair,
-) -> Result<rcgen::CustomExtension, rcgen::RcgenError> {
+/// The message to sign with the private host key for the libp2p Public Key Extension.
+fn extension_signing_message(certificate_keypair: &rcgen::KeyPair) -> Vec<u8> {
     // The peer signs the concatenation of the string `libp2p-tls-handshake:`
     // and the public key that it used to generate the certificate carrying
     // the libp2p Public Key Extension, using its private host key.
-    let signature = {
-        let mut msg = vec![];
-        msg.extend(P2P_SIGNING_PREFIX);
-        msg.extend(certificate_keypair.public_key_der());
-
-        identity_keypair
-            .sign(&msg)
-            .map_err(|_| rcgen::RcgenError::RingUnspecified)?
-    };
+    let mut msg = vec![];
+    msg.extend(P2P_SIGNING_PREFIX);
+    msg.extend(certificate_keypair.public_key_der());
+    msg
+}
 
+fn make_libp2p_extension(
+    identity_public: &identity::PublicKey,
+    signature: Vec<u8>,
+) -> rcgen::CustomExtension {
     // The public host key and the signature are ANS.1-encoded
     // into the SignedKey data structure, which is carried
     // in the libp2p Public Key Extension.
@@ -225,7 +271,7 @@ fn make_libp2p_extension(
     //    signature OCTET STRING
     // }
     let extension_content = {
-        let serialized_pubkey = identity_keypair.public().encode_protobuf();
+        let serialized_pubkey = identity_public.encode_protobuf();
         yasna::encode_der(&(serialized_pubkey, signature))
     };
 
@@ -233,7 +279,7 @@ fn make_libp2p_extension(
     let mut ext = rcgen::CustomExtension::from_oid_content(&P2P_EXT_OID, extension_content);
     ext.set_criticality(true);
 
-    Ok(ext)
+    ext
 }
 
 impl P2pCertificate<'_> {
@@ -465,6 +511,29 @@ mod tests {
         assert_eq!(keypair.public(), parsed_cert.extension.public_key);
     }
 
+    #[test]
+    fn sanity_check_with_rsa_keypair() {
+        let mut pkcs8 = include_bytes!("./test_assets/rsa-2048.pk8").to_vec();
+        let keypair = identity::Keypair::rsa_from_pkcs8(&mut pkcs8).unwrap();
+
+        let (cert, _) = generate(&keypair).unwrap();
+        let parsed_cert = parse(&cert).unwrap();
+
+        assert_eq!(keypair.public(), parsed_cert.extension.public_key);
+        assert_eq!(keypair.public().to_peer_id(), parsed_cert.peer_id());
+    }
+
+    #[test]
+    fn sanity_check_with_ecdsa_keypair() {
+        let keypair = identity::Keypair::generate_ecdsa();
+
+        let (cert, _) = generate(&keypair).unwrap();
+        let parsed_cert = parse(&cert).unwrap();
+
+        assert_eq!(keypair.public(), parsed_cert.extension.public_key);
+        assert_eq!(keypair.public().to_peer_id(), parsed_cert.peer_id());
+    }
+
     macro_rules! check_cert {
         ($name:ident, $path:literal, $scheme:path) => {
             #[test]
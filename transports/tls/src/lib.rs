@@ -26,6 +26,7 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 pub mod certificate;
+mod resumption;
 mod upgrade;
 mod verifier;
 
@@ -40,12 +41,23 @@ pub use upgrade::UpgradeError;
 const P2P_ALPN: [u8; 6] = *b"libp2p";
 
 /// Create a TLS client configuration for libp2p.
+///
+/// TLS 1.3 sessions are cached for resumption by the peer ID of the server, if the peer ID is
+/// used as server name of the connection.
 pub fn make_client_config(
     keypair: &Keypair,
     remote_peer_id: Option<PeerId>,
 ) -> Result<rustls::ClientConfig, certificate::GenError> {
     let (certificate, private_key) = certificate::generate(keypair)?;
 
+    Ok(client_config(certificate, private_key, remote_peer_id))
+}
+
+fn client_config(
+    certificate: rustls::pki_types::CertificateDer<'static>,
+    private_key: rustls::pki_types::PrivateKeyDer<'static>,
+    remote_peer_id: Option<PeerId>,
+) -> rustls::ClientConfig {
     let mut provider = rustls::crypto::ring::default_provider();
     provider.cipher_suites = verifier::CIPHERSUITES.to_vec();
 
@@ -59,16 +71,29 @@ pub fn make_client_config(
         .with_client_auth_cert(vec![certificate], private_key)
         .expect("Client cert key DER is valid; qed");
     crypto.alpn_protocols = vec![P2P_ALPN.to_vec()];
+    crypto.resumption = rustls::client::Resumption::store(Arc::new(resumption::SessionCache::new(
+        resumption::DEFAULT_CAPACITY,
+    )));
 
-    Ok(crypto)
+    crypto
 }
 
 /// Create a TLS server configuration for libp2p.
+///
+/// Sessions are kept in a bounded in-memory cache, allowing clients to resume them via TLS 1.3
+/// session tickets.
 pub fn make_server_config(
     keypair: &Keypair,
 ) -> Result<rustls::ServerConfig, certificate::GenError> {
     let (certificate, private_key) = certificate::generate(keypair)?;
 
+    Ok(server_config(certificate, private_key))
+}
+
+fn server_config(
+    certificate: rustls::pki_types::CertificateDer<'static>,
+    private_key: rustls::pki_types::PrivateKeyDer<'static>,
+) -> rustls::ServerConfig {
     let mut provider = rustls::crypto::ring::default_provider();
     provider.cipher_suites = verifier::CIPHERSUITES.to_vec();
 
@@ -80,5 +105,5 @@ pub fn make_server_config(
         .expect("Server cert key DER is valid; qed");
     crypto.alpn_protocols = vec![P2P_ALPN.to_vec()];
 
-    Ok(crypto)
+    crypto
 }
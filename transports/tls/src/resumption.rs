@@ -0,0 +1,184 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+
+//! TLS 1.3 session resumption.
+//!
+//! Tickets are cached by the peer ID of the server, which the client announces as server name.
+//! Sessions to server names that are not a peer ID are never cached, so that a ticket of one peer
+//! is never offered to another one.
+
+use crate::verifier::peer_id_from_server_name as peer_id;
+use libp2p_identity::PeerId;
+use rustls::{
+    client::{ClientSessionStore, Tls12ClientSessionValue, Tls13ClientSessionValue},
+    pki_types::ServerName,
+    NamedGroup,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// The maximum number of peers whose sessions are cached.
+pub(crate) const DEFAULT_CAPACITY: usize = 256;
+
+/// The maximum number of tickets cached per peer.
+///
+/// Each ticket is used at most once.
+const MAX_TICKETS_PER_PEER: usize = 4;
+
+/// A bounded in-memory cache of TLS 1.3 session tickets, keyed by peer ID.
+#[derive(Debug)]
+pub(crate) struct SessionCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    peers: HashMap<PeerId, Entry>,
+    /// The cached peers in order of insertion, to evict the oldest one once full.
+    order: VecDeque<PeerId>,
+    capacity: usize,
+}
+
+#[derive(Debug, Default)]
+struct Entry {
+    kx_hint: Option<NamedGroup>,
+    tickets: VecDeque<Tls13ClientSessionValue>,
+}
+
+impl SessionCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                peers: HashMap::new(),
+                order: VecDeque::new(),
+                capacity,
+            }),
+        }
+    }
+
+    fn with_entry(&self, server_name: &ServerName<'_>, f: impl FnOnce(&mut Entry)) {
+        let Some(peer) = peer_id(server_name) else {
+            return;
+        };
+        let mut inner = self.inner.lock().unwrap();
+        if inner.capacity == 0 {
+            return;
+        }
+
+        if !inner.peers.contains_key(&peer) {
+            if inner.peers.len() == inner.capacity {
+                if let Some(oldest) = inner.order.pop_front() {
+                    inner.peers.remove(&oldest);
+                }
+            }
+            inner.order.push_back(peer);
+        }
+
+        f(inner.peers.entry(peer).or_default());
+    }
+
+    fn get<T>(
+        &self,
+        server_name: &ServerName<'_>,
+        f: impl FnOnce(&mut Entry) -> Option<T>,
+    ) -> Option<T> {
+        let peer = peer_id(server_name)?;
+
+        self.inner.lock().unwrap().peers.get_mut(&peer).and_then(f)
+    }
+}
+
+impl ClientSessionStore for SessionCache {
+    fn set_kx_hint(&self, server_name: ServerName<'static>, group: NamedGroup) {
+        self.with_entry(&server_name, |entry| entry.kx_hint = Some(group));
+    }
+
+    fn kx_hint(&self, server_name: &ServerName<'_>) -> Option<NamedGroup> {
+        self.get(server_name, |entry| entry.kx_hint)
+    }
+
+    // TLS 1.2 is never negotiated, see `verifier::PROTOCOL_VERSIONS`.
+
+    fn set_tls12_session(&self, _: ServerName<'static>, _: Tls12ClientSessionValue) {}
+
+    fn tls12_session(&self, _: &ServerName<'_>) -> Option<Tls12ClientSessionValue> {
+        None
+    }
+
+    fn remove_tls12_session(&self, _: &ServerName<'static>) {}
+
+    fn insert_tls13_ticket(
+        &self,
+        server_name: ServerName<'static>,
+        value: Tls13ClientSessionValue,
+    ) {
+        self.with_entry(&server_name, |entry| {
+            if entry.tickets.len() == MAX_TICKETS_PER_PEER {
+                entry.tickets.pop_front();
+            }
+            entry.tickets.push_back(value);
+        });
+    }
+
+    fn take_tls13_ticket(
+        &self,
+        server_name: &ServerName<'static>,
+    ) -> Option<Tls13ClientSessionValue> {
+        // Use the most recent ticket first, it is the least likely to have expired.
+        self.get(server_name, |entry| entry.tickets.pop_back())
+    }
+}
+
+/// The server name that announces the given peer ID.
+pub(crate) fn server_name(peer_id: &PeerId) -> ServerName<'static> {
+    ServerName::try_from(peer_id.to_string()).expect("base58 peer ID to be a valid DNS name")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_server_names_that_are_not_peer_ids() {
+        let cache = SessionCache::new(DEFAULT_CAPACITY);
+        let name = ServerName::try_from("example.com").unwrap();
+
+        cache.set_kx_hint(name.clone(), NamedGroup::X25519);
+
+        assert_eq!(cache.kx_hint(&name), None);
+    }
+
+    #[test]
+    fn evicts_oldest_peer() {
+        let cache = SessionCache::new(2);
+        let names = (0..3)
+            .map(|_| server_name(&PeerId::random()))
+            .collect::<Vec<_>>();
+
+        for name in &names {
+            cache.set_kx_hint(name.clone(), NamedGroup::X25519);
+        }
+
+        assert_eq!(cache.kx_hint(&names[0]), None);
+        assert_eq!(cache.kx_hint(&names[1]), Some(NamedGroup::X25519));
+        assert_eq!(cache.kx_hint(&names[2]), Some(NamedGroup::X25519));
+    }
+}
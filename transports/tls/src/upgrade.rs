@@ -49,6 +49,7 @@ pub enum UpgradeError {
 pub struct Config {
     server: rustls::ServerConfig,
     client: rustls::ClientConfig,
+    remote_peer_id: Option<PeerId>,
 }
 
 impl Config {
@@ -56,8 +57,53 @@ impl Config {
         Ok(Self {
             server: crate::make_server_config(identity)?,
             client: crate::make_client_config(identity, None)?,
+            remote_peer_id: None,
         })
     }
+
+    /// Creates a configuration with the identity keypair accessed through a
+    /// [`Signer`](identity::Signer).
+    ///
+    /// This allows the private key to be held outside of memory, e.g. in a hardware security
+    /// module. The signer is only used once, to sign the certificate used for all connections.
+    pub async fn with_signer(
+        signer: &(impl identity::Signer + ?Sized),
+    ) -> Result<Self, certificate::GenError> {
+        let (certificate, private_key) = certificate::generate_with_signer(signer).await?;
+
+        Ok(Self {
+            server: crate::server_config(certificate.clone(), private_key.clone_key()),
+            client: crate::client_config(certificate, private_key, None),
+            remote_peer_id: None,
+        })
+    }
+
+    /// Set the peer ID of the remote when dialing.
+    ///
+    /// The handshake fails if the remote presents a different identity. Furthermore the peer ID is
+    /// sent as TLS server name, which allows resuming a previous session with that peer instead
+    /// of performing a full handshake. Clones of a [`Config`] share the session cache, so a
+    /// configuration for a specific remote is typically derived from a common one via
+    /// `config.clone().with_remote_peer_id(peer_id)`.
+    ///
+    /// Note that this deviates from the spec, which forbids a server name, and reveals the
+    /// identity of the dialed peer to on-path observers.
+    pub fn with_remote_peer_id(mut self, peer_id: PeerId) -> Self {
+        self.remote_peer_id = Some(peer_id);
+        self
+    }
+
+    /// Disable TLS 1.3 session resumption (it is enabled by default).
+    ///
+    /// Resumed sessions derive their keys partially from the previous session. Disabling
+    /// resumption enforces a full handshake on every connection, for deployments that want
+    /// every connection to be independent.
+    pub fn without_session_resumption(mut self) -> Self {
+        self.client.resumption = rustls::client::Resumption::disabled();
+        self.server.session_storage = Arc::new(rustls::server::NoServerSessionStorage {});
+        self.server.send_tls13_tickets = 0;
+        self
+    }
 }
 
 impl UpgradeInfo for Config {
@@ -104,9 +150,14 @@ where
         async move {
             // Spec: In order to keep this flexibility for future versions, clients that only support the version of the handshake defined in this document MUST NOT send any value in the Server Name Indication.
             // Setting `ServerName` to unspecified will disable the use of the SNI extension.
-            let name = ServerName::IpAddress(rustls::pki_types::IpAddr::from(IpAddr::V4(
-                Ipv4Addr::UNSPECIFIED,
-            )));
+            // A known remote is deliberately announced to bind sessions for resumption to it,
+            // see `Config::with_remote_peer_id`.
+            let name = match self.remote_peer_id {
+                Some(peer_id) => crate::resumption::server_name(&peer_id),
+                None => ServerName::IpAddress(rustls::pki_types::IpAddr::from(IpAddr::V4(
+                    Ipv4Addr::UNSPECIFIED,
+                ))),
+            };
 
             let stream = futures_rustls::TlsConnector::from(Arc::new(self.client))
                 .connect(name, socket)
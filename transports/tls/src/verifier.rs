@@ -30,7 +30,7 @@ use rustls::{
     crypto::ring::cipher_suite::{
         TLS13_AES_128_GCM_SHA256, TLS13_AES_256_GCM_SHA384, TLS13_CHACHA20_POLY1305_SHA256,
     },
-    pki_types::CertificateDer,
+    pki_types::{CertificateDer, ServerName},
     server::danger::{ClientCertVerified, ClientCertVerifier},
     CertificateError, DigitallySignedStruct, DistinguishedName, OtherError, SignatureScheme,
     SupportedCipherSuite, SupportedProtocolVersion,
@@ -107,13 +107,19 @@ impl ServerCertVerifier for Libp2pCertificateVerifier {
         &self,
         end_entity: &CertificateDer,
         intermediates: &[CertificateDer],
-        _server_name: &rustls::pki_types::ServerName,
+        server_name: &ServerName,
         _ocsp_response: &[u8],
         _now: rustls::pki_types::UnixTime,
     ) -> Result<ServerCertVerified, rustls::Error> {
         let peer_id = verify_presented_certs(end_entity, intermediates)?;
 
-        if let Some(remote_peer_id) = self.remote_peer_id {
+        // A server name that is a peer ID is treated like an intended remote peer ID. This binds
+        // sessions cached by the server name, e.g. for resumption, to the verified peer.
+        let remote_peer_id = self
+            .remote_peer_id
+            .or_else(|| peer_id_from_server_name(server_name));
+
+        if let Some(remote_peer_id) = remote_peer_id {
             // The public host key allows the peer to calculate the peer ID of the peer
             // it is connecting to. Clients MUST verify that the peer ID derived from
             // the certificate matches the peer ID they intended to connect to,
@@ -257,3 +263,10 @@ impl From<certificate::VerificationError> for rustls::Error {
         }
     }
 }
+
+pub(crate) fn peer_id_from_server_name(server_name: &ServerName) -> Option<PeerId> {
+    match server_name {
+        ServerName::DnsName(name) => name.as_ref().parse().ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,89 @@
+use futures::{AsyncReadExt, AsyncWriteExt};
+use libp2p_core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade};
+use libp2p_identity::{Keypair, PeerId};
+use libp2p_tls as tls;
+use rustls::HandshakeKind;
+
+#[tokio::test]
+async fn resumes_session_with_known_peer() {
+    let server_id = Keypair::generate_ed25519();
+    let server = tls::Config::new(&server_id).unwrap();
+    let client = tls::Config::new(&Keypair::generate_ed25519())
+        .unwrap()
+        .with_remote_peer_id(server_id.public().to_peer_id());
+
+    let first = handshake(&server, &client).await.unwrap();
+    let second = handshake(&server, &client).await.unwrap();
+
+    assert_eq!(
+        first,
+        (HandshakeKind::Full, server_id.public().to_peer_id())
+    );
+    assert_eq!(
+        second,
+        (HandshakeKind::Resumed, server_id.public().to_peer_id())
+    );
+}
+
+#[tokio::test]
+async fn does_not_resume_with_unknown_peer() {
+    let server = tls::Config::new(&Keypair::generate_ed25519()).unwrap();
+    let client = tls::Config::new(&Keypair::generate_ed25519()).unwrap();
+
+    handshake(&server, &client).await.unwrap();
+    let (kind, _) = handshake(&server, &client).await.unwrap();
+
+    assert_eq!(kind, HandshakeKind::Full);
+}
+
+#[tokio::test]
+async fn does_not_resume_if_disabled() {
+    let server_id = Keypair::generate_ed25519();
+    let server = tls::Config::new(&server_id)
+        .unwrap()
+        .without_session_resumption();
+    let client = tls::Config::new(&Keypair::generate_ed25519())
+        .unwrap()
+        .with_remote_peer_id(server_id.public().to_peer_id())
+        .without_session_resumption();
+
+    handshake(&server, &client).await.unwrap();
+    let (kind, _) = handshake(&server, &client).await.unwrap();
+
+    assert_eq!(kind, HandshakeKind::Full);
+}
+
+#[tokio::test]
+async fn rejects_unexpected_peer() {
+    let server = tls::Config::new(&Keypair::generate_ed25519()).unwrap();
+    let client = tls::Config::new(&Keypair::generate_ed25519())
+        .unwrap()
+        .with_remote_peer_id(PeerId::random());
+
+    assert!(handshake(&server, &client).await.is_err());
+}
+
+/// Performs a handshake and returns its kind and the peer ID of the server as seen by the client.
+async fn handshake(
+    server: &tls::Config,
+    client: &tls::Config,
+) -> Result<(HandshakeKind, PeerId), tls::UpgradeError> {
+    let (client_io, server_io) = futures_ringbuf::Endpoint::pair(4096, 4096);
+
+    let ((_, mut server_stream), (server_peer_id, mut client_stream)) = futures::future::try_join(
+        server.clone().upgrade_inbound(server_io, ""),
+        client.clone().upgrade_outbound(client_io, ""),
+    )
+    .await?;
+
+    // Session tickets are sent after the handshake, hence the client has to read from the
+    // connection in order to receive them.
+    server_stream.write_all(b"x").await.unwrap();
+    server_stream.flush().await.unwrap();
+    let mut buf = [0; 1];
+    client_stream.read_exact(&mut buf).await.unwrap();
+
+    let kind = client_stream.get_ref().1.handshake_kind().unwrap();
+
+    Ok((kind, server_peer_id))
+}
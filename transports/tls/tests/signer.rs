@@ -0,0 +1,44 @@
+use libp2p_core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade};
+use libp2p_identity::{Keypair, PublicKey, SignFuture, Signer};
+use libp2p_tls as tls;
+
+/// A [`Signer`] standing in for a key held outside of memory, e.g. in a HSM.
+struct ExternalSigner(Keypair);
+
+impl Signer for ExternalSigner {
+    fn public(&self) -> PublicKey {
+        self.0.public()
+    }
+
+    fn sign(&self, msg: &[u8]) -> SignFuture {
+        let signature = self.0.sign(msg);
+        Box::pin(async move { signature })
+    }
+}
+
+#[tokio::test]
+async fn handshake_with_external_signer() {
+    let server_id = Keypair::generate_ed25519();
+    let client_id = Keypair::generate_ecdsa();
+    let server_peer_id = server_id.public().to_peer_id();
+    let client_peer_id = client_id.public().to_peer_id();
+
+    let server = tls::Config::with_signer(&ExternalSigner(server_id))
+        .await
+        .unwrap();
+    let client = tls::Config::with_signer(&ExternalSigner(client_id))
+        .await
+        .unwrap()
+        .with_remote_peer_id(server_peer_id);
+
+    let (client_io, server_io) = futures_ringbuf::Endpoint::pair(4096, 4096);
+    let ((remote_client, _), (remote_server, _)) = futures::future::try_join(
+        server.upgrade_inbound(server_io, ""),
+        client.upgrade_outbound(client_io, ""),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(remote_client, client_peer_id);
+    assert_eq!(remote_server, server_peer_id);
+}
@@ -8,8 +8,27 @@ use std::time::Duration;
 
 #[tokio::test]
 async fn can_establish_connection() {
-    let mut swarm1 = make_swarm();
-    let mut swarm2 = make_swarm();
+    establish_connection(
+        libp2p_identity::Keypair::generate_ed25519(),
+        libp2p_identity::Keypair::generate_ed25519(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn can_establish_connection_with_rsa_and_ecdsa_host_keys() {
+    let mut pkcs8 = include_bytes!("../src/test_assets/rsa-2048.pk8").to_vec();
+    let rsa = libp2p_identity::Keypair::rsa_from_pkcs8(&mut pkcs8).unwrap();
+
+    establish_connection(rsa, libp2p_identity::Keypair::generate_ecdsa()).await;
+}
+
+async fn establish_connection(
+    identity1: libp2p_identity::Keypair,
+    identity2: libp2p_identity::Keypair,
+) {
+    let mut swarm1 = make_swarm(identity1);
+    let mut swarm2 = make_swarm(identity2);
 
     let listen_address = {
         let expected_listener_id = swarm1.listen_on(Protocol::Memory(0).into()).unwrap();
@@ -56,9 +75,7 @@ async fn can_establish_connection() {
     assert_eq!(&outbound_peer_id, swarm1.local_peer_id());
 }
 
-fn make_swarm() -> Swarm<dummy::Behaviour> {
-    let identity = libp2p_identity::Keypair::generate_ed25519();
-
+fn make_swarm(identity: libp2p_identity::Keypair) -> Swarm<dummy::Behaviour> {
     let transport = MemoryTransport::default()
         .upgrade(Version::V1)
         .authenticate(libp2p_tls::Config::new(&identity).unwrap())
@@ -0,0 +1,216 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A minimal client of the Tor control protocol, creating onion services via `ADD_ONION`.
+
+use std::fmt::Write as _;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// How to authenticate to the Tor control port.
+#[derive(Debug, Clone, Default)]
+pub enum ControlAuth {
+    /// No authentication, i.e. the control port is configured without
+    /// `HashedControlPassword` and `CookieAuthentication`.
+    #[default]
+    None,
+    /// The password configured via `HashedControlPassword`.
+    Password(String),
+    /// The cookie file written by Tor with `CookieAuthentication` enabled.
+    Cookie(PathBuf),
+}
+
+/// A connection to the Tor control port.
+///
+/// Onion services created on the connection are removed by Tor once the connection closes.
+pub(crate) struct Control<S> {
+    stream: BufReader<S>,
+}
+
+impl<S> Control<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Authenticates on the control connection `stream`.
+    pub(crate) async fn authenticate(stream: S, auth: &ControlAuth) -> io::Result<Self> {
+        let mut control = Control {
+            stream: BufReader::new(stream),
+        };
+
+        let command = match auth {
+            ControlAuth::None => "AUTHENTICATE".to_owned(),
+            ControlAuth::Password(password) => {
+                let escaped = password.replace('\\', "\\\\").replace('"', "\\\"");
+                format!("AUTHENTICATE \"{escaped}\"")
+            }
+            ControlAuth::Cookie(path) => {
+                let cookie = std::fs::read(path)?;
+                cookie.iter().fold("AUTHENTICATE ".to_owned(), |mut c, b| {
+                    let _ = write!(c, "{b:02X}");
+                    c
+                })
+            }
+        };
+        control.command(&command).await?;
+
+        Ok(control)
+    }
+
+    /// Creates an onion service forwarding `virtual_port` to `target`, returning its service ID.
+    ///
+    /// Creates a service with a new key unless a `key` of the form `ED25519-V3:<base64>` is given.
+    pub(crate) async fn add_onion(
+        &mut self,
+        key: Option<&str>,
+        virtual_port: u16,
+        target: SocketAddr,
+    ) -> io::Result<String> {
+        let command = match key {
+            Some(key) => format!("ADD_ONION {key} Port={virtual_port},{target}"),
+            None => {
+                format!("ADD_ONION NEW:ED25519-V3 Flags=DiscardPK Port={virtual_port},{target}")
+            }
+        };
+
+        self.command(&command)
+            .await?
+            .into_iter()
+            .find_map(|line| line.strip_prefix("ServiceID=").map(ToOwned::to_owned))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "ADD_ONION reply without service ID",
+                )
+            })
+    }
+
+    /// Sends a command, returning the lines of a successful reply without their status code.
+    async fn command(&mut self, command: &str) -> io::Result<Vec<String>> {
+        let stream = self.stream.get_mut();
+        stream.write_all(command.as_bytes()).await?;
+        stream.write_all(b"\r\n").await?;
+        stream.flush().await?;
+
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let line = line.trim_end();
+            let (Some(status), Some(separator)) = (line.get(..3), line.chars().nth(3)) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed control reply: {line}"),
+                ));
+            };
+            if status != "250" {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("control command failed: {line}"),
+                ));
+            }
+            lines.push(line[4..].to_owned());
+
+            match separator {
+                ' ' => return Ok(lines),
+                '-' => {}
+                // Skip the data of a multi-line reply, terminated by a single dot.
+                '+' => loop {
+                    let mut data = String::new();
+                    if self.stream.read_line(&mut data).await? == 0 {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+                    if data.trim_end() == "." {
+                        break;
+                    }
+                },
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("malformed control reply: {line}"),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn adds_onion_service() {
+        let (client, tor) = tokio::io::duplex(1024);
+
+        tokio::spawn(async move {
+            let mut tor = BufReader::new(tor);
+            let mut line = String::new();
+            tor.read_line(&mut line).await.unwrap();
+            assert_eq!(line, "AUTHENTICATE \"pass\\\"word\"\r\n");
+            tor.get_mut().write_all(b"250 OK\r\n").await.unwrap();
+
+            line.clear();
+            tor.read_line(&mut line).await.unwrap();
+            assert_eq!(
+                line,
+                "ADD_ONION NEW:ED25519-V3 Flags=DiscardPK Port=80,127.0.0.1:4001\r\n"
+            );
+            tor.get_mut()
+                .write_all(b"250-ServiceID=abcdef\r\n250 OK\r\n")
+                .await
+                .unwrap();
+        });
+
+        let mut control =
+            Control::authenticate(client, &ControlAuth::Password("pass\"word".into()))
+                .await
+                .unwrap();
+        let service_id = control
+            .add_onion(None, 80, "127.0.0.1:4001".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(service_id, "abcdef");
+    }
+
+    #[tokio::test]
+    async fn reports_failed_authentication() {
+        let (client, tor) = tokio::io::duplex(1024);
+
+        tokio::spawn(async move {
+            let mut tor = BufReader::new(tor);
+            let mut line = String::new();
+            tor.read_line(&mut line).await.unwrap();
+            tor.get_mut()
+                .write_all(b"515 Authentication failed\r\n")
+                .await
+                .unwrap();
+        });
+
+        let error = Control::authenticate(client, &ControlAuth::None)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
@@ -0,0 +1,369 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Implementation of the libp2p `Transport` trait for the Tor network, on top of a local Tor
+//! daemon.
+//!
+//! # Usage
+//!
+//! The [`Transport`] dials addresses of the form `/onion3/<address>:<port>` through the SOCKS
+//! proxy of the Tor daemon, `127.0.0.1:9050` by default.
+//!
+//! With [`Config::with_hidden_services`], the [`Transport`] also listens on loopback TCP
+//! addresses, e.g. `/ip4/127.0.0.1/tcp/0`, and publishes an onion service forwarding to each
+//! listener via the control port of the Tor daemon. The address of the onion service is
+//! reported as listen address, while the loopback address itself is not. The onion service is
+//! removed once the listener is closed. When combined with the TCP transport, put this transport
+//! first, such that loopback addresses are listened on through Tor.
+//!
+//! The [`Transport`] requires a `tokio` runtime.
+//!
+//! ```
+//! use libp2p_tor::{Config, ControlAuth, Transport};
+//!
+//! let transport = Transport::new(
+//!     Config::default()
+//!         .with_hidden_services("127.0.0.1:9051".parse().unwrap(), ControlAuth::None),
+//! );
+//! ```
+
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+mod control;
+mod socks;
+
+pub use control::ControlAuth;
+
+use control::Control;
+use data_encoding::BASE32;
+use futures::future::{BoxFuture, Ready};
+use futures::prelude::*;
+use futures::stream::BoxStream;
+use libp2p_core::multiaddr::{Multiaddr, Protocol};
+use libp2p_core::transport::{ListenerId, TransportError, TransportEvent};
+use libp2p_tcp::tokio::TcpStream;
+use std::collections::VecDeque;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// The configuration of a Tor [`Transport`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    socks_proxy: SocketAddr,
+    control_port: Option<(SocketAddr, ControlAuth)>,
+    virtual_port: Option<u16>,
+    service_key: Option<String>,
+}
+
+impl Config {
+    /// Creates a configuration dialing through the SOCKS proxy at `socks_proxy`.
+    pub fn new(socks_proxy: SocketAddr) -> Self {
+        Self {
+            socks_proxy,
+            control_port: None,
+            virtual_port: None,
+            service_key: None,
+        }
+    }
+
+    /// Enables listening via onion services, published via the control port at `control_port`.
+    pub fn with_hidden_services(mut self, control_port: SocketAddr, auth: ControlAuth) -> Self {
+        self.control_port = Some((control_port, auth));
+        self
+    }
+
+    /// Sets the port of the onion services, which defaults to the port of the listener.
+    pub fn with_virtual_port(mut self, port: u16) -> Self {
+        self.virtual_port = Some(port);
+        self
+    }
+
+    /// Sets the key of the onion services, of the form `ED25519-V3:<base64>` as returned by
+    /// the `ADD_ONION` command of the control protocol, to listen on a stable onion address.
+    ///
+    /// Without a key, every listener is published with a new onion address.
+    pub fn with_service_key(mut self, key: impl Into<String>) -> Self {
+        self.service_key = Some(key.into());
+        self
+    }
+}
+
+impl Default for Config {
+    /// Dials through the SOCKS proxy at `127.0.0.1:9050`, without listening.
+    fn default() -> Self {
+        Self::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9050))
+    }
+}
+
+type Listener = BoxStream<
+    'static,
+    Result<TransportEvent<Ready<io::Result<TcpStream>>, io::Error>, Result<(), io::Error>>,
+>;
+
+/// A Tor transport, see the [crate-level documentation](crate).
+pub struct Transport {
+    config: Config,
+    listeners: VecDeque<(ListenerId, Listener)>,
+}
+
+impl Transport {
+    /// Creates a new Tor transport with the given configuration.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            listeners: VecDeque::new(),
+        }
+    }
+}
+
+impl libp2p_core::Transport for Transport {
+    type Output = TcpStream;
+    type Error = io::Error;
+    type ListenerUpgrade = Ready<Result<Self::Output, Self::Error>>;
+    type Dial = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn listen_on(
+        &mut self,
+        id: ListenerId,
+        addr: Multiaddr,
+    ) -> Result<(), TransportError<Self::Error>> {
+        let (Some((control_port, auth)), Some(socket_addr)) = (
+            self.config.control_port.clone(),
+            multiaddr_to_loopback(&addr),
+        ) else {
+            return Err(TransportError::MultiaddrNotSupported(addr));
+        };
+        let virtual_port = self.config.virtual_port;
+        let service_key = self.config.service_key.clone();
+
+        let listener = async move {
+            let listener = tokio::net::TcpListener::bind(socket_addr).await?;
+            let local_addr = listener.local_addr()?;
+            let stream = tokio::net::TcpStream::connect(control_port).await?;
+            let mut control = Control::authenticate(stream, &auth).await?;
+            let virtual_port = virtual_port.unwrap_or(local_addr.port());
+            let service_id = control
+                .add_onion(service_key.as_deref(), virtual_port, local_addr)
+                .await?;
+            let onion_addr = service_id_to_multiaddr(&service_id, virtual_port)?;
+
+            Ok((listener, control, onion_addr))
+        }
+        .map_err(Err)
+        .map_ok(move |(listener, control, onion_addr)| {
+            tracing::debug!(address=%onion_addr, "Now listening on onion service");
+            stream::once(future::ok(TransportEvent::NewAddress {
+                listener_id: id,
+                listen_addr: onion_addr.clone(),
+            }))
+            // The onion service is removed once the control connection is dropped.
+            .chain(stream::unfold(
+                (listener, control, onion_addr),
+                move |(listener, control, onion_addr)| async move {
+                    let event = match listener.accept().await {
+                        Ok((stream, remote)) => {
+                            tracing::debug!(address=%onion_addr, "Incoming connection via onion service");
+                            TransportEvent::Incoming {
+                                upgrade: future::ok(TcpStream(stream)),
+                                local_addr: onion_addr.clone(),
+                                send_back_addr: socket_addr_to_multiaddr(remote),
+                                listener_id: id,
+                            }
+                        }
+                        Err(error) => TransportEvent::ListenerError {
+                            listener_id: id,
+                            error,
+                        },
+                    };
+                    Some((Ok(event), (listener, control, onion_addr)))
+                },
+            ))
+        })
+        .try_flatten_stream()
+        .boxed();
+        self.listeners.push_back((id, listener));
+
+        Ok(())
+    }
+
+    fn remove_listener(&mut self, id: ListenerId) -> bool {
+        let Some((_, listener)) = self
+            .listeners
+            .iter_mut()
+            .find(|(listener_id, _)| listener_id == &id)
+        else {
+            return false;
+        };
+        *listener = stream::once(async { Err(Ok(())) }).boxed();
+
+        true
+    }
+
+    fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let Some((host, port)) = multiaddr_to_onion(&addr) else {
+            return Err(TransportError::MultiaddrNotSupported(addr));
+        };
+        let socks_proxy = self.config.socks_proxy;
+
+        tracing::debug!(address=%addr, "Dialing address via Tor");
+        Ok(async move {
+            let mut stream = tokio::net::TcpStream::connect(socks_proxy).await?;
+            socks::connect(&mut stream, &host, port).await?;
+            Ok(TcpStream(stream))
+        }
+        .boxed())
+    }
+
+    fn dial_as_listener(
+        &mut self,
+        addr: Multiaddr,
+    ) -> Result<Self::Dial, TransportError<Self::Error>> {
+        self.dial(addr)
+    }
+
+    fn address_translation(&self, _server: &Multiaddr, _observed: &Multiaddr) -> Option<Multiaddr> {
+        None
+    }
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+        let mut remaining = self.listeners.len();
+        while let Some((id, mut listener)) = self.listeners.pop_back() {
+            let event = match Stream::poll_next(Pin::new(&mut listener), cx) {
+                Poll::Pending => None,
+                Poll::Ready(None) => panic!("Alive listeners always have a sender."),
+                Poll::Ready(Some(Ok(event))) => Some(event),
+                Poll::Ready(Some(Err(reason))) => {
+                    return Poll::Ready(TransportEvent::ListenerClosed {
+                        listener_id: id,
+                        reason,
+                    })
+                }
+            };
+            self.listeners.push_front((id, listener));
+            if let Some(event) = event {
+                return Poll::Ready(event);
+            }
+            remaining -= 1;
+            if remaining == 0 {
+                break;
+            }
+        }
+        Poll::Pending
+    }
+}
+
+/// Turns an address of the form `/onion3/<address>:<port>` into the host name and port to dial.
+fn multiaddr_to_onion(addr: &Multiaddr) -> Option<(String, u16)> {
+    let mut protocols = addr.iter();
+    let Some(Protocol::Onion3(onion)) = protocols.next() else {
+        return None;
+    };
+    match protocols.next() {
+        None | Some(Protocol::P2p(_)) => {}
+        Some(_) => return None,
+    }
+
+    let host = format!("{}.onion", BASE32.encode(onion.hash()).to_lowercase());
+    Some((host, onion.port()))
+}
+
+/// Turns an address of the form `/ip4/127.0.0.1/tcp/<port>` into a loopback socket address.
+fn multiaddr_to_loopback(addr: &Multiaddr) -> Option<SocketAddr> {
+    let mut protocols = addr.iter();
+    let ip = match protocols.next()? {
+        Protocol::Ip4(ip) => IpAddr::V4(ip),
+        Protocol::Ip6(ip) => IpAddr::V6(ip),
+        _ => return None,
+    };
+    let Some(Protocol::Tcp(port)) = protocols.next() else {
+        return None;
+    };
+    if protocols.next().is_some() || !ip.is_loopback() {
+        return None;
+    }
+
+    Some(SocketAddr::new(ip, port))
+}
+
+fn socket_addr_to_multiaddr(addr: SocketAddr) -> Multiaddr {
+    Multiaddr::empty()
+        .with(addr.ip().into())
+        .with(Protocol::Tcp(addr.port()))
+}
+
+/// Turns the service ID returned by the control port into the onion address of the service.
+fn service_id_to_multiaddr(service_id: &str, port: u16) -> io::Result<Multiaddr> {
+    let hash: [u8; 35] = BASE32
+        .decode(service_id.to_uppercase().as_bytes())
+        .ok()
+        .and_then(|hash| hash.try_into().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid onion service ID: {service_id}"),
+            )
+        })?;
+
+    Ok(Protocol::Onion3((hash, port).into()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVICE_ID: &str = "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd";
+
+    #[test]
+    fn onion_addresses_roundtrip() {
+        let addr = service_id_to_multiaddr(SERVICE_ID, 4001).unwrap();
+        assert_eq!(addr, format!("/onion3/{SERVICE_ID}:4001").parse().unwrap());
+
+        let (host, port) = multiaddr_to_onion(&addr).unwrap();
+        assert_eq!(host, format!("{SERVICE_ID}.onion"));
+        assert_eq!(port, 4001);
+
+        assert!(service_id_to_multiaddr("abc", 4001).is_err());
+    }
+
+    #[test]
+    fn only_listens_on_loopback() {
+        assert_eq!(
+            multiaddr_to_loopback(&"/ip4/127.0.0.1/tcp/4001".parse().unwrap()),
+            Some("127.0.0.1:4001".parse().unwrap())
+        );
+        assert_eq!(
+            multiaddr_to_loopback(&"/ip6/::1/tcp/0".parse().unwrap()),
+            Some("[::1]:0".parse().unwrap())
+        );
+        assert_eq!(
+            multiaddr_to_loopback(&"/ip4/0.0.0.0/tcp/4001".parse().unwrap()),
+            None
+        );
+        assert_eq!(
+            multiaddr_to_loopback(&"/ip4/127.0.0.1/udp/4001/quic-v1".parse().unwrap()),
+            None
+        );
+    }
+}
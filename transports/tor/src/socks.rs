@@ -0,0 +1,160 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! The client side of the `CONNECT` command of SOCKS5, as of RFC 1928.
+
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const VERSION: u8 = 5;
+const NO_AUTHENTICATION: u8 = 0;
+const CONNECT: u8 = 1;
+const ADDRESS_IPV4: u8 = 1;
+const ADDRESS_DOMAIN: u8 = 3;
+const ADDRESS_IPV6: u8 = 4;
+
+/// Requests the proxy on the other end of `stream` to connect to `host` and `port`.
+///
+/// Once this returns, `stream` is connected to the target.
+pub(crate) async fn connect<S>(stream: &mut S, host: &str, port: u16) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let host_len = u8::try_from(host.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "host name too long"))?;
+
+    stream.write_all(&[VERSION, 1, NO_AUTHENTICATION]).await?;
+    let mut reply = [0; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply != [VERSION, NO_AUTHENTICATION] {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "SOCKS proxy requires authentication",
+        ));
+    }
+
+    let mut request = vec![VERSION, CONNECT, 0, ADDRESS_DOMAIN, host_len];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected SOCKS version",
+        ));
+    }
+    if reply[1] != 0 {
+        return Err(reply_error(reply[1]));
+    }
+
+    // Skip the address the proxy bound to.
+    let address_len = match reply[3] {
+        ADDRESS_IPV4 => 4,
+        ADDRESS_IPV6 => 16,
+        ADDRESS_DOMAIN => usize::from(stream.read_u8().await?),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unexpected SOCKS address type",
+            ))
+        }
+    };
+    let mut bound = vec![0; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    Ok(())
+}
+
+fn reply_error(code: u8) -> io::Error {
+    let (kind, message) = match code {
+        2 => (io::ErrorKind::PermissionDenied, "connection not allowed"),
+        3 => (io::ErrorKind::Other, "network unreachable"),
+        4 => (io::ErrorKind::Other, "host unreachable"),
+        5 => (io::ErrorKind::ConnectionRefused, "connection refused"),
+        6 => (io::ErrorKind::TimedOut, "TTL expired"),
+        _ => (io::ErrorKind::Other, "general SOCKS server failure"),
+    };
+
+    io::Error::new(kind, format!("SOCKS proxy failed to connect: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn connects_via_domain_name() {
+        let (mut client, mut proxy) = tokio::io::duplex(1024);
+
+        let proxy = tokio::spawn(async move {
+            let mut greeting = [0; 3];
+            proxy.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [VERSION, 1, NO_AUTHENTICATION]);
+            proxy
+                .write_all(&[VERSION, NO_AUTHENTICATION])
+                .await
+                .unwrap();
+
+            let mut request = [0; 5 + 12 + 2];
+            proxy.read_exact(&mut request).await.unwrap();
+            assert_eq!(&request[..5], &[VERSION, CONNECT, 0, ADDRESS_DOMAIN, 12]);
+            assert_eq!(&request[5..17], b"abcdef.onion");
+            assert_eq!(&request[17..], &4001u16.to_be_bytes());
+            proxy
+                .write_all(&[VERSION, 0, 0, ADDRESS_IPV4, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+
+            proxy.write_all(b"hello").await.unwrap();
+        });
+
+        connect(&mut client, "abcdef.onion", 4001).await.unwrap();
+        let mut hello = [0; 5];
+        client.read_exact(&mut hello).await.unwrap();
+        assert_eq!(&hello, b"hello");
+        proxy.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_refused_connections() {
+        let (mut client, mut proxy) = tokio::io::duplex(1024);
+
+        tokio::spawn(async move {
+            let mut greeting = [0; 3];
+            proxy.read_exact(&mut greeting).await.unwrap();
+            proxy
+                .write_all(&[VERSION, NO_AUTHENTICATION])
+                .await
+                .unwrap();
+            let mut request = [0; 5 + 4 + 2];
+            proxy.read_exact(&mut request).await.unwrap();
+            proxy
+                .write_all(&[VERSION, 5, 0, ADDRESS_IPV4, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+        });
+
+        let error = connect(&mut client, "host", 80).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+    }
+}
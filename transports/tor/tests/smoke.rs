@@ -0,0 +1,136 @@
+use futures::future::poll_fn;
+use futures::{AsyncReadExt, AsyncWriteExt};
+use libp2p_core::multiaddr::Protocol;
+use libp2p_core::transport::{ListenerId, TransportEvent};
+use libp2p_core::{Multiaddr, Transport as _};
+use libp2p_tor::{Config, ControlAuth, Transport};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt as _, AsyncWriteExt as _, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+const SERVICE_ID: &str = "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd";
+
+#[tokio::test]
+async fn dials_onion_service_of_listener() {
+    let (socks_proxy, control_port) = spawn_fake_tor().await;
+
+    let mut listener = Transport::new(
+        Config::new(socks_proxy)
+            .with_hidden_services(control_port, ControlAuth::None)
+            .with_virtual_port(4001),
+    );
+    listener
+        .listen_on(ListenerId::next(), "/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+    let onion_addr = match poll_fn(|cx| Pin::new(&mut listener).poll(cx)).await {
+        TransportEvent::NewAddress { listen_addr, .. } => listen_addr,
+        e => panic!("Unexpected event: {e:?}"),
+    };
+    assert_eq!(
+        onion_addr,
+        format!("/onion3/{SERVICE_ID}:4001").parse().unwrap()
+    );
+
+    let mut dialer = Transport::new(Config::new(socks_proxy));
+    assert!(dialer
+        .listen_on(ListenerId::next(), "/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .is_err());
+    let dial = dialer.dial(onion_addr.clone()).unwrap();
+
+    let (incoming, outgoing) = futures::join!(
+        async {
+            match poll_fn(|cx| Pin::new(&mut listener).poll(cx)).await {
+                TransportEvent::Incoming {
+                    upgrade,
+                    local_addr,
+                    ..
+                } => {
+                    assert_eq!(local_addr, onion_addr);
+                    upgrade.await.unwrap()
+                }
+                e => panic!("Unexpected event: {e:?}"),
+            }
+        },
+        dial
+    );
+    let (mut incoming, mut outgoing) = (incoming, outgoing.unwrap());
+
+    outgoing.write_all(b"hello").await.unwrap();
+    let mut hello = [0; 5];
+    incoming.read_exact(&mut hello).await.unwrap();
+    assert_eq!(&hello, b"hello");
+}
+
+#[tokio::test]
+async fn rejects_non_onion_addresses() {
+    let mut transport = Transport::new(Config::default());
+    let addr: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+
+    assert!(transport.dial(addr.clone()).is_err());
+    assert!(transport
+        .dial(addr.with(Protocol::Onion3(([0; 35], 4001).into())))
+        .is_err());
+}
+
+/// Spawns a fake Tor daemon, returning the addresses of its SOCKS proxy and control port.
+///
+/// The onion service added via the control port is reachable via the SOCKS proxy.
+async fn spawn_fake_tor() -> (SocketAddr, SocketAddr) {
+    let socks = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let control = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addrs = (socks.local_addr().unwrap(), control.local_addr().unwrap());
+    let (target_tx, target_rx) = tokio::sync::oneshot::channel::<SocketAddr>();
+
+    tokio::spawn(async move {
+        let (stream, _) = control.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "AUTHENTICATE\r\n");
+        stream.get_mut().write_all(b"250 OK\r\n").await.unwrap();
+
+        line.clear();
+        stream.read_line(&mut line).await.unwrap();
+        let target = line.trim_end().rsplit(',').next().unwrap();
+        assert!(line.starts_with("ADD_ONION NEW:ED25519-V3 Flags=DiscardPK Port=4001,"));
+        target_tx.send(target.parse().unwrap()).unwrap();
+        stream
+            .get_mut()
+            .write_all(format!("250-ServiceID={SERVICE_ID}\r\n250 OK\r\n").as_bytes())
+            .await
+            .unwrap();
+
+        // Keep the onion service alive.
+        stream.read_line(&mut line).await.unwrap();
+    });
+
+    tokio::spawn(async move {
+        let target = target_rx.await.unwrap();
+        let (mut stream, _) = socks.accept().await.unwrap();
+
+        let mut greeting = [0; 3];
+        stream.read_exact(&mut greeting).await.unwrap();
+        stream.write_all(&[5, 0]).await.unwrap();
+        let mut request = [0; 5];
+        stream.read_exact(&mut request).await.unwrap();
+        let mut host = vec![0; usize::from(request[4]) + 2];
+        stream.read_exact(&mut host).await.unwrap();
+        assert_eq!(
+            &host[..host.len() - 2],
+            format!("{SERVICE_ID}.onion").as_bytes()
+        );
+        assert_eq!(&host[host.len() - 2..], &4001u16.to_be_bytes());
+
+        let mut service = TcpStream::connect(target).await.unwrap();
+        stream
+            .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0])
+            .await
+            .unwrap();
+        tokio::io::copy_bidirectional(&mut stream, &mut service)
+            .await
+            .unwrap();
+    });
+
+    addrs
+}
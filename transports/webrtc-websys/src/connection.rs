@@ -1,7 +1,9 @@
 //! A libp2p connection backed by an [RtcPeerConnection](https://developer.mozilla.org/en-US/docs/Web/API/RTCPeerConnection).
 
 use super::{Error, Stream};
+use crate::sdp;
 use crate::stream::DropListener;
+use crate::transport::{Config, IceTransportPolicy};
 use futures::channel::mpsc;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
@@ -16,7 +18,7 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{
     RtcConfiguration, RtcDataChannel, RtcDataChannelEvent, RtcDataChannelInit, RtcDataChannelType,
-    RtcSessionDescriptionInit,
+    RtcIceServer, RtcIceTransportPolicy, RtcSessionDescriptionInit,
 };
 
 /// A WebRTC Connection.
@@ -174,11 +176,20 @@ pub(crate) struct RtcPeerConnection {
 }
 
 impl RtcPeerConnection {
-    pub(crate) async fn new(algorithm: String) -> Result<Self, Error> {
+    pub(crate) async fn new(algorithm: String, config: &Config) -> Result<Self, Error> {
         let algo: Object = Object::new();
         Reflect::set(&algo, &"name".into(), &"ECDSA".into()).unwrap();
         Reflect::set(&algo, &"namedCurve".into(), &"P-256".into()).unwrap();
         Reflect::set(&algo, &"hash".into(), &algorithm.into()).unwrap();
+        if let Some(lifetime) = config.certificate_lifetime {
+            // `expires` is given in milliseconds
+            Reflect::set(
+                &algo,
+                &"expires".into(),
+                &(lifetime.as_millis() as f64).into(),
+            )
+            .unwrap();
+        }
 
         let certificate_promise =
             web_sys::RtcPeerConnection::generate_certificate_with_object(&algo)
@@ -186,13 +197,36 @@ impl RtcPeerConnection {
 
         let certificate = JsFuture::from(certificate_promise).await?;
 
-        let mut config = RtcConfiguration::default();
+        let mut rtc_config = RtcConfiguration::default();
         // wrap certificate in a js Array first before adding it to the config object
         let certificate_arr = js_sys::Array::new();
         certificate_arr.push(&certificate);
-        config.certificates(&certificate_arr);
+        rtc_config.certificates(&certificate_arr);
 
-        let inner = web_sys::RtcPeerConnection::new_with_configuration(&config)?;
+        let ice_servers = js_sys::Array::new();
+        for server in &config.ice_servers {
+            let urls = js_sys::Array::new();
+            for url in &server.urls {
+                urls.push(&url.into());
+            }
+
+            let mut ice_server = RtcIceServer::new();
+            ice_server.urls(&urls);
+            if let Some(username) = &server.username {
+                ice_server.username(username);
+            }
+            if let Some(credential) = &server.credential {
+                ice_server.credential(credential);
+            }
+            ice_servers.push(&ice_server);
+        }
+        rtc_config.ice_servers(&ice_servers);
+        rtc_config.ice_transport_policy(match config.ice_transport_policy {
+            IceTransportPolicy::All => RtcIceTransportPolicy::All,
+            IceTransportPolicy::Relay => RtcIceTransportPolicy::Relay,
+        });
+
+        let inner = web_sys::RtcPeerConnection::new_with_configuration(&rtc_config)?;
 
         Ok(Self { inner })
     }
@@ -256,7 +290,7 @@ impl RtcPeerConnection {
             .sdp();
 
         let fingerprint =
-            parse_fingerprint(sdp).ok_or_else(|| Error::Js("No fingerprint in SDP".to_string()))?;
+            sdp::fingerprint(sdp).ok_or_else(|| Error::Js("No fingerprint in SDP".to_string()))?;
 
         Ok(fingerprint)
     }
@@ -271,37 +305,3 @@ impl RtcPeerConnection {
         Ok(())
     }
 }
-
-/// Parse Fingerprint from a SDP.
-fn parse_fingerprint(sdp: &str) -> Option<Fingerprint> {
-    // split the sdp by new lines / carriage returns
-    let lines = sdp.split("\r\n");
-
-    // iterate through the lines to find the one starting with a=fingerprint:
-    // get the value after the first space
-    // return the value as a Fingerprint
-    for line in lines {
-        if line.starts_with("a=fingerprint:") {
-            let fingerprint = line.split(' ').nth(1).unwrap();
-            let bytes = hex::decode(fingerprint.replace(':', "")).unwrap();
-            let arr: [u8; 32] = bytes.as_slice().try_into().unwrap();
-            return Some(Fingerprint::raw(arr));
-        }
-    }
-    None
-}
-
-#[cfg(test)]
-mod sdp_tests {
-    use super::*;
-
-    #[test]
-    fn test_fingerprint() {
-        let sdp = "v=0\r\no=- 0 0 IN IP6 ::1\r\ns=-\r\nc=IN IP6 ::1\r\nt=0 0\r\na=ice-lite\r\nm=application 61885 UDP/DTLS/SCTP webrtc-datachannel\r\na=mid:0\r\na=setup:passive\r\na=ice-ufrag:libp2p+webrtc+v1/YwapWySn6fE6L9i47PhlB6X4gzNXcgFs\r\na=ice-pwd:libp2p+webrtc+v1/YwapWySn6fE6L9i47PhlB6X4gzNXcgFs\r\na=fingerprint:sha-256 A8:17:77:1E:02:7E:D1:2B:53:92:70:A6:8E:F9:02:CC:21:72:3A:92:5D:F4:97:5F:27:C4:5E:75:D4:F4:31:89\r\na=sctp-port:5000\r\na=max-message-size:16384\r\na=candidate:1467250027 1 UDP 1467250027 ::1 61885 typ host\r\n";
-
-        let fingerprint = parse_fingerprint(sdp).unwrap();
-
-        assert_eq!(fingerprint.algorithm(), "sha-256");
-        assert_eq!(fingerprint.to_sdp_format(), "A8:17:77:1E:02:7E:D1:2B:53:92:70:A6:8E:F9:02:CC:21:72:3A:92:5D:F4:97:5F:27:C4:5E:75:D4:F4:31:89");
-    }
-}
@@ -10,4 +10,4 @@ mod upgrade;
 pub use self::connection::Connection;
 pub use self::error::Error;
 pub use self::stream::Stream;
-pub use self::transport::{Config, Transport};
+pub use self::transport::{Config, IceServer, IceTransportPolicy, Transport};
@@ -1,4 +1,5 @@
 use libp2p_webrtc_utils::Fingerprint;
+use std::fmt;
 use std::net::SocketAddr;
 use web_sys::{RtcSdpType, RtcSessionDescriptionInit};
 
@@ -21,35 +22,223 @@ pub(crate) fn answer(
 ///
 /// Certificate verification is disabled which is why we hardcode a dummy fingerprint here.
 pub(crate) fn offer(offer: String, client_ufrag: &str) -> RtcSessionDescriptionInit {
-    // find line and replace a=ice-ufrag: with "\r\na=ice-ufrag:{client_ufrag}\r\n"
-    // find line and replace a=ice-pwd: with "\r\na=ice-ufrag:{client_ufrag}\r\n"
+    let munged_sdp_offer = munge_offer(&offer, client_ufrag);
 
-    let mut munged_sdp_offer = String::new();
+    tracing::trace!(offer=%munged_sdp_offer, "Created SDP offer");
+
+    let mut offer_obj = RtcSessionDescriptionInit::new(RtcSdpType::Offer);
+    offer_obj.sdp(&munged_sdp_offer);
+
+    offer_obj
+}
+
+/// Replaces the `ice-ufrag` and `ice-pwd` attributes of every section in the given SDP with
+/// `client_ufrag`.
+fn munge_offer(offer: &str, client_ufrag: &str) -> String {
+    let mut description = SessionDescription::parse(offer);
+
+    description.replace_attribute("ice-ufrag", client_ufrag);
+    description.replace_attribute("ice-pwd", client_ufrag);
+
+    description.to_string()
+}
+
+/// Parses the first `a=fingerprint` attribute of the given SDP.
+pub(crate) fn fingerprint(sdp: &str) -> Option<Fingerprint> {
+    let description = SessionDescription::parse(sdp);
+    let value = description.attribute("fingerprint")?;
+
+    // The attribute has the form `<hash-function> <fingerprint>`, see RFC 8122.
+    let (algorithm, fingerprint) = value.split_once(' ')?;
+
+    Fingerprint::try_from_sdp(algorithm, fingerprint.trim())
+}
+
+/// A single `<type>=<value>` line of an SDP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Line {
+    pub(crate) kind: char,
+    pub(crate) value: String,
+}
 
-    for line in offer.split("\r\n") {
-        if line.starts_with("a=ice-ufrag:") {
-            munged_sdp_offer.push_str(&format!("a=ice-ufrag:{client_ufrag}\r\n"));
-            continue;
+impl Line {
+    fn parse(line: &str) -> Option<Self> {
+        let (kind, value) = line.split_once('=')?;
+
+        let mut chars = kind.chars();
+        let kind = chars.next()?;
+        if chars.next().is_some() {
+            return None;
         }
 
-        if line.starts_with("a=ice-pwd:") {
-            munged_sdp_offer.push_str(&format!("a=ice-pwd:{client_ufrag}\r\n"));
-            continue;
+        Some(Line {
+            kind,
+            value: value.to_owned(),
+        })
+    }
+
+    /// Returns the name and the (possibly empty) value if this is an `a=` line.
+    ///
+    /// Property attributes such as `a=ice-lite` have an empty value.
+    pub(crate) fn as_attribute(&self) -> Option<(&str, &str)> {
+        if self.kind != 'a' {
+            return None;
         }
 
-        if !line.is_empty() {
-            munged_sdp_offer.push_str(&format!("{}\r\n", line));
-            continue;
+        Some(self.value.split_once(':').unwrap_or((&self.value, "")))
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.kind, self.value)
+    }
+}
+
+/// A media description, i.e. an `m=` line followed by all lines up to the next `m=` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MediaDescription {
+    /// The value of the `m=` line.
+    pub(crate) media: String,
+    pub(crate) lines: Vec<Line>,
+}
+
+/// A parsed SDP session description, see <https://www.rfc-editor.org/rfc/rfc8866>.
+///
+/// Parsing is lenient: lines may be terminated by either CRLF or LF, and lines that are not of the
+/// form `<type>=<value>` are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SessionDescription {
+    /// All session-level lines, i.e. the lines before the first `m=` line.
+    pub(crate) session: Vec<Line>,
+    pub(crate) media: Vec<MediaDescription>,
+}
+
+impl SessionDescription {
+    pub(crate) fn parse(sdp: &str) -> Self {
+        let mut description = SessionDescription::default();
+
+        for line in sdp
+            .lines()
+            .filter_map(|l| Line::parse(l.trim_end_matches('\r')))
+        {
+            match (line.kind, description.media.last_mut()) {
+                ('m', _) => description.media.push(MediaDescription {
+                    media: line.value,
+                    lines: Vec::new(),
+                }),
+                (_, Some(media)) => media.lines.push(line),
+                (_, None) => description.session.push(line),
+            }
         }
+
+        description
     }
 
-    // remove any double \r\n
-    let munged_sdp_offer = munged_sdp_offer.replace("\r\n\r\n", "\r\n");
+    /// Returns the value of the first attribute with the given name, looking at the session-level
+    /// attributes first and at each media description afterwards.
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.lines()
+            .filter_map(Line::as_attribute)
+            .find_map(|(n, value)| (n == name).then_some(value))
+    }
 
-    tracing::trace!(offer=%munged_sdp_offer, "Created SDP offer");
+    /// Replaces the value of every attribute with the given name, in all sections.
+    pub(crate) fn replace_attribute(&mut self, name: &str, value: &str) {
+        let lines = self
+            .session
+            .iter_mut()
+            .chain(self.media.iter_mut().flat_map(|m| m.lines.iter_mut()));
 
-    let mut offer_obj = RtcSessionDescriptionInit::new(RtcSdpType::Offer);
-    offer_obj.sdp(&munged_sdp_offer);
+        for line in lines {
+            if matches!(line.as_attribute(), Some((n, _)) if n == name) {
+                line.value = format!("{name}:{value}");
+            }
+        }
+    }
 
-    offer_obj
+    fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.session
+            .iter()
+            .chain(self.media.iter().flat_map(|m| m.lines.iter()))
+    }
+}
+
+impl fmt::Display for SessionDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.session {
+            write!(f, "{line}\r\n")?;
+        }
+        for media in &self.media {
+            write!(f, "m={}\r\n", media.media)?;
+            for line in &media.lines {
+                write!(f, "{line}\r\n")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SDP: &str = "v=0\r\no=- 0 0 IN IP6 ::1\r\ns=-\r\nc=IN IP6 ::1\r\nt=0 0\r\na=ice-lite\r\nm=application 61885 UDP/DTLS/SCTP webrtc-datachannel\r\na=mid:0\r\na=setup:passive\r\na=ice-ufrag:libp2p+webrtc+v1/YwapWySn6fE6L9i47PhlB6X4gzNXcgFs\r\na=ice-pwd:libp2p+webrtc+v1/YwapWySn6fE6L9i47PhlB6X4gzNXcgFs\r\na=fingerprint:sha-256 A8:17:77:1E:02:7E:D1:2B:53:92:70:A6:8E:F9:02:CC:21:72:3A:92:5D:F4:97:5F:27:C4:5E:75:D4:F4:31:89\r\na=sctp-port:5000\r\na=max-message-size:16384\r\na=candidate:1467250027 1 UDP 1467250027 ::1 61885 typ host\r\n";
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint = fingerprint(SDP).unwrap();
+
+        assert_eq!(fingerprint.algorithm(), "sha-256");
+        assert_eq!(fingerprint.to_sdp_format(), "A8:17:77:1E:02:7E:D1:2B:53:92:70:A6:8E:F9:02:CC:21:72:3A:92:5D:F4:97:5F:27:C4:5E:75:D4:F4:31:89");
+    }
+
+    #[test]
+    fn test_sha512_fingerprint() {
+        let sha512 = "a=fingerprint:sha-512 ".to_owned() + &["AB"; 64].join(":");
+        let sdp = SDP.replace(
+            "a=fingerprint:sha-256 A8:17:77:1E:02:7E:D1:2B:53:92:70:A6:8E:F9:02:CC:21:72:3A:92:5D:F4:97:5F:27:C4:5E:75:D4:F4:31:89",
+            &sha512,
+        );
+
+        let fingerprint = fingerprint(&sdp).unwrap();
+
+        assert_eq!(fingerprint.algorithm(), "sha-512");
+        assert_eq!(fingerprint.digest(), [0xAB; 64]);
+    }
+
+    #[test]
+    fn parses_lf_only_line_endings() {
+        let lf_only = SDP.replace("\r\n", "\n");
+
+        assert_eq!(
+            SessionDescription::parse(&lf_only),
+            SessionDescription::parse(SDP)
+        );
+        assert!(fingerprint(&lf_only).is_some());
+    }
+
+    #[test]
+    fn round_trips() {
+        assert_eq!(SessionDescription::parse(SDP).to_string(), SDP);
+    }
+
+    #[test]
+    fn munges_all_media_sections() {
+        let sdp = "v=0\nt=0 0\nm=application 9 UDP/DTLS/SCTP webrtc-datachannel\na=ice-ufrag:foo\na=ice-pwd:bar\nm=application 9 UDP/DTLS/SCTP webrtc-datachannel\na=ice-ufrag:foo\na=ice-pwd:bar\n";
+
+        let munged = SessionDescription::parse(&munge_offer(sdp, "ufrag"));
+
+        assert_eq!(munged.media.len(), 2);
+        for media in munged.media {
+            assert_eq!(
+                media.lines,
+                vec![
+                    Line::parse("a=ice-ufrag:ufrag").unwrap(),
+                    Line::parse("a=ice-pwd:ufrag").unwrap()
+                ]
+            );
+        }
+    }
 }
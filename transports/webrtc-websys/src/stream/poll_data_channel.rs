@@ -71,18 +71,22 @@ impl PollDataChannel {
         });
         inner.set_onbufferedamountlow(Some(on_write_closure.as_ref().unchecked_ref()));
 
+        let new_data_waker = Rc::new(AtomicWaker::new());
+
         let close_waker = Rc::new(AtomicWaker::new());
         let on_close_closure = Closure::new({
             let close_waker = close_waker.clone();
+            let new_data_waker = new_data_waker.clone();
 
             move |_: Event| {
                 tracing::trace!("DataChannel closed");
                 close_waker.wake();
+                // Pending reads return the end of the stream.
+                new_data_waker.wake();
             }
         });
         inner.set_onclose(Some(on_close_closure.as_ref().unchecked_ref()));
 
-        let new_data_waker = Rc::new(AtomicWaker::new());
         let read_buffer = Rc::new(Mutex::new(BytesMut::new())); // We purposely don't use `with_capacity` so we don't eagerly allocate `MAX_READ_BUFFER` per stream.
         let overloaded = Rc::new(AtomicBool::new(false));
 
@@ -165,7 +169,17 @@ impl AsyncRead for PollDataChannel {
     ) -> Poll<io::Result<usize>> {
         let this = self.get_mut();
 
-        futures::ready!(this.poll_ready(cx))?;
+        // Data received before the data channel started closing is still returned.
+        if this.read_buffer.lock().unwrap().is_empty() {
+            if matches!(
+                this.ready_state(),
+                RtcDataChannelState::Closing | RtcDataChannelState::Closed
+            ) {
+                return Poll::Ready(Ok(0));
+            }
+
+            futures::ready!(this.poll_ready(cx))?;
+        }
 
         let mut read_buffer = this.read_buffer.lock().unwrap();
 
@@ -9,11 +9,60 @@ use libp2p_identity::{Keypair, PeerId};
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 /// Config for the [`Transport`].
 #[derive(Clone)]
 pub struct Config {
-    keypair: Keypair,
+    pub(crate) keypair: Keypair,
+    pub(crate) ice_servers: Vec<IceServer>,
+    pub(crate) ice_transport_policy: IceTransportPolicy,
+    pub(crate) certificate_lifetime: Option<Duration>,
+}
+
+/// A STUN or TURN server used by the `RTCPeerConnection` to gather ICE candidates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IceServer {
+    pub(crate) urls: Vec<String>,
+    pub(crate) username: Option<String>,
+    pub(crate) credential: Option<String>,
+}
+
+impl IceServer {
+    /// An ICE server reachable at the given URLs, e.g. `stun:stun.l.google.com:19302` or
+    /// `turn:turn.example.com:3478?transport=tcp`.
+    pub fn new<I, S>(urls: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        IceServer {
+            urls: urls.into_iter().map(Into::into).collect(),
+            username: None,
+            credential: None,
+        }
+    }
+
+    /// Sets the username and credential to authenticate with a TURN server.
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        credential: impl Into<String>,
+    ) -> Self {
+        self.username = Some(username.into());
+        self.credential = Some(credential.into());
+        self
+    }
+}
+
+/// Which ICE candidates the `RTCPeerConnection` may use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IceTransportPolicy {
+    /// All candidates may be used.
+    #[default]
+    All,
+    /// Only candidates relayed through a TURN server may be used.
+    Relay,
 }
 
 /// A WebTransport [`Transport`](libp2p_core::Transport) that works with `web-sys`.
@@ -26,8 +75,35 @@ impl Config {
     pub fn new(keypair: &Keypair) -> Self {
         Config {
             keypair: keypair.to_owned(),
+            ice_servers: Vec::new(),
+            ice_transport_policy: IceTransportPolicy::default(),
+            certificate_lifetime: None,
         }
     }
+
+    /// Adds a STUN or TURN server to gather ICE candidates with.
+    ///
+    /// Without any ICE servers, only host candidates are gathered.
+    pub fn with_ice_server(mut self, server: IceServer) -> Self {
+        self.ice_servers.push(server);
+        self
+    }
+
+    /// Sets which ICE candidates may be used, see [`IceTransportPolicy`].
+    ///
+    /// Defaults to [`IceTransportPolicy::All`].
+    pub fn with_ice_transport_policy(mut self, policy: IceTransportPolicy) -> Self {
+        self.ice_transport_policy = policy;
+        self
+    }
+
+    /// Sets how long the certificate generated for each connection is valid.
+    ///
+    /// Defaults to the browser's default, usually 30 days.
+    pub fn with_certificate_lifetime(mut self, lifetime: Duration) -> Self {
+        self.certificate_lifetime = Some(lifetime);
+        self
+    }
 }
 
 impl Transport {
@@ -82,7 +158,7 @@ impl libp2p_core::Transport for Transport {
 
         Ok(async move {
             let (peer_id, connection) =
-                upgrade::outbound(sock_addr, server_fingerprint, config.keypair.clone()).await?;
+                upgrade::outbound(sock_addr, server_fingerprint, config).await?;
 
             Ok((peer_id, connection))
         }
@@ -2,8 +2,8 @@ use super::Error;
 use crate::connection::RtcPeerConnection;
 use crate::error::AuthenticationError;
 use crate::sdp;
-use crate::Connection;
-use libp2p_identity::{Keypair, PeerId};
+use crate::{Config, Connection};
+use libp2p_identity::PeerId;
 use libp2p_webrtc_utils::noise;
 use libp2p_webrtc_utils::Fingerprint;
 use send_wrapper::SendWrapper;
@@ -14,9 +14,9 @@ use std::net::SocketAddr;
 pub(crate) async fn outbound(
     sock_addr: SocketAddr,
     remote_fingerprint: Fingerprint,
-    id_keys: Keypair,
+    config: Config,
 ) -> Result<(PeerId, Connection), Error> {
-    let fut = SendWrapper::new(outbound_inner(sock_addr, remote_fingerprint, id_keys));
+    let fut = SendWrapper::new(outbound_inner(sock_addr, remote_fingerprint, config));
     fut.await
 }
 
@@ -24,9 +24,10 @@ pub(crate) async fn outbound(
 async fn outbound_inner(
     sock_addr: SocketAddr,
     remote_fingerprint: Fingerprint,
-    id_keys: Keypair,
+    config: Config,
 ) -> Result<(PeerId, Connection), Error> {
-    let rtc_peer_connection = RtcPeerConnection::new(remote_fingerprint.algorithm()).await?;
+    let rtc_peer_connection =
+        RtcPeerConnection::new(remote_fingerprint.algorithm(), &config).await?;
 
     // Create stream for Noise handshake
     // Must create data channel before Offer is created for it to be included in the SDP
@@ -49,9 +50,14 @@ async fn outbound_inner(
     tracing::trace!(?local_fingerprint);
     tracing::trace!(?remote_fingerprint);
 
-    let peer_id = noise::outbound(id_keys, channel, remote_fingerprint, local_fingerprint)
-        .await
-        .map_err(AuthenticationError)?;
+    let peer_id = noise::outbound(
+        config.keypair,
+        channel,
+        remote_fingerprint,
+        local_fingerprint,
+    )
+    .await
+    .map_err(AuthenticationError)?;
 
     tracing::debug!(peer=%peer_id, "Remote peer identified");
 
@@ -20,11 +20,9 @@
 
 use webrtc::dtls_transport::dtls_fingerprint::RTCDtlsFingerprint;
 
-const SHA256: &str = "sha-256";
-
 type Multihash = multihash::Multihash<64>;
 
-/// A certificate fingerprint that is assumed to be created using the SHA256 hash algorithm.
+/// A certificate fingerprint, created using either SHA256, SHA384 or SHA512.
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub struct Fingerprint(libp2p_webrtc_utils::Fingerprint);
 
@@ -40,15 +38,13 @@ impl Fingerprint {
     }
 
     /// Converts [`RTCDtlsFingerprint`] to [`Fingerprint`].
+    ///
+    /// Returns `None` if the hash algorithm is not supported.
     pub fn try_from_rtc_dtls(fp: &RTCDtlsFingerprint) -> Option<Self> {
-        if fp.algorithm != SHA256 {
-            return None;
-        }
-
-        let mut buf = [0; 32];
-        hex::decode_to_slice(fp.value.replace(':', ""), &mut buf).ok()?;
-
-        Some(Self(libp2p_webrtc_utils::Fingerprint::raw(buf)))
+        Some(Self(libp2p_webrtc_utils::Fingerprint::try_from_sdp(
+            &fp.algorithm,
+            &fp.value,
+        )?))
     }
 
     /// Converts [`Multihash`](multihash::Multihash) to [`Fingerprint`].
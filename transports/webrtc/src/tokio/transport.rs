@@ -72,6 +72,16 @@ impl Transport {
             listeners: SelectAll::new(),
         }
     }
+
+    /// Sets whether remote ICE candidates with a `.local` mDNS hostname, as sent by browsers
+    /// to hide their local IP address, are resolved via multicast DNS queries.
+    ///
+    /// This allows connections with browsers in the same local network without a STUN server.
+    /// Disabled by default.
+    pub fn with_mdns_candidate_resolution(mut self, enabled: bool) -> Self {
+        self.config.mdns_candidate_resolution = enabled;
+        self
+    }
 }
 
 impl libp2p_core::Transport for Transport {
@@ -143,6 +153,7 @@ impl libp2p_core::Transport for Transport {
                 client_fingerprint.into_inner(),
                 server_fingerprint,
                 config.id_keys,
+                config.mdns_candidate_resolution,
             )
             .await?;
 
@@ -339,6 +350,7 @@ impl Stream for ListenStream {
                         self.config.fingerprint.into_inner(),
                         new_addr.ufrag,
                         self.config.id_keys.clone(),
+                        self.config.mdns_candidate_resolution,
                     )
                     .boxed();
 
@@ -369,6 +381,7 @@ struct Config {
     inner: RTCConfiguration,
     fingerprint: Fingerprint,
     id_keys: identity::Keypair,
+    mdns_candidate_resolution: bool,
 }
 
 impl Config {
@@ -383,6 +396,7 @@ impl Config {
                 ..RTCConfiguration::default()
             },
             fingerprint,
+            mdns_candidate_resolution: false,
         }
     }
 }
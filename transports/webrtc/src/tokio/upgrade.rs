@@ -31,6 +31,7 @@ use webrtc::api::APIBuilder;
 use webrtc::data::data_channel::DataChannel;
 use webrtc::data_channel::data_channel_init::RTCDataChannelInit;
 use webrtc::dtls_transport::dtls_role::DTLSRole;
+use webrtc::ice::mdns::MulticastDnsMode;
 use webrtc::ice::network_type::NetworkType;
 use webrtc::ice::udp_mux::UDPMux;
 use webrtc::ice::udp_network::UDPNetwork;
@@ -48,10 +49,12 @@ pub(crate) async fn outbound(
     client_fingerprint: Fingerprint,
     server_fingerprint: Fingerprint,
     id_keys: identity::Keypair,
+    mdns_candidate_resolution: bool,
 ) -> Result<(PeerId, Connection), Error> {
     tracing::debug!(address=%addr, "new outbound connection to address");
 
-    let (peer_connection, ufrag) = new_outbound_connection(addr, config, udp_mux).await?;
+    let (peer_connection, ufrag) =
+        new_outbound_connection(addr, config, udp_mux, mdns_candidate_resolution).await?;
 
     let offer = peer_connection.create_offer(None).await?;
     tracing::debug!(offer=%offer.sdp, "created SDP offer for outbound connection");
@@ -81,10 +84,18 @@ pub(crate) async fn inbound(
     server_fingerprint: Fingerprint,
     remote_ufrag: String,
     id_keys: identity::Keypair,
+    mdns_candidate_resolution: bool,
 ) -> Result<(PeerId, Connection), Error> {
     tracing::debug!(address=%addr, ufrag=%remote_ufrag, "new inbound connection from address");
 
-    let peer_connection = new_inbound_connection(addr, config, udp_mux, &remote_ufrag).await?;
+    let peer_connection = new_inbound_connection(
+        addr,
+        config,
+        udp_mux,
+        &remote_ufrag,
+        mdns_candidate_resolution,
+    )
+    .await?;
 
     let offer = sdp::offer(addr, &remote_ufrag);
     tracing::debug!(?offer, "calculated SDP offer for inbound connection");
@@ -111,9 +122,10 @@ async fn new_outbound_connection(
     addr: SocketAddr,
     config: RTCConfiguration,
     udp_mux: Arc<dyn UDPMux + Send + Sync>,
+    mdns_candidate_resolution: bool,
 ) -> Result<(RTCPeerConnection, String), Error> {
     let ufrag = random_ufrag();
-    let se = setting_engine(udp_mux, &ufrag, addr);
+    let se = setting_engine(udp_mux, &ufrag, addr, mdns_candidate_resolution);
 
     let connection = APIBuilder::new()
         .with_setting_engine(se)
@@ -129,8 +141,9 @@ async fn new_inbound_connection(
     config: RTCConfiguration,
     udp_mux: Arc<dyn UDPMux + Send + Sync>,
     ufrag: &str,
+    mdns_candidate_resolution: bool,
 ) -> Result<RTCPeerConnection, Error> {
-    let mut se = setting_engine(udp_mux, ufrag, addr);
+    let mut se = setting_engine(udp_mux, ufrag, addr, mdns_candidate_resolution);
     {
         se.set_lite(true);
         se.disable_certificate_fingerprint_verification(true);
@@ -154,6 +167,7 @@ fn setting_engine(
     udp_mux: Arc<dyn UDPMux + Send + Sync>,
     ufrag: &str,
     addr: SocketAddr,
+    mdns_candidate_resolution: bool,
 ) -> SettingEngine {
     let mut se = SettingEngine::default();
 
@@ -176,6 +190,14 @@ fn setting_engine(
     };
     se.set_network_types(vec![network_type]);
 
+    // Only query mDNS hostnames of remote candidates if requested, as this binds a multicast
+    // socket per connection. We never announce our own candidates via mDNS.
+    se.set_ice_multicast_dns_mode(if mdns_candidate_resolution {
+        MulticastDnsMode::QueryOnly
+    } else {
+        MulticastDnsMode::Disabled
+    });
+
     se
 }
 
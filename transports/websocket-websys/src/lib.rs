@@ -60,6 +60,12 @@ use crate::web_context::WebContext;
 ///     .boxed();
 /// ```
 ///
+/// ## Compression
+///
+/// Browsers offer the permessage-deflate extension on their own, without means to configure
+/// it. Frames are compressed if the server accepts the extension, which servers using
+/// `libp2p-websocket` do once a `deflate::Config` is set.
+///
 #[derive(Default)]
 pub struct Transport {
     _private: (),
@@ -0,0 +1,149 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Support for the permessage-deflate extension, see [RFC 7692](https://tools.ietf.org/html/rfc7692).
+
+use soketto::{
+    base::Header,
+    connection::Mode,
+    extension::{Extension, Param},
+    BoxedError, Storage,
+};
+
+/// Frames with a payload smaller than this are not compressed by default.
+const DEFAULT_THRESHOLD: usize = 128;
+
+/// Configuration of the permessage-deflate extension.
+///
+/// When set on a [`WsConfig`](crate::WsConfig), the extension is offered when dialing and
+/// accepted when offered by a dialing remote. Frames are only compressed if both sides agreed on
+/// the extension during the websocket handshake.
+#[derive(Clone, Debug)]
+pub struct Config {
+    max_window_bits: u8,
+    threshold: usize,
+}
+
+impl Config {
+    /// Create a new configuration with the maximum window size and a threshold of 128 bytes.
+    pub fn new() -> Self {
+        Config {
+            max_window_bits: 15,
+            threshold: DEFAULT_THRESHOLD,
+        }
+    }
+
+    /// Get the base-2 logarithm of the LZ77 sliding window size.
+    pub fn max_window_bits(&self) -> u8 {
+        self.max_window_bits
+    }
+
+    /// Set the base-2 logarithm of the LZ77 sliding window size, which limits the memory used
+    /// per connection.
+    ///
+    /// The limit is requested for both directions when dialing. As listener, the limits requested
+    /// by the dialer apply.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is not within `9..=15`.
+    pub fn set_max_window_bits(&mut self, bits: u8) -> &mut Self {
+        assert!(
+            (9..=15).contains(&bits),
+            "max. window bits have to be within 9..=15"
+        );
+        self.max_window_bits = bits;
+        self
+    }
+
+    /// Get the frame payload size below which frames are sent uncompressed.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Set the frame payload size below which frames are sent uncompressed.
+    ///
+    /// Compressing small frames mostly costs CPU time without saving bandwidth.
+    pub fn set_threshold(&mut self, size: usize) -> &mut Self {
+        self.threshold = size;
+        self
+    }
+
+    /// Create the extension to add to a websocket handshake of the given mode.
+    pub(crate) fn extension(&self, mode: Mode) -> Box<dyn Extension + Send> {
+        let mut inner = soketto::extension::deflate::Deflate::new(mode);
+        if mode == Mode::Client {
+            inner.set_max_server_window_bits(self.max_window_bits);
+            inner.set_max_client_window_bits(self.max_window_bits);
+        }
+        Box::new(Deflate {
+            inner,
+            threshold: self.threshold,
+        })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The permessage-deflate extension, skipping compression of frames below a threshold.
+///
+/// Receivers tell compressed and uncompressed frames apart by the RSV1 bit of each frame.
+#[derive(Debug)]
+struct Deflate {
+    inner: soketto::extension::deflate::Deflate,
+    threshold: usize,
+}
+
+impl Extension for Deflate {
+    fn is_enabled(&self) -> bool {
+        self.inner.is_enabled()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn params(&self) -> &[Param<'_>] {
+        self.inner.params()
+    }
+
+    fn configure(&mut self, params: &[Param<'_>]) -> Result<(), BoxedError> {
+        self.inner.configure(params)
+    }
+
+    fn encode(&mut self, header: &mut Header, data: &mut Storage) -> Result<(), BoxedError> {
+        if data.as_ref().len() < self.threshold {
+            return Ok(());
+        }
+        self.inner.encode(header, data)
+    }
+
+    fn decode(&mut self, header: &mut Header, data: &mut Vec<u8>) -> Result<(), BoxedError> {
+        self.inner.decode(header, data)
+    }
+
+    fn reserved_bits(&self) -> (bool, bool, bool) {
+        self.inner.reserved_bits()
+    }
+}
@@ -18,7 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::{error::Error, quicksink, tls};
+use crate::{deflate, error::Error, quicksink, tls};
 use either::Either;
 use futures::{future::BoxFuture, prelude::*, ready, stream::BoxStream};
 use futures_rustls::{client, rustls, server};
@@ -28,9 +28,10 @@ use libp2p_core::{
     transport::{ListenerId, TransportError, TransportEvent},
     Transport,
 };
+use libp2p_tcp::Proxy;
 use parking_lot::Mutex;
 use soketto::{
-    connection::{self, CloseReason},
+    connection::{self, CloseReason, Mode},
     handshake,
 };
 use std::{collections::HashMap, ops::DerefMut, sync::Arc};
@@ -49,6 +50,8 @@ pub struct WsConfig<T> {
     max_data_size: usize,
     tls_config: tls::Config,
     max_redirects: u8,
+    deflate_config: Option<deflate::Config>,
+    proxy: Option<Proxy>,
     /// Websocket protocol of the inner listener.
     ///
     /// This is the suffix of the address provided in `listen_on`.
@@ -67,6 +70,8 @@ where
             max_data_size: MAX_DATA_SIZE,
             tls_config: tls::Config::client(),
             max_redirects: 0,
+            deflate_config: None,
+            proxy: None,
             listener_protos: HashMap::new(),
         }
     }
@@ -98,6 +103,21 @@ where
         self.tls_config = c;
         self
     }
+
+    /// Set the permessage-deflate configuration if compression is desired.
+    pub fn set_deflate_config(&mut self, c: deflate::Config) -> &mut Self {
+        self.deflate_config = Some(c);
+        self
+    }
+
+    /// Set a proxy to connect through when dialing.
+    ///
+    /// The proxy is dialed via the inner transport and asked to connect to the host and port of
+    /// the websocket address, resolving the host name if it is one.
+    pub fn set_proxy(&mut self, proxy: Proxy) -> &mut Self {
+        self.proxy = Some(proxy);
+        self
+    }
 }
 
 type TlsOrPlain<T> = future::Either<future::Either<client::TlsStream<T>, server::TlsStream<T>>, T>;
@@ -278,12 +298,21 @@ where
 
         let transport = self.transport.clone();
         let tls_config = self.tls_config.clone();
+        let deflate_config = self.deflate_config.clone();
+        let proxy = self.proxy.clone();
         let max_redirects = self.max_redirects;
 
         let future = async move {
             loop {
-                match Self::dial_once(transport.clone(), addr, tls_config.clone(), role_override)
-                    .await
+                match Self::dial_once(
+                    transport.clone(),
+                    addr,
+                    tls_config.clone(),
+                    deflate_config.clone(),
+                    proxy.clone(),
+                    role_override,
+                )
+                .await
                 {
                     Ok(Either::Left(redirect)) => {
                         if remaining_redirects == 0 {
@@ -307,20 +336,37 @@ where
         transport: Arc<Mutex<T>>,
         addr: WsAddress,
         tls_config: tls::Config,
+        deflate_config: Option<deflate::Config>,
+        proxy: Option<Proxy>,
         role_override: Endpoint,
     ) -> Result<Either<String, Connection<T::Output>>, Error<T::Error>> {
         tracing::trace!(address=?addr, "Dialing websocket address");
 
+        let dial_addr = match &proxy {
+            Some(proxy) => {
+                Multiaddr::from(proxy.addr().ip()).with(Protocol::Tcp(proxy.addr().port()))
+            }
+            None => addr.tcp_addr,
+        };
         let dial = match role_override {
-            Endpoint::Dialer => transport.lock().dial(addr.tcp_addr),
-            Endpoint::Listener => transport.lock().dial_as_listener(addr.tcp_addr),
+            Endpoint::Dialer => transport.lock().dial(dial_addr),
+            Endpoint::Listener => transport.lock().dial_as_listener(dial_addr),
         }
         .map_err(|e| match e {
             TransportError::MultiaddrNotSupported(a) => Error::InvalidMultiaddr(a),
             TransportError::Other(e) => Error::Transport(e),
         })?;
 
-        let stream = dial.map_err(Error::Transport).await?;
+        let mut stream = dial.map_err(Error::Transport).await?;
+        if let Some(proxy) = proxy {
+            proxy
+                .connect(&mut stream, &addr.host, addr.port)
+                .map_err(|e| {
+                    tracing::debug!(proxy=%proxy.addr(), "Connecting via proxy failed: {}", e);
+                    Error::Handshake(Box::new(e))
+                })
+                .await?;
+        }
         tracing::trace!(port=%addr.host_port, "TCP connection established");
 
         let stream = if addr.use_tls {
@@ -348,6 +394,9 @@ where
         tracing::trace!(port=%addr.host_port, "Sending websocket handshake");
 
         let mut client = handshake::Client::new(stream, &addr.host_port, addr.path.as_ref());
+        if let Some(deflate_config) = deflate_config {
+            client.add_extension(deflate_config.extension(Mode::Client));
+        }
 
         match client
             .handshake()
@@ -384,6 +433,7 @@ where
     ) -> <Self as Transport>::ListenerUpgrade {
         let remote_addr2 = remote_addr.clone(); // used for logging
         let tls_config = self.tls_config.clone();
+        let deflate_config = self.deflate_config.clone();
         let max_size = self.max_data_size;
 
         async move {
@@ -420,6 +470,9 @@ where
             );
 
             let mut server = handshake::Server::new(stream);
+            if let Some(deflate_config) = deflate_config {
+                server.add_extension(deflate_config.extension(Mode::Server));
+            }
 
             let ws_key = {
                 let request = server
@@ -460,6 +513,8 @@ where
 #[derive(Debug)]
 struct WsAddress {
     host_port: String,
+    host: String,
+    port: u16,
     path: String,
     dns_name: Option<rustls::pki_types::ServerName<'static>>,
     use_tls: bool,
@@ -478,19 +533,19 @@ fn parse_ws_dial_addr<T>(addr: Multiaddr) -> Result<WsAddress, Error<T>> {
     let mut protocols = addr.iter();
     let mut ip = protocols.next();
     let mut tcp = protocols.next();
-    let (host_port, dns_name) = loop {
+    let (host, port, dns_name) = loop {
         match (ip, tcp) {
             (Some(Protocol::Ip4(ip)), Some(Protocol::Tcp(port))) => {
-                break (format!("{ip}:{port}"), None)
+                break (ip.to_string(), port, None)
             }
             (Some(Protocol::Ip6(ip)), Some(Protocol::Tcp(port))) => {
-                break (format!("{ip}:{port}"), None)
+                break (ip.to_string(), port, None)
             }
             (Some(Protocol::Dns(h)), Some(Protocol::Tcp(port)))
             | (Some(Protocol::Dns4(h)), Some(Protocol::Tcp(port)))
             | (Some(Protocol::Dns6(h)), Some(Protocol::Tcp(port)))
             | (Some(Protocol::Dnsaddr(h)), Some(Protocol::Tcp(port))) => {
-                break (format!("{}:{}", &h, port), Some(tls::dns_name_ref(&h)?))
+                break (h.to_string(), port, Some(tls::dns_name_ref(&h)?))
             }
             (Some(_), Some(p)) => {
                 ip = Some(p);
@@ -528,7 +583,9 @@ fn parse_ws_dial_addr<T>(addr: Multiaddr) -> Result<WsAddress, Error<T>> {
     };
 
     Ok(WsAddress {
-        host_port,
+        host_port: format!("{host}:{port}"),
+        host,
+        port,
         dns_name,
         path,
         use_tls,
@@ -22,11 +22,14 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+pub mod deflate;
 pub mod error;
 pub mod framed;
 mod quicksink;
 pub mod tls;
 
+pub use libp2p_tcp::{Proxy, ProxyProtocol};
+
 use error::Error;
 use framed::{Connection, Incoming};
 use futures::{future::BoxFuture, prelude::*, ready};
@@ -175,6 +178,21 @@ where
         self.transport.inner_mut().set_tls_config(c);
         self
     }
+
+    /// Set the permessage-deflate configuration if compression is desired.
+    pub fn set_deflate_config(&mut self, c: deflate::Config) -> &mut Self {
+        self.transport.inner_mut().set_deflate_config(c);
+        self
+    }
+
+    /// Set a proxy to connect through when dialing.
+    ///
+    /// The proxy is dialed via the inner transport, which thus must not be configured with a
+    /// proxy itself. Failing to connect via the proxy is reported as [`Error::Handshake`].
+    pub fn set_proxy(&mut self, proxy: Proxy) -> &mut Self {
+        self.transport.inner_mut().set_proxy(proxy);
+        self
+    }
 }
 
 impl<T> Transport for WsConfig<T>
@@ -290,7 +308,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::WsConfig;
+    use super::{deflate, Proxy, WsConfig};
     use futures::prelude::*;
     use libp2p_core::{multiaddr::Protocol, transport::ListenerId, Multiaddr, Transport};
     use libp2p_identity::PeerId;
@@ -308,6 +326,167 @@ mod tests {
         futures::executor::block_on(connect(a))
     }
 
+    #[test]
+    fn deflate_roundtrip() {
+        futures::executor::block_on(async {
+            let mut deflate_config = deflate::Config::new();
+            deflate_config.set_max_window_bits(10);
+
+            let mut listener = new_ws_config();
+            listener.set_deflate_config(deflate_config.clone());
+            let mut listener = listener.boxed();
+            listener
+                .listen_on(
+                    ListenerId::next(),
+                    "/ip4/127.0.0.1/tcp/0/ws".parse().unwrap(),
+                )
+                .expect("listener");
+            let addr = listener
+                .next()
+                .await
+                .expect("no error")
+                .into_new_address()
+                .expect("listen address");
+
+            // One message above and one below the compression threshold.
+            let data = [vec![b'a'; 4096], vec![b'b'; 16]].concat();
+
+            let inbound = async {
+                let (upgrade, _) = listener
+                    .select_next_some()
+                    .map(|ev| ev.into_incoming())
+                    .await
+                    .unwrap();
+                let mut conn = upgrade.await.unwrap();
+                let mut buf = vec![0; data.len()];
+                conn.read_exact(&mut buf).await.unwrap();
+                conn.write_all(&buf).await.unwrap();
+                conn.flush().await.unwrap();
+                conn
+            };
+
+            let mut dialer = new_ws_config();
+            dialer.set_deflate_config(deflate_config);
+            let outbound = async {
+                let mut conn = dialer.boxed().dial(addr).unwrap().await.unwrap();
+                conn.write_all(&data[..4096]).await.unwrap();
+                conn.flush().await.unwrap();
+                conn.write_all(&data[4096..]).await.unwrap();
+                conn.flush().await.unwrap();
+                let mut buf = vec![0; data.len()];
+                conn.read_exact(&mut buf).await.unwrap();
+                buf
+            };
+
+            let (_conn, echoed) = futures::join!(inbound, outbound);
+            assert_eq!(echoed, data);
+        })
+    }
+
+    #[test]
+    fn dialer_connects_via_proxy() {
+        futures::executor::block_on(async {
+            let mut listener = new_ws_config().boxed();
+            listener
+                .listen_on(
+                    ListenerId::next(),
+                    "/ip4/127.0.0.1/tcp/0/ws".parse().unwrap(),
+                )
+                .expect("listener");
+            let listen_addr = listener
+                .next()
+                .await
+                .expect("no error")
+                .into_new_address()
+                .expect("listen address");
+            let Some(Protocol::Tcp(port)) = listen_addr.iter().nth(1) else {
+                panic!("Unexpected listen address: {listen_addr}")
+            };
+
+            // A SOCKS5 proxy resolving `example.com` to the listener.
+            let mut proxy = tcp::async_io::Transport::new(tcp::Config::default()).boxed();
+            proxy
+                .listen_on(ListenerId::next(), "/ip4/127.0.0.1/tcp/0".parse().unwrap())
+                .expect("listener");
+            let proxy_addr = proxy
+                .next()
+                .await
+                .expect("no error")
+                .into_new_address()
+                .expect("listen address");
+            let proxy_socket_addr = match (proxy_addr.iter().next(), proxy_addr.iter().nth(1)) {
+                (Some(Protocol::Ip4(ip)), Some(Protocol::Tcp(port))) => (ip, port).into(),
+                _ => panic!("Unexpected listen address: {proxy_addr}"),
+            };
+            async_std::task::spawn(async move {
+                let (upgrade, _) = proxy
+                    .select_next_some()
+                    .map(|ev| ev.into_incoming())
+                    .await
+                    .unwrap();
+                let mut client = upgrade.await.unwrap();
+                let mut greeting = [0; 3];
+                client.read_exact(&mut greeting).await.unwrap();
+                client.write_all(&[5, 0]).await.unwrap();
+                let mut request = [0; 5 + 11 + 2];
+                client.read_exact(&mut request).await.unwrap();
+                assert_eq!(&request[4..16], b"\x0bexample.com");
+                assert_eq!(&request[16..], &port.to_be_bytes());
+
+                let target = tcp::async_io::Transport::new(tcp::Config::default())
+                    .dial(
+                        listen_addr
+                            .with(Protocol::Tcp(port))
+                            .iter()
+                            .take(2)
+                            .collect(),
+                    )
+                    .unwrap()
+                    .await
+                    .unwrap();
+                client
+                    .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+                    .await
+                    .unwrap();
+                let (client_read, mut client_write) = client.split();
+                let (target_read, mut target_write) = target.split();
+                let _ = futures::join!(
+                    futures::io::copy(client_read, &mut target_write),
+                    futures::io::copy(target_read, &mut client_write)
+                );
+            });
+
+            let inbound = async {
+                let (upgrade, _) = listener
+                    .select_next_some()
+                    .map(|ev| ev.into_incoming())
+                    .await
+                    .unwrap();
+                let mut conn = upgrade.await.unwrap();
+                let mut buf = [0; 3];
+                conn.read_exact(&mut buf).await.unwrap();
+                conn.write_all(&buf).await.unwrap();
+                conn.flush().await.unwrap();
+                conn
+            };
+
+            let mut dialer = new_ws_config();
+            dialer.set_proxy(Proxy::socks5(proxy_socket_addr));
+            let outbound = async {
+                let addr = format!("/dns4/example.com/tcp/{port}/ws").parse().unwrap();
+                let mut conn = dialer.boxed().dial(addr).unwrap().await.unwrap();
+                conn.write_all(&[1, 2, 3]).await.unwrap();
+                conn.flush().await.unwrap();
+                let mut buf = [0; 3];
+                conn.read_exact(&mut buf).await.unwrap();
+                buf
+            };
+
+            let (_conn, echoed) = futures::join!(inbound, outbound);
+            assert_eq!(echoed, [1, 2, 3]);
+        })
+    }
+
     fn new_ws_config() -> WsConfig<tcp::async_io::Transport> {
         WsConfig::new(tcp::async_io::Transport::new(tcp::Config::default()))
     }